@@ -0,0 +1,24 @@
+//! aggregates.rs — Atualização periódica das views agregadas
+//!
+//! As views materializadas (`mv_hourly_availability`, `mv_daily_outage_minutes`,
+//! `mv_hourly_latency` e as versões por probe `mv_hourly_probe_*`) são criadas
+//! pelas migrações e consultadas por dashboards no lugar da tabela bruta
+//! `connectivity_metrics`. Este loop as mantém atualizadas.
+
+use crate::storage::Storage;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Loop de refresh das views agregadas a cada `interval_secs`.
+pub async fn run_refresher(storage: Arc<Storage>, interval_secs: u64) {
+    let mut ticker = interval(Duration::from_secs(interval_secs.max(1)));
+    loop {
+        ticker.tick().await;
+        match storage.refresh_aggregated_views().await {
+            Ok(()) => debug!("[AGGREGATES] Views agregadas atualizadas"),
+            Err(e) => error!("[AGGREGATES] Falha ao atualizar views agregadas: {:?}", e),
+        }
+    }
+}
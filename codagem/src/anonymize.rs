@@ -0,0 +1,254 @@
+//! anonymize.rs — Pseudonimização consistente de targets
+//!
+//! Substitui nomes e IPs de targets por pseudônimos derivados de HMAC-SHA256
+//! com uma chave secreta. O mapeamento é determinístico (o mesmo target gera
+//! sempre o mesmo pseudônimo, permitindo correlacionar séries) e preserva a
+//! estrutura: IPv4 continua IPv4, IPv6 continua IPv6, nomes viram `target-<hex>`.
+//! Nos textos livres das métricas e dos outages (mensagens de erro,
+//! diagnósticos), nome e endereço dos targets são trocados pelos pseudônimos.
+
+use crate::config::AnonymizationConfig;
+use crate::types::{ConnectivityMetric, GeoLocation, OutageEvent, Target};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Gerador de pseudônimos com chave fixa.
+#[derive(Debug, Clone)]
+pub struct Pseudonymizer {
+    key: Vec<u8>,
+    pub status_page: bool,
+    pub exports: bool,
+}
+
+impl Pseudonymizer {
+    pub fn new(config: &AnonymizationConfig) -> Self {
+        Self {
+            key: config.key.as_bytes().to_vec(),
+            status_page: config.status_page,
+            exports: config.exports,
+        }
+    }
+
+    fn digest(&self, domain: &str, value: &[u8]) -> [u8; 32] {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC aceita chave de qualquer tamanho");
+        mac.update(domain.as_bytes());
+        mac.update(b":");
+        mac.update(value);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Pseudônimo estável para um nome (ex: `target-3f9a1c2b`).
+    pub fn name(&self, name: &str) -> String {
+        let digest = self.digest("name", name.as_bytes());
+        format!("target-{}", hex::encode(&digest[..4]))
+    }
+
+    /// Pseudônimo estável para um IP, preservando a família de endereço.
+    pub fn ip(&self, address: IpAddr) -> IpAddr {
+        match address {
+            IpAddr::V4(v4) => {
+                let digest = self.digest("ipv4", &v4.octets());
+                // Faixa privada 10.0.0.0/8 para nunca colidir com um IP público real
+                IpAddr::V4(Ipv4Addr::new(10, digest[0], digest[1], digest[2]))
+            }
+            IpAddr::V6(v6) => {
+                let digest = self.digest("ipv6", &v6.octets());
+                // Faixa ULA fd00::/8
+                let mut octets = [0u8; 16];
+                octets[0] = 0xfd;
+                octets[1..].copy_from_slice(&digest[..15]);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+        }
+    }
+
+    /// Cópia do target com nome e endereço pseudonimizados. Da localização
+    /// GeoIP só o país é mantido: cidade e coordenadas apontariam o endereço.
+    pub fn target(&self, target: &Target) -> Target {
+        Target {
+            name: self.name(&target.name),
+            address: self.ip(target.address),
+            geo: GeoLocation {
+                country_code: target.geo.country_code.clone(),
+                ..GeoLocation::default()
+            },
+            ..target.clone()
+        }
+    }
+
+    /// Cópia da métrica com nome e endereço de `target` trocados pelos
+    /// pseudônimos nos textos de `details` (mensagem de erro, veredito dos
+    /// sinais combinados...).
+    pub fn metric(&self, metric: &ConnectivityMetric, target: &Target) -> ConnectivityMetric {
+        let mut metric = metric.clone();
+        if let Some(details) = metric.details.as_mut() {
+            scrub(details, &self.replacements(std::slice::from_ref(target)));
+        }
+        metric
+    }
+
+    /// Cópia do outage com nomes e endereços de `targets` trocados pelos
+    /// pseudônimos nos textos de `details` (diagnóstico, causa).
+    pub fn outage(&self, event: &OutageEvent, targets: &[Target]) -> OutageEvent {
+        let mut event = event.clone();
+        if let Some(details) = event.details.as_mut() {
+            scrub(details, &self.replacements(targets));
+        }
+        event
+    }
+
+    /// Pares (valor real, pseudônimo) dos targets, os mais longos primeiro
+    /// para que um nome contido em outro não seja trocado pela metade.
+    fn replacements(&self, targets: &[Target]) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = targets
+            .iter()
+            .flat_map(|t| {
+                [
+                    (t.address.to_string(), self.ip(t.address).to_string()),
+                    (t.name.clone(), self.name(&t.name)),
+                ]
+            })
+            .filter(|(real, _)| !real.is_empty())
+            .collect();
+        pairs.sort_by_key(|(real, _)| std::cmp::Reverse(real.len()));
+        pairs
+    }
+}
+
+/// Troca os valores reais em todas as strings de `value`.
+fn scrub(value: &mut Value, replacements: &[(String, String)]) {
+    match value {
+        Value::String(text) => {
+            for (real, pseudonym) in replacements {
+                if text.contains(real.as_str()) {
+                    *text = replace_token(text, real, pseudonym);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub(item, replacements)),
+        Value::Object(map) => map.values_mut().for_each(|item| scrub(item, replacements)),
+        _ => {}
+    }
+}
+
+/// Troca as ocorrências de `from` que não fazem parte de um token maior:
+/// `1.1.1.1` é trocado em `1.1.1.1:53`, mas não em `11.1.1.1` nem em
+/// `1.1.1.10`.
+fn replace_token(text: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in text.match_indices(from) {
+        let end = start + from.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        if start < last
+            || before.is_some_and(|c| c.is_alphanumeric() || c == '.' || c == ':')
+            || after.is_some_and(char::is_alphanumeric)
+        {
+            continue;
+        }
+        result.push_str(&text[last..start]);
+        result.push_str(to);
+        last = end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AnonymizationConfig;
+    use serde_json::json;
+
+    fn pseudonymizer() -> Pseudonymizer {
+        Pseudonymizer::new(&AnonymizationConfig {
+            key: "chave".to_string(),
+            status_page: true,
+            exports: true,
+        })
+    }
+
+    #[test]
+    fn pseudonimos_estaveis_e_na_mesma_familia() {
+        let p = pseudonymizer();
+        let v4: IpAddr = "8.8.8.8".parse().unwrap();
+        let v6: IpAddr = "2001:4860:4860::8888".parse().unwrap();
+        assert_eq!(p.ip(v4), p.ip(v4));
+        assert!(matches!(p.ip(v4), IpAddr::V4(ip) if ip.octets()[0] == 10));
+        assert!(matches!(p.ip(v6), IpAddr::V6(ip) if ip.octets()[0] == 0xfd));
+        assert_eq!(p.name("Google DNS"), p.name("Google DNS"));
+        assert_ne!(p.name("Google DNS"), p.name("Cloudflare"));
+        assert!(p.name("Google DNS").starts_with("target-"));
+    }
+
+    #[test]
+    fn troca_so_tokens_inteiros() {
+        assert_eq!(
+            replace_token("falha em 1.1.1.1:53 (1.1.1.1)", "1.1.1.1", "10.0.0.1"),
+            "falha em 10.0.0.1:53 (10.0.0.1)"
+        );
+        assert_eq!(
+            replace_token("11.1.1.1 e 1.1.1.10", "1.1.1.1", "10.0.0.1"),
+            "11.1.1.1 e 1.1.1.10"
+        );
+        assert_eq!(replace_token("fe80::1", "::1", "x"), "fe80::1");
+    }
+
+    #[test]
+    fn metrica_e_outage_sem_nome_nem_endereco_reais() {
+        let p = pseudonymizer();
+        let target = Target {
+            id: 7,
+            ..Target::unregistered("dns-a".to_string(), "9.9.9.9".parse().unwrap())
+        };
+        let metric = ConnectivityMetric {
+            id: 1,
+            cycle_id: 1,
+            probe_id: 1,
+            target_id: 7,
+            timestamp: chrono::Utc::now(),
+            metric_type: crate::types::MetricType::TcpIpv4,
+            status: crate::types::MetricStatus::Down,
+            response_time_ms: None,
+            packet_loss_percent: Some(100),
+            error: None,
+            details: Some(json!({
+                "error_message": "connect 9.9.9.9:53: connection refused",
+                "verdict": { "note": "dns-a down" },
+            })),
+            provenance: None,
+            attempts: Vec::new(),
+        };
+        let scrubbed = p.metric(&metric, &target);
+        let text = scrubbed.details.unwrap().to_string();
+        assert!(
+            !text.contains("9.9.9.9") && !text.contains("dns-a"),
+            "{}",
+            text
+        );
+        assert!(text.contains(&p.ip(target.address).to_string()));
+        assert!(text.contains(&p.name("dns-a")));
+
+        let event = OutageEvent {
+            id: 1,
+            event_type: crate::types::OutageEventType::Outage,
+            start_time: chrono::Utc::now(),
+            end_time: None,
+            duration_seconds: None,
+            reason: Some("consensus_reached".to_string()),
+            affected_targets: vec![7],
+            affected_probes: Some(vec![1]),
+            consensus_level: Some(1),
+            details: Some(json!({ "diagnosis": { "summary": "sem resposta de 9.9.9.9" } })),
+            affected_asns: Vec::new(),
+        };
+        let text = p.outage(&event, &[target]).details.unwrap().to_string();
+        assert!(!text.contains("9.9.9.9"), "{}", text);
+    }
+}
@@ -0,0 +1,989 @@
+//! api.rs — API HTTP embutida
+//!
+//! - Modo `public`: apenas estatísticas agregadas (disponibilidade e latência
+//!   por provedor/região), próprias para dashboards comunitários de qualidade
+//!   de ISPs. Nenhum IP, nome de target ou métrica bruta é exposto.
+//! - Modo `full`: inclui também o snapshot corrente, a lista de targets, o
+//!   cadastro de targets (`POST /api/targets`), a saúde do caminho por AS
+//!   (`/api/as-health`), os resumos por ciclo (`/api/cycles`) e a latência
+//!   das consultas DNS por nome (`/api/dns-queries`), para uso interno.
+//! - Com um token `admin` (ver `auth`), o modo `full` ganha a edição e
+//!   remoção de targets e o cadastro de probes e janelas de manutenção
+//!   (`POST`/`PUT`/`DELETE`). O monitor lê targets, probes e janelas na
+//!   inicialização: as mudanças valem a partir do próximo início, exceto a
+//!   pausa de targets (`enabled`), relida a cada ciclo.
+//! - `/api/outages/{id}` (modo `full`) devolve um evento de outage, e
+//!   `POST /api/outages/{id}/annotations` anexa a ele uma anotação de
+//!   operador ("rompimento de fibra no km 12, chamado #1234"), que aparece
+//!   nos resumos e no pacote de postmortem; remover uma anotação exige token
+//!   `admin`.
+//! - `/ws/live` (modo `full`) transmite por WebSocket cada métrica e
+//!   transição de status, em tempo real, e `/api/events/outages`, as
+//!   aberturas e encerramentos de outages como Server-Sent Events (ver
+//!   `live`).
+//! - `POST /api/checks/run` (modo `full`) roda na hora os checks de um
+//!   target, ou de todos, e devolve as medições sem gravá-las (ver
+//!   `on_demand`).
+//! - Com `database_read_url`, os `GET` consultam a réplica de leitura; as
+//!   gravações e as leituras que as validam seguem no primário.
+//! - Com tokens configurados, as consultas do modo `full` exigem um token
+//!   `read` ou `admin`; `/health` e `/api/public/summary` continuam abertos.
+
+use crate::as_health::{self, AsHealthReport};
+use crate::auth::{ApiAuth, Authorization, Caller};
+use crate::config::{ApiConfig, ApiMode, TokenScope};
+use crate::live::{LiveFeed, LiveMessage, OutageMessage};
+use crate::on_demand::{OnDemandChecks, OnDemandError, OnDemandRun, Selection};
+use crate::ping;
+use crate::public_stats::{self, ProviderSource, PublicSummary};
+use crate::registration::{self, RegisteredTarget, Registration, RegistrationError};
+use crate::snapshot::{Snapshot, SnapshotStore};
+use crate::storage::{DeleteOutcome, Storage};
+use crate::templates::{TargetTemplate, TemplateSet};
+use crate::types::{
+    CycleSummary, DnsQueryLatency, MaintenanceWindow, OutageAnnotation, OutageEvent,
+    OutageEventType, Probe, Target,
+};
+use anyhow::{Context, Result};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Extension, Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info};
+
+/// Janela máxima aceita nas consultas agregadas (31 dias).
+const MAX_WINDOW_HOURS: i64 = 24 * 31;
+
+/// Máximo de resumos de ciclo devolvidos por `/api/cycles`.
+const MAX_CYCLE_SUMMARIES: i64 = 10_000;
+
+/// Tamanho máximo do texto de uma anotação de outage, em caracteres.
+const MAX_ANNOTATION_CHARS: usize = 2000;
+
+/// Estado compartilhado pelos handlers.
+#[derive(Clone)]
+pub struct ApiState {
+    pub config: ApiConfig,
+    /// Gravações e as leituras que as validam.
+    pub storage: Arc<Storage>,
+    /// Consultas dos `GET`: a réplica de leitura, se configurada (senão o
+    /// mesmo `storage`).
+    pub reads: Arc<Storage>,
+    pub snapshot: Arc<SnapshotStore>,
+    pub templates: Arc<TemplateSet>,
+    pub live: Arc<LiveFeed>,
+    pub on_demand: Arc<OnDemandChecks>,
+}
+
+/// Erro de handler convertido em resposta JSON.
+pub struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        error!("[API] Erro interno: {:?}", e);
+        ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "erro interno".to_string(),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WindowQuery {
+    hours: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AsHealthQuery {
+    hours: Option<i64>,
+    bucket_minutes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CyclesQuery {
+    probe_id: Option<i32>,
+    hours: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LiveQuery {
+    probe_id: Option<i32>,
+    target_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutageEventsQuery {
+    /// Tipos separados por vírgula (ex: `outage,partial`); ausente = todos.
+    event_types: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteQuery {
+    /// Apaga também o histórico gravado do target ou probe.
+    #[serde(default)]
+    purge: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceQuery {
+    /// Inclui as janelas já encerradas.
+    #[serde(default)]
+    all: bool,
+}
+
+/// Campos editáveis de um target; os ausentes ficam como estão.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetUpdate {
+    name: Option<String>,
+    asn: Option<i32>,
+    provider: Option<String>,
+    region: Option<String>,
+    tags: Option<Vec<String>>,
+    check_interval_secs: Option<i32>,
+    fail_threshold: Option<i32>,
+    weight: Option<f64>,
+    degraded_rtt_ms: Option<f64>,
+    degraded_loss_percent: Option<i16>,
+    ping_payload_bytes: Option<i32>,
+    dscp: Option<i16>,
+    tenant: Option<String>,
+    /// `false` pausa o monitoramento do target; `true` retoma.
+    enabled: Option<bool>,
+}
+
+/// Corpo de `POST /api/probes`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewProbe {
+    location: String,
+    ip_address: Option<IpAddr>,
+    provider: Option<String>,
+}
+
+/// Campos editáveis de um probe; os ausentes ficam como estão.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeUpdate {
+    location: Option<String>,
+    ip_address: Option<IpAddr>,
+    provider: Option<String>,
+}
+
+/// Corpo de `POST` e `PUT` de janelas de manutenção (o `PUT` substitui a
+/// janela inteira).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceRequest {
+    probe_id: Option<i32>,
+    target_id: Option<i32>,
+    /// Ausente = agora.
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: DateTime<Utc>,
+    reason: Option<String>,
+}
+
+/// Corpo de `POST /api/outages/{id}/annotations`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotationRequest {
+    text: String,
+    /// Ausente = o nome do token usado no pedido.
+    author: Option<String>,
+}
+
+/// Monta as rotas de acordo com o modo configurado.
+pub fn router(state: ApiState) -> Router {
+    let auth = Arc::new(ApiAuth::from_config(&state.config));
+    let mut router = Router::new()
+        .route("/health", get(health))
+        .route("/api/public/summary", get(public_summary));
+    if state.config.mode == ApiMode::Full {
+        let mut reads = Router::new()
+            .route("/api/snapshot", get(snapshot))
+            .route("/api/targets", get(targets))
+            .route("/api/probes", get(probes))
+            .route("/api/maintenance", get(maintenance_windows))
+            .route("/api/templates", get(templates))
+            .route("/api/as-health", get(as_health))
+            .route("/api/cycles", get(cycles))
+            .route("/api/cycles/{cycle_id}", get(cycle))
+            .route("/api/dns-queries", get(dns_queries))
+            .route("/api/outages/{outage_id}", get(outage))
+            .route("/ws/live", get(live))
+            .route("/api/events/outages", get(outage_events));
+        if auth.enabled() {
+            reads = reads.route_layer(middleware::from_fn_with_state(
+                Arc::clone(&auth),
+                require_read,
+            ));
+        }
+        router = router.merge(reads);
+        // Escritas (cadastro, checks sob demanda, anotações) só com um token
+        // `admin` configurado, e sempre exigindo-o
+        if auth.has_admin() {
+            let writes = Router::new()
+                .route("/api/targets", post(register_targets))
+                .route("/api/checks/run", post(run_checks))
+                .route(
+                    "/api/outages/{outage_id}/annotations",
+                    post(create_outage_annotation),
+                )
+                .route(
+                    "/api/targets/{target_id}",
+                    put(update_target).delete(delete_target),
+                )
+                .route("/api/probes", post(create_probe))
+                .route(
+                    "/api/probes/{probe_id}",
+                    put(update_probe).delete(delete_probe),
+                )
+                .route("/api/maintenance", post(create_maintenance_window))
+                .route(
+                    "/api/maintenance/{window_id}",
+                    put(update_maintenance_window).delete(delete_maintenance_window),
+                )
+                .route(
+                    "/api/outages/{outage_id}/annotations/{index}",
+                    delete(delete_outage_annotation),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    Arc::clone(&auth),
+                    require_admin,
+                ));
+            router = router.merge(writes);
+        } else {
+            info!("[API] Sem token `admin`: rotas de escrita desabilitadas (somente leitura)");
+        }
+    }
+    router
+        .layer(middleware::from_fn_with_state(auth, guard))
+        .with_state(state)
+}
+
+/// Em todas as rotas: recusa IPs fora de `allowed_ips` e, com
+/// `log_requests`, loga o pedido.
+async fn guard(
+    State(auth): State<Arc<ApiAuth>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = if auth.allows(peer.ip()) {
+        next.run(request).await
+    } else {
+        ApiError(StatusCode::FORBIDDEN, "endereço não permitido".to_string()).into_response()
+    };
+    if auth.log_requests {
+        let caller = response
+            .extensions()
+            .get::<Caller>()
+            .map_or("-".to_string(), |c| {
+                format!("{} ({})", c.name, c.scope.as_str())
+            });
+        info!(
+            "[API] {} {} {} de {} (token: {}, {:.1} ms)",
+            method,
+            path,
+            response.status().as_u16(),
+            peer.ip(),
+            caller,
+            started.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+    response
+}
+
+async fn require_read(State(auth): State<Arc<ApiAuth>>, request: Request, next: Next) -> Response {
+    authorized(&auth, TokenScope::Read, request, next).await
+}
+
+async fn require_admin(State(auth): State<Arc<ApiAuth>>, request: Request, next: Next) -> Response {
+    authorized(&auth, TokenScope::Admin, request, next).await
+}
+
+/// Exige `Authorization: Bearer <token>` com o escopo pedido; quem fez o
+/// pedido segue nas extensões do pedido, para os handlers, e nas da
+/// resposta, para o log.
+async fn authorized(
+    auth: &ApiAuth,
+    required: TokenScope,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let (mut response, caller) = match auth.authorize(request.headers(), required) {
+        Authorization::Granted(caller) => {
+            request.extensions_mut().insert(caller.clone());
+            (next.run(request).await, caller)
+        }
+        Authorization::Unauthenticated => {
+            return ApiError(
+                StatusCode::UNAUTHORIZED,
+                "token ausente ou inválido".to_string(),
+            )
+            .into_response();
+        }
+        Authorization::Forbidden(caller) => (
+            ApiError(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "o token `{}` não tem o escopo {}",
+                    caller.name,
+                    required.as_str()
+                ),
+            )
+            .into_response(),
+            caller,
+        ),
+    };
+    response.extensions_mut().insert(caller);
+    response
+}
+
+/// Sobe o servidor HTTP e atende até o processo terminar.
+pub async fn serve(state: ApiState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&state.config.bind)
+        .await
+        .with_context(|| format!("Falha ao escutar em {}", state.config.bind))?;
+    info!(
+        "🌐 API HTTP ({:?}) escutando em {}",
+        state.config.mode, state.config.bind
+    );
+    axum::serve(
+        listener,
+        router(state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .context("Servidor HTTP encerrado com erro")
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok", "time": Utc::now() }))
+}
+
+async fn public_summary(
+    State(state): State<ApiState>,
+    Query(query): Query<WindowQuery>,
+) -> Result<Json<PublicSummary>, ApiError> {
+    let hours = query.hours.unwrap_or(24);
+    if !(1..=MAX_WINDOW_HOURS).contains(&hours) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("hours deve estar entre 1 e {}", MAX_WINDOW_HOURS),
+        ));
+    }
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    let targets = state.reads.list_targets().await?;
+    let availability = state.reads.list_hourly_availability(since).await?;
+    let latency = state.reads.list_hourly_latency(since).await?;
+    Ok(Json(public_stats::summarize(
+        ProviderSource::Target,
+        &targets,
+        &availability,
+        &latency,
+        hours,
+        state.config.min_group_size,
+        state.config.noise_epsilon,
+    )))
+}
+
+/// Saúde do caminho por AS (ver `as_health`).
+async fn as_health(
+    State(state): State<ApiState>,
+    Query(query): Query<AsHealthQuery>,
+) -> Result<Json<AsHealthReport>, ApiError> {
+    let hours = query.hours.unwrap_or(24);
+    if !(1..=MAX_WINDOW_HOURS).contains(&hours) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("hours deve estar entre 1 e {}", MAX_WINDOW_HOURS),
+        ));
+    }
+    let bucket_minutes = query.bucket_minutes.unwrap_or(60);
+    if !(1..=hours * 60).contains(&bucket_minutes) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "bucket_minutes deve estar entre 1 e a janela consultada".to_string(),
+        ));
+    }
+    Ok(Json(
+        as_health::report(&state.reads, hours, bucket_minutes).await?,
+    ))
+}
+
+/// Distribuição da latência das consultas de `checks.dns_queries` por
+/// resolver e nome.
+async fn dns_queries(
+    State(state): State<ApiState>,
+    Query(query): Query<WindowQuery>,
+) -> Result<Json<Vec<DnsQueryLatency>>, ApiError> {
+    let hours = query.hours.unwrap_or(24);
+    if !(1..=MAX_WINDOW_HOURS).contains(&hours) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("hours deve estar entre 1 e {}", MAX_WINDOW_HOURS),
+        ));
+    }
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    Ok(Json(state.reads.list_dns_query_latency(since).await?))
+}
+
+/// Resumos por ciclo (`cycle_summaries`), do mais recente para o mais
+/// antigo.
+async fn cycles(
+    State(state): State<ApiState>,
+    Query(query): Query<CyclesQuery>,
+) -> Result<Json<Vec<CycleSummary>>, ApiError> {
+    let hours = query.hours.unwrap_or(1);
+    if !(1..=MAX_WINDOW_HOURS).contains(&hours) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("hours deve estar entre 1 e {}", MAX_WINDOW_HOURS),
+        ));
+    }
+    let limit = query.limit.unwrap_or(1000);
+    if !(1..=MAX_CYCLE_SUMMARIES).contains(&limit) {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("limit deve estar entre 1 e {}", MAX_CYCLE_SUMMARIES),
+        ));
+    }
+    let since = Utc::now() - chrono::Duration::hours(hours);
+    Ok(Json(
+        state
+            .reads
+            .list_cycle_summaries(query.probe_id, since, limit)
+            .await?,
+    ))
+}
+
+async fn cycle(
+    State(state): State<ApiState>,
+    Path(cycle_id): Path<i64>,
+) -> Result<Json<CycleSummary>, ApiError> {
+    state
+        .reads
+        .get_cycle_summary(cycle_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| {
+            ApiError(
+                StatusCode::NOT_FOUND,
+                format!("ciclo {} sem resumo", cycle_id),
+            )
+        })
+}
+
+/// Fluxo ao vivo em WebSocket: cada métrica concluída e cada transição de
+/// status como uma mensagem de texto JSON (`type`: `metric`, `transition`
+/// ou `lagged`, quando o cliente ficou para trás e perdeu mensagens).
+async fn live(
+    State(state): State<ApiState>,
+    Query(query): Query<LiveQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let receiver = state.live.subscribe();
+    upgrade.on_upgrade(move |socket| stream_live(socket, receiver, query))
+}
+
+async fn stream_live(
+    mut socket: WebSocket,
+    mut receiver: tokio::sync::broadcast::Receiver<LiveMessage>,
+    query: LiveQuery,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    loop {
+        let text = tokio::select! {
+            message = receiver.recv() => match message {
+                Ok(message) if message.matches(query.probe_id, query.target_id) => {
+                    message.json.to_string()
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    json!({ "type": "lagged", "skipped": skipped }).to_string()
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Aberturas e encerramentos de outages como Server-Sent Events: `event` é
+/// `opened` ou `closed` e `data`, o JSON com `kind`, `probe` e `outage`. Um
+/// cliente que ficou para trás recebe `event: lagged` com as perdidas.
+async fn outage_events(
+    State(state): State<ApiState>,
+    Query(query): Query<OutageEventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let event_types: Option<Vec<OutageEventType>> = match &query.event_types {
+        Some(list) => Some(
+            list.split(',')
+                .map(|name| {
+                    let name = name.trim();
+                    [
+                        OutageEventType::Outage,
+                        OutageEventType::Partial,
+                        OutageEventType::Degradation,
+                    ]
+                    .into_iter()
+                    .find(|t| t.as_str() == name)
+                    .ok_or_else(|| {
+                        bad_request(format!(
+                            "event_types: tipo desconhecido `{}` (use outage, partial ou degradation)",
+                            name
+                        ))
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        None => None,
+    };
+    let receiver = state.live.subscribe_outages();
+    let stream = futures_util::stream::unfold(
+        (receiver, event_types),
+        |(mut receiver, event_types)| async move {
+            use tokio::sync::broadcast::error::RecvError;
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(OutageMessage {
+                        kind,
+                        event_type,
+                        json,
+                    }) => {
+                        if event_types
+                            .as_ref()
+                            .is_some_and(|types| !types.contains(&event_type))
+                        {
+                            continue;
+                        }
+                        Event::default().event(kind.as_str()).data(json.as_ref())
+                    }
+                    Err(RecvError::Lagged(skipped)) => Event::default()
+                        .event("lagged")
+                        .data(json!({ "skipped": skipped }).to_string()),
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((Ok(event), (receiver, event_types)));
+            }
+        },
+    );
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn snapshot(State(state): State<ApiState>) -> Json<Snapshot> {
+    Json(Snapshot::clone(&state.snapshot.load()))
+}
+
+async fn targets(State(state): State<ApiState>) -> Result<Json<Vec<Target>>, ApiError> {
+    Ok(Json(state.reads.list_targets().await?))
+}
+
+/// Cadastra targets a partir de entradas livres (ver `registration`). Um
+/// erro em qualquer entrada rejeita o lote inteiro com `400`.
+async fn register_targets(
+    State(state): State<ApiState>,
+    Json(body): Json<Registration>,
+) -> Result<Json<Vec<RegisteredTarget>>, ApiError> {
+    match registration::register(&state.storage, &state.templates, &body).await {
+        Ok(registered) => Ok(Json(registered)),
+        Err(RegistrationError::Invalid(message)) => Err(ApiError(StatusCode::BAD_REQUEST, message)),
+        Err(RegistrationError::Storage(e)) => Err(e.into()),
+    }
+}
+
+async fn templates(State(state): State<ApiState>) -> Json<Vec<TargetTemplate>> {
+    Json(state.templates.iter().cloned().collect())
+}
+
+/// Corpo de `POST /api/checks/run`; vazio = todos os targets em todos os
+/// probes.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckRunRequest {
+    target_id: Option<i32>,
+    /// Nome ou endereço do target.
+    target: Option<String>,
+    probe_id: Option<i32>,
+}
+
+/// Roda os checks selecionados fora do ciclo e devolve as medições.
+async fn run_checks(
+    State(state): State<ApiState>,
+    body: Option<Json<CheckRunRequest>>,
+) -> Result<Json<OnDemandRun>, ApiError> {
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    let selection = Selection {
+        target_id: body.target_id,
+        target: body.target,
+        probe_id: body.probe_id,
+    };
+    match state.on_demand.run(&selection).await {
+        Ok(run) => Ok(Json(run)),
+        Err(OnDemandError::NoMatch) => Err(not_found(
+            "nenhum probe monitora um target com a seleção pedida".to_string(),
+        )),
+        Err(OnDemandError::Busy) => Err(ApiError(
+            StatusCode::TOO_MANY_REQUESTS,
+            "já há checks sob demanda em andamento".to_string(),
+        )),
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message.into())
+}
+
+fn not_found(message: String) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, message)
+}
+
+/// Altera os campos informados do target.
+async fn update_target(
+    State(state): State<ApiState>,
+    Path(target_id): Path<i32>,
+    Json(body): Json<TargetUpdate>,
+) -> Result<Json<Target>, ApiError> {
+    let mut target = state
+        .storage
+        .get_target(target_id)
+        .await?
+        .ok_or_else(|| not_found(format!("target {} não encontrado", target_id)))?;
+    if let Some(name) = body.name {
+        if name.trim().is_empty() {
+            return Err(bad_request("name: não pode ser vazio"));
+        }
+        target.name = name;
+    }
+    if body.check_interval_secs.is_some_and(|s| s <= 0) {
+        return Err(bad_request("check_interval_secs: deve ser maior que zero"));
+    }
+    if body.fail_threshold.is_some_and(|t| t <= 0) {
+        return Err(bad_request("fail_threshold: deve ser maior que zero"));
+    }
+    if body.weight.is_some_and(|w| !(w.is_finite() && w > 0.0)) {
+        return Err(bad_request("weight: deve ser maior que zero"));
+    }
+    if body
+        .degraded_rtt_ms
+        .is_some_and(|r| !(r.is_finite() && r > 0.0))
+    {
+        return Err(bad_request("degraded_rtt_ms: deve ser maior que zero"));
+    }
+    if body
+        .degraded_loss_percent
+        .is_some_and(|l| !(1..=100).contains(&l))
+    {
+        return Err(bad_request(
+            "degraded_loss_percent: deve estar entre 1 e 100",
+        ));
+    }
+    ping::validate_marking(body.ping_payload_bytes, body.dscp).map_err(bad_request)?;
+    target.asn = body.asn.or(target.asn);
+    target.provider = body.provider.or(target.provider);
+    target.region = body.region.or(target.region);
+    target.tags = body.tags.unwrap_or(target.tags);
+    target.check_interval_secs = body.check_interval_secs.or(target.check_interval_secs);
+    target.fail_threshold = body.fail_threshold.or(target.fail_threshold);
+    target.weight = body.weight.or(target.weight);
+    target.degraded_rtt_ms = body.degraded_rtt_ms.or(target.degraded_rtt_ms);
+    target.degraded_loss_percent = body.degraded_loss_percent.or(target.degraded_loss_percent);
+    target.ping_payload_bytes = body.ping_payload_bytes.or(target.ping_payload_bytes);
+    target.dscp = body.dscp.or(target.dscp);
+    target.tenant = body.tenant.or(target.tenant);
+    target.enabled = body.enabled.unwrap_or(target.enabled);
+    if !state.storage.update_target(&target).await? {
+        return Err(not_found(format!("target {} não encontrado", target_id)));
+    }
+    info!("[API] Target {} ({}) atualizado", target.id, target.address);
+    Ok(Json(target))
+}
+
+async fn delete_target(
+    State(state): State<ApiState>,
+    Path(target_id): Path<i32>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, ApiError> {
+    let outcome = state.storage.delete_target(target_id, query.purge).await?;
+    deleted(outcome, format!("target {}", target_id))
+}
+
+async fn probes(State(state): State<ApiState>) -> Result<Json<Vec<Probe>>, ApiError> {
+    Ok(Json(state.reads.list_probes().await?))
+}
+
+async fn create_probe(
+    State(state): State<ApiState>,
+    Json(body): Json<NewProbe>,
+) -> Result<(StatusCode, Json<Probe>), ApiError> {
+    if body.location.trim().is_empty() {
+        return Err(bad_request("location: não pode ser vazio"));
+    }
+    let mut probe = Probe {
+        id: 0,
+        location: body.location,
+        ip_address: body.ip_address,
+        provider: body.provider,
+        created_at: None,
+        geo: Default::default(),
+    };
+    probe.id = state.storage.insert_probe(&probe).await?;
+    info!("[API] Probe {} ({}) cadastrado", probe.id, probe.location);
+    Ok((StatusCode::CREATED, Json(probe)))
+}
+
+/// Altera os campos informados do probe.
+async fn update_probe(
+    State(state): State<ApiState>,
+    Path(probe_id): Path<i32>,
+    Json(body): Json<ProbeUpdate>,
+) -> Result<Json<Probe>, ApiError> {
+    let mut probe = state
+        .storage
+        .list_probes()
+        .await?
+        .into_iter()
+        .find(|p| p.id == probe_id)
+        .ok_or_else(|| not_found(format!("probe {} não encontrado", probe_id)))?;
+    if let Some(location) = body.location {
+        if location.trim().is_empty() {
+            return Err(bad_request("location: não pode ser vazio"));
+        }
+        probe.location = location;
+    }
+    probe.ip_address = body.ip_address.or(probe.ip_address);
+    probe.provider = body.provider.or(probe.provider);
+    if !state.storage.update_probe(&probe).await? {
+        return Err(not_found(format!("probe {} não encontrado", probe_id)));
+    }
+    info!("[API] Probe {} ({}) atualizado", probe.id, probe.location);
+    Ok(Json(probe))
+}
+
+async fn delete_probe(
+    State(state): State<ApiState>,
+    Path(probe_id): Path<i32>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, ApiError> {
+    let outcome = state.storage.delete_probe(probe_id, query.purge).await?;
+    deleted(outcome, format!("probe {}", probe_id))
+}
+
+/// `204` na remoção; `409` se o registro tem histórico e não houve `purge`.
+fn deleted(outcome: DeleteOutcome, what: String) -> Result<StatusCode, ApiError> {
+    match outcome {
+        DeleteOutcome::Deleted => {
+            info!("[API] {} removido", what);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        DeleteOutcome::NotFound => Err(not_found(format!("{} não encontrado", what))),
+        DeleteOutcome::HasHistory => Err(ApiError(
+            StatusCode::CONFLICT,
+            format!(
+                "{} tem histórico gravado; use ?purge=true para apagá-lo junto",
+                what
+            ),
+        )),
+    }
+}
+
+/// Janelas de manutenção ativas ou futuras (`?all=true` inclui as
+/// encerradas).
+async fn maintenance_windows(
+    State(state): State<ApiState>,
+    Query(query): Query<MaintenanceQuery>,
+) -> Result<Json<Vec<MaintenanceWindow>>, ApiError> {
+    let ending_after = (!query.all).then(Utc::now);
+    Ok(Json(
+        state.reads.list_maintenance_windows(ending_after).await?,
+    ))
+}
+
+async fn create_maintenance_window(
+    State(state): State<ApiState>,
+    Json(body): Json<MaintenanceRequest>,
+) -> Result<(StatusCode, Json<MaintenanceWindow>), ApiError> {
+    let mut window = maintenance_window(&state, 0, body).await?;
+    window.id = state.storage.insert_maintenance_window(&window).await?;
+    info!(
+        "[API] Janela de manutenção {} cadastrada ({} a {})",
+        window.id, window.starts_at, window.ends_at
+    );
+    let stored = state.storage.get_maintenance_window(window.id).await?;
+    Ok((StatusCode::CREATED, Json(stored.unwrap_or(window))))
+}
+
+async fn update_maintenance_window(
+    State(state): State<ApiState>,
+    Path(window_id): Path<i64>,
+    Json(body): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceWindow>, ApiError> {
+    let window = maintenance_window(&state, window_id, body).await?;
+    if !state.storage.update_maintenance_window(&window).await? {
+        return Err(not_found(format!(
+            "janela de manutenção {} não encontrada",
+            window_id
+        )));
+    }
+    info!("[API] Janela de manutenção {} atualizada", window_id);
+    Ok(Json(
+        state
+            .storage
+            .get_maintenance_window(window_id)
+            .await?
+            .unwrap_or(window),
+    ))
+}
+
+async fn delete_maintenance_window(
+    State(state): State<ApiState>,
+    Path(window_id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    if !state.storage.delete_maintenance_window(window_id).await? {
+        return Err(not_found(format!(
+            "janela de manutenção {} não encontrada",
+            window_id
+        )));
+    }
+    info!("[API] Janela de manutenção {} removida", window_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Valida o pedido (intervalo, probe e target existentes) e monta a janela.
+async fn maintenance_window(
+    state: &ApiState,
+    id: i64,
+    body: MaintenanceRequest,
+) -> Result<MaintenanceWindow, ApiError> {
+    let starts_at = body.starts_at.unwrap_or_else(Utc::now);
+    if body.ends_at <= starts_at {
+        return Err(bad_request("ends_at: deve ser posterior a starts_at"));
+    }
+    if let Some(probe_id) = body.probe_id
+        && !state
+            .storage
+            .list_probes()
+            .await?
+            .iter()
+            .any(|p| p.id == probe_id)
+    {
+        return Err(bad_request(format!(
+            "probe_id: probe {} não existe",
+            probe_id
+        )));
+    }
+    if let Some(target_id) = body.target_id
+        && state.storage.get_target(target_id).await?.is_none()
+    {
+        return Err(bad_request(format!(
+            "target_id: target {} não existe",
+            target_id
+        )));
+    }
+    Ok(MaintenanceWindow {
+        id,
+        probe_id: body.probe_id,
+        target_id: body.target_id,
+        starts_at,
+        ends_at: body.ends_at,
+        reason: body.reason,
+        created_at: None,
+    })
+}
+
+async fn outage(
+    State(state): State<ApiState>,
+    Path(outage_id): Path<i64>,
+) -> Result<Json<OutageEvent>, ApiError> {
+    state
+        .reads
+        .get_outage_event(outage_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(format!("outage {} não encontrado", outage_id)))
+}
+
+/// Anexa uma anotação de operador ao outage; sem `author`, vale o nome do
+/// token do pedido.
+async fn create_outage_annotation(
+    State(state): State<ApiState>,
+    Path(outage_id): Path<i64>,
+    caller: Option<Extension<Caller>>,
+    Json(body): Json<AnnotationRequest>,
+) -> Result<(StatusCode, Json<OutageEvent>), ApiError> {
+    let text = body.text.trim();
+    if text.is_empty() {
+        return Err(bad_request("text: não pode ser vazio"));
+    }
+    if text.chars().count() > MAX_ANNOTATION_CHARS {
+        return Err(bad_request(format!(
+            "text: no máximo {} caracteres",
+            MAX_ANNOTATION_CHARS
+        )));
+    }
+    let annotation = OutageAnnotation {
+        text: text.to_string(),
+        author: body
+            .author
+            .filter(|a| !a.trim().is_empty())
+            .or(caller.map(|Extension(c)| c.name)),
+        created_at: Utc::now(),
+    };
+    let event = state
+        .storage
+        .add_outage_annotation(outage_id, &annotation)
+        .await?
+        .ok_or_else(|| not_found(format!("outage {} não encontrado", outage_id)))?;
+    info!(
+        "[API] Anotação adicionada ao outage {} ({})",
+        outage_id,
+        annotation.author.as_deref().unwrap_or("sem autor")
+    );
+    Ok((StatusCode::CREATED, Json(event)))
+}
+
+async fn delete_outage_annotation(
+    State(state): State<ApiState>,
+    Path((outage_id, index)): Path<(i64, i32)>,
+) -> Result<StatusCode, ApiError> {
+    if !state
+        .storage
+        .delete_outage_annotation(outage_id, index)
+        .await?
+    {
+        return Err(not_found(format!(
+            "anotação {} do outage {} não encontrada",
+            index, outage_id
+        )));
+    }
+    info!("[API] Anotação {} do outage {} removida", index, outage_id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,219 @@
+//! as_health.rs — Saúde do caminho por AS, a partir dos traceroutes
+//!
+//! Cada traceroute é dividido em trechos por ASN (salto com endereço público
+//! e ASN resolvido). A contribuição de um AS num traceroute é o que ele soma
+//! ao caminho:
+//!
+//! - **latência**: RTT do último salto do AS menos o RTT do último salto
+//!   antes dele (nunca negativa);
+//! - **perda**: perda "que segue adiante" no último salto do AS menos a do
+//!   salto anterior. A perda de um salto só conta se persiste nos saltos
+//!   seguintes (o mínimo dali até o fim), porque roteadores limitam respostas
+//!   ICMP sem perder o tráfego que encaminham.
+//!
+//! As contribuições são médias por AS e por janela de `bucket_minutes`, para
+//! que problemas crônicos dentro de um provedor de trânsito apareçam mesmo
+//! quando nenhum target isolado chega a cair.
+
+use crate::storage::Storage;
+use crate::traceroute::{Hop, Traceroute};
+use crate::types::Target;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Contribuição média de um AS numa janela.
+#[derive(Debug, Clone, Serialize)]
+pub struct AsBucket {
+    pub start: DateTime<Utc>,
+    pub traces: usize,
+    pub latency_ms: f64,
+    pub loss_percent: f64,
+}
+
+/// Saúde de um AS no período consultado.
+#[derive(Debug, Clone, Serialize)]
+pub struct AsHealth {
+    pub asn: i32,
+    /// Provedor dos targets cadastrados com este ASN, se houver.
+    pub provider: Option<String>,
+    pub traces: usize,
+    pub probes: usize,
+    pub targets: usize,
+    pub latency_ms: f64,
+    pub loss_percent: f64,
+    pub buckets: Vec<AsBucket>,
+}
+
+/// Relatório de saúde por AS.
+#[derive(Debug, Clone, Serialize)]
+pub struct AsHealthReport {
+    pub generated_at: DateTime<Utc>,
+    pub window_hours: i64,
+    pub bucket_minutes: i64,
+    pub traceroutes: usize,
+    /// Do AS com mais perda para o de menos (empate: mais latência).
+    pub ases: Vec<AsHealth>,
+}
+
+/// Contribuição de um AS num traceroute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Contribution {
+    asn: i32,
+    latency_ms: f64,
+    loss_percent: f64,
+}
+
+/// Divide um traceroute em trechos por AS e calcula a contribuição de cada um.
+fn contributions(hops: &[Hop]) -> Vec<Contribution> {
+    // Perda que segue adiante: mínimo do salto até o fim do caminho
+    let mut forwarded = vec![0.0; hops.len()];
+    let mut downstream = f64::INFINITY;
+    for (i, hop) in hops.iter().enumerate().rev() {
+        downstream = downstream.min(hop.loss_percent());
+        forwarded[i] = downstream;
+    }
+
+    let mut spans: Vec<(i32, usize, usize)> = Vec::new();
+    for (i, hop) in hops.iter().enumerate() {
+        let Some(asn) = hop.asn else {
+            continue;
+        };
+        match spans.iter_mut().find(|(a, ..)| *a == asn) {
+            Some(span) => span.2 = i,
+            None => spans.push((asn, i, i)),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(asn, first, last)| {
+            let rtt_before = hops[..first].iter().rev().find_map(|h| h.rtt_ms);
+            let rtt_end = hops[first..=last].iter().rev().find_map(|h| h.rtt_ms);
+            let loss_before = first.checked_sub(1).map_or(0.0, |i| forwarded[i]);
+            Contribution {
+                asn,
+                latency_ms: rtt_end.map_or(0.0, |end| (end - rtt_before.unwrap_or(0.0)).max(0.0)),
+                loss_percent: (forwarded[last] - loss_before).max(0.0),
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Accumulator {
+    traces: usize,
+    latency_ms: f64,
+    loss_percent: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, contribution: &Contribution) {
+        self.traces += 1;
+        self.latency_ms += contribution.latency_ms;
+        self.loss_percent += contribution.loss_percent;
+    }
+
+    fn mean(&self) -> (f64, f64) {
+        let n = self.traces.max(1) as f64;
+        (self.latency_ms / n, self.loss_percent / n)
+    }
+}
+
+/// Relatório das últimas `window_hours` horas, direto do banco.
+pub async fn report(
+    storage: &Storage,
+    window_hours: i64,
+    bucket_minutes: i64,
+) -> Result<AsHealthReport> {
+    let since = Utc::now() - chrono::Duration::hours(window_hours);
+    let traceroutes = storage.list_traceroutes(since).await?;
+    let targets = storage.list_targets().await?;
+    Ok(summarize(
+        &traceroutes,
+        &targets,
+        window_hours,
+        bucket_minutes,
+    ))
+}
+
+/// Agrega os traceroutes do período por AS e por janela.
+pub fn summarize(
+    traceroutes: &[Traceroute],
+    targets: &[Target],
+    window_hours: i64,
+    bucket_minutes: i64,
+) -> AsHealthReport {
+    let bucket_secs = bucket_minutes.max(1) * 60;
+    let mut totals: HashMap<i32, Accumulator> = HashMap::new();
+    let mut buckets: HashMap<i32, BTreeMap<i64, Accumulator>> = HashMap::new();
+    let mut seen: HashMap<i32, (HashSet<i32>, HashSet<i32>)> = HashMap::new();
+    for traceroute in traceroutes {
+        let bucket = traceroute.timestamp.timestamp().div_euclid(bucket_secs) * bucket_secs;
+        for contribution in contributions(&traceroute.hops) {
+            totals
+                .entry(contribution.asn)
+                .or_default()
+                .add(&contribution);
+            buckets
+                .entry(contribution.asn)
+                .or_default()
+                .entry(bucket)
+                .or_default()
+                .add(&contribution);
+            let (probes, targets) = seen.entry(contribution.asn).or_default();
+            probes.insert(traceroute.probe_id);
+            targets.insert(traceroute.target_id);
+        }
+    }
+
+    let providers: HashMap<i32, &str> = targets
+        .iter()
+        .filter_map(|t| Some((t.asn?, t.provider.as_deref()?)))
+        .collect();
+    let mut ases: Vec<AsHealth> = totals
+        .into_iter()
+        .map(|(asn, total)| {
+            let (latency_ms, loss_percent) = total.mean();
+            let (probes, targets) = seen.remove(&asn).unwrap_or_default();
+            AsHealth {
+                asn,
+                provider: providers.get(&asn).map(|p| p.to_string()),
+                traces: total.traces,
+                probes: probes.len(),
+                targets: targets.len(),
+                latency_ms,
+                loss_percent,
+                buckets: buckets
+                    .remove(&asn)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|(start, acc)| {
+                        let (latency_ms, loss_percent) = acc.mean();
+                        Some(AsBucket {
+                            start: DateTime::from_timestamp(start, 0)?,
+                            traces: acc.traces,
+                            latency_ms,
+                            loss_percent,
+                        })
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    ases.sort_by(|a, b| {
+        b.loss_percent
+            .total_cmp(&a.loss_percent)
+            .then(b.latency_ms.total_cmp(&a.latency_ms))
+            .then(a.asn.cmp(&b.asn))
+    });
+
+    AsHealthReport {
+        generated_at: Utc::now(),
+        window_hours,
+        bucket_minutes,
+        traceroutes: traceroutes.len(),
+        ases,
+    }
+}
@@ -0,0 +1,224 @@
+//! asymmetry.rs — Estimativa de assimetria de latência via TCP
+//!
+//! Cada amostra abre uma conexão TCP e mede duas idas e voltas:
+//!
+//! - **handshake**: SYN → SYN-ACK (duração do `connect`). Pacotes pequenos nos
+//!   dois sentidos, tratados no kernel: serve de linha de base simétrica.
+//! - **dados**: um segmento grande (`payload_bytes`) até o seu ACK, observado
+//!   pelo `TCP_INFO` do kernel (`tcpi_unacked == 0`). Só o sentido de ida
+//!   carrega o pacote grande; a volta é um ACK pequeno.
+//!
+//! O excedente do RTT dos dados sobre o do handshake é atribuído ao caminho de
+//! ida (serialização/fila de pacotes grandes); a volta fica com metade do RTT
+//! do handshake. É uma estimativa: diferenças grandes e persistentes apontam
+//! roteamento assimétrico ou gargalo num único sentido.
+//!
+//! As medições são gravadas como métricas `tcp_ipv4`/`tcp_ipv6` com a
+//! decomposição em `details` e a marca `diagnostic`, ficando fora do consenso e
+//! dos agregados de disponibilidade.
+
+use crate::config::AsymmetryConfig;
+use crate::provenance;
+use crate::types::{
+    CheckError, ConnectivityMetric, MeasurementBackend, MetricStatus, MetricType, Probe, Target,
+};
+use chrono::Utc;
+use serde_json::json;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Resultado de uma amostra bem-sucedida.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    handshake_ms: f64,
+    data_ack_ms: Option<f64>,
+    kernel_srtt_ms: Option<f64>,
+}
+
+/// Lê `TCP_INFO` do socket: (segmentos sem ACK, srtt em ms).
+#[cfg(target_os = "linux")]
+fn tcp_info(stream: &TcpStream) -> Option<(u32, f64)> {
+    use std::os::fd::AsRawFd;
+    // SAFETY: `tcp_info` é POD; o kernel preenche até `len` bytes.
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            &mut info as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    (rc == 0).then(|| (info.tcpi_unacked, info.tcpi_rtt as f64 / 1000.0))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn tcp_info(_stream: &TcpStream) -> Option<(u32, f64)> {
+    None
+}
+
+/// Requisição `HEAD` preenchida até `size` bytes, para que o servidor aceite o
+/// segmento sem fechar a conexão antes do ACK.
+fn payload(address: IpAddr, size: usize) -> Vec<u8> {
+    let head = format!(
+        "HEAD / HTTP/1.1\r\nHost: {}\r\nUser-Agent: monitoramento_rede\r\nConnection: close\r\nX-Padding: ",
+        address
+    );
+    let tail = "\r\n\r\n";
+    let padding = size.saturating_sub(head.len() + tail.len());
+    format!("{}{}{}", head, "x".repeat(padding), tail).into_bytes()
+}
+
+/// Executa uma amostra (bloqueante).
+fn sample(addr: SocketAddr, body: &[u8], timeout: Duration) -> std::io::Result<Sample> {
+    let started = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    let handshake_ms = started.elapsed().as_secs_f64() * 1000.0;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let sent = Instant::now();
+    stream.write_all(body)?;
+    let mut data_ack_ms = None;
+    let mut kernel_srtt_ms = None;
+    while sent.elapsed() < timeout {
+        match tcp_info(&stream) {
+            Some((0, srtt)) => {
+                data_ack_ms = Some(sent.elapsed().as_secs_f64() * 1000.0);
+                kernel_srtt_ms = Some(srtt);
+                break;
+            }
+            Some(_) => std::thread::sleep(Duration::from_micros(50)),
+            None => break,
+        }
+    }
+    Ok(Sample {
+        handshake_ms,
+        data_ack_ms,
+        kernel_srtt_ms,
+    })
+}
+
+fn min_of(values: impl Iterator<Item = f64>) -> Option<f64> {
+    values.fold(None, |acc, v| Some(acc.map_or(v, |a: f64| a.min(v))))
+}
+
+/// Mede um target e monta a métrica correspondente (bloqueante).
+fn measure_target(
+    target: &Target,
+    probe_id: i32,
+    config: &AsymmetryConfig,
+    cycle_id: i64,
+) -> ConnectivityMetric {
+    let addr = SocketAddr::new(target.address, config.port);
+    let timeout = Duration::from_secs(config.timeout_secs.max(1));
+    let body = payload(target.address, config.payload_bytes);
+
+    let mut samples = Vec::with_capacity(config.samples);
+    let mut last_error = None;
+    let mut timeouts = 0;
+    for _ in 0..config.samples {
+        match sample(addr, &body, timeout) {
+            Ok(s) => samples.push(s),
+            Err(e) => {
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                ) {
+                    timeouts += 1;
+                }
+                last_error = Some((CheckError::from_io(&e), e.to_string()));
+            }
+        }
+    }
+
+    let status = if timeouts == config.samples {
+        MetricStatus::Timeout
+    } else if samples.len() == config.samples {
+        MetricStatus::Up
+    } else if !samples.is_empty() {
+        MetricStatus::Degraded
+    } else {
+        MetricStatus::Down
+    };
+
+    // Menor valor de cada série: descarta ruído de fila momentâneo
+    let handshake = min_of(samples.iter().map(|s| s.handshake_ms));
+    let data_ack = min_of(samples.iter().filter_map(|s| s.data_ack_ms));
+    let kernel_srtt = min_of(samples.iter().filter_map(|s| s.kernel_srtt_ms));
+    let (forward, reverse) = match (handshake, data_ack) {
+        (Some(h), Some(d)) => (Some(h / 2.0 + (d - h).max(0.0)), Some(h / 2.0)),
+        _ => (None, None),
+    };
+
+    let metric_type = match target.address {
+        IpAddr::V4(_) => MetricType::TcpIpv4,
+        IpAddr::V6(_) => MetricType::TcpIpv6,
+    };
+    let mut metric = ConnectivityMetric {
+        id: 0,
+        cycle_id,
+        probe_id,
+        target_id: target.id,
+        timestamp: Utc::now(),
+        metric_type,
+        status,
+        response_time_ms: handshake,
+        packet_loss_percent: Some((100 - (samples.len() * 100) / config.samples.max(1)) as i16),
+        error: None,
+        details: Some(json!({
+            "diagnostic": true,
+            "mode": "tcp_asymmetry",
+            "port": config.port,
+            "payload_bytes": body.len(),
+            "samples": config.samples,
+            "successful_samples": samples.len(),
+            "handshake_rtt_ms": handshake,
+            "data_ack_rtt_ms": data_ack,
+            "kernel_srtt_ms": kernel_srtt,
+            "forward_estimate_ms": forward,
+            "return_estimate_ms": reverse,
+            "asymmetry_ms": forward.zip(reverse).map(|(f, r)| f - r),
+        })),
+        provenance: Some(provenance::resolve(
+            target.address,
+            MeasurementBackend::Native,
+        )),
+        attempts: Vec::new(),
+    };
+    if let Some((kind, message)) = last_error {
+        metric.set_error(kind, message);
+    }
+    metric
+}
+
+/// Mede todos os targets em paralelo (cada um numa thread bloqueante).
+pub async fn measure_targets(
+    targets: &[Target],
+    probe: &Probe,
+    config: &AsymmetryConfig,
+    cycle_id: i64,
+) -> Vec<ConnectivityMetric> {
+    let handles: Vec<_> = targets
+        .iter()
+        .cloned()
+        .map(|target| {
+            let config = config.clone();
+            let probe_id = probe.id;
+            tokio::task::spawn_blocking(move || {
+                measure_target(&target, probe_id, &config, cycle_id)
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        if let Ok(metric) = handle.await {
+            results.push(metric);
+        }
+    }
+    results
+}
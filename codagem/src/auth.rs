@@ -0,0 +1,174 @@
+//! auth.rs — Tokens com escopo e lista de IPs permitidos da API HTTP
+//!
+//! Os tokens de `[[api.tokens]]` (e o `write_token` legado, que equivale a um
+//! token `admin`) são comparados em tempo constante com o
+//! `Authorization: Bearer <token>` do pedido; o escopo `read` dá acesso às
+//! consultas do modo `full` e o `admin`, também à escrita. `allowed_ips`
+//! restringe as conexões por IP ou bloco CIDR, pelo endereço do socket (um
+//! proxy reverso na frente conta como o cliente).
+
+use crate::config::{ApiConfig, TokenScope};
+use axum::http::{HeaderMap, header};
+use std::net::IpAddr;
+
+/// Nome dado ao `write_token` nos logs.
+const WRITE_TOKEN_NAME: &str = "write_token";
+
+/// Bloco de endereços (`10.0.0.0/8`, `2001:db8::/32` ou um IP isolado).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpBlock {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpBlock {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (network, prefix) = match input.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (input, None),
+        };
+        let network: IpAddr = network
+            .parse()
+            .map_err(|_| format!("`{}` não é um IP nem um bloco CIDR", input))?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= bits)
+                .ok_or_else(|| format!("`{}`: prefixo fora de 0-{}", input, bits))?,
+            None => bits,
+        };
+        Ok(Self { network, prefix })
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        // IPv4 mapeado em IPv6 (socket dual-stack) conta como IPv4
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+            v4 => v4,
+        };
+        let (network, address, bits) = match (self.network, address) {
+            (IpAddr::V4(n), IpAddr::V4(a)) => (u32::from(n) as u128, u32::from(a) as u128, 32),
+            (IpAddr::V6(n), IpAddr::V6(a)) => (u128::from(n), u128::from(a), 128),
+            _ => return false,
+        };
+        let host_bits = bits - u32::from(self.prefix);
+        if host_bits >= bits {
+            return true;
+        }
+        network >> host_bits == address >> host_bits
+    }
+}
+
+/// Token conhecido, já resolvido a partir da configuração.
+#[derive(Debug, Clone)]
+struct KnownToken {
+    name: String,
+    token: String,
+    scope: TokenScope,
+}
+
+/// Quem fez o pedido, para os logs.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub name: String,
+    pub scope: TokenScope,
+}
+
+/// Resultado da autenticação de um pedido.
+#[derive(Debug, Clone)]
+pub enum Authorization {
+    Granted(Caller),
+    /// Sem token ou token desconhecido (`401`).
+    Unauthenticated,
+    /// Token válido sem o escopo exigido (`403`).
+    Forbidden(Caller),
+}
+
+/// Regras de acesso da API.
+#[derive(Debug, Clone, Default)]
+pub struct ApiAuth {
+    tokens: Vec<KnownToken>,
+    allowed: Vec<IpBlock>,
+    pub log_requests: bool,
+}
+
+impl ApiAuth {
+    pub fn from_config(config: &ApiConfig) -> Self {
+        let mut tokens: Vec<KnownToken> = config
+            .tokens
+            .iter()
+            .map(|t| KnownToken {
+                name: t.name.clone(),
+                token: t.token.clone(),
+                scope: t.scope,
+            })
+            .collect();
+        if let Some(token) = &config.write_token {
+            tokens.push(KnownToken {
+                name: WRITE_TOKEN_NAME.to_string(),
+                token: token.clone(),
+                scope: TokenScope::Admin,
+            });
+        }
+        Self {
+            tokens,
+            allowed: config
+                .allowed_ips
+                .iter()
+                .filter_map(|entry| IpBlock::parse(entry).ok())
+                .collect(),
+            log_requests: config.log_requests,
+        }
+    }
+
+    /// Há tokens configurados: as rotas do modo `full` exigem um.
+    pub fn enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Há algum token `admin` (as rotas de escrita são montadas).
+    pub fn has_admin(&self) -> bool {
+        self.tokens.iter().any(|t| t.scope == TokenScope::Admin)
+    }
+
+    /// A conexão vem de um endereço permitido (sempre, sem `allowed_ips`).
+    pub fn allows(&self, address: IpAddr) -> bool {
+        self.allowed.is_empty() || self.allowed.iter().any(|b| b.contains(address))
+    }
+
+    /// Confere o token do pedido contra o escopo exigido.
+    pub fn authorize(&self, headers: &HeaderMap, required: TokenScope) -> Authorization {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        let known = self
+            .tokens
+            .iter()
+            .find(|t| constant_time_eq(presented.as_bytes(), t.token.as_bytes()));
+        match known {
+            None => Authorization::Unauthenticated,
+            Some(known) => {
+                let caller = Caller {
+                    name: known.name.clone(),
+                    scope: known.scope,
+                };
+                if known.scope >= required {
+                    Authorization::Granted(caller)
+                } else {
+                    Authorization::Forbidden(caller)
+                }
+            }
+        }
+    }
+}
+
+/// Comparação sem retorno antecipado, para não vazar o token por tempo de
+/// resposta.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,183 @@
+//! baseline.rs — Baselines de latência e perda por target
+//!
+//! Cada par probe/target mantém uma média móvel exponencial (EWMA) e a
+//! variância exponencial do RTT e da perda. Quando o valor corrente se afasta
+//! da baseline por mais de `sigmas` desvios (e de um delta absoluto mínimo),
+//! abre-se um evento de anomalia, encerrado quando o valor volta à faixa.
+//! Captura incidentes "lento, mas no ar" que o consenso Up/Down não vê.
+
+use crate::config::AnomalyConfig;
+use crate::types::{ConnectivityMetric, MetricStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Grandeza monitorada pela baseline.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyKind {
+    Latency,
+    Loss,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::Latency => "latency",
+            AnomalyKind::Loss => "loss",
+        }
+    }
+}
+
+/// Desvio em relação à baseline (aberto ou encerrado).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyEvent {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub kind: AnomalyKind,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Baseline no momento da abertura.
+    pub baseline_mean: f64,
+    pub baseline_stddev: f64,
+    /// Pior valor observado durante o evento.
+    pub peak_value: f64,
+    /// Desvios (sigmas) do pior valor em relação à baseline.
+    pub peak_sigmas: f64,
+}
+
+/// Média e variância exponenciais.
+#[derive(Debug, Clone, Copy, Default)]
+struct Ewma {
+    mean: f64,
+    variance: f64,
+    samples: u64,
+}
+
+impl Ewma {
+    fn stddev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    fn update(&mut self, value: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean = value;
+        } else {
+            let diff = value - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.samples += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct TargetBaseline {
+    rtt: Ewma,
+    loss: Ewma,
+    open: HashMap<AnomalyKind, AnomalyEvent>,
+}
+
+/// Baselines dos targets de um probe.
+#[derive(Debug, Default)]
+pub struct BaselineTracker {
+    targets: HashMap<i32, TargetBaseline>,
+}
+
+impl BaselineTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Avalia as métricas do ciclo contra as baselines e as atualiza.
+    /// Métricas fora da faixa recebem `details.anomaly`. Retorna os eventos
+    /// abertos ou encerrados neste ciclo.
+    pub fn observe(
+        &mut self,
+        metrics: &mut [ConnectivityMetric],
+        config: &AnomalyConfig,
+    ) -> Vec<AnomalyEvent> {
+        let mut events = Vec::new();
+        for metric in metrics.iter_mut() {
+            // Sem resposta é assunto do consenso, não da baseline
+            if metric.status == MetricStatus::Down || metric.status == MetricStatus::Timeout {
+                continue;
+            }
+            let baseline = self.targets.entry(metric.target_id).or_default();
+            let observations = [
+                (
+                    AnomalyKind::Latency,
+                    metric.response_time_ms,
+                    config.min_delta_ms,
+                ),
+                (
+                    AnomalyKind::Loss,
+                    metric.packet_loss_percent.map(f64::from),
+                    config.min_loss_delta_percent,
+                ),
+            ];
+            for (kind, value, min_delta) in observations {
+                let Some(value) = value else { continue };
+                let ewma = match kind {
+                    AnomalyKind::Latency => &mut baseline.rtt,
+                    AnomalyKind::Loss => &mut baseline.loss,
+                };
+                let warmed = ewma.samples >= config.min_samples;
+                let deviation = value - ewma.mean;
+                // Piso no desvio para que baselines muito estáveis não
+                // disparem com jitter mínimo
+                let stddev = ewma.stddev().max(min_delta / config.sigmas);
+                let sigmas = deviation / stddev;
+                let anomalous = warmed && deviation >= min_delta && sigmas >= config.sigmas;
+
+                match (anomalous, baseline.open.get_mut(&kind)) {
+                    (true, Some(open)) => {
+                        if value > open.peak_value {
+                            open.peak_value = value;
+                            open.peak_sigmas = sigmas;
+                        }
+                    }
+                    (true, None) => {
+                        let event = AnomalyEvent {
+                            probe_id: metric.probe_id,
+                            target_id: metric.target_id,
+                            kind,
+                            start_time: metric.timestamp,
+                            end_time: None,
+                            baseline_mean: ewma.mean,
+                            baseline_stddev: ewma.stddev(),
+                            peak_value: value,
+                            peak_sigmas: sigmas,
+                        };
+                        baseline.open.insert(kind, event.clone());
+                        events.push(event);
+                    }
+                    (false, Some(_)) => {
+                        if let Some(mut event) = baseline.open.remove(&kind) {
+                            event.end_time = Some(metric.timestamp);
+                            events.push(event);
+                        }
+                    }
+                    (false, None) => {}
+                }
+
+                if anomalous {
+                    let details = metric.details.get_or_insert_with(|| json!({}));
+                    if let Some(map) = details.as_object_mut() {
+                        let anomaly = map.entry("anomaly").or_insert_with(|| json!({}));
+                        anomaly[kind.as_str()] = json!({
+                            "baseline_mean": ewma.mean,
+                            "baseline_stddev": ewma.stddev(),
+                            "value": value,
+                            "sigmas": sigmas,
+                        });
+                    }
+                }
+                ewma.update(value, config.alpha);
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,302 @@
+//! bgp.rs — Visibilidade BGP dos prefixos dos targets durante outages
+//!
+//! Um target que para de responder pode ter caído ou pode ter ficado sem
+//! rota. Com `[bgp]` configurado, uma task verifica no RIPEstat, a cada
+//! `interval_secs`, os outages (e outages parciais) abertos e, uma última
+//! vez, os recém-encerrados: para o prefixo que cobre cada target afetado,
+//! conta as retiradas e os anúncios vistos pelos peers do RIS desde
+//! `lookback_secs` antes do início e a visibilidade atual.
+//!
+//! O resultado vai para `details.bgp` do evento. Com `routing_incident`, o
+//! outage coincide com retirada de rota (incidente de roteamento); sem ele,
+//! as rotas estavam estáveis e a falha é do host ou do caminho até ele.
+
+use crate::config::BgpConfig;
+use crate::enrichment::{self, RIPESTAT_TIMEOUT};
+use crate::storage::Storage;
+use crate::traceroute;
+use crate::types::{OutageEvent, Target};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error, info, warn};
+
+/// Outages encerrados há mais tempo que isso não são mais verificados.
+const CLOSED_RECHECK_HOURS: i64 = 24;
+
+/// Situação do prefixo durante o outage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteVerdict {
+    /// Sem retiradas relevantes: a rota seguiu anunciada.
+    Stable,
+    /// Retirado e ainda invisível para a maior parte dos peers.
+    Withdrawn,
+    /// Retirado durante o outage e anunciado de novo.
+    Reannounced,
+}
+
+/// Movimento de um prefixo no BGP na janela do outage.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixFinding {
+    pub prefix: String,
+    pub target_ids: Vec<i32>,
+    pub announcements: usize,
+    pub withdrawals: usize,
+    /// Peers do RIS que retiraram o prefixo na janela.
+    pub withdrawn_peers: usize,
+    pub total_peers: Option<u64>,
+    /// Peers que veem o prefixo agora, em % do total.
+    pub visibility_percent: Option<f64>,
+    pub last_withdrawal: Option<DateTime<Utc>>,
+    /// Primeiro anúncio depois da última retirada.
+    pub reannounced_at: Option<DateTime<Utc>>,
+    pub verdict: RouteVerdict,
+}
+
+/// Atualização BGP de um peer do RIS.
+struct Update {
+    timestamp: DateTime<Utc>,
+    withdrawal: bool,
+    peer: String,
+}
+
+/// Loop de verificação; a primeira passada é imediata.
+pub async fn run(storage: Arc<Storage>, config: BgpConfig) {
+    let client = match reqwest::Client::builder()
+        .timeout(RIPESTAT_TIMEOUT)
+        .build()
+        .context("Falha ao criar cliente HTTP do RIPEstat")
+    {
+        Ok(client) => client,
+        Err(e) => {
+            error!("[BGP] Verificação BGP desabilitada: {:?}", e);
+            return;
+        }
+    };
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        match check_pending(&storage, &client, &config).await {
+            Ok(0) => debug!("[BGP] Nenhum outage a verificar"),
+            Ok(checked) => debug!("[BGP] {} outage(s) verificado(s)", checked),
+            Err(e) => warn!("[BGP] Falha na verificação BGP dos outages: {:?}", e),
+        }
+    }
+}
+
+/// Uma passada sobre os outages pendentes; retorna quantos foram
+/// verificados.
+async fn check_pending(
+    storage: &Storage,
+    client: &reqwest::Client,
+    config: &BgpConfig,
+) -> Result<usize> {
+    let since = Utc::now() - chrono::Duration::hours(CLOSED_RECHECK_HOURS);
+    let events = storage.list_outage_events_pending_bgp(since).await?;
+    if events.is_empty() {
+        return Ok(0);
+    }
+    let targets: HashMap<i32, Target> = storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .map(|t| (t.id, t))
+        .collect();
+    // Prefixo de cada endereço, consultado uma vez por passada
+    let mut prefixes: HashMap<IpAddr, Option<String>> = HashMap::new();
+    let mut checked = 0;
+    for event in &events {
+        let Some(bgp) = check_event(client, config, event, &targets, &mut prefixes).await? else {
+            continue;
+        };
+        if bgp["routing_incident"] == json!(true) {
+            info!(
+                "🛰️  [BGP] Outage {} coincide com retirada de rota: {}",
+                event.id, bgp["prefixes"]
+            );
+        }
+        storage.set_outage_event_bgp(event.id, &bgp).await?;
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+/// Verificação de um outage; `None` se nenhum target afetado tem endereço
+/// público. Sem resposta do RIPEstat a passada é interrompida.
+async fn check_event(
+    client: &reqwest::Client,
+    config: &BgpConfig,
+    event: &OutageEvent,
+    targets: &HashMap<i32, Target>,
+    prefixes: &mut HashMap<IpAddr, Option<String>>,
+) -> Result<Option<serde_json::Value>> {
+    let mut by_prefix: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+    let mut unrouted: Vec<i32> = Vec::new();
+    for target_id in &event.affected_targets {
+        let Some(target) = targets.get(target_id) else {
+            continue;
+        };
+        if !traceroute::is_public(target.address) {
+            continue;
+        }
+        let prefix = match prefixes.get(&target.address) {
+            Some(prefix) => prefix.clone(),
+            None => {
+                let prefix = covering_prefix(client, target.address)
+                    .await
+                    .context("RIPEstat indisponível (network-info)")?;
+                prefixes.insert(target.address, prefix.clone());
+                prefix
+            }
+        };
+        match prefix {
+            Some(prefix) => by_prefix.entry(prefix).or_default().push(*target_id),
+            None => unrouted.push(*target_id),
+        }
+    }
+    if by_prefix.is_empty() && unrouted.is_empty() {
+        return Ok(None);
+    }
+
+    let now = Utc::now();
+    let window_start = event.start_time - chrono::Duration::seconds(config.lookback_secs as i64);
+    let window_end = event.end_time.unwrap_or(now);
+    let mut findings = Vec::new();
+    for (prefix, target_ids) in by_prefix {
+        let finding = check_prefix(client, config, &prefix, window_start, window_end)
+            .await
+            .context("RIPEstat indisponível (bgp-updates)")?;
+        findings.push(PrefixFinding {
+            target_ids,
+            ..finding
+        });
+    }
+    let routing_incident =
+        !unrouted.is_empty() || findings.iter().any(|f| f.verdict != RouteVerdict::Stable);
+    Ok(Some(json!({
+        "checked_at": now,
+        "window_start": window_start,
+        "window_end": window_end,
+        "routing_incident": routing_incident,
+        "prefixes": findings,
+        "unrouted_targets": unrouted,
+    })))
+}
+
+/// Prefixo anunciado mais específico que cobre o endereço; `Some(None)` se
+/// o endereço não é anunciado, `None` se o RIPEstat não respondeu.
+async fn covering_prefix(client: &reqwest::Client, address: IpAddr) -> Option<Option<String>> {
+    // {"data": {"asns": ["15169"], "prefix": "8.8.8.0/24"}}
+    let data = enrichment::ripestat(
+        client,
+        "network-info",
+        &[("resource", &address.to_string())],
+    )
+    .await?;
+    Some(
+        data["prefix"]
+            .as_str()
+            .filter(|p| !p.is_empty())
+            .map(str::to_string),
+    )
+}
+
+/// Atualizações e visibilidade de um prefixo na janela.
+async fn check_prefix(
+    client: &reqwest::Client,
+    config: &BgpConfig,
+    prefix: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Option<PrefixFinding> {
+    let (start_param, end_param) = (start.timestamp().to_string(), end.timestamp().to_string());
+    // {"data": {"updates": [{"type": "W", "timestamp": "2024-05-01T12:00:00",
+    //  "attrs": {"source_id": "00-192.0.2.1", ...}}, ...]}}
+    let data = enrichment::ripestat(
+        client,
+        "bgp-updates",
+        &[
+            ("resource", prefix),
+            ("starttime", &start_param),
+            ("endtime", &end_param),
+        ],
+    )
+    .await?;
+    let mut updates: Vec<Update> = data["updates"]
+        .as_array()
+        .map(|updates| updates.iter().filter_map(parse_update).collect())
+        .unwrap_or_default();
+    updates.sort_by_key(|u| u.timestamp);
+
+    // {"data": {"visibility": {"v4": {"ris_peers_seeing": 320, "total_ris_peers": 330}}}}
+    let status = enrichment::ripestat(client, "routing-status", &[("resource", prefix)]).await;
+    let family = if prefix.contains(':') { "v6" } else { "v4" };
+    let visibility = status.as_ref().map(|s| &s["visibility"][family]);
+    let total_peers = visibility.and_then(|v| v["total_ris_peers"].as_u64());
+    let seeing = visibility.and_then(|v| v["ris_peers_seeing"].as_u64());
+    let visibility_percent = match (seeing, total_peers) {
+        (Some(seeing), Some(total)) if total > 0 => Some(100.0 * seeing as f64 / total as f64),
+        _ => None,
+    };
+
+    let withdrawn_peers: HashSet<&str> = updates
+        .iter()
+        .filter(|u| u.withdrawal)
+        .map(|u| u.peer.as_str())
+        .collect();
+    let last_withdrawal = updates
+        .iter()
+        .rev()
+        .find(|u| u.withdrawal)
+        .map(|u| u.timestamp);
+    let reannounced_at = last_withdrawal.and_then(|last| {
+        updates
+            .iter()
+            .find(|u| !u.withdrawal && u.timestamp > last)
+            .map(|u| u.timestamp)
+    });
+    let withdrawn_percent =
+        total_peers.map(|total| 100.0 * withdrawn_peers.len() as f64 / total.max(1) as f64);
+    let verdict = if visibility_percent.is_some_and(|v| v <= 100.0 - config.withdrawal_percent) {
+        RouteVerdict::Withdrawn
+    } else if withdrawn_percent.is_some_and(|w| w >= config.withdrawal_percent) {
+        RouteVerdict::Reannounced
+    } else {
+        RouteVerdict::Stable
+    };
+
+    Some(PrefixFinding {
+        prefix: prefix.to_string(),
+        target_ids: Vec::new(),
+        announcements: updates.iter().filter(|u| !u.withdrawal).count(),
+        withdrawals: updates.iter().filter(|u| u.withdrawal).count(),
+        withdrawn_peers: withdrawn_peers.len(),
+        total_peers,
+        visibility_percent,
+        last_withdrawal,
+        reannounced_at,
+        verdict,
+    })
+}
+
+fn parse_update(update: &serde_json::Value) -> Option<Update> {
+    let timestamp =
+        NaiveDateTime::parse_from_str(update["timestamp"].as_str()?, "%Y-%m-%dT%H:%M:%S")
+            .ok()?
+            .and_utc();
+    Some(Update {
+        timestamp,
+        withdrawal: update["type"].as_str()? == "W",
+        peer: update["attrs"]["source_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
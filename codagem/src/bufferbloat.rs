@@ -0,0 +1,161 @@
+//! bufferbloat.rs — RTT mínimo por target e nota de bufferbloat sob carga
+//!
+//! Médias escondem o principal problema de qualidade de links residenciais e
+//! de pequenas empresas: a fila que cresce no gargalo quando o link está
+//! ocupado. Cada par probe/target mantém o RTT mínimo numa janela deslizante
+//! (`min_rtt_window_secs`), a referência "link vazio". A cada
+//! `interval_cycles` ciclos um teste de banda baixa `load_url` durante
+//! `duration_secs`; os checks do ciclo rodam depois de `ramp_up_secs`, com o
+//! link cheio. O excedente do RTT sobre o mínimo é a latência sob carga, e
+//! vira uma nota (A+ … F, nas faixas usuais dos testes de bufferbloat).
+//!
+//! Toda métrica com RTT recebe `details.bufferbloat` (mínimo e excedente);
+//! nos ciclos sob carga também a vazão do teste e a nota.
+
+use crate::config::BufferbloatConfig;
+use crate::types::{ConnectivityMetric, MetricStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Resultado de um teste de banda.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadResult {
+    pub bytes: u64,
+    pub secs: f64,
+    pub throughput_mbps: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Nota de bufferbloat pelo excedente de RTT sob carga.
+pub fn grade(delta_ms: f64) -> &'static str {
+    match delta_ms {
+        d if d <= 5.0 => "A+",
+        d if d <= 30.0 => "A",
+        d if d <= 60.0 => "B",
+        d if d <= 200.0 => "C",
+        d if d <= 400.0 => "D",
+        _ => "F",
+    }
+}
+
+/// Teste de banda: baixa `load_url` (repetindo se o arquivo acabar) até
+/// `duration_secs`. Uma falha no meio do teste encerra o download e fica em
+/// `error`.
+pub async fn run_load(client: &reqwest::Client, config: &BufferbloatConfig) -> LoadResult {
+    let duration = Duration::from_secs(config.duration_secs);
+    let started = Instant::now();
+    let mut bytes = 0u64;
+    let mut error = None;
+    'download: while started.elapsed() < duration {
+        let left = duration.saturating_sub(started.elapsed());
+        let mut response =
+            match tokio::time::timeout(left, client.get(&config.load_url).send()).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => {
+                    error = Some(e.to_string());
+                    break;
+                }
+                Err(_) => break,
+            };
+        loop {
+            let left = duration.saturating_sub(started.elapsed());
+            match tokio::time::timeout(left, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => bytes += chunk.len() as u64,
+                Ok(Ok(None)) => break,
+                Ok(Err(e)) => {
+                    error = Some(e.to_string());
+                    break 'download;
+                }
+                Err(_) => break 'download,
+            }
+        }
+    }
+    let secs = started.elapsed().as_secs_f64();
+    LoadResult {
+        bytes,
+        secs,
+        throughput_mbps: bytes as f64 * 8.0 / 1_000_000.0 / secs.max(f64::EPSILON),
+        error,
+    }
+}
+
+/// RTT mínimo deslizante dos targets de um probe.
+#[derive(Debug)]
+pub struct MinRttTracker {
+    window: chrono::Duration,
+    /// Amostras fora de carga por target, com RTT crescente (deque
+    /// monotônica: o mínimo da janela é sempre o primeiro elemento).
+    samples: HashMap<i32, VecDeque<(DateTime<Utc>, f64)>>,
+}
+
+impl MinRttTracker {
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window: chrono::Duration::seconds(window_secs as i64),
+            samples: HashMap::new(),
+        }
+    }
+
+    fn min_rtt(&mut self, target_id: i32, now: DateTime<Utc>) -> Option<f64> {
+        let samples = self.samples.get_mut(&target_id)?;
+        while samples.front().is_some_and(|(t, _)| now - *t > self.window) {
+            samples.pop_front();
+        }
+        samples.front().map(|(_, rtt)| *rtt)
+    }
+
+    fn push(&mut self, target_id: i32, timestamp: DateTime<Utc>, rtt: f64) {
+        let samples = self.samples.entry(target_id).or_default();
+        while samples.back().is_some_and(|(_, last)| *last >= rtt) {
+            samples.pop_back();
+        }
+        samples.push_back((timestamp, rtt));
+    }
+
+    /// Anota as métricas do ciclo com o RTT mínimo e o excedente. Com `load`
+    /// (ciclo sob carga) as amostras não entram no mínimo e recebem a nota.
+    /// Retorna o excedente de cada métrica anotada.
+    pub fn observe(
+        &mut self,
+        metrics: &mut [ConnectivityMetric],
+        load: Option<&LoadResult>,
+    ) -> Vec<f64> {
+        let mut deltas = Vec::new();
+        for metric in metrics.iter_mut() {
+            if metric.status == MetricStatus::Down || metric.status == MetricStatus::Timeout {
+                continue;
+            }
+            let Some(rtt) = metric.response_time_ms else {
+                continue;
+            };
+            let min_rtt = self.min_rtt(metric.target_id, metric.timestamp);
+            if load.is_none() {
+                self.push(metric.target_id, metric.timestamp, rtt);
+            }
+            // Sem referência ainda: o primeiro ciclo fora de carga a cria
+            let Some(min_rtt) = min_rtt.or((load.is_none()).then_some(rtt)) else {
+                continue;
+            };
+            let delta = (rtt - min_rtt).max(0.0);
+            let mut annotation = json!({
+                "min_rtt_ms": min_rtt,
+                "delta_ms": delta,
+                "loaded": load.is_some(),
+            });
+            if let Some(load) = load {
+                annotation["grade"] = json!(grade(delta));
+                annotation["throughput_mbps"] = json!(load.throughput_mbps);
+            }
+            let details = metric.details.get_or_insert_with(|| json!({}));
+            if let Some(map) = details.as_object_mut() {
+                map.insert("bufferbloat".into(), annotation);
+            }
+            deltas.push(delta);
+        }
+        deltas
+    }
+}
@@ -0,0 +1,447 @@
+//! bundle.rs — Pacote de postmortem de um incidente (`monitoramento_rede bundle <id>`)
+//!
+//! Junta num único zip tudo o que foi registrado em torno de um evento de
+//! `outage_events`, para anexar a um chamado ou enviar a um fornecedor:
+//!
+//! - `incident.json`: o evento, a janela coberta, os probes e targets
+//!   envolvidos e a contagem de cada arquivo;
+//! - `timeline.md`: linha do tempo (abertura e encerramento, anotações dos
+//!   operadores, mudanças de status por target, decisões do consenso,
+//!   reinícios do scheduler e mudanças de alcance dos traceroutes);
+//! - `metrics.csv`: as métricas dos probes e targets afetados;
+//! - `artifacts.jsonl`: os detalhes das medições que os têm (fases HTTP,
+//!   certificado TLS, decomposição TCP, bufferbloat…), uma por linha;
+//! - `decisions.json`, `traceroutes.json` e `scheduler_incidents.json`.
+//!
+//! A janela vai de `margin_minutes` antes do início até `margin_minutes`
+//! depois do fim (ou até agora, num outage ainda aberto). Sem
+//! `[consensus_decisions]` ou `[traceroute]` configurados, os arquivos
+//! correspondentes saem vazios. Com `[anonymization]` e `exports = true`,
+//! nome e endereço dos targets (e o destino dos traceroutes) saem
+//! pseudonimizados.
+
+use crate::anonymize::Pseudonymizer;
+use crate::cli::BundleArgs;
+use crate::decisions::{ConsensusDecision, DecisionOutcome};
+use crate::storage::Storage;
+use crate::supervisor::SchedulerIncident;
+use crate::traceroute::Traceroute;
+use crate::types::{ConnectivityMetric, OutageEvent, Probe, Target};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Janela coberta pelo pacote.
+#[derive(Debug, Serialize)]
+struct Window {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+/// Conteúdo de `incident.json`.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    generated_at: DateTime<Utc>,
+    event: &'a OutageEvent,
+    window: &'a Window,
+    probes: &'a [Probe],
+    targets: &'a [Target],
+    counts: serde_json::Value,
+}
+
+/// Dados do incidente carregados do banco.
+struct Incident {
+    event: OutageEvent,
+    window: Window,
+    probes: Vec<Probe>,
+    targets: Vec<Target>,
+    metrics: Vec<ConnectivityMetric>,
+    decisions: Vec<ConsensusDecision>,
+    traceroutes: Vec<Traceroute>,
+    scheduler_incidents: Vec<SchedulerIncident>,
+}
+
+/// Gera o pacote do incidente e imprime onde ele foi gravado.
+pub async fn run(
+    storage: &Storage,
+    args: BundleArgs,
+    pseudonymizer: Option<&Pseudonymizer>,
+) -> Result<()> {
+    let mut incident = load(storage, args.incident_id, args.margin_minutes).await?;
+    if let Some(pseudonymizer) = pseudonymizer.filter(|p| p.exports) {
+        incident.pseudonymize(pseudonymizer);
+    }
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("incident-{}.zip", args.incident_id)));
+    let bytes = write_zip(&incident)?;
+    tokio::fs::write(&output, &bytes)
+        .await
+        .with_context(|| format!("Falha ao gravar {}", output.display()))?;
+    println!(
+        "Pacote do incidente {} gravado em {} ({} KiB): {} métrica(s), {} decisão(ões), {} traceroute(s), {} reinício(s) de scheduler",
+        incident.event.id,
+        output.display(),
+        bytes.len().div_ceil(1024),
+        incident.metrics.len(),
+        incident.decisions.len(),
+        incident.traceroutes.len(),
+        incident.scheduler_incidents.len()
+    );
+    Ok(())
+}
+
+async fn load(storage: &Storage, id: i64, margin_minutes: u32) -> Result<Incident> {
+    let event = storage
+        .get_outage_event(id)
+        .await?
+        .with_context(|| format!("Incidente {} não encontrado em outage_events", id))?;
+    let margin = chrono::Duration::minutes(margin_minutes as i64);
+    let window = Window {
+        from: event.start_time - margin,
+        to: event.end_time.unwrap_or_else(Utc::now) + margin,
+    };
+
+    // Sem probes registrados no evento: todos os probes
+    let probes: Vec<Probe> = storage
+        .list_probes()
+        .await?
+        .into_iter()
+        .filter(|p| {
+            event
+                .affected_probes
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&p.id))
+        })
+        .collect();
+    let targets: Vec<Target> = storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .filter(|t| event.affected_targets.contains(&t.id))
+        .collect();
+    let probe_ids: Vec<i32> = probes.iter().map(|p| p.id).collect();
+    let target_ids: Vec<i32> = targets.iter().map(|t| t.id).collect();
+
+    let metrics = storage
+        .list_metrics_between(&probe_ids, &target_ids, window.from, window.to)
+        .await?;
+    let decisions = storage
+        .list_consensus_decisions_between(&probe_ids, window.from, window.to)
+        .await?;
+    let traceroutes = storage
+        .list_traceroutes_between(&probe_ids, &target_ids, window.from, window.to)
+        .await?;
+    let scheduler_incidents = storage
+        .list_scheduler_incidents_between(&probe_ids, window.from, window.to)
+        .await?;
+    Ok(Incident {
+        event,
+        window,
+        probes,
+        targets,
+        metrics,
+        decisions,
+        traceroutes,
+        scheduler_incidents,
+    })
+}
+
+impl Incident {
+    /// Troca nome e endereço dos targets por pseudônimos, inclusive no
+    /// destino dos traceroutes e no salto que é o próprio destino.
+    fn pseudonymize(&mut self, pseudonymizer: &Pseudonymizer) {
+        for target in &mut self.targets {
+            *target = pseudonymizer.target(target);
+        }
+        for traceroute in &mut self.traceroutes {
+            let destination = traceroute.destination;
+            traceroute.destination = pseudonymizer.ip(destination);
+            for hop in &mut traceroute.hops {
+                if hop.address == Some(destination) {
+                    hop.address = traceroute.destination.into();
+                }
+            }
+        }
+    }
+}
+
+fn write_zip(incident: &Incident) -> Result<Vec<u8>> {
+    let artifacts: Vec<String> = incident
+        .metrics
+        .iter()
+        .filter_map(|m| {
+            let details = m.details.as_ref()?;
+            Some(
+                json!({
+                    "metric_id": m.id,
+                    "timestamp": m.timestamp,
+                    "probe_id": m.probe_id,
+                    "target_id": m.target_id,
+                    "metric_type": m.metric_type.as_str(),
+                    "status": m.status.as_str(),
+                    "details": details,
+                })
+                .to_string(),
+            )
+        })
+        .collect();
+    let manifest = Manifest {
+        generated_at: Utc::now(),
+        event: &incident.event,
+        window: &incident.window,
+        probes: &incident.probes,
+        targets: &incident.targets,
+        counts: json!({
+            "metrics": incident.metrics.len(),
+            "artifacts": artifacts.len(),
+            "decisions": incident.decisions.len(),
+            "traceroutes": incident.traceroutes.len(),
+            "scheduler_incidents": incident.scheduler_incidents.len(),
+        }),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let now = manifest.generated_at;
+    let mut options = SimpleFileOptions::default();
+    if let Ok(modified) = zip::DateTime::from_date_and_time(
+        now.year() as u16,
+        now.month() as u8,
+        now.day() as u8,
+        now.hour() as u8,
+        now.minute() as u8,
+        now.second() as u8,
+    ) {
+        options = options.last_modified_time(modified);
+    }
+    let prefix = format!("incident-{}", incident.event.id);
+    let mut add = |name: &str, content: &[u8]| -> Result<()> {
+        zip.start_file(format!("{}/{}", prefix, name), options)?;
+        zip.write_all(content)?;
+        Ok(())
+    };
+    add("incident.json", &serde_json::to_vec_pretty(&manifest)?)?;
+    add("timeline.md", timeline(incident).as_bytes())?;
+    add("metrics.csv", metrics_csv(incident).as_bytes())?;
+    add("artifacts.jsonl", artifacts.join("\n").as_bytes())?;
+    add(
+        "decisions.json",
+        &serde_json::to_vec_pretty(&incident.decisions)?,
+    )?;
+    add(
+        "traceroutes.json",
+        &serde_json::to_vec_pretty(&incident.traceroutes)?,
+    )?;
+    add(
+        "scheduler_incidents.json",
+        &serde_json::to_vec_pretty(&incident.scheduler_incidents)?,
+    )?;
+    Ok(zip.finish()?.into_inner())
+}
+
+fn metrics_csv(incident: &Incident) -> String {
+    let probes = probe_names(&incident.probes);
+    let targets = target_names(&incident.targets);
+    let mut out = String::from(
+        "id,cycle_id,timestamp,probe,target,metric_type,status,response_time_ms,packet_loss_percent,error_kind,error_message\n",
+    );
+    for m in &incident.metrics {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            m.id,
+            m.cycle_id,
+            m.timestamp.to_rfc3339(),
+            csv_field(name_of(&probes, m.probe_id)),
+            csv_field(name_of(&targets, m.target_id)),
+            m.metric_type.as_str(),
+            m.status.as_str(),
+            m.response_time_ms.map_or(String::new(), |v| v.to_string()),
+            m.packet_loss_percent
+                .map_or(String::new(), |v| v.to_string()),
+            m.error.map_or("", |e| e.as_str()),
+            csv_field(m.error_message().unwrap_or(""))
+        );
+    }
+    out
+}
+
+/// Linha do tempo em Markdown, em ordem cronológica.
+fn timeline(incident: &Incident) -> String {
+    let probes = probe_names(&incident.probes);
+    let targets = target_names(&incident.targets);
+    let event = &incident.event;
+    let mut entries: Vec<(DateTime<Utc>, String)> = Vec::new();
+
+    entries.push((
+        event.start_time,
+        format!(
+            "**{} {} aberto** ({})",
+            event.event_type.as_str(),
+            event.id,
+            event.reason.as_deref().unwrap_or("sem motivo registrado")
+        ),
+    ));
+    if let Some(end) = event.end_time {
+        entries.push((
+            end,
+            format!(
+                "**{} {} encerrado** após {}s",
+                event.event_type.as_str(),
+                event.id,
+                event.duration_seconds.unwrap_or_default()
+            ),
+        ));
+    }
+
+    for annotation in event.annotations() {
+        entries.push((
+            annotation.created_at,
+            format!(
+                "📝 anotação de {}: {}",
+                annotation.author.as_deref().unwrap_or("operador sem nome"),
+                annotation.text
+            ),
+        ));
+    }
+
+    // Mudanças de status por probe, target e tipo de medição
+    let mut last_status: HashMap<(i32, i32, &str), &str> = HashMap::new();
+    for m in &incident.metrics {
+        let key = (m.probe_id, m.target_id, m.metric_type.as_str());
+        let status = m.status.as_str();
+        let previous = last_status.insert(key, status);
+        if previous == Some(status) {
+            continue;
+        }
+        let change = match previous {
+            Some(previous) => format!("{} → {}", previous, status),
+            None => format!("{} (primeira medição da janela)", status),
+        };
+        let error = m
+            .error_message()
+            .map_or(String::new(), |e| format!(": {}", e));
+        entries.push((
+            m.timestamp,
+            format!(
+                "[{}] {} {}: {}{}",
+                name_of(&probes, m.probe_id),
+                name_of(&targets, m.target_id),
+                m.metric_type.as_str(),
+                change,
+                error
+            ),
+        ));
+    }
+
+    // Decisões do consenso: só quando o resultado ou o motivo muda
+    let mut last_decision: HashMap<i32, (DecisionOutcome, &str)> = HashMap::new();
+    for d in &incident.decisions {
+        let current = (d.outcome, d.reason.as_str());
+        if last_decision.insert(d.probe_id, current) == Some(current) {
+            continue;
+        }
+        entries.push((
+            d.timestamp,
+            format!(
+                "[{}] consenso: {} ({}; {} em falha, {} contando, consenso = {})",
+                name_of(&probes, d.probe_id),
+                d.outcome.as_str(),
+                d.reason,
+                d.failed_targets.len(),
+                d.counted_targets.len(),
+                d.consensus
+            ),
+        ));
+    }
+
+    for i in &incident.scheduler_incidents {
+        entries.push((
+            i.timestamp,
+            format!(
+                "[{}] scheduler reiniciado ({}{})",
+                name_of(&probes, i.probe_id),
+                i.kind.as_str(),
+                i.detail
+                    .as_deref()
+                    .map_or(String::new(), |d| format!(": {}", d))
+            ),
+        ));
+    }
+
+    // Traceroutes: o primeiro de cada par e as mudanças de alcance
+    let mut last_reached: HashMap<(i32, i32), bool> = HashMap::new();
+    for t in &incident.traceroutes {
+        if last_reached.insert((t.probe_id, t.target_id), t.reached) == Some(t.reached) {
+            continue;
+        }
+        let last_hop = t
+            .hops
+            .iter()
+            .rev()
+            .find_map(|h| h.address.map(|a| (h.ttl, a)));
+        let path = match (t.reached, last_hop) {
+            (true, _) => format!("destino alcançado em {} salto(s)", t.hops.len()),
+            (false, Some((ttl, address))) => {
+                format!("destino não alcançado; último salto {} ({})", ttl, address)
+            }
+            (false, None) => "destino não alcançado; nenhum salto respondeu".to_string(),
+        };
+        entries.push((
+            t.timestamp,
+            format!(
+                "[{}] traceroute até {} ({}): {}",
+                name_of(&probes, t.probe_id),
+                name_of(&targets, t.target_id),
+                t.destination,
+                path
+            ),
+        ));
+    }
+
+    entries.sort_by_key(|(timestamp, _)| *timestamp);
+    let mut out = format!(
+        "# Incidente {} — {}\n\nJanela: {} a {}\n\n",
+        event.id,
+        event.event_type.as_str(),
+        incident.window.from.to_rfc3339(),
+        incident.window.to.to_rfc3339()
+    );
+    for (timestamp, text) in entries {
+        let _ = writeln!(
+            out,
+            "- `{}` {}",
+            timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            text
+        );
+    }
+    out
+}
+
+fn probe_names(probes: &[Probe]) -> HashMap<i32, &str> {
+    probes.iter().map(|p| (p.id, p.location.as_str())).collect()
+}
+
+fn target_names(targets: &[Target]) -> HashMap<i32, &str> {
+    targets.iter().map(|t| (t.id, t.name.as_str())).collect()
+}
+
+fn name_of<'a>(names: &HashMap<i32, &'a str>, id: i32) -> &'a str {
+    names.get(&id).copied().unwrap_or("?")
+}
+
+/// Campo CSV, entre aspas quando necessário.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
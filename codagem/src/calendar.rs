@@ -0,0 +1,108 @@
+//! calendar.rs — Fuso dos relatórios e fronteiras de dia e mês
+//!
+//! Os resumos periódicos, o `report summary` e os horários silenciosos seguem
+//! o calendário local de `report_timezone` (um nome IANA, como
+//! `America/Sao_Paulo`, ou um deslocamento fixo, como `-03:00`): "99,9% em
+//! março" é do primeiro ao último dia de março no fuso do cliente, não em UTC.
+//! Com horário de verão, um dia local pode ter 23 ou 25 horas; as fronteiras
+//! são sempre a meia-noite (ou a hora configurada) local.
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeDelta, TimeZone,
+    Utc,
+};
+use chrono_tz::Tz;
+use std::fmt;
+
+/// Fuso usado nas fronteiras dos relatórios.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Zone::Named(Tz::UTC)
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Fixed(offset) => write!(f, "{}", offset),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// Primeiro instante de `naive` no fuso: num horário pulado pelo horário de
+/// verão, o primeiro instante válido depois dele; num horário repetido, a
+/// primeira ocorrência.
+fn resolve<T: TimeZone>(tz: &T, naive: NaiveDateTime) -> DateTime<Utc> {
+    let mut naive = naive;
+    loop {
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(instant) | LocalResult::Ambiguous(instant, _) => {
+                return instant.with_timezone(&Utc);
+            }
+            LocalResult::None => naive += TimeDelta::minutes(15),
+        }
+    }
+}
+
+impl Zone {
+    /// Nome IANA (`America/Sao_Paulo`) ou deslocamento fixo (`-03:00`).
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Ok(offset) = value.parse::<FixedOffset>() {
+            return Some(Zone::Fixed(offset));
+        }
+        value.parse::<Tz>().ok().map(Zone::Named)
+    }
+
+    /// `value` se presente e válido; senão, `default`.
+    pub fn or(value: Option<&str>, default: Zone) -> Self {
+        value.and_then(Zone::parse).unwrap_or(default)
+    }
+
+    /// Data e hora locais de `instant`.
+    pub fn local(self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Fixed(offset) => instant.with_timezone(&offset).naive_local(),
+            Zone::Named(tz) => instant.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    /// Instante da data e hora locais `naive`.
+    pub fn to_utc(self, naive: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Zone::Fixed(offset) => resolve(&offset, naive),
+            Zone::Named(tz) => resolve(&tz, naive),
+        }
+    }
+
+    /// Início (meia-noite local) de `date`.
+    pub fn start_of(self, date: NaiveDate) -> DateTime<Utc> {
+        self.to_utc(date.and_time(chrono::NaiveTime::MIN))
+    }
+
+    /// Janela `[from, to)` do dia local `date`.
+    pub fn day_bounds(self, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        let next = date.succ_opt().expect("data fora do intervalo");
+        (self.start_of(date), self.start_of(next))
+    }
+
+    /// Janela `[from, to)` do mês local; `None` com mês inválido.
+    pub fn month_bounds(self, year: i32, month: u32) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let next = first.checked_add_months(chrono::Months::new(1))?;
+        Some((self.start_of(first), self.start_of(next)))
+    }
+}
+
+/// Mês de `value` no formato `AAAA-MM`.
+pub fn parse_month(value: &str) -> Option<(i32, u32)> {
+    let (year, month) = value.split_once('-')?;
+    let date = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+    Some((date.year(), date.month()))
+}
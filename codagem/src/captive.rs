@@ -0,0 +1,90 @@
+//! captive.rs — Detecção de captive portal
+//!
+//! Num link de convidados ou de backup, o probe pode estar atrás de um
+//! captive portal: o DNS responde (às vezes com o IP do próprio portal), mas
+//! todo HTTP é interceptado com um redirect para a página de login e os
+//! demais tráfegos são bloqueados. Para o consenso isso é um outage comum.
+//!
+//! Com `[captive_portal]`, a abertura de um outage dispara um `GET` HTTP
+//! simples (sem seguir redirects) a uma URL que, com internet de verdade,
+//! responde `expected_status` (padrão: o `204` do `generate_204`). Se o nome
+//! resolve e a resposta é um redirect ou outro status (um `200` com a página
+//! do portal, `511 Network Authentication Required`), o `reason` do outage
+//! vira `captive_portal` e a resposta vai para `details.captive_portal`.
+//! Sem DNS ou sem conexão não há como concluir nada, e o outage segue com o
+//! `reason` que tinha.
+
+use crate::config::CaptivePortalConfig;
+use crate::http_trace::{Hop, Tracer};
+use crate::types::OutageEvent;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::debug;
+
+/// `reason` gravado no outage.
+pub const REASON: &str = "captive_portal";
+
+/// Resposta interceptada, gravada em `details.captive_portal`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptivePortal {
+    pub url: String,
+    pub expected_status: u16,
+    pub status: u16,
+    /// `Location` do redirect (a página de login do portal).
+    pub location: Option<String>,
+    pub dns_ms: Option<f64>,
+}
+
+impl CaptivePortal {
+    /// Aplica a detecção ao outage: `reason` e `details.captive_portal`.
+    pub fn annotate(&self, event: &mut OutageEvent) {
+        event.reason = Some(REASON.to_string());
+        let details = event.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("captive_portal".into(), json!(self));
+        }
+    }
+}
+
+/// Classifica a resposta: `Some` quando o HTTP foi interceptado.
+fn classify(config: &CaptivePortalConfig, hop: Hop) -> Option<CaptivePortal> {
+    let status = hop.status?;
+    if status == config.expected_status {
+        return None;
+    }
+    Some(CaptivePortal {
+        url: hop.url,
+        expected_status: config.expected_status,
+        status,
+        location: hop.location.filter(|_| (300..400).contains(&status)),
+        dns_ms: hop.dns_ms,
+    })
+}
+
+/// Faz a requisição de teste; `None` se o HTTP não foi interceptado ou se a
+/// requisição não chegou a ter resposta.
+pub async fn detect(config: &CaptivePortalConfig) -> Option<CaptivePortal> {
+    let url = Url::parse(&config.url).ok()?;
+    let tracer = match Tracer::new(0, &[]) {
+        Ok(tracer) => tracer,
+        Err(e) => {
+            debug!("[CAPTIVE] Cliente HTTP indisponível: {:#}", e);
+            return None;
+        }
+    };
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let trace = match tokio::time::timeout(timeout, tracer.trace(url)).await {
+        Ok(trace) => trace,
+        Err(_) => {
+            debug!("[CAPTIVE] Sem resposta de {} em {:?}", config.url, timeout);
+            return None;
+        }
+    };
+    let hop = trace.hops.into_iter().next()?;
+    if let Some(error) = &hop.error {
+        debug!("[CAPTIVE] Requisição a {} falhou: {}", config.url, error);
+    }
+    classify(config, hop)
+}
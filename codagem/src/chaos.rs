@@ -0,0 +1,194 @@
+//! chaos.rs — Injeção de falhas sintéticas nos checks (`[chaos]`)
+//!
+//! Numa rede saudável o consenso, a gravação de outages e as notificações
+//! quase nunca são exercitados. Com `[chaos]`, cada check registrado é
+//! embrulhado por um [`ChaosCheck`] que, antes de medir, pode transformar o
+//! resultado: um `Timeout` com a probabilidade configurada (sem executar o
+//! check), um RTT inflado, ou `Down` durante os períodos de `[[chaos.down]]`.
+//! O restante do pipeline não sabe da injeção; as métricas alteradas levam
+//! `details.chaos` com o tipo da falha, para serem separadas depois.
+//!
+//! Nunca habilite em produção: os outages gerados são gravados e notificados
+//! como reais.
+
+use crate::check::{Attempt, Check, CheckKind, build_metric};
+use crate::config::{ChaosConfig, ChaosDownConfig};
+use crate::types::{CheckError, ConnectivityMetric, MeasurementBackend, Probe, Target};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Falha injetada num check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    Down,
+    Timeout,
+    Latency,
+}
+
+impl Fault {
+    fn as_str(self) -> &'static str {
+        match self {
+            Fault::Down => "down",
+            Fault::Timeout => "timeout",
+            Fault::Latency => "latency",
+        }
+    }
+}
+
+fn matches(target: &Target, entries: &[String]) -> bool {
+    entries.is_empty()
+        || entries
+            .iter()
+            .any(|entry| target.name == *entry || target.address.to_string() == *entry)
+}
+
+/// Período de `Down` forçado ativo em `now`?
+fn down_active(down: &ChaosDownConfig, now: DateTime<Utc>) -> bool {
+    match (down.from, down.to, down.every_secs, down.duration_secs) {
+        (Some(from), Some(to), _, _) => from <= now && now < to,
+        (_, _, Some(every), Some(duration)) if every > 0 => {
+            let since_offset = now.timestamp() - down.offset_secs as i64;
+            (since_offset.rem_euclid(every as i64) as u64) < duration
+        }
+        _ => false,
+    }
+}
+
+/// Check embrulhado pela injeção de falhas.
+pub struct ChaosCheck {
+    inner: Arc<dyn Check>,
+    kind: CheckKind,
+    attempts: usize,
+    config: Arc<ChaosConfig>,
+}
+
+impl ChaosCheck {
+    pub fn new(
+        inner: Arc<dyn Check>,
+        kind: CheckKind,
+        attempts: usize,
+        config: Arc<ChaosConfig>,
+    ) -> Self {
+        Self {
+            inner,
+            kind,
+            attempts: attempts.max(1),
+            config,
+        }
+    }
+
+    fn fault_for(&self, target: &Target, now: DateTime<Utc>) -> Option<Fault> {
+        if self
+            .config
+            .down
+            .iter()
+            .any(|down| matches(target, &down.targets) && down_active(down, now))
+        {
+            return Some(Fault::Down);
+        }
+        if !matches(target, &self.config.targets) {
+            return None;
+        }
+        let mut rng = rand::rng();
+        if rng.random_bool(self.config.timeout_probability) {
+            Some(Fault::Timeout)
+        } else if rng.random_bool(self.config.latency_probability) {
+            Some(Fault::Latency)
+        } else {
+            None
+        }
+    }
+
+    fn inflate(&self, ms: f64) -> f64 {
+        ms * self.config.latency_factor + self.config.latency_add_ms
+    }
+}
+
+fn mark(metric: &mut ConnectivityMetric, fault: Fault) {
+    let details = metric.details.get_or_insert_with(|| json!({}));
+    if let Some(map) = details.as_object_mut() {
+        map.insert("chaos".into(), fault.as_str().into());
+    }
+}
+
+#[async_trait]
+impl Check for ChaosCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let Some(fault) = self.fault_for(target, Utc::now()) else {
+            return self.inner.run(target, probe).await;
+        };
+        let mut metric = match fault {
+            Fault::Down => build_metric(
+                target,
+                probe,
+                self.kind,
+                &vec![
+                    Attempt::Failure(
+                        CheckError::Unreachable,
+                        "falha injetada (chaos)".to_string()
+                    );
+                    self.attempts
+                ],
+                None,
+            ),
+            Fault::Timeout => build_metric(
+                target,
+                probe,
+                self.kind,
+                &vec![Attempt::Timeout; self.attempts],
+                None,
+            ),
+            Fault::Latency => {
+                let mut metric = self.inner.run(target, probe).await;
+                metric.response_time_ms = metric.response_time_ms.map(|ms| self.inflate(ms));
+                for attempt in &mut metric.attempts {
+                    if let Attempt::Success(ms) = attempt {
+                        *ms = self.inflate(*ms);
+                    }
+                }
+                metric
+            }
+        };
+        mark(&mut metric, fault);
+        metric
+    }
+
+    fn heavy(&self) -> bool {
+        self.inner.heavy()
+    }
+
+    fn backend(&self) -> MeasurementBackend {
+        self.inner.backend()
+    }
+
+    fn packets(&self) -> usize {
+        self.inner.packets()
+    }
+}
+
+/// Avisa que o modo chaos está ligado e as entradas de targets que não
+/// casam com nenhum target monitorado.
+pub fn warn_enabled(config: &ChaosConfig, targets: &[Target]) {
+    warn!(
+        "🐒 [CHAOS] Injeção de falhas habilitada (timeout {:.0}%, latência {:.0}%, {} período(s) de down): outages e notificações gerados são sintéticos",
+        config.timeout_probability * 100.0,
+        config.latency_probability * 100.0,
+        config.down.len()
+    );
+    let entries = config
+        .targets
+        .iter()
+        .chain(config.down.iter().flat_map(|down| &down.targets));
+    for entry in entries {
+        if !targets
+            .iter()
+            .any(|t| t.name == *entry || t.address.to_string() == *entry)
+        {
+            warn!("⚠️ [CHAOS] Target `{}` não encontrado", entry);
+        }
+    }
+}
@@ -0,0 +1,1052 @@
+//! check.rs — Framework de checks plugáveis
+//!
+//! Cada tipo de verificação implementa [`Check`] e é registrado no
+//! [`CheckRegistry`] sob um nome (`ping`, `tcp`, `http`, `dns`,
+//! `tls`, `udp`, `sip`). O check de
+//! cada target é escolhido pelo prefixo do seu campo `type` (ex: `dns_ipv4` →
+//! `dns`), de modo que novos tipos de check não exigem mudanças no scheduler.
+//! Targets com tipo desconhecido caem no check `ping`.
+//!
+//! Com `checks.combined`, os checks da lista valem como sinais equivalentes
+//! de alcançabilidade: um target cujo check está nela roda também os demais,
+//! em paralelo, e a métrica do check próprio recebe o veredito combinado (ex:
+//! ICMP filtrado mas TCP respondendo = `Up`, com nota em `details.verdict`).
+//! As medições dos outros sinais são gravadas como `diagnostic`.
+//!
+//! Cada check usa a origem de `checks.sources.<nome>` ou de `checks.source`
+//! (ver `source.rs`), refletida na proveniência das métricas.
+
+use crate::chaos::ChaosCheck;
+use crate::config::{ChaosConfig, Config, DnsQueryConfig, DnsRecordType, SourceConfig};
+use crate::http_trace::{Trace, Tracer};
+use crate::ping::PingCheck;
+use crate::provenance;
+use crate::ratelimit::RateLimiter;
+use crate::sip_check::SipCheck;
+use crate::source;
+use crate::tls_check::TlsCheck;
+use crate::types::{
+    CheckError, ConnectivityMetric, MeasurementBackend, MetricStatus, MetricType, Probe, Target,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
+use trust_dns_resolver::TokioAsyncResolver;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::rr::RecordType;
+
+/// Check executado contra um target a cada ciclo.
+#[async_trait]
+pub trait Check: Send + Sync {
+    /// Executa o check. O `cycle_id` e, se ausente, a proveniência da métrica
+    /// são preenchidos pelo registry.
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric;
+
+    /// Checks pesados (teste de banda, MTR, captura) são pausados enquanto o
+    /// host da probe está sob carga (`host.heavy_max_load_per_cpu`).
+    fn heavy(&self) -> bool {
+        false
+    }
+
+    /// Implementação da medição, registrada na proveniência da métrica.
+    fn backend(&self) -> MeasurementBackend {
+        MeasurementBackend::Native
+    }
+
+    /// Pacotes que uma execução consome do orçamento de `[rate_limit]`.
+    fn packets(&self) -> usize {
+        1
+    }
+}
+
+/// Famílias de check com `MetricType` próprio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    Ping,
+    Tcp,
+    Http,
+    Dns,
+    Tls,
+    Udp,
+    Sip,
+}
+
+impl CheckKind {
+    /// Família de um check registrado pelo nome (`ping` para os demais).
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "tcp" => CheckKind::Tcp,
+            "http" => CheckKind::Http,
+            "dns" => CheckKind::Dns,
+            "tls" => CheckKind::Tls,
+            "udp" => CheckKind::Udp,
+            "sip" => CheckKind::Sip,
+            _ => CheckKind::Ping,
+        }
+    }
+
+    /// `MetricType` para a família de endereço do target.
+    pub fn metric_type(self, address: IpAddr) -> MetricType {
+        match (self, address) {
+            (CheckKind::Ping, IpAddr::V4(_)) => MetricType::PingIpv4,
+            (CheckKind::Ping, IpAddr::V6(_)) => MetricType::PingIpv6,
+            (CheckKind::Tcp, IpAddr::V4(_)) => MetricType::TcpIpv4,
+            (CheckKind::Tcp, IpAddr::V6(_)) => MetricType::TcpIpv6,
+            (CheckKind::Http, IpAddr::V4(_)) => MetricType::HttpIpv4,
+            (CheckKind::Http, IpAddr::V6(_)) => MetricType::HttpIpv6,
+            (CheckKind::Dns, IpAddr::V4(_)) => MetricType::DnsIpv4,
+            (CheckKind::Dns, IpAddr::V6(_)) => MetricType::DnsIpv6,
+            (CheckKind::Tls, IpAddr::V4(_)) => MetricType::TlsIpv4,
+            (CheckKind::Tls, IpAddr::V6(_)) => MetricType::TlsIpv6,
+            (CheckKind::Udp, IpAddr::V4(_)) => MetricType::UdpIpv4,
+            (CheckKind::Udp, IpAddr::V6(_)) => MetricType::UdpIpv6,
+            (CheckKind::Sip, IpAddr::V4(_)) => MetricType::SipIpv4,
+            (CheckKind::Sip, IpAddr::V6(_)) => MetricType::SipIpv6,
+        }
+    }
+}
+
+/// Resultado de uma tentativa individual de um check.
+#[derive(Debug, Clone)]
+pub enum Attempt {
+    /// Sucesso com o tempo de resposta em ms.
+    Success(f64),
+    Timeout,
+    Failure(CheckError, String),
+}
+
+impl Attempt {
+    /// Resultado gravado em `raw_samples.outcome`.
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            Attempt::Success(_) => "success",
+            Attempt::Timeout => "timeout",
+            Attempt::Failure(..) => "failure",
+        }
+    }
+}
+
+/// Tentativa individual de um check, gravada em `raw_samples` com
+/// `raw_samples = true`. As métricas guardam só o agregado do ciclo; as
+/// amostras mostram a variância dentro do ciclo e as retransmissões.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawSample {
+    pub cycle_id: i64,
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub metric_type: MetricType,
+    pub timestamp: DateTime<Utc>,
+    /// Ordem da tentativa no ciclo, a partir de 1.
+    pub attempt_no: i16,
+    pub rtt_ms: Option<f64>,
+    /// `success`, `timeout` ou `failure`.
+    pub outcome: String,
+}
+
+impl RawSample {
+    /// Amostras das tentativas de uma métrica.
+    pub fn of(metric: &ConnectivityMetric) -> impl Iterator<Item = RawSample> + '_ {
+        metric
+            .attempts
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| RawSample {
+                cycle_id: metric.cycle_id,
+                probe_id: metric.probe_id,
+                target_id: metric.target_id,
+                metric_type: metric.metric_type.clone(),
+                timestamp: metric.timestamp,
+                attempt_no: (i + 1) as i16,
+                rtt_ms: match attempt {
+                    Attempt::Success(ms) => Some(*ms),
+                    _ => None,
+                },
+                outcome: attempt.outcome().to_string(),
+            })
+    }
+}
+
+/// Consolida as tentativas numa métrica, com a mesma semântica do ping:
+/// todas em timeout → `Timeout`; todas com sucesso → `Up`; algumas → `Degraded`;
+/// nenhuma → `Down`. O tempo de resposta é a média dos sucessos.
+pub fn build_metric(
+    target: &Target,
+    probe: &Probe,
+    kind: CheckKind,
+    attempts: &[Attempt],
+    details: Option<serde_json::Value>,
+) -> ConnectivityMetric {
+    let total = attempts.len().max(1);
+    let times: Vec<f64> = attempts
+        .iter()
+        .filter_map(|a| match a {
+            Attempt::Success(ms) => Some(*ms),
+            _ => None,
+        })
+        .collect();
+    let timeouts = attempts
+        .iter()
+        .filter(|a| matches!(a, Attempt::Timeout))
+        .count();
+    let last_error = attempts.iter().rev().find_map(|a| match a {
+        Attempt::Success(_) => None,
+        Attempt::Timeout => Some((CheckError::Timeout, "timeout".to_string())),
+        Attempt::Failure(kind, e) => Some((*kind, e.clone())),
+    });
+
+    let success = times.len();
+    let status = if timeouts == total {
+        MetricStatus::Timeout
+    } else if success == total {
+        MetricStatus::Up
+    } else if success > 0 {
+        MetricStatus::Degraded
+    } else {
+        MetricStatus::Down
+    };
+
+    let mut metric = ConnectivityMetric {
+        id: 0,       // será preenchido pelo banco
+        cycle_id: 0, // preenchido pelo registry
+        probe_id: probe.id,
+        target_id: target.id,
+        timestamp: Utc::now(),
+        metric_type: kind.metric_type(target.address),
+        status,
+        response_time_ms: (success > 0).then(|| times.iter().sum::<f64>() / success as f64),
+        packet_loss_percent: Some(100 - ((success * 100) / total) as i16),
+        error: None,
+        details,
+        provenance: None, // preenchida pelo registry
+        attempts: attempts.to_vec(),
+    };
+    if let Some((kind, message)) = last_error {
+        metric.set_error(kind, message);
+    }
+    metric
+}
+
+/// Check TCP: tempo do `connect` numa porta fixa.
+pub struct TcpCheck {
+    port: u16,
+    source: SourceConfig,
+    attempts: usize,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+}
+
+#[async_trait]
+impl Check for TcpCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let addr = SocketAddr::new(target.address, self.port);
+        let mut attempts = Vec::with_capacity(self.attempts);
+        for _ in 0..self.attempts {
+            self.limiter.acquire(target).await;
+            let started = Instant::now();
+            let connect = source::tcp_connect(&self.source, addr);
+            let attempt = match tokio::time::timeout(self.timeout, connect).await {
+                Ok(Ok(_)) => Attempt::Success(started.elapsed().as_secs_f64() * 1000.0),
+                Ok(Err(e)) => Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                Err(_) => Attempt::Timeout,
+            };
+            attempts.push(attempt);
+        }
+        let details = serde_json::json!({ "port": self.port });
+        build_metric(target, probe, CheckKind::Tcp, &attempts, Some(details))
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts
+    }
+}
+
+/// Check UDP: envia um datagrama numa porta fixa e espera a resposta do
+/// serviço ou um ICMP port unreachable, que também prova que o host responde
+/// (o socket conectado recebe o ICMP como `ECONNREFUSED`). Outros ICMPs de
+/// erro contam como falha; sem nada até o timeout, a tentativa é `Timeout`.
+/// Serviços que descartam datagramas inesperados em silêncio (WireGuard, por
+/// exemplo) precisam de um `checks.udp_payload` que eles respondam.
+pub struct UdpCheck {
+    port: u16,
+    payload: Vec<u8>,
+    source: SourceConfig,
+    attempts: usize,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+}
+
+/// Como o target respondeu ao datagrama.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum UdpReply {
+    Response,
+    PortUnreachable,
+}
+
+impl UdpCheck {
+    async fn attempt(&self, addr: SocketAddr) -> (Attempt, Option<UdpReply>) {
+        let socket = match source::udp_connect(&self.source, addr).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                return (
+                    Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                    None,
+                );
+            }
+        };
+        let started = Instant::now();
+        let exchange = async {
+            socket.send(&self.payload).await?;
+            recv_or_error(&socket, &mut [0u8; 2048]).await
+        };
+        match tokio::time::timeout(self.timeout, exchange).await {
+            Ok(Ok(_)) => (
+                Attempt::Success(started.elapsed().as_secs_f64() * 1000.0),
+                Some(UdpReply::Response),
+            ),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => (
+                Attempt::Success(started.elapsed().as_secs_f64() * 1000.0),
+                Some(UdpReply::PortUnreachable),
+            ),
+            Ok(Err(e)) => (
+                Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                None,
+            ),
+            Err(_) => (Attempt::Timeout, None),
+        }
+    }
+}
+
+/// Socket UDP efêmero conectado a `addr` (o kernel só entrega ICMPs de erro
+/// a sockets conectados).
+pub async fn udp_connect(addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    source::udp_connect(&SourceConfig::default(), addr).await
+}
+
+/// Espera um datagrama ou o erro pendente do socket (o ICMP recebido). Um
+/// `recv` puro não acorda com o erro: a prontidão de erro só é entregue a quem
+/// pede `Interest::ERROR`.
+pub async fn recv_or_error(socket: &UdpSocket, buf: &mut [u8]) -> std::io::Result<usize> {
+    loop {
+        let ready = socket
+            .ready(tokio::io::Interest::READABLE | tokio::io::Interest::ERROR)
+            .await?;
+        if ready.is_error()
+            && let Some(e) = socket.take_error()?
+        {
+            return Err(e);
+        }
+        match socket.try_recv(buf) {
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+#[async_trait]
+impl Check for UdpCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let addr = SocketAddr::new(target.address, self.port);
+        let mut attempts = Vec::with_capacity(self.attempts);
+        let mut reply = None;
+        for _ in 0..self.attempts {
+            self.limiter.acquire(target).await;
+            let (attempt, kind) = self.attempt(addr).await;
+            reply = kind.or(reply);
+            attempts.push(attempt);
+        }
+        let details = serde_json::json!({ "port": self.port, "reply": reply });
+        build_metric(target, probe, CheckKind::Udp, &attempts, Some(details))
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts
+    }
+}
+
+/// Check HTTP: `GET` seguindo redirects (cadeia e fases em `details.chain`);
+/// respostas 5xx e cadeias acima do limite contam como falha, e redirects
+/// para hosts inesperados deixam a medição `Degraded`.
+pub struct HttpCheck {
+    tracer: Tracer,
+    port: u16,
+    path: String,
+    attempts: usize,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+}
+
+#[async_trait]
+impl Check for HttpCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let url = format!(
+            "http://{}{}",
+            SocketAddr::new(target.address, self.port),
+            self.path
+        );
+        let mut attempts = Vec::with_capacity(self.attempts);
+        let mut last_trace: Option<Trace> = None;
+        for _ in 0..self.attempts {
+            let Ok(parsed) = reqwest::Url::parse(&url) else {
+                attempts.push(Attempt::Failure(
+                    CheckError::Other,
+                    format!("URL inválida: {}", url),
+                ));
+                continue;
+            };
+            self.limiter.acquire(target).await;
+            let started = Instant::now();
+            let attempt = match tokio::time::timeout(self.timeout, self.tracer.trace(parsed)).await
+            {
+                Ok(trace) => {
+                    let attempt = match (trace.final_status(), trace.error()) {
+                        _ if trace.too_many_redirects => Attempt::Failure(
+                            CheckError::HttpError,
+                            format!("mais de {} redirects", trace.hops.len() - 1),
+                        ),
+                        (Some(status), _) if status >= 500 => {
+                            Attempt::Failure(CheckError::HttpError, format!("HTTP {}", status))
+                        }
+                        (Some(_), _) => Attempt::Success(started.elapsed().as_secs_f64() * 1000.0),
+                        (None, error) => {
+                            let message = error.unwrap_or("sem resposta");
+                            Attempt::Failure(CheckError::classify(message), message.to_string())
+                        }
+                    };
+                    last_trace = Some(trace);
+                    attempt
+                }
+                Err(_) => Attempt::Timeout,
+            };
+            attempts.push(attempt);
+        }
+        let unexpected = last_trace
+            .as_ref()
+            .and_then(|t| t.unexpected_redirect.clone());
+        let details = serde_json::json!({
+            "url": url,
+            "http_status": last_trace.as_ref().and_then(Trace::final_status),
+            "chain": last_trace.as_ref().map(|t| &t.hops),
+            "unexpected_redirect": unexpected,
+        });
+        let mut metric = build_metric(target, probe, CheckKind::Http, &attempts, Some(details));
+        if unexpected.is_some() && metric.status == MetricStatus::Up {
+            metric.status = MetricStatus::Degraded;
+        }
+        metric
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts
+    }
+}
+
+/// Check DNS: consulta `A` direta ao target (porta 53/UDP). Qualquer resposta
+/// do servidor, inclusive NXDOMAIN, conta como sucesso. As consultas de
+/// `checks.dns_queries` rodam uma vez cada, depois das tentativas, e vão para
+/// `details.queries`: a latência por nome, com e sem cache, separa problemas
+/// de cache do resolver de lentidão a montante.
+pub struct DnsCheck {
+    query: String,
+    queries: Vec<DnsQueryConfig>,
+    source: SourceConfig,
+    attempts: usize,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+}
+
+/// Resultado de uma consulta de `checks.dns_queries`.
+#[derive(Debug, Serialize)]
+struct DnsQueryResult<'a> {
+    /// Nome configurado (com `{random}`, se for o caso).
+    name: &'a str,
+    record: &'static str,
+    /// `false` para nomes com `{random}`, que nunca estão no cache.
+    cached: bool,
+    rtt_ms: Option<f64>,
+    error: Option<String>,
+}
+
+impl DnsCheck {
+    async fn lookup(
+        &self,
+        resolver: &TokioAsyncResolver,
+        name: &str,
+        record: RecordType,
+    ) -> Attempt {
+        let started = Instant::now();
+        match resolver.lookup(name, record).await {
+            Ok(_) => Attempt::Success(started.elapsed().as_secs_f64() * 1000.0),
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { .. } => {
+                    Attempt::Success(started.elapsed().as_secs_f64() * 1000.0)
+                }
+                ResolveErrorKind::Timeout => Attempt::Timeout,
+                ResolveErrorKind::Io(io) => {
+                    Attempt::Failure(CheckError::from_io(io), e.to_string())
+                }
+                _ => Attempt::Failure(CheckError::DnsFailure, e.to_string()),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Check for DnsCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        // O resolver só aceita um endereço local: com origem por interface,
+        // um endereço da interface
+        let mut servers = NameServerConfigGroup::from_ips_clear(&[target.address], 53, true);
+        if !self.source.is_default() {
+            let local = source::local_address(&self.source, target.address);
+            for server in servers.iter_mut() {
+                server.bind_addr = local.map(|ip| SocketAddr::new(ip, 0));
+            }
+        }
+        let config = ResolverConfig::from_parts(None, vec![], servers);
+        let mut opts = ResolverOpts::default();
+        opts.timeout = self.timeout;
+        opts.attempts = 0;
+        opts.cache_size = 0;
+        let resolver = TokioAsyncResolver::tokio(config, opts);
+
+        let mut attempts = Vec::with_capacity(self.attempts);
+        for _ in 0..self.attempts {
+            self.limiter.acquire(target).await;
+            attempts.push(
+                self.lookup(&resolver, self.query.as_str(), RecordType::A)
+                    .await,
+            );
+        }
+        let mut details = serde_json::json!({ "query": self.query });
+
+        if !self.queries.is_empty() {
+            let mut results = Vec::with_capacity(self.queries.len());
+            for query in &self.queries {
+                self.limiter.acquire(target).await;
+                let name = query
+                    .name
+                    .replace("{random}", &format!("{:016x}", rand::random::<u64>()));
+                let record = match query.record {
+                    DnsRecordType::A => RecordType::A,
+                    DnsRecordType::Aaaa => RecordType::AAAA,
+                };
+                let (rtt_ms, error) = match self.lookup(&resolver, &name, record).await {
+                    Attempt::Success(ms) => (Some(ms), None),
+                    Attempt::Timeout => (None, Some("timeout".to_string())),
+                    Attempt::Failure(_, message) => (None, Some(message)),
+                };
+                results.push(DnsQueryResult {
+                    name: &query.name,
+                    record: query.record.as_str(),
+                    cached: !query.uncached(),
+                    rtt_ms,
+                    error,
+                });
+            }
+            details["queries"] = serde_json::json!(results);
+        }
+        build_metric(target, probe, CheckKind::Dns, &attempts, Some(details))
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts + self.queries.len()
+    }
+}
+
+/// Métricas de um ciclo: uma por target (com o veredito combinado, se for o
+/// caso) e as medições dos sinais adicionais de `checks.combined`.
+#[derive(Debug, Default)]
+pub struct CycleMetrics {
+    pub metrics: Vec<ConnectivityMetric>,
+    pub signals: Vec<ConnectivityMetric>,
+    /// Targets cujo check foi abortado pelo orçamento do ciclo.
+    pub aborted: Vec<Target>,
+}
+
+/// Registro de checks por nome.
+pub struct CheckRegistry {
+    checks: HashMap<String, Arc<dyn Check>>,
+    fallback: Arc<dyn Check>,
+    /// Orçamento de pacotes por rede, compartilhado pelos checks embutidos.
+    limiter: Arc<RateLimiter>,
+    /// Checks combinados por target (`checks.combined`).
+    combined: Vec<String>,
+    /// Origem dos checks (`checks.source`/`checks.sources`), para a
+    /// proveniência.
+    source: SourceConfig,
+    sources: BTreeMap<String, SourceConfig>,
+}
+
+impl CheckRegistry {
+    /// Cria o registry com os checks embutidos (`ping`, `tcp`, `http`, `dns`,
+    /// `tls`, `udp`, `sip`).
+    pub fn new(config: &Config) -> Result<Self> {
+        Self::with_limiter(
+            config,
+            Arc::new(RateLimiter::new(config.rate_limit.as_ref())),
+        )
+    }
+
+    /// Como [`CheckRegistry::new`], com o limite de pacotes de outro registry
+    /// (perfis de probe com `ping_count`/`timeout_secs` próprios dividem o
+    /// mesmo orçamento).
+    pub fn with_limiter(config: &Config, limiter: Arc<RateLimiter>) -> Result<Self> {
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let attempts = config.ping_count;
+        let ping: Arc<dyn Check> = Arc::new(
+            PingCheck::new(
+                config.ping_count,
+                config.timeout_secs,
+                config.checks.source_for("ping"),
+                Arc::clone(&limiter),
+            )
+            .context("Falha ao criar client ICMP")?,
+        );
+        let tracer = Tracer::new(
+            config.checks.http_max_redirects,
+            &config.checks.http_allowed_redirect_hosts,
+        )
+        .context("Falha ao criar cliente HTTP do check http")?
+        .source(config.checks.source_for("http").clone());
+
+        let mut registry = Self {
+            checks: HashMap::new(),
+            fallback: Arc::clone(&ping),
+            limiter: Arc::clone(&limiter),
+            combined: config.checks.combined.clone(),
+            source: config.checks.source.clone(),
+            sources: config.checks.sources.clone(),
+        };
+        registry.register("ping", ping);
+        registry.register(
+            "tcp",
+            Arc::new(TcpCheck {
+                port: config.checks.tcp_port,
+                source: config.checks.source_for("tcp").clone(),
+                attempts,
+                timeout,
+                limiter: Arc::clone(&limiter),
+            }),
+        );
+        registry.register(
+            "http",
+            Arc::new(HttpCheck {
+                tracer,
+                port: config.checks.http_port,
+                path: config.checks.http_path.clone(),
+                attempts,
+                timeout,
+                limiter: Arc::clone(&limiter),
+            }),
+        );
+        registry.register(
+            "dns",
+            Arc::new(DnsCheck {
+                query: config.checks.dns_query.clone(),
+                queries: config.checks.dns_queries.clone(),
+                source: config.checks.source_for("dns").clone(),
+                attempts,
+                timeout,
+                limiter: Arc::clone(&limiter),
+            }),
+        );
+        registry.register(
+            "tls",
+            Arc::new(
+                TlsCheck::new(config, Arc::clone(&limiter))
+                    .context("Falha ao criar o check tls")?,
+            ),
+        );
+        registry.register(
+            "udp",
+            Arc::new(UdpCheck {
+                port: config.checks.udp_port,
+                payload: config.checks.udp_payload(),
+                source: config.checks.source_for("udp").clone(),
+                attempts,
+                timeout,
+                limiter: Arc::clone(&limiter),
+            }),
+        );
+        registry.register("sip", Arc::new(SipCheck::new(config, limiter)));
+        if let Some(chaos) = &config.chaos {
+            registry.inject_faults(Arc::new(chaos.clone()), attempts);
+        }
+        Ok(registry)
+    }
+
+    /// Registry só com `check`, registrado como `name` e usado como fallback
+    /// (testes do scheduler, sem sockets ICMP).
+    #[cfg(test)]
+    pub fn single(name: &str, check: Arc<dyn Check>) -> Self {
+        let mut registry = Self {
+            checks: HashMap::new(),
+            fallback: Arc::clone(&check),
+            limiter: Arc::new(RateLimiter::new(None)),
+            combined: Vec::new(),
+            source: SourceConfig::default(),
+            sources: BTreeMap::new(),
+        };
+        registry.register(name, check);
+        registry
+    }
+
+    /// Embrulha os checks registrados na injeção de falhas de `[chaos]`.
+    fn inject_faults(&mut self, chaos: Arc<ChaosConfig>, attempts: usize) {
+        for (name, check) in self.checks.iter_mut() {
+            *check = Arc::new(ChaosCheck::new(
+                Arc::clone(check),
+                CheckKind::from_name(name),
+                attempts,
+                Arc::clone(&chaos),
+            ));
+        }
+        if let Some(ping) = self.checks.get("ping") {
+            self.fallback = Arc::clone(ping);
+        }
+    }
+
+    /// Limite de pacotes por rede dos checks embutidos, para outras
+    /// medições que também enviam pacotes aos targets.
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    /// Origem do check `name`.
+    fn source_for(&self, name: &str) -> &SourceConfig {
+        self.sources.get(name).unwrap_or(&self.source)
+    }
+
+    /// Registra (ou substitui) um check.
+    pub fn register(&mut self, name: &str, check: Arc<dyn Check>) {
+        self.checks.insert(name.to_string(), check);
+    }
+
+    /// Nome do check declarado em `type`: prefixo antes de `_`/`-`, sem
+    /// diferenciar maiúsculas (`HTTP_ipv4`, `http-ipv6` e `http` → `http`).
+    /// `icmp` é aceito como sinônimo de `ping`.
+    pub fn kind_of(type_: &str) -> String {
+        let kind = type_
+            .trim()
+            .split(['_', '-'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match kind.as_str() {
+            "icmp" => "ping".to_string(),
+            _ => kind,
+        }
+    }
+
+    /// Nome e check responsável pelo target, conforme seu `type`; tipos sem
+    /// check registrado caem no `ping`.
+    fn check_for(&self, target: &Target) -> (String, Arc<dyn Check>) {
+        let kind = Self::kind_of(&target.type_);
+        match self.checks.get(&kind) {
+            Some(check) => (kind, Arc::clone(check)),
+            None => {
+                debug!(
+                    "[CHECK] Tipo `{}` do target {} sem check registrado, usando ping",
+                    target.type_, target.id
+                );
+                ("ping".to_string(), Arc::clone(&self.fallback))
+            }
+        }
+    }
+
+    /// Pacotes que um ciclo envia ao target: as tentativas do check dele e
+    /// dos sinais de `checks.combined`.
+    pub fn packets_for(&self, target: &Target) -> usize {
+        let (kind, check) = self.check_for(target);
+        check.packets()
+            + self
+                .signals_for(&kind)
+                .iter()
+                .map(|(_, signal)| signal.packets())
+                .sum::<usize>()
+    }
+
+    /// Sinais adicionais do target: os outros checks de `checks.combined`,
+    /// se o check dele estiver na lista.
+    fn signals_for(&self, kind: &str) -> Vec<(String, Arc<dyn Check>)> {
+        if !self.combined.iter().any(|c| c == kind) {
+            return Vec::new();
+        }
+        self.combined
+            .iter()
+            .filter(|c| *c != kind)
+            .filter_map(|c| {
+                self.checks
+                    .get(c)
+                    .map(|check| (c.clone(), Arc::clone(check)))
+            })
+            .collect()
+    }
+
+    /// Loga quantos targets usam cada check e avisa sobre tipos sem check
+    /// registrado (que caem no ping).
+    pub fn log_dispatch(&self, targets: &[Target]) {
+        let mut per_kind: HashMap<String, usize> = HashMap::new();
+        for target in targets {
+            let kind = Self::kind_of(&target.type_);
+            if !self.checks.contains_key(&kind) {
+                warn!(
+                    "[CHECK] Target {} ({}) tem tipo `{}` sem check registrado; será monitorado por ping",
+                    target.id, target.name, target.type_
+                );
+                *per_kind.entry("ping".to_string()).or_default() += 1;
+            } else {
+                *per_kind.entry(kind).or_default() += 1;
+            }
+        }
+        let mut summary: Vec<String> = per_kind
+            .into_iter()
+            .map(|(kind, count)| format!("{}={}", kind, count))
+            .collect();
+        summary.sort();
+        info!("🧪 Checks por target: {}", summary.join(", "));
+    }
+
+    /// Executa o check de cada target em paralelo (e, com
+    /// `checks.combined`, os sinais adicionais de cada um). Com `skip_heavy`,
+    /// checks pesados não rodam e não geram métrica neste ciclo. Os checks
+    /// ainda pendentes em `deadline` (orçamento do ciclo) são abortados e
+    /// entram como `Timeout`, listados em `CycleMetrics::aborted`.
+    pub async fn run_all(
+        &self,
+        targets: &[Target],
+        probe: &Probe,
+        cycle_id: i64,
+        skip_heavy: bool,
+        deadline: Option<tokio::time::Instant>,
+    ) -> CycleMetrics {
+        let pending: Vec<_> = targets
+            .iter()
+            .cloned()
+            .filter_map(|target| {
+                let (kind, check) = self.check_for(&target);
+                if skip_heavy && check.heavy() {
+                    debug!(
+                        "[CHECK] Check pesado de {} ({}) pausado pela carga do host",
+                        target.name, target.type_
+                    );
+                    return None;
+                }
+                let signals: Vec<_> = self
+                    .signals_for(&kind)
+                    .into_iter()
+                    .filter(|(_, check)| !(skip_heavy && check.heavy()))
+                    .map(|(name, check)| {
+                        let source = self.source_for(&name).clone();
+                        (name, check, source)
+                    })
+                    .collect();
+                let handle = {
+                    let target = target.clone();
+                    let probe = probe.clone();
+                    let check = Arc::clone(&check);
+                    let source = self.source_for(&kind).clone();
+                    let kind = kind.clone();
+                    tokio::spawn(async move {
+                        // O JoinSet aborta os sinais se o check for abortado
+                        let mut signal_set = tokio::task::JoinSet::new();
+                        for (index, (name, check, source)) in signals.into_iter().enumerate() {
+                            let target = target.clone();
+                            let probe = probe.clone();
+                            signal_set.spawn(async move {
+                                let metric =
+                                    run_check(&*check, &source, &target, &probe, cycle_id).await;
+                                (index, name, metric)
+                            });
+                        }
+                        let mut metric =
+                            run_check(&*check, &source, &target, &probe, cycle_id).await;
+                        let mut signals = Vec::with_capacity(signal_set.len());
+                        while let Some(result) = signal_set.join_next().await {
+                            if let Ok(signal) = result {
+                                signals.push(signal);
+                            }
+                        }
+                        signals.sort_by_key(|(index, _, _)| *index);
+                        let mut signals: Vec<_> = signals
+                            .into_iter()
+                            .map(|(_, name, metric)| (name, metric))
+                            .collect();
+                        if !signals.is_empty() {
+                            combine(&kind, &mut metric, &mut signals);
+                        }
+                        (metric, signals)
+                    })
+                };
+                Some((target, kind, check, handle))
+            })
+            .collect();
+
+        let mut results = CycleMetrics {
+            metrics: Vec::with_capacity(pending.len()),
+            signals: Vec::new(),
+            aborted: Vec::new(),
+        };
+        for (target, kind, check, mut handle) in pending {
+            let joined = match deadline {
+                // Um check já concluído é lido mesmo com o prazo vencido
+                Some(deadline) => match tokio::time::timeout_at(deadline, &mut handle).await {
+                    Ok(joined) => joined,
+                    Err(_) => {
+                        handle.abort();
+                        results.metrics.push(budget_timeout(
+                            &target,
+                            probe,
+                            CheckKind::from_name(&kind),
+                            &*check,
+                            self.source_for(&kind),
+                            cycle_id,
+                        ));
+                        results.aborted.push(target);
+                        continue;
+                    }
+                },
+                None => handle.await,
+            };
+            if let Ok((metric, signals)) = joined {
+                results.metrics.push(metric);
+                results
+                    .signals
+                    .extend(signals.into_iter().map(|(_, signal)| signal));
+            }
+        }
+        results
+    }
+}
+
+/// Métrica `Timeout` de um check abortado pelo orçamento do ciclo.
+fn budget_timeout(
+    target: &Target,
+    probe: &Probe,
+    kind: CheckKind,
+    check: &dyn Check,
+    source: &SourceConfig,
+    cycle_id: i64,
+) -> ConnectivityMetric {
+    let mut metric = build_metric(
+        target,
+        probe,
+        kind,
+        &[Attempt::Timeout],
+        Some(serde_json::json!({ "budget_exceeded": true })),
+    );
+    metric.set_error(CheckError::Timeout, "orçamento do ciclo esgotado");
+    metric.cycle_id = cycle_id;
+    metric.provenance = Some(provenance::resolve_from(
+        target.address,
+        check.backend(),
+        source,
+    ));
+    metric
+}
+
+/// Executa um check e completa o `cycle_id` e, se ausente, a proveniência.
+async fn run_check(
+    check: &dyn Check,
+    source: &SourceConfig,
+    target: &Target,
+    probe: &Probe,
+    cycle_id: i64,
+) -> ConnectivityMetric {
+    let mut metric = check.run(target, probe).await;
+    metric.cycle_id = cycle_id;
+    if metric.provenance.is_none() {
+        metric.provenance = Some(provenance::resolve_from(
+            target.address,
+            check.backend(),
+            source,
+        ));
+    }
+    metric
+}
+
+/// Veredito combinado dos sinais de um target: o melhor status entre o check
+/// próprio e os sinais adicionais (`Up` > `Degraded` > falha). Quando o
+/// veredito difere do check próprio, a métrica passa ao status, RTT e perda
+/// do sinal que respondeu (`verdict.source`), sem a causa de falha, e a nota
+/// explica quais sinais responderam. As
+/// medições adicionais ficam marcadas como `diagnostic`, fora do consenso e
+/// dos agregados.
+fn combine(
+    kind: &str,
+    metric: &mut ConnectivityMetric,
+    signals: &mut [(String, ConnectivityMetric)],
+) {
+    let rank = |status: &MetricStatus| match status {
+        MetricStatus::Up => 2,
+        MetricStatus::Degraded => 1,
+        MetricStatus::Down | MetricStatus::Timeout => 0,
+    };
+    let summary = |m: &ConnectivityMetric| {
+        serde_json::json!({
+            "status": m.status,
+            "response_time_ms": m.response_time_ms,
+            "error_kind": m.error,
+        })
+    };
+    let mut by_signal = serde_json::Map::new();
+    by_signal.insert(kind.to_string(), summary(metric));
+    for (name, signal) in signals.iter_mut() {
+        by_signal.insert(name.clone(), summary(signal));
+        let details = signal.details.get_or_insert_with(|| serde_json::json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("diagnostic".into(), true.into());
+            map.insert("combined_with".into(), kind.into());
+        }
+    }
+
+    let primary = metric.status.clone();
+    // Primeiro sinal com o melhor status, se melhor que o do check próprio
+    let best = signals
+        .iter()
+        .filter(|(_, s)| rank(&s.status) > rank(&primary))
+        .fold(
+            None,
+            |best: Option<&(String, ConnectivityMetric)>, signal| match best {
+                Some(b) if rank(&b.1.status) >= rank(&signal.1.status) => Some(b),
+                _ => Some(signal),
+            },
+        );
+    let mut verdict = serde_json::json!({
+        "status": best.map_or(&primary, |(_, s)| &s.status),
+        "primary_status": primary,
+        "signals": by_signal,
+    });
+    if let Some((source, signal)) = best {
+        let responded: Vec<&str> = signals
+            .iter()
+            .filter(|(_, s)| rank(&s.status) > rank(&primary))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        verdict["source"] = source.as_str().into();
+        verdict["note"] = format!(
+            "{} {}; responde por {}",
+            kind,
+            primary,
+            responded.join(", ")
+        )
+        .into();
+        metric.status = signal.status.clone();
+        metric.response_time_ms = signal.response_time_ms;
+        metric.packet_loss_percent = signal.packet_loss_percent;
+        metric.error = None;
+    }
+    let details = metric.details.get_or_insert_with(|| serde_json::json!({}));
+    if let Some(map) = details.as_object_mut() {
+        map.insert("verdict".into(), verdict);
+    }
+}
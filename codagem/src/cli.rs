@@ -0,0 +1,427 @@
+//! cli.rs — Argumentos de linha de comando
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Monitor de rede com detecção de outages por consenso.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "monitoramento_rede", version, about)]
+pub struct Cli {
+    /// Arquivo de configuração (padrão: `config.*` no diretório atual).
+    #[arg(long, short, env = "MONITOR_CONFIG")]
+    pub config: Option<String>,
+    /// Exibe o dashboard de terminal ao vivo (os logs vão para `--log-file`).
+    #[arg(long)]
+    pub tui: bool,
+    /// Confere a configuração, lista todos os problemas e sai (sem subir o
+    /// monitor).
+    #[arg(long)]
+    pub validate_config: bool,
+    /// Toma os probes de outra instância em execução (encerra a sessão dela
+    /// no banco) em vez de falhar na inicialização.
+    #[arg(long, env = "MONITOR_TAKEOVER")]
+    pub takeover: bool,
+    /// Arquivo de log usado no modo `--tui`.
+    #[arg(long, default_value = "monitoramento_rede.log")]
+    pub log_file: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcomandos; sem subcomando, o monitor é executado.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Gerenciamento de targets.
+    #[command(subcommand)]
+    Targets(TargetsCommand),
+    /// Teste de carga: targets sintéticos contra um responder local, pelo
+    /// pipeline completo (checks, persistência em lotes, consenso).
+    Soak(SoakArgs),
+    /// Relatórios sobre os dados gravados.
+    #[command(subcommand)]
+    Report(ReportCommand),
+    /// Pacote de postmortem de um incidente: zip com métricas, artefatos,
+    /// decisões do consenso, traceroutes e linha do tempo.
+    Bundle(BundleArgs),
+    /// Importa o histórico de outra ferramenta (smokeping, Uptime Kuma,
+    /// Pingdom) para `connectivity_metrics`, num probe sintético.
+    Import(ImportArgs),
+    /// Exporta métricas ou outages de um período em CSV, JSON Lines ou
+    /// Parquet, para análise fora do banco (pandas, planilhas).
+    Export(ExportArgs),
+    /// Simula o consenso sobre as métricas gravadas, com os parâmetros
+    /// atuais e com alternativos, e compara os outages que cada um teria
+    /// detectado.
+    Replay(ReplayArgs),
+    /// Roda cenários determinísticos do consenso (statuses por target e
+    /// ciclo, em TOML) e confere os outages esperados, sem rede nem banco.
+    Scenario(ScenarioArgs),
+    /// Autoteste do ambiente: banco e schema, permissão de ICMP, DNS, TCP de
+    /// saída e relógio (NTP), com um relatório de aprovado/reprovado.
+    Doctor(DoctorArgs),
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ReportCommand {
+    /// Saúde do caminho por AS (latência e perda que cada AS acrescenta),
+    /// a partir dos traceroutes.
+    AsHealth {
+        /// Janela consultada, em horas.
+        #[arg(long, default_value_t = 24)]
+        hours: i64,
+        /// Tamanho de cada janela da série, em minutos.
+        #[arg(long, default_value_t = 60)]
+        bucket_minutes: i64,
+        /// Imprime o relatório completo em JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Resumo do período (o mesmo de `[periodic_report]`): uptime, outages,
+    /// targets com mais perda e tendência de latência.
+    Summary {
+        /// Janela resumida, em horas, até agora.
+        #[arg(long, default_value_t = 24)]
+        hours: i64,
+        /// Resume um dia do calendário local (`AAAA-MM-DD`, no fuso de
+        /// `report_timezone`) em vez das últimas `--hours`.
+        #[arg(long, conflicts_with = "month")]
+        day: Option<String>,
+        /// Resume um mês do calendário local (`AAAA-MM`), como no SLA mensal.
+        #[arg(long)]
+        month: Option<String>,
+        /// Quantidade de targets listados entre os de maior perda.
+        #[arg(long, default_value_t = 5)]
+        worst: usize,
+        /// Imprime o resumo em JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Comparação de uplinks de um site multi-homed: uptime, perda, RTT e
+    /// minutos de outage de cada probe no período, do melhor ao pior.
+    Uplinks {
+        /// Janela comparada, em horas, até agora.
+        #[arg(long, default_value_t = 168)]
+        hours: i64,
+        /// Compara um dia do calendário local (`AAAA-MM-DD`).
+        #[arg(long, conflicts_with = "month")]
+        day: Option<String>,
+        /// Compara um mês do calendário local (`AAAA-MM`).
+        #[arg(long)]
+        month: Option<String>,
+        /// Probes comparados, por id ou `location` (repetível); padrão =
+        /// todos com medições no período.
+        #[arg(long = "probe")]
+        probes: Vec<String>,
+        /// Imprime a comparação em JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SoakArgs {
+    /// Quantidade de targets sintéticos.
+    #[arg(long, default_value_t = 1000)]
+    pub targets: usize,
+    /// Intervalo entre ciclos (ex: `10s`, `1m`).
+    #[arg(long, default_value = "10s", value_parser = parse_duration)]
+    pub interval: Duration,
+    /// Duração do teste (ex: `5m`, `1h`).
+    #[arg(long, default_value = "5m", value_parser = parse_duration)]
+    pub duration: Duration,
+    /// Mantém no banco o probe, os targets e as métricas do teste.
+    #[arg(long)]
+    pub keep: bool,
+    /// Grava num banco em memória em vez do PostgreSQL (sem conexão ao
+    /// banco).
+    #[arg(long, conflicts_with = "keep")]
+    pub memory: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BundleArgs {
+    /// Id do evento em `outage_events`.
+    pub incident_id: i64,
+    /// Arquivo gerado (padrão: `incident-<id>.zip`).
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// Minutos incluídos antes do início e depois do fim do incidente.
+    #[arg(long, default_value_t = 30)]
+    pub margin_minutes: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ImportArgs {
+    /// Arquivo exportado (CSV com cabeçalho ou JSON).
+    pub file: PathBuf,
+    /// Ferramenta de origem do arquivo.
+    #[arg(long, short, value_enum)]
+    pub format: ImportFormat,
+    /// Target de todos os registros (IP, `host:port` ou URL); obrigatório
+    /// quando o arquivo não traz o endereço (ex: smokeping).
+    #[arg(long)]
+    pub target: Option<String>,
+    /// Check dos registros (`ping`, `tcp`, `http`, `dns`, `tls`); padrão
+    /// conforme o formato.
+    #[arg(long)]
+    pub check: Option<String>,
+    /// `location` do probe sintético (padrão: `import-<formato>`).
+    #[arg(long)]
+    pub probe: Option<String>,
+    /// Só lê o arquivo e mostra o que seria importado, sem tocar no banco.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Formatos aceitos por `import`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    Smokeping,
+    UptimeKuma,
+    Pingdom,
+}
+
+impl ImportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Smokeping => "smokeping",
+            ImportFormat::UptimeKuma => "uptime-kuma",
+            ImportFormat::Pingdom => "pingdom",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    /// Dados exportados.
+    #[arg(value_enum)]
+    pub data: ExportData,
+    /// Formato do arquivo.
+    #[arg(long, short, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// Início da janela (RFC 3339 ou `AAAA-MM-DD`, em UTC; padrão: 24 h
+    /// antes do fim).
+    #[arg(long, value_parser = parse_time)]
+    pub from: Option<DateTime<Utc>>,
+    /// Fim da janela (padrão: agora).
+    #[arg(long, value_parser = parse_time)]
+    pub to: Option<DateTime<Utc>>,
+    /// Probe (id ou `location`); pode ser repetido.
+    #[arg(long = "probe")]
+    pub probes: Vec<String>,
+    /// Target (id, endereço ou nome); pode ser repetido.
+    #[arg(long = "target")]
+    pub targets: Vec<String>,
+    /// Arquivo gerado (padrão: `<dados>.<formato>`, ex: `metrics.csv`).
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReplayArgs {
+    /// Início da janela (RFC 3339 ou `AAAA-MM-DD`, em UTC; padrão: 24 h
+    /// antes do fim).
+    #[arg(long, value_parser = parse_time)]
+    pub from: Option<DateTime<Utc>>,
+    /// Fim da janela (padrão: agora).
+    #[arg(long, value_parser = parse_time)]
+    pub to: Option<DateTime<Utc>>,
+    /// Probe (id ou `location`); pode ser repetido. Padrão: todos.
+    #[arg(long = "probe")]
+    pub probes: Vec<String>,
+    /// `fail_threshold` alternativo (ciclos seguidos em falha).
+    #[arg(long)]
+    pub fail_threshold: Option<usize>,
+    /// `consensus` alternativo (targets em falha para abrir o outage).
+    #[arg(long)]
+    pub consensus: Option<usize>,
+    /// `outage_recovery_cycles` alternativo (histerese do encerramento).
+    #[arg(long)]
+    pub outage_recovery_cycles: Option<usize>,
+    /// `consensus_fail_percent` alternativo (modo percentual).
+    #[arg(long)]
+    pub fail_percent: Option<f64>,
+    /// Imprime o resultado completo em JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ScenarioArgs {
+    /// Arquivos de cenário.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Mostra os logs do consenso durante a execução.
+    #[arg(long)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DoctorArgs {
+    /// Endereço pingado para conferir a permissão de ICMP.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub icmp_target: IpAddr,
+    /// Nome resolvido para conferir o DNS.
+    #[arg(long, default_value = "one.one.one.one")]
+    pub dns_name: String,
+    /// Destino (`host:porta`) da conexão TCP de saída.
+    #[arg(long, default_value = "1.1.1.1:443")]
+    pub tcp_target: String,
+    /// Servidor NTP usado para medir o offset do relógio.
+    #[arg(long, default_value = "pool.ntp.org")]
+    pub ntp_server: String,
+    /// Offset máximo aceito do relógio, em ms.
+    #[arg(long, default_value_t = 1000)]
+    pub max_clock_offset_ms: u64,
+}
+
+/// Dados aceitos por `export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportData {
+    Metrics,
+    Outages,
+}
+
+impl ExportData {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportData::Metrics => "metrics",
+            ExportData::Outages => "outages",
+        }
+    }
+}
+
+/// Formatos aceitos por `export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Extensão do arquivo gerado.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Instante em RFC 3339, `AAAA-MM-DD HH:MM:SS` ou `AAAA-MM-DD` (UTC).
+fn parse_time(input: &str) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S") {
+        return Ok(time.and_utc());
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        .map_err(|_| {
+            format!(
+                "instante inválido: `{}` (use RFC 3339 ou AAAA-MM-DD)",
+                input
+            )
+        })
+}
+
+/// Duração com sufixo `s`, `m` ou `h` (sem sufixo = segundos).
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (value, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => input.split_at(i),
+        None => (input, "s"),
+    };
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("duração inválida: `{}`", input))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        _ => return Err(format!("unidade inválida em `{}` (use s, m ou h)", input)),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum TargetsCommand {
+    /// Cadastra targets (IPs, `host:port`, URLs ou blocos CIDR).
+    Add(Box<TargetsAddArgs>),
+    /// Lista os templates disponíveis.
+    Templates,
+    /// Atribui targets a um probe: ele passa a monitorar só os atribuídos.
+    Assign(AssignArgs),
+    /// Remove atribuições de um probe (sem targets, todas: ele volta a
+    /// monitorar todos).
+    Unassign(AssignArgs),
+    /// Lista os targets atribuídos a cada probe.
+    Assignments,
+    /// Pausa o monitoramento de targets, mantendo o histórico.
+    Pause(PauseArgs),
+    /// Retoma o monitoramento de targets pausados.
+    Resume(PauseArgs),
+}
+
+/// Argumentos de `targets pause` e `targets resume`.
+#[derive(Debug, Clone, Args)]
+pub struct PauseArgs {
+    /// Targets, por id, nome ou endereço.
+    #[arg(required = true)]
+    pub targets: Vec<String>,
+}
+
+/// Argumentos de `targets assign` e `targets unassign`.
+#[derive(Debug, Clone, Args)]
+pub struct AssignArgs {
+    /// Probe, por id ou localização.
+    pub probe: String,
+    /// Targets, por id, nome ou endereço.
+    pub targets: Vec<String>,
+}
+
+/// Argumentos de `targets add`.
+#[derive(Debug, Clone, Args)]
+pub struct TargetsAddArgs {
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    /// Template aplicado (ex: `public-dns`, `web-service`, `gateway`).
+    #[arg(long, short)]
+    pub template: Option<String>,
+    #[arg(long)]
+    pub name: Option<String>,
+    #[arg(long)]
+    pub provider: Option<String>,
+    #[arg(long)]
+    pub region: Option<String>,
+    #[arg(long)]
+    pub asn: Option<i32>,
+    /// Tag adicional (pode ser repetida).
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+    /// RTT (ms) acima do qual o target conta como degradado.
+    #[arg(long)]
+    pub degraded_rtt_ms: Option<f64>,
+    /// Perda (%) acima da qual o target conta como degradado.
+    #[arg(long)]
+    pub degraded_loss_percent: Option<i16>,
+    /// Bytes de payload dos pings (padrão: 32).
+    #[arg(long)]
+    pub ping_payload_bytes: Option<i32>,
+    /// Classe DSCP (0–63) marcada nos pings, ex: 46 (EF).
+    #[arg(long)]
+    pub dscp: Option<i16>,
+    /// Peso do target no consenso (padrão: 1).
+    #[arg(long)]
+    pub weight: Option<f64>,
+    /// Tenant (cliente) dono dos targets; precisa existir em `[[tenants]]`.
+    #[arg(long)]
+    pub tenant: Option<String>,
+}
@@ -0,0 +1,50 @@
+//! clock.rs — Fonte de tempo do consenso e dos outages
+//!
+//! `ConsensusState` e `OutageManager` não chamam `Utc::now()`: leem o
+//! instante de um [`Clock`] injetado. Em produção é o [`SystemClock`]; os
+//! cenários, o `replay` e os testes usam um [`ManualClock`], que só anda
+//! quando mandado, então a mesma sequência de ciclos produz sempre os mesmos
+//! eventos.
+
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::Mutex;
+
+/// Fonte do instante atual.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Relógio do sistema.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Relógio parado em um instante, movido só por [`set`](Self::set).
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn set(&self, instant: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = instant;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
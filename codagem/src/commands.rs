@@ -0,0 +1,480 @@
+//! commands.rs — Execução dos subcomandos de linha de comando
+
+use crate::as_health;
+use crate::calendar::{self, Zone};
+use crate::cli::{AssignArgs, PauseArgs, ReportCommand, TargetsCommand};
+use crate::config::Config;
+use crate::exporters::TargetLabel;
+use crate::periodic_report;
+use crate::registration::{self, Registration, RegistrationError};
+use crate::storage::Storage;
+use crate::templates::TemplateSet;
+use crate::types::Target;
+use crate::uplink_report;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+
+/// Subcomandos que não precisam do banco de dados. Retorna `false` se o
+/// comando precisa de storage e deve ser passado a [`run_targets`].
+pub fn run_offline(command: &TargetsCommand, templates: &TemplateSet) -> bool {
+    match command {
+        TargetsCommand::Templates => {
+            for t in templates.iter() {
+                println!(
+                    "{:<14} check={:<5} intervalo={:<6} fail_threshold={:<4} peso={:<4} região={:<8} tags={}",
+                    t.name,
+                    t.check,
+                    t.check_interval_secs
+                        .map_or("ciclo".to_string(), |i| format!("{}s", i)),
+                    t.fail_threshold.map_or("-".to_string(), |f| f.to_string()),
+                    t.weight.map_or("-".to_string(), |w| w.to_string()),
+                    t.region.as_deref().unwrap_or("-"),
+                    t.tags.join(",")
+                );
+            }
+            true
+        }
+        TargetsCommand::Add(_)
+        | TargetsCommand::Assign(_)
+        | TargetsCommand::Unassign(_)
+        | TargetsCommand::Assignments
+        | TargetsCommand::Pause(_)
+        | TargetsCommand::Resume(_) => false,
+    }
+}
+
+/// Subcomandos `targets` que usam o banco de dados.
+pub async fn run_targets(
+    command: TargetsCommand,
+    storage: &Storage,
+    templates: &TemplateSet,
+) -> Result<()> {
+    let args = match command {
+        TargetsCommand::Add(args) => *args,
+        TargetsCommand::Assign(args) => return run_assign(storage, args, true).await,
+        TargetsCommand::Unassign(args) => return run_assign(storage, args, false).await,
+        TargetsCommand::Assignments => return run_assignments(storage).await,
+        TargetsCommand::Pause(args) => return run_pause(storage, args, true).await,
+        TargetsCommand::Resume(args) => return run_pause(storage, args, false).await,
+        TargetsCommand::Templates => return Ok(()),
+    };
+    let request = Registration {
+        inputs: args.inputs,
+        name: args.name,
+        template: args.template,
+        asn: args.asn,
+        provider: args.provider,
+        region: args.region,
+        tags: args.tags,
+        degraded_rtt_ms: args.degraded_rtt_ms,
+        degraded_loss_percent: args.degraded_loss_percent,
+        ping_payload_bytes: args.ping_payload_bytes,
+        dscp: args.dscp,
+        weight: args.weight,
+        tenant: args.tenant,
+    };
+    match registration::register(storage, templates, &request).await {
+        Ok(registered) => {
+            for r in registered {
+                match r.id {
+                    Some(id) => println!("+ {} -> {} (id {})", r.input, r.address, id),
+                    None => println!("= {} -> {} (já cadastrado)", r.input, r.address),
+                }
+            }
+            Ok(())
+        }
+        Err(RegistrationError::Invalid(message)) => anyhow::bail!("Entrada inválida: {}", message),
+        Err(RegistrationError::Storage(e)) => Err(e),
+    }
+}
+
+/// `targets assign` (`assign = true`) e `targets unassign`.
+async fn run_assign(storage: &Storage, args: AssignArgs, assign: bool) -> Result<()> {
+    let probes = storage.list_probes().await?;
+    let probe = probes
+        .iter()
+        .find(|p| p.id.to_string() == args.probe || p.location == args.probe)
+        .ok_or_else(|| anyhow::anyhow!("Probe `{}` não encontrado", args.probe))?;
+    let target_ids: Vec<i32> = find_targets(storage, &args.targets)
+        .await?
+        .iter()
+        .map(|t| t.id)
+        .collect();
+    if assign {
+        if target_ids.is_empty() {
+            anyhow::bail!("Informe ao menos um target");
+        }
+        let added = storage.assign_probe_targets(probe.id, &target_ids).await?;
+        println!(
+            "+ {} atribuição(ões) ao probe {} (id {})",
+            added, probe.location, probe.id
+        );
+    } else {
+        let removed = storage
+            .unassign_probe_targets(probe.id, &target_ids)
+            .await?;
+        println!(
+            "- {} atribuição(ões) removida(s) do probe {} (id {})",
+            removed, probe.location, probe.id
+        );
+    }
+    let remaining = storage
+        .list_probe_targets()
+        .await?
+        .remove(&probe.id)
+        .unwrap_or_default();
+    if remaining.is_empty() {
+        println!("  O probe monitora todos os targets (sem atribuições)");
+    } else {
+        println!("  O probe monitora {} target(s)", remaining.len());
+    }
+    Ok(())
+}
+
+/// Targets por id, nome ou endereço, na ordem pedida.
+async fn find_targets(storage: &Storage, inputs: &[String]) -> Result<Vec<Target>> {
+    let targets = storage.list_targets().await?;
+    inputs
+        .iter()
+        .map(|input| {
+            targets
+                .iter()
+                .find(|t| {
+                    t.id.to_string() == *input
+                        || t.name == *input
+                        || t.address.to_string() == *input
+                })
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Target `{}` não encontrado", input))
+        })
+        .collect()
+}
+
+/// `targets pause` e `targets resume`: o monitor em execução relê a marcação
+/// a cada ciclo.
+async fn run_pause(storage: &Storage, args: PauseArgs, pause: bool) -> Result<()> {
+    let targets = find_targets(storage, &args.targets).await?;
+    let target_ids: Vec<i32> = targets.iter().map(|t| t.id).collect();
+    let changed = storage.set_targets_enabled(&target_ids, !pause).await?;
+    for target in &targets {
+        println!(
+            "{} {:<4} {} ({})",
+            if pause { "⏸️ " } else { "▶️ " },
+            target.id,
+            target.name,
+            target.address
+        );
+    }
+    println!(
+        "  {} target(s) {}{}",
+        changed,
+        if pause { "pausado(s)" } else { "retomado(s)" },
+        if changed < targets.len() as u64 {
+            " (os demais já estavam assim)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+/// `targets assignments`: targets atribuídos a cada probe.
+async fn run_assignments(storage: &Storage) -> Result<()> {
+    let assignments = storage.list_probe_targets().await?;
+    let names: HashMap<i32, String> = storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .map(|t| (t.id, format!("{} ({})", t.name, t.address)))
+        .collect();
+    for probe in storage.list_probes().await? {
+        match assignments.get(&probe.id) {
+            None => println!("{:<4} {:<36} todos os targets", probe.id, probe.location),
+            Some(target_ids) => {
+                println!(
+                    "{:<4} {:<36} {} target(s)",
+                    probe.id,
+                    probe.location,
+                    target_ids.len()
+                );
+                for id in target_ids {
+                    println!(
+                        "       {:<5} {}",
+                        id,
+                        names.get(id).map_or("?", String::as_str)
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Subcomandos `report`.
+pub async fn run_report(command: ReportCommand, storage: &Storage, config: &Config) -> Result<()> {
+    match command {
+        ReportCommand::AsHealth {
+            hours,
+            bucket_minutes,
+            json,
+        } => run_as_health(storage, hours, bucket_minutes, json).await,
+        ReportCommand::Summary {
+            hours,
+            day,
+            month,
+            worst,
+            json,
+        } => {
+            let window = summary_window(config.report_zone(), hours, day, month)?;
+            run_summary(storage, window, worst, json).await
+        }
+        ReportCommand::Uplinks {
+            hours,
+            day,
+            month,
+            probes,
+            json,
+        } => {
+            let window = summary_window(config.report_zone(), hours, day, month)?;
+            run_uplinks(storage, window, &probes, json).await
+        }
+    }
+}
+
+async fn run_as_health(
+    storage: &Storage,
+    hours: i64,
+    bucket_minutes: i64,
+    json: bool,
+) -> Result<()> {
+    if hours <= 0 || bucket_minutes <= 0 {
+        anyhow::bail!("--hours e --bucket-minutes devem ser maiores que zero");
+    }
+    let report = as_health::report(storage, hours, bucket_minutes).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!(
+        "Saúde por AS nas últimas {}h ({} traceroute(s))",
+        hours, report.traceroutes
+    );
+    println!(
+        "{:<10} {:<24} {:>7} {:>7} {:>12} {:>9} {:>14}",
+        "ASN", "provedor", "traces", "targets", "latência ms", "perda %", "pior janela %"
+    );
+    for a in &report.ases {
+        let worst = a.buckets.iter().map(|b| b.loss_percent).fold(0.0, f64::max);
+        println!(
+            "{:<10} {:<24} {:>7} {:>7} {:>12.1} {:>9.1} {:>14.1}",
+            format!("AS{}", a.asn),
+            a.provider.as_deref().unwrap_or("-"),
+            a.traces,
+            a.targets,
+            a.latency_ms,
+            a.loss_percent,
+            worst
+        );
+    }
+    Ok(())
+}
+
+/// Janela `[from, to)` do resumo e a descrição usada no cabeçalho.
+struct SummaryWindow {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    label: String,
+}
+
+/// Dia ou mês local de `zone`, ou as últimas `hours` até agora.
+fn summary_window(
+    zone: Zone,
+    hours: i64,
+    day: Option<String>,
+    month: Option<String>,
+) -> Result<SummaryWindow> {
+    if let Some(day) = day {
+        let date = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("--day inválido `{}` (use AAAA-MM-DD)", day))?;
+        let (from, to) = zone.day_bounds(date);
+        return Ok(SummaryWindow {
+            from,
+            to,
+            label: format!("de {} ({})", date, zone),
+        });
+    }
+    if let Some(month) = month {
+        let (from, to) = calendar::parse_month(&month)
+            .and_then(|(year, month)| zone.month_bounds(year, month))
+            .ok_or_else(|| anyhow::anyhow!("--month inválido `{}` (use AAAA-MM)", month))?;
+        return Ok(SummaryWindow {
+            from,
+            to,
+            label: format!("de {} ({})", month, zone),
+        });
+    }
+    if hours <= 0 {
+        anyhow::bail!("--hours deve ser maior que zero");
+    }
+    let to = Utc::now();
+    Ok(SummaryWindow {
+        from: to - chrono::Duration::hours(hours),
+        to,
+        label: format!("das últimas {}h", hours),
+    })
+}
+
+async fn run_summary(
+    storage: &Storage,
+    window: SummaryWindow,
+    worst: usize,
+    json: bool,
+) -> Result<()> {
+    let labels: HashMap<i32, TargetLabel> = storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .map(|t| {
+            (
+                t.id,
+                TargetLabel {
+                    name: t.name,
+                    address: t.address,
+                },
+            )
+        })
+        .collect();
+    let report =
+        periodic_report::build(storage, None, &labels, window.from, window.to, worst).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    let fmt = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+    println!(
+        "Resumo {} ({} target(s), {} medição(ões))",
+        window.label, report.targets, report.samples
+    );
+    println!("Uptime: {}%", fmt(report.uptime_percent));
+    println!(
+        "Outages: {} ({:.1} min, {} em aberto); parciais: {}; degradações: {}",
+        report.outages.count,
+        report.outages.minutes,
+        report.outages.open,
+        report.outages.partial,
+        report.outages.degradation
+    );
+    println!(
+        "Latência média: {} ms (período anterior: {} ms, variação: {}%)",
+        fmt(report.latency.avg_ms),
+        fmt(report.latency.previous_avg_ms),
+        fmt(report.latency.change_percent)
+    );
+    if !report.worst_targets.is_empty() {
+        println!(
+            "{:<8} {:<32} {:>9} {:>9}",
+            "target", "nome", "perda %", "uptime %"
+        );
+        for t in &report.worst_targets {
+            println!(
+                "{:<8} {:<32} {:>9.1} {:>9.1}",
+                t.target_id,
+                t.name.as_deref().unwrap_or("-"),
+                t.loss_percent,
+                t.uptime_percent
+            );
+        }
+    }
+    if !report.annotations.is_empty() {
+        println!("Anotações:");
+        for note in &report.annotations {
+            println!(
+                "  {} {} ({}, {}): {}",
+                note.event_type.as_str(),
+                note.outage_id,
+                note.start_time.format("%Y-%m-%d %H:%M"),
+                note.annotation.author.as_deref().unwrap_or("sem autor"),
+                note.annotation.text
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn run_uplinks(
+    storage: &Storage,
+    window: SummaryWindow,
+    probes: &[String],
+    json: bool,
+) -> Result<()> {
+    let probe_ids = if probes.is_empty() {
+        None
+    } else {
+        let known = storage.list_probes().await?;
+        let ids = probes
+            .iter()
+            .map(|entry| {
+                known
+                    .iter()
+                    .find(|p| p.location == *entry || p.id.to_string() == *entry)
+                    .map(|p| p.id)
+                    .ok_or_else(|| anyhow::anyhow!("probe `{}` não encontrado", entry))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Some(ids)
+    };
+    let comparison =
+        uplink_report::compare(storage, probe_ids.as_deref(), None, window.from, window.to).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+    let fmt = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+    println!(
+        "Comparação de uplinks {} ({} target(s) em comum)",
+        window.label, comparison.common_targets
+    );
+    println!(
+        "{:<2} {:<32} {:<16} {:<10} {:>9} {:>9} {:>10} {:>8} {:>11}",
+        "",
+        "probe",
+        "provedor",
+        "interface",
+        "uptime %",
+        "perda %",
+        "RTT ms",
+        "outages",
+        "outage min"
+    );
+    for u in &comparison.uplinks {
+        let best = comparison.best_probe_id == Some(u.probe_id);
+        println!(
+            "{:<2} {:<32} {:<16} {:<10} {:>9} {:>9} {:>10} {:>8} {:>11.1}",
+            if best { "★" } else { "" },
+            u.location,
+            u.provider.as_deref().unwrap_or("-"),
+            if u.interfaces.is_empty() {
+                "-".to_string()
+            } else {
+                u.interfaces.join(",")
+            },
+            fmt(u.uptime_percent),
+            fmt(u.loss_percent),
+            fmt(u.avg_rtt_ms),
+            u.outages,
+            u.outage_minutes
+        );
+    }
+    if let Some(best) = comparison
+        .uplinks
+        .iter()
+        .find(|u| comparison.best_probe_id == Some(u.probe_id))
+    {
+        println!(
+            "Melhor uplink: {} (menos minutos de outage, depois menor perda e menor RTT)",
+            best.location
+        );
+    }
+    Ok(())
+}
@@ -1,5 +1,14 @@
+use crate::calendar::Zone;
+use crate::types::{OutageEventType, Severity};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Months, NaiveDate, NaiveTime, Utc, Weekday,
+};
 use config as config_crate;
-use serde::Deserialize;
+use config_crate::builder::{ConfigBuilder, DefaultState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use tracing::warn;
 
 /// Configuração operacional do sistema.
 #[derive(Debug, Clone, Deserialize)]
@@ -12,28 +21,2804 @@ pub struct Config {
     pub fail_threshold: usize,
     /// Nível de consenso para considerar outage.
     pub consensus: usize,
+    /// Modo percentual do consenso: um target conta como em falha com
+    /// Down/Timeout em pelo menos esta % dos `fail_threshold` ciclos da
+    /// janela (ausente = em todos eles).
+    #[serde(default)]
+    pub consensus_fail_percent: Option<f64>,
+    /// Peso de um ciclo Down e de um ciclo Timeout na pontuação de falha do
+    /// consenso.
+    #[serde(default)]
+    pub consensus_weights: ConsensusWeights,
+    /// Teto de memória (bytes) da janela de histórico do consenso.
+    #[serde(default = "default_consensus_history_max_bytes")]
+    pub consensus_history_max_bytes: usize,
     /// Intervalo entre ciclos em segundos.
     pub cycle_interval_secs: u64,
+    /// Descarta os ticks perdidos quando um ciclo estoura o intervalo, em vez
+    /// de disparar os ciclos atrasados em sequência.
+    #[serde(default = "default_skip_missed_cycles")]
+    pub skip_missed_cycles: bool,
+    /// Parcela (%) de `cycle_interval_secs` disponível para os checks do
+    /// ciclo; os que não terminam nesse prazo são abortados como `Timeout`.
+    #[serde(default = "default_cycle_budget_percent")]
+    pub cycle_budget_percent: f64,
+    /// Ciclos seguidos abaixo do consenso para encerrar um outage (histerese;
+    /// a abertura segue exigindo `fail_threshold` ciclos em falha).
+    #[serde(default = "default_outage_recovery_cycles")]
+    pub outage_recovery_cycles: usize,
+    /// Ciclos seguidos respondendo para encerrar o outage parcial de um target.
+    #[serde(default = "default_partial_outage_recovery_cycles")]
+    pub partial_outage_recovery_cycles: usize,
+    /// Intervalo máximo (segundos) desde a última métrica do probe para que
+    /// um outage deixado aberto seja retomado após um restart; acima disso ele
+    /// é encerrado na última métrica.
+    #[serde(default = "default_outage_resume_max_gap")]
+    pub outage_resume_max_gap_secs: u64,
+    /// Depuração: grava cada tentativa dos checks em `raw_samples`, além da
+    /// métrica agregada do ciclo.
+    #[serde(default)]
+    pub raw_samples: bool,
     /// URL de conexão com o banco PostgreSQL.
     pub database_url: String,
+    /// Réplica somente leitura para as consultas (relatórios, exportações,
+    /// página de status e os `GET` da API); as gravações seguem no
+    /// `database_url`.
+    #[serde(default)]
+    pub database_read_url: Option<String>,
+    /// Idade máxima (segundos) do histórico usado para o warmup no cold-start.
+    #[serde(default = "default_warmup_seed_max_age")]
+    pub warmup_seed_max_age_secs: u64,
+    /// Intervalo (segundos) entre atualizações das views agregadas.
+    #[serde(default = "default_aggregate_refresh_interval")]
+    pub aggregate_refresh_interval_secs: u64,
+    /// Página pública de status (desabilitada se ausente).
+    pub status_page: Option<StatusPageConfig>,
+    /// Sink secundário em Influx line protocol (desabilitado se ausente).
+    pub influx: Option<InfluxConfig>,
+    /// Sink Prometheus remote-write (desabilitado se ausente).
+    pub remote_write: Option<RemoteWriteConfig>,
+    /// Streaming de métricas e outages para NATS/Kafka (desabilitado se ausente).
+    pub streaming: Option<StreamingConfig>,
+    /// Sinks adicionais (stdout, arquivo, webhook), todos ativos ao mesmo tempo.
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    /// Pseudonimização de dados exportados/publicados (desabilitada se ausente).
+    pub anonymization: Option<AnonymizationConfig>,
+    /// API HTTP embutida (desabilitada se ausente).
+    pub api: Option<ApiConfig>,
+    /// Envio de resumos anonimizados a um agregador comunitário (desabilitado se ausente).
+    pub fleet: Option<FleetConfig>,
+    /// Descoberta automática de targets na inicialização (desabilitada se ausente).
+    pub discovery: Option<DiscoveryConfig>,
+    /// Medição de assimetria de latência via TCP (desabilitada se ausente).
+    pub asymmetry: Option<AsymmetryConfig>,
+    /// RTT mínimo por target e nota de bufferbloat sob carga (desabilitado se
+    /// ausente).
+    pub bufferbloat: Option<BufferbloatConfig>,
+    /// Traceroute periódico até cada target, base da saúde por AS
+    /// (desabilitado se ausente).
+    pub traceroute: Option<TracerouteConfig>,
+    /// Diagnóstico de onde está a falha (LAN, CPE ou upstream) quando todos
+    /// os targets caem (desabilitado se ausente).
+    pub diagnosis: Option<DiagnosisConfig>,
+    /// Detecção de captive portal na abertura de outages (desabilitada se
+    /// ausente).
+    pub captive_portal: Option<CaptivePortalConfig>,
+    /// Preenchimento periódico de `asn`/`provider` dos targets por consulta
+    /// externa (desabilitado se ausente).
+    pub enrichment: Option<EnrichmentConfig>,
+    /// País, cidade e coordenadas de targets e probes por uma base MaxMind
+    /// GeoLite2 local (desabilitado se ausente).
+    pub geoip: Option<GeoIpConfig>,
+    /// Visibilidade BGP dos prefixos dos targets durante outages, pelo
+    /// RIPEstat (desabilitada se ausente).
+    pub bgp: Option<BgpConfig>,
+    /// Contadores de interface do CPE/roteador por SNMP, gravados em
+    /// `device_metrics` (desabilitado se ausente).
+    pub snmp: Option<SnmpConfig>,
+    /// Alcance e MAC dos gateways padrão por ARP/ND, gravados em
+    /// `neighbor_samples` (desabilitado se ausente).
+    pub neighbor: Option<NeighborConfig>,
+    /// Mudanças de interface e de rota padrão do host por netlink, gravadas
+    /// em `host_network_events` (desabilitado se ausente).
+    pub netwatch: Option<NetwatchConfig>,
+    /// Parâmetros dos checks tcp/http/dns (o tipo de check vem de `type` do target).
+    #[serde(default)]
+    pub checks: ChecksConfig,
+    /// Métodos da verificação de conectividade do probe (antes de abrir
+    /// outages e enquanto espera a internet voltar).
+    #[serde(default)]
+    pub connectivity: ConnectivityConfig,
+    /// Limite de pacotes por segundo por rede de destino (desabilitado se
+    /// ausente).
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Injeção de falhas sintéticas nos checks, para exercitar consenso,
+    /// outages e notificações numa rede saudável (desabilitada se ausente).
+    pub chaos: Option<ChaosConfig>,
+    /// Detecção de anomalias de latência/perda sobre baselines (desabilitada se ausente).
+    pub anomaly: Option<AnomalyConfig>,
+    /// Limites de recursos do host da probe amostrados a cada ciclo.
+    #[serde(default)]
+    pub host: HostConfig,
+    /// Fila e lotes do pipeline de persistência.
+    #[serde(default)]
+    pub persistence: PersistenceConfig,
+    /// Escalonamento de severidade de outages abertos por tempo.
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    /// Roteamento de notificações de outage por severidade (desabilitado se ausente).
+    pub notifications: Option<NotificationsConfig>,
+    /// Heartbeat externo ao fim de cada ciclo (desabilitado se ausente).
+    pub dead_man_switch: Option<DeadManSwitchConfig>,
+    /// Registro por ciclo das decisões do consenso (desabilitado se ausente).
+    pub consensus_decisions: Option<ConsensusDecisionsConfig>,
+    /// Histogramas horários de RTT para heatmaps de latência (desabilitados
+    /// se ausente).
+    pub latency_heatmap: Option<LatencyHeatmapConfig>,
+    /// `connectivity_metrics` como hypertable do TimescaleDB, com compressão e
+    /// retenção por chunks (desabilitado se ausente).
+    pub timescale: Option<TimescaleConfig>,
+    /// Resumo periódico enviado às rotas de notificação (desabilitado se ausente).
+    pub periodic_report: Option<PeriodicReportConfig>,
+    /// Fuso das fronteiras de dia e mês dos resumos, do `report summary` e dos
+    /// horários silenciosos: nome IANA (ex: `America/Sao_Paulo`) ou
+    /// deslocamento fixo (ex: `-03:00`).
+    #[serde(default = "default_report_timezone")]
+    pub report_timezone: String,
+    /// Templates de target adicionais (sobrescrevem os embutidos pelo nome).
+    #[serde(default)]
+    pub target_templates: Vec<crate::templates::TargetTemplate>,
+    /// Targets cadastrados na inicialização (endereços já existentes são ignorados).
+    #[serde(default)]
+    pub targets: Vec<crate::registration::Registration>,
+    /// Targets e probes lidos de um arquivo de inventário em vez do banco
+    /// (desabilitado se ausente).
+    pub inventory: Option<InventoryConfig>,
+    /// Instâncias de consenso "sombra", avaliadas em paralelo sem alertar.
+    #[serde(default)]
+    pub shadow_consensus: Vec<ShadowConsensusConfig>,
+    /// Tenants (clientes) com targets, consenso e notificações próprios;
+    /// vazio = um único grupo com todos os targets.
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    /// Ajustes por probe (intervalo, tentativas, consenso, targets), para
+    /// probes em links que não comportam o agendamento global.
+    #[serde(default)]
+    pub probe_profiles: Vec<ProbeProfileConfig>,
 }
 
+/// Parâmetros dos checks plugáveis. Todos usam `ping_count` tentativas e
+/// `timeout_secs` por tentativa.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChecksConfig {
+    /// Porta do check `tcp` (connect).
+    #[serde(default = "default_check_tcp_port")]
+    pub tcp_port: u16,
+    /// Porta do check `http`.
+    #[serde(default = "default_check_http_port")]
+    pub http_port: u16,
+    /// Caminho requisitado pelo check `http`.
+    #[serde(default = "default_check_http_path")]
+    pub http_path: String,
+    /// Redirects seguidos pelo check `http` (0 = não segue).
+    #[serde(default = "default_check_http_max_redirects")]
+    pub http_max_redirects: usize,
+    /// Hosts para os quais um redirect é esperado, além do próprio target
+    /// (vazio = qualquer host). Redirects para outros hosts deixam a medição
+    /// `Degraded`.
+    #[serde(default)]
+    pub http_allowed_redirect_hosts: Vec<String>,
+    /// Nome consultado (registro A) pelo check `dns`.
+    #[serde(default = "default_check_dns_query")]
+    pub dns_query: String,
+    /// Consultas adicionais do check `dns`, uma por ciclo cada, com a
+    /// latência de cada nome em `details.queries`.
+    #[serde(default)]
+    pub dns_queries: Vec<DnsQueryConfig>,
+    /// Porta do check `tls` (handshake e certificado).
+    #[serde(default = "default_check_tls_port")]
+    pub tls_port: u16,
+    /// Dias antes do vencimento em que o certificado deixa a medição
+    /// `Degraded`.
+    #[serde(default = "default_check_tls_expiry_warning_days")]
+    pub tls_expiry_warning_days: u32,
+    /// Verifica a revogação do certificado via OCSP (stapling ou responder).
+    #[serde(default)]
+    pub tls_ocsp: bool,
+    /// Porta do check `udp`.
+    #[serde(default = "default_check_udp_port")]
+    pub udp_port: u16,
+    /// Datagrama enviado pelo check `udp`, em texto (vazio = datagrama vazio).
+    #[serde(default)]
+    pub udp_payload: String,
+    /// Datagrama em hexadecimal, para protocolos binários; substitui
+    /// `udp_payload`.
+    #[serde(default)]
+    pub udp_payload_hex: Option<String>,
+    /// Porta do check `sip` (OPTIONS por UDP).
+    #[serde(default = "default_check_sip_port")]
+    pub sip_port: u16,
+    /// Usuário da Request-URI do `OPTIONS` (`sip:usuario@gateway`); ausente =
+    /// só o host do gateway.
+    #[serde(default)]
+    pub sip_user: Option<String>,
+    /// Checks equivalentes de alcançabilidade (ex: `["ping", "tcp", "dns"]`):
+    /// um target cujo check está na lista roda também os demais no mesmo
+    /// ciclo, e o status dele passa a ser o veredito combinado. Vazio
+    /// (padrão) = um check por target.
+    #[serde(default)]
+    pub combined: Vec<String>,
+    /// Interface e/ou endereço de origem de todos os checks (ausente = o
+    /// kernel escolhe pela tabela de rotas).
+    #[serde(default)]
+    pub source: SourceConfig,
+    /// Origem por check (ex: `[checks.sources.http]`), no lugar de `source`.
+    #[serde(default)]
+    pub sources: BTreeMap<String, SourceConfig>,
+}
+
+impl ChecksConfig {
+    /// Origem dos pacotes do check `name`.
+    pub fn source_for(&self, name: &str) -> &SourceConfig {
+        self.sources.get(name).unwrap_or(&self.source)
+    }
+
+    /// Datagrama do check `udp` (`udp_payload_hex`, se houver; senão
+    /// `udp_payload`).
+    pub fn udp_payload(&self) -> Vec<u8> {
+        match &self.udp_payload_hex {
+            Some(hex) => hex::decode(hex.trim()).unwrap_or_default(),
+            None => self.udp_payload.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Default for ChecksConfig {
+    fn default() -> Self {
+        Self {
+            tcp_port: default_check_tcp_port(),
+            http_port: default_check_http_port(),
+            http_path: default_check_http_path(),
+            http_max_redirects: default_check_http_max_redirects(),
+            http_allowed_redirect_hosts: Vec::new(),
+            dns_query: default_check_dns_query(),
+            dns_queries: Vec::new(),
+            tls_port: default_check_tls_port(),
+            tls_expiry_warning_days: default_check_tls_expiry_warning_days(),
+            tls_ocsp: false,
+            udp_port: default_check_udp_port(),
+            udp_payload: String::new(),
+            udp_payload_hex: None,
+            sip_port: default_check_sip_port(),
+            sip_user: None,
+            combined: Vec::new(),
+            source: SourceConfig::default(),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+/// Origem dos pacotes de um check, para hosts com mais de um uplink: a
+/// `interface` prende o socket à interface (`SO_BINDTODEVICE`) e o `address`
+/// fixa o endereço local (só em targets da mesma família).
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq, Hash)]
+pub struct SourceConfig {
+    pub interface: Option<String>,
+    pub address: Option<IpAddr>,
+}
+
+impl SourceConfig {
+    /// Sem origem configurada.
+    pub fn is_default(&self) -> bool {
+        self.interface.is_none() && self.address.is_none()
+    }
+
+    /// Origem nas mensagens (ex: `wan2`, `192.0.2.10`, `wan2/192.0.2.10`).
+    pub fn label(&self) -> String {
+        match (&self.interface, self.address) {
+            (Some(interface), Some(address)) => format!("{}/{}", interface, address),
+            (Some(interface), None) => interface.clone(),
+            (None, Some(address)) => address.to_string(),
+            (None, None) => "padrão".to_string(),
+        }
+    }
+
+    fn validate(&self, key: &str) -> Result<(), String> {
+        if self.is_default() {
+            return Err(format!("{}: informe interface e/ou address", key));
+        }
+        if let Some(interface) = &self.interface
+            && (interface.trim().is_empty() || interface.len() >= libc::IFNAMSIZ)
+        {
+            return Err(format!(
+                "{}.interface: deve ter de 1 a {} caracteres",
+                key,
+                libc::IFNAMSIZ - 1
+            ));
+        }
+        if let Some(address) = self.address
+            && (address.is_unspecified() || address.is_multicast())
+        {
+            return Err(format!(
+                "{}.address: {} não é um endereço local",
+                key, address
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Consulta adicional do check `dns`. `{random}` no nome é trocado por um
+/// rótulo aleatório a cada consulta, o que obriga o resolver a ir ao
+/// servidor autoritativo (consulta sem cache).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DnsQueryConfig {
+    pub name: String,
+    #[serde(default)]
+    pub record: DnsRecordType,
+}
+
+impl DnsQueryConfig {
+    /// Consulta que nunca é respondida do cache do resolver.
+    pub fn uncached(&self) -> bool {
+        self.name.contains("{random}")
+    }
+}
+
+/// Tipo de registro consultado.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+}
+
+impl DnsRecordType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsRecordType::A => "A",
+            DnsRecordType::Aaaa => "AAAA",
+        }
+    }
+}
+
+/// Método da verificação de conectividade.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectivityMethod {
+    /// Connect TCP nas `tcp_ports` de cada target.
+    Tcp,
+    /// DNS reverso do endereço de cada target.
+    Dns,
+    /// Um echo ICMP para os targets.
+    Icmp,
+}
+
+/// Verificação de conectividade do probe: os métodos rodam na ordem de
+/// `methods`, contra os canários (ou os targets), e o primeiro sucesso
+/// basta. Método fora da lista não roda.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectivityConfig {
+    #[serde(default = "default_connectivity_methods")]
+    pub methods: Vec<ConnectivityMethod>,
+    /// Portas do método `tcp`; a porta do check `tcp` (`checks.tcp_port`) é
+    /// tentada por último se não estiver na lista.
+    #[serde(default = "default_connectivity_tcp_ports")]
+    pub tcp_ports: Vec<u16>,
+    /// Timeout de cada tentativa (connect, consulta DNS, echo ICMP).
+    #[serde(default = "default_connectivity_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Endereços independentes (ex: IPs anycast conhecidos) usados só nesta
+    /// verificação; vazio = os próprios targets monitorados.
+    #[serde(default)]
+    pub canaries: Vec<IpAddr>,
+}
+
+impl Default for ConnectivityConfig {
+    fn default() -> Self {
+        Self {
+            methods: default_connectivity_methods(),
+            tcp_ports: default_connectivity_tcp_ports(),
+            timeout_secs: default_connectivity_timeout_secs(),
+            canaries: Vec::new(),
+        }
+    }
+}
+
+fn default_connectivity_methods() -> Vec<ConnectivityMethod> {
+    vec![
+        ConnectivityMethod::Tcp,
+        ConnectivityMethod::Dns,
+        ConnectivityMethod::Icmp,
+    ]
+}
+
+fn default_connectivity_tcp_ports() -> Vec<u16> {
+    vec![53, 80, 443]
+}
+
+fn default_connectivity_timeout_secs() -> u64 {
+    3
+}
+
+/// Baselines EWMA por target para detecção de anomalias.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnomalyConfig {
+    /// Peso da amostra nova na EWMA (0 < alpha ≤ 1).
+    #[serde(default = "default_anomaly_alpha")]
+    pub alpha: f64,
+    /// Desvios acima da média para abrir uma anomalia.
+    #[serde(default = "default_anomaly_sigmas")]
+    pub sigmas: f64,
+    /// Amostras antes de a baseline poder disparar.
+    #[serde(default = "default_anomaly_min_samples")]
+    pub min_samples: u64,
+    /// Aumento mínimo de RTT (ms) sobre a média para contar como anomalia.
+    #[serde(default = "default_anomaly_min_delta_ms")]
+    pub min_delta_ms: f64,
+    /// Aumento mínimo de perda (pontos percentuais) sobre a média.
+    #[serde(default = "default_anomaly_min_loss_delta")]
+    pub min_loss_delta_percent: f64,
+}
+
+/// Limites a partir dos quais o host da probe é considerado sem recursos.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostConfig {
+    /// Load average de 1 minuto por CPU.
+    #[serde(default = "default_host_max_load_per_cpu")]
+    pub max_load_per_cpu: f64,
+    /// Percentual mínimo de memória disponível.
+    #[serde(default = "default_host_min_mem_available")]
+    pub min_mem_available_percent: f64,
+    /// PSI `some avg10` máximo de memória.
+    #[serde(default = "default_host_max_mem_pressure")]
+    pub max_mem_pressure_avg10: f64,
+    /// Sistema de arquivos verificado.
+    #[serde(default = "default_host_disk_path")]
+    pub disk_path: String,
+    /// Percentual mínimo livre em `disk_path`.
+    #[serde(default = "default_host_min_disk_free")]
+    pub min_disk_free_percent: f64,
+    /// Percentual máximo de descritores abertos sobre o limite do processo.
+    #[serde(default = "default_host_max_fd_usage")]
+    pub max_fd_usage_percent: f64,
+    /// Load average por CPU acima do qual checks pesados (e a medição de
+    /// assimetria) são pausados e o ciclo é marcado com `degraded_conditions`.
+    #[serde(default = "default_host_heavy_max_load_per_cpu")]
+    pub heavy_max_load_per_cpu: f64,
+    /// Descarta do consenso e dos agregados os ciclos medidos sem recursos
+    /// (senão apenas anota as métricas).
+    #[serde(default)]
+    pub reject: bool,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            max_load_per_cpu: default_host_max_load_per_cpu(),
+            min_mem_available_percent: default_host_min_mem_available(),
+            max_mem_pressure_avg10: default_host_max_mem_pressure(),
+            disk_path: default_host_disk_path(),
+            min_disk_free_percent: default_host_min_disk_free(),
+            max_fd_usage_percent: default_host_max_fd_usage(),
+            heavy_max_load_per_cpu: default_host_heavy_max_load_per_cpu(),
+            reject: false,
+        }
+    }
+}
+
+/// Pipeline de persistência: os schedulers enfileiram e uma task grava em lotes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersistenceConfig {
+    /// Mensagens na fila antes de os schedulers esperarem pelo banco.
+    #[serde(default = "default_persistence_queue_capacity")]
+    pub queue_capacity: usize,
+    /// Mensagens drenadas da fila por lote de gravação.
+    #[serde(default = "default_persistence_batch_size")]
+    pub batch_size: usize,
+    /// Arquivo JSONL onde ficam as gravações pendentes durante quedas do banco.
+    #[serde(default = "default_persistence_spool_path")]
+    pub spool_path: String,
+    /// Teto do spool em bytes; 0 desabilita o spool (pendências são descartadas).
+    #[serde(default = "default_persistence_spool_max_bytes")]
+    pub spool_max_bytes: u64,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_persistence_queue_capacity(),
+            batch_size: default_persistence_batch_size(),
+            spool_path: default_persistence_spool_path(),
+            spool_max_bytes: default_persistence_spool_max_bytes(),
+        }
+    }
+}
+
+/// Pesos dos status de falha no consenso. Um target filtrando ICMP (sempre
+/// Timeout) não é o mesmo sinal de um RST ou de um destino inalcançável
+/// (Down). Só valem no modo percentual (`consensus_fail_percent`): com
+/// `timeout < 1`, timeouts precisam de mais ciclos para contar. No modo
+/// estrito a janela inteira precisa pontuar 1 por ciclo, então um único ciclo
+/// com peso menor impediria o target de contar; a validação recusa pesos
+/// abaixo de 1 sem `consensus_fail_percent`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct ConsensusWeights {
+    #[serde(default = "default_consensus_weight")]
+    pub down: f64,
+    #[serde(default = "default_consensus_weight")]
+    pub timeout: f64,
+}
+
+impl Default for ConsensusWeights {
+    fn default() -> Self {
+        Self {
+            down: default_consensus_weight(),
+            timeout: default_consensus_weight(),
+        }
+    }
+}
+
+/// Idades a partir das quais um outage aberto sobe de severidade
+/// (`minor` → `major` → `critical`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct EscalationConfig {
+    /// Segundos em aberto até virar `major`.
+    #[serde(default = "default_escalation_major_after")]
+    pub major_after_secs: i64,
+    /// Segundos em aberto até virar `critical`.
+    #[serde(default = "default_escalation_critical_after")]
+    pub critical_after_secs: i64,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            major_after_secs: default_escalation_major_after(),
+            critical_after_secs: default_escalation_critical_after(),
+        }
+    }
+}
+
+/// Resumo periódico (uptime, outages, piores targets e tendência de latência)
+/// enviado às rotas de notificação.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeriodicReportConfig {
+    #[serde(default)]
+    pub period: ReportPeriod,
+    /// Hora do envio (0–23), no fuso de `report_timezone`.
+    #[serde(default = "default_periodic_report_hour")]
+    pub hour: u32,
+    /// Dia do envio no resumo semanal.
+    #[serde(default = "default_periodic_report_weekday")]
+    pub weekday: Weekday,
+    /// Dia do mês do envio no resumo mensal (1–28).
+    #[serde(default = "default_periodic_report_day_of_month")]
+    pub day_of_month: u32,
+    /// Deslocamento fixo de UTC (ex: `-03:00`) no lugar de `report_timezone`.
+    pub utc_offset: Option<String>,
+    /// Quantidade de targets listados entre os de maior perda.
+    #[serde(default = "default_periodic_report_worst_targets")]
+    pub worst_targets: usize,
+    /// Inclui a comparação de uplinks (um probe por uplink) no resumo.
+    #[serde(default)]
+    pub compare_uplinks: bool,
+    /// Rotas que recebem o resumo, pelo nome; vazio = todas.
+    #[serde(default)]
+    pub routes: Vec<String>,
+}
+
+/// Período coberto por cada resumo.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    #[default]
+    Daily,
+    Weekly,
+    /// Mês do calendário local.
+    Monthly,
+}
+
+impl ReportPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Daily => "daily",
+            ReportPeriod::Weekly => "weekly",
+            ReportPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// Data local em que começa o período que termina em `date`.
+    pub fn start_date(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Daily => date - Duration::days(1),
+            ReportPeriod::Weekly => date - Duration::weeks(1),
+            ReportPeriod::Monthly => date
+                .checked_sub_months(Months::new(1))
+                .expect("data fora do intervalo"),
+        }
+    }
+}
+
+/// Configuração do registro de decisões do consenso.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConsensusDecisionsConfig {
+    /// Dias mantidos em `consensus_decisions`.
+    #[serde(default = "default_consensus_decisions_retention_days")]
+    pub retention_days: u32,
+}
+
+/// Modo TimescaleDB (`[timescale]`) de `connectivity_metrics`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimescaleConfig {
+    /// Intervalo de tempo coberto por cada chunk, em horas.
+    #[serde(default = "default_timescale_chunk_interval_hours")]
+    pub chunk_interval_hours: u32,
+    /// Chunks mais antigos que isso (dias) são comprimidos (0 = sem compressão).
+    #[serde(default = "default_timescale_compress_after_days")]
+    pub compress_after_days: u32,
+    /// Chunks mais antigos que isso (dias) são descartados com `drop_chunks`
+    /// (0 = sem limite).
+    #[serde(default)]
+    pub retention_days: u32,
+}
+
+/// Falhas sintéticas injetadas na camada de checks (`[chaos]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosConfig {
+    /// Probabilidade (0 a 1) de um check virar `Timeout` sem ser executado.
+    #[serde(default)]
+    pub timeout_probability: f64,
+    /// Probabilidade (0 a 1) de o RTT medido ser inflado.
+    #[serde(default)]
+    pub latency_probability: f64,
+    /// Fator aplicado ao RTT inflado.
+    #[serde(default = "default_chaos_latency_factor")]
+    pub latency_factor: f64,
+    /// Milissegundos somados ao RTT inflado (depois do fator).
+    #[serde(default)]
+    pub latency_add_ms: f64,
+    /// Targets (nome ou endereço) sujeitos a timeouts e latência; vazio =
+    /// todos.
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Períodos em que targets ficam forçados em `Down`.
+    #[serde(default)]
+    pub down: Vec<ChaosDownConfig>,
+}
+
+/// Período de `Down` forçado: uma janela fixa (`from`/`to`) ou recorrente
+/// (`duration_secs` a cada `every_secs`, contados da meia-noite UTC mais
+/// `offset_secs`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosDownConfig {
+    /// Targets (nome ou endereço) derrubados; vazio = todos.
+    #[serde(default)]
+    pub targets: Vec<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub every_secs: Option<u64>,
+    pub duration_secs: Option<u64>,
+    #[serde(default)]
+    pub offset_secs: u64,
+}
+
+/// Configuração dos histogramas horários de RTT (`latency_histograms`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct LatencyHeatmapConfig {
+    /// Limites superiores das faixas em ms, em ordem crescente; o que passa
+    /// do último conta na faixa `Infinity`.
+    #[serde(default = "default_heatmap_buckets")]
+    pub buckets_ms: Vec<f64>,
+    /// Intervalo (segundos) entre recálculos da hora atual e da anterior.
+    #[serde(default = "default_heatmap_interval")]
+    pub interval_secs: u64,
+    /// Horas recalculadas na inicialização (histórico já existente).
+    #[serde(default = "default_heatmap_backfill_hours")]
+    pub backfill_hours: u32,
+    /// Dias mantidos em `latency_histograms` (0 = sem limite).
+    #[serde(default = "default_heatmap_retention_days")]
+    pub retention_days: u32,
+}
+
+/// Configuração do roteamento de notificações.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationsConfig {
+    /// Destinos; cada notificação vai a todas as rotas cuja severidade mínima
+    /// é atingida.
+    #[serde(default)]
+    pub routes: Vec<NotificationRoute>,
+    /// Timeout de cada envio em segundos.
+    #[serde(default = "default_influx_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Configuração do heartbeat externo (healthchecks.io e compatíveis).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeadManSwitchConfig {
+    /// URL pingada com `GET`; `{probe_id}` é trocado pelo id do probe.
+    pub url: String,
+    /// Timeout de cada ping em segundos.
+    #[serde(default = "default_influx_timeout")]
+    pub timeout_secs: u64,
+}
+
+impl NotificationsConfig {
+    /// Valida as rotas; `prefix` identifica a seção nas mensagens.
+    fn validate(&self, prefix: &str) -> Result<(), String> {
+        for (i, route) in self.routes.iter().enumerate() {
+            if let Some(quiet) = &route.quiet_hours {
+                let key = format!("{}.routes[{}] ({}).quiet_hours", prefix, i, route.name);
+                for (field, value) in [("start", &quiet.start), ("end", &quiet.end)] {
+                    if parse_time_of_day(value).is_none() {
+                        return Err(format!(
+                            "{}.{}: horário inválido `{}` (use HH:MM)",
+                            key, field, value
+                        ));
+                    }
+                }
+                if quiet.start == quiet.end {
+                    return Err(format!("{}: start e end devem ser diferentes", key));
+                }
+                if let Some(offset) = &quiet.utc_offset
+                    && offset.parse::<FixedOffset>().is_err()
+                {
+                    return Err(format!(
+                        "{}.utc_offset: deslocamento inválido `{}` (ex: -03:00)",
+                        key, offset
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rota de notificação: webhook que recebe `POST` JSON.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationRoute {
+    pub name: String,
+    pub url: String,
+    /// Severidade mínima do outage para a rota ser acionada.
+    #[serde(default = "default_route_min_severity")]
+    pub min_severity: Severity,
+    /// Tipos de evento notificados (`outage`, `partial`, `degradation`).
+    #[serde(default = "default_route_event_types")]
+    pub event_types: Vec<OutageEventType>,
+    /// Horário silencioso: eventos que não são outage ficam retidos e vão
+    /// num resumo ao fim da janela.
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Janela diária de silêncio de uma rota (ex: 22:00 às 07:00).
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuietHours {
+    /// Início, `HH:MM`.
+    pub start: String,
+    /// Fim, `HH:MM`; antes do início = a janela atravessa a meia-noite.
+    pub end: String,
+    /// Dias em que a janela começa (ex: `["sat", "sun"]`); vazio = todos.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Deslocamento fixo de UTC (ex: `-03:00`) no lugar de `report_timezone`.
+    pub utc_offset: Option<String>,
+}
+
+impl QuietHours {
+    /// `now` cai dentro da janela, nos horários de `utc_offset` ou, sem ele,
+    /// de `zone`. Horários inválidos (barrados na validação da configuração)
+    /// nunca silenciam.
+    pub fn contains(&self, now: DateTime<Utc>, zone: Zone) -> bool {
+        let (Some(start), Some(end)) =
+            (parse_time_of_day(&self.start), parse_time_of_day(&self.end))
+        else {
+            return false;
+        };
+        let local = Zone::or(self.utc_offset.as_deref(), zone).local(now);
+        let time = local.time();
+        let starts_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        if start < end {
+            starts_on(local.weekday()) && time >= start && time < end
+        } else {
+            // Janela que atravessa a meia-noite: a parte da madrugada pertence
+            // à janela aberta na véspera
+            (time >= start && starts_on(local.weekday()))
+                || (time < end && starts_on((local - Duration::days(1)).weekday()))
+        }
+    }
+}
+
+fn parse_time_of_day(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+/// Configuração do sink InfluxDB/VictoriaMetrics (HTTP write API).
+#[derive(Debug, Clone, Deserialize)]
+pub struct InfluxConfig {
+    /// URL completa de escrita, incluindo parâmetros
+    /// (ex: `http://influx:8086/api/v2/write?org=isp&bucket=rede&precision=ns`
+    /// ou `http://victoria:8428/write`).
+    pub url: String,
+    /// Token enviado como `Authorization: Token <token>` (opcional).
+    pub token: Option<String>,
+    /// Nome da measurement gravada.
+    #[serde(default = "default_influx_measurement")]
+    pub measurement: String,
+    /// Timeout da requisição de escrita em segundos.
+    #[serde(default = "default_influx_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Configuração do sink Prometheus remote-write (Mimir, Thanos, VictoriaMetrics).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteWriteConfig {
+    /// URL do endpoint (ex: `http://mimir:9009/api/v1/push`).
+    pub url: String,
+    /// Usuário da autenticação HTTP basic (opcional).
+    pub username: Option<String>,
+    /// Senha da autenticação HTTP basic (exige `username`).
+    pub password: Option<String>,
+    /// Prefixo do nome das séries (`<prefixo>_up`, ...).
+    #[serde(default = "default_remote_write_prefix")]
+    pub metric_prefix: String,
+    /// Rótulos fixos adicionados a todas as séries (ex: `cluster`).
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Timeout da requisição de escrita em segundos.
+    #[serde(default = "default_influx_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Backend de streaming de eventos.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamingBackend {
+    /// Servidor NATS (protocolo core), ex: `nats://localhost:4222`.
+    Nats,
+    /// Kafka via Confluent REST Proxy, ex: `http://rest-proxy:8082`.
+    KafkaRest,
+}
+
+/// Configuração do publicador de eventos.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamingConfig {
+    pub backend: StreamingBackend,
+    /// Endereço do servidor NATS ou URL base do REST Proxy.
+    pub url: String,
+    /// Prefixo dos subjects/tópicos (`<prefixo>.metrics` e `<prefixo>.outages`).
+    #[serde(default = "default_streaming_prefix")]
+    pub subject_prefix: String,
+}
+
+/// Tipo de um sink de `[[sinks]]`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    /// Uma linha JSON por métrica/outage na saída padrão.
+    Stdout,
+    /// Linhas JSON acrescentadas a um arquivo.
+    File,
+    /// `POST` JSON com as métricas de cada ciclo.
+    Webhook,
+}
+
+impl SinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SinkKind::Stdout => "stdout",
+            SinkKind::File => "file",
+            SinkKind::Webhook => "webhook",
+        }
+    }
+}
+
+/// Sink adicional de métricas e outages.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SinkConfig {
+    #[serde(rename = "type")]
+    pub kind: SinkKind,
+    /// Nome usado nos logs (padrão: o tipo).
+    pub name: Option<String>,
+    /// Arquivo de destino (`file`).
+    pub path: Option<String>,
+    /// URL do `POST` (`webhook`).
+    pub url: Option<String>,
+    /// Timeout da requisição em segundos (`webhook`).
+    #[serde(default = "default_influx_timeout")]
+    pub timeout_secs: u64,
+    /// Também envia aberturas e encerramentos de outages.
+    #[serde(default = "default_true")]
+    pub outages: bool,
+}
+
+impl SinkConfig {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.kind.as_str())
+    }
+}
+
+/// Configuração da pseudonimização (mapeamento HMAC consistente).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnonymizationConfig {
+    /// Chave secreta do HMAC; a mesma chave gera sempre os mesmos pseudônimos.
+    pub key: String,
+    /// Aplica na página de status.
+    #[serde(default = "default_true")]
+    pub status_page: bool,
+    /// Aplica nos caminhos de exportação.
+    #[serde(default = "default_true")]
+    pub exports: bool,
+}
+
+/// Modo de exposição da API HTTP.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiMode {
+    /// Somente estatísticas agregadas (sem IPs nem métricas brutas).
+    Public,
+    /// API completa para uso interno.
+    Full,
+}
+
+/// Configuração da API HTTP.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiConfig {
+    /// Endereço de escuta (ex: `0.0.0.0:8080`).
+    pub bind: String,
+    #[serde(default = "default_api_mode")]
+    pub mode: ApiMode,
+    /// Grupos agregados com menos targets que isso são suprimidos no modo público.
+    #[serde(default = "default_api_min_group_size")]
+    pub min_group_size: usize,
+    /// Epsilon da privacidade diferencial (ruído de Laplace) de cada grupo das
+    /// estatísticas públicas, dividido entre disponibilidade, p50, p95 e a
+    /// contagem de targets; ausente = sem ruído.
+    pub noise_epsilon: Option<f64>,
+    /// Token `admin` sem nome (equivale a um `[[api.tokens]]` com
+    /// `scope = "admin"`).
+    pub write_token: Option<String>,
+    /// Tokens aceitos como `Authorization: Bearer <token>`; com algum, as
+    /// rotas do modo `full` deixam de ser abertas.
+    #[serde(default)]
+    pub tokens: Vec<ApiToken>,
+    /// IPs ou blocos CIDR que podem conectar; vazio = qualquer um.
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+    /// Loga cada pedido (método, caminho, status, IP e token).
+    #[serde(default)]
+    pub log_requests: bool,
+}
+
+/// Token estático da API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiToken {
+    /// Identifica o token nos logs (ex: `grafana`).
+    pub name: String,
+    pub token: String,
+    #[serde(default = "default_token_scope")]
+    pub scope: TokenScope,
+}
+
+/// Escopo de um token: `read` consulta, `admin` também escreve.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Admin,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Admin => "admin",
+        }
+    }
+}
+
+/// Configuração do modo frota: envio periódico de resumos agregados a um
+/// agregador central para comparação colaborativa de provedores.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetConfig {
+    /// Endpoint do agregador que recebe o resumo via `POST` JSON.
+    pub url: String,
+    /// Token enviado como `Authorization: Bearer <token>` (opcional).
+    pub token: Option<String>,
+    /// Identificador opaco desta instância no agregador (opcional).
+    pub reporter_id: Option<String>,
+    /// Intervalo entre envios em segundos.
+    #[serde(default = "default_fleet_interval")]
+    pub interval_secs: u64,
+    /// Janela, em horas, coberta por cada resumo.
+    #[serde(default = "default_fleet_window_hours")]
+    pub window_hours: i64,
+    /// Grupos com menos probes distintos que isso são suprimidos do resumo.
+    #[serde(default = "default_api_min_group_size")]
+    pub min_group_size: usize,
+    /// Epsilon do ruído de Laplace aplicado à disponibilidade, aos
+    /// percentis e à contagem de targets (opcional).
+    pub noise_epsilon: Option<f64>,
+    /// Timeout da requisição em segundos.
+    #[serde(default = "default_fleet_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Configuração da descoberta automática de targets.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryConfig {
+    /// Cadastra o gateway padrão (IPv4/IPv6) da tabela de rotas.
+    #[serde(default = "default_true")]
+    pub gateway: bool,
+    /// Cadastra os servidores DNS listados em `resolv_conf_path`.
+    #[serde(default = "default_true")]
+    pub resolvers: bool,
+    /// Arquivo de onde os servidores DNS são lidos.
+    #[serde(default = "default_resolv_conf_path")]
+    pub resolv_conf_path: String,
+    /// IPs anycast bem conhecidos a cadastrar.
+    #[serde(default = "crate::discovery::default_anycast")]
+    pub anycast: Vec<IpAddr>,
+}
+
+/// Configuração da medição de assimetria de latência via TCP.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AsymmetryConfig {
+    /// Porta TCP usada na medição (o payload é uma requisição HTTP `HEAD`).
+    #[serde(default = "default_asymmetry_port")]
+    pub port: u16,
+    /// Conexões por target em cada medição (vale o menor RTT).
+    #[serde(default = "default_asymmetry_samples")]
+    pub samples: usize,
+    /// Tamanho do segmento de dados enviado após o handshake, em bytes.
+    #[serde(default = "default_asymmetry_payload_bytes")]
+    pub payload_bytes: usize,
+    /// Executa a medição a cada N ciclos de monitoramento.
+    #[serde(default = "default_asymmetry_interval_cycles")]
+    pub interval_cycles: i32,
+    /// Timeout de cada etapa (connect e ACK dos dados) em segundos.
+    #[serde(default = "default_asymmetry_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Teste de banda periódico para medir a latência sob carga (bufferbloat).
+#[derive(Debug, Clone, Deserialize)]
+pub struct BufferbloatConfig {
+    /// Arquivo grande baixado para saturar o link durante o teste.
+    pub load_url: String,
+    /// Executa o teste a cada N ciclos de monitoramento.
+    #[serde(default = "default_bufferbloat_interval_cycles")]
+    pub interval_cycles: i32,
+    /// Duração do download em segundos.
+    #[serde(default = "default_bufferbloat_duration")]
+    pub duration_secs: u64,
+    /// Segundos de download antes de os checks do ciclo começarem.
+    #[serde(default = "default_bufferbloat_ramp_up")]
+    pub ramp_up_secs: u64,
+    /// Janela (segundos) do RTT mínimo de referência de cada target.
+    #[serde(default = "default_bufferbloat_min_rtt_window")]
+    pub min_rtt_window_secs: u64,
+}
+
+/// Traceroute periódico (UDP com TTL crescente) até cada target.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TracerouteConfig {
+    /// Executa o traceroute a cada N ciclos de monitoramento.
+    #[serde(default = "default_traceroute_interval_cycles")]
+    pub interval_cycles: i32,
+    /// TTL máximo.
+    #[serde(default = "default_traceroute_max_hops")]
+    pub max_hops: u8,
+    /// Sondas por salto (a perda do salto vem daqui).
+    #[serde(default = "default_traceroute_probes_per_hop")]
+    pub probes_per_hop: u8,
+    /// Espera por resposta de cada sonda, em milissegundos.
+    #[serde(default = "default_traceroute_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Porta UDP de destino do primeiro salto (incrementada a cada TTL).
+    #[serde(default = "default_traceroute_port")]
+    pub port: u16,
+    /// Resolve o ASN de cada salto (DNS da Team Cymru).
+    #[serde(default = "default_true")]
+    pub asn_lookup: bool,
+}
+
+/// Requisição de teste da detecção de captive portal.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaptivePortalConfig {
+    /// URL `http` que responde `expected_status` com internet de verdade.
+    #[serde(default = "default_captive_portal_url")]
+    pub url: String,
+    #[serde(default = "default_captive_portal_status")]
+    pub expected_status: u16,
+    #[serde(default = "default_captive_portal_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Sondas do diagnóstico de falha local: traceroute curto até o primeiro
+/// target afetado e ping ao gateway padrão.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosisConfig {
+    /// TTL máximo (saltos depois do gateway que contam como upstream).
+    #[serde(default = "default_diagnosis_max_hops")]
+    pub max_hops: u8,
+    #[serde(default = "default_diagnosis_probes_per_hop")]
+    pub probes_per_hop: u8,
+    /// Espera por resposta de cada sonda, em milissegundos.
+    #[serde(default = "default_traceroute_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Porta UDP de destino do primeiro salto (incrementada a cada TTL).
+    #[serde(default = "default_traceroute_port")]
+    pub port: u16,
+}
+
+fn default_diagnosis_max_hops() -> u8 {
+    4
+}
+
+fn default_diagnosis_probes_per_hop() -> u8 {
+    2
+}
+
+/// Agrupamento dos targets no limite de taxa.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitScope {
+    /// Bloco do endereço (`/ipv4_prefix_len` ou `/ipv6_prefix_len`).
+    Prefix,
+    /// ASN do target; targets sem ASN caem no bloco do endereço.
+    Asn,
+}
+
+/// Orçamento de pacotes por segundo por rede de destino, somado entre
+/// todos os checks em andamento, para que centenas de targets num mesmo
+/// provedor não pareçam uma varredura.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    pub packets_per_second: f64,
+    /// Pacotes liberados de uma vez com o orçamento acumulado (padrão:
+    /// `packets_per_second`, no mínimo 1).
+    pub burst: Option<f64>,
+    #[serde(default = "default_rate_limit_scope")]
+    pub scope: RateLimitScope,
+    #[serde(default = "default_rate_limit_ipv4_prefix_len")]
+    pub ipv4_prefix_len: u8,
+    #[serde(default = "default_rate_limit_ipv6_prefix_len")]
+    pub ipv6_prefix_len: u8,
+}
+
+fn default_rate_limit_scope() -> RateLimitScope {
+    RateLimitScope::Prefix
+}
+
+fn default_rate_limit_ipv4_prefix_len() -> u8 {
+    24
+}
+
+fn default_rate_limit_ipv6_prefix_len() -> u8 {
+    48
+}
+
+/// Fonte das consultas de ASN e provedor.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnrichmentSource {
+    /// DNS da Team Cymru (`origin.asn.cymru.com`).
+    Cymru,
+    /// API HTTP do RIPEstat (`stat.ripe.net`).
+    RipeStat,
+}
+
+/// Enriquecimento de ASN e provedor dos targets.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnrichmentConfig {
+    #[serde(default = "default_enrichment_source")]
+    pub source: EnrichmentSource,
+    /// Intervalo entre passadas sobre os targets, em segundos.
+    #[serde(default = "default_enrichment_interval")]
+    pub interval_secs: u64,
+    /// Sobrescreve valores preenchidos à mão; por padrão só completa os
+    /// campos vazios.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Localização GeoIP de targets e probes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeoIpConfig {
+    /// Arquivo `GeoLite2-City.mmdb` (relido a cada passada, para pegar as
+    /// atualizações semanais da MaxMind sem reiniciar).
+    pub database_path: String,
+    /// Intervalo entre passadas sobre targets e probes, em segundos.
+    #[serde(default = "default_enrichment_interval")]
+    pub interval_secs: u64,
+    /// Relocaliza quem já tem localização; por padrão só completa os vazios.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Verificação de rotas BGP dos outages.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BgpConfig {
+    /// Intervalo entre verificações dos outages abertos, em segundos.
+    #[serde(default = "default_bgp_interval")]
+    pub interval_secs: u64,
+    /// Quanto antes do início do outage as atualizações BGP são consideradas
+    /// (a retirada costuma preceder a detecção), em segundos.
+    #[serde(default = "default_bgp_lookback")]
+    pub lookback_secs: u64,
+    /// Porcentagem dos peers do RIS que precisam retirar o prefixo para que
+    /// a retirada conte (uma sessão BGP reiniciando não basta).
+    #[serde(default = "default_bgp_withdrawal_percent")]
+    pub withdrawal_percent: f64,
+}
+
+/// Consulta SNMP dos dispositivos de rede.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnmpConfig {
+    /// Intervalo entre consultas, em segundos.
+    #[serde(default = "default_snmp_interval")]
+    pub interval_secs: u64,
+    /// Espera por cada resposta, em segundos.
+    #[serde(default = "default_snmp_timeout")]
+    pub timeout_secs: u64,
+    /// Reenvios de um pedido sem resposta.
+    #[serde(default = "default_snmp_retries")]
+    pub retries: u32,
+    /// Dispositivos consultados.
+    #[serde(default)]
+    pub devices: Vec<SnmpDeviceConfig>,
+}
+
+/// Dispositivo consultado por SNMPv2c.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnmpDeviceConfig {
+    /// Nome gravado em `device_metrics.device`.
+    pub name: String,
+    pub address: IpAddr,
+    #[serde(default = "default_snmp_port")]
+    pub port: u16,
+    #[serde(default = "default_snmp_community")]
+    pub community: String,
+    /// `ifIndex` das interfaces consultadas.
+    pub interfaces: Vec<u32>,
+}
+
+/// Consulta ARP/ND dos gateways do segmento local.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NeighborConfig {
+    /// Intervalo entre consultas, em segundos.
+    #[serde(default = "default_neighbor_interval")]
+    pub interval_secs: u64,
+    /// Espera pelas respostas de cada tentativa, em milissegundos.
+    #[serde(default = "default_neighbor_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Tentativas até o gateway responder.
+    #[serde(default = "default_neighbor_attempts")]
+    pub attempts: u32,
+    /// Consulta também o gateway IPv6 (Neighbor Solicitation).
+    #[serde(default = "default_true")]
+    pub ipv6: bool,
+}
+
+/// Escuta de mudanças de rede do host.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetwatchConfig {
+    /// Interfaces observadas; vazio = todas, exceto loopback.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+}
+
+/// Parâmetros alternativos de consenso avaliados em modo sombra.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShadowConsensusConfig {
+    /// Nome identificador da instância (gravado junto aos outages simulados).
+    pub name: String,
+    /// Threshold de falhas da instância sombra.
+    pub fail_threshold: usize,
+    /// Nível de consenso da instância sombra.
+    pub consensus: usize,
+    /// Modo percentual da instância sombra (ver `consensus_fail_percent`).
+    #[serde(default)]
+    pub fail_percent: Option<f64>,
+}
+
+/// Inventário declarativo (ex: ConfigMap montado no pod).
+#[derive(Debug, Clone, Deserialize)]
+pub struct InventoryConfig {
+    /// Arquivo YAML ou JSON com `spec.probes` e `spec.targets`.
+    pub path: String,
+}
+
+/// Tenant: grupo isolado de schedulers com os targets marcados com o seu
+/// nome. Parâmetros ausentes herdam os globais.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantConfig {
+    /// Nome do tenant (coluna `tenant` dos targets).
+    pub name: String,
+    pub fail_threshold: Option<usize>,
+    pub consensus: Option<usize>,
+    pub consensus_fail_percent: Option<f64>,
+    /// Rotas de notificação próprias (substituem as globais).
+    pub notifications: Option<NotificationsConfig>,
+}
+
+/// Perfil de um probe, identificado por `probe_id` ou `location`; os campos
+/// ausentes seguem a configuração global (ou a do tenant).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProbeProfileConfig {
+    pub probe_id: Option<i32>,
+    /// Localização do probe (`monitoring_probes.location`).
+    pub location: Option<String>,
+    pub cycle_interval_secs: Option<u64>,
+    pub ping_count: Option<usize>,
+    pub timeout_secs: Option<u64>,
+    pub fail_threshold: Option<usize>,
+    pub consensus: Option<usize>,
+    /// Targets monitorados pelo probe, por nome ou endereço; vazio = todos.
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Origem de todos os checks do probe (substitui `checks.source` e
+    /// `checks.sources`), para medir um uplink específico.
+    pub source: Option<SourceConfig>,
+}
+
+impl ProbeProfileConfig {
+    /// Identificação do perfil nas mensagens.
+    pub fn label(&self) -> String {
+        match (&self.location, self.probe_id) {
+            (Some(location), _) => location.clone(),
+            (None, Some(id)) => format!("probe {}", id),
+            (None, None) => "?".to_string(),
+        }
+    }
+}
+
+/// Formato de saída da página de status.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusPageFormat {
+    Html,
+    Json,
+}
+
+/// Configuração do gerador da página pública de status.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusPageConfig {
+    /// Caminho do arquivo gerado (ex: `/var/www/status/index.html`).
+    pub output_path: String,
+    /// Formato da página gerada.
+    #[serde(default = "default_status_page_format")]
+    pub format: StatusPageFormat,
+    /// Intervalo entre regenerações em segundos.
+    #[serde(default = "default_status_page_interval")]
+    pub interval_secs: u64,
+    /// Quantidade de outages recentes exibidos.
+    #[serde(default = "default_status_page_recent_outages")]
+    pub recent_outages: i64,
+    /// Título exibido no topo da página.
+    #[serde(default = "default_status_page_title")]
+    pub title: String,
+}
+
+fn default_consensus_history_max_bytes() -> usize {
+    crate::consensus::DEFAULT_HISTORY_MAX_BYTES
+}
+
+fn default_skip_missed_cycles() -> bool {
+    true
+}
+
+fn default_cycle_budget_percent() -> f64 {
+    80.0
+}
+
+fn default_consensus_weight() -> f64 {
+    1.0
+}
+
+fn default_outage_recovery_cycles() -> usize {
+    1
+}
+
+fn default_partial_outage_recovery_cycles() -> usize {
+    2
+}
+
+fn default_outage_resume_max_gap() -> u64 {
+    600
+}
+
+fn default_warmup_seed_max_age() -> u64 {
+    300
+}
+
+fn default_aggregate_refresh_interval() -> u64 {
+    300
+}
+
+fn default_influx_measurement() -> String {
+    "connectivity".to_string()
+}
+
+fn default_influx_timeout() -> u64 {
+    5
+}
+
+fn default_remote_write_prefix() -> String {
+    "monitoramento".to_string()
+}
+
+fn default_streaming_prefix() -> String {
+    "monitoramento".to_string()
+}
+
+fn default_api_mode() -> ApiMode {
+    ApiMode::Public
+}
+
+fn default_token_scope() -> TokenScope {
+    TokenScope::Read
+}
+
+fn default_api_min_group_size() -> usize {
+    3
+}
+
+fn default_fleet_interval() -> u64 {
+    3600
+}
+
+fn default_fleet_window_hours() -> i64 {
+    24
+}
+
+fn default_fleet_timeout() -> u64 {
+    10
+}
+
+fn default_resolv_conf_path() -> String {
+    "/etc/resolv.conf".to_string()
+}
+
+fn default_asymmetry_port() -> u16 {
+    80
+}
+
+fn default_asymmetry_samples() -> usize {
+    3
+}
+
+fn default_asymmetry_payload_bytes() -> usize {
+    1200
+}
+
+fn default_asymmetry_interval_cycles() -> i32 {
+    10
+}
+
+fn default_asymmetry_timeout() -> u64 {
+    3
+}
+
+fn default_bufferbloat_interval_cycles() -> i32 {
+    60
+}
+
+fn default_bufferbloat_duration() -> u64 {
+    10
+}
+
+fn default_bufferbloat_ramp_up() -> u64 {
+    2
+}
+
+fn default_bufferbloat_min_rtt_window() -> u64 {
+    3600
+}
+
+fn default_traceroute_interval_cycles() -> i32 {
+    30
+}
+
+fn default_traceroute_max_hops() -> u8 {
+    30
+}
+
+fn default_traceroute_probes_per_hop() -> u8 {
+    3
+}
+
+fn default_traceroute_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_traceroute_port() -> u16 {
+    33434
+}
+
+fn default_enrichment_source() -> EnrichmentSource {
+    EnrichmentSource::Cymru
+}
+
+fn default_enrichment_interval() -> u64 {
+    86_400
+}
+
+fn default_bgp_interval() -> u64 {
+    300
+}
+
+fn default_bgp_lookback() -> u64 {
+    900
+}
+
+fn default_bgp_withdrawal_percent() -> f64 {
+    50.0
+}
+
+fn default_captive_portal_url() -> String {
+    "http://connectivitycheck.gstatic.com/generate_204".to_string()
+}
+
+fn default_captive_portal_status() -> u16 {
+    204
+}
+
+fn default_captive_portal_timeout() -> u64 {
+    5
+}
+
+fn default_neighbor_interval() -> u64 {
+    30
+}
+
+fn default_neighbor_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_neighbor_attempts() -> u32 {
+    3
+}
+
+fn default_snmp_interval() -> u64 {
+    60
+}
+
+fn default_snmp_timeout() -> u64 {
+    2
+}
+
+fn default_snmp_retries() -> u32 {
+    1
+}
+
+fn default_snmp_port() -> u16 {
+    161
+}
+
+fn default_snmp_community() -> String {
+    "public".to_string()
+}
+
+fn default_check_tcp_port() -> u16 {
+    443
+}
+
+fn default_check_http_port() -> u16 {
+    80
+}
+
+fn default_check_http_path() -> String {
+    "/".to_string()
+}
+
+fn default_check_http_max_redirects() -> usize {
+    5
+}
+
+fn default_check_dns_query() -> String {
+    "example.com".to_string()
+}
+
+fn default_check_tls_port() -> u16 {
+    443
+}
+
+fn default_check_tls_expiry_warning_days() -> u32 {
+    14
+}
+
+fn default_check_udp_port() -> u16 {
+    53
+}
+
+fn default_check_sip_port() -> u16 {
+    5060
+}
+
+fn default_anomaly_alpha() -> f64 {
+    0.1
+}
+
+fn default_anomaly_sigmas() -> f64 {
+    3.0
+}
+
+fn default_anomaly_min_samples() -> u64 {
+    30
+}
+
+fn default_anomaly_min_delta_ms() -> f64 {
+    5.0
+}
+
+fn default_anomaly_min_loss_delta() -> f64 {
+    10.0
+}
+
+fn default_host_max_load_per_cpu() -> f64 {
+    2.0
+}
+
+fn default_host_min_mem_available() -> f64 {
+    5.0
+}
+
+fn default_host_max_mem_pressure() -> f64 {
+    20.0
+}
+
+fn default_host_disk_path() -> String {
+    "/".to_string()
+}
+
+fn default_host_min_disk_free() -> f64 {
+    5.0
+}
+
+fn default_host_max_fd_usage() -> f64 {
+    90.0
+}
+
+fn default_host_heavy_max_load_per_cpu() -> f64 {
+    1.0
+}
+
+fn default_persistence_queue_capacity() -> usize {
+    10_000
+}
+
+fn default_persistence_batch_size() -> usize {
+    500
+}
+
+fn default_persistence_spool_path() -> String {
+    "spool/persistence.jsonl".to_string()
+}
+
+fn default_persistence_spool_max_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_chaos_latency_factor() -> f64 {
+    3.0
+}
+
+fn default_heatmap_buckets() -> Vec<f64> {
+    vec![
+        1.0, 2.0, 5.0, 10.0, 15.0, 20.0, 30.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0, 500.0,
+        1000.0, 2000.0,
+    ]
+}
+
+fn default_heatmap_interval() -> u64 {
+    300
+}
+
+fn default_heatmap_backfill_hours() -> u32 {
+    24
+}
+
+fn default_heatmap_retention_days() -> u32 {
+    400
+}
+
+fn default_consensus_decisions_retention_days() -> u32 {
+    7
+}
+
+fn default_timescale_chunk_interval_hours() -> u32 {
+    24
+}
+
+fn default_timescale_compress_after_days() -> u32 {
+    7
+}
+
+fn default_escalation_major_after() -> i64 {
+    10 * 60
+}
+
+fn default_escalation_critical_after() -> i64 {
+    30 * 60
+}
+
+fn default_route_min_severity() -> Severity {
+    Severity::Minor
+}
+
+fn default_periodic_report_hour() -> u32 {
+    8
+}
+
+fn default_periodic_report_weekday() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_periodic_report_day_of_month() -> u32 {
+    1
+}
+
+fn default_periodic_report_worst_targets() -> usize {
+    5
+}
+
+fn default_route_event_types() -> Vec<OutageEventType> {
+    vec![OutageEventType::Outage]
+}
+
+fn default_report_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_status_page_format() -> StatusPageFormat {
+    StatusPageFormat::Html
+}
+
+fn default_status_page_interval() -> u64 {
+    60
+}
+
+fn default_status_page_recent_outages() -> i64 {
+    10
+}
+
+fn default_status_page_title() -> String {
+    "Status da Rede".to_string()
+}
+
+/// Nome de métrica/rótulo Prometheus: `[a-zA-Z_][a-zA-Z0-9_]*`.
+fn is_prometheus_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Prefixo das variáveis de ambiente que sobrescrevem a configuração
+/// (ex: `MONITOR_PING_COUNT`, `MONITOR_STATUS_PAGE__OUTPUT_PATH`).
+pub const ENV_PREFIX: &str = "MONITOR";
+
+/// Arquivo de configuração padrão (sem extensão: `config.toml`, `config.yaml`, ...).
+pub const DEFAULT_CONFIG_FILE: &str = "config";
+
 impl Config {
-    pub fn load() -> anyhow::Result<Self> {
-        let settings = config_crate::Config::builder()
-            .add_source(config_crate::File::with_name("config"))
-            .build()?;
-        let config: Config = settings.try_deserialize()?; // CORRETO!
+    /// Fuso de `report_timezone` (UTC se inválido, o que a validação barra).
+    pub fn report_zone(&self) -> Zone {
+        Zone::or(Some(&self.report_timezone), Zone::default())
+    }
+
+    /// Carrega a configuração em camadas, da menor para a maior precedência:
+    ///
+    /// 1. valores padrão embutidos;
+    /// 2. arquivo de configuração (`path`, ou `config.*` no diretório atual;
+    ///    obrigatório apenas quando `path` é informado);
+    /// 3. variáveis de ambiente `MONITOR_*`, com `__` separando seções
+    ///    (ex: `MONITOR_API__BIND=0.0.0.0:8080`).
+    ///
+    /// O resultado é validado com [`Config::validate`], e as chaves que
+    /// nenhum campo lê são avisadas no log (ver [`unknown_keys`]).
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let settings = Self::settings(path)?;
+        for key in unknown_keys(&settings) {
+            warn!("⚠️ Configuração: chave desconhecida `{}` (ignorada)", key);
+        }
+        let config = Self::from_settings(&settings)?;
+        config
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Configuração inválida: {}", e))?;
         Ok(config)
     }
-    /// Validação customizada (opcional)
+
+    /// Fontes da configuração (padrões, arquivo e ambiente) combinadas, antes
+    /// da desserialização.
+    pub fn settings(path: Option<&str>) -> anyhow::Result<config_crate::Config> {
+        let file = match path {
+            Some(path) => config_crate::File::with_name(path).required(true),
+            None => config_crate::File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+        let settings = Self::defaults()?
+            .add_source(file)
+            .add_source(
+                config_crate::Environment::with_prefix(ENV_PREFIX)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("discovery.anycast"),
+            )
+            .build()?;
+        Ok(settings)
+    }
+
+    /// Valores padrão embutidos, a primeira camada de [`Config::settings`].
+    fn defaults() -> Result<ConfigBuilder<DefaultState>, config_crate::ConfigError> {
+        config_crate::Config::builder()
+            .set_default("ping_count", 3)?
+            .set_default("timeout_secs", 2)?
+            .set_default("fail_threshold", 3)?
+            .set_default("consensus", 3)?
+            .set_default("cycle_interval_secs", 60)
+    }
+
+    /// Fontes dos testes: os padrões embutidos e `toml`, sem arquivo nem
+    /// ambiente.
+    #[cfg(test)]
+    pub fn test_settings(toml: &str) -> config_crate::Config {
+        Self::defaults()
+            .and_then(|builder| {
+                builder
+                    .add_source(config_crate::File::from_str(
+                        toml,
+                        config_crate::FileFormat::Toml,
+                    ))
+                    .build()
+            })
+            .expect("configuração de teste")
+    }
+
+    /// Configuração dos testes (ver [`Config::test_settings`]).
+    #[cfg(test)]
+    pub fn from_toml(toml: &str) -> Self {
+        Self::from_settings(&Self::test_settings(toml)).expect("configuração de teste")
+    }
+
+    /// Desserializa as fontes combinadas, sem validar os valores.
+    pub fn from_settings(settings: &config_crate::Config) -> anyhow::Result<Self> {
+        settings
+            .clone()
+            .try_deserialize()
+            .map_err(|e| anyhow::anyhow!("Configuração inválida: {}", e))
+    }
+
+    /// Validação dos valores carregados: as violações, uma por linha.
     pub fn validate(&self) -> Result<(), String> {
-        if self.ping_count == 0 {
-            return Err("ping_count deve ser maior que zero".into());
+        let violations = self.violations();
+        if violations.is_empty() {
+            return Ok(());
         }
-        if self.timeout_secs == 0 {
-            return Err("timeout_secs deve ser maior que zero".into());
+        Err(format!(
+            "{} problema(s)\n  - {}",
+            violations.len(),
+            violations.join("\n  - ")
+        ))
+    }
+
+    /// Todas as violações dos valores carregados, cada uma começando pela
+    /// chave problemática (ex: `api.min_group_size: deve ser maior que
+    /// zero`). Cada seção (uma chave, uma tabela ou uma lista) para na sua
+    /// primeira violação.
+    pub fn violations(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        section(&mut errors, || {
+            let positive = [
+                ("ping_count", self.ping_count as u64),
+                ("timeout_secs", self.timeout_secs),
+                ("fail_threshold", self.fail_threshold as u64),
+                ("consensus", self.consensus as u64),
+                ("cycle_interval_secs", self.cycle_interval_secs),
+                ("outage_recovery_cycles", self.outage_recovery_cycles as u64),
+                (
+                    "partial_outage_recovery_cycles",
+                    self.partial_outage_recovery_cycles as u64,
+                ),
+                (
+                    "outage_resume_max_gap_secs",
+                    self.outage_resume_max_gap_secs,
+                ),
+                (
+                    "consensus_history_max_bytes",
+                    self.consensus_history_max_bytes as u64,
+                ),
+                (
+                    "persistence.queue_capacity",
+                    self.persistence.queue_capacity as u64,
+                ),
+                ("persistence.batch_size", self.persistence.batch_size as u64),
+            ];
+            if let Some((key, _)) = positive.iter().find(|(_, value)| *value == 0) {
+                return Err(format!("{}: deve ser maior que zero", key));
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if self.database_url.trim().is_empty() {
+                return Err("database_url: não pode ser vazio".into());
+            }
+            if self
+                .database_read_url
+                .as_ref()
+                .is_some_and(|url| url.trim().is_empty())
+            {
+                return Err("database_read_url: não pode ser vazio".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(api) = &self.api {
+                if api.min_group_size == 0 {
+                    return Err("api.min_group_size: deve ser maior que zero".into());
+                }
+                if api.noise_epsilon.is_some_and(|e| e <= 0.0) {
+                    return Err("api.noise_epsilon: deve ser maior que zero".into());
+                }
+                if let Some(token) = &api.write_token {
+                    if token.trim().is_empty() {
+                        return Err("api.write_token: não pode ser vazio".into());
+                    }
+                    if api.mode != ApiMode::Full {
+                        return Err("api.write_token: exige api.mode = \"full\"".into());
+                    }
+                }
+                if !api.tokens.is_empty() && api.mode != ApiMode::Full {
+                    return Err("api.tokens: exige api.mode = \"full\"".into());
+                }
+                let mut token_names: Vec<&str> = Vec::new();
+                for token in &api.tokens {
+                    if token.name.trim().is_empty() {
+                        return Err("api.tokens.name: não pode ser vazio".into());
+                    }
+                    if token_names.contains(&token.name.as_str()) {
+                        return Err(format!("api.tokens.name: `{}` duplicado", token.name));
+                    }
+                    token_names.push(&token.name);
+                    if token.token.trim().is_empty() {
+                        return Err(format!(
+                            "api.tokens.token ({}): não pode ser vazio",
+                            token.name
+                        ));
+                    }
+                }
+                for entry in &api.allowed_ips {
+                    crate::auth::IpBlock::parse(entry)
+                        .map_err(|e| format!("api.allowed_ips: {}", e))?;
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(fleet) = &self.fleet {
+                if fleet.window_hours <= 0 {
+                    return Err("fleet.window_hours: deve ser maior que zero".into());
+                }
+                if fleet.noise_epsilon.is_some_and(|e| e <= 0.0) {
+                    return Err("fleet.noise_epsilon: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(asymmetry) = &self.asymmetry {
+                if asymmetry.samples == 0 {
+                    return Err("asymmetry.samples: deve ser maior que zero".into());
+                }
+                if asymmetry.interval_cycles <= 0 {
+                    return Err("asymmetry.interval_cycles: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(chaos) = &self.chaos {
+                for (key, probability) in [
+                    ("chaos.timeout_probability", chaos.timeout_probability),
+                    ("chaos.latency_probability", chaos.latency_probability),
+                ] {
+                    if !(0.0..=1.0).contains(&probability) {
+                        return Err(format!("{}: deve estar entre 0 e 1", key));
+                    }
+                }
+                if chaos.latency_factor < 1.0 {
+                    return Err("chaos.latency_factor: deve ser pelo menos 1".into());
+                }
+                if chaos.latency_add_ms < 0.0 {
+                    return Err("chaos.latency_add_ms: não pode ser negativo".into());
+                }
+                for down in &chaos.down {
+                    match (down.from, down.to, down.every_secs, down.duration_secs) {
+                        (Some(from), Some(to), None, None) if from < to => {}
+                        (Some(_), Some(_), None, None) => {
+                            return Err("chaos.down: from deve ser anterior a to".into());
+                        }
+                        (None, None, Some(every), Some(duration))
+                            if duration > 0 && duration < every => {}
+                        (None, None, Some(_), Some(_)) => {
+                            return Err(
+                                "chaos.down: duration_secs deve ser maior que zero e menor que every_secs"
+                                    .into(),
+                            );
+                        }
+                        _ => {
+                            return Err(
+                                "chaos.down: informe from e to, ou every_secs e duration_secs"
+                                    .into(),
+                            );
+                        }
+                    }
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(bufferbloat) = &self.bufferbloat {
+                if bufferbloat.interval_cycles <= 0 {
+                    return Err("bufferbloat.interval_cycles: deve ser maior que zero".into());
+                }
+                if bufferbloat.ramp_up_secs >= bufferbloat.duration_secs {
+                    return Err("bufferbloat.ramp_up_secs: deve ser menor que duration_secs".into());
+                }
+                if bufferbloat.duration_secs >= self.cycle_interval_secs {
+                    return Err(
+                        "bufferbloat.duration_secs: deve ser menor que cycle_interval_secs".into(),
+                    );
+                }
+                if bufferbloat.min_rtt_window_secs == 0 {
+                    return Err("bufferbloat.min_rtt_window_secs: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(traceroute) = &self.traceroute {
+                if traceroute.interval_cycles <= 0 {
+                    return Err("traceroute.interval_cycles: deve ser maior que zero".into());
+                }
+                if !(1..=64).contains(&traceroute.max_hops) {
+                    return Err("traceroute.max_hops: deve estar entre 1 e 64".into());
+                }
+                if traceroute.probes_per_hop == 0 {
+                    return Err("traceroute.probes_per_hop: deve ser maior que zero".into());
+                }
+                if traceroute.timeout_ms == 0 {
+                    return Err("traceroute.timeout_ms: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(enrichment) = &self.enrichment
+                && enrichment.interval_secs == 0
+            {
+                return Err("enrichment.interval_secs: deve ser maior que zero".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(geoip) = &self.geoip {
+                if geoip.database_path.trim().is_empty() {
+                    return Err("geoip.database_path: não pode ser vazio".into());
+                }
+                if geoip.interval_secs == 0 {
+                    return Err("geoip.interval_secs: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(bgp) = &self.bgp {
+                if bgp.interval_secs == 0 {
+                    return Err("bgp.interval_secs: deve ser maior que zero".into());
+                }
+                if !(bgp.withdrawal_percent > 0.0 && bgp.withdrawal_percent <= 100.0) {
+                    return Err("bgp.withdrawal_percent: deve estar em (0, 100]".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(snmp) = &self.snmp {
+                if snmp.interval_secs == 0 {
+                    return Err("snmp.interval_secs: deve ser maior que zero".into());
+                }
+                if snmp.timeout_secs == 0 {
+                    return Err("snmp.timeout_secs: deve ser maior que zero".into());
+                }
+                if snmp.devices.is_empty() {
+                    return Err("snmp.devices: deve ter ao menos um dispositivo".into());
+                }
+                for (i, device) in snmp.devices.iter().enumerate() {
+                    if device.name.trim().is_empty() {
+                        return Err(format!("snmp.devices[{}]: name não pode ser vazio", i));
+                    }
+                    if snmp.devices[..i].iter().any(|d| d.name == device.name) {
+                        return Err(format!(
+                            "snmp.devices[{}]: nome {} repetido",
+                            i, device.name
+                        ));
+                    }
+                    if device.interfaces.is_empty() {
+                        return Err(format!(
+                            "snmp.devices[{}] ({}): interfaces não pode ser vazio",
+                            i, device.name
+                        ));
+                    }
+                    if device.interfaces.contains(&0) {
+                        return Err(format!(
+                            "snmp.devices[{}] ({}): ifIndex deve ser maior que zero",
+                            i, device.name
+                        ));
+                    }
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(captive) = &self.captive_portal {
+                match reqwest::Url::parse(&captive.url) {
+                    Ok(url) if url.scheme() == "http" && url.host_str().is_some() => {}
+                    _ => {
+                        return Err(format!(
+                            "captive_portal.url: `{}` deve ser uma URL http",
+                            captive.url
+                        ));
+                    }
+                }
+                if !(100..=599).contains(&captive.expected_status) {
+                    return Err("captive_portal.expected_status: deve estar em [100, 599]".into());
+                }
+                if captive.timeout_secs == 0 {
+                    return Err("captive_portal.timeout_secs: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(neighbor) = &self.neighbor {
+                if neighbor.interval_secs == 0 {
+                    return Err("neighbor.interval_secs: deve ser maior que zero".into());
+                }
+                if neighbor.timeout_ms == 0 {
+                    return Err("neighbor.timeout_ms: deve ser maior que zero".into());
+                }
+                if neighbor.attempts == 0 {
+                    return Err("neighbor.attempts: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(netwatch) = &self.netwatch
+                && netwatch
+                    .interfaces
+                    .iter()
+                    .any(|name| name.trim().is_empty())
+            {
+                return Err("netwatch.interfaces: nome de interface vazio".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(anomaly) = &self.anomaly {
+                if !(anomaly.alpha > 0.0 && anomaly.alpha <= 1.0) {
+                    return Err("anomaly.alpha: deve estar em (0, 1]".into());
+                }
+                if anomaly.sigmas <= 0.0 {
+                    return Err("anomaly.sigmas: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(decisions) = &self.consensus_decisions
+                && decisions.retention_days == 0
+            {
+                return Err("consensus_decisions.retention_days: deve ser maior que zero".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(timescale) = &self.timescale {
+                if timescale.chunk_interval_hours == 0 {
+                    return Err("timescale.chunk_interval_hours: deve ser maior que zero".into());
+                }
+                if timescale.retention_days > 0
+                    && timescale.compress_after_days >= timescale.retention_days
+                {
+                    return Err(
+                        "timescale.compress_after_days: deve ser menor que retention_days".into(),
+                    );
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(heatmap) = &self.latency_heatmap {
+                if heatmap.buckets_ms.is_empty() {
+                    return Err("latency_heatmap.buckets_ms: não pode ser vazio".into());
+                }
+                if heatmap
+                    .buckets_ms
+                    .iter()
+                    .any(|b| !b.is_finite() || *b <= 0.0)
+                {
+                    return Err(
+                        "latency_heatmap.buckets_ms: valores devem ser maiores que zero".into(),
+                    );
+                }
+                if heatmap.buckets_ms.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err("latency_heatmap.buckets_ms: deve estar em ordem crescente".into());
+                }
+                if heatmap.interval_secs == 0 {
+                    return Err("latency_heatmap.interval_secs: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if Zone::parse(&self.report_timezone).is_none() {
+                return Err(format!(
+                    "report_timezone: fuso inválido `{}` (ex: America/Sao_Paulo ou -03:00)",
+                    self.report_timezone
+                ));
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if !(self.cycle_budget_percent > 0.0 && self.cycle_budget_percent <= 100.0) {
+                return Err("cycle_budget_percent: deve estar em (0, 100]".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(percent) = self.consensus_fail_percent
+                && !(percent > 0.0 && percent <= 100.0)
+            {
+                return Err("consensus_fail_percent: deve estar em (0, 100]".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            for (key, weight) in [
+                ("consensus_weights.down", self.consensus_weights.down),
+                ("consensus_weights.timeout", self.consensus_weights.timeout),
+            ] {
+                if !(0.0..=1.0).contains(&weight) {
+                    return Err(format!("{}: deve estar em [0, 1]", key));
+                }
+                if weight < 1.0 && self.consensus_fail_percent.is_none() {
+                    return Err(format!(
+                        "{}: pesos abaixo de 1 exigem consensus_fail_percent (no modo estrito um único ciclo com esse status impede o target de contar)",
+                        key
+                    ));
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if self.consensus_weights.down == 0.0 && self.consensus_weights.timeout == 0.0 {
+                return Err("consensus_weights: ao menos um peso deve ser maior que zero".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if self.escalation.major_after_secs <= 0 {
+                return Err("escalation.major_after_secs: deve ser maior que zero".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if self.escalation.critical_after_secs <= self.escalation.major_after_secs {
+                return Err(
+                    "escalation.critical_after_secs: deve ser maior que escalation.major_after_secs"
+                        .into(),
+                );
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            for (i, shadow) in self.shadow_consensus.iter().enumerate() {
+                if shadow.fail_threshold == 0 || shadow.consensus == 0 {
+                    return Err(format!(
+                        "shadow_consensus[{}] ({}): fail_threshold e consensus devem ser maiores que zero",
+                        i, shadow.name
+                    ));
+                }
+                if let Some(percent) = shadow.fail_percent
+                    && !(percent > 0.0 && percent <= 100.0)
+                {
+                    return Err(format!(
+                        "shadow_consensus[{}] ({}): fail_percent deve estar em (0, 100]",
+                        i, shadow.name
+                    ));
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(diagnosis) = &self.diagnosis {
+                if !(2..=16).contains(&diagnosis.max_hops) {
+                    return Err("diagnosis.max_hops: deve estar entre 2 e 16".into());
+                }
+                if diagnosis.probes_per_hop == 0 {
+                    return Err("diagnosis.probes_per_hop: deve ser maior que zero".into());
+                }
+                if diagnosis.timeout_ms == 0 {
+                    return Err("diagnosis.timeout_ms: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(dead_man_switch) = &self.dead_man_switch {
+                if dead_man_switch.url.trim().is_empty() {
+                    return Err("dead_man_switch.url: não pode ser vazio".into());
+                }
+                if dead_man_switch.timeout_secs == 0 {
+                    return Err("dead_man_switch.timeout_secs: deve ser maior que zero".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            let mut sink_names: Vec<&str> = Vec::new();
+            for sink in &self.sinks {
+                let name = sink.name();
+                if name.trim().is_empty() {
+                    return Err("sinks.name: não pode ser vazio".into());
+                }
+                if sink_names.contains(&name) {
+                    return Err(format!("sinks.name: `{}` duplicado", name));
+                }
+                sink_names.push(name);
+                match sink.kind {
+                    SinkKind::Stdout => {}
+                    SinkKind::File => {
+                        if sink.path.as_deref().is_none_or(|p| p.trim().is_empty()) {
+                            return Err(format!(
+                                "sinks.path ({}): exigido com type = \"file\"",
+                                name
+                            ));
+                        }
+                    }
+                    SinkKind::Webhook => {
+                        if sink.url.as_deref().is_none_or(|u| u.trim().is_empty()) {
+                            return Err(format!(
+                                "sinks.url ({}): exigido com type = \"webhook\"",
+                                name
+                            ));
+                        }
+                        if sink.timeout_secs == 0 {
+                            return Err(format!(
+                                "sinks.timeout_secs ({}): deve ser maior que zero",
+                                name
+                            ));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(remote_write) = &self.remote_write {
+                if remote_write.url.trim().is_empty() {
+                    return Err("remote_write.url: não pode ser vazio".into());
+                }
+                if remote_write.password.is_some() && remote_write.username.is_none() {
+                    return Err("remote_write.password: exige remote_write.username".into());
+                }
+                if remote_write.timeout_secs == 0 {
+                    return Err("remote_write.timeout_secs: deve ser maior que zero".into());
+                }
+                if !is_prometheus_name(&remote_write.metric_prefix) {
+                    return Err(format!(
+                        "remote_write.metric_prefix: `{}` não é um nome Prometheus válido",
+                        remote_write.metric_prefix
+                    ));
+                }
+                if let Some(name) = remote_write
+                    .labels
+                    .keys()
+                    .find(|name| !is_prometheus_name(name) || name.starts_with("__"))
+                {
+                    return Err(format!(
+                        "remote_write.labels: `{}` não é um nome de rótulo válido",
+                        name
+                    ));
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            let queries = &self.checks.dns_queries;
+            if queries.iter().any(|q| q.name.trim().is_empty()) {
+                return Err("checks.dns_queries: name não pode ser vazio".into());
+            }
+            if let Some(query) = queries.iter().enumerate().find_map(|(i, q)| {
+                queries[..i]
+                    .iter()
+                    .any(|other| other.name == q.name && other.record == q.record)
+                    .then_some(q)
+            }) {
+                return Err(format!(
+                    "checks.dns_queries: `{}` ({}) repetido",
+                    query.name,
+                    query.record.as_str()
+                ));
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(hex) = &self.checks.udp_payload_hex
+                && hex::decode(hex.trim()).is_err()
+            {
+                return Err("checks.udp_payload_hex: hexadecimal inválido".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            let combined = &self.checks.combined;
+            if let Some(name) = combined.iter().find(|name| {
+                !["ping", "tcp", "http", "dns", "tls", "udp", "sip"].contains(&name.as_str())
+            }) {
+                return Err(format!("checks.combined: check `{}` desconhecido", name));
+            }
+            if let Some(name) = combined
+                .iter()
+                .enumerate()
+                .find_map(|(i, name)| combined[..i].contains(name).then_some(name))
+            {
+                return Err(format!("checks.combined: `{}` repetido", name));
+            }
+            if combined.len() == 1 {
+                return Err("checks.combined: exige ao menos dois checks".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if !self.checks.source.is_default() {
+                self.checks.source.validate("checks.source")?;
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            for (name, source) in &self.checks.sources {
+                if !["ping", "tcp", "http", "dns", "tls", "udp", "sip"].contains(&name.as_str()) {
+                    return Err(format!("checks.sources: check `{}` desconhecido", name));
+                }
+                source.validate(&format!("checks.sources.{}", name))?;
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            let connectivity = &self.connectivity;
+            if connectivity.methods.is_empty() {
+                return Err("connectivity.methods: não pode ser vazio".into());
+            }
+            if let Some(method) = connectivity
+                .methods
+                .iter()
+                .enumerate()
+                .find_map(|(i, m)| connectivity.methods[..i].contains(m).then_some(m))
+            {
+                return Err(format!("connectivity.methods: {:?} repetido", method));
+            }
+            if connectivity.methods.contains(&ConnectivityMethod::Tcp)
+                && connectivity.tcp_ports.contains(&0)
+            {
+                return Err("connectivity.tcp_ports: portas devem ser maiores que zero".into());
+            }
+            if connectivity.timeout_secs == 0 {
+                return Err("connectivity.timeout_secs: deve ser maior que zero".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(rate_limit) = &self.rate_limit {
+                if rate_limit.packets_per_second <= 0.0 {
+                    return Err("rate_limit.packets_per_second: deve ser maior que zero".into());
+                }
+                if rate_limit.burst.is_some_and(|burst| burst < 1.0) {
+                    return Err("rate_limit.burst: deve ser no mínimo 1".into());
+                }
+                if !(1..=32).contains(&rate_limit.ipv4_prefix_len) {
+                    return Err("rate_limit.ipv4_prefix_len: deve estar em [1, 32]".into());
+                }
+                if !(1..=128).contains(&rate_limit.ipv6_prefix_len) {
+                    return Err("rate_limit.ipv6_prefix_len: deve estar em [1, 128]".into());
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(inventory) = &self.inventory
+                && inventory.path.trim().is_empty()
+            {
+                return Err("inventory.path: não pode ser vazio".into());
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(notifications) = &self.notifications {
+                notifications.validate("notifications")?;
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            if let Some(report) = &self.periodic_report {
+                if report.hour > 23 {
+                    return Err("periodic_report.hour: deve estar em [0, 23]".into());
+                }
+                if report.worst_targets == 0 {
+                    return Err("periodic_report.worst_targets: deve ser maior que zero".into());
+                }
+                if !(1..=28).contains(&report.day_of_month) {
+                    return Err("periodic_report.day_of_month: deve estar em [1, 28]".into());
+                }
+                if let Some(offset) = &report.utc_offset
+                    && offset.parse::<FixedOffset>().is_err()
+                {
+                    return Err(format!(
+                        "periodic_report.utc_offset: deslocamento inválido `{}` (ex: -03:00)",
+                        offset
+                    ));
+                }
+                let notifications: Vec<&NotificationsConfig> = self
+                    .notifications
+                    .iter()
+                    .chain(self.tenants.iter().filter_map(|t| t.notifications.as_ref()))
+                    .collect();
+                if notifications.is_empty() {
+                    return Err("periodic_report: exige [notifications] configurado".into());
+                }
+                if let Some(name) = report.routes.iter().find(|name| {
+                    !notifications
+                        .iter()
+                        .any(|n| n.routes.iter().any(|r| &r.name == *name))
+                }) {
+                    return Err(format!(
+                        "periodic_report.routes: rota desconhecida `{}`",
+                        name
+                    ));
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            for (i, profile) in self.probe_profiles.iter().enumerate() {
+                if profile.probe_id.is_some() == profile.location.is_some() {
+                    return Err(format!(
+                        "probe_profiles[{}]: informe probe_id ou location (um dos dois)",
+                        i
+                    ));
+                }
+                let same_probe = |other: &ProbeProfileConfig| {
+                    (profile.probe_id.is_some() && other.probe_id == profile.probe_id)
+                        || (profile.location.is_some() && other.location == profile.location)
+                };
+                if self.probe_profiles[..i].iter().any(same_probe) {
+                    return Err(format!(
+                        "probe_profiles[{}] ({}): perfil repetido",
+                        i,
+                        profile.label()
+                    ));
+                }
+                let zero = [
+                    (
+                        "cycle_interval_secs",
+                        profile.cycle_interval_secs == Some(0),
+                    ),
+                    ("ping_count", profile.ping_count == Some(0)),
+                    ("timeout_secs", profile.timeout_secs == Some(0)),
+                    ("fail_threshold", profile.fail_threshold == Some(0)),
+                    ("consensus", profile.consensus == Some(0)),
+                ];
+                if let Some((key, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+                    return Err(format!(
+                        "probe_profiles[{}] ({}): {} deve ser maior que zero",
+                        i,
+                        profile.label(),
+                        key
+                    ));
+                }
+                if let Some(source) = &profile.source {
+                    source.validate(&format!("probe_profiles[{}].source", i))?;
+                }
+            }
+            Ok(())
+        });
+        section(&mut errors, || {
+            for (i, tenant) in self.tenants.iter().enumerate() {
+                if let Some(notifications) = &tenant.notifications {
+                    notifications.validate(&format!("tenants[{}].notifications", i))?;
+                }
+                if tenant.name.trim().is_empty() {
+                    return Err(format!("tenants[{}]: name não pode ser vazio", i));
+                }
+                if self.tenants[..i].iter().any(|t| t.name == tenant.name) {
+                    return Err(format!("tenants[{}]: nome {} repetido", i, tenant.name));
+                }
+                if tenant.fail_threshold == Some(0) || tenant.consensus == Some(0) {
+                    return Err(format!(
+                        "tenants[{}] ({}): fail_threshold e consensus devem ser maiores que zero",
+                        i, tenant.name
+                    ));
+                }
+                if let Some(percent) = tenant.consensus_fail_percent
+                    && !(percent > 0.0 && percent <= 100.0)
+                {
+                    return Err(format!(
+                        "tenants[{}] ({}): consensus_fail_percent deve estar em (0, 100]",
+                        i, tenant.name
+                    ));
+                }
+            }
+            Ok(())
+        });
+        // Parâmetros que só fazem sentido juntos: um ciclo precisa caber as
+        // tentativas de cada target
+        section(&mut errors, || {
+            check_cycle_fits(
+                "",
+                self.cycle_interval_secs,
+                self.ping_count,
+                self.timeout_secs,
+            )
+        });
+        for (i, profile) in self.probe_profiles.iter().enumerate() {
+            section(&mut errors, || {
+                check_cycle_fits(
+                    &format!("probe_profiles[{}].", i),
+                    profile
+                        .cycle_interval_secs
+                        .unwrap_or(self.cycle_interval_secs),
+                    profile.ping_count.unwrap_or(self.ping_count),
+                    profile.timeout_secs.unwrap_or(self.timeout_secs),
+                )
+            });
         }
-        Ok(())
+        errors
+    }
+}
+
+/// Chaves presentes nas fontes que nenhum campo de [`Config`] lê (erros de
+/// digitação, opções removidas), como `api.bnd` ou
+/// `probe_profiles[0].consenso`: as que a desserialização descarta, colhidas
+/// numa única passada por `serde_ignored`. Sem uma configuração que
+/// desserializa como está, a lista sai vazia.
+pub fn unknown_keys(settings: &config_crate::Config) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let parsed: Result<Config, _> =
+        serde_ignored::deserialize(settings.clone(), |path| unknown.push(key_path(&path)));
+    if parsed.is_err() {
+        return Vec::new();
+    }
+    unknown
+}
+
+/// Caminho de uma chave no formato da configuração (`a.b[0].c`).
+fn key_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", key_path(parent), index),
+        Path::Map { parent, key } => match key_path(parent) {
+            prefix if prefix.is_empty() => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => key_path(parent),
+    }
+}
+
+/// Roda uma seção da validação, guardando a primeira violação dela.
+fn section(errors: &mut Vec<String>, check: impl FnOnce() -> Result<(), String>) {
+    if let Err(e) = check() {
+        errors.push(e);
+    }
+}
+
+/// `timeout_secs` e as `ping_count` tentativas (em sequência) de um target
+/// cabem no ciclo. Valores zerados já são barrados à parte.
+fn check_cycle_fits(
+    prefix: &str,
+    cycle_interval_secs: u64,
+    ping_count: usize,
+    timeout_secs: u64,
+) -> Result<(), String> {
+    if ping_count == 0 || timeout_secs == 0 || cycle_interval_secs == 0 {
+        return Ok(());
+    }
+    if timeout_secs >= cycle_interval_secs {
+        return Err(format!(
+            "{}timeout_secs: deve ser menor que cycle_interval_secs ({} >= {})",
+            prefix, timeout_secs, cycle_interval_secs
+        ));
+    }
+    let attempts = ping_count as u64 * timeout_secs;
+    if attempts >= cycle_interval_secs {
+        return Err(format!(
+            "{}ping_count × timeout_secs: deve ser menor que cycle_interval_secs ({} × {} = {}s >= {}s)",
+            prefix, ping_count, timeout_secs, attempts, cycle_interval_secs
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pesos_abaixo_de_um_exigem_modo_percentual() {
+        let strict = Config::from_toml(
+            r#"
+            database_url = "postgres://teste"
+            [consensus_weights]
+            timeout = 0.5
+            "#,
+        );
+        let violations = strict.violations();
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        assert!(violations[0].starts_with("consensus_weights.timeout:"));
+
+        let percent = Config::from_toml(
+            r#"
+            database_url = "postgres://teste"
+            consensus_fail_percent = 80
+            [consensus_weights]
+            timeout = 0.5
+            "#,
+        );
+        assert!(
+            percent.violations().is_empty(),
+            "{:?}",
+            percent.violations()
+        );
+    }
+
+    #[test]
+    fn chaves_desconhecidas_com_caminho() {
+        let settings = Config::test_settings(
+            r#"
+            database_url = "postgres://teste"
+            consensu = 2
+            [connectivity]
+            tcp_prot = 443
+            [[probe_profiles]]
+            probe_id = 1
+            consenso = 2
+            "#,
+        );
+        let mut unknown = unknown_keys(&settings);
+        unknown.sort();
+        assert_eq!(
+            unknown,
+            [
+                "connectivity.tcp_prot",
+                "consensu",
+                "probe_profiles[0].consenso"
+            ]
+        );
+
+        let valid = Config::test_settings(r#"database_url = "postgres://teste""#);
+        assert!(unknown_keys(&valid).is_empty());
     }
 }
@@ -0,0 +1,157 @@
+//! config_check.rs — `--validate-config`
+//!
+//! Confere a configuração sem subir o monitor e lista todos os problemas de
+//! uma vez, em vez de parar no primeiro: chaves desconhecidas (typos que
+//! seriam ignorados em silêncio), valores fora da faixa e combinações
+//! incoerentes (ver `Config::violations`). Com a configuração coerente,
+//! confere também o `consensus` contra os targets de cada probe, como na
+//! subida dos schedulers: os targets e probes vêm do `[inventory]` ou, sem
+//! ele, do banco (só leitura, sem migrações) mais os `[[targets]]` ainda
+//! não cadastrados. Sai com erro se houver algum problema.
+
+use crate::config::{self, Config};
+use crate::inventory;
+use crate::probe_profile;
+use crate::registration;
+use crate::storage::Storage;
+use crate::templates::TemplateSet;
+use crate::tenant;
+use crate::types::{Probe, Target};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Confere a configuração em `path` e imprime o resultado.
+pub async fn run(path: Option<&str>) -> Result<()> {
+    let settings = Config::settings(path).context("Falha ao ler a configuração")?;
+    let mut problems: Vec<String> = config::unknown_keys(&settings)
+        .into_iter()
+        .map(|key| format!("{}: chave desconhecida (seria ignorada)", key))
+        .collect();
+    match Config::from_settings(&settings) {
+        Ok(config) => {
+            problems.extend(config.violations());
+            if problems.is_empty() {
+                problems.extend(consensus_problems(Arc::new(config)).await);
+            } else {
+                println!("ℹ️  consensus × targets: não conferido (corrija os problemas abaixo)");
+            }
+        }
+        Err(e) => problems.push(format!("{:#}", e)),
+    }
+
+    if problems.is_empty() {
+        println!("✅ Configuração válida");
+        return Ok(());
+    }
+    println!("❌ Configuração com {} problema(s):", problems.len());
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+    bail!("Configuração inválida: {} problema(s)", problems.len())
+}
+
+/// Confere os parâmetros de consenso de cada probe contra os targets que ele
+/// vai monitorar.
+async fn consensus_problems(config: Arc<Config>) -> Vec<String> {
+    let templates = TemplateSet::new(&config.target_templates);
+    let (targets, probes, assignments, mut problems) = match load(&config, &templates).await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("ℹ️  consensus × targets: não conferido ({:#})", e);
+            return Vec::new();
+        }
+    };
+    if targets.is_empty() || probes.is_empty() {
+        println!("ℹ️  consensus × targets: não conferido (sem targets ou sem probes)");
+        return problems;
+    }
+
+    for group in tenant::partition(&config, &targets) {
+        let label = group
+            .name
+            .as_ref()
+            .map_or(String::new(), |name| format!(" [{}]", name));
+        for probe in &probes {
+            let assigned = probe_profile::assigned(&assignments, probe, &group.targets);
+            let (probe_config, probe_targets) = match probe_profile::find(&config, probe) {
+                Some(profile) => (
+                    probe_profile::apply(&group.config, profile),
+                    probe_profile::select_targets(profile, &assigned),
+                ),
+                None => (Arc::clone(&group.config), assigned),
+            };
+            if probe_targets.is_empty() {
+                continue;
+            }
+            if let Err(e) = crate::consensus::ConsensusState::from_config(
+                &probe_config,
+                probe.id,
+                &probe_targets,
+            ) {
+                problems.push(format!(
+                    "consensus{} do probe {}: {} (fail_threshold = {}, consensus = {}, {} target(s))",
+                    label,
+                    probe.location,
+                    e,
+                    probe_config.fail_threshold,
+                    probe_config.consensus,
+                    probe_targets.len()
+                ));
+            }
+        }
+    }
+    println!(
+        "ℹ️  consensus × targets: {} target(s), {} probe(s)",
+        targets.len(),
+        probes.len()
+    );
+    problems
+}
+
+/// Targets, probes e atribuições que o monitor usaria, mais os problemas das
+/// entradas de `[[targets]]`.
+async fn load(
+    config: &Config,
+    templates: &TemplateSet,
+) -> Result<(Vec<Target>, Vec<Probe>, HashMap<i32, Vec<i32>>, Vec<String>)> {
+    let mut problems = Vec::new();
+    let (mut targets, probes, assignments) = match &config.inventory {
+        Some(inventory_config) => {
+            let (targets, probes) = inventory::read(inventory_config, templates).await?;
+            (targets, probes, HashMap::new())
+        }
+        None => {
+            let storage = timeout(
+                Duration::from_secs(5),
+                Storage::connect(&config.database_url),
+            )
+            .await
+            .context("timeout ao conectar ao banco de dados")??;
+            let mut targets = storage.list_targets().await?;
+            for (i, request) in config.targets.iter().enumerate() {
+                match registration::prepare(templates, request).await {
+                    Ok(prepared) => targets.extend(
+                        prepared
+                            .into_iter()
+                            .map(|(_, target)| target)
+                            .filter(|target| !targets.iter().any(|t| t.address == target.address))
+                            .collect::<Vec<_>>(),
+                    ),
+                    Err(e) => problems.push(format!("targets[{}] {:?}: {}", i, request.inputs, e)),
+                }
+            }
+            let probes = storage.list_probes().await?;
+            let assignments = storage.list_probe_targets().await?;
+            (targets, probes, assignments)
+        }
+    };
+    // Ids provisórios (negativos) para os targets ainda sem linha no banco,
+    // para que os pesos por target não se misturem
+    for (i, target) in targets.iter_mut().filter(|t| t.id == 0).enumerate() {
+        target.id = -(i as i32) - 1;
+    }
+    Ok((targets, probes, assignments, problems))
+}
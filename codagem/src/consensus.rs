@@ -1,17 +1,187 @@
 //! consensus.rs — Estado de consenso multi-ciclo robusto para detecção de outages
+//!
+//! A janela de histórico guarda apenas um resumo compacto por ciclo (IDs dos
+//! targets em Down/Timeout), e não os vetores completos de métricas, com um
+//! teto explícito de memória (`consensus_history_max_bytes`).
 
-use crate::types::{ConnectivityMetric, MetricStatus, OutageEvent};
+use crate::captive::CaptivePortal;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, ConsensusWeights, EscalationConfig};
+use crate::decisions::{ConsensusDecision, DecisionOutcome};
+use crate::degradation;
+use crate::diagnosis::Diagnosis;
+use crate::outage::OutageManager;
+use crate::types::{
+    ConnectivityMetric, MetricStatus, OutageEvent, OutageEventType, Severity, Target,
+};
 use chrono::{DateTime, Utc};
+use serde::Serialize;
 use serde_json::json;
 use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Teto padrão de memória da janela de consenso (8 MiB).
+pub const DEFAULT_HISTORY_MAX_BYTES: usize = 8 * 1024 * 1024;
+
+/// Folga na comparação das pontuações ponderadas (somas de `f64`).
+const SCORE_EPSILON: f64 = 1e-9;
+
+/// Resumo compacto de um ciclo: apenas os targets que falharam.
+#[derive(Debug, Clone)]
+struct CycleSummary {
+    failed_targets: Box<[i32]>,
+    /// Subconjunto de `failed_targets` que só teve Timeout no ciclo (sem Down).
+    timed_out: Box<[i32]>,
+}
+
+impl CycleSummary {
+    fn from_metrics(metrics: &[ConnectivityMetric]) -> Self {
+        let failed_ids = |status: &MetricStatus| {
+            let mut ids: Vec<i32> = metrics
+                .iter()
+                .filter(|m| m.status == *status)
+                .map(|m| m.target_id)
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids
+        };
+        let down = failed_ids(&MetricStatus::Down);
+        let timed_out: Vec<i32> = failed_ids(&MetricStatus::Timeout)
+            .into_iter()
+            .filter(|id| down.binary_search(id).is_err())
+            .collect();
+        let mut failed: Vec<i32> = down.iter().chain(&timed_out).copied().collect();
+        failed.sort_unstable();
+        Self {
+            failed_targets: failed.into_boxed_slice(),
+            timed_out: timed_out.into_boxed_slice(),
+        }
+    }
+
+    /// Peso do ciclo na pontuação de falha do target.
+    fn weight(&self, target_id: i32, weights: &ConsensusWeights) -> f64 {
+        if self.timed_out.binary_search(&target_id).is_ok() {
+            weights.timeout
+        } else if self.failed_targets.binary_search(&target_id).is_ok() {
+            weights.down
+        } else {
+            0.0
+        }
+    }
+
+    fn approx_bytes(&self) -> usize {
+        size_of::<Self>() + (self.failed_targets.len() + self.timed_out.len()) * size_of::<i32>()
+    }
+}
+
+/// Métricas sobre o tamanho da janela de consenso.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct WindowStats {
+    /// Ciclos atualmente na janela.
+    pub cycles: usize,
+    /// Entradas (target, ciclo) com falha guardadas.
+    pub failed_entries: usize,
+    /// Memória aproximada da janela em bytes.
+    pub approx_bytes: usize,
+    /// Teto configurado em bytes.
+    pub max_bytes: usize,
+    /// Ciclos descartados antecipadamente por estourar o teto.
+    pub evictions: u64,
+}
+
+/// Parâmetros do consenso de um probe: os da configuração, ou os de um
+/// cenário (`scenario.rs`).
+#[derive(Debug, Clone)]
+pub struct ConsensusParams {
+    pub fail_threshold: usize,
+    pub consensus: usize,
+    pub fail_percent: Option<f64>,
+    pub weights: ConsensusWeights,
+    pub max_history_bytes: usize,
+    pub outage_recovery_cycles: usize,
+    pub partial_outage_recovery_cycles: usize,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        Self {
+            fail_threshold: 3,
+            consensus: 1,
+            fail_percent: None,
+            weights: ConsensusWeights::default(),
+            max_history_bytes: DEFAULT_HISTORY_MAX_BYTES,
+            outage_recovery_cycles: 1,
+            partial_outage_recovery_cycles: 2,
+        }
+    }
+}
+
+impl From<&Config> for ConsensusParams {
+    fn from(config: &Config) -> Self {
+        Self {
+            fail_threshold: config.fail_threshold,
+            consensus: config.consensus,
+            fail_percent: config.consensus_fail_percent,
+            weights: config.consensus_weights,
+            max_history_bytes: config.consensus_history_max_bytes,
+            outage_recovery_cycles: config.outage_recovery_cycles,
+            partial_outage_recovery_cycles: config.partial_outage_recovery_cycles,
+        }
+    }
+}
 
 /// Estado do consenso multi-ciclo
 #[derive(Debug, Clone)]
 pub struct ConsensusState {
-    pub history: VecDeque<Vec<ConnectivityMetric>>,
+    history: VecDeque<CycleSummary>,
+    /// Contagem incremental de ciclos Down/Timeout por target dentro da janela
+    down_counts: HashMap<i32, usize>,
+    /// Quantos desses ciclos foram só Timeout, por target
+    timeout_counts: HashMap<i32, usize>,
+    /// Peso de cada ciclo Down e Timeout na pontuação de falha
+    weights: ConsensusWeights,
+    approx_bytes: usize,
+    max_bytes: usize,
+    evictions: u64,
     fail_threshold: usize,
+    /// Modo percentual: um target conta no consenso com Down/Timeout em pelo
+    /// menos esta % dos ciclos da janela; ausente = em todos os ciclos
+    fail_percent: Option<f64>,
+    /// Thresholds por target (templates) menores que a janela global
+    target_thresholds: HashMap<i32, usize>,
+    /// Peso de cada target no consenso, quando diferente de 1
+    target_weights: HashMap<i32, f64>,
+    /// Soma dos pesos dos targets em falha necessária para abrir um outage
     consensus: usize,
-    current_outage: Option<OutageEvent>,
+    /// Outage de consenso aberto
+    outage: OutageManager,
+    /// Fonte do instante de cada ciclo
+    clock: Arc<dyn Clock>,
+    /// Ciclos consecutivos acima dos thresholds de degradação, por target
+    degraded_streaks: HashMap<i32, usize>,
+    /// Eventos de degradação abertos, por target
+    open_degradations: HashMap<i32, OutageEvent>,
+    /// Ciclos consecutivos em Down/Timeout, por target
+    down_streaks: HashMap<i32, usize>,
+    /// Ciclos consecutivos respondendo com outage parcial aberto, por target
+    recovery_streaks: HashMap<i32, usize>,
+    /// Outages parciais abertos, por target
+    open_partials: HashMap<i32, OutageEvent>,
+    /// Ciclos respondendo para encerrar um outage parcial (histerese)
+    recovery_cycles: usize,
+    /// Ciclos seguidos abaixo do consenso para encerrar o outage (histerese)
+    outage_recovery_cycles: usize,
+    /// Início da recuperação em curso do outage aberto: primeiro ciclo abaixo
+    /// do consenso e quantos seguiram desde então
+    recovering: Option<(DateTime<Utc>, usize)>,
+    /// Ciclos em que um outage retomado após restart não é encerrado por
+    /// janela incompleta
+    resume_grace: usize,
+    /// Decisão do último `update`, para registro explicável
+    last_decision: Option<ConsensusDecision>,
     /// ID da probe local (necessário para affected_probes)
     probe_id: Option<i32>,
 }
@@ -19,114 +189,705 @@ pub struct ConsensusState {
 impl ConsensusState {
     /// Cria um novo estado de consenso
     pub fn new(fail_threshold: usize, consensus: usize, probe_id: Option<i32>) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
         Self {
             history: VecDeque::with_capacity(fail_threshold),
+            down_counts: HashMap::new(),
+            timeout_counts: HashMap::new(),
+            weights: ConsensusWeights::default(),
+            approx_bytes: 0,
+            max_bytes: DEFAULT_HISTORY_MAX_BYTES,
+            evictions: 0,
             fail_threshold,
+            fail_percent: None,
+            target_thresholds: HashMap::new(),
+            target_weights: HashMap::new(),
             consensus,
-            current_outage: None,
+            outage: OutageManager::new(Arc::clone(&clock)),
+            clock,
+            degraded_streaks: HashMap::new(),
+            open_degradations: HashMap::new(),
+            down_streaks: HashMap::new(),
+            recovery_streaks: HashMap::new(),
+            open_partials: HashMap::new(),
+            recovery_cycles: 1,
+            outage_recovery_cycles: 1,
+            recovering: None,
+            resume_grace: 0,
+            last_decision: None,
             probe_id,
         }
     }
 
-    /// Valida os parâmetros de consenso em relação ao número de targets monitorados.
-    pub fn validate_params(&self, num_targets: usize) -> Result<(), String> {
+    /// Estado do consenso de um probe com `fail_threshold`/`consensus` da
+    /// configuração e os thresholds e pesos por target, validado contra o
+    /// peso somado dos targets monitorados.
+    pub fn from_config(config: &Config, probe_id: i32, targets: &[Target]) -> Result<Self, String> {
+        Self::from_params(&ConsensusParams::from(config), probe_id, targets)
+    }
+
+    /// Como [`from_config`](Self::from_config), com parâmetros avulsos.
+    pub fn from_params(
+        params: &ConsensusParams,
+        probe_id: i32,
+        targets: &[Target],
+    ) -> Result<Self, String> {
+        let mut state = Self::new(params.fail_threshold, params.consensus, Some(probe_id))
+            .with_max_history_bytes(params.max_history_bytes)
+            .with_recovery_cycles(params.partial_outage_recovery_cycles)
+            .with_outage_recovery_cycles(params.outage_recovery_cycles)
+            .with_fail_percent(params.fail_percent)
+            .with_weights(params.weights);
+        state.set_target_weights(targets.iter().filter_map(|t| t.weight.map(|w| (t.id, w))));
+        let total_weight: f64 = targets.iter().map(|t| state.target_weight(t.id)).sum();
+        state.validate_params(total_weight)?;
+        state.set_target_thresholds(
+            targets
+                .iter()
+                .filter_map(|t| t.fail_threshold.map(|f| (t.id, f.max(0) as usize))),
+        );
+        Ok(state)
+    }
+
+    /// Troca o relógio que data os ciclos e os eventos.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.outage = self.outage.with_clock(Arc::clone(&clock));
+        self.clock = clock;
+        self
+    }
+
+    /// Instante atual pelo relógio do consenso.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Define o teto de memória da janela de histórico.
+    pub fn with_max_history_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Define quantos ciclos seguidos abaixo do consenso encerram um outage,
+    /// para que uma melhora breve no meio de uma queda instável não a divida
+    /// em vários incidentes.
+    pub fn with_outage_recovery_cycles(mut self, cycles: usize) -> Self {
+        self.outage_recovery_cycles = cycles.max(1);
+        self
+    }
+
+    /// Liga o modo percentual: um target conta como em falha com Down/Timeout
+    /// em pelo menos `percent`% dos ciclos da janela, para que uma resposta
+    /// isolada no meio da queda não zere a detecção. `None` mantém o modo
+    /// estrito (falha em todos os ciclos).
+    pub fn with_fail_percent(mut self, percent: Option<f64>) -> Self {
+        self.fail_percent = percent;
+        self
+    }
+
+    /// Define o peso de um ciclo Down e de um ciclo Timeout na pontuação de
+    /// falha de cada target.
+    pub fn with_weights(mut self, weights: ConsensusWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Ciclos com falha, numa janela de `window` ciclos, para o target contar
+    /// no consenso (a pontuação ponderada é comparada com esse valor).
+    fn required_failures(&self, window: usize) -> usize {
+        match self.fail_percent {
+            Some(percent) => ((percent * window as f64 / 100.0).ceil() as usize).clamp(1, window),
+            None => window,
+        }
+    }
+
+    /// Define quantos ciclos seguidos respondendo encerram um outage parcial.
+    pub fn with_recovery_cycles(mut self, cycles: usize) -> Self {
+        self.recovery_cycles = cycles.max(1);
+        self
+    }
+
+    /// Define thresholds por target. A janela continua com `fail_threshold`
+    /// ciclos: valores maiores que ela são limitados a ela, e os iguais são
+    /// descartados por não mudarem nada.
+    pub fn set_target_thresholds(&mut self, thresholds: impl IntoIterator<Item = (i32, usize)>) {
+        self.target_thresholds = thresholds
+            .into_iter()
+            .filter(|(_, t)| *t > 0 && *t < self.fail_threshold)
+            .collect();
+    }
+
+    /// Define o peso de cada target no consenso: um outage abre quando a soma
+    /// dos pesos dos targets em falha atinge `consensus`. Pesos iguais a 1
+    /// (o padrão) são descartados.
+    pub fn set_target_weights(&mut self, weights: impl IntoIterator<Item = (i32, f64)>) {
+        self.target_weights = weights.into_iter().filter(|(_, w)| *w != 1.0).collect();
+    }
+
+    fn target_weight(&self, target_id: i32) -> f64 {
+        self.target_weights.get(&target_id).copied().unwrap_or(1.0)
+    }
+
+    /// Target em Down/Timeout nos últimos `threshold` ciclos da janela (no
+    /// modo percentual, na fração configurada deles).
+    fn failed_recently(&self, target_id: i32, threshold: usize) -> bool {
+        let score: f64 = self
+            .history
+            .iter()
+            .rev()
+            .take(threshold)
+            .map(|c| c.weight(target_id, &self.weights))
+            .sum();
+        score + SCORE_EPSILON >= self.required_failures(threshold) as f64
+    }
+
+    /// Pontuação de falha do target na janela inteira: ciclos Down e
+    /// Timeout multiplicados pelos respectivos pesos.
+    fn window_score(&self, target_id: i32, failures: usize) -> f64 {
+        let timeouts = self.timeout_counts.get(&target_id).copied().unwrap_or(0);
+        (failures - timeouts) as f64 * self.weights.down + timeouts as f64 * self.weights.timeout
+    }
+
+    /// Retira a decisão do último `update` (probe e ciclo ficam a cargo de
+    /// quem chamou).
+    pub fn take_decision(&mut self) -> Option<ConsensusDecision> {
+        self.last_decision.take()
+    }
+
+    fn record_decision(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        outcome: DecisionOutcome,
+        reason: &str,
+        counted_targets: &[i32],
+        weighted: f64,
+    ) {
+        let failed_targets = self
+            .history
+            .back()
+            .map(|c| c.failed_targets.to_vec())
+            .unwrap_or_default();
+        self.last_decision = Some(ConsensusDecision {
+            probe_id: self.probe_id.unwrap_or(0),
+            cycle_id: 0,
+            timestamp,
+            outcome,
+            reason: reason.to_string(),
+            history_len: self.history.len() as i32,
+            fail_threshold: self.fail_threshold as i32,
+            consensus: self.consensus as i32,
+            failed_targets,
+            counted_targets: counted_targets.to_vec(),
+            details: json!({
+                "down_counts": self.down_counts,
+                "timeout_counts": self.timeout_counts,
+                "weights": self.weights,
+                "target_thresholds": self.target_thresholds,
+                "target_weights": self.target_weights,
+                "weighted": weighted,
+                "fail_percent": self.fail_percent,
+            }),
+        });
+    }
+
+    /// Outage de consenso aberto, se houver.
+    pub fn current_outage(&self) -> Option<&OutageEvent> {
+        self.outage.current()
+    }
+
+    /// Quantidade de ciclos atualmente na janela.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Métricas sobre o tamanho atual da janela.
+    pub fn window_stats(&self) -> WindowStats {
+        WindowStats {
+            cycles: self.history.len(),
+            failed_entries: self.history.iter().map(|c| c.failed_targets.len()).sum(),
+            approx_bytes: self.approx_bytes,
+            max_bytes: self.max_bytes,
+            evictions: self.evictions,
+        }
+    }
+
+    fn counts_bytes(&self) -> usize {
+        (self.down_counts.len() + self.timeout_counts.len())
+            * (size_of::<i32>() + size_of::<usize>())
+    }
+
+    fn push_cycle(&mut self, summary: CycleSummary) {
+        for target_id in summary.failed_targets.iter() {
+            *self.down_counts.entry(*target_id).or_insert(0) += 1;
+        }
+        for target_id in summary.timed_out.iter() {
+            *self.timeout_counts.entry(*target_id).or_insert(0) += 1;
+        }
+        self.approx_bytes += summary.approx_bytes();
+        self.history.push_back(summary);
+    }
+
+    fn pop_cycle(&mut self) {
+        if let Some(oldest) = self.history.pop_front() {
+            for (counts, targets) in [
+                (&mut self.down_counts, &oldest.failed_targets),
+                (&mut self.timeout_counts, &oldest.timed_out),
+            ] {
+                for target_id in targets.iter() {
+                    if let Some(count) = counts.get_mut(target_id) {
+                        *count -= 1;
+                        if *count == 0 {
+                            counts.remove(target_id);
+                        }
+                    }
+                }
+            }
+            self.approx_bytes -= oldest.approx_bytes();
+        }
+    }
+
+    /// Valida os parâmetros de consenso em relação ao peso somado dos targets
+    /// monitorados (a quantidade deles, sem pesos).
+    pub fn validate_params(&self, total_weight: f64) -> Result<(), String> {
         if self.fail_threshold == 0 {
             return Err("fail_threshold deve ser maior que zero".into());
         }
         if self.consensus == 0 {
             return Err("consensus deve ser maior que zero".into());
         }
-        if self.consensus > num_targets {
+        if self.consensus as f64 > total_weight + SCORE_EPSILON {
             return Err(format!(
-                "consensus ({}) não pode ser maior que o número de targets monitorados ({})",
-                self.consensus, num_targets
+                "consensus ({}) não pode ser maior que o peso somado dos targets monitorados ({})",
+                self.consensus, total_weight
             ));
         }
         Ok(())
     }
 
-    /// Atualiza o estado de consenso com os resultados de um novo ciclo
-    pub fn update(
-        &mut self,
-        cycle_results: Vec<ConnectivityMetric>,
-        cycle_timestamp: DateTime<Utc>,
-    ) -> Option<OutageEvent> {
+    /// Atualiza o estado de consenso com os resultados de um novo ciclo,
+    /// datado pelo relógio do consenso
+    pub fn update(&mut self, cycle_results: &[ConnectivityMetric]) -> Option<OutageEvent> {
+        let cycle_timestamp = self.clock.now();
         // Mantém o histórico limitado ao fail_threshold
         if self.history.len() == self.fail_threshold {
-            self.history.pop_front();
+            self.pop_cycle();
         }
-        self.history.push_back(cycle_results.clone());
+        self.push_cycle(CycleSummary::from_metrics(cycle_results));
 
-        // Conta quantos ciclos cada target ficou Down ou Timeout
-        let mut down_counts: HashMap<i32, usize> = HashMap::new();
-        for cycle in self.history.iter() {
-            for metric in cycle.iter() {
-                if metric.status == MetricStatus::Down || metric.status == MetricStatus::Timeout {
-                    *down_counts.entry(metric.target_id).or_insert(0) += 1;
-                }
-            }
+        // Teto de memória: descarta os ciclos mais antigos (mantendo o atual).
+        // Com a janela encurtada o consenso não é atingido até ela se recompor.
+        while self.approx_bytes + self.counts_bytes() > self.max_bytes && self.history.len() > 1 {
+            self.pop_cycle();
+            self.evictions += 1;
+            warn!(
+                "[CONSENSUS WARN] Janela de consenso excedeu o teto de {} bytes; ciclo mais antigo descartado (evictions: {})",
+                self.max_bytes, self.evictions
+            );
         }
 
         // Targets que ficaram Down/Timeout em todos os ciclos do histórico
-        let majority_down: Vec<i32> = down_counts
+        // (ou na fração `fail_percent` deles), com Down e Timeout ponderados
+        let required = self.required_failures(self.fail_threshold) as f64;
+        let down_counts = &self.down_counts;
+        let mut majority_down: Vec<i32> = down_counts
             .iter()
-            .filter(|(_, count)| **count == self.fail_threshold)
+            .filter(
+                |(target_id, count)| match self.target_thresholds.get(target_id) {
+                    Some(&threshold) => self.failed_recently(**target_id, threshold),
+                    None => self.window_score(**target_id, **count) + SCORE_EPSILON >= required,
+                },
+            )
             .map(|(&target_id, _)| target_id)
             .collect();
+        majority_down.sort_unstable();
+        // Peso somado dos targets em falha (sem pesos, a quantidade deles)
+        let weighted: f64 = majority_down
+            .iter()
+            .map(|&target_id| self.target_weight(target_id))
+            .sum();
 
         // Logging detalhado para auditoria
-        println!(
-            "[CONSENSUS DEBUG] Histórico: {} ciclos, Down/Timeout por target: {:?}, majority_down: {:?}, consensus: {}, fail_threshold: {}",
+        debug!(
+            "[CONSENSUS DEBUG] Histórico: {} ciclos (~{} bytes), Down/Timeout por target: {:?}, majority_down: {:?} (peso {}), consensus: {}, fail_threshold: {}",
             self.history.len(),
+            self.approx_bytes + self.counts_bytes(),
             down_counts,
             majority_down,
+            weighted,
             self.consensus,
             self.fail_threshold
         );
 
         // Se atingiu consenso de falha, dispara outage se ainda não houver um aberto
-        if majority_down.len() >= self.consensus {
-            if self.current_outage.is_none() {
-                let event = OutageEvent {
+        if weighted + SCORE_EPSILON >= self.consensus as f64 {
+            self.recovering = None;
+            // Janela recomposta: a carência do outage retomado não vale mais
+            self.resume_grace = 0;
+            if !self.outage.is_open() {
+                let opened = self.outage.open(OutageEvent {
                     id: 0,
+                    event_type: OutageEventType::Outage,
                     start_time: cycle_timestamp,
                     end_time: None,
                     duration_seconds: None,
                     reason: Some("consensus_reached".to_string()),
                     affected_targets: majority_down.clone(),
-                    affected_probes: match self.probe_id {
-                        Some(n) => Some(vec![n]),
-                        None => None,
-                    }, // Adapte para multi-probe se necessário
+                    affected_probes: self.probe_id.map(|n| vec![n]), // Adapte para multi-probe se necessário
                     consensus_level: Some(majority_down.len() as i32),
                     details: Some(json!({
                         "fail_threshold": self.fail_threshold,
+                        "fail_percent": self.fail_percent,
                         "consensus": self.consensus,
                         "history_len": self.history.len(),
                         "down_counts": down_counts,
+                        "timeout_counts": self.timeout_counts,
+                        "target_weights": self.target_weights,
+                        "weighted": weighted,
+                        "severity": Severity::Minor,
+                        "escalations": [],
                     })),
-                };
-                self.current_outage = Some(event.clone());
-                println!(
-                    "[CONSENSUS INFO] Outage detectado! Atingido consenso de {} targets Down/Timeout.",
-                    self.consensus
+                    affected_asns: Vec::new(),
+                });
+                info!(
+                    "[CONSENSUS INFO] Outage detectado! Atingido consenso de {} ({} targets Down/Timeout, peso {}).",
+                    self.consensus,
+                    majority_down.len(),
+                    weighted
+                );
+                self.record_decision(
+                    cycle_timestamp,
+                    DecisionOutcome::Opened,
+                    "consensus_reached",
+                    &majority_down,
+                    weighted,
                 );
-                return Some(event);
+                return opened;
             }
+            self.record_decision(
+                cycle_timestamp,
+                DecisionOutcome::Ongoing,
+                "consensus_held",
+                &majority_down,
+                weighted,
+            );
         } else {
-            // Se consenso foi perdido, encerra outage aberto
-            if let Some(mut event) = self.current_outage.take() {
-                event.end_time = Some(cycle_timestamp);
-                event.duration_seconds = event
-                    .end_time
-                    .map(|end| (end - event.start_time).num_seconds() as i32);
-                println!(
-                    "[CONSENSUS INFO] Outage encerrado. Duração: {:?} segundos.",
-                    event.duration_seconds
+            // Outage retomado após restart: a janela ainda está se recompondo,
+            // então a falta de consenso não indica recuperação
+            if self.resume_grace > 0 && self.outage.is_open() {
+                self.resume_grace -= 1;
+                self.record_decision(
+                    cycle_timestamp,
+                    DecisionOutcome::Ongoing,
+                    "resumed",
+                    &majority_down,
+                    weighted,
                 );
-                return Some(event);
+                return None;
             }
+            // Se consenso foi perdido, encerra outage aberto. Com histerese,
+            // só após `outage_recovery_cycles` ciclos seguidos sem consenso,
+            // com o fim no primeiro deles
+            if self.outage.is_open() {
+                let (since, cycles) = self.recovering.get_or_insert((cycle_timestamp, 0));
+                *cycles += 1;
+                let (since, cycles) = (*since, *cycles);
+                if cycles < self.outage_recovery_cycles {
+                    self.record_decision(
+                        cycle_timestamp,
+                        DecisionOutcome::Ongoing,
+                        "recovering",
+                        &majority_down,
+                        weighted,
+                    );
+                    return None;
+                }
+                self.recovering = None;
+                if let Some(mut event) = self.outage.close(Some(since)) {
+                    if let Some(map) = event.details.as_mut().and_then(|d| d.as_object_mut()) {
+                        map.insert("recovery_cycles".into(), json!(self.outage_recovery_cycles));
+                    }
+                    info!(
+                        "[CONSENSUS INFO] Outage encerrado. Duração: {:?} segundos.",
+                        event.duration_seconds
+                    );
+                    self.record_decision(
+                        cycle_timestamp,
+                        DecisionOutcome::Closed,
+                        "consensus_lost",
+                        &majority_down,
+                        weighted,
+                    );
+                    return Some(event);
+                }
+            }
+            let reason = if self.history.len() < self.fail_threshold {
+                "window_incomplete"
+            } else {
+                "below_consensus"
+            };
+            self.record_decision(
+                cycle_timestamp,
+                DecisionOutcome::NoOutage,
+                reason,
+                &majority_down,
+                weighted,
+            );
         }
         None
     }
+
+    /// Retoma um evento deixado aberto no banco por uma execução anterior,
+    /// para que ele seja mantido ou encerrado em vez de um novo ser aberto. O
+    /// outage de consenso retomado resiste a `fail_threshold` ciclos sem
+    /// consenso, enquanto a janela se recompõe. Retorna `false` se já há um
+    /// evento aberto equivalente.
+    pub fn resume(&mut self, event: OutageEvent) -> bool {
+        let target_id = event.affected_targets.first().copied();
+        match (event.event_type, target_id) {
+            (OutageEventType::Outage, _) if !self.outage.is_open() => {
+                self.outage.resume(event);
+                self.recovering = None;
+                self.resume_grace = self.fail_threshold;
+                true
+            }
+            (OutageEventType::Partial, Some(target_id))
+                if !self.open_partials.contains_key(&target_id) =>
+            {
+                self.open_partials.insert(target_id, event);
+                true
+            }
+            (OutageEventType::Degradation, Some(target_id))
+                if !self.open_degradations.contains_key(&target_id) =>
+            {
+                self.open_degradations.insert(target_id, event);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Atualiza os eventos de degradação por target: um target acima dos
+    /// seus thresholds (`details.degradation`) por `fail_threshold` ciclos
+    /// seguidos (ou o threshold do target) abre um evento, encerrado no
+    /// primeiro ciclo medido dentro dos limites. Independente do consenso de
+    /// outage: congestionamento num único target já é registrado.
+    pub fn update_degradation(&mut self, cycle_results: &[ConnectivityMetric]) -> Vec<OutageEvent> {
+        let cycle_timestamp = self.clock.now();
+        let mut events = Vec::new();
+        for metric in cycle_results {
+            let target_id = metric.target_id;
+            if !degradation::exceeded(metric) {
+                self.degraded_streaks.remove(&target_id);
+                if let Some(mut event) = self.open_degradations.remove(&target_id) {
+                    event.end_time = Some(cycle_timestamp);
+                    event.duration_seconds =
+                        Some((cycle_timestamp - event.start_time).num_seconds() as i32);
+                    info!(
+                        "[CONSENSUS INFO] Degradação do target {} encerrada. Duração: {:?} segundos.",
+                        target_id, event.duration_seconds
+                    );
+                    events.push(event);
+                }
+                continue;
+            }
+            let threshold = self
+                .target_thresholds
+                .get(&target_id)
+                .copied()
+                .unwrap_or(self.fail_threshold);
+            let streak = self.degraded_streaks.entry(target_id).or_insert(0);
+            *streak += 1;
+            if *streak < threshold || self.open_degradations.contains_key(&target_id) {
+                continue;
+            }
+            let event = OutageEvent {
+                id: 0,
+                event_type: OutageEventType::Degradation,
+                start_time: cycle_timestamp,
+                end_time: None,
+                duration_seconds: None,
+                reason: Some("degradation_threshold".to_string()),
+                affected_targets: vec![target_id],
+                affected_probes: self.probe_id.map(|n| vec![n]),
+                consensus_level: None,
+                details: Some(json!({
+                    "fail_threshold": threshold,
+                    "degradation": metric.details.as_ref().and_then(|d| d.get("degradation")),
+                })),
+                affected_asns: Vec::new(),
+            };
+            info!(
+                "[CONSENSUS INFO] Degradação detectada no target {} após {} ciclos acima dos thresholds.",
+                target_id, threshold
+            );
+            self.open_degradations.insert(target_id, event.clone());
+            events.push(event);
+        }
+        events
+    }
+
+    /// Atualiza os outages parciais por target: um target em Down/Timeout por
+    /// `fail_threshold` ciclos seguidos (ou o threshold do target) abre um
+    /// evento `partial`, mesmo sem consenso. Com histerese, o evento só é
+    /// encerrado após `recovery_cycles` ciclos seguidos respondendo, para que
+    /// um target oscilando não abra e feche eventos a cada ciclo.
+    pub fn update_partial_outages(
+        &mut self,
+        cycle_results: &[ConnectivityMetric],
+    ) -> Vec<OutageEvent> {
+        let cycle_timestamp = self.clock.now();
+        let mut events = Vec::new();
+        for metric in cycle_results {
+            let target_id = metric.target_id;
+            let failed =
+                metric.status == MetricStatus::Down || metric.status == MetricStatus::Timeout;
+            if !failed {
+                self.down_streaks.remove(&target_id);
+                if !self.open_partials.contains_key(&target_id) {
+                    continue;
+                }
+                let recovered = self.recovery_streaks.entry(target_id).or_insert(0);
+                *recovered += 1;
+                if *recovered < self.recovery_cycles {
+                    continue;
+                }
+                self.recovery_streaks.remove(&target_id);
+                if let Some(mut event) = self.open_partials.remove(&target_id) {
+                    event.end_time = Some(cycle_timestamp);
+                    event.duration_seconds =
+                        Some((cycle_timestamp - event.start_time).num_seconds() as i32);
+                    info!(
+                        "[CONSENSUS INFO] Outage parcial do target {} encerrado. Duração: {:?} segundos.",
+                        target_id, event.duration_seconds
+                    );
+                    events.push(event);
+                }
+                continue;
+            }
+            self.recovery_streaks.remove(&target_id);
+            let threshold = self
+                .target_thresholds
+                .get(&target_id)
+                .copied()
+                .unwrap_or(self.fail_threshold);
+            let streak = self.down_streaks.entry(target_id).or_insert(0);
+            *streak += 1;
+            if *streak < threshold || self.open_partials.contains_key(&target_id) {
+                continue;
+            }
+            let event = OutageEvent {
+                id: 0,
+                event_type: OutageEventType::Partial,
+                start_time: cycle_timestamp,
+                end_time: None,
+                duration_seconds: None,
+                reason: Some("target_down".to_string()),
+                affected_targets: vec![target_id],
+                affected_probes: self.probe_id.map(|n| vec![n]),
+                consensus_level: None,
+                details: Some(json!({
+                    "fail_threshold": threshold,
+                    "recovery_cycles": self.recovery_cycles,
+                    "status": metric.status,
+                    "error": metric.error,
+                    "error_message": metric.error_message(),
+                })),
+                affected_asns: Vec::new(),
+            };
+            info!(
+                "[CONSENSUS INFO] Outage parcial no target {} após {} ciclos sem resposta.",
+                target_id, threshold
+            );
+            self.open_partials.insert(target_id, event.clone());
+            events.push(event);
+        }
+        events
+    }
+
+    /// Esquece um target que saiu dos ciclos (pausado): encerra agora o outage
+    /// parcial e a degradação abertos dele (com `details.closed_by =
+    /// "paused"`) e descarta as sequências em curso, que recomeçam do zero
+    /// quando ele voltar. Retorna os eventos encerrados, para gravar.
+    pub fn forget_target(&mut self, target_id: i32) -> Vec<OutageEvent> {
+        let now = self.clock.now();
+        self.down_streaks.remove(&target_id);
+        self.recovery_streaks.remove(&target_id);
+        self.degraded_streaks.remove(&target_id);
+        [
+            self.open_partials.remove(&target_id),
+            self.open_degradations.remove(&target_id),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|mut event| {
+            event.end_time = Some(now);
+            event.duration_seconds = Some((now - event.start_time).num_seconds() as i32);
+            if let Some(details) = event
+                .details
+                .get_or_insert_with(|| json!({}))
+                .as_object_mut()
+            {
+                details.insert("closed_by".into(), json!("paused"));
+            }
+            info!(
+                "[CONSENSUS INFO] Evento {:?} do target {} encerrado: target pausado.",
+                event.event_type, target_id
+            );
+            event
+        })
+        .collect()
+    }
+
+    /// Aplica o diagnóstico de falha local ao outage aberto, para que o
+    /// encerramento grave o mesmo `reason` e `details.diagnosis`.
+    pub fn annotate_outage(&mut self, diagnosis: &Diagnosis) {
+        if let Some(event) = self.outage.current_mut() {
+            diagnosis.annotate(event);
+        }
+    }
+
+    /// Marca o outage aberto como captive portal, para valer no encerramento.
+    pub fn annotate_captive_portal(&mut self, portal: &CaptivePortal) {
+        if let Some(event) = self.outage.current_mut() {
+            portal.annotate(event);
+        }
+    }
+
+    /// Sobe a severidade do outage aberto conforme a idade. Retorna o evento
+    /// atualizado quando houve escalonamento, para ser notificado novamente;
+    /// a trilha fica em `details.escalations` e segue até o encerramento.
+    pub fn escalate(&mut self, config: &EscalationConfig) -> Option<OutageEvent> {
+        let now = self.clock.now();
+        let age_secs = self.outage.age_secs()?;
+        let event = self.outage.current_mut()?;
+        let target = if age_secs >= config.critical_after_secs {
+            Severity::Critical
+        } else if age_secs >= config.major_after_secs {
+            Severity::Major
+        } else {
+            Severity::Minor
+        };
+        let current = Severity::of(event);
+        if target <= current {
+            return None;
+        }
+
+        let details = event.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("severity".into(), json!(target));
+            let trail = map.entry("escalations").or_insert_with(|| json!([]));
+            if let Some(trail) = trail.as_array_mut() {
+                trail.push(json!({
+                    "from": current,
+                    "to": target,
+                    "at": now,
+                    "age_secs": age_secs,
+                }));
+            }
+        }
+        info!(
+            "[CONSENSUS INFO] Outage escalonado de {} para {} após {}s em aberto.",
+            current, target, age_secs
+        );
+        Some(event.clone())
+    }
 }
@@ -0,0 +1,56 @@
+//! deadman.rs — Heartbeat externo (dead man's switch)
+//!
+//! Com `[dead_man_switch]`, cada probe faz um `GET` numa URL de heartbeat
+//! (healthchecks.io, Cronitor, Uptime Kuma "push" e compatíveis) ao fim de
+//! cada ciclo de monitoramento concluído com conectividade. O sistema externo
+//! alerta quando os pings param, o que cobre o caso que o próprio monitor não
+//! consegue reportar: o processo morto, travado ou sem rede.
+//!
+//! `{probe_id}` na URL é trocado pelo id do probe, para cada probe ter o seu
+//! check (ex: `https://hc-ping.com/<chave>/probe-{probe_id}`). Sem o
+//! placeholder, todos os probes pingam a mesma URL.
+
+use crate::config::DeadManSwitchConfig;
+use crate::types::Probe;
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+/// Cliente do heartbeat externo.
+#[derive(Debug, Clone)]
+pub struct DeadManSwitch {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl DeadManSwitch {
+    pub fn new(config: &DeadManSwitchConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP do heartbeat externo")?;
+        Ok(Self {
+            client,
+            url: config.url.clone(),
+        })
+    }
+
+    /// URL de heartbeat do probe.
+    pub fn url(&self, probe: &Probe) -> String {
+        self.url.replace("{probe_id}", &probe.id.to_string())
+    }
+
+    /// Registra um ciclo concluído do probe.
+    pub async fn ping(&self, probe: &Probe) -> Result<()> {
+        let response = self
+            .client
+            .get(self.url(probe))
+            .send()
+            .await
+            .context("Falha ao enviar heartbeat")?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("Heartbeat respondeu {}", status);
+        }
+        Ok(())
+    }
+}
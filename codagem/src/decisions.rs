@@ -0,0 +1,109 @@
+//! decisions.rs — Registro explicável das decisões do consenso
+//!
+//! Com `[consensus_decisions]` configurado, cada ciclo grava em
+//! `consensus_decisions` um resumo compacto do que o motor de consenso viu e
+//! decidiu: targets em falha no ciclo, targets que contaram para o consenso,
+//! tamanho da janela e o motivo de o outage ter (ou não) sido aberto. É a
+//! verdade de referência para ajustar thresholds e para postmortems. Registros
+//! mais antigos que `retention_days` são apagados periodicamente.
+
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Intervalo entre limpezas dos registros expirados.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Resultado de uma avaliação do consenso.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    /// Consenso atingido: outage aberto neste ciclo.
+    Opened,
+    /// Consenso perdido: outage encerrado neste ciclo.
+    Closed,
+    /// Outage já aberto e consenso mantido.
+    Ongoing,
+    /// Sem outage.
+    NoOutage,
+    /// Ciclo fora do consenso (ex: host sem recursos com `host.reject`).
+    Skipped,
+}
+
+impl DecisionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionOutcome::Opened => "opened",
+            DecisionOutcome::Closed => "closed",
+            DecisionOutcome::Ongoing => "ongoing",
+            DecisionOutcome::NoOutage => "no_outage",
+            DecisionOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// Decisão do consenso num ciclo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsensusDecision {
+    pub probe_id: i32,
+    /// 0 = ciclo não registrado (gravado como nulo).
+    pub cycle_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub outcome: DecisionOutcome,
+    /// Motivo: `consensus_reached`, `consensus_held`, `consensus_lost`,
+    /// `below_consensus`, `window_incomplete`, `resumed` (outage retomado após
+    /// restart, janela se recompondo) ou `host_rejected`.
+    pub reason: String,
+    pub history_len: i32,
+    pub fail_threshold: i32,
+    pub consensus: i32,
+    /// Targets em Down/Timeout neste ciclo.
+    pub failed_targets: Vec<i32>,
+    /// Targets que contaram para o consenso (falha em toda a janela ou no
+    /// threshold próprio do target).
+    pub counted_targets: Vec<i32>,
+    /// Contagens de falha por target na janela e thresholds por target.
+    pub details: serde_json::Value,
+}
+
+impl ConsensusDecision {
+    /// Decisão de um ciclo que não passou pelo consenso.
+    pub fn skipped(probe_id: i32, cycle_id: i64, timestamp: DateTime<Utc>, reason: &str) -> Self {
+        Self {
+            probe_id,
+            cycle_id,
+            timestamp,
+            outcome: DecisionOutcome::Skipped,
+            reason: reason.to_string(),
+            history_len: 0,
+            fail_threshold: 0,
+            consensus: 0,
+            failed_targets: Vec::new(),
+            counted_targets: Vec::new(),
+            details: serde_json::Value::Null,
+        }
+    }
+}
+
+/// Loop de limpeza dos registros mais antigos que `retention_days`.
+pub async fn run_retention(storage: Arc<Storage>, retention_days: u32) {
+    let mut ticker = interval(RETENTION_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+        match storage.delete_consensus_decisions_before(cutoff).await {
+            Ok(deleted) => debug!(
+                "[DECISIONS] {} decisão(ões) de consenso expirada(s) removida(s)",
+                deleted
+            ),
+            Err(e) => error!(
+                "[DECISIONS] Falha ao remover decisões de consenso expiradas: {:?}",
+                e
+            ),
+        }
+    }
+}
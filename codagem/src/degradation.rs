@@ -0,0 +1,69 @@
+//! degradation.rs — Thresholds de degradação por target
+//!
+//! Um target pode declarar RTT (`degraded_rtt_ms`) e perda
+//! (`degraded_loss_percent`) máximos. Aplicados logo após os checks: uma
+//! medição que responde, mas acima de algum deles, vira `Degraded` e recebe
+//! `details.degradation` com os valores e limites ultrapassados. O consenso
+//! usa essa marca para abrir eventos de degradação em `outage_events`, de modo
+//! que congestionamento crônico fique visível mesmo sem queda.
+
+use crate::types::{ConnectivityMetric, MetricStatus, Target};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Aplica os thresholds de cada target às métricas do ciclo.
+pub fn apply(metrics: &mut [ConnectivityMetric], targets: &[Target]) {
+    let thresholds: HashMap<i32, &Target> = targets
+        .iter()
+        .filter(|t| t.degraded_rtt_ms.is_some() || t.degraded_loss_percent.is_some())
+        .map(|t| (t.id, t))
+        .collect();
+    if thresholds.is_empty() {
+        return;
+    }
+    for metric in metrics {
+        let Some(target) = thresholds.get(&metric.target_id) else {
+            continue;
+        };
+        if metric.status != MetricStatus::Up && metric.status != MetricStatus::Degraded {
+            continue;
+        }
+        let mut exceeded = Vec::new();
+        if let (Some(rtt), Some(limit)) = (metric.response_time_ms, target.degraded_rtt_ms)
+            && rtt > limit
+        {
+            exceeded.push("rtt");
+        }
+        if let (Some(loss), Some(limit)) =
+            (metric.packet_loss_percent, target.degraded_loss_percent)
+            && loss > limit
+        {
+            exceeded.push("loss");
+        }
+        if exceeded.is_empty() {
+            continue;
+        }
+        metric.status = MetricStatus::Degraded;
+        let details = metric.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert(
+                "degradation".into(),
+                json!({
+                    "exceeded": exceeded,
+                    "rtt_ms": metric.response_time_ms,
+                    "rtt_threshold_ms": target.degraded_rtt_ms,
+                    "loss_percent": metric.packet_loss_percent,
+                    "loss_threshold_percent": target.degraded_loss_percent,
+                }),
+            );
+        }
+    }
+}
+
+/// A métrica ultrapassou os thresholds de degradação do target?
+pub fn exceeded(metric: &ConnectivityMetric) -> bool {
+    metric
+        .details
+        .as_ref()
+        .is_some_and(|d| d.get("degradation").is_some())
+}
@@ -0,0 +1,154 @@
+//! diagnosis.rs — Onde está a falha quando todos os targets caem
+//!
+//! Com `[diagnosis]`, um outage aberto num ciclo em que nenhum target
+//! respondeu dispara um diagnóstico do caminho de saída do probe: ping ao
+//! gateway padrão (`/proc/net/route`) e traceroute curto até o primeiro
+//! target afetado. A falha é classificada como:
+//!
+//! - `lan`: o gateway não responde (ou não há rota padrão); o problema está
+//!   na rede local do probe;
+//! - `cpe`: o gateway (ou o primeiro salto) responde, mas nenhum salto
+//!   depois dele; o roteador/CPE não está encaminhando para o provedor;
+//! - `upstream`: algum salto depois do gateway responde; a falha está no
+//!   provedor ou além dele.
+//!
+//! A classificação vai para o `reason` do outage (`lan_failure`,
+//! `cpe_failure`, `upstream_failure`) e os detalhes para `details.diagnosis`.
+
+use crate::config::{DiagnosisConfig, TracerouteConfig};
+use crate::ping;
+use crate::ratelimit::RateLimiter;
+use crate::traceroute::{self, Hop};
+use crate::types::{MetricStatus, OutageEvent, Probe, Target};
+use serde::Serialize;
+use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Trecho do caminho onde a falha está.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureLocation {
+    Lan,
+    Cpe,
+    Upstream,
+}
+
+impl FailureLocation {
+    /// `reason` gravado no outage.
+    pub fn reason(self) -> &'static str {
+        match self {
+            FailureLocation::Lan => "lan_failure",
+            FailureLocation::Cpe => "cpe_failure",
+            FailureLocation::Upstream => "upstream_failure",
+        }
+    }
+}
+
+/// Resultado do diagnóstico, gravado em `details.diagnosis`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnosis {
+    pub location: FailureLocation,
+    /// Gateway padrão IPv4 do host, se houver.
+    pub gateway: Option<IpAddr>,
+    pub gateway_reachable: bool,
+    /// Destino do traceroute (primeiro target afetado).
+    pub destination: IpAddr,
+    /// Primeiro salto depois do gateway que respondeu.
+    pub upstream_hop: Option<IpAddr>,
+    pub hops: Vec<Hop>,
+}
+
+impl Diagnosis {
+    /// Aplica o diagnóstico ao outage: `reason` e `details.diagnosis`.
+    pub fn annotate(&self, event: &mut OutageEvent) {
+        event.reason = Some(self.location.reason().to_string());
+        let details = event.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("diagnosis".into(), json!(self));
+        }
+    }
+}
+
+/// Gateway padrão IPv4, lido de `/proc/net/route`.
+pub fn default_gateway() -> Option<IpAddr> {
+    let table = std::fs::read_to_string("/proc/net/route").ok()?;
+    // Iface Destination Gateway Flags ... Mask ...; endereços em hex na
+    // ordem de bytes do host
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 || fields[1] != "00000000" || fields[7] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        (gateway != 0).then(|| IpAddr::V4(Ipv4Addr::from(gateway.to_ne_bytes())))
+    })
+}
+
+/// Diagnostica o caminho de saída do probe até `destination`.
+pub async fn diagnose(
+    config: &DiagnosisConfig,
+    destination: IpAddr,
+    probe: &Probe,
+    limiter: Arc<RateLimiter>,
+) -> Diagnosis {
+    let gateway = default_gateway();
+    let gateway_reachable = match gateway {
+        Some(address) => {
+            let target = Target::unregistered(format!("gateway {}", address), address);
+            let timeout_secs = config.timeout_ms.div_ceil(1000).max(1);
+            ping::ping_targets(&[target], probe, 1, timeout_secs, 0, limiter)
+                .await
+                .iter()
+                .any(|m| m.status == MetricStatus::Up)
+        }
+        None => false,
+    };
+
+    let trace_config = TracerouteConfig {
+        interval_cycles: 1,
+        max_hops: config.max_hops,
+        probes_per_hop: config.probes_per_hop,
+        timeout_ms: config.timeout_ms,
+        port: config.port,
+        asn_lookup: false,
+    };
+    let hops =
+        match tokio::task::spawn_blocking(move || traceroute::trace(destination, &trace_config))
+            .await
+        {
+            Ok(Ok((_, hops))) => hops,
+            Ok(Err(e)) => {
+                debug!(
+                    "[DIAGNOSIS {}] Falha no traceroute até {}: {}",
+                    probe.location, destination, e
+                );
+                Vec::new()
+            }
+            Err(_) => Vec::new(),
+        };
+
+    // O primeiro salto é o gateway (ou o roteador que faz esse papel)
+    let first_hop_answered = hops.first().is_some_and(|h| h.address.is_some());
+    let upstream_hop = hops
+        .iter()
+        .skip(1)
+        .filter_map(|h| h.address)
+        .find(|&address| Some(address) != gateway);
+    let location = if upstream_hop.is_some() {
+        FailureLocation::Upstream
+    } else if gateway_reachable || first_hop_answered {
+        FailureLocation::Cpe
+    } else {
+        FailureLocation::Lan
+    };
+    Diagnosis {
+        location,
+        gateway,
+        gateway_reachable,
+        destination,
+        upstream_hop,
+        hops,
+    }
+}
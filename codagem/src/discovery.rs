@@ -0,0 +1,199 @@
+//! discovery.rs — Descoberta automática de targets
+//!
+//! Na inicialização, cadastra targets óbvios para que uma instalação nova já
+//! monitore algo útil: o gateway padrão (de `/proc/net/route` e
+//! `/proc/net/ipv6_route`), os servidores DNS do `resolv.conf` e uma lista de
+//! IPs anycast bem conhecidos. Endereços já cadastrados são ignorados.
+
+use crate::config::DiscoveryConfig;
+use crate::registration::{self, Registration};
+use crate::storage::Storage;
+use crate::target_address::TargetAddress;
+use crate::templates::TemplateSet;
+use crate::types::Target;
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use tracing::{debug, info, warn};
+
+/// IPs anycast bem conhecidos: (endereço, nome, provedor, ASN).
+const WELL_KNOWN_ANYCAST: &[(&str, &str, &str, i32)] = &[
+    ("1.1.1.1", "Cloudflare DNS", "Cloudflare", 13335),
+    ("8.8.8.8", "Google Public DNS", "Google", 15169),
+    ("9.9.9.9", "Quad9 DNS", "Quad9", 19281),
+    (
+        "2606:4700:4700::1111",
+        "Cloudflare DNS (IPv6)",
+        "Cloudflare",
+        13335,
+    ),
+    (
+        "2001:4860:4860::8888",
+        "Google Public DNS (IPv6)",
+        "Google",
+        15169,
+    ),
+];
+
+/// Lista padrão de anycast usada quando `discovery.anycast` não é informado.
+pub fn default_anycast() -> Vec<IpAddr> {
+    WELL_KNOWN_ANYCAST
+        .iter()
+        .filter_map(|(address, ..)| address.parse().ok())
+        .collect()
+}
+
+/// Lê o gateway padrão IPv4 de `/proc/net/route`.
+///
+/// Os endereços vêm em hexadecimal little-endian; a rota padrão tem destino
+/// `00000000` e a flag `RTF_GATEWAY` (0x2).
+fn default_gateway_v4(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (destination, gateway, flags) = (fields.get(1)?, fields.get(2)?, fields.get(3)?);
+        let flags = u16::from_str_radix(flags, 16).ok()?;
+        if *destination != "00000000" || flags & 0x2 == 0 {
+            return None;
+        }
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_le_bytes()))
+    })
+}
+
+/// Lê o gateway padrão IPv6 de `/proc/net/ipv6_route`, ignorando next-hops
+/// link-local (exigiriam scope id para o ping).
+fn default_gateway_v6(route_table: &str) -> Option<Ipv6Addr> {
+    route_table.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (destination, prefix, next_hop) = (fields.first()?, fields.get(1)?, fields.get(4)?);
+        if destination.chars().any(|c| c != '0') || *prefix != "00" {
+            return None;
+        }
+        let next_hop = Ipv6Addr::from(u128::from_str_radix(next_hop, 16).ok()?);
+        let link_local = next_hop.segments()[0] & 0xffc0 == 0xfe80;
+        (!next_hop.is_unspecified() && !link_local).then_some(next_hop)
+    })
+}
+
+/// Extrai os servidores `nameserver` de um `resolv.conf`, ignorando
+/// resolvers locais (loopback, ex: systemd-resolved em 127.0.0.53).
+fn resolvers(resolv_conf: &str) -> Vec<IpAddr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse::<IpAddr>().ok())
+        .filter(|ip| !ip.is_loopback())
+        .collect()
+}
+
+/// Monta o candidato aplicando o template embutido correspondente; a região
+/// da descoberta tem precedência sobre a do template.
+fn candidate(
+    templates: &TemplateSet,
+    name: String,
+    address: IpAddr,
+    template: &str,
+    region: &str,
+) -> Target {
+    let request = Registration {
+        region: Some(region.to_string()),
+        ..Registration::default()
+    };
+    registration::build_target(
+        name,
+        address,
+        &TargetAddress::Ip { ip: address },
+        templates.get(template),
+        &request,
+    )
+}
+
+/// Coleta os targets candidatos conforme a configuração.
+fn candidates(config: &DiscoveryConfig, templates: &TemplateSet) -> Vec<Target> {
+    let mut found = Vec::new();
+
+    if config.gateway {
+        let v4 = std::fs::read_to_string("/proc/net/route")
+            .ok()
+            .and_then(|table| default_gateway_v4(&table));
+        let v6 = std::fs::read_to_string("/proc/net/ipv6_route")
+            .ok()
+            .and_then(|table| default_gateway_v6(&table));
+        if v4.is_none() && v6.is_none() {
+            warn!("[DISCOVERY] Gateway padrão não encontrado na tabela de rotas");
+        }
+        for gateway in [v4.map(IpAddr::V4), v6.map(IpAddr::V6)]
+            .into_iter()
+            .flatten()
+        {
+            found.push(candidate(
+                templates,
+                format!("Gateway padrão ({})", gateway),
+                gateway,
+                "gateway",
+                "local",
+            ));
+        }
+    }
+
+    if config.resolvers {
+        match std::fs::read_to_string(&config.resolv_conf_path) {
+            Ok(contents) => {
+                for resolver in resolvers(&contents) {
+                    found.push(candidate(
+                        templates,
+                        format!("Resolver DNS ({})", resolver),
+                        resolver,
+                        "public-dns",
+                        "local",
+                    ));
+                }
+            }
+            Err(e) => warn!(
+                "[DISCOVERY] Falha ao ler {}: {}",
+                config.resolv_conf_path, e
+            ),
+        }
+    }
+
+    for address in &config.anycast {
+        let known = WELL_KNOWN_ANYCAST
+            .iter()
+            .find(|(ip, ..)| ip.parse::<IpAddr>().ok() == Some(*address));
+        let mut target = candidate(
+            templates,
+            known.map_or_else(|| format!("Anycast ({})", address), |k| k.1.to_string()),
+            *address,
+            "public-dns",
+            "global",
+        );
+        if let Some((_, _, provider, asn)) = known {
+            target.provider = Some(provider.to_string());
+            target.asn = Some(*asn);
+        }
+        found.push(target);
+    }
+
+    found
+}
+
+/// Cadastra os targets descobertos. Retorna quantos foram inseridos.
+pub async fn discover(
+    config: &DiscoveryConfig,
+    storage: &Storage,
+    templates: &TemplateSet,
+) -> Result<usize> {
+    let mut inserted = 0;
+    for target in candidates(config, templates) {
+        match storage.insert_target(&target).await? {
+            Some(id) => {
+                info!(
+                    "🔎 Target descoberto: {} ({}) -> id {}",
+                    target.name, target.address, id
+                );
+                inserted += 1;
+            }
+            None => debug!("[DISCOVERY] {} já cadastrado", target.address),
+        }
+    }
+    Ok(inserted)
+}
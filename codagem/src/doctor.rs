@@ -0,0 +1,371 @@
+//! doctor.rs — Autoteste de ambiente (`doctor`)
+//!
+//! Responde ao "por que não sobe?" sem ler logs: confere, em sequência, a
+//! configuração, a conexão ao banco e a versão do schema, a extensão
+//! TimescaleDB e a réplica de leitura (se configuradas), a permissão de
+//! abrir sockets ICMP, a resolução DNS, uma conexão TCP de saída e o relógio
+//! (offset contra um servidor NTP), e imprime um relatório de
+//! aprovado/reprovado. Os checks de rede usam a
+//! origem de `checks.sources`/`checks.source`, como o monitor. Sai com erro
+//! se algum item falhar; avisos (migrações pendentes, réplica ou NTP
+//! inacessíveis) não impedem a subida.
+
+use crate::cli::DoctorArgs;
+use crate::config::{Config, SourceConfig};
+use crate::migrations::MIGRATIONS;
+use crate::ping;
+use crate::source;
+use crate::storage::Storage;
+use anyhow::{Result, bail};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::timeout;
+
+/// Segundos entre a época do NTP (1900) e a do Unix (1970).
+const NTP_UNIX_OFFSET_SECS: f64 = 2_208_988_800.0;
+
+/// Prazo de cada verificação de rede.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// Não conferido porque um item anterior falhou.
+    Skip,
+}
+
+impl Status {
+    fn icon(&self) -> &'static str {
+        match self {
+            Status::Pass => "✅",
+            Status::Warn => "⚠️ ",
+            Status::Fail => "❌",
+            Status::Skip => "➖",
+        }
+    }
+}
+
+/// Resultados das verificações, impressos à medida que saem.
+#[derive(Default)]
+struct Report {
+    statuses: Vec<Status>,
+}
+
+impl Report {
+    fn add(&mut self, name: &str, status: Status, detail: impl AsRef<str>) {
+        println!("{} {:<16} {}", status.icon(), name, detail.as_ref());
+        self.statuses.push(status);
+    }
+
+    fn count(&self, status: Status) -> usize {
+        self.statuses.iter().filter(|s| **s == status).count()
+    }
+}
+
+/// Roda todas as verificações com a configuração em `path`.
+pub async fn run(path: Option<&str>, args: &DoctorArgs) -> Result<()> {
+    let mut report = Report::default();
+
+    let config = match Config::load(path) {
+        Ok(config) => {
+            report.add("Configuração", Status::Pass, "carregada e válida");
+            Some(config)
+        }
+        Err(e) => {
+            report.add("Configuração", Status::Fail, format!("{:#}", e));
+            None
+        }
+    };
+
+    match &config {
+        Some(config) => check_database(&mut report, config).await,
+        None => {
+            report.add("Banco de dados", Status::Skip, "sem configuração");
+            report.add("Schema", Status::Skip, "sem configuração");
+        }
+    }
+
+    let sources = config.as_ref().map(|c| &c.checks);
+    let source_for = |name: &str| {
+        sources.map_or_else(SourceConfig::default, |checks| {
+            checks.source_for(name).clone()
+        })
+    };
+
+    match ping::self_test(&source_for("ping"), args.icmp_target, NETWORK_TIMEOUT).await {
+        Ok(rtt) => report.add(
+            "ICMP",
+            Status::Pass,
+            format!(
+                "{} respondeu em {:.1} ms",
+                args.icmp_target,
+                rtt.as_secs_f64() * 1000.0
+            ),
+        ),
+        Err(e) => report.add(
+            "ICMP",
+            Status::Fail,
+            format!(
+                "{} (conceda CAP_NET_RAW ou inclua o grupo do processo em net.ipv4.ping_group_range)",
+                e
+            ),
+        ),
+    }
+
+    let started = Instant::now();
+    match timeout(
+        NETWORK_TIMEOUT,
+        tokio::net::lookup_host((args.dns_name.as_str(), 0)),
+    )
+    .await
+    {
+        Ok(Ok(addresses)) => {
+            let addresses: Vec<String> = addresses.map(|a| a.ip().to_string()).collect();
+            report.add(
+                "DNS",
+                Status::Pass,
+                format!(
+                    "{} → {} em {:.1} ms",
+                    args.dns_name,
+                    addresses.join(", "),
+                    elapsed_ms(started)
+                ),
+            );
+        }
+        Ok(Err(e)) => report.add("DNS", Status::Fail, format!("{}: {}", args.dns_name, e)),
+        Err(_) => report.add(
+            "DNS",
+            Status::Fail,
+            format!("{}: sem resposta em {:?}", args.dns_name, NETWORK_TIMEOUT),
+        ),
+    }
+
+    match check_tcp(&source_for("tcp"), &args.tcp_target).await {
+        Ok(ms) => report.add(
+            "TCP de saída",
+            Status::Pass,
+            format!("{} conectado em {:.1} ms", args.tcp_target, ms),
+        ),
+        Err(e) => report.add(
+            "TCP de saída",
+            Status::Fail,
+            format!("{}: {}", args.tcp_target, e),
+        ),
+    }
+
+    match clock_offset_ms(&args.ntp_server).await {
+        Ok(offset) if offset.abs() <= args.max_clock_offset_ms as f64 => report.add(
+            "Relógio",
+            Status::Pass,
+            format!("offset de {:+.1} ms ({})", offset, args.ntp_server),
+        ),
+        Ok(offset) => report.add(
+            "Relógio",
+            Status::Fail,
+            format!(
+                "offset de {:+.1} ms contra {} (limite: {} ms); horários das medições e dos outages ficam fora, sincronize o relógio (NTP)",
+                offset, args.ntp_server, args.max_clock_offset_ms
+            ),
+        ),
+        Err(e) => report.add(
+            "Relógio",
+            Status::Warn,
+            format!("não conferido, NTP {} inacessível: {}", args.ntp_server, e),
+        ),
+    }
+
+    let failed = report.count(Status::Fail);
+    let warnings = report.count(Status::Warn);
+    println!();
+    if failed == 0 {
+        println!("✅ Tudo certo ({} aviso(s))", warnings);
+        return Ok(());
+    }
+    println!("❌ {} falha(s), {} aviso(s)", failed, warnings);
+    bail!("doctor: {} verificação(ões) falharam", failed)
+}
+
+/// Conexão ao banco e versão do schema contra as migrações do binário.
+async fn check_database(report: &mut Report, config: &Config) {
+    let started = Instant::now();
+    let storage = match timeout(
+        Duration::from_secs(10),
+        Storage::connect(&config.database_url),
+    )
+    .await
+    {
+        Ok(Ok(storage)) => {
+            report.add(
+                "Banco de dados",
+                Status::Pass,
+                format!("conectado em {:.1} ms", elapsed_ms(started)),
+            );
+            storage
+        }
+        Ok(Err(e)) => {
+            report.add("Banco de dados", Status::Fail, format!("{:#}", e));
+            report.add("Schema", Status::Skip, "sem banco");
+            return;
+        }
+        Err(_) => {
+            report.add("Banco de dados", Status::Fail, "timeout ao conectar (10s)");
+            report.add("Schema", Status::Skip, "sem banco");
+            return;
+        }
+    };
+
+    let latest = MIGRATIONS.last().map_or(0, |m| m.version);
+    match storage.schema_versions().await {
+        Ok(applied) => {
+            let unknown: Vec<i32> = applied
+                .iter()
+                .copied()
+                .filter(|v| !MIGRATIONS.iter().any(|m| m.version == *v))
+                .collect();
+            let pending = MIGRATIONS
+                .iter()
+                .filter(|m| !applied.contains(&m.version))
+                .count();
+            let current = applied.last().copied().unwrap_or(0);
+            if !unknown.is_empty() {
+                report.add(
+                    "Schema",
+                    Status::Fail,
+                    format!(
+                        "versão {}, com migrações desconhecidas por este binário ({:?}): o banco é de uma versão mais nova",
+                        current, unknown
+                    ),
+                );
+            } else if pending > 0 {
+                report.add(
+                    "Schema",
+                    Status::Warn,
+                    format!(
+                        "versão {} de {}: {} migração(ões) pendente(s), aplicadas na próxima subida",
+                        current, latest, pending
+                    ),
+                );
+            } else {
+                report.add("Schema", Status::Pass, format!("versão {} (atual)", latest));
+            }
+        }
+        Err(e) => report.add("Schema", Status::Fail, format!("{:#}", e)),
+    }
+
+    // A subida cria a extensão, mas ela precisa existir no servidor
+    if config.timescale.is_some() {
+        match storage.timescale_versions().await {
+            Ok(Some((Some(installed), _))) => report.add(
+                "TimescaleDB",
+                Status::Pass,
+                format!("extensão {}", installed),
+            ),
+            Ok(Some((None, available))) => report.add(
+                "TimescaleDB",
+                Status::Warn,
+                format!(
+                    "extensão {} disponível, criada na próxima subida",
+                    available
+                ),
+            ),
+            Ok(None) => report.add(
+                "TimescaleDB",
+                Status::Fail,
+                "extensão timescaledb indisponível no servidor",
+            ),
+            Err(e) => report.add("TimescaleDB", Status::Fail, format!("{:#}", e)),
+        }
+    }
+
+    // Sem a réplica, as consultas vão ao primário: só um aviso
+    if let Some(read_url) = &config.database_read_url {
+        let started = Instant::now();
+        match timeout(Duration::from_secs(10), Storage::connect(read_url)).await {
+            Ok(Ok(_)) => report.add(
+                "Réplica",
+                Status::Pass,
+                format!("conectada em {:.1} ms", elapsed_ms(started)),
+            ),
+            Ok(Err(e)) => report.add(
+                "Réplica",
+                Status::Warn,
+                format!("{:#}; as consultas usariam o primário", e),
+            ),
+            Err(_) => report.add(
+                "Réplica",
+                Status::Warn,
+                "timeout ao conectar (10s); as consultas usariam o primário",
+            ),
+        }
+    }
+}
+
+/// Tempo até conectar em `target` (`host:porta`), em ms.
+async fn check_tcp(source: &SourceConfig, target: &str) -> Result<f64, String> {
+    let started = Instant::now();
+    let connect = async {
+        let addr = tokio::net::lookup_host(target)
+            .await
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| "nome sem endereços".to_string())?;
+        source::tcp_connect(source, addr)
+            .await
+            .map_err(|e| e.to_string())
+    };
+    timeout(NETWORK_TIMEOUT, connect)
+        .await
+        .map_err(|_| format!("sem conexão em {:?}", NETWORK_TIMEOUT))??;
+    Ok(elapsed_ms(started))
+}
+
+/// Offset do relógio local contra `server` por SNTP (RFC 4330), em ms:
+/// positivo com o relógio local atrasado.
+async fn clock_offset_ms(server: &str) -> Result<f64, String> {
+    let exchange = async {
+        let addr: SocketAddr = tokio::net::lookup_host((server, 123))
+            .await
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| "nome sem endereços".to_string())?;
+        let socket = source::udp_connect(&SourceConfig::default(), addr)
+            .await
+            .map_err(|e| e.to_string())?;
+        // LI = 0, versão 4, modo cliente
+        let mut request = [0u8; 48];
+        request[0] = 0x23;
+        let sent = unix_secs(SystemTime::now());
+        socket.send(&request).await.map_err(|e| e.to_string())?;
+        let mut reply = [0u8; 48];
+        let len = socket.recv(&mut reply).await.map_err(|e| e.to_string())?;
+        let received = unix_secs(SystemTime::now());
+        // Modo servidor e stratum válido (0 é um "kiss-o'-death")
+        if len < 48 || reply[0] & 0x07 != 4 || reply[1] == 0 {
+            return Err("resposta NTP inválida".to_string());
+        }
+        let server_received = ntp_secs(&reply[32..40]);
+        let server_sent = ntp_secs(&reply[40..48]);
+        Ok(((server_received - sent) + (server_sent - received)) / 2.0 * 1000.0)
+    };
+    timeout(NETWORK_TIMEOUT, exchange)
+        .await
+        .map_err(|_| format!("sem resposta em {:?}", NETWORK_TIMEOUT))?
+}
+
+/// Timestamp NTP (segundos e fração de 32 bits) em segundos Unix.
+fn ntp_secs(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+    seconds - NTP_UNIX_OFFSET_SECS + fraction / 4_294_967_296.0
+}
+
+fn unix_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
@@ -0,0 +1,203 @@
+//! enrichment.rs — ASN e provedor dos targets por consulta externa
+//!
+//! `asn` e `provider` dos targets são opcionais e quase nunca preenchidos à
+//! mão. Com `[enrichment]` configurado, uma task percorre os targets a cada
+//! `interval_secs`, consulta o ASN de origem do prefixo de cada endereço
+//! público e o nome do AS no RIR (DNS da Team Cymru ou API do RIPEstat) e
+//! grava os campos vazios (todos, com `overwrite = true`).
+//!
+//! Os outages gravados levam em `affected_asns` os ASNs dos targets
+//! afetados; a cada passada os eventos ainda sem ASN são marcados, o que
+//! permite agregar quedas por provedor.
+
+use crate::config::{EnrichmentConfig, EnrichmentSource};
+use crate::storage::Storage;
+use crate::traceroute::{self, AsnResolver};
+use crate::types::Target;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error, info, warn};
+
+/// Timeout das consultas ao RIPEstat.
+pub const RIPESTAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Consulta de ASN e nome do AS.
+enum Lookup {
+    Cymru(Box<AsnResolver>),
+    RipeStat(reqwest::Client),
+}
+
+impl Lookup {
+    fn new(source: EnrichmentSource) -> Result<Self> {
+        Ok(match source {
+            EnrichmentSource::Cymru => Lookup::Cymru(Box::new(
+                AsnResolver::from_system_conf().context("Falha ao criar o resolver DNS")?,
+            )),
+            EnrichmentSource::RipeStat => Lookup::RipeStat(
+                reqwest::Client::builder()
+                    .timeout(RIPESTAT_TIMEOUT)
+                    .build()
+                    .context("Falha ao criar cliente HTTP do RIPEstat")?,
+            ),
+        })
+    }
+
+    async fn origin(&self, address: IpAddr) -> Option<i32> {
+        match self {
+            Lookup::Cymru(resolver) => resolver.lookup(address).await,
+            Lookup::RipeStat(client) => {
+                if !traceroute::is_public(address) {
+                    return None;
+                }
+                // {"data": {"asns": [{"asn": 13335, "holder": "..."}], ...}}
+                let data = ripestat(
+                    client,
+                    "prefix-overview",
+                    &[("resource", &address.to_string())],
+                )
+                .await?;
+                data["asns"][0]["asn"].as_i64().map(|asn| asn as i32)
+            }
+        }
+    }
+
+    async fn name(&self, asn: i32) -> Option<String> {
+        match self {
+            Lookup::Cymru(resolver) => resolver.lookup_name(asn).await,
+            Lookup::RipeStat(client) => {
+                // {"data": {"holder": "CLOUDFLARENET - Cloudflare, Inc.", ...}}
+                let data = ripestat(
+                    client,
+                    "as-overview",
+                    &[("resource", &format!("AS{}", asn))],
+                )
+                .await?;
+                data["holder"]
+                    .as_str()
+                    .filter(|h| !h.is_empty())
+                    .map(str::to_string)
+            }
+        }
+    }
+}
+
+/// Campo `data` de uma consulta à API do RIPEstat.
+pub async fn ripestat(
+    client: &reqwest::Client,
+    call: &str,
+    params: &[(&str, &str)],
+) -> Option<serde_json::Value> {
+    let response = client
+        .get(format!("https://stat.ripe.net/data/{}/data.json", call))
+        .query(params)
+        .query(&[("sourceapp", "monitoramento_rede")])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match response {
+        Ok(response) => response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .map(|mut body| body["data"].take()),
+        Err(e) => {
+            debug!("[RIPESTAT] {} {:?} indisponível: {}", call, params, e);
+            None
+        }
+    }
+}
+
+/// Loop de enriquecimento; a primeira passada é imediata.
+pub async fn run(storage: Arc<Storage>, config: EnrichmentConfig) {
+    let lookup = match Lookup::new(config.source) {
+        Ok(lookup) => lookup,
+        Err(e) => {
+            error!("[ENRICHMENT] Enriquecimento desabilitado: {:?}", e);
+            return;
+        }
+    };
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        match enrich(&storage, &lookup, config.overwrite).await {
+            Ok(0) => debug!("[ENRICHMENT] Nenhum target atualizado"),
+            Ok(updated) => info!(
+                "🏷️  [ENRICHMENT] ASN/provedor atualizado(s) em {} target(s)",
+                updated
+            ),
+            Err(e) => warn!("[ENRICHMENT] Falha no enriquecimento dos targets: {:?}", e),
+        }
+        match storage.tag_outage_event_asns().await {
+            Ok(0) => {}
+            Ok(tagged) => info!(
+                "🏷️  [ENRICHMENT] {} outage(s) marcado(s) com os ASNs afetados",
+                tagged
+            ),
+            Err(e) => warn!("[ENRICHMENT] Falha ao marcar ASNs dos outages: {:?}", e),
+        }
+    }
+}
+
+/// Uma passada sobre os targets; retorna quantos foram atualizados.
+async fn enrich(storage: &Storage, lookup: &Lookup, overwrite: bool) -> Result<usize> {
+    let targets = storage.list_targets().await?;
+    let mut names: HashMap<i32, Option<String>> = HashMap::new();
+    let mut updated = 0;
+    for target in targets
+        .iter()
+        .filter(|t| overwrite || t.asn.is_none() || t.provider.is_none())
+    {
+        let Some((asn, provider)) = resolve(lookup, target, overwrite, &mut names).await else {
+            continue;
+        };
+        if Some(asn) == target.asn && provider == target.provider {
+            continue;
+        }
+        storage
+            .update_target_asn(target.id, Some(asn), provider.as_deref())
+            .await?;
+        debug!(
+            "[ENRICHMENT] Target {} ({}): AS{} {}",
+            target.name,
+            target.address,
+            asn,
+            provider.as_deref().unwrap_or("-")
+        );
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// Valores novos de `asn` e `provider`; `None` se o endereço não tem ASN.
+/// Sem `overwrite`, um ASN preenchido à mão é mantido e só o nome dele é
+/// consultado.
+async fn resolve(
+    lookup: &Lookup,
+    target: &Target,
+    overwrite: bool,
+    names: &mut HashMap<i32, Option<String>>,
+) -> Option<(i32, Option<String>)> {
+    let asn = match target.asn {
+        Some(asn) if !overwrite => asn,
+        _ => lookup.origin(target.address).await?,
+    };
+    let provider = match &target.provider {
+        Some(provider) if !overwrite => Some(provider.clone()),
+        _ => {
+            let name = match names.get(&asn) {
+                Some(name) => name.clone(),
+                None => {
+                    let name = lookup.name(asn).await;
+                    names.insert(asn, name.clone());
+                    name
+                }
+            };
+            name.or_else(|| target.provider.clone())
+        }
+    };
+    Some((asn, provider))
+}
@@ -0,0 +1,417 @@
+//! export.rs — Exportação de métricas e outages (`monitoramento_rede export`)
+//!
+//! Gera um arquivo com as métricas (`connectivity_metrics`) ou os outages
+//! (`outage_events`) de uma janela, para análise sem acesso ao banco
+//! (`pandas.read_csv`, `read_json(lines=True)`, `read_parquet`). Filtros por
+//! probe e target são opcionais; sem eles, entra tudo da janela.
+//!
+//! As métricas são lidas em páginas de [`PAGE_SIZE`] e escritas à medida que
+//! chegam, então janelas longas não precisam caber em memória (no Parquet,
+//! cada página vira um row group). Nomes de probe e target acompanham os ids;
+//! com `[anonymization]` e `exports = true`, nome e endereço dos targets saem
+//! pseudonimizados (os filtros continuam aceitando os valores reais).
+//! Colunas estruturadas (`details`, listas de ids) saem como JSON: texto no
+//! CSV e no Parquet, valores nativos no JSON Lines.
+
+use crate::anonymize::Pseudonymizer;
+use crate::bundle::csv_field;
+use crate::cli::{ExportArgs, ExportData, ExportFormat};
+use crate::storage::Storage;
+use crate::types::{ConnectivityMetric, OutageEvent, Probe, Target};
+use anyhow::{Context, Result, bail};
+use arrow_array::{
+    ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Duration, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Métricas lidas do banco por consulta.
+const PAGE_SIZE: i64 = 10_000;
+
+/// Tipo de uma coluna.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Int,
+    Float,
+    Text,
+    Time,
+    Json,
+}
+
+/// Valor de uma célula; `Null` em qualquer coluna.
+enum Cell {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Time(DateTime<Utc>),
+    Json(Value),
+    Null,
+}
+
+type Columns = &'static [(&'static str, Kind)];
+
+const METRIC_COLUMNS: Columns = &[
+    ("id", Kind::Int),
+    ("cycle_id", Kind::Int),
+    ("timestamp", Kind::Time),
+    ("probe_id", Kind::Int),
+    ("probe", Kind::Text),
+    ("target_id", Kind::Int),
+    ("target", Kind::Text),
+    ("target_address", Kind::Text),
+    ("metric_type", Kind::Text),
+    ("status", Kind::Text),
+    ("response_time_ms", Kind::Float),
+    ("packet_loss_percent", Kind::Int),
+    ("error_kind", Kind::Text),
+    ("error_message", Kind::Text),
+    ("details", Kind::Json),
+];
+
+const OUTAGE_COLUMNS: Columns = &[
+    ("id", Kind::Int),
+    ("event_type", Kind::Text),
+    ("start_time", Kind::Time),
+    ("end_time", Kind::Time),
+    ("duration_seconds", Kind::Int),
+    ("reason", Kind::Text),
+    ("consensus_level", Kind::Int),
+    ("affected_probes", Kind::Json),
+    ("affected_targets", Kind::Json),
+    ("affected_asns", Kind::Json),
+    ("details", Kind::Json),
+];
+
+/// Exporta os dados pedidos e imprime onde o arquivo foi gravado.
+pub async fn run(
+    storage: &Storage,
+    args: ExportArgs,
+    pseudonymizer: Option<&Pseudonymizer>,
+) -> Result<()> {
+    let to = args.to.unwrap_or_else(Utc::now);
+    let from = args.from.unwrap_or(to - Duration::hours(24));
+    if from >= to {
+        bail!("--from deve ser anterior a --to");
+    }
+    let probes = storage.list_probes().await?;
+    let targets = storage.list_targets().await?;
+    let probe_ids = select(&args.probes, "probe", |input| {
+        probes
+            .iter()
+            .find(|p| p.id.to_string() == input || p.location == input)
+            .map(|p| p.id)
+    })?;
+    let target_ids = select(&args.targets, "target", |input| {
+        targets
+            .iter()
+            .find(|t| {
+                t.id.to_string() == input || t.address.to_string() == input || t.name == input
+            })
+            .map(|t| t.id)
+    })?;
+
+    let path = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}.{}",
+            args.data.as_str(),
+            args.format.extension()
+        ))
+    });
+    let (count, what) = match args.data {
+        ExportData::Metrics => {
+            let mut output = Output::create(&path, args.format, METRIC_COLUMNS)?;
+            let exported: Vec<Target> = match pseudonymizer {
+                Some(p) if p.exports => targets.iter().map(|t| p.target(t)).collect(),
+                _ => targets.clone(),
+            };
+            let names = Names::new(&probes, &exported);
+            let mut count = 0;
+            let mut after_id = 0;
+            loop {
+                let page = storage
+                    .list_metrics_page(
+                        probe_ids.as_deref(),
+                        target_ids.as_deref(),
+                        from,
+                        to,
+                        after_id,
+                        PAGE_SIZE,
+                    )
+                    .await?;
+                let Some(last) = page.last() else {
+                    break;
+                };
+                after_id = last.id;
+                count += page.len();
+                let rows: Vec<Vec<Cell>> = page.iter().map(|m| metric_row(m, &names)).collect();
+                output.write(&rows)?;
+            }
+            output.finish()?;
+            (count, "métrica(s)")
+        }
+        ExportData::Outages => {
+            let events = storage
+                .list_outage_events_between(probe_ids.as_deref(), target_ids.as_deref(), from, to)
+                .await?;
+            let mut output = Output::create(&path, args.format, OUTAGE_COLUMNS)?;
+            let rows: Vec<Vec<Cell>> = events.iter().map(outage_row).collect();
+            output.write(&rows)?;
+            output.finish()?;
+            (events.len(), "outage(s)")
+        }
+    };
+    println!(
+        "{} {} de {} a {} exportado(s) em {}",
+        count,
+        what,
+        from.to_rfc3339(),
+        to.to_rfc3339(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Ids dos filtros informados; `None` sem filtro.
+fn select(
+    inputs: &[String],
+    what: &str,
+    find: impl Fn(&str) -> Option<i32>,
+) -> Result<Option<Vec<i32>>> {
+    if inputs.is_empty() {
+        return Ok(None);
+    }
+    inputs
+        .iter()
+        .map(|input| find(input).with_context(|| format!("{} não encontrado: `{}`", what, input)))
+        .collect::<Result<Vec<i32>>>()
+        .map(Some)
+}
+
+/// Nome dos probes e nome e endereço dos targets, por id.
+struct Names<'a> {
+    probes: HashMap<i32, &'a str>,
+    targets: HashMap<i32, &'a Target>,
+}
+
+impl<'a> Names<'a> {
+    fn new(probes: &'a [Probe], targets: &'a [Target]) -> Self {
+        Self {
+            probes: probes.iter().map(|p| (p.id, p.location.as_str())).collect(),
+            targets: targets.iter().map(|t| (t.id, t)).collect(),
+        }
+    }
+}
+
+fn metric_row(m: &ConnectivityMetric, names: &Names) -> Vec<Cell> {
+    let target = names.targets.get(&m.target_id);
+    vec![
+        Cell::Int(m.id),
+        // cycle_id 0 = métrica sem ciclo (NULL no banco)
+        if m.cycle_id == 0 {
+            Cell::Null
+        } else {
+            Cell::Int(m.cycle_id)
+        },
+        Cell::Time(m.timestamp),
+        Cell::Int(m.probe_id.into()),
+        text(names.probes.get(&m.probe_id).copied()),
+        Cell::Int(m.target_id.into()),
+        text(target.map(|t| t.name.as_str())),
+        text(target.map(|t| t.address.to_string()).as_deref()),
+        Cell::Text(m.metric_type.as_str().to_string()),
+        Cell::Text(m.status.as_str().to_string()),
+        m.response_time_ms.map_or(Cell::Null, Cell::Float),
+        m.packet_loss_percent
+            .map_or(Cell::Null, |v| Cell::Int(v.into())),
+        text(m.error.map(|e| e.as_str())),
+        text(m.error_message()),
+        m.details.clone().map_or(Cell::Null, Cell::Json),
+    ]
+}
+
+fn outage_row(e: &OutageEvent) -> Vec<Cell> {
+    vec![
+        Cell::Int(e.id),
+        Cell::Text(e.event_type.as_str().to_string()),
+        Cell::Time(e.start_time),
+        e.end_time.map_or(Cell::Null, Cell::Time),
+        e.duration_seconds
+            .map_or(Cell::Null, |v| Cell::Int(v.into())),
+        text(e.reason.as_deref()),
+        e.consensus_level
+            .map_or(Cell::Null, |v| Cell::Int(v.into())),
+        e.affected_probes
+            .as_ref()
+            .map_or(Cell::Null, |ids| Cell::Json(json!(ids))),
+        Cell::Json(json!(e.affected_targets)),
+        Cell::Json(json!(e.affected_asns)),
+        e.details.clone().map_or(Cell::Null, Cell::Json),
+    ]
+}
+
+fn text(value: Option<&str>) -> Cell {
+    value.map_or(Cell::Null, |v| Cell::Text(v.to_string()))
+}
+
+/// Arquivo de saída no formato pedido.
+struct Output {
+    columns: Columns,
+    writer: Writer,
+}
+
+enum Writer {
+    Csv(BufWriter<File>),
+    Jsonl(BufWriter<File>),
+    Parquet(Box<ArrowWriter<File>>, SchemaRef),
+}
+
+impl Output {
+    fn create(path: &Path, format: ExportFormat, columns: Columns) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Falha ao criar {}", path.display()))?;
+        let writer = match format {
+            ExportFormat::Csv => {
+                let mut out = BufWriter::new(file);
+                let header: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+                writeln!(out, "{}", header.join(","))?;
+                Writer::Csv(out)
+            }
+            ExportFormat::Jsonl => Writer::Jsonl(BufWriter::new(file)),
+            ExportFormat::Parquet => {
+                let schema = schema(columns);
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(properties))
+                    .context("Falha ao iniciar o arquivo Parquet")?;
+                Writer::Parquet(Box::new(writer), schema)
+            }
+        };
+        Ok(Self { columns, writer })
+    }
+
+    fn write(&mut self, rows: &[Vec<Cell>]) -> Result<()> {
+        match &mut self.writer {
+            Writer::Csv(out) => {
+                for row in rows {
+                    let fields: Vec<String> = row.iter().map(csv_cell).collect();
+                    writeln!(out, "{}", fields.join(","))?;
+                }
+            }
+            Writer::Jsonl(out) => {
+                // Montado à mão para manter a ordem das colunas
+                for row in rows {
+                    let fields: Vec<String> = self
+                        .columns
+                        .iter()
+                        .zip(row)
+                        .map(|((name, _), cell)| format!("{}:{}", json!(name), json_cell(cell)))
+                        .collect();
+                    writeln!(out, "{{{}}}", fields.join(","))?;
+                }
+            }
+            Writer::Parquet(writer, schema) => {
+                if rows.is_empty() {
+                    return Ok(());
+                }
+                let arrays: Vec<ArrayRef> = self
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, kind))| array(*kind, rows.iter().map(|row| &row[i])))
+                    .collect();
+                let batch = RecordBatch::try_new(Arc::clone(schema), arrays)
+                    .context("Falha ao montar o lote Parquet")?;
+                writer
+                    .write(&batch)
+                    .context("Falha ao gravar o lote Parquet")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.writer {
+            Writer::Csv(mut out) | Writer::Jsonl(mut out) => out.flush()?,
+            Writer::Parquet(writer, _) => {
+                writer
+                    .close()
+                    .context("Falha ao finalizar o arquivo Parquet")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn schema(columns: Columns) -> SchemaRef {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, kind)| {
+            let data_type = match kind {
+                Kind::Int => DataType::Int64,
+                Kind::Float => DataType::Float64,
+                Kind::Text | Kind::Json => DataType::Utf8,
+                Kind::Time => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            };
+            Field::new(*name, data_type, true)
+        })
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Coluna Arrow com as células de uma coluna do lote.
+fn array<'a>(kind: Kind, cells: impl Iterator<Item = &'a Cell>) -> ArrayRef {
+    match kind {
+        Kind::Int => Arc::new(Int64Array::from_iter(cells.map(|c| match c {
+            Cell::Int(v) => Some(*v),
+            _ => None,
+        }))),
+        Kind::Float => Arc::new(Float64Array::from_iter(cells.map(|c| match c {
+            Cell::Float(v) => Some(*v),
+            _ => None,
+        }))),
+        Kind::Text | Kind::Json => Arc::new(StringArray::from_iter(cells.map(|c| match c {
+            Cell::Text(v) => Some(v.clone()),
+            Cell::Json(v) => Some(v.to_string()),
+            _ => None,
+        }))),
+        Kind::Time => Arc::new(
+            TimestampMicrosecondArray::from_iter(cells.map(|c| match c {
+                Cell::Time(v) => Some(v.timestamp_micros()),
+                _ => None,
+            }))
+            .with_timezone("UTC"),
+        ),
+    }
+}
+
+fn csv_cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Int(v) => v.to_string(),
+        Cell::Float(v) => v.to_string(),
+        Cell::Text(v) => csv_field(v),
+        Cell::Time(v) => v.to_rfc3339(),
+        Cell::Json(v) => csv_field(&v.to_string()),
+        Cell::Null => String::new(),
+    }
+}
+
+fn json_cell(cell: &Cell) -> Value {
+    match cell {
+        Cell::Int(v) => json!(v),
+        Cell::Float(v) => json!(v),
+        Cell::Text(v) => json!(v),
+        Cell::Time(v) => json!(v.to_rfc3339()),
+        Cell::Json(v) => v.clone(),
+        Cell::Null => Value::Null,
+    }
+}
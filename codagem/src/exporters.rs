@@ -0,0 +1,215 @@
+//! exporters.rs — Destinos secundários de métricas e outages
+//!
+//! Agrupa os sinks opcionais que recebem os dados além do PostgreSQL (ver
+//! `sink`). Os envios rodam em background, uma task por sink: um destino
+//! lento ou fora do ar nunca atrasa o ciclo de monitoramento nem os demais.
+
+use crate::anonymize::Pseudonymizer;
+use crate::deadman::DeadManSwitch;
+use crate::live::LiveFeed;
+use crate::notify::{NotificationKind, Notifier};
+use crate::sink::MetricSink;
+use crate::types::{ConnectivityMetric, MaintenanceWindow, OutageEvent, Probe, Target};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Intervalo entre verificações dos resumos de horário silencioso.
+const DIGEST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Rótulos legíveis de um target usados nas exportações.
+#[derive(Debug, Clone)]
+pub struct TargetLabel {
+    pub name: String,
+    pub address: IpAddr,
+}
+
+/// Conjunto de exportadores configurados.
+#[derive(Debug, Default)]
+pub struct Exporters {
+    /// Destinos das métricas (e dos outages, nos que os aceitam).
+    pub sinks: Vec<Arc<dyn MetricSink>>,
+    pub notifier: Option<Notifier>,
+    pub dead_man_switch: Option<DeadManSwitch>,
+    /// Rótulos por target_id (já pseudonimizados, se configurado).
+    pub labels: HashMap<i32, TargetLabel>,
+    /// Pseudonimização das exportações e os targets reais do grupo, cujos
+    /// nomes e endereços ela troca nos detalhes das métricas e dos outages
+    /// entregues aos sinks.
+    pub pseudonymizer: Option<Pseudonymizer>,
+    pub targets: HashMap<i32, Target>,
+    /// Janelas de manutenção lidas na inicialização; eventos cujos targets
+    /// estão todos em manutenção não são notificados.
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// Fluxo ao vivo da API (`/ws/live`), se habilitado.
+    pub live: Option<Arc<LiveFeed>>,
+}
+
+impl Exporters {
+    /// Registra os rótulos dos targets, pseudonimizando-os se solicitado.
+    pub fn set_targets(&mut self, targets: &[Target], pseudonymizer: Option<&Pseudonymizer>) {
+        self.pseudonymizer = pseudonymizer.filter(|p| p.exports).cloned();
+        self.targets = match self.pseudonymizer {
+            Some(_) => targets.iter().map(|t| (t.id, t.clone())).collect(),
+            None => HashMap::new(),
+        };
+        self.labels = targets
+            .iter()
+            .map(|t| {
+                let target = match pseudonymizer {
+                    Some(p) if p.exports => p.target(t),
+                    _ => t.clone(),
+                };
+                (
+                    t.id,
+                    TargetLabel {
+                        name: target.name,
+                        address: target.address,
+                    },
+                )
+            })
+            .collect();
+    }
+
+    /// Exporta as métricas de um ciclo para todos os destinos configurados.
+    pub fn export_metrics(self: &Arc<Self>, metrics: &[ConnectivityMetric], location: &str) {
+        if let Some(live) = &self.live {
+            live.publish(metrics);
+        }
+        if self.sinks.is_empty() {
+            return;
+        }
+        let batch: Arc<[ConnectivityMetric]> = match &self.pseudonymizer {
+            Some(pseudonymizer) => metrics
+                .iter()
+                .map(|m| match self.targets.get(&m.target_id) {
+                    Some(target) => pseudonymizer.metric(m, target),
+                    None => m.clone(),
+                })
+                .collect(),
+            None => metrics.into(),
+        };
+        for sink in &self.sinks {
+            let exporters = Arc::clone(self);
+            let sink = Arc::clone(sink);
+            let batch = Arc::clone(&batch);
+            let location = location.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = sink
+                    .write_metrics(&batch, &exporters.labels, &location)
+                    .await
+                {
+                    warn!(
+                        "[PROBE {}] Falha ao exportar métricas para o sink {}: {:?}",
+                        location,
+                        sink.name(),
+                        e
+                    );
+                }
+            });
+        }
+    }
+
+    /// Exporta um evento de outage (abertura ou encerramento).
+    pub fn export_outage(self: &Arc<Self>, event: &OutageEvent, location: &str) {
+        if let Some(live) = &self.live {
+            live.publish_outage(event, location);
+        }
+        if !self.sinks.iter().any(|s| s.wants_outages()) {
+            return;
+        }
+        let event = Arc::new(match &self.pseudonymizer {
+            Some(pseudonymizer) => {
+                let targets: Vec<Target> = event
+                    .affected_targets
+                    .iter()
+                    .filter_map(|id| self.targets.get(id).cloned())
+                    .collect();
+                pseudonymizer.outage(event, &targets)
+            }
+            None => event.clone(),
+        });
+        for sink in self.sinks.iter().filter(|s| s.wants_outages()) {
+            let sink = Arc::clone(sink);
+            let event = Arc::clone(&event);
+            let location = location.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = sink.write_outage(&event, &location).await {
+                    warn!(
+                        "[PROBE {}] Falha ao exportar outage para o sink {}: {:?}",
+                        location,
+                        sink.name(),
+                        e
+                    );
+                }
+            });
+        }
+    }
+
+    /// Notifica um outage pelas rotas configuradas.
+    pub fn notify_outage(
+        self: &Arc<Self>,
+        kind: NotificationKind,
+        event: &OutageEvent,
+        location: &str,
+    ) {
+        if self.notifier.is_none() {
+            return;
+        }
+        if MaintenanceWindow::suppresses(&self.maintenance, event, Utc::now()) {
+            debug!(
+                "[NOTIFY] {:?} de {:?} suprimido: targets em manutenção",
+                kind, event.affected_targets
+            );
+            return;
+        }
+        let exporters = Arc::clone(self);
+        let event = event.clone();
+        let location = location.to_string();
+        tokio::spawn(async move {
+            if let Some(notifier) = &exporters.notifier {
+                notifier.send(kind, &event, &location).await;
+            }
+        });
+    }
+
+    /// Inicia o envio periódico dos resumos de horário silencioso, se alguma
+    /// rota tiver `quiet_hours`.
+    pub fn start_digests(self: &Arc<Self>) {
+        if !self.notifier.as_ref().is_some_and(|n| n.has_quiet_hours()) {
+            return;
+        }
+        let exporters = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DIGEST_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Some(notifier) = &exporters.notifier {
+                    notifier.flush_digests().await;
+                }
+            }
+        });
+    }
+
+    /// Envia o heartbeat externo de um ciclo concluído do probe.
+    pub fn ping_dead_man_switch(self: &Arc<Self>, probe: &Probe) {
+        if self.dead_man_switch.is_none() {
+            return;
+        }
+        let exporters = Arc::clone(self);
+        let probe = probe.clone();
+        tokio::spawn(async move {
+            if let Some(dead_man_switch) = &exporters.dead_man_switch
+                && let Err(e) = dead_man_switch.ping(&probe).await
+            {
+                warn!(
+                    "[PROBE {}] Falha ao enviar heartbeat externo: {:?}",
+                    probe.location, e
+                );
+            }
+        });
+    }
+}
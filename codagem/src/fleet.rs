@@ -0,0 +1,105 @@
+//! fleet.rs — Modo frota (opt-in)
+//!
+//! Envia periodicamente a um agregador central o mesmo resumo anonimizado da
+//! API pública (`public_stats::summarize`): disponibilidade e latência por
+//! provedor e região, com supressão de grupos pequenos e ruído opcional. Aqui
+//! o provedor é o ISP do probe que mediu (`monitoring_probes.provider`), não
+//! o do target: o agregador compara os acessos.
+//! Nenhum IP, nome de target ou métrica bruta sai do processo.
+
+use crate::config::FleetConfig;
+use crate::public_stats::{self, ProviderSource, PublicSummary};
+use crate::storage::Storage;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{error, info};
+
+/// Corpo enviado ao agregador.
+#[derive(Debug, Serialize)]
+struct FleetReport<'a> {
+    reporter_id: Option<&'a str>,
+    agent_version: &'static str,
+    summary: PublicSummary,
+}
+
+/// Cliente de envio ao agregador.
+pub struct FleetReporter {
+    client: reqwest::Client,
+    config: FleetConfig,
+    storage: Arc<Storage>,
+}
+
+impl FleetReporter {
+    pub fn new(config: FleetConfig, storage: Arc<Storage>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP do modo frota")?;
+        Ok(Self {
+            client,
+            config,
+            storage,
+        })
+    }
+
+    /// Monta o resumo da janela configurada a partir das views agregadas.
+    async fn build_summary(&self) -> Result<PublicSummary> {
+        let since = Utc::now() - chrono::Duration::hours(self.config.window_hours);
+        let probes = self.storage.list_probes().await?;
+        let targets = self.storage.list_targets().await?;
+        let availability = self.storage.list_hourly_probe_availability(since).await?;
+        let latency = self.storage.list_hourly_probe_latency(since).await?;
+        Ok(public_stats::summarize(
+            ProviderSource::Probe(&probes),
+            &targets,
+            &availability,
+            &latency,
+            self.config.window_hours,
+            self.config.min_group_size,
+            self.config.noise_epsilon,
+        ))
+    }
+
+    /// Gera e envia um único resumo.
+    pub async fn submit(&self) -> Result<()> {
+        let report = FleetReport {
+            reporter_id: self.config.reporter_id.as_deref(),
+            agent_version: env!("CARGO_PKG_VERSION"),
+            summary: self.build_summary().await?,
+        };
+        let mut request = self.client.post(&self.config.url).json(&report);
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .context("Falha ao enviar resumo ao agregador")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Agregador respondeu {}: {}", status, body);
+        }
+        Ok(())
+    }
+
+    /// Loop de envio a cada `interval_secs`.
+    pub async fn run(self) {
+        info!(
+            "🛰️ Modo frota ativo: resumo de {}h enviado a {} a cada {}s",
+            self.config.window_hours, self.config.url, self.config.interval_secs
+        );
+        let mut ticker = interval(Duration::from_secs(self.config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            match self.submit().await {
+                Ok(()) => info!("[FLEET] Resumo enviado ao agregador"),
+                Err(e) => error!("[FLEET] Falha no envio ao agregador: {:?}", e),
+            }
+        }
+    }
+}
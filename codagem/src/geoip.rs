@@ -0,0 +1,130 @@
+//! geoip.rs — País, cidade e coordenadas de targets e probes (MaxMind GeoLite2)
+//!
+//! Com `[geoip]` configurado, uma task abre a base `GeoLite2-City.mmdb` a
+//! cada `interval_secs` e grava a localização dos targets e dos probes (pelo
+//! `ip_address`) que ainda não têm uma (todos, com `overwrite = true`).
+//! Endereços privados não são localizados; desses, a localização pode ser
+//! preenchida à mão nas colunas `country_code`, `city`, `latitude` e
+//! `longitude`.
+//!
+//! O país entra nos agrupamentos das estatísticas públicas (`by_country`) e
+//! as coordenadas ficam disponíveis para um mapa dos outages.
+
+use crate::config::GeoIpConfig;
+use crate::storage::Storage;
+use crate::traceroute;
+use crate::types::GeoLocation;
+use anyhow::{Context, Result};
+use maxminddb::{Reader, geoip2};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Base GeoLite2 carregada em memória.
+pub struct GeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl GeoIp {
+    /// Lê a base do disco.
+    pub fn open(path: &str) -> Result<Self> {
+        let reader = Reader::open_readfile(path)
+            .with_context(|| format!("Falha ao abrir a base GeoIP {}", path))?;
+        Ok(Self { reader })
+    }
+
+    /// Localização de um endereço público; `None` se a base não o conhece.
+    pub fn locate(&self, address: IpAddr) -> Option<GeoLocation> {
+        if !traceroute::is_public(address) {
+            return None;
+        }
+        let city: geoip2::City = match self.reader.lookup(address).and_then(|r| r.decode()) {
+            Ok(Some(city)) => city,
+            Ok(None) => return None,
+            Err(e) => {
+                debug!("[GEOIP] Falha ao consultar {}: {}", address, e);
+                return None;
+            }
+        };
+        let geo = GeoLocation {
+            country_code: city.country.iso_code.map(str::to_string),
+            city: city.city.names.english.map(str::to_string),
+            latitude: city.location.latitude,
+            longitude: city.location.longitude,
+        };
+        (!geo.is_empty()).then_some(geo)
+    }
+}
+
+/// Loop de localização; a primeira passada é imediata.
+pub async fn run(storage: Arc<Storage>, config: GeoIpConfig) {
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        let path = config.database_path.clone();
+        let geoip = match tokio::task::spawn_blocking(move || GeoIp::open(&path)).await {
+            Ok(Ok(geoip)) => geoip,
+            Ok(Err(e)) => {
+                warn!("[GEOIP] {:?}", e);
+                continue;
+            }
+            Err(e) => {
+                warn!("[GEOIP] Falha ao carregar a base GeoIP: {}", e);
+                continue;
+            }
+        };
+        match locate_all(&storage, &geoip, config.overwrite).await {
+            Ok((0, 0)) => debug!("[GEOIP] Nenhuma localização atualizada"),
+            Ok((targets, probes)) => info!(
+                "🌍 [GEOIP] Localização atualizada em {} target(s) e {} probe(s)",
+                targets, probes
+            ),
+            Err(e) => warn!("[GEOIP] Falha ao localizar targets e probes: {:?}", e),
+        }
+    }
+}
+
+/// Uma passada sobre targets e probes; retorna quantos de cada foram
+/// atualizados.
+async fn locate_all(storage: &Storage, geoip: &GeoIp, overwrite: bool) -> Result<(usize, usize)> {
+    let mut targets = 0;
+    for target in storage.list_targets().await? {
+        if !overwrite && !target.geo.is_empty() {
+            continue;
+        }
+        if let Some(geo) = geoip.locate(target.address)
+            && geo != target.geo
+        {
+            storage.update_target_geo(target.id, &geo).await?;
+            debug!(
+                "[GEOIP] Target {} ({}): {} {}",
+                target.name,
+                target.address,
+                geo.country_code.as_deref().unwrap_or("-"),
+                geo.city.as_deref().unwrap_or("-")
+            );
+            targets += 1;
+        }
+    }
+    let mut probes = 0;
+    for probe in storage.list_probes().await? {
+        if !overwrite && !probe.geo.is_empty() {
+            continue;
+        }
+        if let Some(geo) = probe.ip_address.and_then(|ip| geoip.locate(ip))
+            && geo != probe.geo
+        {
+            storage.update_probe_geo(probe.id, &geo).await?;
+            debug!(
+                "[GEOIP] Probe {}: {} {}",
+                probe.location,
+                geo.country_code.as_deref().unwrap_or("-"),
+                geo.city.as_deref().unwrap_or("-")
+            );
+            probes += 1;
+        }
+    }
+    Ok((targets, probes))
+}
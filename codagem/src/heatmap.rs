@@ -0,0 +1,59 @@
+//! heatmap.rs — Histogramas horários de RTT para heatmaps de latência
+//!
+//! Com `[latency_heatmap]`, as medições são resumidas em `latency_histograms`:
+//! por probe, target, tipo de métrica e hora, a quantidade de RTTs em cada
+//! faixa de `buckets_ms`. São poucas linhas por hora, que podem ser mantidas
+//! por muito mais tempo que as medições brutas e alimentam heatmaps no estilo
+//! smokeping no Grafana. A hora atual e a anterior são recalculadas a cada
+//! `interval_secs`, o que inclui as medições que chegam atrasadas pelo spool.
+
+use crate::config::LatencyHeatmapConfig;
+use crate::storage::Storage;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error, info};
+
+/// Início da hora de `instant`.
+fn hour_start(instant: DateTime<Utc>) -> DateTime<Utc> {
+    instant
+        .duration_trunc(TimeDelta::hours(1))
+        .unwrap_or(instant)
+}
+
+/// Loop de agregação dos histogramas: recalcula `backfill_hours` na primeira
+/// passada e, depois, a hora atual e a anterior; remove o que passou de
+/// `retention_days`.
+pub async fn run(storage: Arc<Storage>, config: LatencyHeatmapConfig) {
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    let mut hours_back = i64::from(config.backfill_hours.max(1));
+    loop {
+        ticker.tick().await;
+        let now = Utc::now();
+        let from = hour_start(now) - TimeDelta::hours(hours_back);
+        match storage
+            .aggregate_latency_histograms(from, now, &config.buckets_ms)
+            .await
+        {
+            Ok(written) if hours_back > 1 => {
+                info!(
+                    "[HEATMAP] Histogramas de RTT recalculados desde {} ({} faixa(s))",
+                    from, written
+                );
+                hours_back = 1;
+            }
+            Ok(written) => debug!("[HEATMAP] {} faixa(s) de RTT atualizada(s)", written),
+            Err(e) => error!("[HEATMAP] Falha ao agregar histogramas de RTT: {:?}", e),
+        }
+        if config.retention_days > 0 {
+            let cutoff = now - TimeDelta::days(i64::from(config.retention_days));
+            if let Err(e) = storage.delete_latency_histograms_before(cutoff).await {
+                error!(
+                    "[HEATMAP] Falha ao remover histogramas de RTT expirados: {:?}",
+                    e
+                );
+            }
+        }
+    }
+}
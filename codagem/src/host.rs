@@ -0,0 +1,206 @@
+//! host.rs — Recursos do host da probe
+//!
+//! Amostrado a cada ciclo: carga de CPU, memória disponível e pressão de
+//! memória (PSI), espaço em disco e descritores de arquivo do processo. Um
+//! host sem recursos infla os RTTs medidos, então as métricas do ciclo são
+//! anotadas com os motivos (`details.host_starved`) e, com `reject`, o ciclo
+//! não entra no consenso nem nos agregados.
+
+use crate::config::HostConfig;
+use crate::types::ConnectivityMetric;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::ffi::CString;
+
+/// Amostra dos recursos do host. Campos `None` não puderam ser lidos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostSample {
+    pub probe_id: i32,
+    pub timestamp: DateTime<Utc>,
+    /// Load average de 1 minuto.
+    pub load1: Option<f64>,
+    pub cpu_count: usize,
+    /// Percentual de `MemAvailable` sobre `MemTotal`.
+    pub mem_available_percent: Option<f64>,
+    /// PSI `some avg10` de `/proc/pressure/memory` (kernels com PSI).
+    pub mem_pressure_avg10: Option<f64>,
+    /// Percentual livre do sistema de arquivos de `disk_path`.
+    pub disk_free_percent: Option<f64>,
+    /// Descritores abertos pelo processo.
+    pub fd_open: Option<u64>,
+    /// Limite `RLIMIT_NOFILE` (soft) do processo.
+    pub fd_limit: Option<u64>,
+    /// Motivos de falta de recursos; vazio = host saudável.
+    pub starved: Vec<String>,
+    /// Condições degradadas de medição: host sem recursos ou com carga acima
+    /// de `heavy_max_load_per_cpu`. Checks pesados ficam pausados.
+    #[serde(default)]
+    pub degraded_conditions: bool,
+}
+
+impl HostSample {
+    pub fn load_per_cpu(&self) -> Option<f64> {
+        self.load1.map(|l| l / self.cpu_count.max(1) as f64)
+    }
+
+    pub fn fd_usage_percent(&self) -> Option<f64> {
+        match (self.fd_open, self.fd_limit) {
+            (Some(open), Some(limit)) if limit > 0 => Some(100.0 * open as f64 / limit as f64),
+            _ => None,
+        }
+    }
+
+    pub fn is_starved(&self) -> bool {
+        !self.starved.is_empty()
+    }
+}
+
+/// Lê os recursos do host e avalia os limites configurados.
+pub fn sample(probe_id: i32, config: &HostConfig) -> HostSample {
+    let mut sample = HostSample {
+        probe_id,
+        timestamp: Utc::now(),
+        load1: std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| parse_loadavg(&s)),
+        cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        mem_available_percent: std::fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|s| parse_mem_available_percent(&s)),
+        mem_pressure_avg10: std::fs::read_to_string("/proc/pressure/memory")
+            .ok()
+            .and_then(|s| parse_pressure_some_avg10(&s)),
+        disk_free_percent: disk_free_percent(&config.disk_path),
+        fd_open: std::fs::read_dir("/proc/self/fd")
+            .ok()
+            .map(|dir| dir.count() as u64),
+        fd_limit: fd_limit(),
+        starved: Vec::new(),
+        degraded_conditions: false,
+    };
+    sample.starved = starvation_reasons(&sample, config);
+    sample.degraded_conditions = sample.is_starved()
+        || sample
+            .load_per_cpu()
+            .is_some_and(|load| load > config.heavy_max_load_per_cpu);
+    sample
+}
+
+fn starvation_reasons(sample: &HostSample, config: &HostConfig) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if let Some(load) = sample.load_per_cpu()
+        && load > config.max_load_per_cpu
+    {
+        reasons.push(format!("cpu_load: {:.2} por CPU", load));
+    }
+    if let Some(available) = sample.mem_available_percent
+        && available < config.min_mem_available_percent
+    {
+        reasons.push(format!("memory: {:.1}% disponível", available));
+    }
+    if let Some(pressure) = sample.mem_pressure_avg10
+        && pressure > config.max_mem_pressure_avg10
+    {
+        reasons.push(format!("memory_pressure: avg10 {:.1}", pressure));
+    }
+    if let Some(free) = sample.disk_free_percent
+        && free < config.min_disk_free_percent
+    {
+        reasons.push(format!("disk: {:.1}% livre em {}", free, config.disk_path));
+    }
+    if let Some(usage) = sample.fd_usage_percent()
+        && usage > config.max_fd_usage_percent
+    {
+        reasons.push(format!("file_descriptors: {:.1}% do limite", usage));
+    }
+    reasons
+}
+
+/// Anota as métricas do ciclo medido em condições degradadas
+/// (`details.degraded_conditions`) e, com o host sem recursos, os motivos
+/// (`details.host_starved`). Com `reject`, as métricas de um host sem recursos
+/// também são marcadas como `diagnostic`, o que as tira das views agregadas.
+pub fn annotate(metrics: &mut [ConnectivityMetric], sample: &HostSample, reject: bool) {
+    if !sample.degraded_conditions {
+        return;
+    }
+    for metric in metrics {
+        let details = metric.details.get_or_insert_with(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("degraded_conditions".into(), json!(true));
+            if !sample.is_starved() {
+                continue;
+            }
+            map.insert(
+                "host_starved".into(),
+                json!({
+                    "reasons": sample.starved,
+                    "load_per_cpu": sample.load_per_cpu(),
+                    "mem_available_percent": sample.mem_available_percent,
+                    "mem_pressure_avg10": sample.mem_pressure_avg10,
+                    "disk_free_percent": sample.disk_free_percent,
+                    "fd_usage_percent": sample.fd_usage_percent(),
+                    "rejected": reject,
+                }),
+            );
+            if reject {
+                map.insert("diagnostic".into(), json!(true));
+            }
+        }
+    }
+}
+
+/// Primeiro campo de `/proc/loadavg`.
+fn parse_loadavg(contents: &str) -> Option<f64> {
+    contents.split_whitespace().next()?.parse().ok()
+}
+
+/// `MemAvailable / MemTotal` de `/proc/meminfo`, em percentual.
+fn parse_mem_available_percent(contents: &str) -> Option<f64> {
+    let field = |name: &str| -> Option<f64> {
+        contents
+            .lines()
+            .find(|l| l.starts_with(name))?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+    (total > 0.0).then(|| 100.0 * available / total)
+}
+
+/// `avg10` da linha `some` de `/proc/pressure/memory`.
+fn parse_pressure_some_avg10(contents: &str) -> Option<f64> {
+    contents
+        .lines()
+        .find(|l| l.starts_with("some"))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+fn disk_free_percent(path: &str) -> Option<f64> {
+    let path = CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` é uma string C válida e `stat` é um buffer do tipo esperado
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 || stat.f_blocks == 0 {
+        return None;
+    }
+    Some(100.0 * stat.f_bavail as f64 / stat.f_blocks as f64)
+}
+
+fn fd_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` é um buffer válido para `getrlimit`
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    Some(limit.rlim_cur)
+}
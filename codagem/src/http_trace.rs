@@ -0,0 +1,287 @@
+//! http_trace.rs — Cadeia de redirects e fases das requisições do check `http`
+//!
+//! O check `http` segue redirects até `checks.http_max_redirects` e registra
+//! cada salto: URL, status, `Location` e a duração de cada fase (DNS,
+//! connect, TLS e TTFB). A requisição é um `GET` HTTP/1.1 feito à mão sobre
+//! TCP (ou TLS, nos saltos `https`) justamente para medir as fases em
+//! separado. Um redirect para um host fora de
+//! `checks.http_allowed_redirect_hosts` é marcado como inesperado: redirects
+//! mal configurados costumam quebrar um serviço em silêncio.
+
+use crate::config::SourceConfig;
+use crate::source;
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::Url;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, crypto};
+
+/// Tamanho máximo do cabeçalho de resposta lido por salto.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// Um salto da cadeia.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hop {
+    pub url: String,
+    pub status: Option<u16>,
+    pub location: Option<String>,
+    /// Resolução do nome; ausente quando o host já é um IP.
+    pub dns_ms: Option<f64>,
+    pub connect_ms: Option<f64>,
+    /// Handshake TLS; ausente em `http`.
+    pub tls_ms: Option<f64>,
+    /// Do envio da requisição ao primeiro byte da resposta.
+    pub ttfb_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Hop {
+    fn new(url: &Url) -> Self {
+        Self {
+            url: url.to_string(),
+            status: None,
+            location: None,
+            dns_ms: None,
+            connect_ms: None,
+            tls_ms: None,
+            ttfb_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Resultado de uma requisição com seus redirects.
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    pub hops: Vec<Hop>,
+    /// Primeiro redirect para um host não permitido.
+    pub unexpected_redirect: Option<String>,
+    /// A cadeia foi interrompida no limite de redirects.
+    pub too_many_redirects: bool,
+}
+
+impl Trace {
+    /// Status do último salto.
+    pub fn final_status(&self) -> Option<u16> {
+        self.hops.last().and_then(|h| h.status)
+    }
+
+    /// Erro do último salto.
+    pub fn error(&self) -> Option<&str> {
+        self.hops.last().and_then(|h| h.error.as_deref())
+    }
+}
+
+/// Executa requisições seguindo redirects.
+pub struct Tracer {
+    tls: TlsConnector,
+    max_redirects: usize,
+    allowed_hosts: Vec<String>,
+    /// Origem das conexões (`checks.sources.http`).
+    source: SourceConfig,
+}
+
+impl Tracer {
+    pub fn new(max_redirects: usize, allowed_hosts: &[String]) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .context("Configuração TLS inválida")?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+        Ok(Self {
+            tls: TlsConnector::from(Arc::new(config)),
+            max_redirects,
+            allowed_hosts: allowed_hosts
+                .iter()
+                .map(|h| h.to_ascii_lowercase())
+                .collect(),
+            source: SourceConfig::default(),
+        })
+    }
+
+    /// Conecta com a origem `source` (interface e/ou endereço local).
+    pub fn source(mut self, source: SourceConfig) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Segue a cadeia a partir de `url`. O host de `url` (o próprio target) é
+    /// sempre um destino de redirect esperado.
+    pub async fn trace(&self, url: Url) -> Trace {
+        let origin = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut trace = Trace {
+            hops: Vec::new(),
+            unexpected_redirect: None,
+            too_many_redirects: false,
+        };
+        let mut current = url;
+        loop {
+            let hop = self.request(&current).await;
+            let location = hop
+                .location
+                .clone()
+                .filter(|_| hop.status.is_some_and(|s| (300..400).contains(&s)));
+            trace.hops.push(hop);
+            let Some(location) = location else {
+                break;
+            };
+            if self.max_redirects == 0 {
+                break;
+            }
+            if trace.hops.len() > self.max_redirects {
+                trace.too_many_redirects = true;
+                break;
+            }
+            let next = match current.join(&location) {
+                Ok(next) => next,
+                Err(e) => {
+                    if let Some(last) = trace.hops.last_mut() {
+                        last.error = Some(format!("Location inválido `{}`: {}", location, e));
+                    }
+                    break;
+                }
+            };
+            if trace.unexpected_redirect.is_none() && !self.allowed(&next, &origin) {
+                trace.unexpected_redirect = Some(next.to_string());
+            }
+            current = next;
+        }
+        trace
+    }
+
+    fn allowed(&self, url: &Url, origin: &str) -> bool {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        self.allowed_hosts.is_empty() || host == origin || self.allowed_hosts.contains(&host)
+    }
+
+    async fn request(&self, url: &Url) -> Hop {
+        let mut hop = Hop::new(url);
+        if let Err(e) = self.fetch(url, &mut hop).await {
+            hop.error = Some(format!("{:#}", e));
+        }
+        hop
+    }
+
+    async fn fetch(&self, url: &Url, hop: &mut Hop) -> Result<()> {
+        let host = url.host_str().context("URL sem host")?;
+        let port = url
+            .port_or_known_default()
+            .with_context(|| format!("esquema sem porta padrão: {}", url.scheme()))?;
+        let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+        let address = match bare_host.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => {
+                let started = Instant::now();
+                let resolved = tokio::net::lookup_host((bare_host, port))
+                    .await
+                    .with_context(|| format!("falha ao resolver {}", bare_host))?
+                    .next()
+                    .ok_or_else(|| anyhow!("{} não resolve para nenhum endereço", bare_host))?;
+                hop.dns_ms = Some(elapsed_ms(started));
+                resolved
+            }
+        };
+
+        let started = Instant::now();
+        let stream = source::tcp_connect(&self.source, address)
+            .await
+            .with_context(|| format!("falha no connect a {}", address))?;
+        hop.connect_ms = Some(elapsed_ms(started));
+
+        let host_header = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: monitoramento_rede/{}\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+            path,
+            host_header,
+            env!("CARGO_PKG_VERSION")
+        );
+
+        match url.scheme() {
+            "http" => exchange(stream, &request, hop).await,
+            "https" => {
+                let server_name = ServerName::try_from(bare_host.to_string())
+                    .with_context(|| format!("nome TLS inválido: {}", bare_host))?;
+                let started = Instant::now();
+                let stream = self
+                    .tls
+                    .connect(server_name, stream)
+                    .await
+                    .context("falha no handshake TLS")?;
+                hop.tls_ms = Some(elapsed_ms(started));
+                exchange(stream, &request, hop).await
+            }
+            other => bail!("esquema não suportado: {}", other),
+        }
+    }
+}
+
+/// Envia a requisição e lê o cabeçalho da resposta (status e `Location`).
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    request: &str,
+    hop: &mut Hop,
+) -> Result<()> {
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .context("falha ao enviar a requisição")?;
+    let started = Instant::now();
+    let mut head = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .context("falha ao ler a resposta")?;
+        if read == 0 {
+            break;
+        }
+        if head.is_empty() {
+            hop.ttfb_ms = Some(elapsed_ms(started));
+        }
+        head.extend_from_slice(&chunk[..read]);
+        if head.len() > MAX_HEAD_BYTES {
+            bail!("cabeçalho da resposta maior que {} bytes", MAX_HEAD_BYTES);
+        }
+    }
+    if head.is_empty() {
+        bail!("conexão encerrada sem resposta");
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    hop.status = Some(
+        status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .with_context(|| format!("linha de status inválida: `{}`", status_line))?,
+    );
+    hop.location = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+        .map(|(_, value)| value.trim().to_string());
+    Ok(())
+}
+
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
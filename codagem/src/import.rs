@@ -0,0 +1,524 @@
+//! import.rs — Importação de histórico de outras ferramentas (`monitoramento_rede import`)
+//!
+//! Migra para `connectivity_metrics` o histórico exportado de outra
+//! ferramenta de monitoramento, para que ele entre nos mesmos relatórios,
+//! agregados e páginas das medições próprias. As métricas ficam num probe
+//! sintético (`import-<formato>`, ou `--probe`), sem ciclo, com
+//! `details.imported` indicando a origem.
+//!
+//! O arquivo pode ser CSV com cabeçalho ou JSON (lista de objetos, ou objeto
+//! com a lista em `results`, `heartbeats` ou `data`). Campos por formato:
+//!
+//! - `smokeping`: `time`, `median` (segundos), `loss` (pings perdidos) e
+//!   `pings` (padrão 20), como sai de `rrdtool xport`; linhas sem `loss`
+//!   (NaN no RRD) são ignoradas. Check `ping`.
+//! - `uptime-kuma`: heartbeats com `time`, `status` (0 = down, 1 = up,
+//!   2 = pending, 3 = manutenção, ignorado), `ping` (ms), `msg` e o endereço
+//!   do monitor em `url` ou `hostname` (+ `port`); `type` do monitor define
+//!   o check (padrão `http`).
+//! - `pingdom`: resultados da API de checks (`time`, `status` up, down,
+//!   unconfirmed_down ou unknown, este ignorado, `responsetime` em ms,
+//!   `statusdesc`/`statusdesclong`). Check `http`.
+//!
+//! `time` aceita epoch (segundos ou milissegundos), RFC 3339 ou
+//! `AAAA-MM-DD HH:MM:SS` em UTC. Um campo `target` (ou `--target`, que vale
+//! para todos os registros) informa o endereço quando o formato não o traz.
+//!
+//! Os targets são cadastrados pelo fluxo normal (`registration`) se ainda não
+//! existirem. A importação é idempotente: registros que não são mais novos
+//! que a última métrica do probe para o target e o tipo de métrica são
+//! ignorados, então reimportar um arquivo maior só acrescenta o que falta.
+
+use crate::check::CheckKind;
+use crate::cli::{ImportArgs, ImportFormat};
+use crate::persistence::METRICS_PER_INSERT;
+use crate::registration::{self, Registration, RegistrationError};
+use crate::storage::Storage;
+use crate::templates::TemplateSet;
+use crate::types::{CheckError, ConnectivityMetric, GeoLocation, MetricStatus, Probe, Target};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+
+/// Pings por medição do smokeping quando o arquivo não informa.
+const SMOKEPING_DEFAULT_PINGS: f64 = 20.0;
+
+/// Linha do arquivo: campos (em minúsculas) com valor não vazio, como texto.
+type Row = HashMap<String, String>;
+
+/// Registro normalizado, pronto para virar métrica.
+struct Record {
+    timestamp: DateTime<Utc>,
+    target: String,
+    kind: CheckKind,
+    status: MetricStatus,
+    rtt_ms: Option<f64>,
+    loss_percent: Option<i16>,
+    message: Option<String>,
+}
+
+/// Registros do arquivo agrupados por target, em ordem cronológica.
+struct Parsed {
+    targets: BTreeMap<String, Vec<Record>>,
+    /// Linhas sem medição (NaN, manutenção, status desconhecido).
+    ignored: usize,
+}
+
+/// `--dry-run`: lê o arquivo e imprime o que seria importado.
+pub fn preview(args: &ImportArgs) -> Result<()> {
+    let parsed = read(args)?;
+    for (target, records) in &parsed.targets {
+        let failed = records
+            .iter()
+            .filter(|r| r.status != MetricStatus::Up)
+            .count();
+        println!(
+            "{}: {} registro(s) de {} a {} ({} com falha ou degradação)",
+            target,
+            records.len(),
+            records[0].timestamp,
+            records[records.len() - 1].timestamp,
+            failed
+        );
+    }
+    println!(
+        "{} registro(s) para importar, {} linha(s) sem medição ignorada(s)",
+        parsed.targets.values().map(Vec::len).sum::<usize>(),
+        parsed.ignored
+    );
+    Ok(())
+}
+
+/// Importa o arquivo e imprime quantas métricas foram gravadas por target.
+pub async fn run(storage: &Storage, templates: &TemplateSet, args: ImportArgs) -> Result<()> {
+    let parsed = read(&args)?;
+    if parsed.targets.is_empty() {
+        bail!("Nenhum registro para importar em {}", args.file.display());
+    }
+    let location = args
+        .probe
+        .clone()
+        .unwrap_or_else(|| format!("import-{}", args.format.as_str()));
+    let probe = probe(storage, &location, args.format).await?;
+    let last = storage.last_metric_times(probe.id).await?;
+
+    let mut imported = 0;
+    let mut existing = 0;
+    for (input, records) in parsed.targets {
+        let target = target(storage, templates, &input).await?;
+        let total = records.len();
+        let metrics: Vec<ConnectivityMetric> = records
+            .into_iter()
+            .map(|record| metric(&record, &probe, &target, args.format))
+            .filter(|metric| {
+                let key = (target.id, metric.metric_type.to_string());
+                last.get(&key).is_none_or(|&last| metric.timestamp > last)
+            })
+            .collect();
+        for chunk in metrics.chunks(METRICS_PER_INSERT) {
+            storage
+                .insert_connectivity_metrics(chunk)
+                .await
+                .with_context(|| format!("Falha ao gravar as métricas de {}", input))?;
+        }
+        println!(
+            "{} -> {} (id {}): {} métrica(s) importada(s), {} já existente(s)",
+            input,
+            target.address,
+            target.id,
+            metrics.len(),
+            total - metrics.len()
+        );
+        imported += metrics.len();
+        existing += total - metrics.len();
+    }
+    println!(
+        "Importação concluída no probe {} (id {}): {} métrica(s) gravada(s), {} já existente(s), {} linha(s) sem medição",
+        probe.location, probe.id, imported, existing, parsed.ignored
+    );
+    Ok(())
+}
+
+/// Probe sintético da importação, cadastrado se ainda não existir.
+async fn probe(storage: &Storage, location: &str, format: ImportFormat) -> Result<Probe> {
+    if let Some(probe) = storage
+        .list_probes()
+        .await?
+        .into_iter()
+        .find(|p| p.location == location)
+    {
+        return Ok(probe);
+    }
+    let mut probe = Probe {
+        id: 0,
+        location: location.to_string(),
+        ip_address: None,
+        provider: Some(format.as_str().to_string()),
+        created_at: None,
+        geo: GeoLocation::default(),
+    };
+    probe.id = storage
+        .insert_probe(&probe)
+        .await
+        .context("Falha ao cadastrar o probe da importação")?;
+    Ok(probe)
+}
+
+/// Target cadastrado para a entrada, cadastrando-o se ainda não existir.
+async fn target(storage: &Storage, templates: &TemplateSet, input: &str) -> Result<Target> {
+    let request = Registration {
+        inputs: vec![input.to_string()],
+        ..Default::default()
+    };
+    let registered = match registration::register(storage, templates, &request).await {
+        Ok(registered) => registered,
+        Err(RegistrationError::Invalid(message)) => bail!("Target inválido: {}", message),
+        Err(RegistrationError::Storage(e)) => return Err(e),
+    };
+    let [registered] = registered.as_slice() else {
+        bail!(
+            "`{}` corresponde a {} endereços; informe um único target",
+            input,
+            registered.len()
+        );
+    };
+    storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .find(|t| t.address == registered.address)
+        .with_context(|| {
+            format!(
+                "Target {} não encontrado após o cadastro",
+                registered.address
+            )
+        })
+}
+
+fn metric(
+    record: &Record,
+    probe: &Probe,
+    target: &Target,
+    format: ImportFormat,
+) -> ConnectivityMetric {
+    let mut metric = ConnectivityMetric {
+        id: 0,
+        cycle_id: 0,
+        probe_id: probe.id,
+        target_id: target.id,
+        timestamp: record.timestamp,
+        metric_type: record.kind.metric_type(target.address),
+        status: record.status.clone(),
+        response_time_ms: record.rtt_ms,
+        packet_loss_percent: record.loss_percent,
+        error: None,
+        details: Some(json!({ "imported": format.as_str() })),
+        provenance: None,
+        attempts: Vec::new(),
+    };
+    // Degradação sem mensagem (perda parcial do smokeping) não tem causa
+    let message = match (&record.status, &record.message) {
+        (MetricStatus::Up, _) | (MetricStatus::Degraded, None) => None,
+        (_, Some(message)) => Some(message.clone()),
+        (status, None) => Some(format!("{} na origem ({})", status, format.as_str())),
+    };
+    if let Some(message) = message {
+        let error = match record.status {
+            MetricStatus::Timeout => CheckError::Timeout,
+            _ => CheckError::classify(&message),
+        };
+        metric.set_error(error, message);
+    }
+    metric
+}
+
+/// Lê e normaliza o arquivo.
+fn read(args: &ImportArgs) -> Result<Parsed> {
+    let content = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Falha ao ler {}", args.file.display()))?;
+    let check = match &args.check {
+        Some(name) => Some(check_kind(name).with_context(|| {
+            format!(
+                "check desconhecido: `{}` (use ping, tcp, http, dns ou tls)",
+                name
+            )
+        })?),
+        None => None,
+    };
+
+    let mut parsed = Parsed {
+        targets: BTreeMap::new(),
+        ignored: 0,
+    };
+    for (i, row) in rows(&content)?.iter().enumerate() {
+        let n = i + 1;
+        let normalized = match args.format {
+            ImportFormat::Smokeping => smokeping(row),
+            ImportFormat::UptimeKuma => uptime_kuma(row),
+            ImportFormat::Pingdom => pingdom(row),
+        }
+        .with_context(|| format!("registro {}", n))?;
+        let Some(mut record) = normalized else {
+            parsed.ignored += 1;
+            continue;
+        };
+        if let Some(target) = &args.target {
+            record.target = target.clone();
+        }
+        if record.target.is_empty() {
+            bail!("registro {}: sem endereço do target; informe --target", n);
+        }
+        if let Some(kind) = check {
+            record.kind = kind;
+        }
+        parsed
+            .targets
+            .entry(record.target.clone())
+            .or_default()
+            .push(record);
+    }
+    for records in parsed.targets.values_mut() {
+        records.sort_by_key(|r| r.timestamp);
+    }
+    Ok(parsed)
+}
+
+fn smokeping(row: &Row) -> Result<Option<Record>> {
+    let timestamp = time(row)?;
+    // Sem `loss` o RRD não tem medição no intervalo
+    let Some(lost) = number(row, "loss") else {
+        return Ok(None);
+    };
+    let pings = number(row, "pings").unwrap_or(SMOKEPING_DEFAULT_PINGS);
+    if pings <= 0.0 {
+        bail!("`pings` deve ser maior que zero");
+    }
+    let loss_percent = (lost / pings * 100.0).round().clamp(0.0, 100.0) as i16;
+    let status = match loss_percent {
+        100 => MetricStatus::Timeout,
+        0 => MetricStatus::Up,
+        _ => MetricStatus::Degraded,
+    };
+    let message = (status == MetricStatus::Timeout).then(|| "100% de perda".to_string());
+    Ok(Some(Record {
+        timestamp,
+        target: field(row, "target").unwrap_or_default(),
+        kind: CheckKind::Ping,
+        status,
+        rtt_ms: number(row, "median").map(|secs| secs * 1000.0),
+        loss_percent: Some(loss_percent),
+        message,
+    }))
+}
+
+fn uptime_kuma(row: &Row) -> Result<Option<Record>> {
+    let timestamp = time(row)?;
+    let status = match field(row, "status").as_deref() {
+        Some("1") | Some("true") => MetricStatus::Up,
+        Some("0") | Some("false") => MetricStatus::Down,
+        Some("2") => MetricStatus::Degraded,
+        Some("3") => return Ok(None),
+        other => bail!("`status` inválido: {:?}", other),
+    };
+    let kind = match field(row, "type").as_deref() {
+        None | Some("http") | Some("keyword") | Some("json-query") => CheckKind::Http,
+        Some("port") => CheckKind::Tcp,
+        Some("ping") => CheckKind::Ping,
+        Some("dns") => CheckKind::Dns,
+        // Monitores sem medição de rede (push, docker, bancos…)
+        Some(_) => return Ok(None),
+    };
+    let target = match (field(row, "url"), field(row, "hostname")) {
+        (Some(url), _) if url != "https://" => url,
+        (_, Some(hostname)) => match field(row, "port") {
+            Some(port) if kind == CheckKind::Tcp => format!("{}:{}", hostname, port),
+            _ => hostname,
+        },
+        _ => field(row, "target").unwrap_or_default(),
+    };
+    Ok(Some(Record {
+        timestamp,
+        target,
+        kind,
+        status,
+        rtt_ms: number(row, "ping"),
+        loss_percent: None,
+        message: field(row, "msg"),
+    }))
+}
+
+fn pingdom(row: &Row) -> Result<Option<Record>> {
+    let timestamp = time(row)?;
+    let status = match field(row, "status").as_deref() {
+        Some("up") => MetricStatus::Up,
+        Some("down") => MetricStatus::Down,
+        // Falha vista de uma só localidade, ainda não confirmada pelas demais
+        Some("unconfirmed_down") => MetricStatus::Degraded,
+        Some("unknown") => return Ok(None),
+        other => bail!("`status` inválido: {:?}", other),
+    };
+    Ok(Some(Record {
+        timestamp,
+        target: field(row, "hostname")
+            .or_else(|| field(row, "target"))
+            .unwrap_or_default(),
+        kind: CheckKind::Http,
+        status,
+        rtt_ms: number(row, "responsetime"),
+        loss_percent: None,
+        message: field(row, "statusdesclong").or_else(|| field(row, "statusdesc")),
+    }))
+}
+
+fn check_kind(name: &str) -> Option<CheckKind> {
+    Some(match name {
+        "ping" => CheckKind::Ping,
+        "tcp" => CheckKind::Tcp,
+        "http" => CheckKind::Http,
+        "dns" => CheckKind::Dns,
+        "tls" => CheckKind::Tls,
+        "udp" => CheckKind::Udp,
+        "sip" => CheckKind::Sip,
+        _ => return None,
+    })
+}
+
+fn field(row: &Row, name: &str) -> Option<String> {
+    row.get(name).cloned()
+}
+
+/// Número finito do campo (`NaN` e vazio contam como ausente).
+fn number(row: &Row, name: &str) -> Option<f64> {
+    row.get(name)?.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn time(row: &Row) -> Result<DateTime<Utc>> {
+    let value = row.get("time").context("campo `time` ausente")?;
+    parse_time(value).with_context(|| format!("`time` inválido: `{}`", value))
+}
+
+/// Epoch em segundos ou milissegundos, RFC 3339 ou `AAAA-MM-DD HH:MM:SS`
+/// (UTC).
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(epoch) = value.parse::<f64>() {
+        // Segundos só passam de 1e11 depois do ano 5000
+        let millis = if epoch > 1e11 { epoch } else { epoch * 1000.0 };
+        return DateTime::from_timestamp_millis(millis as i64);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|time| time.and_utc())
+}
+
+/// Linhas do arquivo, em JSON ou CSV conforme o primeiro caractere.
+fn rows(content: &str) -> Result<Vec<Row>> {
+    let trimmed = content.trim_start();
+    if !trimmed.starts_with('[') && !trimmed.starts_with('{') {
+        return csv_rows(content);
+    }
+    let items = match serde_json::from_str(content).context("JSON inválido")? {
+        Value::Array(items) => items,
+        Value::Object(mut map) => ["results", "heartbeats", "data"]
+            .iter()
+            .find_map(|key| match map.remove(*key) {
+                Some(Value::Array(items)) => Some(items),
+                _ => None,
+            })
+            .context("JSON sem lista de registros (esperado um array ou `results`, `heartbeats` ou `data`)")?,
+        _ => bail!("JSON sem lista de registros"),
+    };
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Object(map) => Ok(map
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let text = match value {
+                        Value::Null => return None,
+                        Value::String(text) => text,
+                        other => other.to_string(),
+                    };
+                    Some((key.to_ascii_lowercase(), text))
+                })
+                .filter(|(_, text)| !text.trim().is_empty())
+                .collect()),
+            _ => bail!("registro {}: esperado um objeto", i + 1),
+        })
+        .collect()
+}
+
+/// CSV com cabeçalho na primeira linha.
+fn csv_rows(content: &str) -> Result<Vec<Row>> {
+    let mut records = csv_records(content).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let header: Vec<String> = header
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            if record.len() != header.len() {
+                bail!(
+                    "registro {}: {} campo(s), o cabeçalho tem {}",
+                    i + 1,
+                    record.len(),
+                    header.len()
+                );
+            }
+            Ok(header
+                .iter()
+                .zip(record)
+                .map(|(name, value)| (name.clone(), value.trim().to_string()))
+                .filter(|(_, value)| !value.is_empty())
+                .collect())
+        })
+        .collect()
+}
+
+/// Registros CSV (RFC 4180: aspas duplas, `""` como escape e quebras de
+/// linha dentro de aspas); linhas vazias são descartadas.
+fn csv_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    let mut finish = |record: &mut Vec<String>, field: &mut String| {
+        record.push(std::mem::take(field));
+        if !(record.len() == 1 && record[0].trim().is_empty()) {
+            records.push(std::mem::take(record));
+        }
+        record.clear();
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\n' if !quoted => finish(&mut record, &mut field),
+            '\r' if !quoted => {}
+            _ => field.push(c),
+        }
+    }
+    finish(&mut record, &mut field);
+    records
+}
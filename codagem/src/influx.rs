@@ -0,0 +1,111 @@
+//! influx.rs — Sink secundário em Influx line protocol
+//!
+//! Escreve as métricas de cada ciclo via HTTP write API (InfluxDB 1.x/2.x,
+//! VictoriaMetrics e compatíveis), em paralelo à persistência no PostgreSQL.
+//! Falhas aqui nunca afetam o ciclo de monitoramento: são apenas logadas.
+
+use crate::config::InfluxConfig;
+use crate::types::{ConnectivityMetric, MetricStatus};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Cliente de escrita em line protocol.
+#[derive(Debug, Clone)]
+pub struct InfluxSink {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    measurement: String,
+}
+
+impl InfluxSink {
+    pub fn new(config: &InfluxConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP do sink Influx")?;
+        Ok(Self {
+            client,
+            url: config.url.clone(),
+            token: config.token.clone(),
+            measurement: config.measurement.clone(),
+        })
+    }
+
+    /// Converte métricas em linhas do line protocol (precisão em nanossegundos).
+    ///
+    /// ```text
+    /// connectivity,probe_id=1,target_id=3,metric_type=ping_ipv4 status="up",up=1i,response_time_ms=12.3,packet_loss_percent=0i 1700000000000000000
+    /// ```
+    ///
+    /// O target vai só pelo id: nome e endereço ficam no banco.
+    pub fn encode(&self, metrics: &[ConnectivityMetric]) -> String {
+        let mut body = String::new();
+        for metric in metrics {
+            let _ = write!(
+                body,
+                "{},probe_id={},target_id={},metric_type={}",
+                escape_measurement(&self.measurement),
+                metric.probe_id,
+                metric.target_id,
+                metric.metric_type,
+            );
+            if let Some(error) = metric.error {
+                let _ = write!(body, ",error_kind={}", error);
+            }
+            let _ = write!(
+                body,
+                " status=\"{}\",up={}i",
+                metric.status,
+                i32::from(metric.status == MetricStatus::Up),
+            );
+            if let Some(rtt) = metric.response_time_ms {
+                let _ = write!(body, ",response_time_ms={}", rtt);
+            }
+            if let Some(loss) = metric.packet_loss_percent {
+                let _ = write!(body, ",packet_loss_percent={}i", loss);
+            }
+            if let Some(error) = metric.error_message() {
+                let _ = write!(body, ",error_message=\"{}\"", escape_field(error));
+            }
+            let timestamp = metric
+                .timestamp
+                .timestamp_nanos_opt()
+                .unwrap_or_else(|| metric.timestamp.timestamp() * 1_000_000_000);
+            let _ = writeln!(body, " {}", timestamp);
+        }
+        body
+    }
+
+    /// Envia as métricas de um ciclo para a API de escrita.
+    pub async fn write_metrics(&self, metrics: &[ConnectivityMetric]) -> Result<()> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        let mut request = self.client.post(&self.url).body(self.encode(metrics));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        let response = request
+            .send()
+            .await
+            .context("Falha ao enviar métricas ao Influx")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Influx respondeu {}: {}", status, body);
+        }
+        Ok(())
+    }
+}
+
+/// Escapa vírgulas e espaços no nome da measurement.
+fn escape_measurement(input: &str) -> String {
+    input.replace(',', "\\,").replace(' ', "\\ ")
+}
+
+/// Escapa aspas e barras invertidas em campos string.
+fn escape_field(input: &str) -> String {
+    input.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,232 @@
+//! inventory.rs — Targets e probes declarados em arquivo (modo GitOps)
+//!
+//! Com `[inventory]`, targets e probes vêm de um arquivo YAML ou JSON no
+//! formato de um recurso do Kubernetes, tipicamente um ConfigMap montado no
+//! pod, em vez das tabelas `monitoring_targets` e `monitoring_probes`:
+//!
+//! ```yaml
+//! apiVersion: monitoramento-rede/v1
+//! kind: MonitoringInventory
+//! spec:
+//!   probes:
+//!     - location: "São Paulo - k8s"
+//!       provider: "AWS"
+//!   targets:
+//!     - inputs: ["8.8.8.8", "https://loja.exemplo.com/"]
+//!       template: "web-service"
+//! ```
+//!
+//! As entradas de `targets` aceitam os mesmos campos de `[[targets]]`. O
+//! banco segue guardando os resultados: os targets e probes do arquivo
+//! ganham uma linha nas tabelas (pelo endereço e pela localização) só para
+//! ter um id, mas a configuração que vale é a do arquivo. Linhas que não
+//! estão no arquivo não são monitoradas nem removidas, já que os resultados
+//! antigos apontam para elas.
+
+use crate::config::InventoryConfig;
+use crate::registration::{self, Registration};
+use crate::storage::Storage;
+use crate::templates::TemplateSet;
+use crate::types::{GeoLocation, Probe, Target};
+use anyhow::{Context, Result, anyhow, bail};
+use config as config_crate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use tracing::info;
+
+/// `kind` esperado no arquivo.
+const KIND: &str = "MonitoringInventory";
+
+#[derive(Debug, Deserialize)]
+struct InventoryFile {
+    kind: Option<String>,
+    spec: InventorySpec,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct InventorySpec {
+    #[serde(default)]
+    probes: Vec<InventoryProbe>,
+    #[serde(default)]
+    targets: Vec<Registration>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InventoryProbe {
+    location: String,
+    ip_address: Option<IpAddr>,
+    provider: Option<String>,
+}
+
+/// Lê o inventário e garante as linhas correspondentes no banco; retorna
+/// os targets e probes a monitorar, com os ids do banco.
+pub async fn load(
+    config: &InventoryConfig,
+    storage: &Storage,
+    templates: &TemplateSet,
+) -> Result<(Vec<Target>, Vec<Probe>)> {
+    let file = parse(config)?;
+    let probes = sync_probes(storage, &file.spec.probes).await?;
+    let targets = sync_targets(storage, templates, &file.spec.targets).await?;
+    info!(
+        "📄 Inventário {}: {} target(s) e {} probe(s)",
+        config.path,
+        targets.len(),
+        probes.len()
+    );
+    Ok((targets, probes))
+}
+
+/// Targets e probes do inventário sem tocar no banco (ids 0), para o
+/// `--validate-config`.
+pub async fn read(
+    config: &InventoryConfig,
+    templates: &TemplateSet,
+) -> Result<(Vec<Target>, Vec<Probe>)> {
+    let file = parse(config)?;
+    let mut targets = Vec::new();
+    for (i, entry) in file.spec.targets.iter().enumerate() {
+        let prepared = registration::prepare(templates, entry)
+            .await
+            .map_err(|e| anyhow!("Inventário: targets[{}] {:?}: {}", i, entry.inputs, e))?;
+        targets.extend(prepared.into_iter().map(|(_, target)| target));
+    }
+    let probes = file
+        .spec
+        .probes
+        .iter()
+        .map(|entry| Probe {
+            id: 0,
+            location: entry.location.clone(),
+            ip_address: entry.ip_address,
+            provider: entry.provider.clone(),
+            created_at: None,
+            geo: GeoLocation::default(),
+        })
+        .collect();
+    Ok((targets, probes))
+}
+
+/// Lê e confere o arquivo.
+fn parse(config: &InventoryConfig) -> Result<InventoryFile> {
+    let file: InventoryFile = config_crate::Config::builder()
+        .add_source(config_crate::File::with_name(&config.path).required(true))
+        .build()
+        .and_then(|settings| settings.try_deserialize())
+        .with_context(|| format!("Inventário inválido: {}", config.path))?;
+    if let Some(kind) = &file.kind
+        && kind != KIND
+    {
+        bail!(
+            "Inventário {}: kind `{}` (esperado `{}`)",
+            config.path,
+            kind,
+            KIND
+        );
+    }
+
+    if file.spec.targets.is_empty() || file.spec.probes.is_empty() {
+        bail!(
+            "Inventário {}: spec.targets e spec.probes não podem ser vazios",
+            config.path
+        );
+    }
+    Ok(file)
+}
+
+/// Probes do arquivo, com o id da linha de mesma localização (inserida se
+/// ainda não existe).
+async fn sync_probes(storage: &Storage, entries: &[InventoryProbe]) -> Result<Vec<Probe>> {
+    let existing: HashMap<String, Probe> = storage
+        .list_probes()
+        .await?
+        .into_iter()
+        .map(|p| (p.location.clone(), p))
+        .collect();
+    let mut probes: Vec<Probe> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if probes.iter().any(|p| p.location == entry.location) {
+            bail!("Inventário: probe {} repetido", entry.location);
+        }
+        let mut probe = Probe {
+            id: 0,
+            location: entry.location.clone(),
+            ip_address: entry.ip_address,
+            provider: entry.provider.clone(),
+            created_at: None,
+            geo: GeoLocation::default(),
+        };
+        match existing.get(&entry.location) {
+            Some(row) => {
+                probe.id = row.id;
+                probe.created_at = row.created_at;
+                probe.geo = row.geo.clone();
+            }
+            None => {
+                probe.id = storage.insert_probe(&probe).await?;
+                info!(
+                    "📡 Probe do inventário cadastrado: {} (id {})",
+                    probe.location, probe.id
+                );
+            }
+        }
+        probes.push(probe);
+    }
+    Ok(probes)
+}
+
+/// Targets do arquivo, com o id da linha de mesmo endereço (inserida se
+/// ainda não existe). ASN, provedor e localização preenchidos no banco
+/// (enriquecimento, GeoIP) completam os campos que o arquivo deixa vazios.
+async fn sync_targets(
+    storage: &Storage,
+    templates: &TemplateSet,
+    entries: &[Registration],
+) -> Result<Vec<Target>> {
+    let mut prepared: Vec<(String, Target)> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let targets = registration::prepare(templates, entry)
+            .await
+            .map_err(|e| anyhow!("Inventário: targets[{}] {:?}: {}", i, entry.inputs, e))?;
+        prepared.extend(targets);
+    }
+
+    let existing: HashMap<IpAddr, Target> = storage
+        .list_targets()
+        .await?
+        .into_iter()
+        .map(|t| (t.address, t))
+        .collect();
+    let mut targets: Vec<Target> = Vec::with_capacity(prepared.len());
+    for (input, mut target) in prepared {
+        if targets.iter().any(|t| t.address == target.address) {
+            bail!(
+                "Inventário: endereço {} ({}) repetido",
+                target.address,
+                input
+            );
+        }
+        match existing.get(&target.address) {
+            Some(row) => {
+                target.id = row.id;
+                target.created_at = row.created_at;
+                target.asn = target.asn.or(row.asn);
+                target.provider = target.provider.or_else(|| row.provider.clone());
+                target.geo = row.geo.clone();
+                target.enabled = row.enabled;
+            }
+            None => {
+                target.id = storage.insert_target(&target).await?.with_context(|| {
+                    format!("Target {} cadastrado concorrentemente", target.address)
+                })?;
+                info!(
+                    "🎯 Target do inventário cadastrado: {} -> {} (tipo: {}, id: {})",
+                    input, target.address, target.type_, target.id
+                );
+            }
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
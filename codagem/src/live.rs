@@ -0,0 +1,178 @@
+//! live.rs — Fluxos ao vivo: métricas e transições (`/ws/live`) e outages
+//! (`/api/events/outages`, Server-Sent Events)
+//!
+//! Cada métrica concluída é serializada uma única vez e distribuída por um
+//! canal `broadcast` aos WebSockets conectados; aberturas e encerramentos
+//! de outages seguem por um canal próprio. A mudança de status de um
+//! target em um probe vira também uma mensagem `transition`; a primeira
+//! métrica de cada par depois do início só registra o status. Um cliente
+//! lento perde as mensagens mais antigas (ver `lagged` em `api`), sem
+//! segurar o ciclo de monitoramento.
+
+use crate::notify::NotificationKind;
+use crate::types::{ConnectivityMetric, MetricStatus, OutageEvent, OutageEventType};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Mensagens retidas por cliente antes de descartar as mais antigas.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Mensagem do fluxo ao vivo.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LiveEvent<'a> {
+    Metric(&'a ConnectivityMetric),
+    Transition(Transition),
+}
+
+/// Mudança de status de um target visto por um probe.
+#[derive(Debug, Serialize)]
+struct Transition {
+    target_id: i32,
+    probe_id: i32,
+    old_status: MetricStatus,
+    new_status: MetricStatus,
+    at: DateTime<Utc>,
+}
+
+/// Mensagem já serializada, com as chaves usadas nos filtros.
+#[derive(Debug, Clone)]
+pub struct LiveMessage {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub json: Arc<str>,
+}
+
+impl LiveMessage {
+    /// A mensagem passa nos filtros (ausente = qualquer um).
+    pub fn matches(&self, probe_id: Option<i32>, target_id: Option<i32>) -> bool {
+        probe_id.is_none_or(|id| id == self.probe_id)
+            && target_id.is_none_or(|id| id == self.target_id)
+    }
+}
+
+/// Abertura ou encerramento de outage, com o probe que o detectou.
+#[derive(Debug, Serialize)]
+struct OutageNotice<'a> {
+    kind: NotificationKind,
+    probe: &'a str,
+    outage: &'a OutageEvent,
+}
+
+/// Outage já serializado, com as chaves usadas nos filtros.
+#[derive(Debug, Clone)]
+pub struct OutageMessage {
+    pub kind: NotificationKind,
+    pub event_type: OutageEventType,
+    pub json: Arc<str>,
+}
+
+/// Distribuidor das mensagens aos clientes conectados.
+#[derive(Debug)]
+pub struct LiveFeed {
+    sender: broadcast::Sender<LiveMessage>,
+    outages: broadcast::Sender<OutageMessage>,
+    /// Último status por (target, probe).
+    statuses: Mutex<HashMap<(i32, i32), MetricStatus>>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (outages, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            outages,
+            statuses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveMessage> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscribe_outages(&self) -> broadcast::Receiver<OutageMessage> {
+        self.outages.subscribe()
+    }
+
+    /// Publica a abertura ou o encerramento de um outage (de consenso,
+    /// parcial ou degradação).
+    pub fn publish_outage(&self, event: &OutageEvent, location: &str) {
+        if self.outages.receiver_count() == 0 {
+            return;
+        }
+        let kind = NotificationKind::for_event(event);
+        let notice = OutageNotice {
+            kind,
+            probe: location,
+            outage: event,
+        };
+        let Ok(json) = serde_json::to_string(&notice) else {
+            return;
+        };
+        let _ = self.outages.send(OutageMessage {
+            kind,
+            event_type: event.event_type,
+            json: json.into(),
+        });
+    }
+
+    /// Publica as métricas de um ciclo e as transições que elas causam. Os
+    /// status são acompanhados mesmo sem clientes, para que a primeira
+    /// transição vista por um cliente novo seja real.
+    pub fn publish(&self, metrics: &[ConnectivityMetric]) {
+        let mut transitions: Vec<Transition> = Vec::new();
+        {
+            let mut statuses = self.statuses.lock().unwrap();
+            for metric in metrics {
+                let key = (metric.target_id, metric.probe_id);
+                if let Some(old_status) = statuses.insert(key, metric.status.clone())
+                    && old_status != metric.status
+                {
+                    transitions.push(Transition {
+                        target_id: metric.target_id,
+                        probe_id: metric.probe_id,
+                        old_status,
+                        new_status: metric.status.clone(),
+                        at: metric.timestamp,
+                    });
+                }
+            }
+        }
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        for metric in metrics {
+            self.send(
+                metric.probe_id,
+                metric.target_id,
+                &LiveEvent::Metric(metric),
+            );
+        }
+        for transition in transitions {
+            let (probe_id, target_id) = (transition.probe_id, transition.target_id);
+            self.send(probe_id, target_id, &LiveEvent::Transition(transition));
+        }
+    }
+
+    fn send(&self, probe_id: i32, target_id: i32, event: &LiveEvent) {
+        let Ok(json) = serde_json::to_string(event) else {
+            return;
+        };
+        // Sem clientes, o envio falha; não há o que fazer
+        let _ = self.sender.send(LiveMessage {
+            probe_id,
+            target_id,
+            json: json.into(),
+        });
+    }
+}
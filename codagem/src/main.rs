@@ -1,39 +1,168 @@
 // src/main.rs
+mod aggregates;
+mod anonymize;
+mod api;
+mod as_health;
+mod asymmetry;
+mod auth;
+mod baseline;
+mod bgp;
+mod bufferbloat;
+mod bundle;
+mod calendar;
+mod captive;
+mod chaos;
+mod check;
+mod cli;
+mod clock;
+mod commands;
 mod config;
+mod config_check;
 mod consensus;
+mod deadman;
+mod decisions;
+mod degradation;
+mod diagnosis;
+mod discovery;
+mod doctor;
+mod enrichment;
+mod export;
+mod exporters;
+mod fleet;
+mod geoip;
+mod heatmap;
+mod host;
+mod http_trace;
+mod import;
+mod influx;
+mod inventory;
+mod live;
+mod memory_storage;
+mod migrations;
+mod neighbor;
+mod netwatch;
+mod notify;
+mod ocsp;
+mod on_demand;
 mod outage;
+mod periodic_report;
+mod persistence;
 mod ping;
+mod probe_lock;
+mod probe_profile;
+mod provenance;
+mod public_stats;
+mod ratelimit;
+mod reconcile;
+mod registration;
+mod remote_write;
+mod replay;
+mod scenario;
 mod scheduler;
+mod shadow;
+mod sink;
+mod sip_check;
+mod snapshot;
+mod snmp;
+mod soak;
+mod source;
+mod spool;
+mod status_page;
 mod storage;
+mod streaming;
+mod supervisor;
+mod target_address;
+mod templates;
+mod tenant;
+mod timescale;
+mod tls_check;
+mod traceroute;
+mod tui;
 mod types;
+mod uplink_report;
 
-use crate::consensus::ConsensusState;
+use crate::scheduler::{SchedulerBuilder, SchedulerHandle};
+use crate::shadow::ShadowConsensusSet;
 use anyhow::{Context, Result};
+use clap::Parser;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::task;
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
-use tracing_subscriber;
+
+/// Parâmetros que exigem um registry de checks próprio por perfil de probe:
+/// tentativas, timeout e origem dos checks.
+type ProfileChecksKey = (
+    usize,
+    u64,
+    config::SourceConfig,
+    std::collections::BTreeMap<String, config::SourceConfig>,
+);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt().init();
+    let cli = cli::Cli::parse();
+
+    if cli.tui {
+        // O terminal pertence ao dashboard: logs vão para arquivo
+        let log_file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&cli.log_file)
+            .with_context(|| format!("Falha ao abrir arquivo de log {}", cli.log_file))?;
+        tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(std::sync::Mutex::new(log_file))
+            .init();
+    } else {
+        tracing_subscriber::fmt().init();
+    }
+
+    if cli.validate_config {
+        return config_check::run(cli.config.as_deref()).await;
+    }
+    if let Some(cli::Command::Doctor(args)) = &cli.command {
+        return doctor::run(cli.config.as_deref(), args).await;
+    }
+    if let Some(cli::Command::Scenario(args)) = &cli.command {
+        return scenario::run(args);
+    }
 
     info!("🚀 Iniciando aplicação de monitoramento de rede...");
 
     // Carregando configuração
     info!("🔧 Carregando configuração...");
-    let config: Arc<config::Config> =
-        Arc::new(config::Config::load().context("Falha ao carregar configuração")?);
+    let config: Arc<config::Config> = Arc::new(
+        config::Config::load(cli.config.as_deref()).context("Falha ao carregar configuração")?,
+    );
     debug!("Configuração carregada: {:?}", config);
+    let templates = Arc::new(templates::TemplateSet::new(&config.target_templates));
 
-    let consensus_state: Arc<Mutex<ConsensusState>> = Arc::new(Mutex::new(ConsensusState::new(
-        config.fail_threshold,
-        config.consensus,
-        None,
-    )));
+    if let Some(cli::Command::Targets(command)) = &cli.command
+        && commands::run_offline(command, &templates)
+    {
+        return Ok(());
+    }
+    if let Some(cli::Command::Import(args)) = &cli.command
+        && args.dry_run
+    {
+        return import::preview(args);
+    }
+    if let Some(cli::Command::Soak(args)) = &cli.command
+        && args.memory
+    {
+        return soak::run(Arc::clone(&config), None, args.clone()).await;
+    }
+
+    if !config.shadow_consensus.is_empty() {
+        info!(
+            "👥 {} instância(s) de consenso sombra configurada(s)",
+            config.shadow_consensus.len()
+        );
+    }
 
     // Conectando ao banco de dados com timeout
     info!("🗄️  Conectando ao banco de dados...");
@@ -47,35 +176,566 @@ async fn main() -> Result<()> {
     );
     info!("✅ Conexão ao banco de dados estabelecida.");
 
-    // Listando targets
-    info!("🎯 Consultando targets...");
-    let targets: Vec<types::Target> = timeout(Duration::from_secs(8), storage.list_targets())
+    // Aplicando migrações pendentes
+    let applied = storage
+        .run_migrations()
         .await
-        .context("Timeout ao consultar targets")??;
-    info!("Targets encontrados: {}", targets.len());
-    if targets.is_empty() {
-        error!("Nenhum alvo registrado no banco de dados");
-        anyhow::bail!("Nenhum alvo registrado no banco de dados");
+        .context("Falha ao aplicar migrações do banco de dados")?;
+    info!("🧱 Migrações aplicadas: {}", applied);
+
+    // Modo TimescaleDB (opcional): hypertable e compressão das métricas
+    if let Some(timescale_config) = &config.timescale {
+        storage
+            .setup_timescale(timescale_config)
+            .await
+            .context("Falha ao configurar o TimescaleDB")?;
     }
 
-    // Listando probes
-    info!("📡 Consultando probes...");
-    let probes: Vec<types::Probe> = timeout(Duration::from_secs(8), storage.list_probes())
+    // Réplica de leitura (opcional) para relatórios, exportações, página de
+    // status e os GETs da API; sem ela, ou sem conexão, as consultas vão ao
+    // primário
+    let reads: Arc<storage::Storage> = match &config.database_read_url {
+        Some(read_url) => match timeout(
+            Duration::from_secs(10),
+            storage::Storage::connect_replica(read_url),
+        )
         .await
-        .context("Timeout ao consultar probes")??;
-    info!("Probes encontrados: {}", probes.len());
-    if probes.is_empty() {
-        error!("Nenhum probe registrado no banco de dados");
-        anyhow::bail!("Nenhum probe registrado no banco de dados");
+        {
+            Ok(Ok(replica)) => {
+                info!("📖 Réplica de leitura conectada: consultas e relatórios vão a ela");
+                replica
+            }
+            Ok(Err(e)) => {
+                warn!(
+                    "⚠️ Falha ao conectar à réplica de leitura; consultas seguem no primário: {:?}",
+                    e
+                );
+                Arc::clone(&storage)
+            }
+            Err(_) => {
+                warn!("⚠️ Timeout ao conectar à réplica de leitura; consultas seguem no primário");
+                Arc::clone(&storage)
+            }
+        },
+        None => Arc::clone(&storage),
+    };
+
+    // Pseudonimização (opcional) para página de status e exportações
+    let pseudonymizer: Option<anonymize::Pseudonymizer> = config
+        .anonymization
+        .as_ref()
+        .map(anonymize::Pseudonymizer::new);
+
+    match cli.command {
+        Some(cli::Command::Targets(command)) => {
+            return commands::run_targets(command, &storage, &templates).await;
+        }
+        Some(cli::Command::Report(command)) => {
+            return commands::run_report(command, &reads, &config).await;
+        }
+        Some(cli::Command::Bundle(args)) => {
+            return bundle::run(&reads, args, pseudonymizer.as_ref()).await;
+        }
+        Some(cli::Command::Export(args)) => {
+            return export::run(&reads, args, pseudonymizer.as_ref()).await;
+        }
+        Some(cli::Command::Replay(args)) => {
+            return replay::run(&reads, &config, args).await;
+        }
+        Some(cli::Command::Scenario(_)) | Some(cli::Command::Doctor(_)) => {
+            unreachable!("executado antes da conexão ao banco")
+        }
+        Some(cli::Command::Import(args)) => {
+            return import::run(&storage, &templates, args).await;
+        }
+        Some(cli::Command::Soak(args)) => {
+            return soak::run(Arc::clone(&config), Some(Arc::clone(&storage)), args).await;
+        }
+        None => {}
     }
 
-    // Spawn de schedulers para cada probe
-    let mut handles: Vec<task::JoinHandle<()>> = Vec::new();
-    for probe in probes {
-        let config = Arc::clone(&config);
-        let storage = Arc::clone(&storage);
-        let targets = targets.clone();
+    let (targets, probes): (Vec<types::Target>, Vec<types::Probe>) = match &config.inventory {
+        // Targets e probes do arquivo de inventário; o banco só guarda resultados
+        Some(inventory_config) => {
+            if !config.targets.is_empty() || config.discovery.is_some() {
+                warn!("⚠️ [[targets]] e [discovery] são ignorados com [inventory]");
+            }
+            inventory::load(inventory_config, &storage, &templates).await?
+        }
+        None => {
+            // Targets declarados na configuração
+            for request in &config.targets {
+                if let Err(e) = registration::register(&storage, &templates, request).await {
+                    warn!(
+                        "⚠️ Falha ao cadastrar targets {:?} da configuração: {}",
+                        request.inputs, e
+                    );
+                }
+            }
+
+            // Descoberta automática de targets (opcional)
+            if let Some(discovery_config) = &config.discovery {
+                info!("🔎 Descobrindo targets...");
+                match discovery::discover(discovery_config, &storage, &templates).await {
+                    Ok(inserted) => info!("Targets descobertos e cadastrados: {}", inserted),
+                    Err(e) => warn!("⚠️ Falha na descoberta de targets: {:?}", e),
+                }
+            }
+
+            // Listando targets
+            info!("🎯 Consultando targets...");
+            let targets: Vec<types::Target> =
+                timeout(Duration::from_secs(8), storage.list_targets())
+                    .await
+                    .context("Timeout ao consultar targets")??;
+            info!("Targets encontrados: {}", targets.len());
+            if targets.is_empty() {
+                error!("Nenhum alvo registrado no banco de dados");
+                anyhow::bail!("Nenhum alvo registrado no banco de dados");
+            }
+
+            // Listando probes
+            info!("📡 Consultando probes...");
+            let probes: Vec<types::Probe> = timeout(Duration::from_secs(8), storage.list_probes())
+                .await
+                .context("Timeout ao consultar probes")??;
+            info!("Probes encontrados: {}", probes.len());
+            if probes.is_empty() {
+                error!("Nenhum probe registrado no banco de dados");
+                anyhow::bail!("Nenhum probe registrado no banco de dados");
+            }
+
+            (targets, probes)
+        }
+    };
+
+    // Targets separados por tenant; em cada grupo, um consenso por probe com
+    // os parâmetros do grupo e os thresholds por target. Parâmetros
+    // incompatíveis com os targets abortam
+    let groups: Vec<tenant::TenantGroup> = tenant::partition(&config, &targets);
+    probe_profile::warn_unknown_targets(&config, &targets);
+    if let Some(chaos) = &config.chaos {
+        chaos::warn_enabled(chaos, &targets);
+    }
+    // Targets atribuídos por probe (`probe_targets`); sem atribuição, todos
+    let assignments: HashMap<i32, Vec<i32>> = match storage.list_probe_targets().await {
+        Ok(assignments) => assignments,
+        Err(e) => {
+            warn!(
+                "⚠️ Falha ao consultar atribuições de targets; todos os probes monitoram todos os targets: {:?}",
+                e
+            );
+            HashMap::new()
+        }
+    };
+    for probe in probes.iter().filter(|p| assignments.contains_key(&p.id)) {
+        info!(
+            "📌 Probe {}: {} target(s) atribuído(s)",
+            probe.location,
+            assignments[&probe.id].len()
+        );
+    }
+    let mut builders: Vec<(usize, SchedulerBuilder)> = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        let label = group
+            .name
+            .as_ref()
+            .map_or(String::new(), |name| format!(" [{}]", name));
+        for probe in &probes {
+            // Perfil do probe: parâmetros e targets próprios
+            let assigned = probe_profile::assigned(&assignments, probe, &group.targets);
+            let profile = probe_profile::find(&config, probe);
+            let (probe_config, probe_targets) = match profile {
+                Some(profile) => (
+                    probe_profile::apply(&group.config, profile),
+                    probe_profile::select_targets(profile, &assigned),
+                ),
+                None => (Arc::clone(&group.config), assigned),
+            };
+            if probe_targets.is_empty() {
+                warn!(
+                    "⚠️ Probe {}{} sem targets (atribuições ou perfil); scheduler não criado",
+                    probe.location, label
+                );
+                continue;
+            }
+            if profile.is_some() {
+                info!(
+                    "🎛️  Perfil do probe {}{}: ciclo de {}s, {} tentativa(s) de {}s, fail_threshold = {}, consensus = {} ({} target(s))",
+                    probe.location,
+                    label,
+                    probe_config.cycle_interval_secs,
+                    probe_config.ping_count,
+                    probe_config.timeout_secs,
+                    probe_config.fail_threshold,
+                    probe_config.consensus,
+                    probe_targets.len()
+                );
+            }
+            let targets_count = probe_targets.len();
+            let builder = SchedulerBuilder::new(
+                group.probe(probe),
+                probe_targets,
+                Arc::clone(&probe_config),
+            )
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Parâmetros de consenso{} do probe {} inválidos (fail_threshold = {}, consensus = {}, {} target(s)): {}",
+                    label,
+                    probe.location,
+                    probe_config.fail_threshold,
+                    probe_config.consensus,
+                    targets_count,
+                    e
+                )
+            })?;
+            builders.push((index, builder));
+        }
+        info!(
+            "🧮 Consenso{}: fail_threshold = {}, consensus = {}, {} ({} target(s))",
+            label,
+            group.config.fail_threshold,
+            group.config.consensus,
+            group.config.consensus_fail_percent.map_or(
+                "falha em todos os ciclos".to_string(),
+                |p| format!("falha em ≥ {}% dos ciclos", p)
+            ),
+            group.targets.len()
+        );
+    }
+
+    // Consenso sombra por grupo: as métricas de um tenant não alimentam o de outro
+    let shadow_states: Vec<Arc<Mutex<ShadowConsensusSet>>> = groups
+        .iter()
+        .map(|_| {
+            Arc::new(Mutex::new(ShadowConsensusSet::new(
+                &config.shadow_consensus,
+                config.consensus_history_max_bytes,
+            )))
+        })
+        .collect();
+
+    // Um probe por instância: advisory lock no banco
+    probe_lock::acquire(&config.database_url, &probes, cli.takeover)
+        .await
+        .context("Falha ao obter o lock de instância dos probes")?;
+
+    // Atualização periódica das views agregadas (dashboards)
+    task::spawn(aggregates::run_refresher(
+        Arc::clone(&storage),
+        config.aggregate_refresh_interval_secs,
+    ));
+
+    // Limpeza dos registros de decisão do consenso (opcional)
+    if let Some(decisions_config) = &config.consensus_decisions {
+        info!(
+            "🧾 Registro de decisões do consenso habilitado (retenção: {} dias)",
+            decisions_config.retention_days
+        );
+        task::spawn(decisions::run_retention(
+            Arc::clone(&storage),
+            decisions_config.retention_days,
+        ));
+    }
+
+    // Descarte dos chunks de métricas expirados (modo TimescaleDB)
+    if let Some(timescale_config) = &config.timescale
+        && timescale_config.retention_days > 0
+    {
+        info!(
+            "🐯 Retenção das métricas por chunks habilitada ({} dias)",
+            timescale_config.retention_days
+        );
+        task::spawn(timescale::run_retention(
+            Arc::clone(&storage),
+            timescale_config.retention_days,
+        ));
+    }
+
+    // Histogramas horários de RTT para heatmaps (opcional)
+    if let Some(heatmap_config) = &config.latency_heatmap {
+        info!(
+            "🌡️  Histogramas de latência habilitados ({} faixa(s), a cada {}s)",
+            heatmap_config.buckets_ms.len() + 1,
+            heatmap_config.interval_secs
+        );
+        task::spawn(heatmap::run(Arc::clone(&storage), heatmap_config.clone()));
+    }
+
+    // ASN e provedor dos targets por consulta externa (opcional)
+    if let Some(enrichment_config) = &config.enrichment {
+        info!(
+            "🏷️  Enriquecimento de ASN/provedor habilitado ({:?}, a cada {}s)",
+            enrichment_config.source, enrichment_config.interval_secs
+        );
+        task::spawn(enrichment::run(
+            Arc::clone(&storage),
+            enrichment_config.clone(),
+        ));
+    }
+
+    // País, cidade e coordenadas de targets e probes (opcional)
+    if let Some(geoip_config) = &config.geoip {
+        info!(
+            "🌍 GeoIP habilitado ({}, a cada {}s)",
+            geoip_config.database_path, geoip_config.interval_secs
+        );
+        task::spawn(geoip::run(Arc::clone(&storage), geoip_config.clone()));
+    }
+
+    // Visibilidade BGP dos prefixos durante outages (opcional)
+    if let Some(bgp_config) = &config.bgp {
+        info!(
+            "🛰️  Verificação BGP dos outages habilitada (RIPEstat, a cada {}s)",
+            bgp_config.interval_secs
+        );
+        task::spawn(bgp::run(Arc::clone(&storage), bgp_config.clone()));
+    }
+
+    // Contadores de interface do CPE/roteador por SNMP (opcional)
+    if let Some(snmp_config) = &config.snmp {
+        info!(
+            "🔌 Consulta SNMP habilitada ({} dispositivo(s), a cada {}s)",
+            snmp_config.devices.len(),
+            snmp_config.interval_secs
+        );
+        task::spawn(snmp::run(Arc::clone(&storage), snmp_config.clone()));
+    }
+
+    // Alcance e MAC dos gateways do segmento local por ARP/ND (opcional)
+    if let Some(neighbor_config) = &config.neighbor {
+        info!(
+            "📡 Consulta ARP/ND dos gateways habilitada (a cada {}s)",
+            neighbor_config.interval_secs
+        );
+        task::spawn(neighbor::run(Arc::clone(&storage), neighbor_config.clone()));
+    }
+
+    // Mudanças de interface e de rota padrão do host por netlink (opcional)
+    if let Some(netwatch_config) = &config.netwatch {
+        info!("🧭 Escuta de mudanças de interface e rota padrão habilitada (netlink)");
+        task::spawn(netwatch::run(Arc::clone(&storage), netwatch_config.clone()));
+    }
+
+    if pseudonymizer.is_some() {
+        info!("🕶️  Pseudonimização de targets habilitada");
+    }
+
+    // Snapshot do estado corrente, compartilhado com os consumidores de leitura
+    let snapshot: Arc<snapshot::SnapshotStore> = Arc::new(snapshot::SnapshotStore::new());
+
+    // Pipeline de persistência: schedulers enfileiram, uma task grava em lotes.
+    // Ambos só dependem das operações de `StorageBackend`
+    let backend: Arc<dyn storage::StorageBackend> = storage.clone();
+    let persistence = persistence::Persistence::spawn(
+        Arc::clone(&backend),
+        &config.persistence,
+        Arc::clone(&snapshot),
+    );
+
+    // Página pública de status (opcional)
+    if let Some(status_page_config) = config.status_page.clone() {
+        task::spawn(status_page::run_status_page(
+            status_page_config,
+            Arc::clone(&reads),
+            Arc::clone(&snapshot),
+            pseudonymizer.clone(),
+        ));
+    }
 
+    // Destinos secundários (opcionais): Influx, remote-write, streaming de
+    // eventos, heartbeat externo e notificações, estas pelas rotas de cada
+    // tenant
+    if let Some(influx_config) = &config.influx {
+        info!("📤 Exportando métricas para Influx: {}", influx_config.url);
+    }
+    if let Some(remote_write_config) = &config.remote_write {
+        info!(
+            "📤 Exportando métricas via Prometheus remote-write: {}",
+            remote_write_config.url
+        );
+    }
+    if let Some(streaming_config) = &config.streaming {
+        info!(
+            "📡 Publicando eventos via {:?} em {} (prefixo: {})",
+            streaming_config.backend, streaming_config.url, streaming_config.subject_prefix
+        );
+    }
+    if let Some(dead_man_switch_config) = &config.dead_man_switch {
+        info!(
+            "💓 Heartbeat externo ao fim de cada ciclo: {}",
+            dead_man_switch_config.url
+        );
+    }
+    // Janelas de manutenção ativas ou futuras: suprimem as notificações
+    let maintenance: Vec<types::MaintenanceWindow> = storage
+        .list_maintenance_windows(Some(chrono::Utc::now()))
+        .await
+        .context("Falha ao consultar janelas de manutenção")?;
+    if !maintenance.is_empty() {
+        info!(
+            "🛠️  Janelas de manutenção ativas ou futuras: {}",
+            maintenance.len()
+        );
+    }
+    // Fluxo ao vivo (`/ws/live`), só com a API completa
+    let live: Arc<live::LiveFeed> = Arc::new(live::LiveFeed::new());
+    let live_enabled = config
+        .api
+        .as_ref()
+        .is_some_and(|api| api.mode == config::ApiMode::Full);
+    // Sinks compartilhados pelos grupos (os rótulos vão em cada envio)
+    let mut sinks: Vec<Arc<dyn sink::MetricSink>> = Vec::new();
+    if let Some(influx_config) = &config.influx {
+        sinks.push(Arc::new(influx::InfluxSink::new(influx_config)?));
+    }
+    if let Some(remote_write_config) = &config.remote_write {
+        sinks.push(Arc::new(remote_write::RemoteWriteSink::new(
+            remote_write_config,
+        )?));
+    }
+    if let Some(streaming_config) = &config.streaming {
+        sinks.push(Arc::new(streaming::EventStream::new(streaming_config)?));
+    }
+    for sink_config in &config.sinks {
+        if cli.tui && sink_config.kind == config::SinkKind::Stdout {
+            // A saída padrão pertence ao dashboard
+            warn!(
+                "Sink {} ignorado: stdout não é usado com --tui",
+                sink_config.name()
+            );
+            continue;
+        }
+        sinks.push(sink::from_config(sink_config)?);
+    }
+    if !sinks.is_empty() {
+        info!(
+            "📤 Sinks: {}",
+            sinks
+                .iter()
+                .map(|s| s.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let mut group_exporters: Vec<Arc<exporters::Exporters>> = Vec::new();
+    for group in &groups {
+        let mut exporters = exporters::Exporters {
+            sinks: sinks.clone(),
+            ..Default::default()
+        };
+        if let Some(notifications_config) = &group.config.notifications {
+            info!(
+                "🔔 Notificações{} habilitadas: {} rota(s)",
+                group
+                    .name
+                    .as_ref()
+                    .map_or(String::new(), |name| format!(" [{}]", name)),
+                notifications_config.routes.len()
+            );
+            exporters.notifier = Some(notify::Notifier::new(
+                notifications_config,
+                config.report_zone(),
+            )?);
+        }
+        if let Some(dead_man_switch_config) = &config.dead_man_switch {
+            exporters.dead_man_switch = Some(deadman::DeadManSwitch::new(dead_man_switch_config)?);
+        }
+        exporters.set_targets(&group.targets, pseudonymizer.as_ref());
+        exporters.maintenance = maintenance.clone();
+        if live_enabled {
+            exporters.live = Some(Arc::clone(&live));
+        }
+        let exporters = Arc::new(exporters);
+        exporters.start_digests();
+        if let Some(report_config) = &config.periodic_report
+            && exporters.notifier.is_some()
+        {
+            let report_zone =
+                calendar::Zone::or(report_config.utc_offset.as_deref(), config.report_zone());
+            info!(
+                "📰 Resumo periódico{} habilitado ({}, às {}h {})",
+                group
+                    .name
+                    .as_ref()
+                    .map_or(String::new(), |name| format!(" [{}]", name)),
+                report_config.period.as_str(),
+                report_config.hour,
+                report_zone
+            );
+            task::spawn(periodic_report::run(
+                Arc::clone(&reads),
+                report_config.clone(),
+                Arc::clone(&exporters),
+                group.targets.iter().map(|t| t.id).collect(),
+                group.name.clone(),
+                report_zone,
+            ));
+        }
+        group_exporters.push(exporters);
+    }
+
+    // Modo frota (opt-in)
+    if let Some(fleet_config) = config.fleet.clone() {
+        let reporter = fleet::FleetReporter::new(fleet_config, Arc::clone(&storage))?;
+        task::spawn(reporter.run());
+    }
+
+    let tui_names = tui::Names {
+        targets: targets.iter().map(|t| (t.id, t.name.clone())).collect(),
+        probes: probes.iter().map(|p| (p.id, p.location.clone())).collect(),
+    };
+
+    // Checks por target, escolhidos pelo campo `type`
+    let checks = Arc::new(check::CheckRegistry::new(&config).context("Falha ao registrar checks")?);
+    checks.log_dispatch(&targets);
+    checks.limiter().log_budget(
+        &targets,
+        |target| checks.packets_for(target) * probes.len(),
+        Duration::from_secs(config.cycle_interval_secs)
+            .mul_f64(config.cycle_budget_percent / 100.0),
+    );
+
+    // Outages deixados abertos por uma execução anterior: retomados,
+    // incorporados ou encerrados antes do primeiro ciclo
+    for (index, builder) in builders.iter_mut() {
+        let probe = builder.probe().clone();
+        // Com tenants, cada grupo só reconcilia os eventos dos seus targets
+        let scope = (!config.tenants.is_empty()).then(|| groups[*index].targets.clone());
+        let consensus = builder.consensus_mut();
+        match reconcile::resume_open_outages(
+            &storage,
+            &config,
+            &probe,
+            consensus,
+            &snapshot,
+            scope.as_deref(),
+        )
+        .await
+        {
+            Ok(r) if r.resumed + r.merged + r.closed > 0 => info!(
+                "♻️  Probe {}: eventos abertos reconciliados ({} retomado(s), {} incorporado(s), {} encerrado(s))",
+                probe.location, r.resumed, r.merged, r.closed
+            ),
+            Ok(_) => {}
+            Err(e) => warn!(
+                "[RECONCILE {}] Falha ao reconciliar outages abertos: {:?}",
+                probe.location, e
+            ),
+        }
+    }
+
+    // Spawn de schedulers para cada probe
+    let mut handles: Vec<SchedulerHandle> = Vec::new();
+    let mut profile_checks: HashMap<ProfileChecksKey, Arc<check::CheckRegistry>> = HashMap::new();
+    let mut on_demand = on_demand::OnDemandChecks::default();
+    let mut demand_checks: HashMap<ProfileChecksKey, Arc<check::CheckRegistry>> = HashMap::new();
+    let on_demand_enabled = config
+        .api
+        .as_ref()
+        .is_some_and(|api| api.mode == config::ApiMode::Full);
+    for (index, builder) in builders {
+        let probe = builder.probe();
         info!(
             "🟢 Spawnando scheduler para probe: {} ({})",
             probe.location,
@@ -84,33 +744,124 @@ async fn main() -> Result<()> {
                 .map_or("N/A".to_string(), |ip| ip.to_string())
         );
 
-        let consensus_state: Arc<Mutex<ConsensusState>> = consensus_state.clone();
+        // Perfis com tentativas, timeout ou origem próprios têm checks
+        // próprios, com o mesmo limite de pacotes
+        let probe_config = Arc::clone(builder.config());
+        if probe_config.checks.source != config.checks.source {
+            info!(
+                "🔌 Probe {}: checks com origem {}",
+                probe.location,
+                probe_config.checks.source.label()
+            );
+        }
+        let checks_key = |c: &config::Config| {
+            (
+                c.ping_count,
+                c.timeout_secs,
+                c.checks.source.clone(),
+                c.checks.sources.clone(),
+            )
+        };
+        let key = checks_key(&probe_config);
+        let probe_checks = if key == checks_key(&config) {
+            Arc::clone(&checks)
+        } else if let Some(registry) = profile_checks.get(&key) {
+            Arc::clone(registry)
+        } else {
+            let registry = Arc::new(
+                check::CheckRegistry::with_limiter(&probe_config, Arc::clone(checks.limiter()))
+                    .context("Falha ao registrar checks do perfil do probe")?,
+            );
+            profile_checks.insert(key.clone(), Arc::clone(&registry));
+            registry
+        };
+        // Checks sob demanda da API, com clients próprios: um ping pedido
+        // durante o ciclo não colide com o do scheduler (mesmo identificador
+        // ICMP), e o limite de pacotes segue compartilhado
+        if on_demand_enabled {
+            let registry = match demand_checks.get(&key) {
+                Some(registry) => Arc::clone(registry),
+                None => {
+                    let registry = Arc::new(
+                        check::CheckRegistry::with_limiter(
+                            &probe_config,
+                            Arc::clone(checks.limiter()),
+                        )
+                        .context("Falha ao registrar checks sob demanda")?,
+                    );
+                    demand_checks.insert(key, Arc::clone(&registry));
+                    registry
+                }
+            };
+            on_demand.add(
+                probe.clone(),
+                builder.targets().to_vec(),
+                registry,
+                Duration::from_secs(probe_config.cycle_interval_secs)
+                    .mul_f64(probe_config.cycle_budget_percent / 100.0),
+            );
+        }
 
-        let handle = task::spawn(async move {
-            scheduler::run_scheduler(probe, targets, config, storage, consensus_state).await;
-        });
+        // O supervisor recria o scheduler se ele morrer ou travar
+        let handle = builder
+            .storage(Arc::clone(&backend))
+            .persistence(persistence.clone())
+            .shadow_state(Arc::clone(&shadow_states[index]))
+            .exporters(Arc::clone(&group_exporters[index]))
+            .snapshot(Arc::clone(&snapshot))
+            .checks(probe_checks)
+            .start()?;
         handles.push(handle);
     }
 
+    // API HTTP (opcional)
+    if let Some(api_config) = config.api.clone() {
+        let state = api::ApiState {
+            config: api_config,
+            storage: Arc::clone(&storage),
+            reads: Arc::clone(&reads),
+            snapshot: Arc::clone(&snapshot),
+            templates: Arc::clone(&templates),
+            live: Arc::clone(&live),
+            on_demand: Arc::new(on_demand),
+        };
+        task::spawn(async move {
+            if let Err(e) = api::serve(state).await {
+                error!("❌ API HTTP encerrada: {:?}", e);
+            }
+        });
+    }
+
+    // Modo dashboard: a aplicação termina quando o operador sai do TUI
+    if cli.tui {
+        let snapshot = Arc::clone(&snapshot);
+        task::spawn_blocking(move || tui::run(snapshot, tui_names))
+            .await
+            .context("Falha na thread do dashboard")??;
+        info!("👋 Dashboard encerrado pelo operador.");
+        return Ok(());
+    }
+
     // Pattern matching idiomático para tratar panics e erros via JoinHandle
     let mut panic_count = 0;
     let mut error_count = 0;
 
-    for (index, handle) in handles.into_iter().enumerate() {
-        match handle.await {
+    for handle in handles {
+        let location = handle.probe().location.clone();
+        match handle.join().await {
             Ok(_) => {
-                info!("✅ Scheduler {} finalizado com sucesso", index + 1);
+                info!("✅ Scheduler {} finalizado com sucesso", location);
             }
             Err(join_err) if join_err.is_panic() => {
                 panic_count += 1;
-                error!("💥 Task {} panicked: {:?}", index + 1, join_err);
+                error!("💥 Task {} panicked: {:?}", location, join_err);
             }
             Err(join_err) if join_err.is_cancelled() => {
-                warn!("🚫 Task {} foi cancelada: {:?}", index + 1, join_err);
+                warn!("🚫 Task {} foi cancelada: {:?}", location, join_err);
             }
             Err(join_err) => {
                 error_count += 1;
-                error!("❌ Scheduler {} error: {:?}", index + 1, join_err);
+                error!("❌ Scheduler {} error: {:?}", location, join_err);
             }
         }
     }
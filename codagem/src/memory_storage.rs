@@ -0,0 +1,378 @@
+//! memory_storage.rs — Backend de armazenamento em memória
+//!
+//! `MemoryStorage` implementa `StorageBackend` sem banco: ciclos, métricas,
+//! outages e status ficam em memória, com a semântica que o scheduler e o
+//! pipeline de persistência esperam do PostgreSQL (ids gerados, abertura e
+//! encerramento idempotentes de outages, último status por target e probe).
+//! Com ele o loop roda de ponta a ponta (ciclo → métricas → consenso →
+//! outages) num CI sem container do PostgreSQL, como em `soak --memory` e
+//! nos testes do scheduler. As demais tabelas (decisões, amostras,
+//! traceroutes...) guardam as linhas como foram gravadas.
+
+use crate::baseline::AnomalyEvent;
+use crate::check::RawSample;
+use crate::decisions::ConsensusDecision;
+use crate::host::HostSample;
+use crate::storage::StorageBackend;
+use crate::supervisor::SchedulerIncident;
+use crate::traceroute::Traceroute;
+use crate::types::{
+    ConnectivityMetric, Cycle, CycleSummary, MetricKey, MetricStatus, OutageEvent, TargetStatus,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Debug, Default)]
+struct Tables {
+    cycles: Vec<Cycle>,
+    metrics: Vec<ConnectivityMetric>,
+    outages: Vec<OutageEvent>,
+    /// Transições gravadas, na ordem; o status atual de cada target num
+    /// probe (`target_status`) é a última do par.
+    transitions: Vec<Transition>,
+    cycle_summaries: Vec<CycleSummary>,
+    raw_samples: Vec<RawSample>,
+    host_samples: Vec<HostSample>,
+    anomalies: Vec<AnomalyEvent>,
+    traceroutes: Vec<Traceroute>,
+    incidents: Vec<SchedulerIncident>,
+    decisions: Vec<ConsensusDecision>,
+    /// Eventos dos cenários sombra: (nome do cenário, evento).
+    shadow_outages: Vec<(String, OutageEvent)>,
+    /// Targets pausados (`enabled = false`).
+    disabled: HashSet<i32>,
+}
+
+/// Linha gravada em `transitions`.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub target_id: i32,
+    pub probe_id: Option<i32>,
+    pub status: MetricStatus,
+    pub at: DateTime<Utc>,
+    /// Métrica que causou a transição; só os testes a consultam.
+    #[cfg(test)]
+    pub metric_id: Option<i64>,
+}
+
+/// Mesmo evento de outage: tipo, início, targets e probes.
+fn same_event(a: &OutageEvent, b: &OutageEvent) -> bool {
+    a.event_type == b.event_type
+        && a.start_time == b.start_time
+        && a.affected_targets == b.affected_targets
+        && a.affected_probes == b.affected_probes
+}
+
+/// Banco em memória (ver o módulo).
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Linhas gravadas por tabela.
+    pub fn row_counts(&self) -> BTreeMap<&'static str, u64> {
+        let tables = self.tables();
+        [
+            ("monitoring_cycles", tables.cycles.len()),
+            ("cycle_summaries", tables.cycle_summaries.len()),
+            ("connectivity_metrics", tables.metrics.len()),
+            ("raw_samples", tables.raw_samples.len()),
+            ("probe_host_samples", tables.host_samples.len()),
+            ("anomaly_events", tables.anomalies.len()),
+            ("traceroutes", tables.traceroutes.len()),
+            ("scheduler_incidents", tables.incidents.len()),
+            ("consensus_decisions", tables.decisions.len()),
+            ("outage_events", tables.outages.len()),
+            ("shadow_outage_events", tables.shadow_outages.len()),
+            ("transitions", tables.transitions.len()),
+        ]
+        .into_iter()
+        .filter(|&(_, rows)| rows > 0)
+        .map(|(table, rows)| (table, rows as u64))
+        .collect()
+    }
+
+    #[cfg(test)]
+    pub fn cycles(&self) -> Vec<Cycle> {
+        self.tables().cycles.clone()
+    }
+
+    #[cfg(test)]
+    pub fn metrics(&self) -> Vec<ConnectivityMetric> {
+        self.tables().metrics.clone()
+    }
+
+    #[cfg(test)]
+    pub fn outages(&self) -> Vec<OutageEvent> {
+        self.tables().outages.clone()
+    }
+
+    /// Pausa (`enabled = false`) ou retoma um target.
+    #[cfg(test)]
+    pub fn set_enabled(&self, target_id: i32, enabled: bool) {
+        let disabled = &mut self.tables().disabled;
+        if enabled {
+            disabled.remove(&target_id);
+        } else {
+            disabled.insert(target_id);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn decisions(&self) -> Vec<ConsensusDecision> {
+        self.tables().decisions.clone()
+    }
+
+    #[cfg(test)]
+    pub fn cycle_summaries(&self) -> Vec<CycleSummary> {
+        self.tables().cycle_summaries.clone()
+    }
+
+    #[cfg(test)]
+    pub fn transitions(&self) -> Vec<Transition> {
+        self.tables().transitions.clone()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn insert_cycle(&self, cycle: &Cycle) -> Result<i64> {
+        let mut tables = self.tables();
+        let id = tables.cycles.len() as i64 + 1;
+        tables.cycles.push(Cycle {
+            id,
+            ..cycle.clone()
+        });
+        Ok(id)
+    }
+
+    async fn insert_cycle_summary(&self, summary: &CycleSummary) -> Result<()> {
+        self.tables().cycle_summaries.push(summary.clone());
+        Ok(())
+    }
+
+    async fn insert_connectivity_metric(&self, metric: &ConnectivityMetric) -> Result<Option<i64>> {
+        self.insert_connectivity_metrics(std::slice::from_ref(metric))
+            .await
+            .map(|inserted| inserted.first().map(|&(_, id)| id))
+    }
+
+    async fn insert_connectivity_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+    ) -> Result<Vec<(MetricKey, i64)>> {
+        let mut tables = self.tables();
+        let mut inserted = Vec::with_capacity(metrics.len());
+        for metric in metrics {
+            let id = tables.metrics.len() as i64 + 1;
+            tables.metrics.push(ConnectivityMetric {
+                id,
+                ..metric.clone()
+            });
+            inserted.push((MetricKey::of(metric), id));
+        }
+        Ok(inserted)
+    }
+
+    async fn insert_raw_samples(&self, samples: &[RawSample]) -> Result<u64> {
+        self.tables().raw_samples.extend_from_slice(samples);
+        Ok(samples.len() as u64)
+    }
+
+    async fn insert_host_sample(&self, sample: &HostSample) -> Result<()> {
+        self.tables().host_samples.push(sample.clone());
+        Ok(())
+    }
+
+    async fn insert_anomaly_event(&self, event: &AnomalyEvent) -> Result<()> {
+        self.tables().anomalies.push(event.clone());
+        Ok(())
+    }
+
+    async fn insert_traceroute(&self, traceroute: &Traceroute) -> Result<()> {
+        self.tables().traceroutes.push(traceroute.clone());
+        Ok(())
+    }
+
+    async fn insert_scheduler_incident(&self, incident: &SchedulerIncident) -> Result<()> {
+        self.tables().incidents.push(incident.clone());
+        Ok(())
+    }
+
+    async fn insert_consensus_decision(&self, decision: &ConsensusDecision) -> Result<()> {
+        self.tables().decisions.push(decision.clone());
+        Ok(())
+    }
+
+    /// Como no PostgreSQL: a abertura é idempotente e o encerramento fecha a
+    /// linha de `event.id` ou a aberta do mesmo evento.
+    async fn insert_outage_event(&self, event: &OutageEvent) -> Result<i64> {
+        let mut tables = self.tables();
+        let index = if event.end_time.is_some() {
+            tables
+                .outages
+                .iter()
+                .position(|o| event.id > 0 && o.id == event.id)
+                .or_else(|| {
+                    tables
+                        .outages
+                        .iter()
+                        .position(|o| o.end_time.is_none() && same_event(o, event))
+                })
+        } else {
+            tables
+                .outages
+                .iter()
+                .position(|o| o.end_time.is_none() && same_event(o, event))
+        };
+        match index {
+            Some(index) if event.end_time.is_some() => {
+                let row = &mut tables.outages[index];
+                row.end_time = event.end_time;
+                row.duration_seconds = event.duration_seconds;
+                row.reason = event.reason.clone();
+                row.consensus_level = event.consensus_level;
+                row.details = event.details.clone();
+                Ok(row.id)
+            }
+            Some(index) => Ok(tables.outages[index].id),
+            None => {
+                let id = tables.outages.len() as i64 + 1;
+                tables.outages.push(OutageEvent {
+                    id,
+                    ..event.clone()
+                });
+                Ok(id)
+            }
+        }
+    }
+
+    async fn insert_shadow_outage_event(
+        &self,
+        shadow_name: &str,
+        event: &OutageEvent,
+    ) -> Result<()> {
+        self.tables()
+            .shadow_outages
+            .push((shadow_name.to_string(), event.clone()));
+        Ok(())
+    }
+
+    async fn set_target_status(
+        &self,
+        target_id: i32,
+        status: &MetricStatus,
+        _old_status: Option<&MetricStatus>,
+        probe_id: Option<i32>,
+        at: Option<DateTime<Utc>>,
+        metric_id: Option<i64>,
+    ) -> Result<()> {
+        self.tables().transitions.push(Transition {
+            target_id,
+            probe_id,
+            status: status.clone(),
+            at: at.unwrap_or_else(Utc::now),
+            #[cfg(test)]
+            metric_id,
+        });
+        #[cfg(not(test))]
+        let _ = metric_id;
+        Ok(())
+    }
+
+    async fn list_latest_transitions(&self) -> Result<HashMap<(i32, Option<i32>), MetricStatus>> {
+        Ok(self
+            .tables()
+            .transitions
+            .iter()
+            .map(|t| ((t.target_id, t.probe_id), t.status.clone()))
+            .collect())
+    }
+
+    async fn list_all_target_status(&self) -> Result<Vec<TargetStatus>> {
+        let latest: BTreeMap<(i32, Option<i32>), TargetStatus> = self
+            .tables()
+            .transitions
+            .iter()
+            .map(|t| {
+                let status = TargetStatus {
+                    target_id: t.target_id,
+                    probe_id: t.probe_id,
+                    last_status: t.status.clone(),
+                    last_change: t.at,
+                };
+                ((t.target_id, t.probe_id), status)
+            })
+            .collect();
+        Ok(latest.into_values().collect())
+    }
+
+    async fn list_recent_metrics_by_probe(
+        &self,
+        probe_id: i32,
+        since: DateTime<Utc>,
+        per_target: i64,
+    ) -> Result<Vec<ConnectivityMetric>> {
+        let tables = self.tables();
+        let mut by_target: BTreeMap<i32, Vec<&ConnectivityMetric>> = BTreeMap::new();
+        for metric in &tables.metrics {
+            let diagnostic = metric
+                .details
+                .as_ref()
+                .is_some_and(|d| d.get("diagnostic").is_some());
+            if metric.probe_id == probe_id && metric.timestamp >= since && !diagnostic {
+                by_target.entry(metric.target_id).or_default().push(metric);
+            }
+        }
+        Ok(by_target
+            .into_values()
+            .flat_map(|mut metrics| {
+                metrics.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+                metrics.truncate(per_target.max(0) as usize);
+                metrics.into_iter().cloned()
+            })
+            .collect())
+    }
+
+    async fn list_cycle_durations_ms(&self, probe_id: i32) -> Result<Vec<f64>> {
+        let tables = self.tables();
+        let mut last_metric: BTreeMap<i64, DateTime<Utc>> = BTreeMap::new();
+        for metric in tables.metrics.iter().filter(|m| m.probe_id == probe_id) {
+            let last = last_metric
+                .entry(metric.cycle_id)
+                .or_insert(metric.timestamp);
+            *last = (*last).max(metric.timestamp);
+        }
+        Ok(last_metric
+            .into_iter()
+            .filter_map(|(cycle_id, last)| {
+                let cycle = tables.cycles.get((cycle_id - 1).max(0) as usize)?;
+                (cycle.id == cycle_id).then(|| (last - cycle.started_at).num_milliseconds() as f64)
+            })
+            .collect())
+    }
+
+    async fn list_disabled_target_ids(&self) -> Result<HashSet<i32>> {
+        Ok(self.tables().disabled.clone())
+    }
+}
@@ -0,0 +1,772 @@
+//! migrations.rs — Migrações versionadas do schema
+//!
+//! O schema base é criado por `docker/postgres/init.sql`. Tudo o que vem depois
+//! é aplicado aqui, na inicialização, em ordem de versão e uma única vez
+//! (controle em `schema_migrations`). Cada migração roda numa transação junto
+//! com o registro da sua versão. Limpezas grandes demais para uma transação só
+//! rodam antes dela, em passos confirmados um a um (ver [`prepare`]).
+//!
+//! O modo TimescaleDB (`[timescale]`) não é uma migração versionada: é
+//! aplicado depois delas, a cada subida, e é idempotente. Assim um banco pode
+//! passar a usá-lo (ou mudar os intervalos) sem divergir da lista abaixo.
+
+use crate::config::TimescaleConfig;
+use anyhow::{Context, Result};
+use tokio_postgres::Client;
+use tracing::info;
+
+/// Migração embutida no binário.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Lista ordenada de migrações. Nunca altere uma migração já publicada;
+/// crie uma nova versão.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "aggregated_views",
+        sql: r#"
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_hourly_availability AS
+SELECT target_id,
+       date_trunc('hour', timestamp) AS hour,
+       COUNT(*) AS samples,
+       COUNT(*) FILTER (WHERE status = 'up') AS up_samples,
+       (100.0 * COUNT(*) FILTER (WHERE status = 'up') / COUNT(*))::DOUBLE PRECISION AS availability_percent
+FROM connectivity_metrics
+GROUP BY target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_hourly_availability ON mv_hourly_availability (target_id, hour);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_daily_outage_minutes AS
+SELECT date_trunc('day', start_time) AS day,
+       COUNT(*) AS outage_count,
+       (COALESCE(SUM(duration_seconds), 0) / 60.0)::DOUBLE PRECISION AS outage_minutes
+FROM outage_events
+WHERE end_time IS NOT NULL
+GROUP BY date_trunc('day', start_time);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_daily_outage_minutes ON mv_daily_outage_minutes (day);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_hourly_latency AS
+SELECT target_id,
+       date_trunc('hour', timestamp) AS hour,
+       AVG(response_time_ms) AS avg_ms,
+       percentile_cont(0.5) WITHIN GROUP (ORDER BY response_time_ms) AS p50_ms,
+       percentile_cont(0.95) WITHIN GROUP (ORDER BY response_time_ms) AS p95_ms,
+       percentile_cont(0.99) WITHIN GROUP (ORDER BY response_time_ms) AS p99_ms
+FROM connectivity_metrics
+WHERE response_time_ms IS NOT NULL
+GROUP BY target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_hourly_latency ON mv_hourly_latency (target_id, hour);
+"#,
+    },
+    Migration {
+        version: 2,
+        name: "connectivity_metric_details",
+        sql: r#"
+ALTER TABLE connectivity_metrics ADD COLUMN IF NOT EXISTS details JSONB;
+
+-- Medições de diagnóstico (ex: assimetria TCP) não entram nos agregados de disponibilidade
+DROP MATERIALIZED VIEW IF EXISTS mv_hourly_availability;
+CREATE MATERIALIZED VIEW mv_hourly_availability AS
+SELECT target_id,
+       date_trunc('hour', timestamp) AS hour,
+       COUNT(*) AS samples,
+       COUNT(*) FILTER (WHERE status = 'up') AS up_samples,
+       (100.0 * COUNT(*) FILTER (WHERE status = 'up') / COUNT(*))::DOUBLE PRECISION AS availability_percent
+FROM connectivity_metrics
+WHERE details IS NULL OR NOT details ? 'diagnostic'
+GROUP BY target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX ux_mv_hourly_availability ON mv_hourly_availability (target_id, hour);
+
+DROP MATERIALIZED VIEW IF EXISTS mv_hourly_latency;
+CREATE MATERIALIZED VIEW mv_hourly_latency AS
+SELECT target_id,
+       date_trunc('hour', timestamp) AS hour,
+       AVG(response_time_ms) AS avg_ms,
+       percentile_cont(0.5) WITHIN GROUP (ORDER BY response_time_ms) AS p50_ms,
+       percentile_cont(0.95) WITHIN GROUP (ORDER BY response_time_ms) AS p95_ms,
+       percentile_cont(0.99) WITHIN GROUP (ORDER BY response_time_ms) AS p99_ms
+FROM connectivity_metrics
+WHERE response_time_ms IS NOT NULL
+  AND (details IS NULL OR NOT details ? 'diagnostic')
+GROUP BY target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX ux_mv_hourly_latency ON mv_hourly_latency (target_id, hour);
+"#,
+    },
+    Migration {
+        version: 3,
+        name: "target_templates",
+        sql: r#"
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS template TEXT;
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS check_interval_secs INTEGER;
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS fail_threshold INTEGER;
+"#,
+    },
+    Migration {
+        version: 4,
+        name: "probe_host_samples",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS probe_host_samples (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    timestamp TIMESTAMPTZ NOT NULL,
+    load1 DOUBLE PRECISION,
+    cpu_count INTEGER NOT NULL,
+    mem_available_percent DOUBLE PRECISION,
+    mem_pressure_avg10 DOUBLE PRECISION,
+    disk_free_percent DOUBLE PRECISION,
+    fd_open BIGINT,
+    fd_limit BIGINT,
+    starved TEXT[] NOT NULL DEFAULT '{}'
+);
+CREATE INDEX IF NOT EXISTS ix_probe_host_samples_probe_time ON probe_host_samples (probe_id, timestamp);
+"#,
+    },
+    Migration {
+        version: 5,
+        name: "anomaly_events",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS anomaly_events (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id),
+    kind TEXT NOT NULL,
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ,
+    baseline_mean DOUBLE PRECISION NOT NULL,
+    baseline_stddev DOUBLE PRECISION NOT NULL,
+    peak_value DOUBLE PRECISION NOT NULL,
+    peak_sigmas DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_anomaly_events_target_time ON anomaly_events (target_id, start_time);
+"#,
+    },
+    Migration {
+        version: 6,
+        name: "cycle_degraded_conditions",
+        sql: r#"
+ALTER TABLE monitoring_cycles ADD COLUMN IF NOT EXISTS degraded_conditions BOOLEAN NOT NULL DEFAULT FALSE;
+"#,
+    },
+    Migration {
+        version: 7,
+        name: "metric_provenance",
+        sql: r#"
+ALTER TABLE connectivity_metrics
+    ADD COLUMN IF NOT EXISTS backend TEXT,
+    ADD COLUMN IF NOT EXISTS source_address INET,
+    ADD COLUMN IF NOT EXISTS interface TEXT,
+    ADD COLUMN IF NOT EXISTS probe_version TEXT;
+"#,
+    },
+    Migration {
+        version: 8,
+        name: "degradation_thresholds",
+        sql: r#"
+ALTER TABLE monitoring_targets
+    ADD COLUMN IF NOT EXISTS degraded_rtt_ms DOUBLE PRECISION,
+    ADD COLUMN IF NOT EXISTS degraded_loss_percent SMALLINT;
+ALTER TABLE outage_events ADD COLUMN IF NOT EXISTS event_type TEXT NOT NULL DEFAULT 'outage';
+
+-- Minutos de outage por dia não contam degradações
+DROP MATERIALIZED VIEW IF EXISTS mv_daily_outage_minutes;
+CREATE MATERIALIZED VIEW mv_daily_outage_minutes AS
+SELECT date_trunc('day', start_time) AS day,
+       COUNT(*) AS outage_count,
+       (COALESCE(SUM(duration_seconds), 0) / 60.0)::DOUBLE PRECISION AS outage_minutes
+FROM outage_events
+WHERE end_time IS NOT NULL AND event_type = 'outage'
+GROUP BY date_trunc('day', start_time);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_daily_outage_minutes ON mv_daily_outage_minutes (day);
+"#,
+    },
+    Migration {
+        version: 9,
+        name: "scheduler_incidents",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS scheduler_incidents (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    timestamp TIMESTAMPTZ NOT NULL,
+    kind TEXT NOT NULL,
+    detail TEXT,
+    restarts INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_scheduler_incidents_probe_time ON scheduler_incidents (probe_id, timestamp);
+"#,
+    },
+    Migration {
+        version: 10,
+        name: "consensus_decisions",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS consensus_decisions (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    cycle_id BIGINT,
+    timestamp TIMESTAMPTZ NOT NULL,
+    outcome TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    history_len INTEGER NOT NULL,
+    fail_threshold INTEGER NOT NULL,
+    consensus INTEGER NOT NULL,
+    failed_targets INTEGER[] NOT NULL,
+    counted_targets INTEGER[] NOT NULL,
+    details JSONB
+);
+CREATE INDEX IF NOT EXISTS ix_consensus_decisions_time ON consensus_decisions (timestamp);
+CREATE INDEX IF NOT EXISTS ix_consensus_decisions_probe_time ON consensus_decisions (probe_id, timestamp);
+"#,
+    },
+    Migration {
+        version: 11,
+        name: "tls_metric_type",
+        sql: r#"
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'tls_ipv4';
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'tls_ipv6';
+"#,
+    },
+    Migration {
+        version: 12,
+        name: "traceroutes",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS traceroutes (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id),
+    cycle_id BIGINT,
+    timestamp TIMESTAMPTZ NOT NULL,
+    destination INET NOT NULL,
+    reached BOOLEAN NOT NULL,
+    hops JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_traceroutes_time ON traceroutes (timestamp);
+CREATE INDEX IF NOT EXISTS ix_traceroutes_target_time ON traceroutes (target_id, timestamp);
+"#,
+    },
+    Migration {
+        version: 13,
+        name: "raw_samples",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS raw_samples (
+    id BIGSERIAL PRIMARY KEY,
+    cycle_id BIGINT,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id),
+    metric_type metric_type NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    attempt_no SMALLINT NOT NULL,
+    rtt_ms DOUBLE PRECISION,
+    outcome TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_raw_samples_time ON raw_samples (timestamp);
+CREATE INDEX IF NOT EXISTS ix_raw_samples_target_time ON raw_samples (target_id, timestamp);
+CREATE INDEX IF NOT EXISTS ix_raw_samples_cycle ON raw_samples (cycle_id);
+"#,
+    },
+    Migration {
+        version: 14,
+        name: "outage_affected_asns",
+        sql: r#"
+ALTER TABLE outage_events ADD COLUMN IF NOT EXISTS affected_asns INTEGER[];
+UPDATE outage_events e
+SET affected_asns = (
+    SELECT array_agg(DISTINCT t.asn ORDER BY t.asn) FROM monitoring_targets t
+    WHERE t.id = ANY(e.affected_targets) AND t.asn IS NOT NULL
+)
+WHERE affected_asns IS NULL;
+CREATE INDEX IF NOT EXISTS ix_outage_events_asns ON outage_events USING GIN (affected_asns);
+"#,
+    },
+    Migration {
+        version: 15,
+        name: "geoip",
+        sql: r#"
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS country_code TEXT;
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS city TEXT;
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION;
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION;
+ALTER TABLE monitoring_probes ADD COLUMN IF NOT EXISTS country_code TEXT;
+ALTER TABLE monitoring_probes ADD COLUMN IF NOT EXISTS city TEXT;
+ALTER TABLE monitoring_probes ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION;
+ALTER TABLE monitoring_probes ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION;
+CREATE INDEX IF NOT EXISTS ix_monitoring_targets_country ON monitoring_targets (country_code);
+"#,
+    },
+    Migration {
+        version: 16,
+        name: "target_tenant",
+        sql: r#"
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS tenant TEXT;
+CREATE INDEX IF NOT EXISTS ix_monitoring_targets_tenant ON monitoring_targets (tenant);
+"#,
+    },
+    Migration {
+        version: 17,
+        name: "transitions",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS transitions (
+    id BIGSERIAL PRIMARY KEY,
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id),
+    probe_id INTEGER REFERENCES monitoring_probes(id),
+    old_status metric_status,
+    new_status metric_status NOT NULL,
+    metric_id BIGINT,
+    changed_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_transitions_target_time ON transitions (target_id, changed_at DESC);
+"#,
+    },
+    Migration {
+        version: 18,
+        name: "metric_error_kind",
+        sql: r#"
+ALTER TABLE connectivity_metrics ADD COLUMN IF NOT EXISTS error_kind TEXT;
+"#,
+    },
+    Migration {
+        version: 19,
+        name: "cycle_summaries",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS cycle_summaries (
+    id BIGSERIAL PRIMARY KEY,
+    cycle_id BIGINT,
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    started_at TIMESTAMPTZ NOT NULL,
+    targets_up INTEGER NOT NULL,
+    targets_degraded INTEGER NOT NULL,
+    targets_down INTEGER NOT NULL,
+    avg_rtt_ms DOUBLE PRECISION,
+    loss_avg_percent DOUBLE PRECISION,
+    duration_ms DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ix_cycle_summaries_probe_time ON cycle_summaries (probe_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS ix_cycle_summaries_cycle ON cycle_summaries (cycle_id);
+"#,
+    },
+    Migration {
+        version: 20,
+        name: "target_weight",
+        sql: r#"
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS weight DOUBLE PRECISION;
+"#,
+    },
+    Migration {
+        version: 21,
+        name: "maintenance_windows",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS maintenance_windows (
+    id BIGSERIAL PRIMARY KEY,
+    probe_id INTEGER REFERENCES monitoring_probes(id) ON DELETE CASCADE,
+    target_id INTEGER REFERENCES monitoring_targets(id) ON DELETE CASCADE,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+CREATE INDEX IF NOT EXISTS ix_maintenance_windows_ends ON maintenance_windows (ends_at);
+"#,
+    },
+    Migration {
+        version: 22,
+        name: "latency_histograms",
+        sql: r#"
+-- Contagem horária de RTTs por faixa (`le_ms` = limite superior; Infinity
+-- para o que passa da última faixa), base dos heatmaps de latência
+CREATE TABLE IF NOT EXISTS latency_histograms (
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id),
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id),
+    metric_type metric_type NOT NULL,
+    hour TIMESTAMPTZ NOT NULL,
+    le_ms DOUBLE PRECISION NOT NULL,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (target_id, probe_id, metric_type, hour, le_ms)
+);
+CREATE INDEX IF NOT EXISTS ix_latency_histograms_hour ON latency_histograms (hour);
+"#,
+    },
+    Migration {
+        version: 23,
+        name: "probe_targets",
+        sql: r#"
+-- Targets atribuídos a cada probe; um probe sem linhas monitora todos
+CREATE TABLE IF NOT EXISTS probe_targets (
+    probe_id INTEGER NOT NULL REFERENCES monitoring_probes(id) ON DELETE CASCADE,
+    target_id INTEGER NOT NULL REFERENCES monitoring_targets(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (probe_id, target_id)
+);
+"#,
+    },
+    Migration {
+        version: 24,
+        name: "ping_payload_dscp",
+        sql: r#"
+ALTER TABLE monitoring_targets
+    ADD COLUMN IF NOT EXISTS ping_payload_bytes INTEGER,
+    ADD COLUMN IF NOT EXISTS dscp SMALLINT;
+"#,
+    },
+    Migration {
+        version: 25,
+        name: "udp_metric_type",
+        sql: r#"
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'udp_ipv4';
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'udp_ipv6';
+"#,
+    },
+    Migration {
+        version: 26,
+        name: "sip_metric_type",
+        sql: r#"
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'sip_ipv4';
+ALTER TYPE metric_type ADD VALUE IF NOT EXISTS 'sip_ipv6';
+"#,
+    },
+    Migration {
+        version: 27,
+        name: "device_metrics",
+        sql: r#"
+-- Contadores SNMP das interfaces do CPE/roteador (`[snmp]`); deltas nulos na
+-- primeira leitura e depois de um reinício do dispositivo
+CREATE TABLE IF NOT EXISTS device_metrics (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    device TEXT NOT NULL,
+    address INET NOT NULL,
+    if_index INTEGER NOT NULL,
+    if_descr TEXT,
+    oper_status TEXT,
+    in_errors BIGINT,
+    out_discards BIGINT,
+    in_errors_delta BIGINT,
+    out_discards_delta BIGINT,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS ix_device_metrics_device_timestamp ON device_metrics (device, timestamp);
+CREATE INDEX IF NOT EXISTS ix_device_metrics_timestamp ON device_metrics (timestamp);
+"#,
+    },
+    Migration {
+        version: 28,
+        name: "neighbor_samples",
+        sql: r#"
+-- Consultas ARP/ND aos gateways padrão (`[neighbor]`); `mac_changed` marca
+-- troca do MAC em relação à consulta anterior
+CREATE TABLE IF NOT EXISTS neighbor_samples (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    interface TEXT NOT NULL,
+    gateway INET NOT NULL,
+    reachable BOOLEAN NOT NULL,
+    mac TEXT,
+    rtt_ms DOUBLE PRECISION,
+    previous_mac TEXT,
+    mac_changed BOOLEAN NOT NULL DEFAULT FALSE,
+    conflicting_macs TEXT[],
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS ix_neighbor_samples_timestamp ON neighbor_samples (timestamp);
+CREATE INDEX IF NOT EXISTS ix_neighbor_samples_changed ON neighbor_samples (timestamp) WHERE mac_changed;
+"#,
+    },
+    Migration {
+        version: 29,
+        name: "host_network_events",
+        sql: r#"
+-- Mudanças de interface e de rota padrão do host (`[netwatch]`)
+CREATE TABLE IF NOT EXISTS host_network_events (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp TIMESTAMPTZ NOT NULL,
+    event TEXT NOT NULL,
+    interface TEXT,
+    oper_state TEXT,
+    family TEXT,
+    gateway INET,
+    metric INTEGER
+);
+CREATE INDEX IF NOT EXISTS ix_host_network_events_timestamp ON host_network_events (timestamp);
+"#,
+    },
+    Migration {
+        version: 30,
+        name: "target_enabled",
+        sql: r#"
+-- Targets pausados (`enabled = false`) ficam fora dos ciclos, com o histórico preservado
+ALTER TABLE monitoring_targets ADD COLUMN IF NOT EXISTS enabled BOOLEAN NOT NULL DEFAULT TRUE;
+"#,
+    },
+    Migration {
+        version: 31,
+        name: "connectivity_metrics_dedup",
+        sql: r#"
+-- Chave determinística das métricas: um reenvio (spool, lote regravado) não
+-- duplica amostras. Sem ciclo (banco fora no momento), vale o horário da medição.
+-- `timestamp` fica na chave mesmo com ciclo: com `[timescale]` a tabela é uma
+-- hypertable particionada por `timestamp`, e o TimescaleDB só aceita índices
+-- únicos (e chave primária) que contenham a coluna de partição.
+--
+-- As duplicatas já gravadas são removidas antes, fora desta transação, por
+-- `dedup_connectivity_metrics`.
+CREATE UNIQUE INDEX IF NOT EXISTS ux_connectivity_metrics_dedup
+    ON connectivity_metrics (probe_id, target_id, metric_type, timestamp, (COALESCE(cycle_id, 0)));
+DROP INDEX IF EXISTS ix_connectivity_metrics_dedup_key;
+"#,
+    },
+    Migration {
+        version: 32,
+        name: "hourly_views_by_probe",
+        sql: r#"
+-- Disponibilidade e latência horárias por probe e target: o modo frota agrupa
+-- pelo provedor de acesso (ISP) do probe, que as views por target perdem
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_hourly_probe_availability AS
+SELECT probe_id,
+       target_id,
+       date_trunc('hour', timestamp) AS hour,
+       COUNT(*) AS samples,
+       COUNT(*) FILTER (WHERE status = 'up') AS up_samples,
+       (100.0 * COUNT(*) FILTER (WHERE status = 'up') / COUNT(*))::DOUBLE PRECISION AS availability_percent
+FROM connectivity_metrics
+WHERE details IS NULL OR NOT details ? 'diagnostic'
+GROUP BY probe_id, target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_hourly_probe_availability
+    ON mv_hourly_probe_availability (probe_id, target_id, hour);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_hourly_probe_latency AS
+SELECT probe_id,
+       target_id,
+       date_trunc('hour', timestamp) AS hour,
+       AVG(response_time_ms) AS avg_ms,
+       percentile_cont(0.5) WITHIN GROUP (ORDER BY response_time_ms) AS p50_ms,
+       percentile_cont(0.95) WITHIN GROUP (ORDER BY response_time_ms) AS p95_ms,
+       percentile_cont(0.99) WITHIN GROUP (ORDER BY response_time_ms) AS p99_ms
+FROM connectivity_metrics
+WHERE response_time_ms IS NOT NULL
+  AND (details IS NULL OR NOT details ? 'diagnostic')
+GROUP BY probe_id, target_id, date_trunc('hour', timestamp);
+CREATE UNIQUE INDEX IF NOT EXISTS ux_mv_hourly_probe_latency
+    ON mv_hourly_probe_latency (probe_id, target_id, hour);
+"#,
+    },
+    Migration {
+        version: 33,
+        name: "target_status_by_probe",
+        sql: r#"
+-- Um status por (target, probe): com uma linha só por target, dois probes que
+-- discordam deixavam gravado o último que mudou. probe_id 0 = sem probe
+-- (mensagens antigas do spool e linhas anteriores a esta migração).
+ALTER TABLE target_status ADD COLUMN IF NOT EXISTS probe_id INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE target_status DROP CONSTRAINT IF EXISTS target_status_pkey;
+ALTER TABLE target_status ADD PRIMARY KEY (target_id, probe_id);
+-- O último status de cada probe sai de `transitions`; a linha sem probe só
+-- fica para os targets que não têm nenhuma transição registrada
+INSERT INTO target_status (target_id, probe_id, last_status, last_change)
+SELECT DISTINCT ON (target_id, probe_id) target_id, probe_id, new_status, changed_at
+FROM transitions
+WHERE probe_id IS NOT NULL
+ORDER BY target_id, probe_id, changed_at DESC, id DESC
+ON CONFLICT (target_id, probe_id) DO NOTHING;
+DELETE FROM target_status s
+WHERE s.probe_id = 0
+  AND EXISTS (SELECT 1 FROM target_status p WHERE p.target_id = s.target_id AND p.probe_id <> 0);
+"#,
+    },
+];
+
+/// Faixa de `id`s de cada `DELETE` da limpeza de duplicatas.
+const DEDUP_BATCH_IDS: i64 = 50_000;
+
+/// Passo de uma migração que roda antes da transação dela. Precisa ser
+/// idempotente: se a migração falhar, roda de novo na próxima subida.
+async fn prepare(client: &Client, version: i32) -> Result<()> {
+    match version {
+        31 => dedup_connectivity_metrics(client).await,
+        _ => Ok(()),
+    }
+}
+
+/// Remove as métricas duplicadas (mesma chave do índice único da migração 31),
+/// mantendo a mais antiga. Anda em faixas de `id`, cada `DELETE` confirmado
+/// sozinho: nenhuma transação segura os locks e as versões mortas da tabela
+/// inteira. O índice comum na chave vem antes, para que cada linha ache a sua
+/// cópia mais antiga por ele; a migração o remove depois do índice único.
+async fn dedup_connectivity_metrics(client: &Client) -> Result<()> {
+    client
+        .batch_execute(
+            "CREATE INDEX IF NOT EXISTS ix_connectivity_metrics_dedup_key
+                 ON connectivity_metrics (probe_id, target_id, metric_type, timestamp, (COALESCE(cycle_id, 0)), id)",
+        )
+        .await
+        .context("Falha ao indexar a chave das métricas")?;
+    let bounds = client
+        .query_one("SELECT MIN(id), MAX(id) FROM connectivity_metrics", &[])
+        .await?;
+    let (Some(first), Some(last)): (Option<i64>, Option<i64>) = (bounds.get(0), bounds.get(1))
+    else {
+        return Ok(());
+    };
+    let mut removed = 0;
+    for batch_start in (first..=last).step_by(DEDUP_BATCH_IDS as usize) {
+        removed += client
+            .execute(
+                "DELETE FROM connectivity_metrics m
+                 USING connectivity_metrics d
+                 WHERE m.id >= $1
+                   AND m.id < $2
+                   AND m.probe_id = d.probe_id
+                   AND m.target_id = d.target_id
+                   AND m.metric_type = d.metric_type
+                   AND m.timestamp = d.timestamp
+                   AND COALESCE(m.cycle_id, 0) = COALESCE(d.cycle_id, 0)
+                   AND m.id > d.id",
+                &[&batch_start, &(batch_start + DEDUP_BATCH_IDS)],
+            )
+            .await
+            .context("Falha ao remover métricas duplicadas")?;
+    }
+    if removed > 0 {
+        info!("🧱 {} métrica(s) duplicada(s) removida(s)", removed);
+    }
+    Ok(())
+}
+
+/// Aplica as migrações pendentes e retorna quantas foram aplicadas.
+pub async fn apply(client: &Client) -> Result<usize> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                 version INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+             )",
+        )
+        .await
+        .context("Falha ao criar tabela schema_migrations")?;
+
+    let applied = applied(client).await?;
+
+    let mut count = 0;
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        info!(
+            "🧱 Aplicando migração {:04} ({})",
+            migration.version, migration.name
+        );
+        prepare(client, migration.version).await.with_context(|| {
+            format!(
+                "Falha ao preparar migração {:04} ({})",
+                migration.version, migration.name
+            )
+        })?;
+        let batch = format!(
+            "BEGIN;\n{}\nINSERT INTO schema_migrations (version, name) VALUES ({}, '{}');\nCOMMIT;",
+            migration.sql, migration.version, migration.name
+        );
+        if let Err(e) = client.batch_execute(&batch).await {
+            // Garante que a sessão não fique presa numa transação abortada
+            let _ = client.batch_execute("ROLLBACK").await;
+            return Err(e).with_context(|| {
+                format!(
+                    "Falha ao aplicar migração {:04} ({})",
+                    migration.version, migration.name
+                )
+            });
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Converte `connectivity_metrics` em hypertable (migrando as linhas já
+/// existentes) e aplica os intervalos de chunk e a política de compressão de
+/// `config`. Exige a extensão `timescaledb` disponível no servidor (2.11 ou
+/// mais nova, para o `ON CONFLICT` e as remoções em chunks comprimidos).
+pub async fn apply_timescale(client: &Client, config: &TimescaleConfig) -> Result<()> {
+    client
+        .batch_execute("CREATE EXTENSION IF NOT EXISTS timescaledb")
+        .await
+        .context("Falha ao habilitar a extensão timescaledb")?;
+
+    let chunk_interval = format!("{} hours", config.chunk_interval_hours);
+    client
+        .execute(
+            "SELECT create_hypertable('connectivity_metrics', 'timestamp',
+                 chunk_time_interval => $1::text::interval,
+                 if_not_exists => TRUE,
+                 migrate_data => TRUE)",
+            &[&chunk_interval],
+        )
+        .await
+        .context("Falha ao converter connectivity_metrics em hypertable")?;
+    // Vale para os chunks criados daqui em diante
+    client
+        .execute(
+            "SELECT set_chunk_time_interval('connectivity_metrics', $1::text::interval)",
+            &[&chunk_interval],
+        )
+        .await
+        .context("Falha ao ajustar o intervalo dos chunks")?;
+
+    // A política é recriada para refletir mudanças de compress_after_days
+    client
+        .batch_execute(
+            "SELECT remove_compression_policy('connectivity_metrics', if_exists => TRUE)",
+        )
+        .await
+        .context("Falha ao remover a política de compressão")?;
+    if config.compress_after_days > 0 {
+        // As configurações não mudam depois que há chunks comprimidos
+        client
+            .batch_execute(
+                "DO $$
+                 BEGIN
+                     IF NOT EXISTS (
+                         SELECT 1 FROM timescaledb_information.hypertables
+                         WHERE hypertable_name = 'connectivity_metrics' AND compression_enabled
+                     ) THEN
+                         ALTER TABLE connectivity_metrics SET (
+                             timescaledb.compress,
+                             timescaledb.compress_segmentby = 'probe_id, target_id',
+                             timescaledb.compress_orderby = 'timestamp DESC'
+                         );
+                     END IF;
+                 END $$",
+            )
+            .await
+            .context("Falha ao habilitar a compressão de connectivity_metrics")?;
+        client
+            .execute(
+                "SELECT add_compression_policy('connectivity_metrics', $1::text::interval)",
+                &[&format!("{} days", config.compress_after_days)],
+            )
+            .await
+            .context("Falha ao criar a política de compressão")?;
+    }
+    info!(
+        "🐯 connectivity_metrics como hypertable do TimescaleDB (chunks de {}h)",
+        config.chunk_interval_hours
+    );
+    Ok(())
+}
+
+/// Versões já aplicadas no banco, em ordem (vazio sem `schema_migrations`).
+pub async fn applied(client: &Client) -> Result<Vec<i32>> {
+    let exists: bool = client
+        .query_one("SELECT to_regclass('schema_migrations') IS NOT NULL", &[])
+        .await?
+        .get(0);
+    if !exists {
+        return Ok(Vec::new());
+    }
+    Ok(client
+        .query(
+            "SELECT version FROM schema_migrations ORDER BY version",
+            &[],
+        )
+        .await?
+        .iter()
+        .map(|row| row.get("version"))
+        .collect())
+}
@@ -0,0 +1,506 @@
+//! neighbor.rs — Alcance do gateway local por ARP/ND
+//!
+//! Com `[neighbor]`, uma task pergunta a cada `interval_secs` pelo endereço
+//! de enlace dos gateways padrão do host (`/proc/net/route` e
+//! `/proc/net/ipv6_route`): ARP request em broadcast no IPv4 e Neighbor
+//! Solicitation para o multicast solicited-node no IPv6. A resposta não passa
+//! por roteamento nem por firewall de ICMP, então um gateway que não responde
+//! aqui está fora do segmento local (CPE desligado, reiniciando, cabo/Wi-Fi).
+//!
+//! Cada consulta vira uma linha em `neighbor_samples` com o MAC e o tempo de
+//! resposta. Um MAC diferente do anterior (inclusive o da última linha
+//! gravada antes de um restart) é marcado em `mac_changed`: troca do CPE ou
+//! ARP spoofing. Mais de um MAC respondendo pelo mesmo gateway vai para
+//! `conflicting_macs`. Os dois casos e a perda de alcance geram avisos no log
+//! e costumam preceder um outage de WAN aparente.
+//!
+//! Os sockets são crus (`AF_PACKET` e ICMPv6): exige `CAP_NET_RAW`.
+
+use crate::config::NeighborConfig;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// Gateway padrão e a interface por onde ele é alcançado.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Gateway {
+    pub interface: String,
+    pub address: IpAddr,
+}
+
+/// Linha de `neighbor_samples`: uma consulta a um gateway.
+#[derive(Debug, Clone, Serialize)]
+pub struct NeighborSample {
+    pub timestamp: DateTime<Utc>,
+    pub interface: String,
+    pub gateway: IpAddr,
+    pub reachable: bool,
+    /// MAC da primeira resposta.
+    pub mac: Option<String>,
+    pub rtt_ms: Option<f64>,
+    /// MAC conhecido antes desta consulta, quando mudou.
+    pub previous_mac: Option<String>,
+    pub mac_changed: bool,
+    /// Todos os MACs que responderam, quando mais de um.
+    pub conflicting_macs: Vec<String>,
+    /// Falha local da consulta (socket, interface sem MAC...).
+    pub error: Option<String>,
+}
+
+/// Respostas recebidas a uma consulta.
+#[derive(Debug, Default)]
+struct Replies {
+    rtt_ms: Option<f64>,
+    /// MACs distintos, na ordem de chegada.
+    macs: Vec<[u8; 6]>,
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Gateways padrão IPv4 (`/proc/net/route`, endereços em hex na ordem de
+/// bytes do host) e IPv6 (`/proc/net/ipv6_route`, inclusive link-local).
+pub fn default_gateways(ipv6: bool) -> Vec<Gateway> {
+    let mut gateways = Vec::new();
+    if let Ok(table) = std::fs::read_to_string("/proc/net/route") {
+        for line in table.lines().skip(1) {
+            // Iface Destination Gateway Flags ...; RTF_GATEWAY = 0x2
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 || fields[1] != "00000000" {
+                continue;
+            }
+            let flags = u16::from_str_radix(fields[3], 16).unwrap_or(0);
+            if let Ok(gateway) = u32::from_str_radix(fields[2], 16)
+                && gateway != 0
+                && flags & 0x2 != 0
+            {
+                gateways.push(Gateway {
+                    interface: fields[0].to_string(),
+                    address: IpAddr::V4(Ipv4Addr::from(gateway.to_ne_bytes())),
+                });
+            }
+        }
+    }
+    if ipv6 && let Ok(table) = std::fs::read_to_string("/proc/net/ipv6_route") {
+        for line in table.lines() {
+            // destino, prefixo, origem, prefixo, next-hop, métrica, refs,
+            // uso, flags, interface
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[0].chars().any(|c| c != '0') || fields[1] != "00" {
+                continue;
+            }
+            if let Ok(next_hop) = u128::from_str_radix(fields[4], 16)
+                && next_hop != 0
+                && fields[9] != "lo"
+            {
+                gateways.push(Gateway {
+                    interface: fields[9].to_string(),
+                    address: IpAddr::V6(Ipv6Addr::from(next_hop)),
+                });
+            }
+        }
+    }
+    gateways.dedup();
+    gateways
+}
+
+/// MAC da interface, de `/sys/class/net/<interface>/address`.
+#[cfg(target_os = "linux")]
+fn interface_mac(interface: &str) -> std::io::Result<[u8; 6]> {
+    let text = std::fs::read_to_string(format!("/sys/class/net/{}/address", interface))?;
+    let bytes: Vec<u8> = text
+        .trim()
+        .split(':')
+        .filter_map(|part| u8::from_str_radix(part, 16).ok())
+        .collect();
+    bytes.try_into().map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("interface {} sem endereço MAC", interface),
+        )
+    })
+}
+
+/// Índice da interface (`if_nametoindex`).
+#[cfg(target_os = "linux")]
+fn interface_index(interface: &str) -> std::io::Result<u32> {
+    let name = std::ffi::CString::new(interface)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `name` é uma string C válida durante a chamada.
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(std::io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+/// Espera o socket ficar legível por até `left`; `false` no timeout.
+#[cfg(target_os = "linux")]
+fn wait_readable(fd: libc::c_int, left: Duration) -> std::io::Result<bool> {
+    loop {
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: um único `pollfd` válido durante a chamada.
+        let rc = unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as libc::c_int) };
+        if rc < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        return Ok(rc > 0);
+    }
+}
+
+/// Envia `request` até `attempts` vezes e junta as respostas aceitas por
+/// `parse` durante todo o `timeout` da tentativa (para notar um segundo MAC);
+/// para na primeira tentativa respondida.
+#[cfg(target_os = "linux")]
+fn exchange(
+    fd: libc::c_int,
+    send: &dyn Fn() -> std::io::Result<()>,
+    parse: &dyn Fn(&[u8]) -> Option<[u8; 6]>,
+    timeout: Duration,
+    attempts: u32,
+) -> std::io::Result<Replies> {
+    use std::time::Instant;
+
+    let mut replies = Replies::default();
+    for _ in 0..attempts {
+        send()?;
+        let started = Instant::now();
+        loop {
+            let left = timeout.saturating_sub(started.elapsed());
+            if left.is_zero() || !wait_readable(fd, left)? {
+                break;
+            }
+            let mut buf = [0u8; 1500];
+            // SAFETY: `buf` é válido para escrita no tamanho informado.
+            let len = unsafe {
+                libc::recv(
+                    fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    libc::MSG_DONTWAIT,
+                )
+            };
+            if len < 0 {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == std::io::ErrorKind::WouldBlock {
+                    continue;
+                }
+                return Err(e);
+            }
+            if let Some(mac) = parse(&buf[..len as usize]) {
+                replies
+                    .rtt_ms
+                    .get_or_insert(started.elapsed().as_secs_f64() * 1000.0);
+                if !replies.macs.contains(&mac) {
+                    replies.macs.push(mac);
+                }
+            }
+        }
+        if replies.rtt_ms.is_some() {
+            break;
+        }
+    }
+    Ok(replies)
+}
+
+/// ARP request em broadcast por um socket `AF_PACKET` ligado à interface.
+#[cfg(target_os = "linux")]
+fn solicit_v4(
+    interface: &str,
+    gateway: Ipv4Addr,
+    timeout: Duration,
+    attempts: u32,
+) -> std::io::Result<Replies> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    let mac = interface_mac(interface)?;
+    let index = interface_index(interface)?;
+    // Endereço de origem que o kernel usaria para falar com o gateway
+    let probe = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    probe.connect((gateway, 9))?;
+    let source = match probe.local_addr()?.ip() {
+        IpAddr::V4(source) => source,
+        IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+    };
+
+    let protocol = (libc::ETH_P_ARP as u16).to_be();
+    // SAFETY: chamada sem ponteiros; o descritor passa a ser do `OwnedFd`.
+    let raw = unsafe {
+        libc::socket(
+            libc::AF_PACKET,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::c_int::from(protocol),
+        )
+    };
+    if raw < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `raw` é um descritor recém-criado e sem outro dono.
+    let socket = unsafe { OwnedFd::from_raw_fd(raw) };
+    let fd = socket.as_raw_fd();
+    // SAFETY: `sockaddr_ll` é POD; zerado é um valor válido.
+    let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+    address.sll_family = libc::AF_PACKET as u16;
+    address.sll_protocol = protocol;
+    address.sll_ifindex = index as libc::c_int;
+    let address_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+    // SAFETY: `address` vive durante a chamada e o tamanho informado é o dele.
+    if unsafe {
+        libc::bind(
+            fd,
+            &address as *const _ as *const libc::sockaddr,
+            address_len,
+        )
+    } < 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut broadcast = address;
+    broadcast.sll_halen = 6;
+    broadcast.sll_addr[..6].copy_from_slice(&[0xff; 6]);
+
+    // Ethernet/IPv4, request: remetente = nós, alvo = gateway
+    let mut request = [0u8; 28];
+    request[..8].copy_from_slice(&[0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01]);
+    request[8..14].copy_from_slice(&mac);
+    request[14..18].copy_from_slice(&source.octets());
+    request[24..28].copy_from_slice(&gateway.octets());
+    let send = || {
+        // SAFETY: `request` e `broadcast` vivem durante a chamada.
+        let rc = unsafe {
+            libc::sendto(
+                fd,
+                request.as_ptr() as *const libc::c_void,
+                request.len(),
+                0,
+                &broadcast as *const _ as *const libc::sockaddr,
+                address_len,
+            )
+        };
+        if rc < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    };
+    // Reply (op 2) cujo remetente é o gateway
+    let parse = |packet: &[u8]| {
+        (packet.len() >= 28 && packet[6..8] == [0x00, 0x02] && packet[14..18] == gateway.octets())
+            .then(|| packet[8..14].try_into().ok())
+            .flatten()
+    };
+    exchange(fd, &send, &parse, timeout, attempts)
+}
+
+/// Neighbor Solicitation para o multicast solicited-node do gateway por um
+/// socket ICMPv6 cru (hop limit 255, como exige o ND).
+#[cfg(target_os = "linux")]
+fn solicit_v6(
+    interface: &str,
+    gateway: Ipv6Addr,
+    timeout: Duration,
+    attempts: u32,
+) -> std::io::Result<Replies> {
+    use crate::traceroute::set_option;
+    use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+    use std::net::SocketAddrV6;
+    use std::os::fd::AsRawFd;
+
+    let mac = interface_mac(interface)?;
+    let index = interface_index(interface)?;
+    let socket = Socket::new(
+        Domain::IPV6,
+        Type::from(libc::SOCK_RAW),
+        Some(Protocol::ICMPV6),
+    )?;
+    let fd = socket.as_raw_fd();
+    set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_HOPS, 255)?;
+    set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, 255)?;
+    set_option(
+        fd,
+        libc::IPPROTO_IPV6,
+        libc::IPV6_MULTICAST_IF,
+        index as libc::c_int,
+    )?;
+
+    let octets = gateway.octets();
+    let mut group = [0u8; 16];
+    group[..2].copy_from_slice(&[0xff, 0x02]);
+    group[11..13].copy_from_slice(&[0x01, 0xff]);
+    group[13..].copy_from_slice(&octets[13..]);
+    let destination = SockAddr::from(SocketAddrV6::new(Ipv6Addr::from(group), 0, 0, index));
+
+    // Tipo 135 (checksum pelo kernel), alvo e opção Source Link-Layer Address
+    let mut solicitation = [0u8; 32];
+    solicitation[0] = 135;
+    solicitation[8..24].copy_from_slice(&octets);
+    solicitation[24..26].copy_from_slice(&[1, 1]);
+    solicitation[26..].copy_from_slice(&mac);
+    let send = || socket.send_to(&solicitation, &destination).map(|_| ());
+    // Advertisement (136) do gateway com a opção Target Link-Layer Address
+    let parse = |packet: &[u8]| {
+        if packet.len() < 24 || packet[0] != 136 || packet[8..24] != octets {
+            return None;
+        }
+        let mut options = &packet[24..];
+        while options.len() >= 8 && options[1] > 0 {
+            let len = (options[1] as usize * 8).min(options.len());
+            if options[0] == 2 {
+                return options[2..8].try_into().ok();
+            }
+            options = &options[len..];
+        }
+        None
+    };
+    exchange(fd, &send, &parse, timeout, attempts)
+}
+
+#[cfg(target_os = "linux")]
+fn solicit(gateway: &Gateway, timeout: Duration, attempts: u32) -> std::io::Result<Replies> {
+    match gateway.address {
+        IpAddr::V4(address) => solicit_v4(&gateway.interface, address, timeout, attempts),
+        IpAddr::V6(address) => solicit_v6(&gateway.interface, address, timeout, attempts),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn solicit(_gateway: &Gateway, _timeout: Duration, _attempts: u32) -> std::io::Result<Replies> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "ARP/ND só é suportado no Linux",
+    ))
+}
+
+/// Consulta um gateway e compara o MAC com o conhecido.
+async fn probe(
+    gateway: &Gateway,
+    config: &NeighborConfig,
+    known: &mut HashMap<Gateway, String>,
+    reachable: &mut HashMap<Gateway, bool>,
+) -> NeighborSample {
+    let timestamp = Utc::now();
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let target = gateway.clone();
+    let attempts = config.attempts;
+    let result = tokio::task::spawn_blocking(move || solicit(&target, timeout, attempts))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    let mut sample = NeighborSample {
+        timestamp,
+        interface: gateway.interface.clone(),
+        gateway: gateway.address,
+        reachable: false,
+        mac: None,
+        rtt_ms: None,
+        previous_mac: None,
+        mac_changed: false,
+        conflicting_macs: Vec::new(),
+        error: None,
+    };
+    let replies = match result {
+        Ok(replies) => replies,
+        Err(e) => {
+            warn!(
+                "[NEIGHBOR] Falha ao consultar o gateway {} ({}): {}",
+                gateway.address, gateway.interface, e
+            );
+            sample.error = Some(e.to_string());
+            return sample;
+        }
+    };
+    sample.reachable = replies.rtt_ms.is_some();
+    sample.rtt_ms = replies.rtt_ms;
+    let was_reachable = reachable.insert(gateway.clone(), sample.reachable);
+    match (was_reachable, sample.reachable) {
+        (Some(true) | None, false) => warn!(
+            "📵 [NEIGHBOR] Gateway {} ({}) não responde a ARP/ND",
+            gateway.address, gateway.interface
+        ),
+        (Some(false), true) => info!(
+            "[NEIGHBOR] Gateway {} ({}) voltou a responder",
+            gateway.address, gateway.interface
+        ),
+        _ => {}
+    }
+    let Some(first) = replies.macs.first() else {
+        return sample;
+    };
+    let mac = format_mac(first);
+    if replies.macs.len() > 1 {
+        sample.conflicting_macs = replies.macs.iter().map(format_mac).collect();
+        warn!(
+            "🚨 [NEIGHBOR] Gateway {} ({}) respondido por {} MACs: {} (possível ARP spoofing)",
+            gateway.address,
+            gateway.interface,
+            replies.macs.len(),
+            sample.conflicting_macs.join(", ")
+        );
+    }
+    if let Some(previous) = known.get(gateway)
+        && *previous != mac
+    {
+        warn!(
+            "🚨 [NEIGHBOR] MAC do gateway {} ({}) mudou: {} → {} (troca do CPE ou ARP spoofing)",
+            gateway.address, gateway.interface, previous, mac
+        );
+        sample.previous_mac = Some(previous.clone());
+        sample.mac_changed = true;
+    }
+    known.insert(gateway.clone(), mac.clone());
+    sample.mac = Some(mac);
+    sample
+}
+
+/// Loop de consulta; os gateways são relidos a cada passada (a rota padrão
+/// pode mudar com DHCP ou failover).
+pub async fn run(storage: Arc<Storage>, config: NeighborConfig) {
+    // MACs gravados antes do restart, para notar uma troca enquanto parado
+    let mut known: HashMap<Gateway, String> = match storage.list_latest_gateway_macs().await {
+        Ok(macs) => macs
+            .into_iter()
+            .map(|(interface, address, mac)| (Gateway { interface, address }, mac))
+            .collect(),
+        Err(e) => {
+            warn!(
+                "[NEIGHBOR] Falha ao ler os últimos MACs dos gateways: {:?}",
+                e
+            );
+            HashMap::new()
+        }
+    };
+    let mut reachable: HashMap<Gateway, bool> = HashMap::new();
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        let gateways = default_gateways(config.ipv6);
+        if gateways.is_empty() {
+            debug!("[NEIGHBOR] Nenhuma rota padrão; nada a consultar");
+            continue;
+        }
+        let mut samples = Vec::with_capacity(gateways.len());
+        for gateway in &gateways {
+            samples.push(probe(gateway, &config, &mut known, &mut reachable).await);
+        }
+        match storage.insert_neighbor_samples(&samples).await {
+            Ok(written) => debug!("[NEIGHBOR] {} linha(s) em neighbor_samples", written),
+            Err(e) => warn!("[NEIGHBOR] Falha ao gravar neighbor_samples: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! netwatch.rs — Mudanças de interface e de rota padrão do host
+//!
+//! Com `[netwatch]`, uma thread escuta as notificações de rtnetlink do kernel
+//! (grupos de link e de rotas IPv4/IPv6) e grava em `host_network_events` as
+//! mudanças da própria conectividade do probe: interface que sobe, cai ou
+//! some e rota padrão (tabela `main`) adicionada ou removida, com gateway,
+//! interface e métrica. Um outage que coincide com `link_down` ou com a troca
+//! da rota padrão (failover, renovação de DHCP) é do probe, não dos targets.
+//!
+//! O estado inicial vem de um dump de links e rotas na inicialização e não
+//! gera eventos; só as mudanças a partir dele são gravadas. As notificações
+//! repetidas do kernel (estatísticas, flags sem relação com o estado) são
+//! descartadas comparando com o último estado conhecido.
+
+use crate::config::NetwatchConfig;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Tipo do evento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkEventKind {
+    LinkUp,
+    LinkDown,
+    LinkRemoved,
+    DefaultRouteAdded,
+    DefaultRouteRemoved,
+}
+
+impl NetworkEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkEventKind::LinkUp => "link_up",
+            NetworkEventKind::LinkDown => "link_down",
+            NetworkEventKind::LinkRemoved => "link_removed",
+            NetworkEventKind::DefaultRouteAdded => "default_route_added",
+            NetworkEventKind::DefaultRouteRemoved => "default_route_removed",
+        }
+    }
+}
+
+/// Linha de `host_network_events`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostNetworkEvent {
+    pub timestamp: DateTime<Utc>,
+    pub event: NetworkEventKind,
+    pub interface: Option<String>,
+    /// `IFLA_OPERSTATE` da interface (`up`, `down`, `lowerlayerdown`...).
+    pub oper_state: Option<String>,
+    /// `ipv4` ou `ipv6`, nos eventos de rota.
+    pub family: Option<&'static str>,
+    pub gateway: Option<IpAddr>,
+    pub metric: Option<i32>,
+}
+
+fn oper_state_name(state: u8) -> &'static str {
+    match state {
+        1 => "notpresent",
+        2 => "down",
+        3 => "lowerlayerdown",
+        4 => "testing",
+        5 => "dormant",
+        6 => "up",
+        _ => "unknown",
+    }
+}
+
+/// Rota padrão conhecida.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DefaultRoute {
+    v6: bool,
+    gateway: Option<IpAddr>,
+    oif: Option<u32>,
+    metric: Option<u32>,
+}
+
+/// Estado conhecido de links e rotas padrão.
+#[derive(Debug, Default)]
+struct State {
+    /// Nome e "up" (`IFF_LOWER_UP`) por índice de interface.
+    links: HashMap<u32, (String, bool)>,
+    routes: HashSet<DefaultRoute>,
+}
+
+/// Atributos `rtattr` de uma mensagem: (tipo, valor).
+fn attributes(mut data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attrs = Vec::new();
+    while data.len() >= 4 {
+        let len = u16::from_ne_bytes([data[0], data[1]]) as usize;
+        let kind = u16::from_ne_bytes([data[2], data[3]]) & 0x3fff;
+        if len < 4 || len > data.len() {
+            break;
+        }
+        attrs.push((kind, &data[4..len]));
+        data = &data[((len + 3) & !3).min(data.len())..];
+    }
+    attrs
+}
+
+fn u32_attr(value: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+}
+
+impl State {
+    fn link_name(&self, index: u32) -> Option<String> {
+        self.links.get(&index).map(|(name, _)| name.clone())
+    }
+
+    /// `RTM_NEWLINK`/`RTM_DELLINK`: corpo `ifinfomsg` (16 bytes) e atributos.
+    fn on_link(
+        &mut self,
+        kind: u16,
+        body: &[u8],
+        config: &NetwatchConfig,
+    ) -> Option<HostNetworkEvent> {
+        if body.len() < 16 {
+            return None;
+        }
+        let index = u32::from_ne_bytes(body[4..8].try_into().ok()?);
+        let flags = u32::from_ne_bytes(body[8..12].try_into().ok()?);
+        let mut name = None;
+        let mut oper_state = None;
+        for (attr, value) in attributes(&body[16..]) {
+            match attr {
+                libc::IFLA_IFNAME => {
+                    name = Some(
+                        String::from_utf8_lossy(value)
+                            .trim_end_matches('\0')
+                            .to_string(),
+                    )
+                }
+                libc::IFLA_OPERSTATE => oper_state = value.first().copied(),
+                _ => {}
+            }
+        }
+        let name = name.or_else(|| self.link_name(index))?;
+        let loopback = flags & libc::IFF_LOOPBACK as u32 != 0;
+        let watched = if config.interfaces.is_empty() {
+            !loopback
+        } else {
+            config.interfaces.contains(&name)
+        };
+        let event = |event| HostNetworkEvent {
+            timestamp: Utc::now(),
+            event,
+            interface: Some(name.clone()),
+            oper_state: oper_state.map(|s| oper_state_name(s).to_string()),
+            family: None,
+            gateway: None,
+            metric: None,
+        };
+        if kind == libc::RTM_DELLINK {
+            let known = self.links.remove(&index).is_some();
+            return (known && watched).then(|| event(NetworkEventKind::LinkRemoved));
+        }
+        let up = flags & libc::IFF_LOWER_UP as u32 != 0 && flags & libc::IFF_UP as u32 != 0;
+        let previous = self.links.insert(index, (name.clone(), up));
+        match previous {
+            Some((_, was_up)) if was_up != up && watched => Some(event(if up {
+                NetworkEventKind::LinkUp
+            } else {
+                NetworkEventKind::LinkDown
+            })),
+            // Interface nova depois do dump (USB, VPN): registra o estado
+            None if watched => Some(event(if up {
+                NetworkEventKind::LinkUp
+            } else {
+                NetworkEventKind::LinkDown
+            })),
+            _ => None,
+        }
+    }
+
+    /// `RTM_NEWROUTE`/`RTM_DELROUTE`: corpo `rtmsg` (12 bytes) e atributos;
+    /// só rotas padrão unicast da tabela `main`.
+    fn on_route(
+        &mut self,
+        kind: u16,
+        body: &[u8],
+        config: &NetwatchConfig,
+    ) -> Option<HostNetworkEvent> {
+        if body.len() < 12 {
+            return None;
+        }
+        let (family, dst_len, mut table, route_type) =
+            (body[0], body[1], u32::from(body[4]), body[7]);
+        if dst_len != 0 || route_type != libc::RTN_UNICAST {
+            return None;
+        }
+        let v6 = match family as i32 {
+            libc::AF_INET => false,
+            libc::AF_INET6 => true,
+            _ => return None,
+        };
+        let mut route = DefaultRoute {
+            v6,
+            gateway: None,
+            oif: None,
+            metric: None,
+        };
+        for (attr, value) in attributes(&body[12..]) {
+            match attr {
+                libc::RTA_TABLE => table = u32_attr(value).unwrap_or(table),
+                libc::RTA_OIF => route.oif = u32_attr(value),
+                libc::RTA_PRIORITY => route.metric = u32_attr(value),
+                libc::RTA_GATEWAY => {
+                    route.gateway = match value.len() {
+                        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(value).ok()?))),
+                        16 => Some(IpAddr::V6(Ipv6Addr::from(
+                            <[u8; 16]>::try_from(value).ok()?,
+                        ))),
+                        _ => None,
+                    }
+                }
+                _ => {}
+            }
+        }
+        if table != u32::from(libc::RT_TABLE_MAIN) {
+            return None;
+        }
+        let interface = route.oif.and_then(|index| self.link_name(index));
+        if !config.interfaces.is_empty()
+            && !interface
+                .as_ref()
+                .is_some_and(|name| config.interfaces.contains(name))
+        {
+            return None;
+        }
+        let event = if kind == libc::RTM_DELROUTE {
+            if !self.routes.remove(&route) {
+                return None;
+            }
+            NetworkEventKind::DefaultRouteRemoved
+        } else {
+            if !self.routes.insert(route.clone()) {
+                return None;
+            }
+            NetworkEventKind::DefaultRouteAdded
+        };
+        Some(HostNetworkEvent {
+            timestamp: Utc::now(),
+            event,
+            interface,
+            oper_state: None,
+            family: Some(if v6 { "ipv6" } else { "ipv4" }),
+            gateway: route.gateway,
+            metric: route.metric.map(|m| m as i32),
+        })
+    }
+
+    /// Processa um datagrama de netlink; retorna os eventos e se o dump em
+    /// andamento terminou (`NLMSG_DONE`). `port` é o endereço netlink do
+    /// socket: as respostas aos nossos dumps vêm com ele em `nlmsg_pid`.
+    fn on_datagram(
+        &mut self,
+        mut data: &[u8],
+        port: u32,
+        config: &NetwatchConfig,
+    ) -> (Vec<HostNetworkEvent>, bool) {
+        let mut events = Vec::new();
+        let mut done = false;
+        while data.len() >= 16 {
+            let len = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            let kind = u16::from_ne_bytes([data[4], data[5]]);
+            let pid = u32::from_ne_bytes([data[12], data[13], data[14], data[15]]);
+            if len < 16 || len > data.len() {
+                break;
+            }
+            let body = &data[16..len];
+            // Respostas ao dump só alimentam o estado; as notificações levam
+            // o pid de quem causou a mudança (0 = o kernel)
+            let event = match kind {
+                k if k == libc::NLMSG_DONE as u16 || k == libc::NLMSG_ERROR as u16 => {
+                    done |= pid == port;
+                    None
+                }
+                libc::RTM_NEWLINK | libc::RTM_DELLINK => self.on_link(kind, body, config),
+                libc::RTM_NEWROUTE | libc::RTM_DELROUTE => self.on_route(kind, body, config),
+                _ => None,
+            };
+            if pid != port
+                && let Some(event) = event
+            {
+                events.push(event);
+            }
+            data = &data[((len + 3) & !3).min(data.len())..];
+        }
+        (events, done)
+    }
+}
+
+/// Socket rtnetlink inscrito nos grupos de link e rotas.
+#[cfg(target_os = "linux")]
+struct NetlinkSocket {
+    fd: std::os::fd::OwnedFd,
+    /// `nl_pid` atribuído pelo kernel no `bind`.
+    port: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl NetlinkSocket {
+    fn open() -> std::io::Result<Self> {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        // SAFETY: chamada sem ponteiros; o descritor passa a ser do `OwnedFd`.
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if raw < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: `raw` é um descritor recém-criado e sem outro dono.
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+        // SAFETY: `sockaddr_nl` é POD; zerado é um valor válido.
+        let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as u16;
+        address.nl_groups =
+            (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_ROUTE | libc::RTMGRP_IPV6_ROUTE) as u32;
+        // SAFETY: `address` vive durante a chamada e o tamanho informado é o dele.
+        let rc = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &address as *const _ as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if rc < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut len = std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+        // SAFETY: `address` e `len` vivem durante a chamada.
+        let rc = unsafe {
+            libc::getsockname(
+                fd.as_raw_fd(),
+                &mut address as *mut _ as *mut libc::sockaddr,
+                &mut len,
+            )
+        };
+        if rc < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            fd,
+            port: address.nl_pid,
+        })
+    }
+
+    /// Pede o dump de links (`RTM_GETLINK`) ou rotas (`RTM_GETROUTE`).
+    fn request_dump(&self, kind: u16, seq: u32) -> std::io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        // nlmsghdr + rtgenmsg (família AF_UNSPEC), alinhado a 4 bytes
+        let mut request = [0u8; 20];
+        request[..4].copy_from_slice(&20u32.to_ne_bytes());
+        request[4..6].copy_from_slice(&kind.to_ne_bytes());
+        let flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+        request[6..8].copy_from_slice(&flags.to_ne_bytes());
+        request[8..12].copy_from_slice(&seq.to_ne_bytes());
+        // SAFETY: `request` vive durante a chamada.
+        let rc = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                request.as_ptr() as *const libc::c_void,
+                request.len(),
+                0,
+            )
+        };
+        if rc < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::os::fd::AsRawFd;
+
+        loop {
+            // SAFETY: `buf` é válido para escrita no tamanho informado.
+            let rc = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            };
+            if rc >= 0 {
+                return Ok(rc as usize);
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != std::io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Thread de leitura: dump inicial e, depois, as notificações (bloqueante).
+#[cfg(target_os = "linux")]
+fn watch(config: &NetwatchConfig, events: mpsc::Sender<HostNetworkEvent>) -> std::io::Result<()> {
+    let socket = NetlinkSocket::open()?;
+    let mut state = State::default();
+    let mut buf = vec![0u8; 64 * 1024];
+    for (seq, kind) in [(1, libc::RTM_GETLINK), (2, libc::RTM_GETROUTE)] {
+        socket.request_dump(kind, seq)?;
+        loop {
+            let len = socket.recv(&mut buf)?;
+            let (found, done) = state.on_datagram(&buf[..len], socket.port, config);
+            for event in found {
+                if events.blocking_send(event).is_err() {
+                    return Ok(());
+                }
+            }
+            if done {
+                break;
+            }
+        }
+    }
+    info!(
+        "[NETWATCH] {} interface(s) e {} rota(s) padrão no estado inicial",
+        state.links.len(),
+        state.routes.len()
+    );
+    loop {
+        let len = match socket.recv(&mut buf) {
+            Ok(len) => len,
+            // Fila do socket estourou: notificações perdidas, o estado segue
+            Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                warn!("[NETWATCH] Notificações do kernel perdidas (ENOBUFS)");
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for event in state.on_datagram(&buf[..len], socket.port, config).0 {
+            if events.blocking_send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch(_config: &NetwatchConfig, _events: mpsc::Sender<HostNetworkEvent>) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "netlink só é suportado no Linux",
+    ))
+}
+
+fn log_event(event: &HostNetworkEvent) {
+    let interface = event.interface.as_deref().unwrap_or("?");
+    match event.event {
+        NetworkEventKind::LinkUp => info!("🔗 [NETWATCH] Interface {} subiu", interface),
+        NetworkEventKind::LinkDown => warn!("⛓️‍💥 [NETWATCH] Interface {} caiu", interface),
+        NetworkEventKind::LinkRemoved => warn!("[NETWATCH] Interface {} removida", interface),
+        NetworkEventKind::DefaultRouteAdded | NetworkEventKind::DefaultRouteRemoved => {
+            let gateway = event
+                .gateway
+                .map_or_else(|| "-".to_string(), |gateway| gateway.to_string());
+            let action = if event.event == NetworkEventKind::DefaultRouteAdded {
+                "adicionada"
+            } else {
+                "removida"
+            };
+            warn!(
+                "🧭 [NETWATCH] Rota padrão {} {} (via {} em {})",
+                event.family.unwrap_or("?"),
+                action,
+                gateway,
+                interface
+            );
+        }
+    }
+}
+
+/// Inicia a escuta e grava os eventos até o canal fechar.
+pub async fn run(storage: Arc<Storage>, config: NetwatchConfig) {
+    let (tx, mut rx) = mpsc::channel(256);
+    let watcher = tokio::task::spawn_blocking(move || watch(&config, tx));
+    while let Some(event) = rx.recv().await {
+        log_event(&event);
+        if let Err(e) = storage.insert_host_network_event(&event).await {
+            warn!("[NETWATCH] Falha ao gravar host_network_events: {:?}", e);
+        }
+    }
+    match watcher.await {
+        Ok(Err(e)) => warn!("[NETWATCH] Escuta de netlink encerrada: {}", e),
+        Err(e) => warn!("[NETWATCH] Escuta de netlink encerrada: {}", e),
+        Ok(Ok(())) => {}
+    }
+}
@@ -0,0 +1,275 @@
+//! notify.rs — Roteamento de notificações de outage por severidade
+//!
+//! Cada rota é um webhook (`POST` JSON) com severidade mínima. Aberturas,
+//! escalonamentos e encerramentos vão a todas as rotas cuja severidade mínima
+//! é atingida pela severidade atual do outage, de modo que uma rota `critical`
+//! só é acionada quando o outage chega a `critical`.
+//!
+//! Uma rota com `quiet_hours` retém, dentro da janela, os eventos que não são
+//! outage de consenso (outages parciais e degradações) e os entrega num único
+//! resumo (`kind = "digest"`) quando a janela termina. Outages sempre são
+//! enviados na hora. O resumo fica em memória: um restart durante a janela o
+//! descarta. Os horários da janela seguem `report_timezone`, salvo
+//! `utc_offset` na própria rota.
+
+use crate::calendar::Zone;
+use crate::config::{NotificationRoute, NotificationsConfig, ReportPeriod};
+use crate::periodic_report::PeriodicReport;
+use crate::types::{OutageEvent, OutageEventType, Severity};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Teto de notificações retidas por rota; as mais antigas são descartadas.
+const MAX_DIGEST_ITEMS: usize = 1000;
+
+/// Motivo da notificação.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Opened,
+    Escalated,
+    Closed,
+    /// Resumo das notificações retidas em horário silencioso.
+    Digest,
+    /// Resumo periódico (`[periodic_report]`).
+    Report,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Opened => "opened",
+            NotificationKind::Escalated => "escalated",
+            NotificationKind::Closed => "closed",
+            NotificationKind::Digest => "digest",
+            NotificationKind::Report => "report",
+        }
+    }
+
+    /// Abertura ou encerramento, conforme `end_time`.
+    pub fn for_event(event: &OutageEvent) -> Self {
+        if event.end_time.is_some() {
+            NotificationKind::Closed
+        } else {
+            NotificationKind::Opened
+        }
+    }
+}
+
+/// Corpo enviado às rotas.
+#[derive(Debug, Clone, Serialize)]
+struct Notification {
+    kind: NotificationKind,
+    severity: Severity,
+    probe: String,
+    outage: OutageEvent,
+}
+
+/// Notificação retida em horário silencioso.
+#[derive(Debug, Clone, Serialize)]
+struct QueuedNotification {
+    queued_at: DateTime<Utc>,
+    #[serde(flatten)]
+    notification: Notification,
+}
+
+/// Notificações retidas de uma rota.
+#[derive(Debug, Default)]
+struct Digest {
+    items: Vec<QueuedNotification>,
+    /// Descartadas por exceder `MAX_DIGEST_ITEMS`.
+    dropped: usize,
+}
+
+/// Corpo do resumo enviado ao fim do horário silencioso.
+#[derive(Debug, Serialize)]
+struct DigestBody<'a> {
+    kind: NotificationKind,
+    route: &'a str,
+    notifications: &'a [QueuedNotification],
+    dropped: usize,
+}
+
+/// Corpo do resumo periódico.
+#[derive(Debug, Serialize)]
+struct ReportBody<'a> {
+    kind: NotificationKind,
+    period: ReportPeriod,
+    /// Fuso das fronteiras do período.
+    timezone: String,
+    tenant: Option<&'a str>,
+    #[serde(flatten)]
+    report: &'a PeriodicReport,
+}
+
+/// Cliente de envio das notificações.
+#[derive(Debug)]
+pub struct Notifier {
+    client: reqwest::Client,
+    routes: Vec<NotificationRoute>,
+    /// Resumos pendentes, por nome de rota.
+    digests: Mutex<HashMap<String, Digest>>,
+    /// Fuso dos horários silenciosos sem `utc_offset`.
+    timezone: Zone,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationsConfig, timezone: Zone) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP de notificações")?;
+        Ok(Self {
+            client,
+            routes: config.routes.clone(),
+            digests: Mutex::new(HashMap::new()),
+            timezone,
+        })
+    }
+
+    /// Há alguma rota com horário silencioso (e, portanto, resumos a enviar).
+    pub fn has_quiet_hours(&self) -> bool {
+        self.routes.iter().any(|r| r.quiet_hours.is_some())
+    }
+
+    /// Envia a notificação às rotas aplicáveis, ou a retém nas rotas em
+    /// horário silencioso. Falhas são logadas por rota e não interrompem as
+    /// demais.
+    pub async fn send(&self, kind: NotificationKind, event: &OutageEvent, location: &str) {
+        let now = Utc::now();
+        let severity = Severity::of(event);
+        let body = Notification {
+            kind,
+            severity,
+            probe: location.to_string(),
+            outage: event.clone(),
+        };
+        let routes = self
+            .routes
+            .iter()
+            .filter(|r| severity >= r.min_severity && r.event_types.contains(&event.event_type));
+        for route in routes {
+            let quiet = route
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|q| q.contains(now, self.timezone));
+            if quiet && event.event_type != OutageEventType::Outage {
+                self.queue(route, now, body.clone());
+                debug!(
+                    "[NOTIFY] {:?} ({}) retido para o resumo da rota {}",
+                    kind, severity, route.name
+                );
+                continue;
+            }
+            match self.post(route, &body).await {
+                Ok(()) => debug!(
+                    "[NOTIFY] {:?} ({}) enviado para a rota {}",
+                    kind, severity, route.name
+                ),
+                Err(e) => warn!("[NOTIFY] Falha ao notificar a rota {}: {:?}", route.name, e),
+            }
+        }
+    }
+
+    fn queue(&self, route: &NotificationRoute, now: DateTime<Utc>, notification: Notification) {
+        let mut digests = self.digests.lock().unwrap();
+        let digest = digests.entry(route.name.clone()).or_default();
+        digest.items.push(QueuedNotification {
+            queued_at: now,
+            notification,
+        });
+        if digest.items.len() > MAX_DIGEST_ITEMS {
+            digest.items.remove(0);
+            digest.dropped += 1;
+        }
+    }
+
+    /// Envia os resumos das rotas cujo horário silencioso terminou. Um envio
+    /// com falha devolve o resumo à fila, para a próxima tentativa.
+    pub async fn flush_digests(&self) {
+        let now = Utc::now();
+        for route in &self.routes {
+            if route
+                .quiet_hours
+                .as_ref()
+                .is_none_or(|q| q.contains(now, self.timezone))
+            {
+                continue;
+            }
+            let Some(digest) = self.digests.lock().unwrap().remove(&route.name) else {
+                continue;
+            };
+            let body = DigestBody {
+                kind: NotificationKind::Digest,
+                route: &route.name,
+                notifications: &digest.items,
+                dropped: digest.dropped,
+            };
+            match self.post(route, &body).await {
+                Ok(()) => info!(
+                    "[NOTIFY] Resumo do horário silencioso enviado para a rota {} ({} notificação(ões))",
+                    route.name,
+                    digest.items.len()
+                ),
+                Err(e) => {
+                    warn!(
+                        "[NOTIFY] Falha ao enviar o resumo da rota {}: {:?}",
+                        route.name, e
+                    );
+                    let mut digests = self.digests.lock().unwrap();
+                    let pending = digests.entry(route.name.clone()).or_default();
+                    let mut items = digest.items;
+                    items.append(&mut pending.items);
+                    pending.items = items;
+                    pending.dropped += digest.dropped;
+                }
+            }
+        }
+    }
+
+    /// Envia o resumo periódico às rotas nomeadas (vazio = todas), sem
+    /// filtro de severidade nem horário silencioso.
+    pub async fn send_report(
+        &self,
+        period: ReportPeriod,
+        timezone: Zone,
+        tenant: Option<&str>,
+        report: &PeriodicReport,
+        route_names: &[String],
+    ) {
+        let body = ReportBody {
+            kind: NotificationKind::Report,
+            period,
+            timezone: timezone.to_string(),
+            tenant,
+            report,
+        };
+        let routes = self
+            .routes
+            .iter()
+            .filter(|r| route_names.is_empty() || route_names.contains(&r.name));
+        for route in routes {
+            if let Err(e) = self.post(route, &body).await {
+                warn!(
+                    "[NOTIFY] Falha ao enviar o resumo periódico para a rota {}: {:?}",
+                    route.name, e
+                );
+            }
+        }
+    }
+
+    async fn post(&self, route: &NotificationRoute, body: &impl Serialize) -> reqwest::Result<()> {
+        self.client
+            .post(&route.url)
+            .json(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,356 @@
+//! ocsp.rs — Leitura de certificados X.509 e revogação via OCSP
+//!
+//! O check `tls` precisa de poucos campos do certificado (serial, emissor,
+//! validade, chave pública e a URL OCSP do `Authority Information Access`) e
+//! de montar/ler mensagens OCSP (RFC 6960). Um leitor DER mínimo basta para
+//! isso. A assinatura da resposta OCSP não é validada: o objetivo é monitorar
+//! o que o endpoint serve, não decidir confiança.
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0A;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// 1.3.6.1.5.5.7.1.1 (Authority Information Access)
+const OID_AIA: &[u8] = &[0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x01];
+/// 1.3.6.1.5.5.7.48.1 (id-ad-ocsp)
+const OID_AD_OCSP: &[u8] = &[0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01];
+/// 1.3.14.3.2.26 (SHA-1, usado no CertID por compatibilidade com os responders)
+const OID_SHA1: &[u8] = &[0x2B, 0x0E, 0x03, 0x02, 0x1A];
+
+/// Leitor sequencial de elementos DER.
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Lê o próximo elemento e retorna (tag, conteúdo, elemento completo).
+    fn next_raw(&mut self) -> Result<(u8, &'a [u8], &'a [u8])> {
+        let tag = *self.data.first().context("DER truncado")?;
+        let first = *self.data.get(1).context("DER truncado")?;
+        let (len, header) = if first < 0x80 {
+            (usize::from(first), 2)
+        } else {
+            let count = usize::from(first & 0x7F);
+            if count == 0 || count > 4 {
+                bail!("comprimento DER não suportado");
+            }
+            let bytes = self.data.get(2..2 + count).context("DER truncado")?;
+            let len = bytes
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+            (len, 2 + count)
+        };
+        let end = header + len;
+        let element = self.data.get(..end).context("DER truncado")?;
+        self.data = &self.data[end..];
+        Ok((tag, &element[header..], element))
+    }
+
+    /// Lê o próximo elemento exigindo a tag.
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let (found, content, _) = self.next_raw()?;
+        if found != tag {
+            bail!("DER: esperada tag {:#04x}, encontrada {:#04x}", tag, found);
+        }
+        Ok(content)
+    }
+
+    /// Lê o próximo elemento se ele tiver a tag.
+    fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>> {
+        if self.peek_tag() == Some(tag) {
+            self.expect(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Campos do certificado usados pelo check `tls`.
+#[derive(Debug, Clone)]
+pub struct CertInfo {
+    /// Conteúdo do INTEGER do serial.
+    pub serial: Vec<u8>,
+    /// `Name` do emissor em DER (elemento completo).
+    pub issuer: Vec<u8>,
+    /// `Name` do titular em DER (elemento completo).
+    pub subject: Vec<u8>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Bits da chave pública (conteúdo do BIT STRING sem o byte de padding).
+    pub public_key: Vec<u8>,
+    pub ocsp_url: Option<String>,
+}
+
+impl CertInfo {
+    /// Serial em hexadecimal, como exibido pelos navegadores.
+    pub fn serial_hex(&self) -> String {
+        hex::encode(&self.serial)
+    }
+}
+
+/// Lê os campos relevantes de um certificado DER.
+pub fn parse_certificate(der: &[u8]) -> Result<CertInfo> {
+    let mut cert = Der::new(Der::new(der).expect(TAG_SEQUENCE)?);
+    let mut tbs = Der::new(cert.expect(TAG_SEQUENCE)?);
+    tbs.optional(0xA0)?; // version
+    let serial = tbs.expect(TAG_INTEGER)?.to_vec();
+    tbs.expect(TAG_SEQUENCE)?; // signature
+    let (_, _, issuer) = tbs.next_raw()?;
+    let mut validity = Der::new(tbs.expect(TAG_SEQUENCE)?);
+    let not_before = parse_time(&mut validity)?;
+    let not_after = parse_time(&mut validity)?;
+    let (_, _, subject) = tbs.next_raw()?;
+    let mut spki = Der::new(tbs.expect(TAG_SEQUENCE)?);
+    spki.expect(TAG_SEQUENCE)?; // algorithm
+    let public_key = spki
+        .expect(TAG_BIT_STRING)?
+        .get(1..)
+        .context("chave pública vazia")?
+        .to_vec();
+    tbs.optional(0x81)?; // issuerUniqueID
+    tbs.optional(0x82)?; // subjectUniqueID
+    let ocsp_url = match tbs.optional(0xA3)? {
+        Some(extensions) => find_ocsp_url(Der::new(extensions).expect(TAG_SEQUENCE)?)?,
+        None => None,
+    };
+    Ok(CertInfo {
+        serial,
+        issuer: issuer.to_vec(),
+        subject: subject.to_vec(),
+        not_before,
+        not_after,
+        public_key,
+        ocsp_url,
+    })
+}
+
+fn find_ocsp_url(extensions: &[u8]) -> Result<Option<String>> {
+    let mut extensions = Der::new(extensions);
+    while !extensions.is_empty() {
+        let mut extension = Der::new(extensions.expect(TAG_SEQUENCE)?);
+        let oid = extension.expect(TAG_OID)?;
+        extension.optional(TAG_BOOLEAN)?; // critical
+        if oid != OID_AIA {
+            continue;
+        }
+        let value = extension.expect(TAG_OCTET_STRING)?;
+        let mut descriptions = Der::new(Der::new(value).expect(TAG_SEQUENCE)?);
+        while !descriptions.is_empty() {
+            let mut description = Der::new(descriptions.expect(TAG_SEQUENCE)?);
+            let method = description.expect(TAG_OID)?;
+            // GeneralName uniformResourceIdentifier: [6] IMPLICIT IA5String
+            if method == OID_AD_OCSP
+                && let Some(uri) = description.optional(0x86)?
+            {
+                return Ok(Some(String::from_utf8_lossy(uri).into_owned()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn parse_time(der: &mut Der<'_>) -> Result<DateTime<Utc>> {
+    let (tag, content, _) = der.next_raw()?;
+    let text = std::str::from_utf8(content).context("data DER inválida")?;
+    let full = match tag {
+        // YYMMDDHHMMSSZ; anos < 50 são 20YY (RFC 5280)
+        TAG_UTC_TIME => {
+            let year: u32 = text
+                .get(..2)
+                .and_then(|y| y.parse().ok())
+                .context("UTCTime inválido")?;
+            format!("{}{}", if year < 50 { "20" } else { "19" }, text)
+        }
+        TAG_GENERALIZED_TIME => text.to_string(),
+        other => bail!("DER: tag de data inesperada {:#04x}", other),
+    };
+    // Frações de segundo (GeneralizedTime) não interessam
+    let trimmed = match full.split_once('.') {
+        Some((head, _)) => format!("{}Z", head),
+        None => full,
+    };
+    NaiveDateTime::parse_from_str(&trimmed, "%Y%m%d%H%M%SZ")
+        .map(|t| t.and_utc())
+        .with_context(|| format!("data DER inválida: {}", text))
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sha1(data: &[u8]) -> Vec<u8> {
+    ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, data)
+        .as_ref()
+        .to_vec()
+}
+
+/// Monta um `OCSPRequest` DER para `cert`, emitido por `issuer`.
+pub fn build_request(cert: &CertInfo, issuer: &CertInfo) -> Vec<u8> {
+    let algorithm = tlv(
+        TAG_SEQUENCE,
+        &[tlv(TAG_OID, OID_SHA1), tlv(TAG_NULL, &[])].concat(),
+    );
+    let cert_id = tlv(
+        TAG_SEQUENCE,
+        &[
+            algorithm,
+            tlv(TAG_OCTET_STRING, &sha1(&issuer.subject)),
+            tlv(TAG_OCTET_STRING, &sha1(&issuer.public_key)),
+            tlv(TAG_INTEGER, &cert.serial),
+        ]
+        .concat(),
+    );
+    let request = tlv(TAG_SEQUENCE, &cert_id);
+    let request_list = tlv(TAG_SEQUENCE, &request);
+    let tbs_request = tlv(TAG_SEQUENCE, &request_list);
+    tlv(TAG_SEQUENCE, &tbs_request)
+}
+
+/// Situação de revogação de um certificado segundo o OCSP.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum CertStatus {
+    Good,
+    Revoked {
+        revoked_at: DateTime<Utc>,
+        reason: Option<&'static str>,
+    },
+    /// O responder não conhece o certificado.
+    Unknown,
+}
+
+/// Resposta OCSP para um certificado.
+#[derive(Debug, Clone, Serialize)]
+pub struct OcspStatus {
+    #[serde(flatten)]
+    pub status: CertStatus,
+    pub this_update: DateTime<Utc>,
+    /// Fim da validade da resposta; ausente = sem prazo informado.
+    pub next_update: Option<DateTime<Utc>>,
+}
+
+/// Lê um `OCSPResponse` DER e retorna a situação do certificado com `serial`.
+pub fn parse_response(der: &[u8], serial: &[u8]) -> Result<OcspStatus> {
+    let mut response = Der::new(Der::new(der).expect(TAG_SEQUENCE)?);
+    let status = response.expect(TAG_ENUMERATED)?;
+    match status {
+        [0] => {}
+        [code] => bail!("responder OCSP retornou {}", response_status(*code)),
+        _ => bail!("status OCSP inválido"),
+    }
+    let bytes = response
+        .optional(0xA0)?
+        .ok_or_else(|| anyhow!("resposta OCSP sem conteúdo"))?;
+    let mut bytes = Der::new(Der::new(bytes).expect(TAG_SEQUENCE)?);
+    bytes.expect(TAG_OID)?; // id-pkix-ocsp-basic
+    let basic = bytes.expect(TAG_OCTET_STRING)?;
+    let mut basic = Der::new(Der::new(basic).expect(TAG_SEQUENCE)?);
+    let mut data = Der::new(basic.expect(TAG_SEQUENCE)?);
+    data.optional(0xA0)?; // version
+    data.next_raw()?; // responderID
+    data.expect(TAG_GENERALIZED_TIME)?; // producedAt
+    let mut responses = Der::new(data.expect(TAG_SEQUENCE)?);
+    while !responses.is_empty() {
+        let mut single = Der::new(responses.expect(TAG_SEQUENCE)?);
+        let mut cert_id = Der::new(single.expect(TAG_SEQUENCE)?);
+        cert_id.expect(TAG_SEQUENCE)?;
+        cert_id.expect(TAG_OCTET_STRING)?;
+        cert_id.expect(TAG_OCTET_STRING)?;
+        if cert_id.expect(TAG_INTEGER)? != serial {
+            continue;
+        }
+        let (tag, content, _) = single.next_raw()?;
+        let status = match tag {
+            0x80 => CertStatus::Good,
+            0xA1 => {
+                let mut info = Der::new(content);
+                let revoked_at = parse_time(&mut info)?;
+                let reason = match info.optional(0xA0)? {
+                    Some(reason) => match Der::new(reason).expect(TAG_ENUMERATED)? {
+                        [code] => Some(revocation_reason(*code)),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                CertStatus::Revoked { revoked_at, reason }
+            }
+            0x82 => CertStatus::Unknown,
+            other => bail!("certStatus OCSP inválido: {:#04x}", other),
+        };
+        let this_update = parse_time(&mut single)?;
+        let next_update = match single.optional(0xA0)? {
+            Some(next) => Some(parse_time(&mut Der::new(next))?),
+            None => None,
+        };
+        return Ok(OcspStatus {
+            status,
+            this_update,
+            next_update,
+        });
+    }
+    bail!("resposta OCSP não cobre o certificado")
+}
+
+fn response_status(code: u8) -> &'static str {
+    match code {
+        1 => "malformedRequest",
+        2 => "internalError",
+        3 => "tryLater",
+        5 => "sigRequired",
+        6 => "unauthorized",
+        _ => "status desconhecido",
+    }
+}
+
+fn revocation_reason(code: u8) -> &'static str {
+    match code {
+        0 => "unspecified",
+        1 => "keyCompromise",
+        2 => "cACompromise",
+        3 => "affiliationChanged",
+        4 => "superseded",
+        5 => "cessationOfOperation",
+        6 => "certificateHold",
+        8 => "removeFromCRL",
+        9 => "privilegeWithdrawn",
+        10 => "aACompromise",
+        _ => "desconhecido",
+    }
+}
@@ -0,0 +1,146 @@
+//! on_demand.rs — Checks sob demanda
+//!
+//! `POST /api/checks/run` (ver `api`) roda na hora, fora do ciclo, os checks
+//! de um target (ou de todos) em cada probe que o monitora, com os mesmos
+//! checks, orçamento de pacotes e origem do scheduler do probe. Serve para o
+//! suporte confirmar "ainda está fora?" sem esperar o próximo ciclo. As
+//! medições só voltam na resposta: não são gravadas, não entram no consenso
+//! e não aparecem no snapshot nem no `/ws/live`. Só uma execução roda por
+//! vez, para que pedidos repetidos não multipliquem o tráfego dos checks.
+
+use crate::check::CheckRegistry;
+use crate::types::{ConnectivityMetric, Probe, Target};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Checks de um probe, como o scheduler dele os roda.
+struct ProbeChecks {
+    probe: Probe,
+    targets: Vec<Target>,
+    checks: Arc<CheckRegistry>,
+    /// Prazo dos checks (o orçamento do ciclo do probe).
+    budget: Duration,
+}
+
+/// Quais targets rodar.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub target_id: Option<i32>,
+    /// Nome ou endereço do target.
+    pub target: Option<String>,
+    pub probe_id: Option<i32>,
+}
+
+impl Selection {
+    fn matches(&self, target: &Target) -> bool {
+        self.target_id.is_none_or(|id| target.id == id)
+            && self.target.as_ref().is_none_or(|wanted| {
+                target.name == *wanted
+                    || wanted
+                        .parse::<std::net::IpAddr>()
+                        .is_ok_and(|address| address == target.address)
+            })
+    }
+}
+
+/// Resultado de um probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeRun {
+    pub probe_id: i32,
+    pub location: String,
+    pub metrics: Vec<ConnectivityMetric>,
+    /// Targets cujo check estourou o prazo.
+    pub aborted_target_ids: Vec<i32>,
+}
+
+/// Resposta de uma execução.
+#[derive(Debug, Clone, Serialize)]
+pub struct OnDemandRun {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    pub probes: Vec<ProbeRun>,
+}
+
+#[derive(Debug)]
+pub enum OnDemandError {
+    /// Nenhum probe monitora um target com a seleção pedida.
+    NoMatch,
+    /// Outra execução ainda está em andamento.
+    Busy,
+}
+
+/// Checks de todos os probes do monitor, registrados na subida dos
+/// schedulers.
+#[derive(Default)]
+pub struct OnDemandChecks {
+    probes: Vec<ProbeChecks>,
+    running: Mutex<()>,
+}
+
+impl OnDemandChecks {
+    pub fn add(
+        &mut self,
+        probe: Probe,
+        targets: Vec<Target>,
+        checks: Arc<CheckRegistry>,
+        budget: Duration,
+    ) {
+        self.probes.push(ProbeChecks {
+            probe,
+            targets,
+            checks,
+            budget,
+        });
+    }
+
+    /// Roda os checks selecionados, um probe por vez: com sockets ICMP sem
+    /// privilégio, o kernel troca o identificador dos pings, e dois probes
+    /// pingando o mesmo endereço ao mesmo tempo disputariam a resposta.
+    pub async fn run(&self, selection: &Selection) -> Result<OnDemandRun, OnDemandError> {
+        let pending: Vec<(&ProbeChecks, Vec<Target>)> = self
+            .probes
+            .iter()
+            .filter(|p| selection.probe_id.is_none_or(|id| p.probe.id == id))
+            .map(|p| {
+                let targets = p
+                    .targets
+                    .iter()
+                    .filter(|t| selection.matches(t))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (p, targets)
+            })
+            .filter(|(_, targets)| !targets.is_empty())
+            .collect();
+        if pending.is_empty() {
+            return Err(OnDemandError::NoMatch);
+        }
+        let _running = self.running.try_lock().map_err(|_| OnDemandError::Busy)?;
+
+        let started_at = Utc::now();
+        let started = Instant::now();
+        let mut probes = Vec::with_capacity(pending.len());
+        for (p, targets) in pending {
+            let deadline = tokio::time::Instant::now() + p.budget;
+            // Ciclo fictício 0, como nas medições sem internet
+            let cycle = p
+                .checks
+                .run_all(&targets, &p.probe, 0, false, Some(deadline))
+                .await;
+            probes.push(ProbeRun {
+                probe_id: p.probe.id,
+                location: p.probe.location.clone(),
+                metrics: cycle.metrics,
+                aborted_target_ids: cycle.aborted.iter().map(|t| t.id).collect(),
+            });
+        }
+        Ok(OnDemandRun {
+            started_at,
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+            probes,
+        })
+    }
+}
@@ -1,37 +1,78 @@
+use crate::clock::Clock;
 use crate::types::OutageEvent;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
 
-/// Gerencia início/fim de outages, calcula duração e monta detalhes.
+/// Gerencia início/fim do outage de consenso, calcula duração e idade pelo
+/// relógio injetado.
 #[derive(Debug, Clone)]
 pub struct OutageManager {
     current: Option<OutageEvent>,
+    clock: Arc<dyn Clock>,
 }
 
 impl OutageManager {
-    pub fn new() -> Self {
-        Self { current: None }
-    }
-
-    pub fn handle_cycle(
-        &mut self,
-        maybe_outage: Option<OutageEvent>,
-        timestamp: chrono::DateTime<Utc>,
-    ) -> Option<OutageEvent> {
-        match (&self.current, maybe_outage) {
-            (None, Some(event)) => {
-                self.current = Some(event.clone());
-                Some(event)
-            }
-            (Some(current), None) => {
-                let mut finished = current.clone();
-                finished.end_time = Some(timestamp);
-                finished.duration_seconds = finished
-                    .end_time
-                    .map(|end| (end - finished.start_time).num_seconds() as i32);
-                self.current = None;
-                Some(finished)
-            }
-            _ => None,
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            current: None,
+            clock,
         }
     }
+
+    /// Troca o relógio, mantendo o outage aberto.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Outage aberto, se houver.
+    pub fn current(&self) -> Option<&OutageEvent> {
+        self.current.as_ref()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut OutageEvent> {
+        self.current.as_mut()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Abre `event`, iniciado agora. Retorna `None` se já há um outage aberto.
+    pub fn open(&mut self, mut event: OutageEvent) -> Option<OutageEvent> {
+        if self.current.is_some() {
+            return None;
+        }
+        event.start_time = self.clock.now();
+        event.end_time = None;
+        event.duration_seconds = None;
+        self.current = Some(event.clone());
+        Some(event)
+    }
+
+    /// Retoma um outage deixado aberto por uma execução anterior, mantendo o
+    /// início original. Retorna `false` se já há um outage aberto.
+    pub fn resume(&mut self, event: OutageEvent) -> bool {
+        if self.current.is_some() {
+            return false;
+        }
+        self.current = Some(event);
+        true
+    }
+
+    /// Encerra o outage aberto em `end` (ou agora) e calcula a duração.
+    pub fn close(&mut self, end: Option<DateTime<Utc>>) -> Option<OutageEvent> {
+        let mut finished = self.current.take()?;
+        let end = end.unwrap_or_else(|| self.clock.now());
+        finished.end_time = Some(end);
+        finished.duration_seconds = Some((end - finished.start_time).num_seconds() as i32);
+        Some(finished)
+    }
+
+    /// Segundos desde o início do outage aberto.
+    pub fn age_secs(&self) -> Option<i64> {
+        self.current
+            .as_ref()
+            .map(|event| (self.clock.now() - event.start_time).num_seconds())
+    }
 }
@@ -0,0 +1,286 @@
+//! periodic_report.rs — Resumo periódico (diário, semanal ou mensal) nas
+//! notificações
+//!
+//! Com `[periodic_report]`, cada grupo de notificações recebe, na hora
+//! configurada, um resumo do período que acabou: uptime, outages, os targets
+//! com mais perda e a tendência de latência em relação ao período anterior.
+//! É o relatório para quem não vai escrever SQL. O mesmo resumo sai na linha
+//! de comando com `report summary`. Os períodos seguem o calendário local de
+//! `report_timezone`: o resumo mensal de março vai da meia-noite de 1º de
+//! março à de 1º de abril no fuso do cliente. Com `compare_uplinks`, o
+//! resumo traz também a comparação dos uplinks do site (`uplink_report.rs`).
+
+use crate::calendar::Zone;
+use crate::config::{PeriodicReportConfig, ReportPeriod};
+use crate::exporters::{Exporters, TargetLabel};
+use crate::storage::Storage;
+use crate::types::{OutageAnnotation, OutageEventType, TargetPeriodStats};
+use crate::uplink_report::{self, UplinkComparison};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Resumo de um período.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodicReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Targets com medições no período.
+    pub targets: usize,
+    pub samples: i64,
+    /// Medições `up` sobre o total; ausente sem medições.
+    pub uptime_percent: Option<f64>,
+    pub outages: OutageSummary,
+    /// Targets com maior perda média, do pior ao melhor (só os com perda).
+    pub worst_targets: Vec<TargetLoss>,
+    pub latency: LatencyTrend,
+    /// Anotações dos operadores nos eventos do período, em ordem cronológica
+    /// dos eventos.
+    pub annotations: Vec<OutageNote>,
+    /// Comparação dos uplinks (`compare_uplinks`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uplinks: Option<UplinkComparison>,
+}
+
+/// Eventos de `outage_events` que tocaram o período.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OutageSummary {
+    /// Outages de consenso.
+    pub count: usize,
+    /// Minutos de outage dentro do período (eventos cortados nas bordas).
+    pub minutes: f64,
+    /// Outages ainda abertos no fim do período.
+    pub open: usize,
+    pub partial: usize,
+    pub degradation: usize,
+}
+
+/// Anotação de operador num evento do período.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutageNote {
+    pub outage_id: i64,
+    pub event_type: OutageEventType,
+    pub start_time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub annotation: OutageAnnotation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetLoss {
+    pub target_id: i32,
+    pub name: Option<String>,
+    pub address: Option<IpAddr>,
+    pub loss_percent: f64,
+    pub uptime_percent: f64,
+}
+
+/// RTT médio do período e do período anterior de mesma duração.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyTrend {
+    pub avg_ms: Option<f64>,
+    pub previous_avg_ms: Option<f64>,
+    /// Variação em relação ao período anterior.
+    pub change_percent: Option<f64>,
+}
+
+/// Monta o resumo da janela `[from, to)`. Com `target_ids`, só os targets do
+/// grupo; `labels` dá nome e endereço aos piores targets.
+pub async fn build(
+    storage: &Storage,
+    target_ids: Option<&[i32]>,
+    labels: &HashMap<i32, TargetLabel>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    worst_targets: usize,
+) -> Result<PeriodicReport> {
+    let stats = storage
+        .list_target_period_stats(target_ids, from, to)
+        .await?;
+    let previous = storage
+        .list_target_period_stats(target_ids, from - (to - from), from)
+        .await?;
+    let events = storage
+        .list_outage_events_between(None, target_ids, from, to)
+        .await?;
+
+    let samples: i64 = stats.iter().map(|s| s.samples).sum();
+    let up_samples: i64 = stats.iter().map(|s| s.up_samples).sum();
+
+    let mut outages = OutageSummary::default();
+    let mut annotations = Vec::new();
+    for event in &events {
+        annotations.extend(
+            event
+                .annotations()
+                .into_iter()
+                .map(|annotation| OutageNote {
+                    outage_id: event.id,
+                    event_type: event.event_type,
+                    start_time: event.start_time,
+                    annotation,
+                }),
+        );
+        match event.event_type {
+            OutageEventType::Outage => {
+                let end = event.end_time.map_or(to, |end| end.min(to));
+                outages.count += 1;
+                outages.minutes +=
+                    (end - event.start_time.max(from)).num_seconds().max(0) as f64 / 60.0;
+                if event.end_time.is_none_or(|end| end > to) {
+                    outages.open += 1;
+                }
+            }
+            OutageEventType::Partial => outages.partial += 1,
+            OutageEventType::Degradation => outages.degradation += 1,
+        }
+    }
+
+    let mut worst: Vec<TargetLoss> = stats
+        .iter()
+        .filter_map(|s| {
+            let loss_percent = s.loss_percent.filter(|l| *l > 0.0)?;
+            let label = labels.get(&s.target_id);
+            Some(TargetLoss {
+                target_id: s.target_id,
+                name: label.map(|l| l.name.clone()),
+                address: label.map(|l| l.address),
+                loss_percent,
+                uptime_percent: percent(s.up_samples, s.samples).unwrap_or(0.0),
+            })
+        })
+        .collect();
+    worst.sort_by(|a, b| b.loss_percent.total_cmp(&a.loss_percent));
+    worst.truncate(worst_targets);
+
+    let avg_ms = average_rtt(&stats);
+    let previous_avg_ms = average_rtt(&previous);
+    let change_percent = match (avg_ms, previous_avg_ms) {
+        (Some(current), Some(previous)) if previous > 0.0 => {
+            Some((current - previous) / previous * 100.0)
+        }
+        _ => None,
+    };
+
+    Ok(PeriodicReport {
+        from,
+        to,
+        targets: stats.len(),
+        samples,
+        uptime_percent: percent(up_samples, samples),
+        outages,
+        worst_targets: worst,
+        latency: LatencyTrend {
+            avg_ms,
+            previous_avg_ms,
+            change_percent,
+        },
+        annotations,
+        uplinks: None,
+    })
+}
+
+fn percent(part: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| 100.0 * part as f64 / total as f64)
+}
+
+/// RTT médio ponderado pelas medições com resposta de cada target.
+fn average_rtt(stats: &[TargetPeriodStats]) -> Option<f64> {
+    let (sum, count) = stats
+        .iter()
+        .fold((0.0, 0i64), |(sum, count), s| match s.avg_rtt_ms {
+            Some(avg) => (sum + avg * s.rtt_samples as f64, count + s.rtt_samples),
+            None => (sum, count),
+        });
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// `hour`:00 local de `date`.
+fn at_hour(config: &PeriodicReportConfig, zone: Zone, date: NaiveDate) -> DateTime<Utc> {
+    zone.to_utc(
+        date.and_hms_opt(config.hour, 0, 0)
+            .expect("hour validada em [0, 23]"),
+    )
+}
+
+/// Próximo envio depois de `now`: `hour`:00 no fuso `zone`, todo dia, no
+/// `weekday` do resumo semanal ou no `day_of_month` do mensal.
+pub fn next_run(config: &PeriodicReportConfig, zone: Zone, now: DateTime<Utc>) -> NaiveDate {
+    let mut date = zone.local(now).date();
+    loop {
+        let on_day = match config.period {
+            ReportPeriod::Daily => true,
+            ReportPeriod::Weekly => date.weekday() == config.weekday,
+            ReportPeriod::Monthly => date.day() == config.day_of_month,
+        };
+        if on_day && at_hour(config, zone, date) > now {
+            return date;
+        }
+        date = date.succ_opt().expect("data fora do intervalo");
+    }
+}
+
+/// Loop de envio do resumo de um grupo: no horário, resume o período que
+/// acabou e o entrega pelas rotas de notificação do grupo.
+pub async fn run(
+    storage: Arc<Storage>,
+    config: PeriodicReportConfig,
+    exporters: Arc<Exporters>,
+    target_ids: Vec<i32>,
+    tenant: Option<String>,
+    zone: Zone,
+) {
+    loop {
+        let date = next_run(&config, zone, Utc::now());
+        let due = at_hour(&config, zone, date);
+        let wait = (due - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        // Pela data local, e não pela duração: com horário de verão um dia
+        // tem 23 ou 25 horas, e um mês, de 28 a 31 dias
+        let from = at_hour(&config, zone, config.period.start_date(date));
+        let mut report = match build(
+            &storage,
+            Some(&target_ids),
+            &exporters.labels,
+            from,
+            due,
+            config.worst_targets,
+        )
+        .await
+        {
+            Ok(report) => report,
+            Err(e) => {
+                error!("[REPORT] Falha ao montar o resumo periódico: {:?}", e);
+                continue;
+            }
+        };
+        if config.compare_uplinks {
+            match uplink_report::compare(&storage, None, Some(&target_ids), from, due).await {
+                Ok(comparison) => report.uplinks = Some(comparison),
+                Err(e) => error!("[REPORT] Falha ao comparar os uplinks: {:?}", e),
+            }
+        }
+        if let Some(notifier) = &exporters.notifier {
+            notifier
+                .send_report(
+                    config.period,
+                    zone,
+                    tenant.as_deref(),
+                    &report,
+                    &config.routes,
+                )
+                .await;
+            info!(
+                "[REPORT] Resumo {} de {} a {} ({}) enviado",
+                config.period.as_str(),
+                zone.local(from),
+                zone.local(due),
+                zone
+            );
+        }
+    }
+}
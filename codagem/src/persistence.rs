@@ -0,0 +1,715 @@
+//! persistence.rs — Pipeline de persistência desacoplado dos schedulers
+//!
+//! Os schedulers enviam métricas, status e outages para uma fila `mpsc`
+//! limitada; uma task dedicada drena a fila em lotes e grava no PostgreSQL.
+//! Um banco lento não atrasa o ciclo de medição enquanto houver espaço na
+//! fila; com a fila cheia o envio espera (backpressure), e cada espera é
+//! contada em [`PersistenceStats`], publicado no snapshot após cada lote.
+//! Sem conexão com o banco, o que não pôde ser gravado vai para o spool em
+//! disco (`spool.rs`) e é reenviado quando a conexão volta.
+
+use crate::baseline::AnomalyEvent;
+use crate::check::RawSample;
+use crate::config::PersistenceConfig;
+use crate::decisions::ConsensusDecision;
+use crate::host::HostSample;
+use crate::snapshot::SnapshotStore;
+use crate::spool::Spool;
+use crate::storage::StorageBackend;
+use crate::supervisor::SchedulerIncident;
+use crate::traceroute::Traceroute;
+use crate::types::{
+    ConnectivityMetric, CycleSummary, MetricKey, MetricStatus, MetricType, OutageEvent,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::timeout;
+use tracing::{debug, error, info, warn};
+
+/// Linhas por `INSERT` multi-linha (14 parâmetros por linha, bem abaixo do
+/// limite de 65535 parâmetros do PostgreSQL).
+pub const METRICS_PER_INSERT: usize = 500;
+
+/// Intervalo mínimo entre tentativas de reconexão ao banco.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+/// Tempo máximo de uma tentativa de reconexão.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Mensagem enviada à task de persistência (também o formato do spool).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum PersistMessage {
+    Metrics(Vec<ConnectivityMetric>),
+    TargetStatus {
+        target_id: i32,
+        status: MetricStatus,
+        /// Probe e instante da métrica que produziu o status (ausentes em
+        /// mensagens do spool anteriores à tabela `transitions`).
+        #[serde(default)]
+        probe_id: Option<i32>,
+        #[serde(default)]
+        timestamp: Option<DateTime<Utc>>,
+        /// Tipo da métrica, para achar o id dela entre as gravadas.
+        #[serde(default)]
+        metric_type: Option<MetricType>,
+    },
+    Outage(OutageEvent),
+    ShadowOutage {
+        name: String,
+        event: OutageEvent,
+    },
+    HostSample(HostSample),
+    Anomaly(AnomalyEvent),
+    SchedulerIncident(SchedulerIncident),
+    ConsensusDecision(ConsensusDecision),
+    Traceroute(Traceroute),
+    RawSamples(Vec<RawSample>),
+    CycleSummary(CycleSummary),
+}
+
+/// Contadores do pipeline, visíveis em `/api/snapshot`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PersistenceStats {
+    /// Mensagens aguardando gravação.
+    pub queue_depth: u64,
+    pub queue_capacity: u64,
+    /// Envios que encontraram a fila cheia e esperaram.
+    pub backpressure_waits: u64,
+    pub batches: u64,
+    pub metrics_written: u64,
+    /// Métricas que já estavam gravadas (reenvios do spool ou de lotes
+    /// regravados) e foram ignoradas.
+    pub metrics_duplicates: u64,
+    /// Gravações que falharam (métricas, status, amostras do host, anomalias,
+    /// incidentes de scheduler, decisões do consenso, traceroutes, amostras
+    /// brutas, resumos de ciclo ou outages).
+    pub write_failures: u64,
+    /// Duração do último lote em milissegundos.
+    pub last_batch_ms: f64,
+    pub database_connected: bool,
+    /// Mensagens guardadas no spool em disco por queda do banco.
+    pub spooled: u64,
+    /// Mensagens do spool já reenviadas ao banco.
+    pub replayed: u64,
+    /// Tamanho atual do spool em disco.
+    pub spool_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    enqueued: AtomicU64,
+    dequeued: AtomicU64,
+    backpressure_waits: AtomicU64,
+    batches: AtomicU64,
+    metrics_written: AtomicU64,
+    metrics_duplicates: AtomicU64,
+    write_failures: AtomicU64,
+    spooled: AtomicU64,
+    replayed: AtomicU64,
+}
+
+/// Lado de envio do pipeline (barato de clonar).
+#[derive(Debug, Clone)]
+pub struct Persistence {
+    tx: mpsc::Sender<PersistMessage>,
+    counters: Arc<Counters>,
+}
+
+impl Persistence {
+    /// Cria a fila e inicia a task de gravação.
+    pub fn spawn(
+        storage: Arc<dyn StorageBackend>,
+        config: &PersistenceConfig,
+        snapshot: Arc<SnapshotStore>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(config.queue_capacity);
+        let counters = Arc::new(Counters::default());
+        tokio::spawn(run_writer(
+            rx,
+            storage,
+            Arc::clone(&counters),
+            config.clone(),
+            snapshot,
+        ));
+        Self { tx, counters }
+    }
+
+    /// Enfileira uma mensagem; com a fila cheia, espera por espaço.
+    pub async fn send(&self, message: PersistMessage) {
+        self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+        let message = match self.tx.try_send(message) {
+            Ok(()) => return,
+            Err(TrySendError::Full(message)) => message,
+            Err(TrySendError::Closed(_)) => {
+                error!("[PERSISTENCE] Task de gravação encerrada; mensagem descartada");
+                return;
+            }
+        };
+        let waits = self
+            .counters
+            .backpressure_waits
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        warn!(
+            "[PERSISTENCE] Fila de gravação cheia ({} mensagens); aguardando o banco (esperas: {})",
+            self.tx.max_capacity(),
+            waits
+        );
+        if self.tx.send(message).await.is_err() {
+            error!("[PERSISTENCE] Task de gravação encerrada; mensagem descartada");
+        }
+    }
+
+    pub async fn metrics(&self, metrics: &[ConnectivityMetric]) {
+        if !metrics.is_empty() {
+            self.send(PersistMessage::Metrics(metrics.to_vec())).await;
+        }
+    }
+
+    /// Status do target segundo a métrica; só mudanças chegam ao banco.
+    pub async fn target_status(&self, metric: &ConnectivityMetric) {
+        self.send(PersistMessage::TargetStatus {
+            target_id: metric.target_id,
+            status: metric.status.clone(),
+            probe_id: Some(metric.probe_id),
+            timestamp: Some(metric.timestamp),
+            metric_type: Some(metric.metric_type.clone()),
+        })
+        .await;
+    }
+
+    pub async fn outage(&self, event: &OutageEvent) {
+        self.send(PersistMessage::Outage(event.clone())).await;
+    }
+
+    pub async fn host_sample(&self, sample: &HostSample) {
+        self.send(PersistMessage::HostSample(sample.clone())).await;
+    }
+
+    pub async fn anomaly(&self, event: &AnomalyEvent) {
+        self.send(PersistMessage::Anomaly(event.clone())).await;
+    }
+
+    pub async fn scheduler_incident(&self, incident: &SchedulerIncident) {
+        self.send(PersistMessage::SchedulerIncident(incident.clone()))
+            .await;
+    }
+
+    pub async fn consensus_decision(&self, decision: ConsensusDecision) {
+        self.send(PersistMessage::ConsensusDecision(decision)).await;
+    }
+
+    pub async fn traceroute(&self, traceroute: &Traceroute) {
+        self.send(PersistMessage::Traceroute(traceroute.clone()))
+            .await;
+    }
+
+    pub async fn cycle_summary(&self, summary: &CycleSummary) {
+        self.send(PersistMessage::CycleSummary(summary.clone()))
+            .await;
+    }
+
+    /// Tentativas individuais das métricas (com `raw_samples = true`).
+    pub async fn raw_samples(&self, metrics: &[ConnectivityMetric]) {
+        let samples: Vec<RawSample> = metrics.iter().flat_map(RawSample::of).collect();
+        if !samples.is_empty() {
+            self.send(PersistMessage::RawSamples(samples)).await;
+        }
+    }
+
+    pub async fn shadow_outage(&self, name: &str, event: &OutageEvent) {
+        self.send(PersistMessage::ShadowOutage {
+            name: name.to_string(),
+            event: event.clone(),
+        })
+        .await;
+    }
+}
+
+async fn run_writer(
+    mut rx: mpsc::Receiver<PersistMessage>,
+    storage: Arc<dyn StorageBackend>,
+    counters: Arc<Counters>,
+    config: PersistenceConfig,
+    snapshot: Arc<SnapshotStore>,
+) {
+    let spool = (config.spool_max_bytes > 0)
+        .then(|| Spool::new(&config.spool_path, config.spool_max_bytes));
+    let mut last_reconnect: Option<Instant> = None;
+    let mut last_batch_ms = 0.0;
+    let mut open_outages: HashMap<OutageKey, i64> = HashMap::new();
+    // Último status gravado por (target, probe): status repetidos não voltam
+    // ao banco
+    let mut written_statuses: HashMap<(i32, Option<i32>), MetricStatus> =
+        match storage.list_latest_transitions().await {
+            Ok(latest) => latest,
+            Err(e) => {
+                warn!(
+                    "[PERSISTENCE] Falha ao carregar as últimas transições: {:?}",
+                    e
+                );
+                HashMap::new()
+            }
+        };
+    let mut metric_ids = LatestMetricIds::new();
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        while batch.len() < config.batch_size {
+            match rx.try_recv() {
+                Ok(message) => batch.push(message),
+                Err(_) => break,
+            }
+        }
+        counters
+            .dequeued
+            .fetch_add(batch.len() as u64, Ordering::Relaxed);
+
+        let started = Instant::now();
+        // Conexão caída: tenta reabrir no máximo a cada RECONNECT_INTERVAL,
+        // para não segurar a fila esperando o banco
+        if !storage.is_connected()
+            && last_reconnect.is_none_or(|at| at.elapsed() >= RECONNECT_INTERVAL)
+        {
+            last_reconnect = Some(Instant::now());
+            match timeout(RECONNECT_TIMEOUT, storage.reconnect()).await {
+                Ok(Ok(())) => info!("[PERSISTENCE] Conexão com o banco restabelecida"),
+                Ok(Err(e)) => debug!("[PERSISTENCE] Banco ainda indisponível: {:?}", e),
+                Err(_) => debug!("[PERSISTENCE] Timeout ao reconectar ao banco"),
+            }
+        }
+
+        let mut pending = batch;
+        if storage.is_connected() {
+            // Reenvia primeiro o que ficou no spool durante a queda
+            if let Some(spool) = &spool
+                && !spool.is_empty()
+            {
+                match spool.take() {
+                    Ok(mut spooled) => {
+                        info!(
+                            "[PERSISTENCE] Reenviando {} mensagem(ns) do spool em disco",
+                            spooled.len()
+                        );
+                        counters
+                            .replayed
+                            .fetch_add(spooled.len() as u64, Ordering::Relaxed);
+                        spooled.append(&mut pending);
+                        pending = spooled;
+                    }
+                    Err(e) => warn!("[PERSISTENCE] Falha ao ler o spool em disco: {:?}", e),
+                }
+            }
+            pending = write_batch(
+                storage.as_ref(),
+                pending,
+                &counters,
+                &mut open_outages,
+                &mut written_statuses,
+                &mut metric_ids,
+            )
+            .await;
+        }
+        if !pending.is_empty() {
+            spool_pending(spool.as_ref(), &pending, &counters);
+        }
+        counters.batches.fetch_add(1, Ordering::Relaxed);
+        last_batch_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let enqueued = counters.enqueued.load(Ordering::Relaxed);
+        let dequeued = counters.dequeued.load(Ordering::Relaxed);
+        snapshot.set_persistence(PersistenceStats {
+            queue_depth: enqueued.saturating_sub(dequeued),
+            queue_capacity: config.queue_capacity as u64,
+            backpressure_waits: counters.backpressure_waits.load(Ordering::Relaxed),
+            batches: counters.batches.load(Ordering::Relaxed),
+            metrics_written: counters.metrics_written.load(Ordering::Relaxed),
+            metrics_duplicates: counters.metrics_duplicates.load(Ordering::Relaxed),
+            write_failures: counters.write_failures.load(Ordering::Relaxed),
+            last_batch_ms,
+            database_connected: storage.is_connected(),
+            spooled: counters.spooled.load(Ordering::Relaxed),
+            replayed: counters.replayed.load(Ordering::Relaxed),
+            spool_bytes: spool.as_ref().map_or(0, Spool::len_bytes),
+        });
+    }
+    debug!(
+        "[PERSISTENCE] Fila fechada; task de gravação encerrada (último lote: {:.1} ms)",
+        last_batch_ms
+    );
+}
+
+/// Identidade de um outage aberto (tipo, início, targets e probes), para
+/// ligar o encerramento à linha gravada na abertura.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct OutageKey {
+    event_type: &'static str,
+    start_time: DateTime<Utc>,
+    affected_targets: Vec<i32>,
+    affected_probes: Option<Vec<i32>>,
+}
+
+impl OutageKey {
+    fn of(event: &OutageEvent) -> Self {
+        Self {
+            event_type: event.event_type.as_str(),
+            start_time: event.start_time,
+            affected_targets: event.affected_targets.clone(),
+            affected_probes: event.affected_probes.clone(),
+        }
+    }
+}
+
+/// Guarda no spool o que não pôde ser gravado por falta de conexão.
+fn spool_pending(spool: Option<&Spool>, pending: &[PersistMessage], counters: &Counters) {
+    let written = match spool {
+        Some(spool) => match spool.append(pending) {
+            Ok(written) => written,
+            Err(e) => {
+                error!("[PERSISTENCE] Falha ao gravar no spool em disco: {:?}", e);
+                0
+            }
+        },
+        None => 0,
+    };
+    counters
+        .spooled
+        .fetch_add(written as u64, Ordering::Relaxed);
+    let dropped = pending.len() - written;
+    if dropped > 0 {
+        counters
+            .write_failures
+            .fetch_add(dropped as u64, Ordering::Relaxed);
+        error!(
+            "[PERSISTENCE] Banco indisponível: {} mensagem(ns) descartada(s) (spool desabilitado ou cheio)",
+            dropped
+        );
+    } else {
+        warn!(
+            "[PERSISTENCE] Banco indisponível: {} mensagem(ns) guardada(s) no spool em disco",
+            written
+        );
+    }
+}
+
+/// Status de um target segundo um probe, com o instante e o tipo da métrica
+/// que o produziu.
+type StatusUpdate = (MetricStatus, Option<DateTime<Utc>>, Option<MetricType>);
+
+/// Id da última métrica gravada de cada (probe, target, tipo), com o horário
+/// dela: a transição aponta para a métrica que a causou mesmo quando o status
+/// chega num lote seguinte ao da métrica.
+#[derive(Debug, Default)]
+struct LatestMetricIds(HashMap<(i32, i32, MetricType), (DateTime<Utc>, i64)>);
+
+impl LatestMetricIds {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(&mut self, key: MetricKey, id: i64) {
+        let entry = self
+            .0
+            .entry((key.probe_id, key.target_id, key.metric_type))
+            .or_insert((key.timestamp, id));
+        if key.timestamp >= entry.0 {
+            *entry = (key.timestamp, id);
+        }
+    }
+
+    fn get(&self, key: &MetricKey) -> Option<i64> {
+        self.0
+            .get(&(key.probe_id, key.target_id, key.metric_type.clone()))
+            .filter(|(timestamp, _)| *timestamp == key.timestamp)
+            .map(|&(_, id)| id)
+    }
+}
+
+/// Grava um lote: métricas em `INSERT`s multi-linha, status deduplicados
+/// por target e probe (vale o mais recente, e só se mudou desde a última
+/// gravação, com a transição em `transitions`) e outages na ordem de chegada. Retorna
+/// o que falhou por queda da conexão, para ir ao spool; as demais falhas são
+/// apenas contadas.
+async fn write_batch(
+    storage: &dyn StorageBackend,
+    batch: Vec<PersistMessage>,
+    counters: &Counters,
+    open_outages: &mut HashMap<OutageKey, i64>,
+    written_statuses: &mut HashMap<(i32, Option<i32>), MetricStatus>,
+    metric_ids: &mut LatestMetricIds,
+) -> Vec<PersistMessage> {
+    let mut metrics: Vec<ConnectivityMetric> = Vec::new();
+    let mut statuses: HashMap<(i32, Option<i32>), StatusUpdate> = HashMap::new();
+    let mut outages: Vec<(Option<String>, OutageEvent)> = Vec::new();
+    let mut host_samples: Vec<HostSample> = Vec::new();
+    let mut anomalies: Vec<AnomalyEvent> = Vec::new();
+    let mut incidents: Vec<SchedulerIncident> = Vec::new();
+    let mut decisions: Vec<ConsensusDecision> = Vec::new();
+    let mut traceroutes: Vec<Traceroute> = Vec::new();
+    let mut raw_samples: Vec<RawSample> = Vec::new();
+    let mut summaries: Vec<CycleSummary> = Vec::new();
+    for message in batch {
+        match message {
+            PersistMessage::Metrics(m) => metrics.extend(m),
+            PersistMessage::TargetStatus {
+                target_id,
+                status,
+                probe_id,
+                timestamp,
+                metric_type,
+            } => {
+                statuses.insert((target_id, probe_id), (status, timestamp, metric_type));
+            }
+            PersistMessage::Outage(event) => outages.push((None, event)),
+            PersistMessage::ShadowOutage { name, event } => outages.push((Some(name), event)),
+            PersistMessage::HostSample(sample) => host_samples.push(sample),
+            PersistMessage::Anomaly(event) => anomalies.push(event),
+            PersistMessage::SchedulerIncident(incident) => incidents.push(incident),
+            PersistMessage::ConsensusDecision(decision) => decisions.push(decision),
+            PersistMessage::Traceroute(traceroute) => traceroutes.push(traceroute),
+            PersistMessage::RawSamples(samples) => raw_samples.extend(samples),
+            PersistMessage::CycleSummary(summary) => summaries.push(summary),
+        }
+    }
+    let mut pending: Vec<PersistMessage> = Vec::new();
+
+    for chunk in metrics.chunks(METRICS_PER_INSERT) {
+        match storage.insert_connectivity_metrics(chunk).await {
+            Ok(inserted) => {
+                let written = inserted.len() as u64;
+                counters
+                    .metrics_written
+                    .fetch_add(written, Ordering::Relaxed);
+                counters.metrics_duplicates.fetch_add(
+                    (chunk.len() as u64).saturating_sub(written),
+                    Ordering::Relaxed,
+                );
+                for (key, id) in inserted {
+                    metric_ids.record(key, id);
+                }
+            }
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::Metrics(chunk.to_vec()));
+            }
+            Err(e) => {
+                // Regrava linha a linha para que uma métrica inválida não
+                // descarte o lote inteiro
+                warn!(
+                    "[PERSISTENCE] Falha no lote de {} métricas, gravando individualmente: {:?}",
+                    chunk.len(),
+                    e
+                );
+                for metric in chunk {
+                    match storage.insert_connectivity_metric(metric).await {
+                        Ok(Some(id)) => {
+                            counters.metrics_written.fetch_add(1, Ordering::Relaxed);
+                            metric_ids.record(MetricKey::of(metric), id);
+                        }
+                        Ok(None) => {
+                            counters.metrics_duplicates.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(_) if !storage.is_connected() => {
+                            pending.push(PersistMessage::Metrics(vec![metric.clone()]));
+                        }
+                        Err(e) => {
+                            counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                            error!(
+                                "[PERSISTENCE] Falha ao persistir métrica: {:?} (target_id: {})",
+                                e, metric.target_id
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for ((target_id, probe_id), (status, timestamp, metric_type)) in statuses {
+        let old = written_statuses.get(&(target_id, probe_id));
+        if old == Some(&status) {
+            continue;
+        }
+        let metric_id = match (probe_id, timestamp, &metric_type) {
+            (Some(probe_id), Some(timestamp), Some(metric_type)) => metric_ids.get(&MetricKey {
+                probe_id,
+                target_id,
+                metric_type: metric_type.clone(),
+                timestamp,
+            }),
+            _ => None,
+        };
+        match storage
+            .set_target_status(target_id, &status, old, probe_id, timestamp, metric_id)
+            .await
+        {
+            Ok(()) => {
+                written_statuses.insert((target_id, probe_id), status);
+            }
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::TargetStatus {
+                    target_id,
+                    status,
+                    probe_id,
+                    timestamp,
+                    metric_type,
+                });
+            }
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao atualizar status do target {}: {:?}",
+                    target_id, e
+                );
+            }
+        }
+    }
+
+    for sample in host_samples {
+        match storage.insert_host_sample(&sample).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => pending.push(PersistMessage::HostSample(sample)),
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir amostra do host (probe {}): {:?}",
+                    sample.probe_id, e
+                );
+            }
+        }
+    }
+
+    for event in anomalies {
+        match storage.insert_anomaly_event(&event).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => pending.push(PersistMessage::Anomaly(event)),
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir anomalia do target {}: {:?}",
+                    event.target_id, e
+                );
+            }
+        }
+    }
+
+    for incident in incidents {
+        match storage.insert_scheduler_incident(&incident).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::SchedulerIncident(incident))
+            }
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir incidente do scheduler (probe {}): {:?}",
+                    incident.probe_id, e
+                );
+            }
+        }
+    }
+
+    for decision in decisions {
+        match storage.insert_consensus_decision(&decision).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::ConsensusDecision(decision))
+            }
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir decisão do consenso (probe {}): {:?}",
+                    decision.probe_id, e
+                );
+            }
+        }
+    }
+
+    for traceroute in traceroutes {
+        match storage.insert_traceroute(&traceroute).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::Traceroute(traceroute))
+            }
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir traceroute (probe {}, target {}): {:?}",
+                    traceroute.probe_id, traceroute.target_id, e
+                );
+            }
+        }
+    }
+
+    for summary in summaries {
+        match storage.insert_cycle_summary(&summary).await {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => {
+                pending.push(PersistMessage::CycleSummary(summary))
+            }
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "[PERSISTENCE] Falha ao persistir resumo do ciclo (probe {}): {:?}",
+                    summary.probe_id, e
+                );
+            }
+        }
+    }
+
+    match storage.insert_raw_samples(&raw_samples).await {
+        Ok(_) => {}
+        Err(_) if !storage.is_connected() => pending.push(PersistMessage::RawSamples(raw_samples)),
+        Err(e) => {
+            counters.write_failures.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "[PERSISTENCE] Falha ao persistir {} amostra(s) bruta(s): {:?}",
+                raw_samples.len(),
+                e
+            );
+        }
+    }
+
+    for (shadow_name, mut event) in outages {
+        let result = match &shadow_name {
+            Some(name) => storage.insert_shadow_outage_event(name, &event).await,
+            None => {
+                // O consenso não conhece o id da linha que abriu: o
+                // encerramento herda o id gravado na abertura
+                let key = OutageKey::of(&event);
+                if event.id == 0
+                    && event.end_time.is_some()
+                    && let Some(id) = open_outages.remove(&key)
+                {
+                    event.id = id;
+                }
+                storage.insert_outage_event(&event).await.map(|id| {
+                    if event.end_time.is_none() {
+                        open_outages.insert(key, id);
+                    }
+                })
+            }
+        };
+        match result {
+            Ok(()) => {}
+            Err(_) if !storage.is_connected() => pending.push(match shadow_name {
+                Some(name) => PersistMessage::ShadowOutage { name, event },
+                None => PersistMessage::Outage(event),
+            }),
+            Err(e) => {
+                counters.write_failures.fetch_add(1, Ordering::Relaxed);
+                error!(
+                    "[PERSISTENCE] Falha ao persistir outage{}: {:?}",
+                    shadow_name
+                        .as_ref()
+                        .map_or(String::new(), |n| format!(" sombra '{}'", n)),
+                    e
+                );
+            }
+        }
+    }
+    pending
+}
@@ -2,13 +2,81 @@
 //!
 //! Implementação idiomática, funcional e auditável usando surge-ping.
 //! Compatível com types.rs moderno: usa ConnectivityMetric, MetricType granular, status robusto.
+//! Também expõe o check `ping` do framework de checks plugáveis ([`PingCheck`]).
+//!
+//! Pings sem resposta guardam o ICMP de erro recebido (tipo, código e quem o
+//! enviou) em `details.icmp_error`: um "admin prohibited" durante uma queda
+//! aponta direto para um firewall, onde o timeout sozinho não diz nada.
+//!
+//! Cada target pode fixar o tamanho do payload (`ping_payload_bytes`) e a
+//! classe DSCP (`dscp`) dos seus echo requests: comparando targets marcados
+//! e não marcados dá para ver se o provedor respeita o QoS, e payloads
+//! grandes revelam perda que depende do tamanho (fragmentação, MTU). Os
+//! parâmetros usados ficam em `details.ping` de cada métrica.
 
-use crate::types::{ConnectivityMetric, MetricStatus, MetricType, Probe, Target};
-use chrono::Utc;
-use std::net::IpAddr;
-use std::sync::Arc;
-use surge_ping::{Client, Config, PingIdentifier, PingSequence};
+use crate::check::{Attempt, Check, CheckKind, build_metric};
+use crate::config::SourceConfig;
+use crate::provenance;
+use crate::ratelimit::RateLimiter;
+use crate::source;
+use crate::types::{CheckError, ConnectivityMetric, MeasurementBackend, Probe, Target};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use surge_ping::{Client, Config, ICMP, IcmpPacket, PingIdentifier, PingSequence};
 use tokio::time::Duration;
+use tracing::{debug, warn};
+
+/// Payload padrão dos echo requests, em bytes.
+pub const DEFAULT_PAYLOAD_BYTES: usize = 32;
+
+/// Maior payload aceito: o surge-ping lê as respostas num buffer de 2048
+/// bytes, que também precisa comportar os cabeçalhos IP e ICMP.
+pub const MAX_PAYLOAD_BYTES: i32 = 2000;
+
+/// Tamanho e marcação DSCP dos echo requests de um target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PingMarking {
+    pub payload_bytes: usize,
+    pub dscp: u8,
+}
+
+impl Default for PingMarking {
+    fn default() -> Self {
+        Self {
+            payload_bytes: DEFAULT_PAYLOAD_BYTES,
+            dscp: 0,
+        }
+    }
+}
+
+impl PingMarking {
+    /// Parâmetros do target; os ausentes usam o padrão.
+    pub fn for_target(target: &Target) -> Self {
+        Self {
+            payload_bytes: target
+                .ping_payload_bytes
+                .map_or(DEFAULT_PAYLOAD_BYTES, |bytes| bytes.max(0) as usize),
+            dscp: target.dscp.map_or(0, |dscp| dscp.clamp(0, 63) as u8),
+        }
+    }
+}
+
+/// Valida `ping_payload_bytes` e `dscp` de um cadastro ou edição de target.
+pub fn validate_marking(payload_bytes: Option<i32>, dscp: Option<i16>) -> Result<(), String> {
+    if payload_bytes.is_some_and(|bytes| !(0..=MAX_PAYLOAD_BYTES).contains(&bytes)) {
+        return Err(format!(
+            "ping_payload_bytes: deve estar entre 0 e {}",
+            MAX_PAYLOAD_BYTES
+        ));
+    }
+    if dscp.is_some_and(|dscp| !(0..=63).contains(&dscp)) {
+        return Err("dscp: deve estar entre 0 e 63".into());
+    }
+    Ok(())
+}
 
 /// Executa pings concorrentes a múltiplos alvos, retornando métricas detalhadas.
 ///
@@ -23,6 +91,7 @@ use tokio::time::Duration;
 /// - `ping_count`: número de tentativas por alvo
 /// - `timeout_secs`: timeout por tentativa
 /// - `cycle_id`: ciclo de monitoramento
+/// - `limiter`: limite de pacotes por rede de destino
 ///
 /// # Retorno
 /// - `Vec<ConnectivityMetric>`: resultados detalhados por alvo
@@ -32,6 +101,7 @@ pub async fn ping_targets(
     ping_count: usize,
     timeout_secs: u64,
     cycle_id: i64,
+    limiter: Arc<RateLimiter>,
 ) -> Vec<ConnectivityMetric> {
     let config = Config::default();
     let client = Arc::new(Client::new(&config).expect("Falha ao criar Client surge-ping"));
@@ -41,68 +111,25 @@ pub async fn ping_targets(
     for (i, target) in targets.iter().cloned().enumerate() {
         let probe = probe.clone();
         let client = client.clone();
+        let limiter = Arc::clone(&limiter);
         let handle = tokio::spawn(async move {
-            let mut success = 0;
-            let mut total_time = 0.0;
-            let mut last_error = None;
-            let mut timeout_count = 0;
-
-            let metric_type = match target.address {
-                IpAddr::V4(_) => MetricType::PingIpv4,
-                IpAddr::V6(_) => MetricType::PingIpv6,
-            };
-
-            // Criação síncrona do pinger
-            let mut pinger = client.pinger(target.address, PingIdentifier(i as u16));
-            pinger.await.timeout(Duration::from_secs(timeout_secs));
-            let payload = [0u8; 32]; // Payload padrão de 32 bytes
-
-            for seq in 0..ping_count {
-                pinger = client.pinger(target.address, PingIdentifier(i as u16));
-                match pinger.await.ping(PingSequence(seq as u16), &payload).await {
-                    Ok((_reply, dur)) => {
-                        success += 1;
-                        total_time += dur.as_secs_f64() * 1000.0; // ms
-                    }
-                    Err(e) => {
-                        if e.to_string().contains("timeout") {
-                            timeout_count += 1;
-                            last_error = Some("timeout".to_string());
-                        } else {
-                            last_error = Some(e.to_string());
-                        }
-                    }
-                }
-            }
-
-            let status = if timeout_count == ping_count {
-                MetricStatus::Timeout
-            } else if success == ping_count {
-                MetricStatus::Up
-            } else if success > 0 {
-                MetricStatus::Degraded
-            } else {
-                MetricStatus::Down
-            };
-
-            let avg_time = if success > 0 {
-                Some(total_time / success as f64)
-            } else {
-                None
-            };
-
-            ConnectivityMetric {
-                id: 0, // será preenchido pelo banco
-                cycle_id,
-                probe_id: probe.id,
-                target_id: target.id,
-                timestamp: Utc::now(),
-                metric_type,
-                status,
-                response_time_ms: avg_time,
-                packet_loss_percent: Some(100 - ((success * 100) / ping_count) as i16),
-                error_message: last_error,
-            }
+            let mut metric = ping_target(
+                &client,
+                &target,
+                &probe,
+                PingIdentifier(i as u16),
+                ping_count,
+                timeout_secs,
+                &limiter,
+                PingMarking::default(),
+            )
+            .await;
+            metric.cycle_id = cycle_id;
+            metric.provenance = Some(provenance::resolve(
+                target.address,
+                MeasurementBackend::Native,
+            ));
+            metric
         });
         handles.push(handle);
     }
@@ -115,3 +142,402 @@ pub async fn ping_targets(
     }
     results
 }
+
+/// Executa `ping_count` pings a um único alvo usando um client compartilhado
+/// (já com o DSCP de `marking` aplicado ao socket).
+#[allow(clippy::too_many_arguments)]
+async fn ping_target(
+    client: &Client,
+    target: &Target,
+    probe: &Probe,
+    identifier: PingIdentifier,
+    ping_count: usize,
+    timeout_secs: u64,
+    limiter: &RateLimiter,
+    marking: PingMarking,
+) -> ConnectivityMetric {
+    let payload = vec![0u8; marking.payload_bytes];
+    let mut attempts = Vec::with_capacity(ping_count);
+    let mut icmp_error = None;
+
+    for seq in 0..ping_count {
+        limiter.acquire(target).await;
+        let mut pinger = client.pinger(target.address, identifier).await;
+        pinger.timeout(Duration::from_secs(timeout_secs));
+        let attempt = match pinger.ping(PingSequence(seq as u16), &payload).await {
+            // O surge-ping entrega também ICMPs de erro vindos do próprio destino
+            Ok((reply, dur)) => match IcmpError::from_reply(&reply) {
+                None => Attempt::Success(dur.as_secs_f64() * 1000.0), // ms
+                Some(error) => {
+                    let attempt = Attempt::Failure(error.check_error(), error.message());
+                    icmp_error = Some(error);
+                    attempt
+                }
+            },
+            Err(e) if e.to_string().contains("timeout") => Attempt::Timeout,
+            Err(e) => {
+                let message = e.to_string();
+                Attempt::Failure(CheckError::classify(&message), message)
+            }
+        };
+        attempts.push(attempt);
+    }
+
+    // ICMPs de erro de roteadores no caminho não chegam ao surge-ping (que só
+    // casa respostas pelo endereço do destino): sem nenhuma resposta, uma
+    // sonda extra lê a fila de erros do socket para saber o porquê
+    let answered = attempts.iter().any(|a| matches!(a, Attempt::Success(_)));
+    if icmp_error.is_none() && !answered {
+        limiter.acquire(target).await;
+        let address = target.address;
+        let timeout = Duration::from_secs(timeout_secs);
+        match tokio::task::spawn_blocking(move || capture_icmp_error(address, timeout)).await {
+            Ok(Ok(Some(error))) => {
+                for attempt in attempts.iter_mut() {
+                    *attempt = Attempt::Failure(error.check_error(), error.message());
+                }
+                icmp_error = Some(error);
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => debug!("Sem captura de ICMP de erro para {}: {}", address, e),
+            Err(e) => debug!("Captura de ICMP de erro para {} falhou: {}", address, e),
+        }
+    }
+
+    let mut details = serde_json::json!({ "ping": marking });
+    if let Some(error) = icmp_error {
+        details["icmp_error"] = serde_json::json!(error);
+    }
+    build_metric(target, probe, CheckKind::Ping, &attempts, Some(details))
+}
+
+/// ICMP de erro recebido em resposta a um echo request.
+#[derive(Debug, Clone, Serialize)]
+pub struct IcmpError {
+    #[serde(rename = "type")]
+    pub icmp_type: u8,
+    pub code: u8,
+    /// Nome legível do par tipo/código (ex: `admin_prohibited`).
+    pub name: &'static str,
+    /// Roteador ou host que enviou o ICMP.
+    pub from: Option<IpAddr>,
+}
+
+impl IcmpError {
+    pub fn new(v6: bool, icmp_type: u8, code: u8, from: Option<IpAddr>) -> Self {
+        let name = match (v6, icmp_type, code) {
+            (false, 3, 0) => "net_unreachable",
+            (false, 3, 1) => "host_unreachable",
+            (false, 3, 2) => "protocol_unreachable",
+            (false, 3, 3) => "port_unreachable",
+            (false, 3, 4) => "fragmentation_needed",
+            (false, 3, 9 | 10 | 13) => "admin_prohibited",
+            (false, 3, _) => "unreachable",
+            (false, 11, _) => "ttl_exceeded",
+            (true, 1, 0) => "no_route",
+            (true, 1, 1) => "admin_prohibited",
+            (true, 1, 3) => "address_unreachable",
+            (true, 1, 4) => "port_unreachable",
+            (true, 1, 5) => "source_policy_failed",
+            (true, 1, 6) => "reject_route",
+            (true, 1, _) => "unreachable",
+            (true, 2, _) => "packet_too_big",
+            (true, 3, _) => "ttl_exceeded",
+            _ => "other",
+        };
+        Self {
+            icmp_type,
+            code,
+            name,
+            from,
+        }
+    }
+
+    /// ICMP de erro numa resposta do surge-ping; `None` para echo reply.
+    fn from_reply(reply: &IcmpPacket) -> Option<Self> {
+        let (v6, icmp_type, code, from) = match reply {
+            IcmpPacket::V4(p) => (
+                false,
+                p.get_icmp_type().0,
+                p.get_icmp_code().0,
+                IpAddr::V4(p.get_source()),
+            ),
+            IcmpPacket::V6(p) => (
+                true,
+                p.get_icmpv6_type().0,
+                p.get_icmpv6_code().0,
+                IpAddr::V6(p.get_source()),
+            ),
+        };
+        let echo_reply = if v6 { 129 } else { 0 };
+        (icmp_type != echo_reply).then(|| Self::new(v6, icmp_type, code, Some(from)))
+    }
+
+    pub fn check_error(&self) -> CheckError {
+        match self.name {
+            "admin_prohibited" | "source_policy_failed" | "reject_route" => {
+                CheckError::AdminProhibited
+            }
+            "packet_too_big" | "other" => CheckError::Other,
+            _ => CheckError::Unreachable,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        let mut message = format!(
+            "ICMP {} (tipo {}, código {})",
+            self.name, self.icmp_type, self.code
+        );
+        if let Some(from) = self.from {
+            message.push_str(&format!(" de {}", from));
+        }
+        message
+    }
+}
+
+/// Envia um echo request por um socket ICMP não privilegiado com
+/// `IP_RECVERR`/`IPV6_RECVERR` e devolve o ICMP de erro que voltar, se houver
+/// (bloqueante). Exige `net.ipv4.ping_group_range` cobrindo o grupo do processo.
+#[cfg(target_os = "linux")]
+fn capture_icmp_error(address: IpAddr, timeout: Duration) -> std::io::Result<Option<IcmpError>> {
+    use crate::traceroute::{read_icmp_error, set_option};
+    use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+    use std::net::SocketAddr;
+    use std::os::fd::AsRawFd;
+    use std::time::Instant;
+
+    let (domain, protocol, level, recverr, echo_request) = match address {
+        IpAddr::V4(_) => (
+            Domain::IPV4,
+            Protocol::ICMPV4,
+            libc::SOL_IP,
+            libc::IP_RECVERR,
+            8u8,
+        ),
+        IpAddr::V6(_) => (
+            Domain::IPV6,
+            Protocol::ICMPV6,
+            libc::SOL_IPV6,
+            libc::IPV6_RECVERR,
+            128u8,
+        ),
+    };
+    let socket = Socket::new(domain, Type::DGRAM, Some(protocol))?;
+    let fd = socket.as_raw_fd();
+    set_option(fd, level, recverr, 1)?;
+    socket.connect(&SockAddr::from(SocketAddr::new(address, 0)))?;
+
+    // Identificador e checksum são preenchidos pelo kernel
+    let mut packet = [0u8; 16];
+    packet[0] = echo_request;
+    socket.send(&packet)?;
+
+    let started = Instant::now();
+    loop {
+        let left = timeout.saturating_sub(started.elapsed());
+        if left.is_zero() {
+            return Ok(None);
+        }
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN | libc::POLLERR,
+            revents: 0,
+        };
+        // SAFETY: um único `pollfd` válido durante a chamada.
+        let rc = unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as libc::c_int) };
+        if rc < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        if rc == 0 {
+            return Ok(None);
+        }
+        if let Some(icmp) = read_icmp_error(fd)? {
+            return Ok(Some(IcmpError::new(
+                icmp.v6,
+                icmp.icmp_type,
+                icmp.code,
+                icmp.from,
+            )));
+        }
+        if poll.revents & libc::POLLIN != 0 {
+            // O destino respondeu desta vez: não há erro a registrar
+            return Ok(None);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn capture_icmp_error(_address: IpAddr, _timeout: Duration) -> std::io::Result<Option<IcmpError>> {
+    Ok(None)
+}
+
+/// Check ICMP: `ping_count` echo requests por ciclo, com um client por
+/// família de endereço (ICMPv6 é opcional: hosts sem IPv6 seguem só com v4).
+/// Targets com DSCP usam clients próprios, um por família e classe, porque a
+/// marcação é do socket.
+pub struct PingCheck {
+    client_v4: Client,
+    client_v6: Option<Client>,
+    /// Client e DSCP efetivamente aplicado, por (IPv6?, DSCP pedido).
+    marked: Mutex<HashMap<(bool, u8), (Client, u8)>>,
+    /// Origem dos clients (`checks.sources.ping`).
+    source: SourceConfig,
+    ping_count: usize,
+    timeout_secs: u64,
+    limiter: Arc<RateLimiter>,
+}
+
+impl PingCheck {
+    pub fn new(
+        ping_count: usize,
+        timeout_secs: u64,
+        source: &SourceConfig,
+        limiter: Arc<RateLimiter>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            client_v4: Client::new(&client_config(ICMP::V4, source))?,
+            client_v6: Client::new(&client_config(ICMP::V6, source)).ok(),
+            marked: Mutex::new(HashMap::new()),
+            source: source.clone(),
+            ping_count,
+            timeout_secs,
+            limiter,
+        })
+    }
+}
+
+impl PingCheck {
+    /// Client para `address` com a classe `dscp`, e o DSCP que ele de fato
+    /// marca: se o socket recusar a marcação, o target segue sem ela (0).
+    fn client(&self, address: IpAddr, dscp: u8) -> (Client, u8) {
+        let v6 = address.is_ipv6() && self.client_v6.is_some();
+        let unmarked = match (v6, &self.client_v6) {
+            (true, Some(client)) => client,
+            _ => &self.client_v4,
+        };
+        if dscp == 0 {
+            return (unmarked.clone(), 0);
+        }
+        let mut marked = self.marked.lock().unwrap_or_else(|e| e.into_inner());
+        marked
+            .entry((v6, dscp))
+            .or_insert_with(|| {
+                let kind = if v6 { ICMP::V6 } else { ICMP::V4 };
+                let client = Client::new(&client_config(kind, &self.source))
+                    .and_then(|client| set_dscp(&client, v6, dscp).map(|_| client));
+                match client {
+                    Ok(client) => (client, dscp),
+                    Err(e) => {
+                        warn!(
+                            "⚠️ DSCP {} não aplicado aos pings {}: {}; seguindo sem marcação",
+                            dscp,
+                            if v6 { "IPv6" } else { "IPv4" },
+                            e
+                        );
+                        (unmarked.clone(), 0)
+                    }
+                }
+            })
+            .clone()
+    }
+}
+
+/// Um echo request a `address` por um socket ICMP com a origem `source`,
+/// como os do check `ping` (autoteste do `doctor`). Retorna o RTT; sockets
+/// recusados (sem `CAP_NET_RAW` nem `net.ipv4.ping_group_range`) ou sem
+/// resposta no `timeout` viram erro.
+pub async fn self_test(
+    source: &SourceConfig,
+    address: IpAddr,
+    timeout: Duration,
+) -> Result<Duration, String> {
+    let kind = if address.is_ipv6() {
+        ICMP::V6
+    } else {
+        ICMP::V4
+    };
+    let client = Client::new(&client_config(kind, source))
+        .map_err(|e| format!("socket ICMP recusado: {}", e))?;
+    let mut pinger = client.pinger(address, PingIdentifier(0)).await;
+    pinger.timeout(timeout);
+    let payload = [0u8; DEFAULT_PAYLOAD_BYTES];
+    match pinger.ping(PingSequence(0), &payload).await {
+        Ok((_, rtt)) => Ok(rtt),
+        Err(e) => Err(format!("sem resposta de {}: {}", address, e)),
+    }
+}
+
+/// Configuração de um client ICMP da família `kind` com a origem `source`.
+fn client_config(kind: ICMP, source: &SourceConfig) -> Config {
+    let family = match kind {
+        ICMP::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        ICMP::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let mut builder = Config::builder().kind(kind);
+    let address = source::bind_address(source, family);
+    if !address.is_unspecified() {
+        builder = builder.bind(SocketAddr::new(address, 0));
+    }
+    if let Some(interface) = &source.interface {
+        builder = builder.interface(interface);
+    }
+    builder.build()
+}
+
+/// Marca os pacotes do socket do client com a classe `dscp` (os 6 bits altos
+/// do TOS no IPv4 e da traffic class no IPv6).
+#[cfg(target_os = "linux")]
+fn set_dscp(client: &Client, v6: bool, dscp: u8) -> std::io::Result<()> {
+    use crate::traceroute::set_option;
+
+    let fd = client.get_socket().get_native_sock();
+    let tos = (dscp as libc::c_int) << 2;
+    if v6 {
+        set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, tos)
+    } else {
+        set_option(fd, libc::IPPROTO_IP, libc::IP_TOS, tos)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_dscp(_client: &Client, _v6: bool, _dscp: u8) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "marcação DSCP só é suportada no Linux",
+    ))
+}
+
+#[async_trait]
+impl Check for PingCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        // O client é compartilhado entre probes: o identificador ICMP combina
+        // probe e target para que respostas simultâneas não se misturem
+        let identifier = PingIdentifier(
+            (probe.id as u16)
+                .wrapping_mul(1024)
+                .wrapping_add(target.id as u16),
+        );
+        let mut marking = PingMarking::for_target(target);
+        let (client, dscp) = self.client(target.address, marking.dscp);
+        marking.dscp = dscp;
+        ping_target(
+            &client,
+            target,
+            probe,
+            identifier,
+            self.ping_count,
+            self.timeout_secs,
+            &self.limiter,
+            marking,
+        )
+        .await
+    }
+
+    fn packets(&self) -> usize {
+        self.ping_count
+    }
+}
@@ -0,0 +1,204 @@
+//! probe_lock.rs — Exclusividade de cada probe entre instâncias
+//!
+//! Duas instâncias iniciadas por engano com a mesma identidade de probe
+//! gravariam métricas em dobro e disputariam o status dos targets. Na
+//! inicialização cada probe toma um advisory lock de sessão do PostgreSQL
+//! (`pg_try_advisory_lock(namespace, probe_id)`) numa conexão dedicada,
+//! mantida pelo tempo de vida do processo. Se outra instância já detém o lock,
+//! a inicialização falha apontando quem o detém; com `--takeover`, a sessão
+//! da outra instância é encerrada e o lock é tomado.
+//!
+//! Se a conexão do lock cai, a task de supervisão reconecta e retoma os
+//! locks. Se outra instância os tomou nesse meio tempo, este processo é
+//! encerrado para não gravar em dobro.
+
+use crate::types::Probe;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tokio_postgres::{Client, NoTls};
+use tracing::{error, info, warn};
+
+/// Primeira chave dos advisory locks, separando-os de outros usos no banco.
+const LOCK_NAMESPACE: i32 = 0x4D52_0001;
+/// Intervalo de verificação da conexão do lock.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Tempo máximo de espera pela liberação do lock após um takeover.
+const TAKEOVER_WAIT: Duration = Duration::from_secs(10);
+
+/// Sessão que detém o lock de um probe.
+#[derive(Debug)]
+struct Holder {
+    pid: i32,
+    application_name: String,
+    client: String,
+    backend_start: Option<DateTime<Utc>>,
+}
+
+/// Toma o lock de todos os probes e mantém a conexão numa task de
+/// supervisão. Falha se algum probe já estiver com outra instância (a menos
+/// que `takeover` seja usado).
+pub async fn acquire(database_url: &str, probes: &[Probe], takeover: bool) -> Result<()> {
+    let client = open(database_url).await?;
+    for probe in probes {
+        lock_probe(&client, probe, takeover).await?;
+    }
+    info!("🔒 Lock de instância obtido para {} probe(s)", probes.len());
+    tokio::spawn(supervise(database_url.to_string(), probes.to_vec(), client));
+    Ok(())
+}
+
+async fn open(database_url: &str) -> Result<Client> {
+    let mut config: tokio_postgres::Config = database_url
+        .parse()
+        .context("URL do banco inválida para o lock de instância")?;
+    config.application_name(application_name());
+    let (client, connection) = config
+        .connect(NoTls)
+        .await
+        .context("Falha ao abrir a conexão do lock de instância")?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            warn!("[PROBE LOCK] Conexão do lock encerrada: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+/// Identifica esta instância em `pg_stat_activity`.
+fn application_name() -> String {
+    let host = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|_| "desconhecido".to_string());
+    format!("monitoramento_rede@{}:{}", host, std::process::id())
+}
+
+async fn try_lock(client: &Client, probe_id: i32) -> Result<bool> {
+    let row = client
+        .query_one(
+            "SELECT pg_try_advisory_lock($1, $2) AS locked",
+            &[&LOCK_NAMESPACE, &probe_id],
+        )
+        .await?;
+    Ok(row.get("locked"))
+}
+
+async fn holders(client: &Client, probe_id: i32) -> Result<Vec<Holder>> {
+    let rows = client
+        .query(
+            "SELECT a.pid, COALESCE(a.application_name, '') AS application_name,
+                    COALESCE(host(a.client_addr), 'local') AS client, a.backend_start
+             FROM pg_locks l JOIN pg_stat_activity a ON a.pid = l.pid
+             WHERE l.locktype = 'advisory' AND l.granted
+               AND l.classid::bigint = $1 AND l.objid::bigint = $2 AND l.objsubid = 2
+               AND l.pid <> pg_backend_pid()",
+            &[&i64::from(LOCK_NAMESPACE), &i64::from(probe_id)],
+        )
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| Holder {
+            pid: row.get("pid"),
+            application_name: row.get("application_name"),
+            client: row.get("client"),
+            backend_start: row.get("backend_start"),
+        })
+        .collect())
+}
+
+async fn lock_probe(client: &Client, probe: &Probe, takeover: bool) -> Result<()> {
+    if try_lock(client, probe.id).await? {
+        return Ok(());
+    }
+    let holders = holders(client, probe.id).await?;
+    let described: Vec<String> = holders
+        .iter()
+        .map(|h| {
+            format!(
+                "pid {} ({}, {}, desde {})",
+                h.pid,
+                h.application_name,
+                h.client,
+                h.backend_start.map_or("?".to_string(), |t| t.to_rfc3339())
+            )
+        })
+        .collect();
+    if !takeover {
+        bail!(
+            "Probe {} (id {}) já está em execução em outra instância: {}. \
+             Encerre a outra instância ou use --takeover",
+            probe.location,
+            probe.id,
+            described.join("; ")
+        );
+    }
+
+    warn!(
+        "[PROBE LOCK] Takeover do probe {} (id {}): encerrando {}",
+        probe.location,
+        probe.id,
+        described.join("; ")
+    );
+    for holder in &holders {
+        client
+            .execute("SELECT pg_terminate_backend($1)", &[&holder.pid])
+            .await
+            .with_context(|| format!("Falha ao encerrar a sessão {}", holder.pid))?;
+    }
+    // O lock só é liberado quando a sessão antiga termina de fato
+    let deadline = tokio::time::Instant::now() + TAKEOVER_WAIT;
+    while tokio::time::Instant::now() < deadline {
+        if try_lock(client, probe.id).await? {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    bail!(
+        "Takeover do probe {} (id {}) falhou: lock não foi liberado em {}s",
+        probe.location,
+        probe.id,
+        TAKEOVER_WAIT.as_secs()
+    )
+}
+
+/// Mantém a conexão do lock viva; ao perdê-la, reconecta e retoma os locks.
+async fn supervise(database_url: String, probes: Vec<Probe>, mut client: Client) {
+    let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if !client.is_closed() {
+            continue;
+        }
+        warn!("[PROBE LOCK] Conexão do lock perdida; tentando retomar os locks");
+        let reopened = match open(&database_url).await {
+            Ok(reopened) => reopened,
+            Err(e) => {
+                warn!("[PROBE LOCK] Falha ao reconectar: {:?}", e);
+                continue;
+            }
+        };
+        let mut retaken = true;
+        for probe in &probes {
+            match try_lock(&reopened, probe.id).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    error!(
+                        "[PROBE LOCK] Probe {} (id {}) foi tomado por outra instância; encerrando para não gravar em dobro",
+                        probe.location, probe.id
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    warn!("[PROBE LOCK] Falha ao retomar o lock: {:?}", e);
+                    retaken = false;
+                    break;
+                }
+            }
+        }
+        if !retaken {
+            continue;
+        }
+        info!("🔒 Locks de instância retomados");
+        client = reopened;
+    }
+}
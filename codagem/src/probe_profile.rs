@@ -0,0 +1,99 @@
+//! probe_profile.rs — Ajustes de agendamento e consenso por probe
+//!
+//! Um `[[probe_profiles]]` casa com um probe pelo `probe_id` ou pela
+//! `location` e substitui, só para ele, o intervalo do ciclo, as tentativas e
+//! o timeout dos checks, os parâmetros de consenso, o conjunto de targets e
+//! a origem dos pacotes.
+//! É o caso de um probe num link LTE de contingência, com franquia de dados,
+//! que não pode seguir o agendamento agressivo do probe do datacenter, ou de
+//! dois probes lógicos num host com dois uplinks, cada um preso à sua
+//! interface por `source`. Com tenants, o perfil é aplicado sobre os
+//! parâmetros do tenant.
+//!
+//! Antes do perfil vale a tabela `probe_targets`: um probe com targets
+//! atribuídos monitora só esses, em vez de todos os probes checarem todos os
+//! targets.
+
+use crate::config::{ChecksConfig, Config, ProbeProfileConfig};
+use crate::types::{Probe, Target};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Perfil do probe, se houver.
+pub fn find<'a>(config: &'a Config, probe: &Probe) -> Option<&'a ProbeProfileConfig> {
+    config.probe_profiles.iter().find(|profile| {
+        profile.probe_id == Some(probe.id) || profile.location.as_deref() == Some(&probe.location)
+    })
+}
+
+/// Configuração do grupo com os ajustes do perfil aplicados.
+pub fn apply(config: &Arc<Config>, profile: &ProbeProfileConfig) -> Arc<Config> {
+    Arc::new(Config {
+        cycle_interval_secs: profile
+            .cycle_interval_secs
+            .unwrap_or(config.cycle_interval_secs),
+        ping_count: profile.ping_count.unwrap_or(config.ping_count),
+        timeout_secs: profile.timeout_secs.unwrap_or(config.timeout_secs),
+        fail_threshold: profile.fail_threshold.unwrap_or(config.fail_threshold),
+        consensus: profile.consensus.unwrap_or(config.consensus),
+        checks: match &profile.source {
+            Some(source) => ChecksConfig {
+                source: source.clone(),
+                sources: Default::default(),
+                ..config.checks.clone()
+            },
+            None => config.checks.clone(),
+        },
+        ..Config::clone(config)
+    })
+}
+
+/// Targets atribuídos ao probe em `probe_targets` (todos, sem atribuição).
+pub fn assigned(
+    assignments: &HashMap<i32, Vec<i32>>,
+    probe: &Probe,
+    targets: &[Target],
+) -> Vec<Target> {
+    match assignments.get(&probe.id) {
+        Some(target_ids) => targets
+            .iter()
+            .filter(|t| target_ids.contains(&t.id))
+            .cloned()
+            .collect(),
+        None => targets.to_vec(),
+    }
+}
+
+fn matches(target: &Target, entry: &str) -> bool {
+    target.name == entry || target.address.to_string() == entry
+}
+
+/// Targets do grupo que o probe monitora: os de `targets` do perfil (por
+/// nome ou endereço), ou todos se a lista estiver vazia.
+pub fn select_targets(profile: &ProbeProfileConfig, targets: &[Target]) -> Vec<Target> {
+    if profile.targets.is_empty() {
+        return targets.to_vec();
+    }
+    targets
+        .iter()
+        .filter(|t| profile.targets.iter().any(|entry| matches(t, entry)))
+        .cloned()
+        .collect()
+}
+
+/// Avisa as entradas de `targets` dos perfis que não casam com nenhum
+/// target cadastrado.
+pub fn warn_unknown_targets(config: &Config, targets: &[Target]) {
+    for profile in &config.probe_profiles {
+        for entry in &profile.targets {
+            if !targets.iter().any(|t| matches(t, entry)) {
+                warn!(
+                    "⚠️ Perfil {}: target `{}` não encontrado",
+                    profile.label(),
+                    entry
+                );
+            }
+        }
+    }
+}
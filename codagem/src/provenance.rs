@@ -0,0 +1,119 @@
+//! provenance.rs — Proveniência das medições
+//!
+//! Cada métrica registra o backend que a produziu, o endereço de origem e a
+//! interface de saída usados para chegar ao target e a versão do binário.
+//! Numa frota mista (versões, hosts com várias interfaces, backends
+//! diferentes) isso mantém os dados interpretáveis meses depois.
+//!
+//! A origem é a que o kernel escolheria para o target: um socket UDP
+//! "conectado" (sem enviar pacotes) revela o endereço local da rota, e a
+//! interface é a que tem esse endereço (`getifaddrs`). Em checks com origem
+//! configurada (`source.rs`), o socket de teste recebe a mesma origem e a
+//! interface registrada é a configurada.
+
+use crate::config::SourceConfig;
+use crate::source;
+use crate::types::{MeasurementBackend, Provenance};
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Proveniência de uma medição ao `address` feita pelo `backend`.
+pub fn resolve(address: IpAddr, backend: MeasurementBackend) -> Provenance {
+    resolve_from(address, backend, &SourceConfig::default())
+}
+
+/// Como [`resolve`], para um check com origem configurada: o endereço local
+/// sai de um socket com a mesma origem e a interface é a configurada.
+pub fn resolve_from(
+    address: IpAddr,
+    backend: MeasurementBackend,
+    source: &SourceConfig,
+) -> Provenance {
+    let source_address = source_address(address, source);
+    Provenance {
+        backend,
+        source_address,
+        interface: source
+            .interface
+            .clone()
+            .or_else(|| source_address.and_then(interface_of)),
+        probe_version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+/// Endereço local que o kernel usaria para alcançar `address`.
+fn source_address(address: IpAddr, source: &SourceConfig) -> Option<IpAddr> {
+    let socket = source::std_udp_socket(source, address).ok()?;
+    // A porta é irrelevante: `connect` em UDP só resolve a rota
+    socket.connect(SocketAddr::new(address, 9)).ok()?;
+    let local = socket.local_addr().ok()?.ip();
+    (!local.is_unspecified()).then_some(local)
+}
+
+/// Nome da interface que possui o endereço `address`.
+fn interface_of(address: IpAddr) -> Option<String> {
+    interface_addresses()
+        .into_iter()
+        .find(|(_, entry)| *entry == address)
+        .map(|(name, _)| name)
+}
+
+/// Um endereço da família pedida na interface `interface`, preferindo no
+/// IPv6 os globais aos link-local.
+pub fn address_of(interface: &str, v6: bool) -> Option<IpAddr> {
+    let mut candidates: Vec<IpAddr> = interface_addresses()
+        .into_iter()
+        .filter(|(name, address)| name == interface && address.is_ipv6() == v6)
+        .map(|(_, address)| address)
+        .collect();
+    candidates.sort_by_key(|address| match address {
+        IpAddr::V6(v6) => v6.is_unicast_link_local(),
+        IpAddr::V4(_) => false,
+    });
+    candidates.into_iter().next()
+}
+
+/// Endereços IP das interfaces do host, com o nome de cada uma.
+fn interface_addresses() -> Vec<(String, IpAddr)> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: `addrs` recebe a lista alocada por `getifaddrs`, liberada abaixo
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        // SAFETY: `cursor` é um nó válido da lista retornada por `getifaddrs`
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+        if entry.ifa_addr.is_null() {
+            continue;
+        }
+        // SAFETY: `ifa_addr` não é nulo e a família determina o tipo concreto
+        let entry_address = unsafe {
+            match i32::from((*entry.ifa_addr).sa_family) {
+                libc::AF_INET => {
+                    let sin = &*(entry.ifa_addr as *const libc::sockaddr_in);
+                    Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                        sin.sin_addr.s_addr,
+                    ))))
+                }
+                libc::AF_INET6 => {
+                    let sin6 = &*(entry.ifa_addr as *const libc::sockaddr_in6);
+                    Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+                }
+                _ => None,
+            }
+        };
+        if let Some(address) = entry_address {
+            // SAFETY: `ifa_name` é uma string C terminada em nulo
+            let name = unsafe { CStr::from_ptr(entry.ifa_name) }
+                .to_string_lossy()
+                .into_owned();
+            found.push((name, address));
+        }
+    }
+    // SAFETY: `addrs` foi alocado por `getifaddrs` e não é mais usado
+    unsafe { libc::freeifaddrs(addrs) };
+    found
+}
@@ -0,0 +1,567 @@
+//! public_stats.rs — Estatísticas agregadas seguras para publicação
+//!
+//! Reduz as views horárias a disponibilidade e latência por provedor, por
+//! região e por país (GeoIP), sem IPs, nomes ou métricas brutas. Grupos pequenos demais são
+//! suprimidos (k-anonimato) — inclusive o agregado global — e disponibilidade,
+//! percentis e a contagem de targets podem receber ruído de Laplace
+//! (privacidade diferencial) antes de sair do processo. Com ruído, a contagem
+//! de amostras não é publicada: a contribuição de um target a ela não tem teto.
+//!
+//! O ruído é sorteado uma única vez por janela de agregação: a amostra deriva
+//! de um HMAC, com chave aleatória do processo, sobre a janela (hora de fim e
+//! duração), o grupo e a métrica. Repetir a consulta na mesma janela devolve o
+//! mesmo valor, então tirar a média de várias respostas não remove o ruído.
+
+use crate::types::{HourlyAvailability, LatencyPercentiles, Probe, Target};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Teto da latência de um target na média do grupo: limita a sensibilidade
+/// dos percentis ao ruído.
+const LATENCY_BOUND_MS: f64 = 2000.0;
+
+/// Valores ruidosos por grupo (disponibilidade, p50, p95 e targets): o
+/// `noise_epsilon` é dividido igualmente entre eles.
+const NOISY_VALUES: f64 = 4.0;
+
+/// De onde vem o provedor que nomeia os grupos de `by_provider`.
+#[derive(Debug, Clone, Copy)]
+pub enum ProviderSource<'a> {
+    /// O provedor do target (`monitoring_targets.provider`).
+    Target,
+    /// O provedor de acesso (ISP) do probe que mediu
+    /// (`monitoring_probes.provider`), para comparar os acessos. Exige linhas
+    /// por probe (`probe_id` preenchido), e `min_group_size` passa a contar
+    /// probes distintos: é a rede do probe que o grupo expõe.
+    Probe(&'a [Probe]),
+}
+
+/// Estatísticas agregadas de um grupo de targets.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregateStats {
+    pub targets: usize,
+    /// Amostras do grupo; `None` com ruído.
+    pub samples: Option<i64>,
+    pub availability_percent: Option<f64>,
+    pub latency_p50_ms: Option<f64>,
+    pub latency_p95_ms: Option<f64>,
+}
+
+/// Resumo público: global, por provedor, por região e por país.
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicSummary {
+    pub generated_at: DateTime<Utc>,
+    pub window_hours: i64,
+    /// Agregado de todos os targets; `None` se eles são menos que
+    /// `min_group_size` (contado em `suppressed_groups`).
+    pub overall: Option<AggregateStats>,
+    /// Por provedor do target ou, no modo frota, por ISP do probe.
+    pub by_provider: BTreeMap<String, AggregateStats>,
+    pub by_region: BTreeMap<String, AggregateStats>,
+    /// Por código ISO do país do target (localização GeoIP).
+    pub by_country: BTreeMap<String, AggregateStats>,
+    /// Grupos omitidos por terem menos de `min_group_size` targets (probes,
+    /// no modo frota).
+    pub suppressed_groups: usize,
+    pub noise_epsilon: Option<f64>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    targets: HashSet<i32>,
+    probes: HashSet<i32>,
+    /// Amostras `Up` e totais de cada target.
+    availability: HashMap<i32, (i64, i64)>,
+    p50: HashMap<i32, Vec<f64>>,
+    p95: HashMap<i32, Vec<f64>>,
+}
+
+/// Identifica a janela de agregação na qual o ruído é sorteado.
+struct NoiseWindow<'a> {
+    end: DateTime<Utc>,
+    hours: i64,
+    epsilon: f64,
+    group: &'a str,
+}
+
+impl NoiseWindow<'_> {
+    /// Soma a `value` o ruído de Laplace(0, sensitivity / ε) da `metric` nesta
+    /// janela.
+    fn perturb(&self, metric: &str, value: f64, sensitivity: f64) -> f64 {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(noise_key()).expect("HMAC aceita qualquer chave");
+        mac.update(
+            format!(
+                "{}|{}|{}|{}",
+                self.end.timestamp(),
+                self.hours,
+                self.group,
+                metric
+            )
+            .as_bytes(),
+        );
+        let digest = mac.finalize().into_bytes();
+        let bits = u64::from_be_bytes(digest[..8].try_into().expect("digest tem 32 bytes"));
+        value + laplace_noise(open_unit(bits), sensitivity / (self.epsilon / NOISY_VALUES))
+    }
+}
+
+/// Chave do HMAC do ruído, sorteada uma vez por processo.
+fn noise_key() -> &'static [u8; 32] {
+    static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    KEY.get_or_init(|| rand::rng().random())
+}
+
+/// Mapeia 64 bits aleatórios para o intervalo aberto (0, 1).
+fn open_unit(bits: u64) -> f64 {
+    // 52 bits + 0.5 cabem exatos na mantissa: nunca arredonda para 0 ou 1
+    ((bits >> 12) as f64 + 0.5) / (1u64 << 52) as f64
+}
+
+impl Accumulator {
+    fn finish(&self, noise: Option<&NoiseWindow>) -> AggregateStats {
+        let targets = self.targets.len().max(1) as f64;
+        // Média da disponibilidade de cada target: um target move o
+        // resultado em no máximo 100 / n, independente de quantas amostras tem
+        let per_target: Vec<f64> = self
+            .availability
+            .values()
+            .filter(|&&(_, samples)| samples > 0)
+            .map(|&(up, samples)| 100.0 * up as f64 / samples as f64)
+            .collect();
+        let availability = (!per_target.is_empty()).then(|| {
+            let value = per_target.iter().sum::<f64>() / per_target.len() as f64;
+            match noise {
+                Some(noise) => noise
+                    .perturb("availability", value, 100.0 / targets)
+                    .clamp(0.0, 100.0),
+                None => value,
+            }
+        });
+        // Média por target e depois entre targets, cada um limitado a
+        // LATENCY_BOUND_MS: um target move o resultado em no máximo
+        // LATENCY_BOUND_MS / n
+        let latency = |metric: &str, per_target: &HashMap<i32, Vec<f64>>| {
+            if per_target.is_empty() {
+                return None;
+            }
+            let value = per_target
+                .values()
+                .map(|values| {
+                    (values.iter().sum::<f64>() / values.len() as f64).min(LATENCY_BOUND_MS)
+                })
+                .sum::<f64>()
+                / per_target.len() as f64;
+            Some(match noise {
+                Some(noise) => noise
+                    .perturb(metric, value, LATENCY_BOUND_MS / targets)
+                    .clamp(0.0, LATENCY_BOUND_MS),
+                None => value,
+            })
+        };
+        let samples = self
+            .availability
+            .values()
+            .map(|&(_, samples)| samples)
+            .sum();
+        let (target_count, samples) = match noise {
+            // Um target a mais ou a menos muda a contagem em 1
+            Some(noise) => {
+                let value = noise.perturb("targets", self.targets.len() as f64, 1.0);
+                (value.round().max(0.0) as usize, None)
+            }
+            None => (self.targets.len(), Some(samples)),
+        };
+        AggregateStats {
+            targets: target_count,
+            samples,
+            availability_percent: availability,
+            latency_p50_ms: latency("p50", &self.p50),
+            latency_p95_ms: latency("p95", &self.p95),
+        }
+    }
+}
+
+/// Amostra de uma distribuição de Laplace(0, scale) pela inversa da CDF, com
+/// `u` uniforme no intervalo aberto (0, 1).
+fn laplace_noise(u: f64, scale: f64) -> f64 {
+    let centered = u - 0.5;
+    -scale * centered.signum() * (1.0 - 2.0 * centered.abs()).ln()
+}
+
+/// Monta o resumo público a partir das views agregadas.
+pub fn summarize(
+    providers: ProviderSource,
+    targets: &[Target],
+    availability: &[HourlyAvailability],
+    latency: &[LatencyPercentiles],
+    window_hours: i64,
+    min_group_size: usize,
+    noise_epsilon: Option<f64>,
+) -> PublicSummary {
+    summarize_at(
+        Utc::now(),
+        providers,
+        targets,
+        availability,
+        latency,
+        window_hours,
+        min_group_size,
+        noise_epsilon,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn summarize_at(
+    now: DateTime<Utc>,
+    providers: ProviderSource,
+    targets: &[Target],
+    availability: &[HourlyAvailability],
+    latency: &[LatencyPercentiles],
+    window_hours: i64,
+    min_group_size: usize,
+    noise_epsilon: Option<f64>,
+) -> PublicSummary {
+    let by_id: HashMap<i32, &Target> = targets.iter().map(|t| (t.id, t)).collect();
+    let probe_providers: HashMap<i32, &str> = match providers {
+        ProviderSource::Target => HashMap::new(),
+        ProviderSource::Probe(probes) => probes
+            .iter()
+            .filter_map(|p| Some((p.id, p.provider.as_deref()?)))
+            .collect(),
+    };
+    let provider_of = |probe_id: Option<i32>, target_id: i32| {
+        match providers {
+            ProviderSource::Target => by_id.get(&target_id).and_then(|t| t.provider.clone()),
+            ProviderSource::Probe(_) => probe_id
+                .and_then(|id| probe_providers.get(&id))
+                .map(|provider| provider.to_string()),
+        }
+        .unwrap_or_else(|| "desconhecido".to_string())
+    };
+    let region_of = |id: i32| {
+        by_id
+            .get(&id)
+            .and_then(|t| t.region.clone())
+            .unwrap_or_else(|| "desconhecida".to_string())
+    };
+    let country_of = |id: i32| {
+        by_id
+            .get(&id)
+            .and_then(|t| t.geo.country_code.clone())
+            .unwrap_or_else(|| "desconhecido".to_string())
+    };
+
+    let mut overall = Accumulator::default();
+    let mut groups: HashMap<String, Accumulator> = HashMap::new();
+    let mut regions: HashMap<String, Accumulator> = HashMap::new();
+    let mut countries: HashMap<String, Accumulator> = HashMap::new();
+
+    for row in availability
+        .iter()
+        .filter(|r| by_id.contains_key(&r.target_id))
+    {
+        for acc in [
+            &mut overall,
+            groups
+                .entry(provider_of(row.probe_id, row.target_id))
+                .or_default(),
+            regions.entry(region_of(row.target_id)).or_default(),
+            countries.entry(country_of(row.target_id)).or_default(),
+        ] {
+            acc.targets.insert(row.target_id);
+            acc.probes.extend(row.probe_id);
+            let entry = acc.availability.entry(row.target_id).or_default();
+            entry.0 += row.up_samples;
+            entry.1 += row.samples;
+        }
+    }
+    for row in latency.iter().filter(|r| by_id.contains_key(&r.target_id)) {
+        for acc in [
+            &mut overall,
+            groups
+                .entry(provider_of(row.probe_id, row.target_id))
+                .or_default(),
+            regions.entry(region_of(row.target_id)).or_default(),
+            countries.entry(country_of(row.target_id)).or_default(),
+        ] {
+            acc.targets.insert(row.target_id);
+            acc.probes.extend(row.probe_id);
+            if let Some(p50) = row.p50_ms {
+                acc.p50.entry(row.target_id).or_default().push(p50);
+            }
+            if let Some(p95) = row.p95_ms {
+                acc.p95.entry(row.target_id).or_default().push(p95);
+            }
+        }
+    }
+
+    // As views são horárias: a janela muda a cada hora cheia
+    let window_end = now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now);
+    let noise_for = |group: &'static str, name: &str| {
+        noise_epsilon
+            .filter(|epsilon| *epsilon > 0.0)
+            .map(|epsilon| (epsilon, format!("{}:{}", group, name)))
+    };
+    let finish = |group: &'static str, name: &str, acc: &Accumulator| {
+        let noise = noise_for(group, name);
+        acc.finish(
+            noise
+                .as_ref()
+                .map(|(epsilon, group)| NoiseWindow {
+                    end: window_end,
+                    hours: window_hours,
+                    epsilon: *epsilon,
+                    group,
+                })
+                .as_ref(),
+        )
+    };
+
+    let mut suppressed_groups = 0;
+    let mut keep = |acc: &Accumulator| {
+        let members = match providers {
+            ProviderSource::Target => acc.targets.len(),
+            ProviderSource::Probe(_) => acc.probes.len(),
+        };
+        let keep = members >= min_group_size;
+        if !keep {
+            suppressed_groups += 1;
+        }
+        keep
+    };
+    let overall = keep(&overall).then(|| finish("overall", "", &overall));
+    let mut finish_groups = |group: &'static str, groups: HashMap<String, Accumulator>| {
+        groups
+            .into_iter()
+            .filter(|(_, acc)| keep(acc))
+            .map(|(name, acc)| {
+                let stats = finish(group, &name, &acc);
+                (name, stats)
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let by_provider = finish_groups("provider", groups);
+    let by_region = finish_groups("region", regions);
+    let by_country = finish_groups("country", countries);
+
+    PublicSummary {
+        generated_at: now,
+        window_hours,
+        overall,
+        by_provider,
+        by_region,
+        by_country,
+        suppressed_groups,
+        noise_epsilon,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GeoLocation;
+    use chrono::TimeZone;
+
+    fn target(id: i32, provider: &str) -> Target {
+        Target {
+            id,
+            provider: Some(provider.to_string()),
+            region: Some("sul".to_string()),
+            ..Target::unregistered(
+                format!("t{}", id),
+                format!("10.0.0.{}", id).parse().unwrap(),
+            )
+        }
+    }
+
+    fn rows(targets: &[Target]) -> (Vec<HourlyAvailability>, Vec<LatencyPercentiles>) {
+        let hour = Utc.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+        let availability = targets
+            .iter()
+            .map(|t| HourlyAvailability {
+                probe_id: None,
+                target_id: t.id,
+                hour,
+                samples: 60,
+                up_samples: 57,
+                availability_percent: 95.0,
+            })
+            .collect();
+        let latency = targets
+            .iter()
+            .map(|t| LatencyPercentiles {
+                probe_id: None,
+                target_id: t.id,
+                hour,
+                avg_ms: Some(20.0),
+                p50_ms: Some(18.0),
+                p95_ms: Some(40.0),
+                p99_ms: None,
+            })
+            .collect();
+        (availability, latency)
+    }
+
+    #[test]
+    fn ruido_fixo_dentro_da_janela() {
+        // Muitos targets e epsilon alto: ruído pequeno, longe do clamp
+        let targets: Vec<_> = (1..=100).map(|id| target(id, "isp")).collect();
+        let (availability, latency) = rows(&targets);
+        let at = |minute| Utc.with_ymd_and_hms(2026, 1, 1, 11, minute, 0).unwrap();
+        let summary = |now| {
+            summarize_at(
+                now,
+                ProviderSource::Target,
+                &targets,
+                &availability,
+                &latency,
+                24,
+                3,
+                Some(30.0),
+            )
+            .by_provider["isp"]
+                .clone()
+        };
+        let (first, again) = (summary(at(5)), summary(at(55)));
+        assert_eq!(first.availability_percent, again.availability_percent);
+        assert_eq!(first.latency_p50_ms, again.latency_p50_ms);
+        assert_eq!(first.latency_p95_ms, again.latency_p95_ms);
+        // Percentis também recebem ruído
+        assert_ne!(first.latency_p50_ms, Some(18.0));
+        assert_ne!(first.latency_p95_ms, Some(40.0));
+        let next = summary(Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap());
+        assert_ne!(first.availability_percent, next.availability_percent);
+        assert_ne!(first.latency_p50_ms, next.latency_p50_ms);
+    }
+
+    #[test]
+    fn global_respeita_min_group_size() {
+        let targets = vec![target(1, "a"), target(2, "b")];
+        let (availability, latency) = rows(&targets);
+        let summary = summarize(
+            ProviderSource::Target,
+            &targets,
+            &availability,
+            &latency,
+            24,
+            3,
+            None,
+        );
+        assert!(summary.overall.is_none());
+        assert!(summary.by_provider.is_empty());
+        assert_eq!(summary.suppressed_groups, 5);
+
+        let summary = summarize(
+            ProviderSource::Target,
+            &targets,
+            &availability,
+            &latency,
+            24,
+            2,
+            None,
+        );
+        let overall = summary.overall.expect("grupo global com 2 targets");
+        assert_eq!(overall.availability_percent, Some(95.0));
+        assert_eq!(overall.latency_p50_ms, Some(18.0));
+    }
+
+    #[test]
+    fn disponibilidade_media_por_target() {
+        let targets = vec![target(1, "isp"), target(2, "isp")];
+        let (mut availability, latency) = rows(&targets);
+        // Um target com cem vezes mais amostras não domina o grupo
+        availability[0].up_samples = 60;
+        availability[1].samples = 6000;
+        availability[1].up_samples = 0;
+        let summary = |epsilon| {
+            summarize(
+                ProviderSource::Target,
+                &targets,
+                &availability,
+                &latency,
+                24,
+                2,
+                epsilon,
+            )
+            .by_provider["isp"]
+                .clone()
+        };
+        let exact = summary(None);
+        assert_eq!(exact.availability_percent, Some(50.0));
+        assert_eq!((exact.targets, exact.samples), (2, Some(6060)));
+        // Com ruído, a contagem de amostras não sai do processo
+        assert_eq!(summary(Some(1.0)).samples, None);
+    }
+
+    #[test]
+    fn frota_agrupa_pelo_isp_do_probe() {
+        let targets: Vec<_> = (1..=3).map(|id| target(id, "dns-publico")).collect();
+        let probe = |id: i32, provider: &str| Probe {
+            id,
+            location: format!("p{}", id),
+            ip_address: None,
+            provider: Some(provider.to_string()),
+            created_at: None,
+            geo: GeoLocation::default(),
+        };
+        let probes = [probe(1, "isp-a"), probe(2, "isp-b")];
+        let (mut availability, latency) = rows(&targets);
+        for row in &mut availability {
+            row.probe_id = Some(1);
+        }
+        // O probe do isp-b vê os mesmos targets com metade das respostas
+        availability.extend(
+            availability
+                .clone()
+                .into_iter()
+                .map(|row| HourlyAvailability {
+                    probe_id: Some(2),
+                    up_samples: 30,
+                    ..row
+                }),
+        );
+
+        let summary = |min_group_size| {
+            summarize(
+                ProviderSource::Probe(&probes),
+                &targets,
+                &availability,
+                &latency,
+                24,
+                min_group_size,
+                None,
+            )
+        };
+        // Cada ISP tem um probe só, e as linhas sem probe não formam grupo
+        let suppressed = summary(2);
+        assert!(suppressed.by_provider.is_empty());
+        assert!(suppressed.overall.is_some());
+
+        let summary = summary(1);
+        let providers: Vec<_> = summary.by_provider.keys().map(String::as_str).collect();
+        assert_eq!(providers, ["isp-a", "isp-b"]);
+        assert_eq!(
+            summary.by_provider["isp-a"].availability_percent,
+            Some(95.0)
+        );
+        assert_eq!(
+            summary.by_provider["isp-b"].availability_percent,
+            Some(50.0)
+        );
+    }
+
+    #[test]
+    fn amostra_uniforme_no_intervalo_aberto() {
+        for bits in [0, 1, u64::MAX / 2, u64::MAX] {
+            let u = open_unit(bits);
+            assert!(u > 0.0 && u < 1.0, "{} fora de (0, 1)", u);
+            assert!(laplace_noise(u, 1.0).is_finite());
+        }
+    }
+}
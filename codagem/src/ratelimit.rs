@@ -0,0 +1,146 @@
+//! ratelimit.rs — Limite de pacotes por segundo por rede de destino
+//!
+//! Com `[rate_limit]`, cada tentativa dos checks do ciclo (echo ICMP,
+//! connect TCP, requisição HTTP, consulta DNS, handshake TLS) e das
+//! verificações de conectividade do scheduler consome um pacote do
+//! orçamento da rede do target: o bloco `/24` (`/48` no IPv6) do endereço
+//! ou, com `scope = "asn"`, o ASN. O orçamento é um token bucket de
+//! `packets_per_second` com rajada de `burst`, compartilhado por todos os
+//! schedulers, e uma tentativa sem orçamento espera a sua vez em vez de ser
+//! descartada. O tempo de espera não entra na latência medida.
+
+use crate::config::{RateLimitConfig, RateLimitScope};
+use crate::types::Target;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Rede de destino que divide um orçamento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Network {
+    Prefix(IpAddr, u8),
+    Asn(i32),
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Network::Prefix(address, len) => write!(f, "{}/{}", address, len),
+            Network::Asn(asn) => write!(f, "AS{}", asn),
+        }
+    }
+}
+
+/// Orçamento de uma rede; `tokens` fica negativo com tentativas na fila.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets por rede de destino; sem configuração, não limita nada.
+pub struct RateLimiter {
+    config: Option<RateLimitConfig>,
+    buckets: Mutex<HashMap<Network, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: Option<&RateLimitConfig>) -> Self {
+        Self {
+            config: config.cloned(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserva um pacote para o target, esperando se a rede dele já gastou
+    /// o orçamento.
+    pub async fn acquire(&self, target: &Target) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        let burst = burst(config);
+        let wait = {
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let bucket = buckets.entry(network(config, target)).or_insert(Bucket {
+                tokens: burst,
+                updated: now,
+            });
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * config.packets_per_second).min(burst) - 1.0;
+            bucket.updated = now;
+            (bucket.tokens < 0.0).then(|| -bucket.tokens / config.packets_per_second)
+        };
+        if let Some(secs) = wait {
+            tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+        }
+    }
+
+    /// Loga o limite e avisa sobre redes cujos pacotes de um ciclo
+    /// (`packets_of` de cada target, somados os probes) não cabem em
+    /// `budget`, o tempo do ciclo reservado aos checks.
+    pub fn log_budget(
+        &self,
+        targets: &[Target],
+        packets_of: impl Fn(&Target) -> usize,
+        budget: Duration,
+    ) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        info!(
+            "🚦 Limite de taxa: {} pacote(s)/s por rede ({:?}, rajada de {})",
+            config.packets_per_second,
+            config.scope,
+            burst(config)
+        );
+        let mut packets: HashMap<Network, usize> = HashMap::new();
+        for target in targets {
+            *packets.entry(network(config, target)).or_default() += packets_of(target);
+        }
+        let mut crowded: Vec<(Network, usize)> = packets
+            .into_iter()
+            .filter(|(_, count)| {
+                (*count as f64 - burst(config)) / config.packets_per_second > budget.as_secs_f64()
+            })
+            .collect();
+        crowded.sort_by_key(|(network, _)| network.to_string());
+        for (network, count) in crowded {
+            warn!(
+                "⚠️ [RATE LIMIT] {}: {} pacote(s) por ciclo não cabem em {:.1}s a {} pacote(s)/s; os ciclos vão estourar",
+                network,
+                count,
+                budget.as_secs_f64(),
+                config.packets_per_second
+            );
+        }
+    }
+}
+
+fn burst(config: &RateLimitConfig) -> f64 {
+    config
+        .burst
+        .unwrap_or_else(|| config.packets_per_second.max(1.0))
+}
+
+/// Rede do target conforme o `scope`.
+fn network(config: &RateLimitConfig, target: &Target) -> Network {
+    if config.scope == RateLimitScope::Asn
+        && let Some(asn) = target.asn
+    {
+        return Network::Asn(asn);
+    }
+    match target.address {
+        IpAddr::V4(v4) => {
+            let len = config.ipv4_prefix_len.min(32);
+            let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+            Network::Prefix(IpAddr::V4((u32::from(v4) & mask).into()), len)
+        }
+        IpAddr::V6(v6) => {
+            let len = config.ipv6_prefix_len.min(128);
+            let mask = u128::MAX.checked_shl(128 - len as u32).unwrap_or(0);
+            Network::Prefix(IpAddr::V6((u128::from(v6) & mask).into()), len)
+        }
+    }
+}
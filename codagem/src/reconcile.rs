@@ -0,0 +1,138 @@
+//! reconcile.rs — Reconciliação dos outages deixados abertos por um restart
+//!
+//! Um processo que cai com um outage aberto deixa a linha em `outage_events`
+//! com `end_time` nulo; sem reconciliação ela ficaria órfã para sempre e o
+//! próximo processo abriria outro evento para a mesma queda, contando o
+//! downtime em dobro. Na inicialização, antes de os schedulers começarem, os
+//! eventos abertos de cada probe são carregados e:
+//!
+//! - eventos abertos sobrepostos do mesmo tipo (e do mesmo target, nos
+//!   eventos por target) são incorporados ao mais antigo;
+//! - se a última métrica do probe é mais recente que
+//!   `outage_resume_max_gap_secs`, o evento é retomado no `ConsensusState` e
+//!   será mantido ou encerrado pelos ciclos seguintes;
+//! - caso contrário não há como saber quando a queda terminou, e o evento é
+//!   encerrado na última métrica registrada (`details.closed_on_restart`).
+
+use crate::config::Config;
+use crate::consensus::ConsensusState;
+use crate::snapshot::SnapshotStore;
+use crate::storage::Storage;
+use crate::types::{OutageEvent, OutageEventType, Probe, Target};
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Resultado da reconciliação de um probe.
+#[derive(Debug, Default)]
+pub struct Reconciled {
+    pub resumed: usize,
+    pub merged: usize,
+    pub closed: usize,
+}
+
+/// Reconcilia os outages abertos do probe com o seu estado de consenso. Com
+/// `scope` (modo tenant: os targets do grupo), só os eventos que afetam targets do grupo
+/// são considerados; os dos demais tenants ficam para os consensos deles.
+pub async fn resume_open_outages(
+    storage: &Storage,
+    config: &Config,
+    probe: &Probe,
+    consensus: &mut ConsensusState,
+    snapshot: &SnapshotStore,
+    scope: Option<&[Target]>,
+) -> Result<Reconciled> {
+    let mut report = Reconciled::default();
+    let mut open = storage.list_open_outage_events(probe.id).await?;
+    if let Some(targets) = scope {
+        open.retain(|e| {
+            e.affected_targets
+                .iter()
+                .any(|id| targets.iter().any(|t| t.id == *id))
+        });
+    }
+    if open.is_empty() {
+        return Ok(report);
+    }
+    let last_seen = storage.last_metric_time(probe.id).await?;
+    let fresh = last_seen.is_some_and(|t| {
+        (Utc::now() - t).num_seconds() <= config.outage_resume_max_gap_secs as i64
+    });
+
+    // Outages de consenso formam um único grupo; os demais, um por target
+    let mut groups: BTreeMap<(&'static str, Option<i32>), Vec<OutageEvent>> = BTreeMap::new();
+    for event in open {
+        let target = match event.event_type {
+            OutageEventType::Outage => None,
+            _ => event.affected_targets.first().copied(),
+        };
+        groups
+            .entry((event.event_type.as_str(), target))
+            .or_default()
+            .push(event);
+    }
+
+    for (_, mut events) in groups {
+        // Já ordenados por início: o mais antigo absorve os demais
+        let mut kept = events.remove(0);
+        if !events.is_empty() {
+            let merged_ids: Vec<i64> = events.iter().map(|e| e.id).collect();
+            for event in &events {
+                for target in &event.affected_targets {
+                    if !kept.affected_targets.contains(target) {
+                        kept.affected_targets.push(*target);
+                    }
+                }
+            }
+            kept.affected_targets.sort_unstable();
+            if kept.event_type == OutageEventType::Outage {
+                kept.consensus_level = Some(kept.affected_targets.len() as i32);
+            }
+            set_detail(&mut kept, "merged_ids", json!(merged_ids));
+            storage.merge_outage_events(&kept, &merged_ids).await?;
+            info!(
+                "[RECONCILE {}] {} {} aberto(s) sobreposto(s) incorporado(s) ao evento {}",
+                probe.location,
+                merged_ids.len(),
+                kept.event_type.as_str(),
+                kept.id
+            );
+            report.merged += merged_ids.len();
+        }
+
+        if fresh {
+            let incident = (kept.event_type == OutageEventType::Outage).then(|| kept.clone());
+            if consensus.resume(kept) {
+                if let Some(incident) = incident {
+                    snapshot.resume_incident(&incident);
+                }
+                report.resumed += 1;
+            }
+            continue;
+        }
+
+        let end = last_seen.map_or(kept.start_time, |t| t.max(kept.start_time));
+        kept.end_time = Some(end);
+        kept.duration_seconds = Some((end - kept.start_time).num_seconds() as i32);
+        set_detail(&mut kept, "closed_on_restart", json!(true));
+        storage.insert_outage_event(&kept).await?;
+        info!(
+            "[RECONCILE {}] {} {} encerrado na última métrica ({}); probe sem métricas recentes",
+            probe.location,
+            kept.event_type.as_str(),
+            kept.id,
+            end.to_rfc3339()
+        );
+        report.closed += 1;
+    }
+    Ok(report)
+}
+
+fn set_detail(event: &mut OutageEvent, key: &str, value: serde_json::Value) {
+    let details = event.details.get_or_insert_with(|| json!({}));
+    if let Some(map) = details.as_object_mut() {
+        map.insert(key.to_string(), value);
+    }
+}
@@ -0,0 +1,226 @@
+//! registration.rs — Cadastro de targets a partir de entradas livres
+//!
+//! Compartilhado por CLI (`targets add`), API (`POST /api/targets`),
+//! configuração (`[[targets]]`) e inventário (`[inventory]`, que só monta os
+//! targets com `prepare`): faz o parsing das entradas
+//! (`target_address`), aplica o template escolhido e insere via
+//! `Storage::insert_target`. Todas as entradas são validadas antes de
+//! qualquer inserção.
+
+use crate::ping;
+use crate::storage::Storage;
+use crate::target_address::{self, TargetAddress};
+use crate::templates::{TargetTemplate, TemplateSet};
+use crate::types::{GeoLocation, Target};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use thiserror::Error;
+use tracing::info;
+
+/// Pedido de cadastro. Campos explícitos têm precedência sobre o template.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Registration {
+    /// IPs, `host:port`, URLs ou blocos CIDR.
+    pub inputs: Vec<String>,
+    /// Nome base; ausente = a própria entrada.
+    pub name: Option<String>,
+    pub template: Option<String>,
+    pub asn: Option<i32>,
+    pub provider: Option<String>,
+    pub region: Option<String>,
+    /// Tags somadas às do template.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Thresholds de degradação (sobrescrevem os do template).
+    pub degraded_rtt_ms: Option<f64>,
+    pub degraded_loss_percent: Option<i16>,
+    /// Payload e DSCP dos pings (sobrescrevem os do template).
+    pub ping_payload_bytes: Option<i32>,
+    pub dscp: Option<i16>,
+    /// Peso no consenso (sobrescreve o do template).
+    pub weight: Option<f64>,
+    /// Tenant (cliente) dono dos targets.
+    pub tenant: Option<String>,
+}
+
+/// Resultado por endereço cadastrado.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredTarget {
+    pub input: String,
+    pub address: IpAddr,
+    /// `None` quando o endereço já estava cadastrado.
+    pub id: Option<i32>,
+}
+
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    /// Entrada inválida (deve virar erro do usuário, ex: HTTP 400).
+    #[error("{0}")]
+    Invalid(String),
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+/// Valida e cadastra os targets do pedido.
+pub async fn register(
+    storage: &Storage,
+    templates: &TemplateSet,
+    request: &Registration,
+) -> Result<Vec<RegisteredTarget>, RegistrationError> {
+    let prepared = prepare(templates, request).await?;
+    let mut registered = Vec::with_capacity(prepared.len());
+    for (input, target) in prepared {
+        let id = storage.insert_target(&target).await?;
+        info!(
+            "🎯 Target cadastrado: {} -> {} (tipo: {}, template: {:?}, id: {:?})",
+            input, target.address, target.type_, target.template, id
+        );
+        registered.push(RegisteredTarget {
+            input,
+            address: target.address,
+            id,
+        });
+    }
+    Ok(registered)
+}
+
+/// Valida o pedido e monta os targets (com `id = 0`), sem inseri-los; cada
+/// um acompanha a entrada que o originou.
+pub async fn prepare(
+    templates: &TemplateSet,
+    request: &Registration,
+) -> Result<Vec<(String, Target)>, RegistrationError> {
+    let template: Option<&TargetTemplate> = match &request.template {
+        Some(name) => Some(templates.get(name).ok_or_else(|| {
+            RegistrationError::Invalid(format!(
+                "template desconhecido: `{}` (disponíveis: {})",
+                name,
+                templates.names().join(", ")
+            ))
+        })?),
+        None => None,
+    };
+
+    if let Some(weight) = request.weight.or_else(|| template.and_then(|t| t.weight))
+        && !(weight.is_finite() && weight > 0.0)
+    {
+        return Err(RegistrationError::Invalid(format!(
+            "weight: deve ser maior que zero (recebido {})",
+            weight
+        )));
+    }
+    let payload = request
+        .ping_payload_bytes
+        .or_else(|| template.and_then(|t| t.ping_payload_bytes));
+    let dscp = request.dscp.or_else(|| template.and_then(|t| t.dscp));
+    if let Err(message) = ping::validate_marking(payload, dscp) {
+        return Err(RegistrationError::Invalid(message));
+    }
+
+    let mut parsed: Vec<(String, TargetAddress)> = Vec::new();
+    for input in &request.inputs {
+        let addresses = target_address::parse_many(input, target_address::DEFAULT_MAX_EXPANSION)
+            .map_err(|e| RegistrationError::Invalid(format!("{}: {}", input, e)))?;
+        parsed.extend(addresses.into_iter().map(|a| (input.clone(), a)));
+    }
+    if parsed.is_empty() {
+        return Err(RegistrationError::Invalid(
+            "nenhuma entrada informada".into(),
+        ));
+    }
+
+    // Resolve nomes antes de inserir, para que o lote falhe por inteiro
+    let mut resolved: Vec<(String, TargetAddress, IpAddr)> = Vec::with_capacity(parsed.len());
+    for (input, address) in parsed {
+        let ip = match (address.ip(), address.hostname()) {
+            (Some(ip), _) => ip,
+            (None, Some(hostname)) => resolve(hostname, address.port()).await.ok_or_else(|| {
+                RegistrationError::Invalid(format!(
+                    "{}: não foi possível resolver `{}`",
+                    input, hostname
+                ))
+            })?,
+            (None, None) => unreachable!("endereço sem IP nem nome"),
+        };
+        resolved.push((input, address, ip));
+    }
+
+    let multiple = resolved.len() > 1;
+    Ok(resolved
+        .into_iter()
+        .map(|(input, address, ip)| {
+            let name = match &request.name {
+                Some(name) if multiple => format!("{} ({})", name, ip),
+                Some(name) => name.clone(),
+                None => address.to_string(),
+            };
+            let target = build_target(name, ip, &address, template, request);
+            (input, target)
+        })
+        .collect())
+}
+
+/// Monta o target aplicando template e campos explícitos.
+pub fn build_target(
+    name: String,
+    ip: IpAddr,
+    address: &TargetAddress,
+    template: Option<&TargetTemplate>,
+    request: &Registration,
+) -> Target {
+    let type_ = match template {
+        Some(t) => match ip {
+            IpAddr::V4(_) => format!("{}_ipv4", t.check),
+            IpAddr::V6(_) => format!("{}_ipv6", t.check),
+        },
+        None => address.check_type(ip),
+    };
+    let mut tags: Vec<String> = template.map(|t| t.tags.clone()).unwrap_or_default();
+    for tag in &request.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    Target {
+        id: 0,
+        name,
+        address: ip,
+        asn: request.asn,
+        provider: request
+            .provider
+            .clone()
+            .or_else(|| template.and_then(|t| t.provider.clone())),
+        type_,
+        region: request
+            .region
+            .clone()
+            .or_else(|| template.and_then(|t| t.region.clone())),
+        created_at: None,
+        template: template.map(|t| t.name.clone()),
+        tags,
+        check_interval_secs: template.and_then(|t| t.check_interval_secs),
+        fail_threshold: template.and_then(|t| t.fail_threshold),
+        weight: request.weight.or_else(|| template.and_then(|t| t.weight)),
+        degraded_rtt_ms: request
+            .degraded_rtt_ms
+            .or_else(|| template.and_then(|t| t.degraded_rtt_ms)),
+        degraded_loss_percent: request
+            .degraded_loss_percent
+            .or_else(|| template.and_then(|t| t.degraded_loss_percent)),
+        ping_payload_bytes: request
+            .ping_payload_bytes
+            .or_else(|| template.and_then(|t| t.ping_payload_bytes)),
+        dscp: request.dscp.or_else(|| template.and_then(|t| t.dscp)),
+        geo: GeoLocation::default(),
+        tenant: request.tenant.clone(),
+        enabled: true,
+    }
+}
+
+async fn resolve(hostname: &str, port: Option<u16>) -> Option<IpAddr> {
+    tokio::net::lookup_host((hostname, port.unwrap_or(0)))
+        .await
+        .ok()?
+        .next()
+        .map(|addr| addr.ip())
+}
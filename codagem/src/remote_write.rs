@@ -0,0 +1,182 @@
+//! remote_write.rs — Sink Prometheus remote-write
+//!
+//! Envia as métricas de cada ciclo para um endpoint remote-write (Mimir,
+//! Thanos Receive, VictoriaMetrics, Prometheus com o receiver habilitado),
+//! para probes atrás de NAT que não podem ser coletados por scrape. O corpo é
+//! um `WriteRequest` em protobuf (codificado aqui, sem gerador) comprimido
+//! com snappy, conforme o protocolo 1.0. Como no sink Influx, falhas apenas
+//! são logadas.
+//!
+//! Cada métrica vira até três séries (`<prefixo>_up`,
+//! `<prefixo>_response_time_ms`, `<prefixo>_packet_loss_percent`) com os
+//! rótulos `probe`, `probe_id`, `target_id`, `metric_type`, `target_name` e
+//! `target_address`, além dos rótulos fixos de `labels`. A causa da falha
+//! (`error_kind`) fica de fora: um rótulo que muda a cada falha quebraria a
+//! série `up` do target em várias.
+
+use crate::config::RemoteWriteConfig;
+use crate::exporters::TargetLabel;
+use crate::types::{ConnectivityMetric, MetricStatus};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Cliente de escrita remote-write.
+#[derive(Debug, Clone)]
+pub struct RemoteWriteSink {
+    client: reqwest::Client,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    metric_prefix: String,
+    labels: BTreeMap<String, String>,
+}
+
+/// Série com uma única amostra.
+struct Series {
+    labels: Vec<(String, String)>,
+    value: f64,
+    timestamp_ms: i64,
+}
+
+impl RemoteWriteSink {
+    pub fn new(config: &RemoteWriteConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP do sink remote-write")?;
+        Ok(Self {
+            client,
+            url: config.url.clone(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            metric_prefix: config.metric_prefix.clone(),
+            labels: config.labels.clone(),
+        })
+    }
+
+    /// Séries de um ciclo, com os rótulos ordenados por nome (exigência do
+    /// protocolo).
+    fn series(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Vec<Series> {
+        let mut series = Vec::with_capacity(metrics.len() * 3);
+        for metric in metrics {
+            let mut common = self.labels.clone();
+            common.insert("probe".into(), location.to_string());
+            common.insert("probe_id".into(), metric.probe_id.to_string());
+            common.insert("target_id".into(), metric.target_id.to_string());
+            common.insert("metric_type".into(), metric.metric_type.to_string());
+            if let Some(label) = labels.get(&metric.target_id) {
+                common.insert("target_name".into(), label.name.clone());
+                common.insert("target_address".into(), label.address.to_string());
+            }
+            let timestamp_ms = metric.timestamp.timestamp_millis();
+
+            let mut push = |name: &str, value: f64| {
+                let mut labels = common.clone();
+                labels.insert(
+                    "__name__".into(),
+                    format!("{}_{}", self.metric_prefix, name),
+                );
+                series.push(Series {
+                    labels: labels.into_iter().collect(),
+                    value,
+                    timestamp_ms,
+                });
+            };
+            push("up", f64::from(u8::from(metric.status == MetricStatus::Up)));
+            if let Some(rtt) = metric.response_time_ms {
+                push("response_time_ms", rtt);
+            }
+            if let Some(loss) = metric.packet_loss_percent {
+                push("packet_loss_percent", f64::from(loss));
+            }
+        }
+        series
+    }
+
+    /// Envia as métricas de um ciclo ao endpoint remote-write.
+    pub async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&encode_write_request(
+                &self.series(metrics, labels, location),
+            ))
+            .context("Falha ao comprimir o WriteRequest")?;
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Encoding", "snappy")
+            .header("Content-Type", "application/x-protobuf")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+            .body(body);
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+        let response = request
+            .send()
+            .await
+            .context("Falha ao enviar métricas ao remote-write")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Remote-write respondeu {}: {}", status, body);
+        }
+        Ok(())
+    }
+}
+
+/// `WriteRequest { repeated TimeSeries timeseries = 1; }`, com
+/// `TimeSeries { repeated Label labels = 1; repeated Sample samples = 2; }`,
+/// `Label { string name = 1; string value = 2; }` e
+/// `Sample { double value = 1; int64 timestamp = 2; }`.
+fn encode_write_request(series: &[Series]) -> Vec<u8> {
+    let mut request = Vec::new();
+    for s in series {
+        let mut timeseries = Vec::new();
+        for (name, value) in &s.labels {
+            let mut label = Vec::new();
+            put_bytes(&mut label, 1, name.as_bytes());
+            put_bytes(&mut label, 2, value.as_bytes());
+            put_bytes(&mut timeseries, 1, &label);
+        }
+        let mut sample = Vec::new();
+        put_key(&mut sample, 1, 1);
+        sample.extend_from_slice(&s.value.to_le_bytes());
+        put_key(&mut sample, 2, 0);
+        put_varint(&mut sample, s.timestamp_ms as u64);
+        put_bytes(&mut timeseries, 2, &sample);
+        put_bytes(&mut request, 1, &timeseries);
+    }
+    request
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(buf, (u64::from(field) << 3) | u64::from(wire_type));
+}
+
+/// Campo length-delimited (strings e mensagens aninhadas).
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(buf, field, 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
@@ -0,0 +1,504 @@
+//! replay.rs — Simulação do consenso sobre o histórico (`monitoramento_rede replay`)
+//!
+//! Relê do banco as métricas de cada probe, ciclo a ciclo, e as passa por
+//! `ConsensusState` duas vezes: com os parâmetros atuais (globais, do tenant e
+//! do perfil do probe) e com os alternativos da linha de comando
+//! (`--fail-threshold`, `--consensus`, `--outage-recovery-cycles`,
+//! `--fail-percent`). O resultado compara quantos outages cada cenário teria
+//! detectado, e com que duração, com os gravados em `outage_events`, para
+//! ajustar os parâmetros com dados reais em vez de no chute. Nada é gravado.
+//!
+//! Como no scheduler, um target fora do ciclo (intervalo próprio) entra com o
+//! último resultado conhecido, e as medições de diagnóstico ficam de fora.
+
+use crate::cli::ReplayArgs;
+use crate::clock::ManualClock;
+use crate::config::Config;
+use crate::consensus::ConsensusState;
+use crate::probe_profile;
+use crate::storage::Storage;
+use crate::tenant;
+use crate::types::{ConnectivityMetric, OutageEvent, OutageEventType, Probe, Target};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Métricas lidas do banco por consulta.
+const PAGE_SIZE: i64 = 10_000;
+
+/// Parâmetros do consenso de um cenário.
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Params {
+    fail_threshold: usize,
+    consensus: usize,
+    outage_recovery_cycles: usize,
+    fail_percent: Option<f64>,
+}
+
+impl Params {
+    fn of(config: &Config) -> Self {
+        Self {
+            fail_threshold: config.fail_threshold,
+            consensus: config.consensus,
+            outage_recovery_cycles: config.outage_recovery_cycles,
+            fail_percent: config.consensus_fail_percent,
+        }
+    }
+
+    /// Parâmetros de `config` com os da linha de comando por cima.
+    fn alternative(config: &Config, args: &ReplayArgs) -> Self {
+        Self {
+            fail_threshold: args.fail_threshold.unwrap_or(config.fail_threshold),
+            consensus: args.consensus.unwrap_or(config.consensus),
+            outage_recovery_cycles: args
+                .outage_recovery_cycles
+                .unwrap_or(config.outage_recovery_cycles),
+            fail_percent: args.fail_percent.or(config.consensus_fail_percent),
+        }
+    }
+
+    fn state(
+        &self,
+        config: &Config,
+        probe_id: i32,
+        targets: &[Target],
+        clock: &Arc<ManualClock>,
+    ) -> Result<ConsensusState> {
+        let config = Config {
+            fail_threshold: self.fail_threshold,
+            consensus: self.consensus,
+            outage_recovery_cycles: self.outage_recovery_cycles,
+            consensus_fail_percent: self.fail_percent,
+            ..config.clone()
+        };
+        Ok(ConsensusState::from_config(&config, probe_id, targets)
+            .map_err(anyhow::Error::msg)?
+            .with_clock(Arc::clone(clock) as _))
+    }
+}
+
+/// Outages de um cenário (ou os gravados).
+#[derive(Debug, Clone, Default, Serialize)]
+struct Outcome {
+    outages: usize,
+    /// Ainda abertos no fim da janela (a duração conta até lá).
+    open: usize,
+    total_minutes: f64,
+    median_secs: Option<i64>,
+    max_secs: Option<i64>,
+    #[serde(skip)]
+    durations: Vec<i64>,
+}
+
+impl Outcome {
+    fn add(&mut self, start: DateTime<Utc>, end: Option<DateTime<Utc>>, to: DateTime<Utc>) {
+        let secs = (end.unwrap_or(to).min(to) - start).num_seconds().max(0);
+        self.outages += 1;
+        if end.is_none_or(|end| end > to) {
+            self.open += 1;
+        }
+        self.durations.push(secs);
+    }
+
+    fn merge(&mut self, other: &Outcome) {
+        self.outages += other.outages;
+        self.open += other.open;
+        self.durations.extend(&other.durations);
+    }
+
+    fn finish(mut self) -> Self {
+        self.durations.sort_unstable();
+        self.total_minutes = self.durations.iter().sum::<i64>() as f64 / 60.0;
+        self.median_secs = self.durations.get(self.durations.len() / 2).copied();
+        self.max_secs = self.durations.last().copied();
+        self
+    }
+}
+
+/// Outages simulados de um cenário, ou por que os parâmetros não valem para
+/// os targets do scheduler (ex: `consensus` acima do peso total).
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Simulated {
+    Outcome(Outcome),
+    Invalid { error: String },
+}
+
+/// Resultado de um scheduler (probe, e tenant se houver).
+#[derive(Debug, Serialize)]
+struct StreamReport {
+    probe: String,
+    probe_id: i32,
+    targets: usize,
+    cycles: usize,
+    recorded: Outcome,
+    current: Simulated,
+    alternative: Simulated,
+}
+
+#[derive(Debug, Serialize)]
+struct ReplayReport {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    current: Params,
+    alternative: Params,
+    streams: Vec<StreamReport>,
+    totals: Totals,
+}
+
+#[derive(Debug, Serialize)]
+struct Totals {
+    cycles: usize,
+    recorded: Outcome,
+    current: Outcome,
+    alternative: Outcome,
+}
+
+/// Scheduler simulado: o probe com os targets e a configuração do grupo.
+struct Stream {
+    label: String,
+    probe: Probe,
+    config: Arc<Config>,
+    targets: Vec<Target>,
+}
+
+/// Roda a simulação e imprime a comparação.
+pub async fn run(storage: &Storage, config: &Arc<Config>, args: ReplayArgs) -> Result<()> {
+    let to = args.to.unwrap_or_else(Utc::now);
+    let from = args.from.unwrap_or(to - Duration::hours(24));
+    if from >= to {
+        bail!("--from deve ser anterior a --to");
+    }
+    for (flag, value) in [
+        ("--fail-threshold", args.fail_threshold),
+        ("--consensus", args.consensus),
+        ("--outage-recovery-cycles", args.outage_recovery_cycles),
+    ] {
+        if value == Some(0) {
+            bail!("{} deve ser maior que zero", flag);
+        }
+    }
+    if args
+        .fail_percent
+        .is_some_and(|percent| !(percent > 0.0 && percent <= 100.0))
+    {
+        bail!("--fail-percent deve estar em (0, 100]");
+    }
+    let all_probes = storage.list_probes().await?;
+    let probes: Vec<Probe> = if args.probes.is_empty() {
+        all_probes
+    } else {
+        args.probes
+            .iter()
+            .map(|input| {
+                all_probes
+                    .iter()
+                    .find(|p| p.id.to_string() == *input || p.location == *input)
+                    .cloned()
+                    .with_context(|| format!("probe não encontrado: `{}`", input))
+            })
+            .collect::<Result<_>>()?
+    };
+    let targets = storage.list_targets().await?;
+    let assignments = storage.list_probe_targets().await?;
+    let groups = tenant::partition(config, &targets);
+
+    let mut streams_by_probe: HashMap<i32, Vec<Stream>> = HashMap::new();
+    for group in &groups {
+        for probe in &probes {
+            let assigned = probe_profile::assigned(&assignments, probe, &group.targets);
+            let (probe_config, probe_targets) = match probe_profile::find(config, probe) {
+                Some(profile) => (
+                    probe_profile::apply(&group.config, profile),
+                    probe_profile::select_targets(profile, &assigned),
+                ),
+                None => (Arc::clone(&group.config), assigned),
+            };
+            if probe_targets.is_empty() {
+                continue;
+            }
+            streams_by_probe.entry(probe.id).or_default().push(Stream {
+                label: group.probe(probe).location,
+                probe: probe.clone(),
+                config: probe_config,
+                targets: probe_targets,
+            });
+        }
+    }
+
+    let recorded_events = storage
+        .list_outage_events_between(None, None, from, to)
+        .await?;
+    let mut reports = Vec::new();
+    for probe in &probes {
+        let Some(streams) = streams_by_probe.remove(&probe.id) else {
+            continue;
+        };
+        let cycles = load_cycles(storage, probe.id, from, to).await?;
+        if cycles.is_empty() {
+            continue;
+        }
+        for stream in streams {
+            reports.push(replay_stream(
+                &stream,
+                &cycles,
+                &recorded_events,
+                &args,
+                from,
+                to,
+            ));
+        }
+    }
+
+    let mut totals = Totals {
+        cycles: 0,
+        recorded: Outcome::default(),
+        current: Outcome::default(),
+        alternative: Outcome::default(),
+    };
+    for report in &reports {
+        totals.cycles += report.cycles;
+        totals.recorded.merge(&report.recorded);
+        if let Simulated::Outcome(outcome) = &report.current {
+            totals.current.merge(outcome);
+        }
+        if let Simulated::Outcome(outcome) = &report.alternative {
+            totals.alternative.merge(outcome);
+        }
+    }
+    let report = ReplayReport {
+        from,
+        to,
+        current: Params::of(config),
+        alternative: Params::alternative(config, &args),
+        streams: reports,
+        totals: Totals {
+            cycles: totals.cycles,
+            recorded: totals.recorded.finish(),
+            current: totals.current.finish(),
+            alternative: totals.alternative.finish(),
+        },
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+/// Um ciclo gravado: o instante da última medição e as medições.
+type Cycle = (DateTime<Utc>, Vec<ConnectivityMetric>);
+
+/// Ciclos do probe na janela, em ordem cronológica, sem as medições de
+/// diagnóstico. Métricas sem ciclo (importadas) formam um ciclo por instante.
+async fn load_cycles(
+    storage: &Storage,
+    probe_id: i32,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Cycle>> {
+    let mut by_cycle: HashMap<(i64, Option<DateTime<Utc>>), Cycle> = HashMap::new();
+    let mut after_id = 0;
+    loop {
+        let page = storage
+            .list_metrics_page(Some(&[probe_id]), None, from, to, after_id, PAGE_SIZE)
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after_id = last.id;
+        for mut metric in page {
+            if metric
+                .details
+                .as_ref()
+                .is_some_and(|d| d.get("diagnostic").is_some())
+            {
+                continue;
+            }
+            // O consenso só usa o status; os detalhes não precisam ficar em memória
+            metric.details = None;
+            let key = match metric.cycle_id {
+                0 => (0, Some(metric.timestamp)),
+                id => (id, None),
+            };
+            let cycle = by_cycle
+                .entry(key)
+                .or_insert_with(|| (metric.timestamp, Vec::new()));
+            cycle.0 = cycle.0.max(metric.timestamp);
+            cycle.1.push(metric);
+        }
+    }
+    let mut cycles: Vec<_> = by_cycle.into_values().collect();
+    cycles.sort_by_key(|(at, _)| *at);
+    Ok(cycles)
+}
+
+fn replay_stream(
+    stream: &Stream,
+    cycles: &[Cycle],
+    recorded_events: &[OutageEvent],
+    args: &ReplayArgs,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> StreamReport {
+    let target_ids: Vec<i32> = stream.targets.iter().map(|t| t.id).collect();
+    let mut recorded = Outcome::default();
+    for event in recorded_events.iter().filter(|e| {
+        e.event_type == OutageEventType::Outage
+            && e.start_time >= from
+            && e.affected_probes
+                .as_ref()
+                .is_some_and(|probes| probes.contains(&stream.probe.id))
+            && e.affected_targets.iter().any(|t| target_ids.contains(t))
+    }) {
+        recorded.add(event.start_time, event.end_time, to);
+    }
+
+    let scenarios = [
+        Params::of(&stream.config),
+        Params::alternative(&stream.config, args),
+    ];
+    // Os ciclos são datados pelo horário gravado
+    let clock = Arc::new(ManualClock::new(from));
+    let mut states: Vec<Result<(ConsensusState, Outcome), String>> = scenarios
+        .iter()
+        .map(|params| {
+            params
+                .state(&stream.config, stream.probe.id, &stream.targets, &clock)
+                .map(|state| (state, Outcome::default()))
+                .map_err(|e| e.to_string())
+        })
+        .collect();
+
+    // Os logs do consenso de cada ciclo simulado só poluiriam a saída
+    let mut replayed = 0;
+    tracing::dispatcher::with_default(&tracing::Dispatch::none(), || {
+        let mut carried: HashMap<i32, ConnectivityMetric> = HashMap::new();
+        for (at, metrics) in cycles {
+            let mut cycle: Vec<ConnectivityMetric> = metrics
+                .iter()
+                .filter(|m| target_ids.contains(&m.target_id))
+                .cloned()
+                .collect();
+            if cycle.is_empty() {
+                continue;
+            }
+            for metric in &cycle {
+                carried.insert(metric.target_id, metric.clone());
+            }
+            cycle.extend(
+                carried
+                    .values()
+                    .filter(|c| !metrics.iter().any(|m| m.target_id == c.target_id))
+                    .cloned(),
+            );
+            replayed += 1;
+            clock.set(*at);
+            for (state, outcome) in states.iter_mut().flatten() {
+                if let Some(event) = state.update(&cycle)
+                    && let Some(end) = event.end_time
+                {
+                    outcome.add(event.start_time, Some(end), to);
+                }
+            }
+        }
+    });
+    // Outages ainda abertos no fim da janela
+    for (state, outcome) in states.iter_mut().flatten() {
+        if let Some(open) = state.current_outage() {
+            outcome.add(open.start_time, None, to);
+        }
+    }
+    let mut results = states.into_iter().map(|state| match state {
+        Ok((_, outcome)) => Simulated::Outcome(outcome.finish()),
+        Err(error) => Simulated::Invalid { error },
+    });
+    StreamReport {
+        probe: stream.label.clone(),
+        probe_id: stream.probe.id,
+        targets: stream.targets.len(),
+        cycles: replayed,
+        recorded: recorded.finish(),
+        current: results.next().expect("cenário atual"),
+        alternative: results.next().expect("cenário alternativo"),
+    }
+}
+
+fn print_report(report: &ReplayReport) {
+    let params = |p: &Params| {
+        format!(
+            "fail_threshold = {}, consensus = {}, outage_recovery_cycles = {}{}",
+            p.fail_threshold,
+            p.consensus,
+            p.outage_recovery_cycles,
+            p.fail_percent
+                .map_or(String::new(), |f| format!(", fail_percent = {}", f))
+        )
+    };
+    println!(
+        "Replay de {} a {} ({} ciclo(s))",
+        report.from.to_rfc3339(),
+        report.to.to_rfc3339(),
+        report.totals.cycles
+    );
+    println!("Atual:       {}", params(&report.current));
+    println!("Alternativo: {}", params(&report.alternative));
+    println!();
+    let width = report
+        .streams
+        .iter()
+        .map(|s| s.probe.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    println!(
+        "{:<width$} {:>7} {:>10} {:>10} {:>12}",
+        "probe", "ciclos", "gravados", "atual", "alternativo"
+    );
+    let count = |simulated: &Simulated| match simulated {
+        Simulated::Outcome(outcome) => outcome.outages.to_string(),
+        Simulated::Invalid { .. } => "inválido".to_string(),
+    };
+    for stream in &report.streams {
+        println!(
+            "{:<width$} {:>7} {:>10} {:>10} {:>12}",
+            stream.probe,
+            stream.cycles,
+            stream.recorded.outages,
+            count(&stream.current),
+            count(&stream.alternative)
+        );
+        for (name, outcome) in [
+            ("atual", &stream.current),
+            ("alternativo", &stream.alternative),
+        ] {
+            if let Simulated::Invalid { error } = outcome {
+                println!("  {}: {}", name, error);
+            }
+        }
+    }
+    println!();
+    println!(
+        "{:<12} {:>8} {:>8} {:>12} {:>12} {:>10}",
+        "cenário", "outages", "abertos", "total (min)", "mediana (s)", "máx (s)"
+    );
+    let secs = |value: Option<i64>| value.map_or("-".to_string(), |v| v.to_string());
+    for (name, outcome) in [
+        ("gravados", &report.totals.recorded),
+        ("atual", &report.totals.current),
+        ("alternativo", &report.totals.alternative),
+    ] {
+        println!(
+            "{:<12} {:>8} {:>8} {:>12.1} {:>12} {:>10}",
+            name,
+            outcome.outages,
+            outcome.open,
+            outcome.total_minutes,
+            secs(outcome.median_secs),
+            secs(outcome.max_secs)
+        );
+    }
+}
@@ -0,0 +1,756 @@
+//! scenario.rs — Cenários determinísticos do consenso (`monitoramento_rede scenario`)
+//!
+//! Um cenário descreve, num arquivo TOML, o status de cada target ciclo a
+//! ciclo (`.` Up, `x` Down, `t` Timeout, `d` Degraded, `-` sem medição; os
+//! espaços são ignorados), os ciclos em que o probe reinicia e, opcionalmente,
+//! os outages esperados. O `ConsensusState` real é alimentado com essas
+//! métricas num [`ManualClock`] (`start` + ciclo × `interval_secs`), sem
+//! tokio, rede, banco ou arquivo de configuração, o que torna reproduzíveis
+//! casos difíceis de provocar ao vivo: flaps na borda da janela, histerese,
+//! restart no meio de um outage. Os cenários de `cenarios/` rodam como
+//! testes do `cargo test`; o subcomando imprime a linha do tempo.
+//!
+//! ```toml
+//! name = "Restart no meio do outage"
+//! fail_threshold = 3
+//! consensus = 2
+//! restarts = [6]
+//!
+//! [[targets]]
+//! name = "dns-a"
+//! statuses = "... xxxxxxx ..."
+//!
+//! [[targets]]
+//! name = "dns-b"
+//! statuses = "... xxxxxxx ..."
+//!
+//! [[expect]]
+//! kind = "outage"
+//! start = 5
+//! end = 10
+//! ```
+//!
+//! Os parâmetros omitidos ficam nos padrões de [`ConsensusParams`]. No restart o estado é
+//! recriado e os eventos abertos são retomados (`resume`), como o scheduler
+//! faz com os eventos abertos no banco. Com `[[expect]]`, os eventos
+//! detectados (`outage` e `partial`, com `target`) precisam ser exatamente os
+//! esperados; `end` ausente é um evento ainda aberto no último ciclo.
+
+use crate::cli::ScenarioArgs;
+use crate::clock::{Clock, ManualClock};
+use crate::consensus::{ConsensusParams, ConsensusState};
+use crate::types::{
+    ConnectivityMetric, GeoLocation, MetricStatus, MetricType, OutageEvent, OutageEventType, Target,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use config as config_crate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Intervalo entre ciclos sem `interval_secs`.
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+/// Probe dos cenários.
+const SCENARIO_PROBE_ID: i32 = 1;
+
+#[derive(Debug, Deserialize)]
+struct ScenarioFile {
+    name: Option<String>,
+    fail_threshold: Option<usize>,
+    consensus: Option<usize>,
+    outage_recovery_cycles: Option<usize>,
+    partial_outage_recovery_cycles: Option<usize>,
+    fail_percent: Option<f64>,
+    interval_secs: Option<u64>,
+    start: Option<DateTime<Utc>>,
+    /// Ciclos antes dos quais o probe reinicia.
+    #[serde(default)]
+    restarts: Vec<usize>,
+    targets: Vec<ScenarioTarget>,
+    #[serde(default)]
+    expect: Vec<Expectation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScenarioTarget {
+    name: String,
+    statuses: String,
+    weight: Option<f64>,
+    fail_threshold: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EventKind {
+    Outage,
+    Partial,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Outage => write!(f, "outage"),
+            EventKind::Partial => write!(f, "partial"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Expectation {
+    kind: EventKind,
+    target: Option<String>,
+    start: usize,
+    end: Option<usize>,
+}
+
+/// Evento em ciclos do cenário; `end` ausente = ainda aberto.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Event {
+    kind: EventKind,
+    target: Option<String>,
+    start: usize,
+    end: Option<usize>,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(target) = &self.target {
+            write!(f, " {}", target)?;
+        }
+        match self.end {
+            Some(end) => write!(f, " ciclos {}–{}", self.start, end),
+            None => write!(f, " desde o ciclo {} (aberto)", self.start),
+        }
+    }
+}
+
+/// Calendário do cenário: o instante de cada ciclo é fixo, então a mesma
+/// entrada produz sempre os mesmos eventos.
+#[derive(Debug, Clone, Copy)]
+struct Timeline {
+    start: DateTime<Utc>,
+    interval: TimeDelta,
+}
+
+impl Timeline {
+    fn at(self, cycle: usize) -> DateTime<Utc> {
+        self.start + self.interval * cycle as i32
+    }
+
+    fn cycle_of(self, instant: DateTime<Utc>) -> usize {
+        ((instant - self.start).num_seconds() / self.interval.num_seconds()) as usize
+    }
+}
+
+fn parse_statuses(statuses: &str) -> Result<Vec<Option<MetricStatus>>, char> {
+    statuses
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '.' => Ok(Some(MetricStatus::Up)),
+            'x' => Ok(Some(MetricStatus::Down)),
+            't' => Ok(Some(MetricStatus::Timeout)),
+            'd' => Ok(Some(MetricStatus::Degraded)),
+            '-' => Ok(None),
+            other => Err(other),
+        })
+        .collect()
+}
+
+fn target(index: usize, spec: &ScenarioTarget) -> Target {
+    Target {
+        id: index as i32 + 1,
+        name: spec.name.clone(),
+        // TEST-NET-1: os cenários não medem nada
+        address: IpAddr::V4(Ipv4Addr::new(192, 0, 2, (index % 250 + 1) as u8)),
+        asn: None,
+        provider: None,
+        type_: "ping_ipv4".to_string(),
+        region: None,
+        created_at: None,
+        template: None,
+        tags: Vec::new(),
+        check_interval_secs: None,
+        fail_threshold: spec.fail_threshold,
+        weight: spec.weight,
+        degraded_rtt_ms: None,
+        degraded_loss_percent: None,
+        ping_payload_bytes: None,
+        dscp: None,
+        geo: GeoLocation::default(),
+        tenant: None,
+        enabled: true,
+    }
+}
+
+/// Cenário carregado e validado.
+struct Scenario {
+    name: String,
+    params: ConsensusParams,
+    timeline: Timeline,
+    restarts: Vec<usize>,
+    targets: Vec<Target>,
+    statuses: Vec<Vec<Option<MetricStatus>>>,
+    cycles: usize,
+    expect: Option<Vec<Event>>,
+}
+
+fn load(path: &Path) -> Result<Scenario> {
+    let file: ScenarioFile = config_crate::Config::builder()
+        .add_source(config_crate::File::from(path).required(true))
+        .build()
+        .and_then(|settings| settings.try_deserialize())
+        .with_context(|| format!("Cenário inválido: {}", path.display()))?;
+    from_file(file, &path.display().to_string())
+}
+
+fn from_file(file: ScenarioFile, origin: &str) -> Result<Scenario> {
+    let invalid = |message: String| anyhow!("Cenário {}: {}", origin, message);
+    if file.targets.is_empty() {
+        return Err(invalid("targets não pode ser vazio".to_string()));
+    }
+    for (key, value) in [
+        ("fail_threshold", file.fail_threshold),
+        ("consensus", file.consensus),
+        ("outage_recovery_cycles", file.outage_recovery_cycles),
+        (
+            "partial_outage_recovery_cycles",
+            file.partial_outage_recovery_cycles,
+        ),
+        ("interval_secs", file.interval_secs.map(|v| v as usize)),
+    ] {
+        if value == Some(0) {
+            return Err(invalid(format!("{}: deve ser maior que zero", key)));
+        }
+    }
+
+    let mut statuses = Vec::with_capacity(file.targets.len());
+    for spec in &file.targets {
+        let parsed = parse_statuses(&spec.statuses).map_err(|c| {
+            invalid(format!(
+                "target `{}`: status `{}` inválido (use . x t d -)",
+                spec.name, c
+            ))
+        })?;
+        statuses.push(parsed);
+    }
+    let cycles = statuses[0].len();
+    if cycles == 0 || statuses.iter().any(|s| s.len() != cycles) {
+        return Err(invalid(
+            "os statuses dos targets devem ter o mesmo número de ciclos (e pelo menos um)"
+                .to_string(),
+        ));
+    }
+    if let Some(cycle) = file.restarts.iter().find(|&&c| c == 0 || c >= cycles) {
+        return Err(invalid(format!(
+            "restarts: ciclo {} fora de 1..{}",
+            cycle,
+            cycles - 1
+        )));
+    }
+
+    let targets: Vec<Target> = file
+        .targets
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| target(index, spec))
+        .collect();
+    let expect = if file.expect.is_empty() {
+        None
+    } else {
+        let mut events = Vec::with_capacity(file.expect.len());
+        for expectation in file.expect {
+            match (&expectation.kind, &expectation.target) {
+                (EventKind::Partial, None) => {
+                    return Err(invalid("expect: partial exige target".to_string()));
+                }
+                (_, Some(name)) if !targets.iter().any(|t| t.name == *name) => {
+                    return Err(invalid(format!("expect: target `{}` não existe", name)));
+                }
+                _ => {}
+            }
+            events.push(Event {
+                kind: expectation.kind,
+                target: expectation.target,
+                start: expectation.start,
+                end: expectation.end,
+            });
+        }
+        events.sort();
+        Some(events)
+    };
+
+    let defaults = ConsensusParams::default();
+    let params = ConsensusParams {
+        fail_threshold: file.fail_threshold.unwrap_or(defaults.fail_threshold),
+        consensus: file.consensus.unwrap_or(defaults.consensus),
+        outage_recovery_cycles: file
+            .outage_recovery_cycles
+            .unwrap_or(defaults.outage_recovery_cycles),
+        partial_outage_recovery_cycles: file
+            .partial_outage_recovery_cycles
+            .unwrap_or(defaults.partial_outage_recovery_cycles),
+        fail_percent: file.fail_percent.or(defaults.fail_percent),
+        ..defaults
+    };
+    let interval_secs = file.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS);
+    Ok(Scenario {
+        name: file.name.unwrap_or_else(|| origin.to_string()),
+        params,
+        timeline: Timeline {
+            start: file
+                .start
+                .unwrap_or_else(|| Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()),
+            interval: TimeDelta::seconds(interval_secs as i64),
+        },
+        restarts: file.restarts,
+        targets,
+        statuses,
+        cycles,
+        expect,
+    })
+}
+
+/// Resultado da execução: eventos e, por ciclo, se havia outage aberto.
+struct Run {
+    events: Vec<Event>,
+    outage_open: Vec<bool>,
+}
+
+fn kind_of(event: &OutageEvent) -> Option<EventKind> {
+    match event.event_type {
+        OutageEventType::Outage => Some(EventKind::Outage),
+        OutageEventType::Partial => Some(EventKind::Partial),
+        _ => None,
+    }
+}
+
+fn execute(scenario: &Scenario) -> Result<Run> {
+    let clock = Arc::new(ManualClock::new(scenario.timeline.start));
+    let fresh = || {
+        ConsensusState::from_params(&scenario.params, SCENARIO_PROBE_ID, &scenario.targets)
+            .map(|state| state.with_clock(Arc::clone(&clock) as Arc<dyn Clock>))
+            .map_err(|e| anyhow!("Cenário {}: {}", scenario.name, e))
+    };
+    let names: HashMap<i32, &str> = scenario
+        .targets
+        .iter()
+        .map(|t| (t.id, t.name.as_str()))
+        .collect();
+    let mut state = fresh()?;
+    let mut events: Vec<Event> = Vec::new();
+    // Eventos abertos, como ficariam no banco: retomados a cada restart
+    let mut open: HashMap<(EventKind, Option<i32>), (usize, OutageEvent)> = HashMap::new();
+    let mut outage_open = Vec::with_capacity(scenario.cycles);
+
+    for cycle in 0..scenario.cycles {
+        if scenario.restarts.contains(&cycle) {
+            state = fresh()?;
+            for (_, event) in open.values() {
+                state.resume(event.clone());
+            }
+        }
+        let now = scenario.timeline.at(cycle);
+        clock.set(now);
+        let metrics: Vec<ConnectivityMetric> = scenario
+            .targets
+            .iter()
+            .zip(&scenario.statuses)
+            .filter_map(|(target, statuses)| {
+                statuses[cycle].clone().map(|status| ConnectivityMetric {
+                    id: 0,
+                    cycle_id: cycle as i64 + 1,
+                    probe_id: SCENARIO_PROBE_ID,
+                    target_id: target.id,
+                    timestamp: now,
+                    metric_type: MetricType::PingIpv4,
+                    status,
+                    response_time_ms: None,
+                    packet_loss_percent: None,
+                    error: None,
+                    details: None,
+                    provenance: None,
+                    attempts: Vec::new(),
+                })
+            })
+            .collect();
+
+        let mut changed: Vec<OutageEvent> = state.update(&metrics).into_iter().collect();
+        changed.extend(state.update_partial_outages(&metrics));
+        for event in changed {
+            let Some(kind) = kind_of(&event) else {
+                continue;
+            };
+            let target_id = match kind {
+                EventKind::Outage => None,
+                EventKind::Partial => event.affected_targets.first().copied(),
+            };
+            let key = (kind, target_id);
+            match event.end_time {
+                None => {
+                    open.insert(key, (events.len(), event.clone()));
+                    events.push(Event {
+                        kind,
+                        target: target_id
+                            .and_then(|id| names.get(&id))
+                            .map(|n| n.to_string()),
+                        start: scenario.timeline.cycle_of(event.start_time),
+                        end: None,
+                    });
+                }
+                Some(end) => {
+                    if let Some((index, _)) = open.remove(&key) {
+                        events[index].end = Some(scenario.timeline.cycle_of(end));
+                    }
+                }
+            }
+        }
+        outage_open.push(state.current_outage().is_some());
+    }
+    Ok(Run {
+        events,
+        outage_open,
+    })
+}
+
+fn status_char(status: &Option<MetricStatus>) -> char {
+    match status {
+        Some(MetricStatus::Up) => '.',
+        Some(MetricStatus::Down) => 'x',
+        Some(MetricStatus::Timeout) => 't',
+        Some(MetricStatus::Degraded) => 'd',
+        None => '-',
+    }
+}
+
+/// Imprime a linha do tempo e os eventos; retorna se o cenário bateu com o
+/// esperado (sempre `true` sem `[[expect]]`).
+fn print_run(scenario: &Scenario, run: &Run) -> bool {
+    let params = &scenario.params;
+    println!(
+        "Cenário: {} (fail_threshold = {}, consensus = {}, outage_recovery_cycles = {}{})",
+        scenario.name,
+        params.fail_threshold,
+        params.consensus,
+        params.outage_recovery_cycles,
+        params
+            .fail_percent
+            .map_or(String::new(), |f| format!(", fail_percent = {}", f))
+    );
+    let width = scenario
+        .targets
+        .iter()
+        .map(|t| t.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("restart".len());
+    let header: String = (0..scenario.cycles)
+        .map(|c| char::from_digit((c % 10) as u32, 10).unwrap_or('?'))
+        .collect();
+    println!("  {:<width$} {}", "ciclo", header);
+    for (target, statuses) in scenario.targets.iter().zip(&scenario.statuses) {
+        let row: String = statuses.iter().map(status_char).collect();
+        println!("  {:<width$} {}", target.name, row);
+    }
+    let row: String = run
+        .outage_open
+        .iter()
+        .map(|&open| if open { '#' } else { '.' })
+        .collect();
+    println!("  {:<width$} {}", "outage", row);
+    if !scenario.restarts.is_empty() {
+        let row: String = (0..scenario.cycles)
+            .map(|c| {
+                if scenario.restarts.contains(&c) {
+                    '^'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        println!("  {:<width$} {}", "restart", row.trim_end());
+    }
+
+    if run.events.is_empty() {
+        println!("  Nenhum evento detectado");
+    }
+    for event in &run.events {
+        println!("  • {}", event);
+    }
+    let mismatches = mismatches(scenario, run);
+    if scenario.expect.is_some() {
+        if mismatches.is_empty() {
+            println!("  ✅ Conforme o esperado");
+        } else {
+            println!("  ❌ Diferente do esperado:");
+            for mismatch in &mismatches {
+                println!("     {}", mismatch);
+            }
+        }
+    }
+    println!();
+    mismatches.is_empty()
+}
+
+/// Diferenças entre os eventos detectados e os `[[expect]]` (nenhuma sem
+/// `[[expect]]`).
+fn mismatches(scenario: &Scenario, run: &Run) -> Vec<String> {
+    let Some(expected) = &scenario.expect else {
+        return Vec::new();
+    };
+    let mut detected = run.events.clone();
+    detected.sort();
+    expected
+        .iter()
+        .filter(|e| !detected.contains(e))
+        .map(|e| format!("esperado, não detectado: {}", e))
+        .chain(
+            detected
+                .iter()
+                .filter(|e| !expected.contains(e))
+                .map(|e| format!("detectado, não esperado: {}", e)),
+        )
+        .collect()
+}
+
+/// Roda os cenários e imprime o resultado de cada um. Falha se algum
+/// divergir do esperado.
+pub fn run(args: &ScenarioArgs) -> Result<()> {
+    let mut failed = 0;
+    for path in &args.files {
+        let scenario = load(path)?;
+        let execute = || execute(&scenario);
+        let run = if args.verbose {
+            execute()?
+        } else {
+            // Os logs do consenso poluiriam a linha do tempo
+            tracing::dispatcher::with_default(&tracing::Dispatch::none(), execute)?
+        };
+        if !print_run(&scenario, &run) {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{} de {} cenário(s) diferente(s) do esperado",
+            failed,
+            args.files.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::path::PathBuf;
+
+    fn cenarios() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("cenarios");
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .expect("diretório cenarios/")
+            .map(|entry| entry.expect("entrada de cenarios/").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn cenarios_do_repositorio_conferem() {
+        let paths = cenarios();
+        assert!(!paths.is_empty(), "nenhum cenário em cenarios/");
+        for path in paths {
+            let scenario = load(&path).unwrap();
+            assert!(
+                scenario.expect.is_some(),
+                "{}: cenário sem [[expect]]",
+                path.display()
+            );
+            let run = execute(&scenario).unwrap();
+            let mismatches = mismatches(&scenario, &run);
+            assert!(
+                mismatches.is_empty(),
+                "{}: {}",
+                path.display(),
+                mismatches.join("; ")
+            );
+        }
+    }
+
+    fn file(statuses: &[&str]) -> ScenarioFile {
+        ScenarioFile {
+            name: None,
+            fail_threshold: None,
+            consensus: None,
+            outage_recovery_cycles: None,
+            partial_outage_recovery_cycles: None,
+            fail_percent: None,
+            interval_secs: None,
+            start: None,
+            restarts: Vec::new(),
+            targets: statuses
+                .iter()
+                .enumerate()
+                .map(|(index, statuses)| ScenarioTarget {
+                    name: format!("t{}", index),
+                    statuses: statuses.to_string(),
+                    weight: None,
+                    fail_threshold: None,
+                })
+                .collect(),
+            expect: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rejeita_status_invalido_e_tamanhos_diferentes() {
+        let error = from_file(file(&["..?"]), "teste").err().unwrap();
+        assert!(error.to_string().contains("status `?` inválido"));
+        let error = from_file(file(&["...", ".."]), "teste").err().unwrap();
+        assert!(error.to_string().contains("mesmo número de ciclos"));
+        let mut restart = file(&["..."]);
+        restart.restarts = vec![3];
+        assert!(from_file(restart, "teste").is_err());
+    }
+
+    #[test]
+    fn consenso_acima_dos_targets_falha_na_execucao() {
+        let mut scenario = file(&["xxx"]);
+        scenario.consensus = Some(2);
+        let scenario = from_file(scenario, "teste").unwrap();
+        assert!(execute(&scenario).is_err());
+    }
+
+    /// Eventos esperados pelo modelo de referência: outage com histerese
+    /// (modo estrito, pesos 1) e outages parciais por target.
+    fn reference(
+        failed: &[Vec<bool>],
+        fail_threshold: usize,
+        consensus: usize,
+        outage_recovery: usize,
+        partial_recovery: usize,
+    ) -> (Vec<Event>, Vec<bool>) {
+        let cycles = failed[0].len();
+        let mut events = Vec::new();
+        let mut outage_open = Vec::with_capacity(cycles);
+        let mut open: Option<usize> = None;
+        let mut recovering: Option<(usize, usize)> = None;
+        let mut partials: Vec<Option<usize>> = vec![None; failed.len()];
+        let mut down_streaks = vec![0; failed.len()];
+        let mut up_streaks = vec![0; failed.len()];
+        for cycle in 0..cycles {
+            let reached = cycle + 1 >= fail_threshold
+                && failed
+                    .iter()
+                    .filter(|f| f[cycle + 1 - fail_threshold..=cycle].iter().all(|&x| x))
+                    .count()
+                    >= consensus;
+            if reached {
+                recovering = None;
+                if open.is_none() {
+                    open = Some(events.len());
+                    events.push(Event {
+                        kind: EventKind::Outage,
+                        target: None,
+                        start: cycle,
+                        end: None,
+                    });
+                }
+            } else if let Some(index) = open {
+                let (since, count) = recovering.get_or_insert((cycle, 0));
+                *count += 1;
+                if *count >= outage_recovery {
+                    events[index].end = Some(*since);
+                    open = None;
+                    recovering = None;
+                }
+            }
+            outage_open.push(open.is_some());
+
+            for (target, failed) in failed.iter().enumerate() {
+                if failed[cycle] {
+                    up_streaks[target] = 0;
+                    down_streaks[target] += 1;
+                    if down_streaks[target] >= fail_threshold && partials[target].is_none() {
+                        partials[target] = Some(events.len());
+                        events.push(Event {
+                            kind: EventKind::Partial,
+                            target: Some(format!("t{}", target)),
+                            start: cycle,
+                            end: None,
+                        });
+                    }
+                } else {
+                    down_streaks[target] = 0;
+                    if let Some(index) = partials[target] {
+                        up_streaks[target] += 1;
+                        if up_streaks[target] >= partial_recovery {
+                            events[index].end = Some(cycle);
+                            partials[target] = None;
+                            up_streaks[target] = 0;
+                        }
+                    }
+                }
+            }
+        }
+        events.sort();
+        (events, outage_open)
+    }
+
+    fn statuses_strategy() -> impl Strategy<Value = Vec<Vec<char>>> {
+        (1usize..=4, 1usize..=30).prop_flat_map(|(targets, cycles)| {
+            prop::collection::vec(
+                prop::collection::vec(prop::sample::select(vec!['.', 'x', 't']), cycles),
+                targets,
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn eventos_batem_com_o_modelo_de_referencia(
+            statuses in statuses_strategy(),
+            fail_threshold in 1usize..=4,
+            consensus_seed in 0usize..4,
+            outage_recovery in 1usize..=3,
+            partial_recovery in 1usize..=3,
+        ) {
+            let consensus = consensus_seed % statuses.len() + 1;
+            let rows: Vec<String> = statuses.iter().map(|row| row.iter().collect()).collect();
+            let mut scenario = file(&rows.iter().map(String::as_str).collect::<Vec<_>>());
+            scenario.fail_threshold = Some(fail_threshold);
+            scenario.consensus = Some(consensus);
+            scenario.outage_recovery_cycles = Some(outage_recovery);
+            scenario.partial_outage_recovery_cycles = Some(partial_recovery);
+            let scenario = from_file(scenario, "proptest").unwrap();
+            let run = tracing::dispatcher::with_default(&tracing::Dispatch::none(), || {
+                execute(&scenario)
+            })
+            .unwrap();
+
+            let failed: Vec<Vec<bool>> = statuses
+                .iter()
+                .map(|row| row.iter().map(|&c| c != '.').collect())
+                .collect();
+            let (expected, outage_open) = reference(
+                &failed,
+                fail_threshold,
+                consensus,
+                outage_recovery,
+                partial_recovery,
+            );
+            let mut detected = run.events.clone();
+            detected.sort();
+            prop_assert_eq!(detected, expected);
+            prop_assert_eq!(run.outage_open, outage_open);
+        }
+    }
+}
@@ -10,32 +10,633 @@
 //! - Enum MetricType granular (PingIpv4/PingIpv6)
 //! - Lógica funcional, concorrente e auditável
 
+use crate::baseline::BaselineTracker;
+use crate::bufferbloat::{self, LoadResult, MinRttTracker};
+use crate::captive;
+use crate::check::{CheckRegistry, CycleMetrics};
+use crate::config::{AsymmetryConfig, Config, ConnectivityMethod, TracerouteConfig};
 use crate::consensus::ConsensusState;
+use crate::decisions::ConsensusDecision;
+use crate::exporters::Exporters;
+use crate::notify::NotificationKind;
+use crate::persistence::Persistence;
+use crate::ratelimit::RateLimiter;
+use crate::shadow::ShadowConsensusSet;
+use crate::snapshot::SnapshotStore;
+use crate::supervisor::{self, Heartbeat};
+use crate::traceroute::{self, AsnResolver};
 use crate::types::{
-    ConnectivityMetric, Cycle, MetricStatus, MetricType, OutageEvent, Probe, SchedulerState,
+    ConnectivityMetric, Cycle, CycleSummary, MetricStatus, OutageEvent, Probe, SchedulerState,
     Target, TargetWarmupState,
 };
-use crate::{config::Config, ping, storage::Storage};
+use crate::{asymmetry, degradation, diagnosis, host, ping, source, storage::StorageBackend};
 use chrono::Utc;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::net::TcpStream;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::sync::{Mutex, MutexGuard};
-use tokio::time::interval;
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{MissedTickBehavior, interval};
 use tracing::{debug, error, info, warn};
 use trust_dns_resolver::TokioAsyncResolver;
 
+/// Estado compartilhado entre os schedulers de todos os probes.
+#[derive(Clone)]
+pub struct SchedulerContext {
+    pub config: Arc<Config>,
+    pub storage: Arc<dyn StorageBackend>,
+    pub persistence: Persistence,
+    pub consensus_state: Arc<Mutex<ConsensusState>>,
+    pub shadow_state: Arc<Mutex<ShadowConsensusSet>>,
+    pub exporters: Arc<Exporters>,
+    pub snapshot: Arc<SnapshotStore>,
+    pub checks: Arc<CheckRegistry>,
+}
+
+/// Monta o scheduler supervisionado de um probe. O consenso do probe é
+/// criado (e validado contra os targets) em [`SchedulerBuilder::new`]; as
+/// dependências compartilhadas entram por nome. `storage` e `persistence` são
+/// obrigatórias; sem as demais, o scheduler roda isolado (snapshot próprio,
+/// sem exportadores nem consenso sombra, checks montados da configuração).
+pub struct SchedulerBuilder {
+    probe: Probe,
+    targets: Vec<Target>,
+    config: Arc<Config>,
+    consensus: ConsensusState,
+    storage: Option<Arc<dyn StorageBackend>>,
+    persistence: Option<Persistence>,
+    shadow_state: Option<Arc<Mutex<ShadowConsensusSet>>>,
+    exporters: Option<Arc<Exporters>>,
+    snapshot: Option<Arc<SnapshotStore>>,
+    checks: Option<Arc<CheckRegistry>>,
+}
+
+impl SchedulerBuilder {
+    /// Falha se os parâmetros de consenso não cabem nos targets.
+    pub fn new(probe: Probe, targets: Vec<Target>, config: Arc<Config>) -> Result<Self, String> {
+        let consensus = ConsensusState::from_config(&config, probe.id, &targets)?;
+        Ok(Self {
+            probe,
+            targets,
+            config,
+            consensus,
+            storage: None,
+            persistence: None,
+            shadow_state: None,
+            exporters: None,
+            snapshot: None,
+            checks: None,
+        })
+    }
+
+    pub fn probe(&self) -> &Probe {
+        &self.probe
+    }
+
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+
+    /// Consenso do probe antes da partida (ex: retomada de outages abertos).
+    pub fn consensus_mut(&mut self) -> &mut ConsensusState {
+        &mut self.consensus
+    }
+
+    pub fn storage(mut self, storage: Arc<dyn StorageBackend>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn persistence(mut self, persistence: Persistence) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+
+    pub fn shadow_state(mut self, shadow_state: Arc<Mutex<ShadowConsensusSet>>) -> Self {
+        self.shadow_state = Some(shadow_state);
+        self
+    }
+
+    pub fn exporters(mut self, exporters: Arc<Exporters>) -> Self {
+        self.exporters = Some(exporters);
+        self
+    }
+
+    pub fn snapshot(mut self, snapshot: Arc<SnapshotStore>) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    pub fn checks(mut self, checks: Arc<CheckRegistry>) -> Self {
+        self.checks = Some(checks);
+        self
+    }
+
+    /// Sobe o supervisor do probe, que cria (e recria) o scheduler.
+    pub fn start(self) -> anyhow::Result<SchedulerHandle> {
+        let storage = self
+            .storage
+            .ok_or_else(|| anyhow::anyhow!("scheduler sem storage"))?;
+        let persistence = self
+            .persistence
+            .ok_or_else(|| anyhow::anyhow!("scheduler sem pipeline de persistência"))?;
+        let checks = match self.checks {
+            Some(checks) => checks,
+            None => Arc::new(CheckRegistry::new(&self.config)?),
+        };
+        let shadow_state = self.shadow_state.unwrap_or_else(|| {
+            Arc::new(Mutex::new(ShadowConsensusSet::new(
+                &self.config.shadow_consensus,
+                self.config.consensus_history_max_bytes,
+            )))
+        });
+        let ctx = SchedulerContext {
+            config: self.config,
+            storage,
+            persistence,
+            consensus_state: Arc::new(Mutex::new(self.consensus)),
+            shadow_state,
+            exporters: self.exporters.unwrap_or_default(),
+            snapshot: self
+                .snapshot
+                .unwrap_or_else(|| Arc::new(SnapshotStore::new())),
+            checks,
+        };
+        let task = tokio::spawn(supervisor::supervise(self.probe.clone(), self.targets, ctx));
+        Ok(SchedulerHandle {
+            probe: self.probe,
+            task,
+        })
+    }
+}
+
+/// Scheduler supervisionado em execução.
+pub struct SchedulerHandle {
+    probe: Probe,
+    task: JoinHandle<()>,
+}
+
+impl SchedulerHandle {
+    pub fn probe(&self) -> &Probe {
+        &self.probe
+    }
+
+    /// Para o supervisor e o scheduler e espera a task terminar.
+    pub async fn stop(self) {
+        self.task.abort();
+        let _ = self.task.await;
+    }
+
+    /// Espera o supervisor terminar (só acontece em panic ou se abortado).
+    pub async fn join(self) -> Result<(), JoinError> {
+        self.task.await
+    }
+}
+
+/// Ciclos que estouraram `cycle_interval_secs` num probe, em `/api/snapshot`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CycleOverrunStats {
+    /// Ciclos mais longos que o intervalo.
+    pub overruns: u64,
+    /// Ticks descartados por estouro (com `skip_missed_cycles`).
+    pub skipped_cycles: u64,
+    /// Duração do último ciclo que estourou, em milissegundos.
+    pub last_overrun_ms: f64,
+    /// Ciclos com checks abortados por `cycle_budget_percent`.
+    pub budget_exceeded: u64,
+    /// Checks abortados (gravados como `Timeout`).
+    pub aborted_checks: u64,
+}
+
+impl CycleOverrunStats {
+    fn record(&mut self, elapsed: Duration, interval: Duration, skipping: bool) {
+        self.overruns += 1;
+        if skipping {
+            // Ticks vencidos durante o ciclo, menos o atrasado que dispara
+            // logo em seguida
+            let due = (elapsed.as_secs_f64() / interval.as_secs_f64()) as u64;
+            self.skipped_cycles += due.saturating_sub(1);
+        }
+        self.last_overrun_ms = elapsed.as_secs_f64() * 1000.0;
+    }
+}
+
+/// Loga o aviso de capacidade quando o orçamento do ciclo abortou checks.
+fn report_budget_exceeded(
+    probe: &Probe,
+    config: &Config,
+    aborted: &[Target],
+    overruns: &mut CycleOverrunStats,
+    snapshot: &SnapshotStore,
+) {
+    if aborted.is_empty() {
+        return;
+    }
+    overruns.budget_exceeded += 1;
+    overruns.aborted_checks += aborted.len() as u64;
+    let names: Vec<&str> = aborted.iter().map(|t| t.name.as_str()).collect();
+    warn!(
+        "[PROBE {}] {} check(s) abortado(s) ao fim do orçamento do ciclo ({}% de {}s) e gravado(s) como timeout: {}; capacidade insuficiente, considere aumentar cycle_interval_secs ou reduzir timeouts",
+        probe.location,
+        aborted.len(),
+        config.cycle_budget_percent,
+        config.cycle_interval_secs,
+        names.join(", ")
+    );
+    snapshot.set_cycle_overruns(probe.id, *overruns);
+}
+
+/// Agenda de uma medição periódica (assimetria, teste de banda): a cada
+/// `interval_cycles` ciclos, adiada enquanto o host estiver em condições
+/// degradadas.
+#[derive(Debug)]
+struct PeriodicSchedule {
+    /// Prefixo dos logs (ex: `ASYMMETRY`).
+    name: &'static str,
+    deferred: bool,
+}
+
+impl PeriodicSchedule {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            deferred: false,
+        }
+    }
+
+    /// A medição deve rodar neste ciclo?
+    fn due(
+        &mut self,
+        interval_cycles: i32,
+        cycle_number: i32,
+        paused: bool,
+        location: &str,
+    ) -> bool {
+        if cycle_number % interval_cycles != 0 && !self.deferred {
+            return false;
+        }
+        if paused {
+            if !self.deferred {
+                info!(
+                    "[{} {}] Medição adiada: host em condições degradadas",
+                    self.name, location
+                );
+            }
+            self.deferred = true;
+            return false;
+        }
+        self.deferred = false;
+        true
+    }
+}
+
+/// Dispara a medição de assimetria TCP em background. Os resultados só são
+/// persistidos: não entram no consenso, no warmup nem no snapshot.
+fn spawn_asymmetry_measurement(
+    asymmetry_config: &AsymmetryConfig,
+    persistence: &Persistence,
+    targets: &[Target],
+    probe: &Probe,
+    cycle_id: i64,
+) {
+    let asymmetry_config = asymmetry_config.clone();
+    let persistence = persistence.clone();
+    let targets = targets.to_vec();
+    let probe = probe.clone();
+    tokio::spawn(async move {
+        let metrics =
+            asymmetry::measure_targets(&targets, &probe, &asymmetry_config, cycle_id).await;
+        persistence.metrics(&metrics).await;
+        debug!(
+            "[ASYMMETRY {}] {} medição(ões) de assimetria enfileirada(s)",
+            probe.location,
+            metrics.len()
+        );
+    });
+}
+
+/// Dispara os traceroutes até os targets em background. Os caminhos só são
+/// persistidos, para a saúde por AS (`as_health.rs`).
+fn spawn_traceroutes(
+    traceroute_config: &TracerouteConfig,
+    asn_resolver: Option<Arc<AsnResolver>>,
+    persistence: &Persistence,
+    targets: &[Target],
+    probe: &Probe,
+    cycle_id: i64,
+) {
+    let traceroute_config = traceroute_config.clone();
+    let persistence = persistence.clone();
+    let targets = targets.to_vec();
+    let probe = probe.clone();
+    tokio::spawn(async move {
+        let traceroutes = traceroute::trace_targets(
+            &targets,
+            &probe,
+            &traceroute_config,
+            asn_resolver.as_deref(),
+            cycle_id,
+        )
+        .await;
+        for traceroute in &traceroutes {
+            persistence.traceroute(traceroute).await;
+        }
+        debug!(
+            "[TRACEROUTE {}] {} traceroute(s) enfileirado(s)",
+            probe.location,
+            traceroutes.len()
+        );
+    });
+}
+
+/// Resume o teste de banda do ciclo: vazão e nota pelo excedente mediano.
+fn log_bufferbloat(probe: &Probe, load: &LoadResult, mut deltas: Vec<f64>) {
+    if let Some(error) = &load.error {
+        warn!(
+            "[BUFFERBLOAT {}] Teste de banda interrompido: {}",
+            probe.location, error
+        );
+    }
+    if deltas.is_empty() {
+        info!(
+            "[BUFFERBLOAT {}] {:.1} Mbps sob carga; nenhum target com RTT de referência",
+            probe.location, load.throughput_mbps
+        );
+        return;
+    }
+    deltas.sort_by(f64::total_cmp);
+    let median = deltas[deltas.len() / 2];
+    info!(
+        "[BUFFERBLOAT {}] {:.1} Mbps sob carga; nota {} (excedente mediano de {:.1} ms em {} target(s))",
+        probe.location,
+        load.throughput_mbps,
+        bufferbloat::grade(median),
+        median,
+        deltas.len()
+    );
+}
+
+/// Amostra os recursos do host antes dos checks; com condições degradadas os
+/// checks pesados do ciclo são pausados.
+fn sample_host(config: &Config, probe: &Probe) -> host::HostSample {
+    let sample = host::sample(probe.id, &config.host);
+    if sample.degraded_conditions && !sample.is_starved() {
+        warn!(
+            "[HOST {}] Condições de medição degradadas (load {:.2} por CPU); checks pesados pausados",
+            probe.location,
+            sample.load_per_cpu().unwrap_or_default()
+        );
+    }
+    sample
+}
+
+/// Anota as métricas do ciclo conforme a amostra do host e a publica
+/// (snapshot e banco). Retorna `true` se o ciclo deve ser descartado do
+/// consenso (`host.reject`).
+async fn apply_host_sample(
+    config: &Config,
+    probe: &Probe,
+    snapshot: &SnapshotStore,
+    persistence: &Persistence,
+    sample: &host::HostSample,
+    metrics: &mut [ConnectivityMetric],
+) -> bool {
+    let rejected = config.host.reject && sample.is_starved();
+    if sample.is_starved() {
+        warn!(
+            "[HOST {}] Host sem recursos durante o ciclo ({}){}",
+            probe.location,
+            sample.starved.join("; "),
+            if rejected {
+                "; ciclo descartado do consenso"
+            } else {
+                ""
+            }
+        );
+    }
+    host::annotate(metrics, sample, config.host.reject);
+    snapshot.set_host(sample);
+    persistence.host_sample(sample).await;
+    rejected
+}
+
+/// Decisão do consenso neste ciclo, identificada pelo probe e ciclo, se
+/// `[consensus_decisions]` estiver configurado. Sempre consome a decisão
+/// pendente do `ConsensusState`.
+fn take_decision(
+    config: &Config,
+    consensus: &mut ConsensusState,
+    probe: &Probe,
+    cycle_id: i64,
+    now: chrono::DateTime<Utc>,
+    host_rejected: bool,
+) -> Option<ConsensusDecision> {
+    let decision = consensus.take_decision();
+    config.consensus_decisions.as_ref()?;
+    let mut decision = if host_rejected {
+        ConsensusDecision::skipped(probe.id, cycle_id, now, "host_rejected")
+    } else {
+        decision?
+    };
+    decision.probe_id = probe.id;
+    decision.cycle_id = cycle_id;
+    Some(decision)
+}
+
+/// Com `[diagnosis]`, localiza a falha (LAN, CPE ou upstream) de um outage
+/// recém-aberto num ciclo em que nenhum target respondeu. O diagnóstico vai
+/// também para o outage aberto no consenso, para valer no encerramento.
+async fn diagnose_outage(
+    config: &Config,
+    event: &mut OutageEvent,
+    consensus: &mut ConsensusState,
+    metrics: &[ConnectivityMetric],
+    targets: &[Target],
+    probe: &Probe,
+    limiter: &Arc<RateLimiter>,
+) {
+    let Some(diagnosis_config) = &config.diagnosis else {
+        return;
+    };
+    if event.end_time.is_some()
+        || metrics.is_empty()
+        || metrics.iter().any(|m| m.status == MetricStatus::Up)
+    {
+        return;
+    }
+    let Some(destination) = targets
+        .iter()
+        .find(|t| event.affected_targets.contains(&t.id))
+        .map(|t| t.address)
+    else {
+        return;
+    };
+    let diagnosis =
+        diagnosis::diagnose(diagnosis_config, destination, probe, Arc::clone(limiter)).await;
+    info!(
+        "[DIAGNOSIS {}] Todos os targets falharam: {} (gateway {}, {}; primeiro salto upstream: {})",
+        probe.location,
+        diagnosis.location.reason(),
+        diagnosis
+            .gateway
+            .map_or_else(|| "ausente".to_string(), |g| g.to_string()),
+        if diagnosis.gateway_reachable {
+            "responde"
+        } else {
+            "não responde"
+        },
+        diagnosis
+            .upstream_hop
+            .map_or_else(|| "-".to_string(), |h| h.to_string())
+    );
+    diagnosis.annotate(event);
+    consensus.annotate_outage(&diagnosis);
+}
+
+/// Com `[captive_portal]`, testa se o outage recém-aberto é um captive
+/// portal interceptando o HTTP; se for, o `reason` (inclusive o do
+/// diagnóstico) vira `captive_portal` até o encerramento.
+async fn detect_captive_portal(
+    config: &Config,
+    event: &mut OutageEvent,
+    consensus: &mut ConsensusState,
+    probe: &Probe,
+) {
+    let Some(captive_config) = &config.captive_portal else {
+        return;
+    };
+    if event.end_time.is_some() {
+        return;
+    }
+    let Some(portal) = captive::detect(captive_config).await else {
+        return;
+    };
+    warn!(
+        "🏨 [CAPTIVE {}] Captive portal interceptando o HTTP: {} respondeu {} (esperado {}){}",
+        probe.location,
+        portal.url,
+        portal.status,
+        portal.expected_status,
+        portal
+            .location
+            .as_ref()
+            .map_or_else(String::new, |location| format!(
+                ", redirect para {}",
+                location
+            ))
+    );
+    portal.annotate(event);
+    consensus.annotate_captive_portal(&portal);
+}
+
 /// Verificação multi-método de conectividade.
-/// Tenta TCP connect, resolução DNS e ICMP/ping (fallback).
+/// Roda os métodos de `[connectivity]` na ordem configurada (padrão: TCP
+/// connect, DNS reverso e ICMP/ping).
 /// Loga detalhadamente cada tentativa e motivo de falha.
 /// Retorna true se qualquer método/alvo responder.
-async fn check_connectivity_resilient(targets: &[Target], probe: &Probe, config: &Config) -> bool {
-    // 1. TCP connect para portas 53, 80, 443 em todos os targets
-    let tcp_ports = [53u16, 80, 443];
+async fn check_connectivity_resilient(
+    targets: &[Target],
+    probe: &Probe,
+    config: &Config,
+    limiter: &Arc<RateLimiter>,
+) -> bool {
+    let timeout = Duration::from_secs(config.connectivity.timeout_secs);
+    for method in &config.connectivity.methods {
+        let ok = match method {
+            ConnectivityMethod::Tcp => {
+                check_connectivity_tcp(targets, probe, config, timeout, limiter).await
+            }
+            ConnectivityMethod::Dns => check_connectivity_dns(targets, probe, timeout).await,
+            ConnectivityMethod::Icmp => {
+                check_connectivity_icmp(targets, probe, config, limiter).await
+            }
+        };
+        if ok {
+            return true;
+        }
+    }
+
+    warn!(
+        "[PROBE {}] Nenhum método de conectividade teve sucesso",
+        probe.location
+    );
+    false
+}
+
+/// Relê os targets pausados (`enabled = false`), loga os que pausaram ou
+/// voltaram e retorna os recém-pausados; sem banco, fica o conjunto anterior.
+async fn refresh_paused(
+    storage: &dyn StorageBackend,
+    probe: &Probe,
+    targets: &[Target],
+    paused: &mut HashSet<i32>,
+) -> Vec<i32> {
+    let disabled = match storage.list_disabled_target_ids().await {
+        Ok(disabled) => disabled,
+        Err(e) => {
+            debug!(
+                "[PROBE {}] Falha ao ler targets pausados; mantendo os anteriores: {:?}",
+                probe.location, e
+            );
+            return Vec::new();
+        }
+    };
+    let mut newly_paused = Vec::new();
+    for target in targets {
+        match (paused.contains(&target.id), disabled.contains(&target.id)) {
+            (false, true) => {
+                info!(
+                    "⏸️  [PROBE {}] Target {} ({}) pausado",
+                    probe.location, target.name, target.address
+                );
+                newly_paused.push(target.id);
+            }
+            (true, false) => info!(
+                "▶️  [PROBE {}] Target {} ({}) retomado",
+                probe.location, target.name, target.address
+            ),
+            _ => {}
+        }
+    }
+    *paused = disabled;
+    newly_paused
+}
+
+/// Canários da verificação de conectividade como targets (sem id; os
+/// resultados não são gravados).
+fn canary_targets(canaries: &[IpAddr]) -> Vec<Target> {
+    canaries
+        .iter()
+        .map(|&address| Target::unregistered(format!("canário {}", address), address))
+        .collect()
+}
+
+/// TCP connect nas `connectivity.tcp_ports` (e na do check tcp) em todos os
+/// targets, com a origem do check tcp.
+async fn check_connectivity_tcp(
+    targets: &[Target],
+    probe: &Probe,
+    config: &Config,
+    timeout: Duration,
+    limiter: &RateLimiter,
+) -> bool {
+    let mut tcp_ports = config.connectivity.tcp_ports.clone();
+    if !tcp_ports.contains(&config.checks.tcp_port) {
+        tcp_ports.push(config.checks.tcp_port);
+    }
     for target in targets {
         for &port in &tcp_ports {
-            let addr = format!("{}:{}", target.address, port);
-            match tokio::time::timeout(Duration::from_secs(3), TcpStream::connect(&addr)).await {
+            let addr = SocketAddr::new(target.address, port);
+            limiter.acquire(target).await;
+            let connect = source::tcp_connect(config.checks.source_for("tcp"), addr);
+            match tokio::time::timeout(timeout, connect).await {
                 Ok(Ok(_)) => {
                     info!(
                         "[PROBE {}] TCP connect OK em {}:{} (target: {})",
@@ -58,35 +659,50 @@ async fn check_connectivity_resilient(targets: &[Target], probe: &Probe, config:
             }
         }
     }
+    false
+}
 
-    // 2. Resolução DNS (usando trust-dns)
-    if let Ok(resolver) = TokioAsyncResolver::tokio_from_system_conf() {
-        for target in targets {
-            // Tenta resolver o nome reverso do IP
-            match resolver.reverse_lookup(target.address).await {
-                Ok(response) if response.iter().next().is_some() => {
-                    // resposta DNS reversa não vazia
-                    info!(
-                        "[PROBE {}] DNS reverso OK para {} (target: {})",
-                        probe.location, target.address, target.name
-                    );
-                    return true;
-                }
-                Ok(_) | Err(_) => {
-                    warn!(
-                        "[PROBE {}] Falha DNS reverso para {} (target: {})",
-                        probe.location, target.address, target.name
-                    );
-                }
+/// Resolução DNS reversa dos targets (usando trust-dns).
+async fn check_connectivity_dns(targets: &[Target], probe: &Probe, timeout: Duration) -> bool {
+    let Ok(resolver) = TokioAsyncResolver::tokio_from_system_conf() else {
+        return false;
+    };
+    for target in targets {
+        // Tenta resolver o nome reverso do IP
+        match tokio::time::timeout(timeout, resolver.reverse_lookup(target.address)).await {
+            Ok(Ok(response)) if response.iter().next().is_some() => {
+                // resposta DNS reversa não vazia
+                info!(
+                    "[PROBE {}] DNS reverso OK para {} (target: {})",
+                    probe.location, target.address, target.name
+                );
+                return true;
+            }
+            _ => {
+                warn!(
+                    "[PROBE {}] Falha DNS reverso para {} (target: {})",
+                    probe.location, target.address, target.name
+                );
             }
         }
     }
+    false
+}
 
-    // 3. ICMP/ping (fallback)
+/// ICMP/ping: uma tentativa rápida por target.
+async fn check_connectivity_icmp(
+    targets: &[Target],
+    probe: &Probe,
+    config: &Config,
+    limiter: &Arc<RateLimiter>,
+) -> bool {
     let ping_results = ping::ping_targets(
-        targets, probe, 1, // apenas 1 tentativa rápida
-        2, // timeout curto
+        targets,
+        probe,
+        1, // apenas 1 tentativa rápida
+        config.connectivity.timeout_secs,
         0, // ciclo fictício
+        Arc::clone(limiter),
     )
     .await;
     if ping_results.iter().any(|m| m.status == MetricStatus::Up) {
@@ -96,36 +712,231 @@ async fn check_connectivity_resilient(targets: &[Target], probe: &Probe, config:
         );
         return true;
     }
+    false
+}
 
-    warn!(
-        "[PROBE {}] Nenhum método de conectividade teve sucesso",
-        probe.location
+/// Target deve rodar neste ciclo? Sem `check_interval_secs` roda sempre.
+/// Tolera 1s de atraso do ticker para que intervalo igual ao ciclo não pule ciclos.
+fn is_due(
+    target: &Target,
+    last_checked: Option<&chrono::DateTime<Utc>>,
+    now: chrono::DateTime<Utc>,
+) -> bool {
+    match (target.check_interval_secs, last_checked) {
+        (Some(secs), Some(last)) => {
+            (now - *last).num_milliseconds() + 1000 >= i64::from(secs) * 1000
+        }
+        _ => true,
+    }
+}
+
+/// Alimenta as instâncias de consenso sombra e persiste o que teriam detectado.
+/// Nunca alerta: os eventos vão apenas para `shadow_outage_events`.
+async fn update_shadow_consensus(
+    shadow_state: &Mutex<ShadowConsensusSet>,
+    metrics: &[ConnectivityMetric],
+    persistence: &Persistence,
+    probe: &Probe,
+) {
+    let events = {
+        let mut shadows = shadow_state.lock().await;
+        if shadows.is_empty() {
+            return;
+        }
+        shadows.update(metrics)
+    };
+
+    for (name, event) in events {
+        debug!(
+            "[SHADOW {}] Instância '{}' detectou/encerrou outage: {:?}",
+            probe.location, name, event
+        );
+        persistence.shadow_outage(&name, &event).await;
+    }
+}
+
+/// Semeia o warmup e o estado inicial do scheduler a partir do histórico
+/// persistido (cold-start rápido).
+///
+/// - Cada target recebe como streak a quantidade de medições `Up` consecutivas
+///   mais recentes deste probe, dentro de `warmup_seed_max_age_secs`.
+/// - Se o histórico recente (métricas do probe ou `target_status`) mostra algum
+///   target `Up`, o probe já inicia em `Monitoring`, sem janela cega após restart.
+async fn seed_from_history(
+    storage: &dyn StorageBackend,
+    probe: &Probe,
+    config: &Config,
+    warmup: &mut TargetWarmupState,
+) -> SchedulerState {
+    let since = Utc::now() - chrono::Duration::seconds(config.warmup_seed_max_age_secs as i64);
+    let mut had_recent_up = false;
+
+    match storage
+        .list_recent_metrics_by_probe(probe.id, since, warmup.required_streak as i64)
+        .await
+    {
+        Ok(recent) => {
+            let mut by_target: HashMap<i32, Vec<&ConnectivityMetric>> = HashMap::new();
+            for metric in &recent {
+                by_target.entry(metric.target_id).or_default().push(metric);
+            }
+            for (target_id, metrics) in by_target {
+                // Métricas já vêm da mais recente para a mais antiga
+                let streak = metrics
+                    .iter()
+                    .take_while(|m| m.status == MetricStatus::Up)
+                    .count();
+                had_recent_up |= streak > 0;
+                warmup.seed(target_id, streak);
+            }
+        }
+        Err(e) => warn!(
+            "[PROBE {}] Falha ao carregar métricas recentes para warmup: {:?}",
+            probe.location, e
+        ),
+    }
+
+    if !had_recent_up {
+        match storage.list_all_target_status().await {
+            Ok(statuses) => {
+                had_recent_up = statuses
+                    .iter()
+                    .any(|s| s.last_status == MetricStatus::Up && s.last_change >= since);
+            }
+            Err(e) => warn!(
+                "[PROBE {}] Falha ao carregar target_status para warmup: {:?}",
+                probe.location, e
+            ),
+        }
+    }
+
+    info!(
+        "[PROBE {}] Cold-start: {} target(s) já aquecido(s) pelo histórico",
+        probe.location,
+        warmup.warmed_count()
     );
-    false
+
+    if had_recent_up {
+        info!(
+            "[PROBE {}] Histórico recente indica conectividade, iniciando direto em monitoramento.",
+            probe.location
+        );
+        SchedulerState::Monitoring
+    } else {
+        SchedulerState::WaitingForInternet
+    }
 }
 
 /// Loop principal do scheduler para um probe.
 /// Executa ciclos de monitoramento, coleta métricas e persiste resultados.
+/// - Semeia warmup/estado a partir do histórico (cold-start rápido)
 /// - Aguarda internet antes de iniciar ciclos
 /// - Usa TargetWarmupState para evitar falsos positivos
 /// - Integra com storage, ping e consensus
-
+/// - Publica um `Snapshot` ao fim de cada ciclo
+/// - Registra um heartbeat no início de cada ciclo (watchdog do supervisor)
 pub async fn run_scheduler(
     probe: Probe,
-    targets: Vec<Target>,
-    config: Arc<Config>,
-    storage: Arc<Storage>,
-    consensus_state: Arc<Mutex<ConsensusState>>,
+    all_targets: Vec<Target>,
+    ctx: SchedulerContext,
+    heartbeat: Heartbeat,
 ) {
-    let mut state: SchedulerState = SchedulerState::WaitingForInternet;
+    let SchedulerContext {
+        config,
+        storage,
+        persistence,
+        consensus_state,
+        shadow_state,
+        exporters,
+        snapshot,
+        checks,
+    } = ctx;
+    // A verificação de "tem internet" usa os canários, se configurados: uma
+    // queda do lado dos targets não prende o probe em WaitingForInternet
+    let canaries = canary_targets(&config.connectivity.canaries);
+    let mut paused: HashSet<i32> = all_targets
+        .iter()
+        .filter(|t| !t.enabled)
+        .map(|t| t.id)
+        .collect();
     let mut warmup: TargetWarmupState = TargetWarmupState::new(3);
+    let mut state: SchedulerState =
+        seed_from_history(storage.as_ref(), &probe, &config, &mut warmup).await;
     let mut cycle_number = 0;
+    // Targets com intervalo próprio (templates): último check e último
+    // resultado, reaproveitado no consenso nos ciclos em que não rodam
+    let mut last_checked: HashMap<i32, chrono::DateTime<Utc>> = HashMap::new();
+    let mut carried: HashMap<i32, ConnectivityMetric> = HashMap::new();
+    let mut baselines = BaselineTracker::new();
+    let mut asymmetry_schedule = PeriodicSchedule::new("ASYMMETRY");
+    let mut bufferbloat_schedule = PeriodicSchedule::new("BUFFERBLOAT");
+    let mut min_rtts = config
+        .bufferbloat
+        .as_ref()
+        .map(|b| MinRttTracker::new(b.min_rtt_window_secs));
+    let load_client = reqwest::Client::new();
+    let mut traceroute_schedule = PeriodicSchedule::new("TRACEROUTE");
+    let asn_resolver = config
+        .traceroute
+        .as_ref()
+        .filter(|t| t.asn_lookup)
+        .and_then(|_| match AsnResolver::from_system_conf() {
+            Ok(resolver) => Some(Arc::new(resolver)),
+            Err(e) => {
+                warn!(
+                    "[TRACEROUTE {}] Resolver DNS indisponível; saltos sem ASN: {}",
+                    probe.location, e
+                );
+                None
+            }
+        });
 
-    let mut ticker: tokio::time::Interval =
-        interval(Duration::from_secs(config.cycle_interval_secs));
+    let cycle_interval = Duration::from_secs(config.cycle_interval_secs);
+    let mut ticker: tokio::time::Interval = interval(cycle_interval);
+    // Ciclo mais longo que o intervalo: por padrão os ticks perdidos são
+    // descartados em vez de disparados em rajada
+    ticker.set_missed_tick_behavior(if config.skip_missed_cycles {
+        MissedTickBehavior::Skip
+    } else {
+        MissedTickBehavior::Burst
+    });
+    let check_budget = cycle_interval.mul_f64(config.cycle_budget_percent / 100.0);
+    let mut overruns = CycleOverrunStats::default();
     loop {
         ticker.tick().await;
+        heartbeat.beat();
         let now = Utc::now();
+        let cycle_started = Instant::now();
+        // Prazo dos checks do ciclo (orçamento), a partir do tick
+        let deadline = tokio::time::Instant::from_std(cycle_started) + check_budget;
+        // Targets pausados ficam fora do ciclo: sem checks, sem métricas e
+        // fora do consenso
+        let newly_paused =
+            refresh_paused(storage.as_ref(), &probe, &all_targets, &mut paused).await;
+        if !newly_paused.is_empty() {
+            // Eventos por target abertos na pausa não seriam mais encerrados
+            let closed: Vec<OutageEvent> = {
+                let mut consensus = consensus_state.lock().await;
+                newly_paused
+                    .iter()
+                    .flat_map(|&id| consensus.forget_target(id))
+                    .collect()
+            };
+            for event in &closed {
+                exporters.export_outage(event, &probe.location);
+                persistence.outage(event).await;
+            }
+        }
+        let targets: Vec<Target> = all_targets
+            .iter()
+            .filter(|t| !paused.contains(&t.id))
+            .cloned()
+            .collect();
+        let internet_targets: &[Target] = if canaries.is_empty() {
+            &targets
+        } else {
+            &canaries
+        };
 
         match state {
             SchedulerState::WaitingForInternet => {
@@ -135,56 +946,107 @@ pub async fn run_scheduler(
                 );
 
                 // Coleta métricas (todas Down) mesmo sem internet
-                let metrics = ping::ping_targets(
-                    &targets,
+                let host_sample = sample_host(&config, &probe);
+                let cycle_metrics = checks
+                    .run_all(
+                        &targets,
+                        &probe,
+                        0, // ciclo fictício
+                        host_sample.degraded_conditions,
+                        Some(deadline),
+                    )
+                    .await;
+                report_budget_exceeded(
                     &probe,
-                    config.ping_count,
-                    config.timeout_secs,
-                    0, // ciclo fictício
+                    &config,
+                    &cycle_metrics.aborted,
+                    &mut overruns,
+                    &snapshot,
+                );
+                let mut metrics = cycle_metrics.metrics;
+                let host_rejected = apply_host_sample(
+                    &config,
+                    &probe,
+                    &snapshot,
+                    &persistence,
+                    &host_sample,
+                    &mut metrics,
                 )
                 .await;
 
-                let now: chrono::DateTime<Utc> = Utc::now();
-
                 // Atualiza o consenso e loga o histórico
-                let outage_event_opt: Option<OutageEvent> = {
+                let (outage_event_opt, escalated, window, decision) = {
                     let mut consensus: MutexGuard<'_, ConsensusState> =
                         consensus_state.lock().await;
+                    let now = consensus.now();
                     debug!(
                         "[CONSENSUS {}] [WAITING] Lock ConsensusState OK, histórico: {} ciclos",
                         probe.location,
-                        consensus.history.len()
+                        consensus.history_len()
                     );
-                    let result: Option<OutageEvent> = consensus.update(metrics.clone(), now);
+                    let mut result: Option<OutageEvent> = if host_rejected {
+                        None
+                    } else {
+                        consensus.update(&metrics)
+                    };
+                    if let Some(event) = result.as_mut() {
+                        diagnose_outage(
+                            &config,
+                            event,
+                            &mut consensus,
+                            &metrics,
+                            &targets,
+                            &probe,
+                            checks.limiter(),
+                        )
+                        .await;
+                        detect_captive_portal(&config, event, &mut consensus, &probe).await;
+                    }
                     debug!(
                         "[CONSENSUS {}] [WAITING] ConsensusState::update = {:?} | Histórico: {} ciclos",
                         probe.location,
                         result,
-                        consensus.history.len()
+                        consensus.history_len()
                     );
-                    result
+                    let escalated = consensus.escalate(&config.escalation);
+                    let decision =
+                        take_decision(&config, &mut consensus, &probe, 0, now, host_rejected);
+                    (result, escalated, consensus.window_stats(), decision)
                 };
+                if let Some(decision) = decision {
+                    persistence.consensus_decision(decision).await;
+                }
+                snapshot.apply_cycle(&metrics, outage_event_opt.as_ref(), window);
 
                 if let Some(outage_event) = outage_event_opt {
                     info!(
                         "[CONSENSUS {}] [WAITING] Outage detectado/encerrado: {:?}",
                         probe.location, outage_event
                     );
-                    if let Err(e) = storage.insert_outage_event(&outage_event).await {
-                        error!(
-                            "[CONSENSUS {}] [WAITING] Falha ao persistir outage: {:?}",
-                            probe.location, e
-                        );
-                    }
+                    exporters.export_outage(&outage_event, &probe.location);
+                    exporters.notify_outage(
+                        NotificationKind::for_event(&outage_event),
+                        &outage_event,
+                        &probe.location,
+                    );
+                    persistence.outage(&outage_event).await;
                 } else {
                     debug!(
                         "[CONSENSUS {}] [WAITING] Sem outages detectados neste ciclo (sem internet)",
                         probe.location
                     );
                 }
+                if let Some(event) = escalated {
+                    exporters.notify_outage(NotificationKind::Escalated, &event, &probe.location);
+                }
+                if !host_rejected {
+                    update_shadow_consensus(&shadow_state, &metrics, &persistence, &probe).await;
+                }
 
                 // Checa se a internet voltou
-                if check_connectivity_resilient(&targets, &probe, &config).await {
+                if check_connectivity_resilient(internet_targets, &probe, &config, checks.limiter())
+                    .await
+                {
                     info!(
                         "[PROBE {}] Internet detectada, iniciando monitoramento.",
                         probe.location
@@ -195,13 +1057,17 @@ pub async fn run_scheduler(
 
             SchedulerState::Monitoring => {
                 cycle_number += 1;
+                let host_sample = sample_host(&config, &probe);
                 let cycle = Cycle {
                     id: 0,
                     started_at: now,
                     ended_at: None,
                     cycle_number,
                     probe_count: 1,
+                    degraded_conditions: host_sample.degraded_conditions,
                 };
+                // Sem banco o ciclo segue sem id (0): as métricas vão para o
+                // spool da persistência e são gravadas com cycle_id nulo
                 let cycle_id = match storage.insert_cycle(&cycle).await {
                     Ok(id) => id,
                     Err(e) => {
@@ -209,61 +1075,225 @@ pub async fn run_scheduler(
                             "[PROBE {}] Falha ao inserir ciclo no banco: {:?}",
                             probe.location, e
                         );
-                        continue;
+                        0
                     }
                 };
 
-                let metrics: Vec<ConnectivityMetric> = ping::ping_targets(
-                    &targets,
+                let due: Vec<Target> = targets
+                    .iter()
+                    .filter(|t| is_due(t, last_checked.get(&t.id), now))
+                    .cloned()
+                    .collect();
+                // Teste de banda: os checks rodam com o link já saturado (sem
+                // os checks pesados, que disputariam o link com a carga)
+                let load_config = config.bufferbloat.as_ref().filter(|b| {
+                    bufferbloat_schedule.due(
+                        b.interval_cycles,
+                        cycle_number,
+                        host_sample.degraded_conditions,
+                        &probe.location,
+                    )
+                });
+                let (cycle_metrics, load): (CycleMetrics, Option<LoadResult>) = match load_config {
+                    Some(load_config) => {
+                        let ramp_up = Duration::from_secs(load_config.ramp_up_secs);
+                        let (metrics, load) = tokio::join!(
+                            async {
+                                tokio::time::sleep(ramp_up).await;
+                                checks
+                                    .run_all(&due, &probe, cycle_id, true, Some(deadline))
+                                    .await
+                            },
+                            bufferbloat::run_load(&load_client, load_config)
+                        );
+                        (metrics, Some(load))
+                    }
+                    None => (
+                        checks
+                            .run_all(
+                                &due,
+                                &probe,
+                                cycle_id,
+                                host_sample.degraded_conditions,
+                                Some(deadline),
+                            )
+                            .await,
+                        None,
+                    ),
+                };
+                let CycleMetrics {
+                    mut metrics,
+                    signals,
+                    aborted,
+                } = cycle_metrics;
+                report_budget_exceeded(&probe, &config, &aborted, &mut overruns, &snapshot);
+                let host_rejected = apply_host_sample(
+                    &config,
                     &probe,
-                    config.ping_count,
-                    config.timeout_secs,
-                    cycle_id,
+                    &snapshot,
+                    &persistence,
+                    &host_sample,
+                    &mut metrics,
                 )
                 .await;
+                // RTT mínimo e latência sob carga por target
+                if let Some(min_rtts) = min_rtts.as_mut()
+                    && !host_rejected
+                {
+                    let deltas = min_rtts.observe(&mut metrics, load.as_ref());
+                    if let Some(load) = &load {
+                        log_bufferbloat(&probe, load, deltas);
+                    }
+                }
+                // Thresholds de RTT/perda por target: Up acima deles vira
+                // Degraded (ciclos sob carga ficam de fora)
+                if load.is_none() {
+                    degradation::apply(&mut metrics, &due);
+                }
+                for target in &due {
+                    if target.check_interval_secs.is_some() {
+                        last_checked.insert(target.id, now);
+                    }
+                }
+                for metric in metrics.iter().filter(|_| !host_rejected) {
+                    if last_checked.contains_key(&metric.target_id) {
+                        carried.insert(metric.target_id, metric.clone());
+                    }
+                }
+                // Consenso e sombra veem todos os targets; os fora do
+                // intervalo entram com o último resultado conhecido
+                let consensus_metrics: Vec<ConnectivityMetric> = metrics
+                    .iter()
+                    .cloned()
+                    .chain(
+                        targets
+                            .iter()
+                            .filter(|t| !due.iter().any(|d| d.id == t.id))
+                            .filter_map(|t| carried.get(&t.id).cloned()),
+                    )
+                    .collect();
 
-                for metric in &metrics {
-                    if let Err(e) = storage.insert_connectivity_metric(metric).await {
-                        error!(
-                            "[PROBE {}] Falha ao persistir métrica: {:?} (target_id: {})",
-                            probe.location, e, metric.target_id
-                        );
+                // Baselines de latência/perda (fora dos ciclos descartados e
+                // dos ciclos sob carga)
+                if let Some(anomaly_config) = &config.anomaly
+                    && !host_rejected
+                    && load.is_none()
+                {
+                    let events = baselines.observe(&mut metrics, anomaly_config);
+                    for event in &events {
+                        match event.end_time {
+                            None => warn!(
+                                "[ANOMALY {}] Target {}: {} {:.1} ({:.1}σ acima da baseline {:.1} ± {:.1})",
+                                probe.location,
+                                event.target_id,
+                                event.kind.as_str(),
+                                event.peak_value,
+                                event.peak_sigmas,
+                                event.baseline_mean,
+                                event.baseline_stddev
+                            ),
+                            Some(_) => info!(
+                                "[ANOMALY {}] Target {}: {} de volta à baseline (pico {:.1}, {:.1}σ)",
+                                probe.location,
+                                event.target_id,
+                                event.kind.as_str(),
+                                event.peak_value,
+                                event.peak_sigmas
+                            ),
+                        }
+                        persistence.anomaly(event).await;
                     }
+                    snapshot.apply_anomalies(&events);
+                }
+
+                // Gravação em lote pela task de persistência
+                persistence.metrics(&metrics).await;
+                persistence.metrics(&signals).await;
+                if config.raw_samples {
+                    persistence.raw_samples(&metrics).await;
+                    persistence.raw_samples(&signals).await;
                 }
 
+                // Destinos secundários (Influx, streaming) em background
+                exporters.export_metrics(&metrics, &probe.location);
+
                 for metric in &metrics {
-                    let is_success: bool = metric.status == MetricStatus::Up;
-                    let warmed: bool = warmup.update(metric.target_id, is_success);
-                    debug!(
-                        "[PROBE {}] Target {} warmup: {} (status: {:?})",
-                        probe.location, metric.target_id, warmed, metric.status
-                    );
-                    if let Err(e) = storage
-                        .set_target_status(metric.target_id, &metric.status)
-                        .await
-                    {
-                        warn!(
-                            "[PROBE {}] Falha ao atualizar status do target {}: {:?}",
-                            probe.location, metric.target_id, e
+                    if !host_rejected {
+                        let is_success: bool = metric.status == MetricStatus::Up;
+                        let warmed: bool = warmup.update(metric.target_id, is_success);
+                        debug!(
+                            "[PROBE {}] Target {} warmup: {} (status: {:?})",
+                            probe.location, metric.target_id, warmed, metric.status
                         );
                     }
+                    persistence.target_status(metric).await;
                 }
 
                 // 3️⃣ INTEGRAÇÃO DO CONSENSO: Atualiza ConsensusState e persiste outages
                 let mut consensus: MutexGuard<'_, ConsensusState> = consensus_state.lock().await;
-                let now: chrono::DateTime<Utc> = Utc::now();
+                let now = consensus.now();
+                // Ciclo medido com o host sem recursos não avança a janela
+                let mut outage_event_opt: Option<OutageEvent> = if host_rejected {
+                    None
+                } else {
+                    consensus.update(&consensus_metrics)
+                };
+                if let Some(event) = outage_event_opt.as_mut() {
+                    diagnose_outage(
+                        &config,
+                        event,
+                        &mut consensus,
+                        &consensus_metrics,
+                        &targets,
+                        &probe,
+                        checks.limiter(),
+                    )
+                    .await;
+                    detect_captive_portal(&config, event, &mut consensus, &probe).await;
+                }
+                // Eventos por target, fora do consenso: outages parciais e degradações
+                let target_events: Vec<OutageEvent> = if host_rejected {
+                    Vec::new()
+                } else {
+                    let mut events = consensus.update_partial_outages(&consensus_metrics);
+                    events.extend(consensus.update_degradation(&consensus_metrics));
+                    events
+                };
+                let escalated: Option<OutageEvent> = consensus.escalate(&config.escalation);
+                if let Some(decision) = take_decision(
+                    &config,
+                    &mut consensus,
+                    &probe,
+                    cycle_id,
+                    now,
+                    host_rejected,
+                ) {
+                    persistence.consensus_decision(decision).await;
+                }
+                let window = consensus.window_stats();
+                debug!(
+                    "[CONSENSO {}] Janela: {} ciclos, {} entradas com falha, ~{}/{} bytes, {} evictions",
+                    probe.location,
+                    window.cycles,
+                    window.failed_entries,
+                    window.approx_bytes,
+                    window.max_bytes,
+                    window.evictions
+                );
+                snapshot.apply_cycle(&metrics, outage_event_opt.as_ref(), window);
 
-                if let Some(outage_event) = consensus.update(metrics.clone(), now) {
+                if let Some(outage_event) = outage_event_opt {
                     info!(
                         "[CONSENSO {}] Outage detectado: {:?}",
                         probe.location, outage_event
                     );
-                    if let Err(e) = storage.insert_outage_event(&outage_event).await {
-                        error!(
-                            "[CONSENSO {}] Falha ao persistir outage: {:?}",
-                            probe.location, e
-                        );
-                    }
+                    exporters.export_outage(&outage_event, &probe.location);
+                    exporters.notify_outage(
+                        NotificationKind::for_event(&outage_event),
+                        &outage_event,
+                        &probe.location,
+                    );
+                    persistence.outage(&outage_event).await;
                 } else {
                     info!(
                         "[CONSENSO {}] Sem outages detectados neste ciclo",
@@ -271,15 +1301,475 @@ pub async fn run_scheduler(
                     );
                 }
                 drop(consensus);
+                for event in &target_events {
+                    exporters.export_outage(event, &probe.location);
+                    exporters.notify_outage(
+                        NotificationKind::for_event(event),
+                        event,
+                        &probe.location,
+                    );
+                    persistence.outage(event).await;
+                }
+                if let Some(event) = escalated {
+                    exporters.notify_outage(NotificationKind::Escalated, &event, &probe.location);
+                }
+                if !host_rejected {
+                    update_shadow_consensus(
+                        &shadow_state,
+                        &consensus_metrics,
+                        &persistence,
+                        &probe,
+                    )
+                    .await;
+                }
+                if let Some(asymmetry_config) = &config.asymmetry
+                    && asymmetry_schedule.due(
+                        asymmetry_config.interval_cycles,
+                        cycle_number,
+                        host_sample.degraded_conditions,
+                        &probe.location,
+                    )
+                {
+                    spawn_asymmetry_measurement(
+                        asymmetry_config,
+                        &persistence,
+                        &targets,
+                        &probe,
+                        cycle_id,
+                    );
+                }
+                if let Some(traceroute_config) = &config.traceroute
+                    && traceroute_schedule.due(
+                        traceroute_config.interval_cycles,
+                        cycle_number,
+                        host_sample.degraded_conditions,
+                        &probe.location,
+                    )
+                {
+                    spawn_traceroutes(
+                        traceroute_config,
+                        asn_resolver.clone(),
+                        &persistence,
+                        &targets,
+                        &probe,
+                        cycle_id,
+                    );
+                }
 
-                if !check_connectivity_resilient(&targets, &probe, &config).await {
+                let connected = check_connectivity_resilient(
+                    internet_targets,
+                    &probe,
+                    &config,
+                    checks.limiter(),
+                )
+                .await;
+
+                // Resumo do ciclo (todos os targets, os fora do intervalo com
+                // o último resultado conhecido)
+                let summary = CycleSummary::from_metrics(
+                    cycle_id,
+                    probe.id,
+                    now,
+                    &consensus_metrics,
+                    cycle_started.elapsed().as_secs_f64() * 1000.0,
+                );
+                persistence.cycle_summary(&summary).await;
+
+                if !connected {
                     warn!(
                         "[PROBE {}] Perda de conectividade detectada, retornando para WAITING_FOR_INTERNET.",
                         probe.location
                     );
                     state = SchedulerState::WaitingForInternet;
+                } else {
+                    // Ciclo concluído com internet: o monitor está vivo
+                    exporters.ping_dead_man_switch(&probe);
                 }
             }
         }
+
+        let elapsed = cycle_started.elapsed();
+        if elapsed > cycle_interval {
+            overruns.record(elapsed, cycle_interval, config.skip_missed_cycles);
+            warn!(
+                "[PROBE {}] Ciclo levou {:.1}s, acima do intervalo de {}s ({} estouro(s), {} ciclo(s) pulado(s)); considere aumentar cycle_interval_secs",
+                probe.location,
+                elapsed.as_secs_f64(),
+                cycle_interval.as_secs(),
+                overruns.overruns,
+                overruns.skipped_cycles
+            );
+            snapshot.set_cycle_overruns(probe.id, overruns);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::Check;
+    use crate::decisions::DecisionOutcome;
+    use crate::memory_storage::MemoryStorage;
+    use crate::types::{GeoLocation, MetricType, OutageEventType};
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    /// Check que devolve, por target, os status roteirizados (`.` Up, `x`
+    /// Down, `t` Timeout), um por ciclo; depois do roteiro, Up.
+    struct ScriptedCheck {
+        scripts: std::sync::Mutex<HashMap<i32, VecDeque<MetricStatus>>>,
+    }
+
+    impl ScriptedCheck {
+        fn new(scripts: &[&str]) -> Self {
+            let scripts = scripts
+                .iter()
+                .enumerate()
+                .map(|(index, script)| {
+                    let statuses = script
+                        .chars()
+                        .map(|c| match c {
+                            '.' => MetricStatus::Up,
+                            'x' => MetricStatus::Down,
+                            't' => MetricStatus::Timeout,
+                            other => panic!("status `{}` inválido", other),
+                        })
+                        .collect();
+                    (index as i32 + 1, statuses)
+                })
+                .collect();
+            Self {
+                scripts: std::sync::Mutex::new(scripts),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Check for ScriptedCheck {
+        async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+            let status = self
+                .scripts
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get_mut(&target.id)
+                .and_then(VecDeque::pop_front)
+                .unwrap_or(MetricStatus::Up);
+            let up = status == MetricStatus::Up;
+            ConnectivityMetric {
+                id: 0,
+                cycle_id: 0,
+                probe_id: probe.id,
+                target_id: target.id,
+                timestamp: Utc::now(),
+                metric_type: MetricType::PingIpv4,
+                status,
+                response_time_ms: up.then_some(1.0),
+                packet_loss_percent: Some(if up { 0 } else { 100 }),
+                error: None,
+                details: None,
+                provenance: None,
+                attempts: Vec::new(),
+            }
+        }
+    }
+
+    /// Roda o scheduler de verdade (builder, supervisor, persistência em
+    /// `MemoryStorage`) sobre os roteiros, um target por roteiro, até
+    /// `cycles` ciclos gravados. A verificação de conectividade conecta num
+    /// listener local; o probe parte em monitoramento (um Up recente no
+    /// `target_status`). Com `pause = Some((target, n))`, o target é pausado
+    /// depois de `n` ciclos gravados.
+    async fn run_scripted(
+        scripts: &[&str],
+        toml: &str,
+        cycles: usize,
+        pause: Option<(i32, usize)>,
+    ) -> Arc<MemoryStorage> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                drop(stream);
+            }
+        });
+
+        let mut config = Config::from_toml(toml);
+        config.cycle_interval_secs = 1;
+        config.connectivity.methods = vec![ConnectivityMethod::Tcp];
+        config.connectivity.tcp_ports = vec![port];
+        config.connectivity.canaries = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+        config.checks.tcp_port = port;
+        config.host.reject = false;
+        config.persistence.spool_max_bytes = 0;
+        let config = Arc::new(config);
+
+        let probe = Probe {
+            id: 1,
+            location: "teste".to_string(),
+            ip_address: None,
+            provider: None,
+            created_at: None,
+            geo: GeoLocation::default(),
+        };
+        let targets: Vec<Target> = (1..=scripts.len() as i32)
+            .map(|id| Target {
+                id,
+                type_: "scripted".to_string(),
+                ..Target::unregistered(format!("t{}", id), IpAddr::V4(Ipv4Addr::LOCALHOST))
+            })
+            .collect();
+
+        let storage = Arc::new(MemoryStorage::new());
+        storage
+            .set_target_status(
+                1,
+                &MetricStatus::Up,
+                None,
+                Some(probe.id),
+                Some(Utc::now()),
+                None,
+            )
+            .await
+            .unwrap();
+        let backend: Arc<dyn StorageBackend> = storage.clone();
+        let snapshot = Arc::new(SnapshotStore::new());
+        let checks = CheckRegistry::single("scripted", Arc::new(ScriptedCheck::new(scripts)));
+        let handle = SchedulerBuilder::new(probe, targets, Arc::clone(&config))
+            .unwrap()
+            .storage(Arc::clone(&backend))
+            .persistence(Persistence::spawn(
+                backend,
+                &config.persistence,
+                Arc::clone(&snapshot),
+            ))
+            .snapshot(snapshot)
+            .checks(Arc::new(checks))
+            .start()
+            .unwrap();
+
+        // O resumo é a última gravação do ciclo
+        let deadline = Instant::now() + Duration::from_secs(cycles as u64 * 3 + 10);
+        while storage.cycle_summaries().len() < cycles {
+            if let Some((target_id, after)) = pause
+                && storage.cycle_summaries().len() >= after
+            {
+                storage.set_enabled(target_id, false);
+            }
+            assert!(
+                Instant::now() < deadline,
+                "scheduler não completou os ciclos"
+            );
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        handle.stop().await;
+        accept.abort();
+        storage
+    }
+
+    fn statuses_of(storage: &MemoryStorage, target_id: i32, cycles: usize) -> String {
+        let mut metrics: Vec<ConnectivityMetric> = storage
+            .metrics()
+            .into_iter()
+            .filter(|m| m.target_id == target_id && m.cycle_id as usize <= cycles)
+            .collect();
+        metrics.sort_by_key(|m| m.cycle_id);
+        metrics
+            .iter()
+            .map(|m| match m.status {
+                MetricStatus::Up => '.',
+                MetricStatus::Down => 'x',
+                MetricStatus::Timeout => 't',
+                MetricStatus::Degraded => 'd',
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn outage_abre_e_fecha_com_o_consenso() {
+        let scripts = ["..xxx...", "..xxt...", "........"];
+        let storage = run_scripted(
+            &scripts,
+            r#"
+            database_url = "postgres://teste"
+            fail_threshold = 2
+            consensus = 2
+            outage_recovery_cycles = 1
+            partial_outage_recovery_cycles = 1
+            [consensus_decisions]
+            "#,
+            8,
+            None,
+        )
+        .await;
+
+        let cycles = storage.cycles();
+        assert!(cycles.len() >= 8);
+        for (index, cycle) in cycles.iter().take(8).enumerate() {
+            assert_eq!(cycle.id, index as i64 + 1);
+            assert_eq!(cycle.cycle_number, index as i32 + 1);
+        }
+        for (index, script) in scripts.iter().enumerate() {
+            assert_eq!(statuses_of(&storage, index as i32 + 1, 8), *script);
+        }
+
+        // Consenso no ciclo 4 (2º ciclo de falha dos dois targets), perdido
+        // no ciclo 6
+        let outages: Vec<OutageEvent> = storage
+            .outages()
+            .into_iter()
+            .filter(|o| o.event_type == OutageEventType::Outage)
+            .collect();
+        assert_eq!(outages.len(), 1, "{:?}", outages);
+        let outage = &outages[0];
+        let mut affected = outage.affected_targets.clone();
+        affected.sort();
+        assert_eq!(affected, vec![1, 2]);
+        assert_eq!(outage.affected_probes, Some(vec![1]));
+        assert!(outage.start_time >= cycles[3].started_at);
+        assert!(outage.start_time < cycles[4].started_at);
+        let end = outage.end_time.expect("outage encerrado");
+        assert!(end >= cycles[5].started_at);
+        assert!(end < cycles[6].started_at);
+        assert_eq!(
+            outage.duration_seconds,
+            Some((end - outage.start_time).num_seconds() as i32)
+        );
+
+        let mut partials: Vec<(Vec<i32>, bool)> = storage
+            .outages()
+            .into_iter()
+            .filter(|o| o.event_type == OutageEventType::Partial)
+            .map(|o| (o.affected_targets, o.end_time.is_some()))
+            .collect();
+        partials.sort();
+        assert_eq!(partials, vec![(vec![1], true), (vec![2], true)]);
+
+        let decisions: Vec<(i64, DecisionOutcome)> = storage
+            .decisions()
+            .into_iter()
+            .filter(|d| d.cycle_id <= 8)
+            .map(|d| (d.cycle_id, d.outcome))
+            .collect();
+        assert_eq!(
+            decisions,
+            vec![
+                (1, DecisionOutcome::NoOutage),
+                (2, DecisionOutcome::NoOutage),
+                (3, DecisionOutcome::NoOutage),
+                (4, DecisionOutcome::Opened),
+                (5, DecisionOutcome::Ongoing),
+                (6, DecisionOutcome::Closed),
+                (7, DecisionOutcome::NoOutage),
+                (8, DecisionOutcome::NoOutage),
+            ]
+        );
+
+        // Só as mudanças de status, depois do Up semeado
+        let transitions = storage.transitions();
+        assert!(
+            transitions
+                .iter()
+                .all(|t| t.probe_id == Some(1) && t.at <= Utc::now())
+        );
+        // Cada transição aponta para a métrica que a causou
+        let metrics = storage.metrics();
+        for transition in transitions.iter().skip(1) {
+            let metric = transition
+                .metric_id
+                .and_then(|id| metrics.iter().find(|m| m.id == id))
+                .expect("transição sem a métrica que a causou");
+            assert_eq!(metric.target_id, transition.target_id);
+            assert_eq!(metric.status, transition.status);
+            assert_eq!(metric.timestamp, transition.at);
+        }
+        let target_1: Vec<MetricStatus> = transitions
+            .into_iter()
+            .skip(1)
+            .filter(|transition| transition.target_id == 1)
+            .map(|transition| transition.status)
+            .collect();
+        assert_eq!(target_1, vec![MetricStatus::Down, MetricStatus::Up]);
+    }
+
+    #[tokio::test]
+    async fn falha_de_um_target_nao_abre_outage() {
+        let scripts = ["..xxxx..", "........", "........"];
+        let storage = run_scripted(
+            &scripts,
+            r#"
+            database_url = "postgres://teste"
+            fail_threshold = 2
+            consensus = 2
+            partial_outage_recovery_cycles = 2
+            "#,
+            8,
+            None,
+        )
+        .await;
+
+        assert_eq!(statuses_of(&storage, 1, 8), scripts[0]);
+        let outages = storage.outages();
+        assert!(
+            outages
+                .iter()
+                .all(|o| o.event_type == OutageEventType::Partial),
+            "{:?}",
+            outages
+        );
+        assert_eq!(outages.len(), 1);
+        assert_eq!(outages[0].affected_targets, vec![1]);
+        assert!(outages[0].end_time.is_some());
+        // Sem `[consensus_decisions]`, nada é registrado
+        assert!(storage.decisions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pausa_encerra_o_outage_parcial_aberto() {
+        let scripts = ["xxxxxxxx", "........", "........"];
+        let storage = run_scripted(
+            &scripts,
+            r#"
+            database_url = "postgres://teste"
+            fail_threshold = 2
+            consensus = 2
+            "#,
+            6,
+            Some((1, 3)),
+        )
+        .await;
+
+        // O target some dos ciclos depois da pausa
+        let measured = statuses_of(&storage, 1, 6);
+        assert!(
+            (3..6).contains(&measured.len()),
+            "target pausado ainda medido: {}",
+            measured
+        );
+        let cycles = storage.cycles();
+        let last_measured = storage
+            .metrics()
+            .into_iter()
+            .filter(|m| m.target_id == 1)
+            .map(|m| m.timestamp)
+            .max()
+            .unwrap();
+
+        let partials: Vec<OutageEvent> = storage
+            .outages()
+            .into_iter()
+            .filter(|o| o.event_type == OutageEventType::Partial)
+            .collect();
+        assert_eq!(partials.len(), 1, "{:?}", partials);
+        let partial = &partials[0];
+        assert_eq!(partial.affected_targets, vec![1]);
+        assert!(partial.start_time >= cycles[1].started_at);
+        let end = partial.end_time.expect("parcial encerrado na pausa");
+        assert!(end > last_measured);
+        assert_eq!(
+            partial.details.as_ref().and_then(|d| d.get("closed_by")),
+            Some(&serde_json::json!("paused"))
+        );
     }
 }
@@ -0,0 +1,59 @@
+//! shadow.rs — Consenso em modo sombra ("shadow mode")
+//!
+//! Executa instâncias adicionais de `ConsensusState` com parâmetros alternativos
+//! sobre as mesmas métricas do consenso principal. O que cada instância teria
+//! detectado é gravado em `shadow_outage_events`, sem disparar alertas nem
+//! afetar `outage_events`, permitindo avaliar uma mudança de thresholds com
+//! dados reais antes de promovê-la.
+
+use crate::config::ShadowConsensusConfig;
+use crate::consensus::ConsensusState;
+use crate::types::{ConnectivityMetric, OutageEvent};
+
+/// Uma instância de consenso sombra.
+#[derive(Debug, Clone)]
+pub struct ShadowConsensus {
+    pub name: String,
+    state: ConsensusState,
+}
+
+/// Conjunto de instâncias sombra alimentadas em paralelo.
+#[derive(Debug, Clone, Default)]
+pub struct ShadowConsensusSet {
+    instances: Vec<ShadowConsensus>,
+}
+
+impl ShadowConsensusSet {
+    /// Cria as instâncias a partir da configuração.
+    pub fn new(configs: &[ShadowConsensusConfig], max_history_bytes: usize) -> Self {
+        Self {
+            instances: configs
+                .iter()
+                .map(|c| ShadowConsensus {
+                    name: c.name.clone(),
+                    state: ConsensusState::new(c.fail_threshold, c.consensus, None)
+                        .with_max_history_bytes(max_history_bytes)
+                        .with_fail_percent(c.fail_percent),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Alimenta todas as instâncias com o ciclo e retorna os eventos que cada
+    /// uma teria aberto/encerrado, identificados pelo nome da instância.
+    pub fn update(&mut self, cycle_results: &[ConnectivityMetric]) -> Vec<(String, OutageEvent)> {
+        self.instances
+            .iter_mut()
+            .filter_map(|shadow| {
+                shadow
+                    .state
+                    .update(cycle_results)
+                    .map(|event| (shadow.name.clone(), event))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,374 @@
+//! sink.rs — Destinos de métricas e outages atrás de um trait comum
+//!
+//! Cada destino (Influx, Prometheus remote-write, streaming NATS/Kafka e os
+//! `[[sinks]]` stdout, arquivo e webhook) implementa `MetricSink`; o
+//! `Exporters` de cada grupo entrega o mesmo lote a todos, cada um na sua
+//! própria task, de modo que um destino lento não atrasa os demais. O
+//! PostgreSQL continua sendo o registro principal, pelo pipeline de
+//! persistência (fila, lotes e spool), e não passa por aqui.
+
+use crate::config::{SinkConfig, SinkKind};
+use crate::exporters::TargetLabel;
+use crate::influx::InfluxSink;
+use crate::notify::NotificationKind;
+use crate::remote_write::RemoteWriteSink;
+use crate::streaming::EventStream;
+use crate::types::{ConnectivityMetric, OutageEvent};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Destino das métricas de cada ciclo e, opcionalmente, dos outages.
+#[async_trait]
+pub trait MetricSink: Send + Sync + std::fmt::Debug {
+    /// Nome usado nos logs.
+    fn name(&self) -> &str;
+
+    /// Recebe as aberturas e encerramentos de outages.
+    fn wants_outages(&self) -> bool {
+        false
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()>;
+
+    async fn write_outage(&self, _event: &OutageEvent, _location: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricSink for InfluxSink {
+    fn name(&self) -> &str {
+        "influx"
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        _labels: &HashMap<i32, TargetLabel>,
+        _location: &str,
+    ) -> Result<()> {
+        InfluxSink::write_metrics(self, metrics).await
+    }
+}
+
+#[async_trait]
+impl MetricSink for RemoteWriteSink {
+    fn name(&self) -> &str {
+        "remote_write"
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()> {
+        RemoteWriteSink::write_metrics(self, metrics, labels, location).await
+    }
+}
+
+#[async_trait]
+impl MetricSink for EventStream {
+    fn name(&self) -> &str {
+        "streaming"
+    }
+
+    fn wants_outages(&self) -> bool {
+        true
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        _labels: &HashMap<i32, TargetLabel>,
+        _location: &str,
+    ) -> Result<()> {
+        self.publish_metrics(metrics).await
+    }
+
+    async fn write_outage(&self, event: &OutageEvent, _location: &str) -> Result<()> {
+        self.publish_outage(event).await
+    }
+}
+
+/// Métrica com o probe e os rótulos do target (já pseudonimizados, se
+/// configurado).
+#[derive(Debug, Serialize)]
+struct LabeledMetric<'a> {
+    probe: &'a str,
+    target: Option<&'a str>,
+    address: Option<IpAddr>,
+    #[serde(flatten)]
+    metric: &'a ConnectivityMetric,
+}
+
+/// Registro JSON escrito pelos sinks de `[[sinks]]`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record<'a> {
+    /// Uma métrica (stdout e arquivo, uma por linha).
+    Metric(LabeledMetric<'a>),
+    /// As métricas de um ciclo (webhook).
+    Metrics {
+        probe: &'a str,
+        metrics: Vec<LabeledMetric<'a>>,
+    },
+    Outage {
+        kind: NotificationKind,
+        probe: &'a str,
+        outage: &'a OutageEvent,
+    },
+}
+
+fn labeled<'a>(
+    metrics: &'a [ConnectivityMetric],
+    labels: &'a HashMap<i32, TargetLabel>,
+    location: &'a str,
+) -> impl Iterator<Item = LabeledMetric<'a>> {
+    metrics.iter().map(move |metric| {
+        let label = labels.get(&metric.target_id);
+        LabeledMetric {
+            probe: location,
+            target: label.map(|l| l.name.as_str()),
+            address: label.map(|l| l.address),
+            metric,
+        }
+    })
+}
+
+fn outage_record<'a>(event: &'a OutageEvent, location: &'a str) -> Record<'a> {
+    Record::Outage {
+        kind: NotificationKind::for_event(event),
+        probe: location,
+        outage: event,
+    }
+}
+
+/// Linhas JSON (uma métrica por linha) de um ciclo.
+fn json_lines(
+    metrics: &[ConnectivityMetric],
+    labels: &HashMap<i32, TargetLabel>,
+    location: &str,
+) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    for metric in labeled(metrics, labels, location) {
+        serde_json::to_writer(&mut buffer, &Record::Metric(metric))?;
+        buffer.push(b'\n');
+    }
+    Ok(buffer)
+}
+
+fn json_line(record: &Record) -> Result<Vec<u8>> {
+    let mut buffer = serde_json::to_vec(record)?;
+    buffer.push(b'\n');
+    Ok(buffer)
+}
+
+/// Linhas JSON na saída padrão (sem arquivo de log, os logs também vão para
+/// lá).
+#[derive(Debug)]
+pub struct StdoutSink {
+    name: String,
+    outages: bool,
+}
+
+impl StdoutSink {
+    fn write(&self, buffer: &[u8]) -> Result<()> {
+        // Um único write por lote, para não intercalar com outras tasks
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(buffer)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricSink for StdoutSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants_outages(&self) -> bool {
+        self.outages
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        self.write(&json_lines(metrics, labels, location)?)
+    }
+
+    async fn write_outage(&self, event: &OutageEvent, location: &str) -> Result<()> {
+        self.write(&json_line(&outage_record(event, location))?)
+    }
+}
+
+/// Linhas JSON acrescentadas a um arquivo (aberto na inicialização).
+#[derive(Debug)]
+pub struct FileSink {
+    name: String,
+    path: String,
+    outages: bool,
+    file: Mutex<tokio::fs::File>,
+}
+
+impl FileSink {
+    fn open(name: String, path: &str, outages: bool) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Falha ao abrir o arquivo do sink {}: {}", name, path))?;
+        Ok(Self {
+            name,
+            path: path.to_string(),
+            outages,
+            file: Mutex::new(tokio::fs::File::from_std(file)),
+        })
+    }
+
+    async fn write(&self, buffer: &[u8]) -> Result<()> {
+        let mut file = self.file.lock().await;
+        file.write_all(buffer)
+            .await
+            .with_context(|| format!("Falha ao escrever em {}", self.path))?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricSink for FileSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants_outages(&self) -> bool {
+        self.outages
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        self.write(&json_lines(metrics, labels, location)?).await
+    }
+
+    async fn write_outage(&self, event: &OutageEvent, location: &str) -> Result<()> {
+        self.write(&json_line(&outage_record(event, location))?)
+            .await
+    }
+}
+
+/// `POST` JSON com as métricas de cada ciclo (`type = "metrics"`) e, se
+/// habilitado, cada outage (`type = "outage"`).
+#[derive(Debug)]
+pub struct WebhookSink {
+    name: String,
+    url: String,
+    outages: bool,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    fn new(name: String, url: &str, timeout_secs: u64, outages: bool) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .build()
+            .context("Falha ao criar cliente HTTP do sink webhook")?;
+        Ok(Self {
+            name,
+            url: url.to_string(),
+            outages,
+            client,
+        })
+    }
+
+    async fn post(&self, record: &Record<'_>) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(record)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Falha ao enviar ao webhook {}", self.url))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricSink for WebhookSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn wants_outages(&self) -> bool {
+        self.outages
+    }
+
+    async fn write_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+        labels: &HashMap<i32, TargetLabel>,
+        location: &str,
+    ) -> Result<()> {
+        if metrics.is_empty() {
+            return Ok(());
+        }
+        self.post(&Record::Metrics {
+            probe: location,
+            metrics: labeled(metrics, labels, location).collect(),
+        })
+        .await
+    }
+
+    async fn write_outage(&self, event: &OutageEvent, location: &str) -> Result<()> {
+        self.post(&outage_record(event, location)).await
+    }
+}
+
+/// Cria o sink descrito por uma entrada de `[[sinks]]`.
+pub fn from_config(config: &SinkConfig) -> Result<Arc<dyn MetricSink>> {
+    let name = config.name().to_string();
+    Ok(match config.kind {
+        SinkKind::Stdout => Arc::new(StdoutSink {
+            name,
+            outages: config.outages,
+        }),
+        SinkKind::File => Arc::new(FileSink::open(
+            name,
+            config.path.as_deref().unwrap_or_default(),
+            config.outages,
+        )?),
+        SinkKind::Webhook => Arc::new(WebhookSink::new(
+            name,
+            config.url.as_deref().unwrap_or_default(),
+            config.timeout_secs,
+            config.outages,
+        )?),
+    })
+}
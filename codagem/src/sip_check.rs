@@ -0,0 +1,203 @@
+//! sip_check.rs — Check `sip`: OPTIONS a gateways VoIP
+//!
+//! O check envia um `OPTIONS` SIP por UDP na porta `checks.sip_port` e mede o
+//! tempo até a resposta final (as provisórias, como `100 Trying`, são
+//! ignoradas). Qualquer resposta abaixo de 500 prova que a pilha SIP do
+//! gateway está de pé, mesmo um `403` ou `404` de quem não aceita o usuário
+//! da requisição; `5xx` e `6xx` (ex: `503 Service Unavailable` com o tronco
+//! fora) contam como falha. Status, motivo e o `Server`/`User-Agent` do
+//! gateway ficam em `details`.
+
+use crate::check::{Attempt, Check, CheckKind, build_metric, recv_or_error};
+use crate::config::{Config, SourceConfig};
+use crate::ratelimit::RateLimiter;
+use crate::source;
+use crate::types::{CheckError, ConnectivityMetric, Probe, Target};
+use async_trait::async_trait;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Resposta SIP recebida.
+#[derive(Debug, Clone)]
+struct SipResponse {
+    status: u16,
+    reason: String,
+    /// `Server` (ou `User-Agent`) do gateway.
+    server: Option<String>,
+    call_id: Option<String>,
+}
+
+/// Host de uma URI SIP (IPv6 entre colchetes).
+fn uri_host(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => format!("[{}]", v6),
+    }
+}
+
+/// Lê a linha de status e os cabeçalhos usados de uma resposta SIP; `None`
+/// para requisições e datagramas que não são SIP.
+fn parse_response(datagram: &[u8]) -> Option<SipResponse> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    let mut lines = text.split("\r\n");
+    let status_line = lines.next()?.strip_prefix("SIP/2.0 ")?;
+    let (code, reason) = status_line.split_once(' ').unwrap_or((status_line, ""));
+    let mut response = SipResponse {
+        status: code.trim().parse().ok()?,
+        reason: reason.trim().to_string(),
+        server: None,
+        call_id: None,
+    };
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match name.trim().to_ascii_lowercase().as_str() {
+            // `i` é a forma compacta de `Call-ID`
+            "call-id" | "i" => response.call_id = Some(value),
+            "server" => response.server = Some(value),
+            "user-agent" if response.server.is_none() => response.server = Some(value),
+            _ => {}
+        }
+    }
+    Some(response)
+}
+
+/// Check SIP (ver o módulo).
+pub struct SipCheck {
+    port: u16,
+    /// Usuário da Request-URI (`sip:usuario@gateway`); ausente = só o host.
+    user: Option<String>,
+    source: SourceConfig,
+    attempts: usize,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+}
+
+impl SipCheck {
+    pub fn new(config: &Config, limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            port: config.checks.sip_port,
+            user: config.checks.sip_user.clone(),
+            source: config.checks.source_for("sip").clone(),
+            attempts: config.ping_count,
+            timeout: Duration::from_secs(config.timeout_secs),
+            limiter,
+        }
+    }
+
+    /// `OPTIONS` com o endereço local do socket em `Via`, `From` e `Contact`.
+    fn request(&self, target: SocketAddr, local: SocketAddr, call_id: &str, tag: u32) -> String {
+        let user = self
+            .user
+            .as_deref()
+            .map(|user| format!("{}@", user))
+            .unwrap_or_default();
+        let uri = format!("sip:{}{}:{}", user, uri_host(target.ip()), target.port());
+        let local_host = uri_host(local.ip());
+        format!(
+            "OPTIONS {uri} SIP/2.0\r\n\
+             Via: SIP/2.0/UDP {local_host}:{local_port};branch=z9hG4bK{branch:016x};rport\r\n\
+             Max-Forwards: 70\r\n\
+             From: <sip:monitor@{local_host}>;tag={tag:08x}\r\n\
+             To: <{uri}>\r\n\
+             Call-ID: {call_id}\r\n\
+             CSeq: 1 OPTIONS\r\n\
+             Contact: <sip:monitor@{local_host}:{local_port}>\r\n\
+             Accept: application/sdp\r\n\
+             User-Agent: monitoramento-rede/{version}\r\n\
+             Content-Length: 0\r\n\r\n",
+            local_port = local.port(),
+            branch = rand::random::<u64>(),
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    async fn attempt(&self, addr: SocketAddr) -> (Attempt, Option<SipResponse>) {
+        let socket = match source::udp_connect(&self.source, addr).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                return (
+                    Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                    None,
+                );
+            }
+        };
+        let local = match socket.local_addr() {
+            Ok(local) => local,
+            Err(e) => {
+                return (
+                    Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                    None,
+                );
+            }
+        };
+        let call_id = format!("{:016x}@{}", rand::random::<u64>(), local.ip());
+        let request = self.request(addr, local, &call_id, rand::random());
+        let started = Instant::now();
+        let exchange = async {
+            socket.send(request.as_bytes()).await?;
+            let mut buf = [0u8; 4096];
+            loop {
+                let len = recv_or_error(&socket, &mut buf).await?;
+                match parse_response(&buf[..len]) {
+                    Some(response)
+                        if response.status >= 200
+                            && response.call_id.as_deref() == Some(call_id.as_str()) =>
+                    {
+                        return Ok::<_, std::io::Error>(response);
+                    }
+                    // Provisória, de outra transação ou não SIP
+                    _ => continue,
+                }
+            }
+        };
+        match tokio::time::timeout(self.timeout, exchange).await {
+            Ok(Ok(response)) => {
+                let ms = started.elapsed().as_secs_f64() * 1000.0;
+                let attempt = if response.status >= 500 {
+                    Attempt::Failure(
+                        CheckError::SipError,
+                        format!("SIP {} {}", response.status, response.reason),
+                    )
+                } else {
+                    Attempt::Success(ms)
+                };
+                (attempt, Some(response))
+            }
+            Ok(Err(e)) => (
+                Attempt::Failure(CheckError::from_io(&e), e.to_string()),
+                None,
+            ),
+            Err(_) => (Attempt::Timeout, None),
+        }
+    }
+}
+
+#[async_trait]
+impl Check for SipCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let addr = SocketAddr::new(target.address, self.port);
+        let mut attempts = Vec::with_capacity(self.attempts);
+        let mut last = None;
+        for _ in 0..self.attempts {
+            self.limiter.acquire(target).await;
+            let (attempt, response) = self.attempt(addr).await;
+            last = response.or(last);
+            attempts.push(attempt);
+        }
+        let details = serde_json::json!({
+            "port": self.port,
+            "sip_status": last.as_ref().map(|r| r.status),
+            "sip_reason": last.as_ref().map(|r| &r.reason),
+            "server": last.as_ref().and_then(|r| r.server.as_ref()),
+        });
+        build_metric(target, probe, CheckKind::Sip, &attempts, Some(details))
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts
+    }
+}
@@ -0,0 +1,330 @@
+//! snapshot.rs — Visão consistente e sem lock do estado corrente
+//!
+//! Após cada ciclo o scheduler publica um novo `Snapshot` (via `ArcSwap`) com o
+//! estado atual de cada par probe/target, os RTTs recentes, os incidentes ativos
+//! e os contadores da janela de consenso. Consumidores de leitura (página de
+//! status, API, TUI, exportadores) apenas chamam `SnapshotStore::load`, sem
+//! travar o mutex do consenso nem consultar o banco.
+//!
+//! O estado de cada par probe/target fica num `ArcSwap` próprio: um ciclo troca
+//! só os pares que mediu, sem copiar o mapa nem o histórico dos demais. O mapa
+//! só é copiado quando aparece um par novo. Por isso os targets de um
+//! `Snapshot` já carregado refletem sempre o último ciclo de cada par.
+
+use crate::baseline::AnomalyEvent;
+use crate::consensus::WindowStats;
+use crate::host::HostSample;
+use crate::persistence::PersistenceStats;
+use crate::scheduler::CycleOverrunStats;
+use crate::supervisor::SchedulerIncident;
+use crate::types::{ConnectivityMetric, MetricStatus, OutageEvent};
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde::ser::SerializeSeq;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+/// Quantidade de RTTs recentes mantidos por target.
+pub const RECENT_RTT_LEN: usize = 60;
+
+/// Estado corrente de um target visto por um probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetSnapshot {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub status: MetricStatus,
+    pub last_metric_at: DateTime<Utc>,
+    /// Instante da última mudança de status vista por este processo; `None`
+    /// se o status não mudou desde o primeiro ciclo do par.
+    pub last_change_at: Option<DateTime<Utc>>,
+    pub packet_loss_percent: Option<i16>,
+    /// RTTs médios dos últimos ciclos (mais antigo primeiro); `None` = sem resposta.
+    pub recent_rtts: VecDeque<Option<f64>>,
+}
+
+impl TargetSnapshot {
+    /// Estado após `metric`, partindo do estado anterior do par (se houver).
+    fn next(previous: Option<&TargetSnapshot>, metric: &ConnectivityMetric) -> Self {
+        let mut recent_rtts = previous.map(|t| t.recent_rtts.clone()).unwrap_or_default();
+        if recent_rtts.len() == RECENT_RTT_LEN {
+            recent_rtts.pop_front();
+        }
+        recent_rtts.push_back(metric.response_time_ms);
+        let last_change_at = match previous {
+            Some(t) if t.status != metric.status => Some(metric.timestamp),
+            Some(t) => t.last_change_at,
+            None => None,
+        };
+        Self {
+            probe_id: metric.probe_id,
+            target_id: metric.target_id,
+            status: metric.status.clone(),
+            last_metric_at: metric.timestamp,
+            last_change_at,
+            packet_loss_percent: metric.packet_loss_percent,
+            recent_rtts,
+        }
+    }
+}
+
+/// Pares probe/target, cada um trocado individualmente.
+pub type TargetMap = BTreeMap<(i32, i32), Arc<ArcSwap<TargetSnapshot>>>;
+
+/// Visão imutável do estado do monitor num instante.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Snapshot {
+    pub generated_at: Option<DateTime<Utc>>,
+    /// Ciclos aplicados desde o início do processo (todos os probes).
+    pub cycles: u64,
+    /// Estado por (probe_id, target_id).
+    #[serde(serialize_with = "serialize_targets")]
+    pub targets: Arc<TargetMap>,
+    /// Outages abertos no momento.
+    pub active_incidents: Vec<OutageEvent>,
+    /// Contadores da janela de consenso principal.
+    pub consensus: WindowStats,
+    /// Fila e lotes do pipeline de persistência.
+    pub persistence: PersistenceStats,
+    /// Anomalias de latência/perda abertas no momento.
+    pub active_anomalies: Vec<AnomalyEvent>,
+    /// Última amostra de recursos do host por probe.
+    #[serde(serialize_with = "serialize_hosts")]
+    pub hosts: BTreeMap<i32, Arc<HostSample>>,
+    /// Reinícios de scheduler pelo supervisor, por probe.
+    pub scheduler_restarts: BTreeMap<i32, i32>,
+    /// Ciclos que estouraram o intervalo, por probe.
+    pub cycle_overruns: BTreeMap<i32, CycleOverrunStats>,
+}
+
+impl Snapshot {
+    /// Estado mais recente de um target, considerando todos os probes.
+    pub fn latest_for_target(&self, target_id: i32) -> Option<Arc<TargetSnapshot>> {
+        self.targets
+            .iter()
+            .filter(|((_, id), _)| *id == target_id)
+            .map(|(_, cell)| cell.load_full())
+            .max_by_key(|t| t.last_metric_at)
+    }
+}
+
+fn serialize_targets<S: serde::Serializer>(
+    targets: &TargetMap,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Some(targets.len()))?;
+    for cell in targets.values() {
+        seq.serialize_element(cell.load().as_ref())?;
+    }
+    seq.end()
+}
+
+fn serialize_hosts<S: serde::Serializer>(
+    hosts: &BTreeMap<i32, Arc<HostSample>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(hosts.values().map(Arc::as_ref))
+}
+
+/// Armazena o snapshot corrente e aplica atualizações copy-on-write.
+#[derive(Debug, Default)]
+pub struct SnapshotStore {
+    current: ArcSwap<Snapshot>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot corrente (barato: apenas incrementa um contador de referência).
+    pub fn load(&self) -> Arc<Snapshot> {
+        self.current.load_full()
+    }
+
+    /// Atualiza os contadores do pipeline de persistência.
+    pub fn set_persistence(&self, stats: PersistenceStats) {
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.persistence = stats;
+            next
+        });
+    }
+
+    /// Registra um outage aberto retomado após um restart.
+    pub fn resume_incident(&self, event: &OutageEvent) {
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.active_incidents
+                .retain(|open| open.start_time != event.start_time);
+            next.active_incidents.push(event.clone());
+            next
+        });
+    }
+
+    /// Atualiza as anomalias abertas com os eventos de um ciclo.
+    pub fn apply_anomalies(&self, events: &[AnomalyEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            for event in events {
+                next.active_anomalies.retain(|open| {
+                    (open.probe_id, open.target_id, open.kind)
+                        != (event.probe_id, event.target_id, event.kind)
+                });
+                if event.end_time.is_none() {
+                    next.active_anomalies.push(event.clone());
+                }
+            }
+            next
+        });
+    }
+
+    /// Atualiza os contadores de estouro de ciclo de um probe.
+    pub fn set_cycle_overruns(&self, probe_id: i32, stats: CycleOverrunStats) {
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.cycle_overruns.insert(probe_id, stats);
+            next
+        });
+    }
+
+    /// Registra um reinício de scheduler pelo supervisor.
+    pub fn record_scheduler_restart(&self, incident: &SchedulerIncident) {
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.scheduler_restarts
+                .insert(incident.probe_id, incident.restarts);
+            next
+        });
+    }
+
+    /// Publica a última amostra de recursos do host de um probe.
+    pub fn set_host(&self, sample: &HostSample) {
+        let sample = Arc::new(sample.clone());
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.hosts.insert(sample.probe_id, Arc::clone(&sample));
+            next
+        });
+    }
+
+    /// Publica o resultado de um ciclo de um probe.
+    pub fn apply_cycle(
+        &self,
+        metrics: &[ConnectivityMetric],
+        outage_event: Option<&OutageEvent>,
+        consensus: WindowStats,
+    ) {
+        // Pares já conhecidos: troca só o estado do par. Cada probe só escreve
+        // nos próprios pares, então não há escrita concorrente na mesma célula
+        let known = self.current.load();
+        let mut added = Vec::new();
+        for metric in metrics {
+            let key = (metric.probe_id, metric.target_id);
+            match known.targets.get(&key) {
+                Some(cell) => {
+                    let next = TargetSnapshot::next(Some(&cell.load()), metric);
+                    cell.store(Arc::new(next));
+                }
+                None => added.push((key, TargetSnapshot::next(None, metric))),
+            }
+        }
+        drop(known);
+        let added: Vec<_> = added
+            .into_iter()
+            .map(|(key, target)| (key, Arc::new(ArcSwap::from_pointee(target))))
+            .collect();
+
+        self.current.rcu(|current| {
+            let mut next = Snapshot::clone(current);
+            next.generated_at = Some(Utc::now());
+            next.cycles += 1;
+            next.consensus = consensus;
+
+            if !added.is_empty() {
+                let mut targets = TargetMap::clone(&current.targets);
+                for (key, cell) in &added {
+                    targets.entry(*key).or_insert_with(|| Arc::clone(cell));
+                }
+                next.targets = Arc::new(targets);
+            }
+
+            if let Some(event) = outage_event {
+                next.active_incidents
+                    .retain(|open| open.start_time != event.start_time);
+                if event.end_time.is_none() {
+                    next.active_incidents.push(event.clone());
+                }
+            }
+            next
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MetricType;
+    use chrono::TimeZone;
+
+    fn metric(target_id: i32, second: u32, status: MetricStatus) -> ConnectivityMetric {
+        ConnectivityMetric {
+            id: 0,
+            cycle_id: 0,
+            probe_id: 1,
+            target_id,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, second).unwrap(),
+            metric_type: MetricType::TcpIpv4,
+            response_time_ms: (status == MetricStatus::Up).then_some(10.0),
+            status,
+            packet_loss_percent: None,
+            error: None,
+            details: None,
+            provenance: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn ciclo_troca_so_os_pares_medidos() {
+        let store = SnapshotStore::new();
+        let window = WindowStats::default();
+        store.apply_cycle(
+            &[
+                metric(1, 0, MetricStatus::Up),
+                metric(2, 0, MetricStatus::Up),
+            ],
+            None,
+            window,
+        );
+        let first = store.load();
+        let untouched = first.targets[&(1, 2)].load_full();
+
+        store.apply_cycle(&[metric(1, 1, MetricStatus::Down)], None, window);
+        let second = store.load();
+        // Sem par novo, o mapa é o mesmo; o par 2 nem foi trocado
+        assert!(Arc::ptr_eq(&first.targets, &second.targets));
+        assert!(Arc::ptr_eq(
+            &untouched,
+            &second.targets[&(1, 2)].load_full()
+        ));
+        assert_eq!(second.cycles, 2);
+
+        let changed = second.latest_for_target(1).unwrap();
+        assert_eq!(changed.status, MetricStatus::Down);
+        assert_eq!(changed.recent_rtts, [Some(10.0), None]);
+        assert_eq!(changed.last_change_at, Some(changed.last_metric_at));
+        assert_eq!(second.latest_for_target(2).unwrap().last_change_at, None);
+
+        store.apply_cycle(&[metric(1, 2, MetricStatus::Down)], None, window);
+        let stable = store.load().latest_for_target(1).unwrap();
+        assert_eq!(stable.last_change_at, changed.last_change_at);
+        assert_ne!(stable.last_metric_at, changed.last_metric_at);
+
+        store.apply_cycle(&[metric(3, 3, MetricStatus::Up)], None, window);
+        assert_eq!(store.load().targets.len(), 3);
+    }
+}
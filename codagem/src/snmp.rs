@@ -0,0 +1,453 @@
+//! snmp.rs — Contadores de interface do CPE/roteador por SNMP
+//!
+//! Com `[snmp]`, uma task consulta (SNMPv2c `GetRequest`) a cada
+//! `interval_secs` as interfaces configuradas de cada dispositivo:
+//! `ifDescr`, `ifOperStatus`, `ifInErrors` e `ifOutDiscards`, mais o
+//! `sysUpTime` do dispositivo. Cada interface vira uma linha em
+//! `device_metrics`, com os contadores brutos e o quanto cresceram desde a
+//! consulta anterior. Assim, erros no enlace do roteador de acesso podem ser
+//! cruzados com os outages detectados. Um `sysUpTime` menor que o anterior
+//! indica reinício, e os contadores zerados não geram delta. Um dispositivo
+//! sem resposta grava as linhas só com `error`.
+//!
+//! O BER das mensagens SNMP é codificado aqui mesmo: bastam inteiros,
+//! strings, OIDs e os tipos de aplicação dos contadores.
+
+use crate::check::{recv_or_error, udp_connect};
+use crate::config::{SnmpConfig, SnmpDeviceConfig};
+use crate::storage::Storage;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// `sysUpTime.0`.
+const SYS_UPTIME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 3, 0];
+/// Colunas da `ifTable` (`1.3.6.1.2.1.2.2.1.<coluna>.<ifIndex>`).
+const IF_TABLE: &[u32] = &[1, 3, 6, 1, 2, 1, 2, 2, 1];
+const IF_DESCR: u32 = 2;
+const IF_OPER_STATUS: u32 = 8;
+const IF_IN_ERRORS: u32 = 14;
+const IF_OUT_DISCARDS: u32 = 19;
+
+/// Linha de `device_metrics`: uma interface de um dispositivo numa consulta.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceMetric {
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub address: IpAddr,
+    pub if_index: i32,
+    pub if_descr: Option<String>,
+    /// `ifOperStatus` por nome (`up`, `down`, `lowerLayerDown`...).
+    pub oper_status: Option<String>,
+    pub in_errors: Option<i64>,
+    pub out_discards: Option<i64>,
+    /// Crescimento dos contadores desde a consulta anterior.
+    pub in_errors_delta: Option<i64>,
+    pub out_discards_delta: Option<i64>,
+    /// Falha da consulta (timeout, erro SNMP).
+    pub error: Option<String>,
+}
+
+fn oper_status_name(value: i64) -> String {
+    match value {
+        1 => "up".into(),
+        2 => "down".into(),
+        3 => "testing".into(),
+        4 => "unknown".into(),
+        5 => "dormant".into(),
+        6 => "notPresent".into(),
+        7 => "lowerLayerDown".into(),
+        other => other.to_string(),
+    }
+}
+
+// --- BER ---------------------------------------------------------------
+
+fn encode_length(len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    encode_length(content.len(), &mut out);
+    out.extend_from_slice(content);
+    out
+}
+
+fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // Menor complemento de dois que preserva o sinal
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    tlv(0x02, &bytes[start..])
+}
+
+fn oid(arcs: &[u32]) -> Vec<u8> {
+    let mut content = vec![(arcs[0] * 40 + arcs[1]) as u8];
+    for &arc in &arcs[2..] {
+        let mut chunk = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        content.extend(chunk.iter().rev());
+    }
+    tlv(0x06, &content)
+}
+
+/// `GetRequest` SNMPv2c das `oids`.
+fn get_request(community: &str, request_id: i32, oids: &[Vec<u32>]) -> Vec<u8> {
+    let varbinds: Vec<u8> = oids
+        .iter()
+        .flat_map(|arcs| tlv(0x30, &[oid(arcs), vec![0x05, 0x00]].concat()))
+        .collect();
+    let pdu = [
+        integer(i64::from(request_id)),
+        integer(0),
+        integer(0),
+        tlv(0x30, &varbinds),
+    ]
+    .concat();
+    tlv(
+        0x30,
+        &[
+            integer(1), // v2c
+            tlv(0x04, community.as_bytes()),
+            tlv(0xa0, &pdu), // GetRequest-PDU
+        ]
+        .concat(),
+    )
+}
+
+/// Lê um TLV; retorna tag, conteúdo e o restante.
+fn read_tlv(data: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first().ok_or_else(|| anyhow!("BER truncado"))?;
+    let (&first, mut rest) = rest.split_first().ok_or_else(|| anyhow!("BER truncado"))?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            bail!("comprimento BER inválido");
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        rest = &rest[count..];
+        len
+    };
+    if rest.len() < len {
+        bail!("BER truncado");
+    }
+    Ok((tag, &rest[..len], &rest[len..]))
+}
+
+fn read_integer(content: &[u8]) -> i64 {
+    let sign = if content.first().is_some_and(|b| b & 0x80 != 0) {
+        -1i64
+    } else {
+        0
+    };
+    content
+        .iter()
+        .fold(sign, |acc, b| (acc << 8) | i64::from(*b))
+}
+
+fn read_unsigned(content: &[u8]) -> u64 {
+    content
+        .iter()
+        .fold(0u64, |acc, b| (acc << 8) | u64::from(*b))
+}
+
+/// Valor de um varbind da resposta.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Integer(i64),
+    /// Counter32, Gauge32, TimeTicks e Counter64.
+    Unsigned(u64),
+    Bytes(Vec<u8>),
+    /// `noSuchObject`, `noSuchInstance` ou `endOfMibView`.
+    Missing,
+    Other,
+}
+
+/// Varbind da resposta: OID codificado (sem tag e comprimento) e valor.
+type Varbind = (Vec<u8>, Value);
+
+/// Varbinds de uma `Response`, na ordem pedida;
+/// `None` para respostas a outro pedido.
+fn parse_response(datagram: &[u8], request_id: i32) -> Result<Option<Vec<Varbind>>> {
+    let (_, message, _) = read_tlv(datagram)?;
+    let (_, _version, rest) = read_tlv(message)?;
+    let (_, _community, rest) = read_tlv(rest)?;
+    let (tag, pdu, _) = read_tlv(rest)?;
+    if tag != 0xa2 {
+        bail!("PDU SNMP inesperada (tag 0x{:02x})", tag);
+    }
+    let (_, id, rest) = read_tlv(pdu)?;
+    if read_integer(id) != i64::from(request_id) {
+        return Ok(None);
+    }
+    let (_, error_status, rest) = read_tlv(rest)?;
+    let (_, error_index, rest) = read_tlv(rest)?;
+    let error_status = read_integer(error_status);
+    if error_status != 0 {
+        bail!(
+            "erro SNMP {} no varbind {}",
+            error_status,
+            read_integer(error_index)
+        );
+    }
+    let (_, mut varbinds, _) = read_tlv(rest)?;
+    let mut values = Vec::new();
+    while !varbinds.is_empty() {
+        let (_, varbind, rest) = read_tlv(varbinds)?;
+        varbinds = rest;
+        let (_, name, rest) = read_tlv(varbind)?;
+        let (tag, content, _) = read_tlv(rest)?;
+        let value = match tag {
+            0x02 => Value::Integer(read_integer(content)),
+            0x04 => Value::Bytes(content.to_vec()),
+            0x41 | 0x42 | 0x43 | 0x46 => Value::Unsigned(read_unsigned(content)),
+            0x80..=0x82 => Value::Missing,
+            _ => Value::Other,
+        };
+        values.push((name.to_vec(), value));
+    }
+    Ok(Some(values))
+}
+
+// --- Consulta ----------------------------------------------------------
+
+/// Valores lidos de uma interface.
+#[derive(Debug, Default)]
+struct InterfaceReading {
+    descr: Option<String>,
+    oper_status: Option<i64>,
+    in_errors: Option<u64>,
+    out_discards: Option<u64>,
+}
+
+/// `GetRequest` com `retries` reenvios (cada um com até `timeout`).
+async fn get(
+    device: &SnmpDeviceConfig,
+    oids: &[Vec<u32>],
+    timeout: Duration,
+    retries: u32,
+) -> Result<Vec<Value>> {
+    let addr = SocketAddr::new(device.address, device.port);
+    let socket = udp_connect(addr)
+        .await
+        .with_context(|| format!("socket UDP para {}", addr))?;
+    let request_id = rand::random::<i32>().abs();
+    let request = get_request(&device.community, request_id, oids);
+    let expected: Vec<Vec<u8>> = oids.iter().map(|arcs| oid(arcs)[2..].to_vec()).collect();
+    let mut buf = vec![0u8; 65_535];
+    for _ in 0..=retries {
+        socket.send(&request).await?;
+        let exchange = async {
+            loop {
+                let len = recv_or_error(&socket, &mut buf).await?;
+                // Respostas atrasadas de outro pedido são descartadas
+                if let Some(values) = parse_response(&buf[..len], request_id)? {
+                    return Ok::<_, anyhow::Error>(values);
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, exchange).await {
+            Ok(Ok(values)) => {
+                if values.len() != oids.len()
+                    || values.iter().zip(&expected).any(|((name, _), e)| name != e)
+                {
+                    bail!("varbinds da resposta não correspondem aos pedidos");
+                }
+                return Ok(values.into_iter().map(|(_, value)| value).collect());
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => continue,
+        }
+    }
+    bail!("sem resposta SNMP em {} tentativa(s)", retries + 1)
+}
+
+/// `sysUpTime` (centésimos de segundo) e as leituras das interfaces.
+async fn poll_device(
+    device: &SnmpDeviceConfig,
+    timeout: Duration,
+    retries: u32,
+) -> Result<(Option<u64>, Vec<InterfaceReading>)> {
+    let mut oids = vec![SYS_UPTIME.to_vec()];
+    for &if_index in &device.interfaces {
+        for column in [IF_DESCR, IF_OPER_STATUS, IF_IN_ERRORS, IF_OUT_DISCARDS] {
+            oids.push([IF_TABLE, &[column, if_index]].concat());
+        }
+    }
+    let values = get(device, &oids, timeout, retries).await?;
+    let uptime = match values[0] {
+        Value::Unsigned(ticks) => Some(ticks),
+        _ => None,
+    };
+    let readings = values[1..]
+        .chunks(4)
+        .map(|chunk| {
+            let unsigned = |value: &Value| match value {
+                Value::Unsigned(v) => Some(*v),
+                _ => None,
+            };
+            InterfaceReading {
+                descr: match &chunk[0] {
+                    Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+                    _ => None,
+                },
+                oper_status: match chunk[1] {
+                    Value::Integer(v) => Some(v),
+                    _ => None,
+                },
+                in_errors: unsigned(&chunk[2]),
+                out_discards: unsigned(&chunk[3]),
+            }
+        })
+        .collect();
+    Ok((uptime, readings))
+}
+
+/// Última leitura de uma interface, base dos deltas e das transições.
+#[derive(Debug, Clone, Copy)]
+struct Previous {
+    uptime: Option<u64>,
+    oper_status: Option<i64>,
+    in_errors: Option<u64>,
+    out_discards: Option<u64>,
+}
+
+/// Crescimento de um Counter32 (com volta em 2^32); `None` sem base ou
+/// depois de um reinício do dispositivo.
+fn counter_delta(previous: Option<u64>, current: Option<u64>, restarted: bool) -> Option<i64> {
+    let (previous, current) = (previous?, current?);
+    if restarted {
+        return None;
+    }
+    Some(if current >= previous {
+        (current - previous) as i64
+    } else {
+        (current + (1u64 << 32) - previous) as i64
+    })
+}
+
+/// Loop de consulta; a primeira passada é imediata.
+pub async fn run(storage: Arc<Storage>, config: SnmpConfig) {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let mut previous: HashMap<(String, u32), Previous> = HashMap::new();
+    let mut ticker = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticker.tick().await;
+        let mut rows = Vec::new();
+        for device in &config.devices {
+            let timestamp = Utc::now();
+            let polled = poll_device(device, timeout, config.retries).await;
+            let (uptime, readings) = match polled {
+                Ok(polled) => polled,
+                Err(e) => {
+                    warn!(
+                        "[SNMP] Falha ao consultar {} ({}): {:#}",
+                        device.name, device.address, e
+                    );
+                    rows.extend(device.interfaces.iter().map(|&if_index| DeviceMetric {
+                        timestamp,
+                        device: device.name.clone(),
+                        address: device.address,
+                        if_index: if_index as i32,
+                        if_descr: None,
+                        oper_status: None,
+                        in_errors: None,
+                        out_discards: None,
+                        in_errors_delta: None,
+                        out_discards_delta: None,
+                        error: Some(format!("{:#}", e)),
+                    }));
+                    continue;
+                }
+            };
+            for (&if_index, reading) in device.interfaces.iter().zip(readings) {
+                let key = (device.name.clone(), if_index);
+                let last = previous.get(&key).copied();
+                let restarted = matches!(
+                    (last.and_then(|p| p.uptime), uptime),
+                    (Some(before), Some(now)) if now < before
+                );
+                if restarted {
+                    info!(
+                        "[SNMP] {} ({}) reiniciou; contadores da interface {} zerados",
+                        device.name, device.address, if_index
+                    );
+                }
+                let was = last.and_then(|p| p.oper_status);
+                if let (Some(was), Some(now)) = (was, reading.oper_status)
+                    && was != now
+                {
+                    warn!(
+                        "🔌 [SNMP] {} interface {} ({}): {} → {}",
+                        device.name,
+                        if_index,
+                        reading.descr.as_deref().unwrap_or("?"),
+                        oper_status_name(was),
+                        oper_status_name(now)
+                    );
+                }
+                rows.push(DeviceMetric {
+                    timestamp,
+                    device: device.name.clone(),
+                    address: device.address,
+                    if_index: if_index as i32,
+                    if_descr: reading.descr.clone(),
+                    oper_status: reading.oper_status.map(oper_status_name),
+                    in_errors: reading.in_errors.map(|v| v as i64),
+                    out_discards: reading.out_discards.map(|v| v as i64),
+                    in_errors_delta: counter_delta(
+                        last.and_then(|p| p.in_errors),
+                        reading.in_errors,
+                        restarted,
+                    ),
+                    out_discards_delta: counter_delta(
+                        last.and_then(|p| p.out_discards),
+                        reading.out_discards,
+                        restarted,
+                    ),
+                    error: None,
+                });
+                previous.insert(
+                    key,
+                    Previous {
+                        uptime,
+                        oper_status: reading.oper_status,
+                        in_errors: reading.in_errors,
+                        out_discards: reading.out_discards,
+                    },
+                );
+            }
+        }
+        match storage.insert_device_metrics(&rows).await {
+            Ok(written) => debug!("[SNMP] {} linha(s) em device_metrics", written),
+            Err(e) => warn!("[SNMP] Falha ao gravar device_metrics: {:?}", e),
+        }
+    }
+}
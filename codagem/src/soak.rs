@@ -0,0 +1,405 @@
+//! soak.rs — Teste de carga com targets sintéticos (`monitoramento_rede soak`)
+//!
+//! Antes de levar uma instalação para produção, o modo soak valida se o host
+//! e o banco aguentam a carga pretendida. Um responder TCP local atende
+//! endereços de loopback (`127.77.0.0/16`); para eles são cadastrados um probe
+//! e N targets `tcp` sintéticos, monitorados pelo scheduler de verdade
+//! (checks, persistência em lotes, consenso e supervisor) no intervalo
+//! pedido. Ao fim, o relatório mostra a duração dos ciclos e a vazão de
+//! gravação no banco, e os dados do teste são removidos (exceto com `--keep`).
+//!
+//! Exportadores e notificações não são usados: nada do teste sai do host.
+//! Com `--memory`, o banco é trocado por `MemoryStorage`: o loop roda de
+//! ponta a ponta sem PostgreSQL (útil no CI), e o relatório mostra as linhas
+//! que teriam sido gravadas.
+
+use crate::cli::SoakArgs;
+use crate::config::Config;
+use crate::memory_storage::MemoryStorage;
+use crate::persistence::Persistence;
+use crate::scheduler::SchedulerBuilder;
+use crate::snapshot::SnapshotStore;
+use crate::storage::{Storage, StorageBackend};
+use crate::types::{GeoLocation, Probe, Target};
+use anyhow::{Context, Result, bail};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpSocket;
+use tracing::{info, warn};
+
+/// Bloco de loopback dos targets sintéticos (segundo octeto).
+const SOAK_OCTET: u8 = 77;
+/// Hosts usados por /24 do bloco (`.1` a `.250`).
+const HOSTS_PER_BLOCK: usize = 250;
+/// Máximo de targets sintéticos.
+const MAX_TARGETS: usize = HOSTS_PER_BLOCK * 256;
+/// Backlog do responder: os checks de um ciclo conectam todos de uma vez.
+const LISTEN_BACKLOG: u32 = 4096;
+/// Espera máxima pelo esvaziamento da fila de persistência no fim do teste.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Executa o teste de carga e imprime o relatório. Falha se algum ciclo
+/// estourou o intervalo, alguma gravação falhou ou o scheduler foi reiniciado.
+/// Sem `storage` (`--memory`), grava em `MemoryStorage`.
+pub async fn run(config: Arc<Config>, storage: Option<Arc<Storage>>, args: SoakArgs) -> Result<()> {
+    if args.targets == 0 || args.targets > MAX_TARGETS {
+        bail!("--targets deve estar entre 1 e {}", MAX_TARGETS);
+    }
+    if args.interval.as_secs() == 0 {
+        bail!("--interval deve ser de pelo menos 1s");
+    }
+
+    let port = spawn_echo_responder()
+        .await
+        .context("Falha ao iniciar o responder local")?;
+    info!("🧪 [SOAK] Responder local na porta {}", port);
+
+    let mut soak_config = Config::clone(&config);
+    soak_config.cycle_interval_secs = args.interval.as_secs();
+    soak_config.checks.tcp_port = port;
+    soak_config.asymmetry = None;
+    soak_config.bufferbloat = None;
+    soak_config.traceroute = None;
+    // Sem spool: gravações que falham contam como falha do teste
+    soak_config.persistence.spool_max_bytes = 0;
+    let soak_config = Arc::new(soak_config);
+
+    let mut probe = Probe {
+        id: 0,
+        location: format!("soak-{}", std::process::id()),
+        ip_address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        provider: Some("soak".to_string()),
+        created_at: None,
+        geo: GeoLocation::default(),
+    };
+    let mut targets = Vec::with_capacity(args.targets);
+    let memory = Arc::new(MemoryStorage::new());
+    let backend: Arc<dyn StorageBackend> = match &storage {
+        Some(storage) => {
+            probe.id = storage
+                .insert_probe(&probe)
+                .await
+                .context("Falha ao cadastrar o probe do soak")?;
+            info!(
+                "🧪 [SOAK] Cadastrando {} targets sintéticos...",
+                args.targets
+            );
+            if let Err(e) = register_targets(storage, args.targets, &mut targets).await {
+                cleanup(Some(storage), &probe, &targets).await;
+                return Err(e);
+            }
+            storage.clone()
+        }
+        None => {
+            probe.id = 1;
+            targets.extend((0..args.targets).map(|index| Target {
+                id: index as i32 + 1,
+                ..synthetic_target(index)
+            }));
+            info!("🧪 [SOAK] Banco em memória: nada será gravado no PostgreSQL");
+            memory.clone()
+        }
+    };
+
+    let builder =
+        match SchedulerBuilder::new(probe.clone(), targets.clone(), Arc::clone(&soak_config)) {
+            Ok(builder) => builder,
+            Err(e) => {
+                cleanup(storage.as_deref(), &probe, &targets).await;
+                bail!("Parâmetros de consenso inválidos para o soak: {}", e);
+            }
+        };
+
+    let snapshot = Arc::new(SnapshotStore::new());
+    let persistence = Persistence::spawn(
+        Arc::clone(&backend),
+        &soak_config.persistence,
+        Arc::clone(&snapshot),
+    );
+    // Sem exportadores nem consenso sombra: os padrões do builder
+    let builder = builder
+        .storage(Arc::clone(&backend))
+        .persistence(persistence)
+        .snapshot(Arc::clone(&snapshot));
+
+    info!(
+        "🧪 [SOAK] Iniciando: {} targets, ciclo de {}s, duração de {}s",
+        targets.len(),
+        args.interval.as_secs(),
+        args.duration.as_secs()
+    );
+    let started = Instant::now();
+    let handle = builder
+        .start()
+        .context("Falha ao iniciar o scheduler do soak")?;
+    let mut ticker = tokio::time::interval(args.interval);
+    ticker.tick().await;
+    while started.elapsed() < args.duration {
+        ticker.tick().await;
+        let current = snapshot.load();
+        info!(
+            "🧪 [SOAK] {:.0}s: {} ciclo(s), {} métrica(s) gravada(s), fila {}/{}",
+            started.elapsed().as_secs_f64(),
+            current.cycles,
+            current.persistence.metrics_written,
+            current.persistence.queue_depth,
+            current.persistence.queue_capacity
+        );
+    }
+    handle.stop().await;
+    let elapsed = started.elapsed();
+
+    // As métricas do último ciclo ainda podem estar na fila
+    let drain_started = Instant::now();
+    while snapshot.load().persistence.queue_depth > 0 && drain_started.elapsed() < DRAIN_TIMEOUT {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let report = Report::collect(backend.as_ref(), &snapshot, &probe, &args, elapsed).await?;
+    report.print();
+    if storage.is_none() {
+        let rows: Vec<String> = memory
+            .row_counts()
+            .iter()
+            .map(|(table, rows)| format!("{} {}", table, rows))
+            .collect();
+        println!("Linhas em memória: {}", rows.join(", "));
+    }
+
+    if args.keep {
+        println!(
+            "Dados mantidos: probe {} (id {}) e {} targets",
+            probe.location,
+            probe.id,
+            targets.len()
+        );
+    } else {
+        cleanup(storage.as_deref(), &probe, &targets).await;
+    }
+
+    if !report.passed() {
+        bail!(
+            "Soak reprovado: {} estouro(s) de ciclo, {} falha(s) de gravação, {} reinício(s) do scheduler",
+            report.overruns,
+            report.write_failures,
+            report.restarts
+        );
+    }
+    Ok(())
+}
+
+/// Responder TCP que ecoa o que recebe. Escuta em todas as interfaces para
+/// atender qualquer endereço de `127.0.0.0/8`, mas só aceita conexões de
+/// loopback.
+async fn spawn_echo_responder() -> Result<u16> {
+    let socket = TcpSocket::new_v4()?;
+    socket.set_reuseaddr(true)?;
+    socket.bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))?;
+    let listener = socket.listen(LISTEN_BACKLOG)?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((mut stream, peer)) => {
+                    if !peer.ip().is_loopback() {
+                        continue;
+                    }
+                    tokio::spawn(async move {
+                        let (mut reader, mut writer) = stream.split();
+                        let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                    });
+                }
+                Err(e) => {
+                    warn!("[SOAK] Falha ao aceitar conexão no responder: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    });
+    Ok(port)
+}
+
+/// Endereço do `index`-ésimo target sintético.
+fn synthetic_address(index: usize) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(
+        127,
+        SOAK_OCTET,
+        (index / HOSTS_PER_BLOCK) as u8,
+        (index % HOSTS_PER_BLOCK + 1) as u8,
+    ))
+}
+
+/// `index`-ésimo target sintético, ainda sem id.
+fn synthetic_target(index: usize) -> Target {
+    Target {
+        id: 0,
+        name: format!("soak-{}", index + 1),
+        address: synthetic_address(index),
+        asn: None,
+        provider: Some("soak".to_string()),
+        type_: "tcp_ipv4".to_string(),
+        region: Some("soak".to_string()),
+        created_at: None,
+        template: None,
+        tags: vec!["soak".to_string()],
+        check_interval_secs: None,
+        fail_threshold: None,
+        weight: None,
+        degraded_rtt_ms: None,
+        degraded_loss_percent: None,
+        ping_payload_bytes: None,
+        dscp: None,
+        geo: GeoLocation::default(),
+        tenant: None,
+        enabled: true,
+    }
+}
+
+/// Cadastra os targets sintéticos em `targets`; em caso de erro, os já
+/// cadastrados ficam lá para a limpeza.
+async fn register_targets(
+    storage: &Storage,
+    count: usize,
+    targets: &mut Vec<Target>,
+) -> Result<()> {
+    for index in 0..count {
+        let mut target = synthetic_target(index);
+        match storage.insert_target(&target).await? {
+            Some(id) => target.id = id,
+            None => bail!(
+                "Endereço {} já está cadastrado (soak anterior com --keep?)",
+                target.address
+            ),
+        }
+        targets.push(target);
+    }
+    Ok(())
+}
+
+async fn cleanup(storage: Option<&Storage>, probe: &Probe, targets: &[Target]) {
+    let Some(storage) = storage else {
+        return;
+    };
+    let target_ids: Vec<i32> = targets.iter().map(|t| t.id).collect();
+    match storage.delete_probe_data(probe.id, &target_ids).await {
+        Ok(metrics) => info!(
+            "🧹 [SOAK] Dados do teste removidos ({} métrica(s))",
+            metrics
+        ),
+        Err(e) => warn!(
+            "⚠️ [SOAK] Falha ao remover os dados do teste (probe id {}): {:?}",
+            probe.id, e
+        ),
+    }
+}
+
+/// Resultado do teste.
+struct Report {
+    targets: usize,
+    interval: Duration,
+    elapsed: Duration,
+    /// Durações dos ciclos em ms, ordenadas.
+    cycle_ms: Vec<f64>,
+    overruns: u64,
+    skipped_cycles: u64,
+    restarts: i32,
+    metrics_written: u64,
+    batches: u64,
+    last_batch_ms: f64,
+    backpressure_waits: u64,
+    write_failures: u64,
+}
+
+impl Report {
+    async fn collect(
+        storage: &dyn StorageBackend,
+        snapshot: &SnapshotStore,
+        probe: &Probe,
+        args: &SoakArgs,
+        elapsed: Duration,
+    ) -> Result<Self> {
+        let mut cycle_ms = storage
+            .list_cycle_durations_ms(probe.id)
+            .await
+            .context("Falha ao consultar a duração dos ciclos do soak")?;
+        cycle_ms.sort_by(f64::total_cmp);
+        let current = snapshot.load();
+        let overruns = current
+            .cycle_overruns
+            .get(&probe.id)
+            .copied()
+            .unwrap_or_default();
+        Ok(Self {
+            targets: args.targets,
+            interval: args.interval,
+            elapsed,
+            cycle_ms,
+            overruns: overruns.overruns,
+            skipped_cycles: overruns.skipped_cycles,
+            restarts: current
+                .scheduler_restarts
+                .get(&probe.id)
+                .copied()
+                .unwrap_or(0),
+            metrics_written: current.persistence.metrics_written,
+            batches: current.persistence.batches,
+            last_batch_ms: current.persistence.last_batch_ms,
+            backpressure_waits: current.persistence.backpressure_waits,
+            write_failures: current.persistence.write_failures,
+        })
+    }
+
+    fn percentile(&self, p: f64) -> f64 {
+        if self.cycle_ms.is_empty() {
+            return 0.0;
+        }
+        let index = ((self.cycle_ms.len() - 1) as f64 * p).round() as usize;
+        self.cycle_ms[index]
+    }
+
+    fn passed(&self) -> bool {
+        self.overruns == 0 && self.write_failures == 0 && self.restarts == 0
+    }
+
+    fn print(&self) {
+        let secs = self.elapsed.as_secs_f64().max(1.0);
+        println!(
+            "=== Soak: {} targets, ciclo de {}s, {:.0}s ===",
+            self.targets,
+            self.interval.as_secs(),
+            secs
+        );
+        println!(
+            "Ciclos medidos: {} | p50 {:.0} ms | p95 {:.0} ms | máx {:.0} ms | intervalo {} ms",
+            self.cycle_ms.len(),
+            self.percentile(0.5),
+            self.percentile(0.95),
+            self.cycle_ms.last().copied().unwrap_or(0.0),
+            self.interval.as_millis()
+        );
+        println!(
+            "Estouros de ciclo: {} ({} ciclo(s) pulado(s)) | reinícios do scheduler: {}",
+            self.overruns, self.skipped_cycles, self.restarts
+        );
+        println!(
+            "Banco: {} métrica(s) gravada(s) ({:.0}/s) em {} lote(s), último lote {:.0} ms",
+            self.metrics_written,
+            self.metrics_written as f64 / secs,
+            self.batches,
+            self.last_batch_ms
+        );
+        println!(
+            "Fila cheia: {} espera(s) | falhas de gravação: {}",
+            self.backpressure_waits, self.write_failures
+        );
+        println!(
+            "Resultado: {}",
+            if self.passed() {
+                "aprovado"
+            } else {
+                "reprovado"
+            }
+        );
+    }
+}
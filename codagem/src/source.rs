@@ -0,0 +1,100 @@
+//! source.rs — Origem dos pacotes dos checks
+//!
+//! Num host com dois uplinks (ex: fibra e LTE), a rota padrão leva todos os
+//! checks pelo mesmo link. Com `checks.source`, `checks.sources.<check>` ou o
+//! `source` de um `[[probe_profiles]]`, os sockets dos checks são presos a
+//! uma interface (`SO_BINDTODEVICE`, que exige `CAP_NET_RAW`) e/ou a um
+//! endereço local, e cada probe lógico mede o seu uplink de forma
+//! independente. O endereço só vale para targets da mesma família; nos da
+//! outra, fica só a interface.
+
+use crate::config::SourceConfig;
+use crate::provenance;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::fd::{AsRawFd, RawFd};
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+
+/// Endereço local para alcançar `target`: o `address` configurado, se for da
+/// mesma família, ou o não especificado.
+pub fn bind_address(source: &SourceConfig, target: IpAddr) -> IpAddr {
+    match (source.address, target) {
+        (Some(address), _) if address.is_ipv4() == target.is_ipv4() => address,
+        (_, IpAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        (_, IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// Endereço local explícito para `target`, para APIs que só aceitam um
+/// endereço (ex: o resolver do check `dns`): o `address` da mesma família ou
+/// um endereço dessa família na `interface`.
+pub fn local_address(source: &SourceConfig, target: IpAddr) -> Option<IpAddr> {
+    let address = bind_address(source, target);
+    if !address.is_unspecified() {
+        return Some(address);
+    }
+    provenance::address_of(source.interface.as_deref()?, target.is_ipv6())
+}
+
+/// Prende o socket `fd` à interface `interface`.
+#[cfg(target_os = "linux")]
+pub fn bind_device(fd: RawFd, interface: &str) -> std::io::Result<()> {
+    // SAFETY: `fd` é um socket válido e o buffer tem `interface.len()` bytes
+    let result = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            interface.as_ptr() as *const libc::c_void,
+            interface.len() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bind_device(_fd: RawFd, _interface: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "origem por interface só é suportada no Linux",
+    ))
+}
+
+/// Socket UDP (bloqueante) com a origem aplicada, para alcançar `target`.
+pub fn std_udp_socket(
+    source: &SourceConfig,
+    target: IpAddr,
+) -> std::io::Result<std::net::UdpSocket> {
+    let socket = std::net::UdpSocket::bind(SocketAddr::new(bind_address(source, target), 0))?;
+    if let Some(interface) = &source.interface {
+        bind_device(socket.as_raw_fd(), interface)?;
+    }
+    Ok(socket)
+}
+
+/// Socket UDP efêmero com a origem aplicada e conectado a `addr`.
+pub async fn udp_connect(source: &SourceConfig, addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = std_udp_socket(source, addr.ip())?;
+    socket.set_nonblocking(true)?;
+    let socket = UdpSocket::from_std(socket)?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+/// Conexão TCP a `addr` com a origem aplicada.
+pub async fn tcp_connect(source: &SourceConfig, addr: SocketAddr) -> std::io::Result<TcpStream> {
+    if source.is_default() {
+        return TcpStream::connect(addr).await;
+    }
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    if let Some(interface) = &source.interface {
+        bind_device(socket.as_raw_fd(), interface)?;
+    }
+    socket.bind(SocketAddr::new(bind_address(source, addr.ip()), 0))?;
+    socket.connect(addr).await
+}
@@ -0,0 +1,96 @@
+//! spool.rs — Fila em disco para gravações pendentes durante quedas do banco
+//!
+//! Mensagens de persistência que não puderam ser gravadas por falta de
+//! conexão são anexadas a um arquivo JSONL (uma mensagem por linha). Quando a
+//! conexão volta, a task de persistência lê o arquivo inteiro, reenvia as
+//! mensagens antes das novas e o remove. O arquivo tem teto de tamanho: acima
+//! dele as mensagens novas são descartadas (e contadas).
+
+use crate::persistence::PersistMessage;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Arquivo de spool.
+#[derive(Debug)]
+pub struct Spool {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl Spool {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+        }
+    }
+
+    /// Tamanho atual do arquivo (0 se não existir).
+    pub fn len_bytes(&self) -> u64 {
+        fs::metadata(&self.path).map_or(0, |m| m.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len_bytes() == 0
+    }
+
+    /// Anexa as mensagens. Retorna quantas foram gravadas: as que passariam
+    /// do teto são descartadas.
+    pub fn append(&self, messages: &[PersistMessage]) -> io::Result<usize> {
+        if let Some(dir) = self.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let mut size = self.len_bytes();
+        let mut buffer = Vec::new();
+        let mut written = 0;
+        for message in messages {
+            let mut line = serde_json::to_vec(message)?;
+            line.push(b'\n');
+            if size + line.len() as u64 > self.max_bytes {
+                break;
+            }
+            size += line.len() as u64;
+            buffer.extend_from_slice(&line);
+            written += 1;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&buffer)?;
+        file.sync_data()?;
+        Ok(written)
+    }
+
+    /// Lê e remove todas as mensagens pendentes, na ordem em que foram
+    /// anexadas. Linhas ilegíveis (ex: escrita interrompida) são ignoradas.
+    pub fn take(&self) -> io::Result<Vec<PersistMessage>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut messages = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(message) => messages.push(message),
+                Err(e) => warn!(
+                    "[SPOOL] Linha {} de {} ignorada: {}",
+                    i + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+        fs::remove_file(&self.path)?;
+        Ok(messages)
+    }
+}
@@ -0,0 +1,485 @@
+//! status_page.rs — Geração da página pública de status
+//!
+//! Renderiza periodicamente um arquivo estático (HTML ou JSON) a partir do
+//! `target_status` atual, dos `outage_events` recentes e do último resumo de
+//! ciclo de cada probe (`cycle_summaries`), pronto para ser publicado por
+//! qualquer servidor web (nginx, S3, GitHub Pages...).
+
+use crate::anonymize::Pseudonymizer;
+use crate::config::{StatusPageConfig, StatusPageFormat};
+use crate::snapshot::{Snapshot, SnapshotStore};
+use crate::storage::Storage;
+use crate::types::{
+    CycleSummary, DailyOutageMinutes, HourlyAvailability, LatencyPercentiles, MetricStatus,
+    OutageEvent, OutageEventType, Probe, Target, TargetStatus,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error, info};
+
+/// Situação geral exibida no topo da página.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverallStatus {
+    Operational,
+    Degraded,
+    Outage,
+}
+
+impl OverallStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            OverallStatus::Operational => "Todos os sistemas operacionais",
+            OverallStatus::Degraded => "Degradação parcial",
+            OverallStatus::Outage => "Indisponibilidade em andamento",
+        }
+    }
+}
+
+/// Linha de status de um target.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetEntry {
+    pub id: i32,
+    pub name: String,
+    pub provider: Option<String>,
+    pub region: Option<String>,
+    /// `None` quando o target ainda não foi medido.
+    pub status: Option<MetricStatus>,
+    pub last_change: Option<DateTime<Utc>>,
+    /// Disponibilidade (%) nas últimas 24 horas.
+    pub availability_24h: Option<f64>,
+    /// Latência p95 (ms) da hora mais recente com amostras.
+    pub p95_latency_ms: Option<f64>,
+}
+
+/// Último ciclo de um probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeEntry {
+    pub id: i32,
+    pub location: String,
+    pub last_cycle_at: DateTime<Utc>,
+    pub targets_up: i32,
+    pub targets_degraded: i32,
+    pub targets_down: i32,
+    pub avg_rtt_ms: Option<f64>,
+    pub loss_avg_percent: Option<f64>,
+}
+
+/// Agregados lidos das views materializadas e dos resumos de ciclo.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregates {
+    pub availability: Vec<HourlyAvailability>,
+    pub latency: Vec<LatencyPercentiles>,
+    pub daily_outages: Vec<DailyOutageMinutes>,
+    /// Último resumo de ciclo de cada probe ativo.
+    pub latest_cycles: Vec<CycleSummary>,
+}
+
+/// Outage recente, com os nomes dos targets afetados já resolvidos.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutageEntry {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub duration_seconds: Option<i32>,
+    pub reason: Option<String>,
+    pub affected_targets: Vec<String>,
+}
+
+/// Conteúdo completo da página de status.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusPage {
+    pub title: String,
+    pub generated_at: DateTime<Utc>,
+    pub overall: OverallStatus,
+    /// Minutos de outage nos últimos 7 dias.
+    pub outage_minutes_7d: f64,
+    pub targets: Vec<TargetEntry>,
+    /// Probes com ciclo na última hora.
+    pub probes: Vec<ProbeEntry>,
+    pub outages: Vec<OutageEntry>,
+}
+
+impl StatusPage {
+    /// Monta a página a partir dos dados já carregados do banco.
+    pub fn build(
+        title: &str,
+        targets: &[Target],
+        probes: &[Probe],
+        statuses: &[TargetStatus],
+        outages: &[OutageEvent],
+        aggregates: &Aggregates,
+        snapshot: &Snapshot,
+    ) -> Self {
+        let mut availability: HashMap<i32, (i64, i64)> = HashMap::new();
+        for row in &aggregates.availability {
+            let entry = availability.entry(row.target_id).or_insert((0, 0));
+            entry.0 += row.up_samples;
+            entry.1 += row.samples;
+        }
+        // Linhas vêm ordenadas por hora: a última com p95 vence
+        let mut p95: HashMap<i32, f64> = HashMap::new();
+        for row in &aggregates.latency {
+            if let Some(value) = row.p95_ms {
+                p95.insert(row.target_id, value);
+            }
+        }
+
+        // Um status por probe: vale o pior, e entre iguais a mudança mais recente
+        let rank = |s: &TargetStatus| (s.last_status.severity(), s.last_change);
+        let mut by_target: HashMap<i32, &TargetStatus> = HashMap::new();
+        for status in statuses {
+            let worst = by_target.entry(status.target_id).or_insert(status);
+            if rank(status) > rank(worst) {
+                *worst = status;
+            }
+        }
+        let names: HashMap<i32, &str> = targets.iter().map(|t| (t.id, t.name.as_str())).collect();
+
+        let targets: Vec<TargetEntry> = targets
+            .iter()
+            .map(|t| {
+                // O snapshot em memória é mais recente que o target_status do banco
+                let stored = by_target.get(&t.id);
+                let (status, last_change) = match snapshot.latest_for_target(t.id) {
+                    // Sem mudança vista por este processo, a última é a do
+                    // banco, se ele concorda com o status atual
+                    Some(live) => (
+                        Some(live.status.clone()),
+                        live.last_change_at.or_else(|| {
+                            statuses
+                                .iter()
+                                .filter(|s| s.target_id == t.id && s.last_status == live.status)
+                                .map(|s| s.last_change)
+                                .max()
+                        }),
+                    ),
+                    None => stored.map_or((None, None), |s| {
+                        (Some(s.last_status.clone()), Some(s.last_change))
+                    }),
+                };
+                TargetEntry {
+                    id: t.id,
+                    name: t.name.clone(),
+                    provider: t.provider.clone(),
+                    region: t.region.clone(),
+                    status,
+                    last_change,
+                    availability_24h: availability
+                        .get(&t.id)
+                        .filter(|(_, total)| *total > 0)
+                        .map(|(up, total)| 100.0 * *up as f64 / *total as f64),
+                    p95_latency_ms: p95.get(&t.id).copied(),
+                }
+            })
+            .collect();
+
+        let locations: HashMap<i32, &str> =
+            probes.iter().map(|p| (p.id, p.location.as_str())).collect();
+        let probes: Vec<ProbeEntry> = aggregates
+            .latest_cycles
+            .iter()
+            .map(|c| ProbeEntry {
+                id: c.probe_id,
+                location: locations
+                    .get(&c.probe_id)
+                    .map_or_else(|| format!("#{}", c.probe_id), |l| l.to_string()),
+                last_cycle_at: c.started_at,
+                targets_up: c.targets_up,
+                targets_degraded: c.targets_degraded,
+                targets_down: c.targets_down,
+                avg_rtt_ms: c.avg_rtt_ms,
+                loss_avg_percent: c.loss_avg_percent,
+            })
+            .collect();
+
+        // Degradações aparecem pelo status dos targets, não como outage
+        let outages: Vec<OutageEntry> = outages
+            .iter()
+            .filter(|o| o.event_type == OutageEventType::Outage)
+            .map(|o| OutageEntry {
+                start_time: o.start_time,
+                end_time: o.end_time,
+                duration_seconds: o.duration_seconds,
+                reason: o.reason.clone(),
+                affected_targets: o
+                    .affected_targets
+                    .iter()
+                    .map(|id| {
+                        names
+                            .get(id)
+                            .map_or_else(|| format!("#{}", id), |n| n.to_string())
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let overall = if !snapshot.active_incidents.is_empty()
+            || outages.iter().any(|o| o.end_time.is_none())
+        {
+            OverallStatus::Outage
+        } else if targets
+            .iter()
+            .any(|t| matches!(&t.status, Some(s) if *s != MetricStatus::Up))
+        {
+            OverallStatus::Degraded
+        } else {
+            OverallStatus::Operational
+        };
+
+        Self {
+            title: title.to_string(),
+            generated_at: Utc::now(),
+            overall,
+            outage_minutes_7d: aggregates
+                .daily_outages
+                .iter()
+                .map(|d| d.outage_minutes)
+                .sum(),
+            targets,
+            probes,
+            outages,
+        }
+    }
+
+    /// Consulta o banco e monta a página.
+    pub async fn collect(
+        storage: &Storage,
+        snapshot: &Snapshot,
+        config: &StatusPageConfig,
+        pseudonymizer: Option<&Pseudonymizer>,
+    ) -> Result<Self> {
+        let mut targets = storage.list_targets().await?;
+        if let Some(p) = pseudonymizer.filter(|p| p.status_page) {
+            targets = targets.iter().map(|t| p.target(t)).collect();
+        }
+        let probes = storage.list_probes().await?;
+        let statuses = storage.list_all_target_status().await?;
+        let outages = storage
+            .list_recent_outage_events(config.recent_outages)
+            .await?;
+        let now = Utc::now();
+        let day_ago = now - chrono::Duration::hours(24);
+        let aggregates = Aggregates {
+            availability: storage.list_hourly_availability(day_ago).await?,
+            latency: storage.list_hourly_latency(day_ago).await?,
+            daily_outages: storage
+                .list_daily_outage_minutes(now - chrono::Duration::days(7))
+                .await?,
+            latest_cycles: storage
+                .list_latest_cycle_summaries(now - chrono::Duration::hours(1))
+                .await?,
+        };
+        Ok(Self::build(
+            &config.title,
+            &targets,
+            &probes,
+            &statuses,
+            &outages,
+            &aggregates,
+            snapshot,
+        ))
+    }
+
+    /// Renderiza a página no formato JSON.
+    pub fn render_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renderiza a página como HTML estático autocontido.
+    pub fn render_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"pt-BR\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta http-equiv=\"refresh\" content=\"60\">\n<title>{title}</title>\n<style>\n\
+             body{{font-family:sans-serif;max-width:960px;margin:2em auto;color:#222}}\n\
+             table{{border-collapse:collapse;width:100%}}td,th{{padding:.4em;border-bottom:1px solid #ddd;text-align:left}}\n\
+             .banner{{padding:1em;border-radius:6px;color:#fff}}\n\
+             .operational{{background:#2e7d32}}.degraded{{background:#f9a825}}.outage{{background:#c62828}}\n\
+             .up{{color:#2e7d32}}.down,.timeout{{color:#c62828}}.degraded-status{{color:#f9a825}}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+             <div class=\"banner {overall_class}\">{overall}</div>\n\
+             <p>Minutos de indisponibilidade nos últimos 7 dias: {outage_minutes:.1}</p>\n",
+            title = escape_html(&self.title),
+            overall_class = overall_class(self.overall),
+            overall = self.overall.label(),
+            outage_minutes = self.outage_minutes_7d,
+        );
+
+        html.push_str("<h2>Targets</h2>\n<table>\n<tr><th>Nome</th><th>Provedor</th><th>Região</th><th>Status</th><th>Disponibilidade 24h</th><th>Latência p95</th><th>Última mudança</th></tr>\n");
+        for target in &self.targets {
+            let (class, label) = status_label(target.status.as_ref());
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&target.name),
+                escape_html(target.provider.as_deref().unwrap_or("-")),
+                escape_html(target.region.as_deref().unwrap_or("-")),
+                class,
+                label,
+                target
+                    .availability_24h
+                    .map_or("-".to_string(), |a| format!("{:.2}%", a)),
+                target
+                    .p95_latency_ms
+                    .map_or("-".to_string(), |l| format!("{:.1} ms", l)),
+                target
+                    .last_change
+                    .map_or("-".to_string(), |t| t.to_rfc3339()),
+            );
+        }
+        html.push_str("</table>\n");
+
+        if !self.probes.is_empty() {
+            html.push_str("<h2>Probes</h2>\n<table>\n<tr><th>Localização</th><th>Up</th><th>Degradados</th><th>Down</th><th>RTT médio</th><th>Perda média</th><th>Último ciclo</th></tr>\n");
+            for probe in &self.probes {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape_html(&probe.location),
+                    probe.targets_up,
+                    probe.targets_degraded,
+                    probe.targets_down,
+                    probe
+                        .avg_rtt_ms
+                        .map_or("-".to_string(), |r| format!("{:.1} ms", r)),
+                    probe
+                        .loss_avg_percent
+                        .map_or("-".to_string(), |l| format!("{:.1}%", l)),
+                    probe.last_cycle_at.to_rfc3339(),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Outages recentes</h2>\n");
+        if self.outages.is_empty() {
+            html.push_str("<p>Nenhum outage registrado.</p>\n");
+        } else {
+            html.push_str("<table>\n<tr><th>Início</th><th>Fim</th><th>Duração (s)</th><th>Motivo</th><th>Targets afetados</th></tr>\n");
+            for outage in &self.outages {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    outage.start_time.to_rfc3339(),
+                    outage
+                        .end_time
+                        .map_or("em andamento".to_string(), |t| t.to_rfc3339()),
+                    outage
+                        .duration_seconds
+                        .map_or("-".to_string(), |d| d.to_string()),
+                    escape_html(outage.reason.as_deref().unwrap_or("-")),
+                    escape_html(&outage.affected_targets.join(", ")),
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        let _ = write!(
+            html,
+            "<p><small>Gerado em {}</small></p>\n</body>\n</html>\n",
+            self.generated_at.to_rfc3339()
+        );
+        html
+    }
+
+    /// Renderiza no formato configurado.
+    pub fn render(&self, format: StatusPageFormat) -> Result<String> {
+        match format {
+            StatusPageFormat::Html => Ok(self.render_html()),
+            StatusPageFormat::Json => self.render_json(),
+        }
+    }
+}
+
+fn overall_class(status: OverallStatus) -> &'static str {
+    match status {
+        OverallStatus::Operational => "operational",
+        OverallStatus::Degraded => "degraded",
+        OverallStatus::Outage => "outage",
+    }
+}
+
+fn status_label(status: Option<&MetricStatus>) -> (&'static str, &'static str) {
+    match status {
+        Some(MetricStatus::Up) => ("up", "Operacional"),
+        Some(MetricStatus::Degraded) => ("degraded-status", "Degradado"),
+        Some(MetricStatus::Down) => ("down", "Fora do ar"),
+        Some(MetricStatus::Timeout) => ("timeout", "Sem resposta"),
+        None => ("", "Sem dados"),
+    }
+}
+
+/// Escapa os caracteres especiais de HTML.
+fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Grava o conteúdo de forma atômica (arquivo temporário + rename), para que
+/// o servidor web nunca sirva uma página pela metade.
+async fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, content)
+        .await
+        .with_context(|| format!("Falha ao gravar {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Falha ao mover {} para {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Loop de geração da página de status.
+/// Regenera o arquivo a cada `interval_secs`; falhas são logadas e o loop continua.
+pub async fn run_status_page(
+    config: StatusPageConfig,
+    storage: Arc<Storage>,
+    snapshot: Arc<SnapshotStore>,
+    pseudonymizer: Option<Pseudonymizer>,
+) {
+    info!(
+        "📰 Página de status habilitada: {} ({:?}, a cada {}s)",
+        config.output_path, config.format, config.interval_secs
+    );
+    let path = Path::new(&config.output_path).to_path_buf();
+    let mut ticker = interval(Duration::from_secs(config.interval_secs.max(1)));
+
+    loop {
+        ticker.tick().await;
+        let result = async {
+            let page =
+                StatusPage::collect(&storage, &snapshot.load(), &config, pseudonymizer.as_ref())
+                    .await?;
+            let content = page.render(config.format)?;
+            write_atomic(&path, &content).await?;
+            Ok::<_, anyhow::Error>(page.overall)
+        }
+        .await;
+
+        match result {
+            Ok(overall) => debug!(
+                "[STATUS PAGE] Página gerada em {} ({:?})",
+                path.display(),
+                overall
+            ),
+            Err(e) => error!("[STATUS PAGE] Falha ao gerar página de status: {:?}", e),
+        }
+    }
+}
@@ -1,8 +1,65 @@
+use crate::baseline::AnomalyEvent;
+use crate::check::RawSample;
+use crate::config::TimescaleConfig;
+use crate::decisions::ConsensusDecision;
+use crate::host::HostSample;
+use crate::migrations;
+use crate::neighbor::NeighborSample;
+use crate::netwatch::HostNetworkEvent;
+use crate::snmp::DeviceMetric;
+use crate::supervisor::SchedulerIncident;
+use crate::traceroute::Traceroute;
 use crate::types::{
-    ConnectivityMetric, Cycle, MetricStatus, MetricType, OutageEvent, Probe, Target, TargetStatus,
+    ConnectivityMetric, Cycle, CycleSummary, DailyOutageMinutes, DnsQueryLatency, GeoLocation,
+    HourlyAvailability, LatencyPercentiles, MaintenanceWindow, MetricKey, MetricStatus,
+    OutageAnnotation, OutageEvent, Probe, ProbeTargetStats, Target, TargetPeriodStats,
+    TargetStatus,
 };
 use anyhow::Result;
-use tokio_postgres::{Client, NoTls, Row};
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls};
+use tracing::{debug, info};
+
+/// Resultado da remoção de um target ou probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    /// Há métricas ou eventos gravados que referenciam o registro.
+    HasHistory,
+}
+
+/// Tabelas com histórico por target (chave estrangeira `target_id`).
+const TARGET_HISTORY_TABLES: [&str; 7] = [
+    "connectivity_metrics",
+    "latency_histograms",
+    "anomaly_events",
+    "traceroutes",
+    "raw_samples",
+    "transitions",
+    "target_status",
+];
+
+/// Tabelas com histórico por probe (chave estrangeira `probe_id`).
+const PROBE_HISTORY_TABLES: [&str; 10] = [
+    "connectivity_metrics",
+    "latency_histograms",
+    "probe_host_samples",
+    "anomaly_events",
+    "scheduler_incidents",
+    "consensus_decisions",
+    "traceroutes",
+    "raw_samples",
+    "transitions",
+    "cycle_summaries",
+];
 
 /// Storage: Camada de persistência usando tokio_postgres
 ///
@@ -10,7 +67,9 @@ use tokio_postgres::{Client, NoTls, Row};
 /// utilizando tipos seguros e padrões funcionais do Rust. Todos os métodos são
 /// assíncronos e retornam Result<T> para tratamento robusto de erros.
 pub struct Storage {
-    client: Client,
+    database_url: String,
+    /// Trocado por [`Storage::reconnect`] quando a conexão cai.
+    client: ArcSwap<Client>,
 }
 
 impl Storage {
@@ -27,6 +86,38 @@ impl Storage {
     /// let storage = Storage::connect("postgresql://localhost/monitoring").await?;
     /// ```
     pub async fn connect(database_url: &str) -> Result<Self> {
+        let client = Self::open(database_url).await?;
+        Ok(Self {
+            database_url: database_url.to_string(),
+            client: ArcSwap::from_pointee(client),
+        })
+    }
+
+    /// Conecta a uma réplica somente leitura (`database_read_url`), usada
+    /// pelas consultas. Como a réplica não passa pela task de persistência,
+    /// uma task própria reabre a conexão a cada 10s enquanto ela estiver
+    /// caída.
+    pub async fn connect_replica(database_url: &str) -> Result<Arc<Self>> {
+        let storage = Arc::new(Self::connect(database_url).await?);
+        let replica = Arc::clone(&storage);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                interval.tick().await;
+                if replica.is_connected() {
+                    continue;
+                }
+                match tokio::time::timeout(Duration::from_secs(5), replica.reconnect()).await {
+                    Ok(Ok(())) => info!("📖 Conexão com a réplica de leitura restabelecida"),
+                    Ok(Err(e)) => debug!("Réplica de leitura ainda indisponível: {:?}", e),
+                    Err(_) => debug!("Timeout ao reconectar à réplica de leitura"),
+                }
+            }
+        });
+        Ok(storage)
+    }
+
+    async fn open(database_url: &str) -> Result<Client> {
         let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
         // Spawn a task to drive the connection
         tokio::spawn(async move {
@@ -34,7 +125,62 @@ impl Storage {
                 eprintln!("Postgres connection error: {}", e);
             }
         });
-        Ok(Self { client })
+        Ok(client)
+    }
+
+    fn client(&self) -> Arc<Client> {
+        self.client.load_full()
+    }
+
+    /// A conexão atual ainda está aberta?
+    pub fn is_connected(&self) -> bool {
+        !self.client.load().is_closed()
+    }
+
+    /// Reabre a conexão se ela caiu. Chamadas em andamento na conexão antiga
+    /// falham normalmente; as seguintes usam a nova.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso (conexão aberta) ou erro de conexão
+    pub async fn reconnect(&self) -> Result<()> {
+        if self.is_connected() {
+            return Ok(());
+        }
+        let client = Self::open(&self.database_url).await?;
+        self.client.store(Arc::new(client));
+        Ok(())
+    }
+
+    /// Aplica as migrações de schema pendentes.
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Quantidade de migrações aplicadas
+    pub async fn run_migrations(&self) -> Result<usize> {
+        migrations::apply(&self.client()).await
+    }
+
+    /// Aplica o modo TimescaleDB (hypertable e compressão) de `[timescale]`.
+    pub async fn setup_timescale(&self, config: &TimescaleConfig) -> Result<()> {
+        migrations::apply_timescale(&self.client(), config).await
+    }
+
+    /// Versões da extensão `timescaledb` no servidor: `None` se não estiver
+    /// disponível, senão a instalada no banco (se houver) e a padrão.
+    pub async fn timescale_versions(&self) -> Result<Option<(Option<String>, String)>> {
+        Ok(self
+            .client()
+            .query_opt(
+                "SELECT installed_version, default_version
+                 FROM pg_available_extensions WHERE name = 'timescaledb'",
+                &[],
+            )
+            .await?
+            .map(|row| (row.get("installed_version"), row.get("default_version"))))
+    }
+
+    /// Versões de schema já aplicadas, sem aplicar nada.
+    pub async fn schema_versions(&self) -> Result<Vec<i32>> {
+        migrations::applied(&self.client()).await
     }
 
     /// Lista todos os targets monitorados.
@@ -43,166 +189,2323 @@ impl Storage {
     /// * `Result<Vec<Target>>` - Lista de targets ou erro de consulta
     pub async fn list_targets(&self) -> Result<Vec<Target>> {
         let rows = self
-            .client
+            .client()
             .query(
-                "SELECT id, name, address, asn, provider, type, region, created_at FROM monitoring_targets ORDER BY id",
+                "SELECT id, name, address, asn, provider, type, region, created_at,
+                        template, tags, check_interval_secs, fail_threshold, weight,
+                        degraded_rtt_ms, degraded_loss_percent, ping_payload_bytes, dscp,
+                        country_code, city, latitude, longitude, tenant, enabled
+                 FROM monitoring_targets ORDER BY id",
                 &[],
             )
             .await?;
         Ok(rows.into_iter().map(Target::from).collect())
     }
 
+    /// Insere um target, ignorando-o se o endereço já estiver cadastrado.
+    ///
+    /// # Returns
+    /// * `Result<Option<i32>>` - ID do target inserido, ou `None` se já existia
+    pub async fn insert_target(&self, target: &Target) -> Result<Option<i32>> {
+        let row = self
+            .client()
+            .query_opt(
+                "INSERT INTO monitoring_targets
+                 (name, address, asn, provider, type, region, template, tags, check_interval_secs, fail_threshold, weight, degraded_rtt_ms, degraded_loss_percent, ping_payload_bytes, dscp, tenant)
+                 VALUES ($1, $2, $3, $4, $5, COALESCE($6, 'global'), $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+                 ON CONFLICT (address) DO NOTHING RETURNING id",
+                &[
+                    &target.name,
+                    &target.address,
+                    &target.asn,
+                    &target.provider,
+                    &target.type_,
+                    &target.region,
+                    &target.template,
+                    &target.tags,
+                    &target.check_interval_secs,
+                    &target.fail_threshold,
+                    &target.weight,
+                    &target.degraded_rtt_ms,
+                    &target.degraded_loss_percent,
+                    &target.ping_payload_bytes,
+                    &target.dscp,
+                    &target.tenant,
+                ],
+            )
+            .await?;
+        Ok(row.map(|r| r.get("id")))
+    }
+
     /// Lista todos os probes cadastrados.
     ///
     /// # Returns
     /// * `Result<Vec<Probe>>` - Lista de probes ou erro de consulta
     pub async fn list_probes(&self) -> Result<Vec<Probe>> {
         let rows = self
-            .client
+            .client()
             .query(
-                "SELECT id, location, ip_address, provider, created_at FROM monitoring_probes ORDER BY id",
+                "SELECT id, location, ip_address, provider, created_at,
+                        country_code, city, latitude, longitude
+                 FROM monitoring_probes ORDER BY id",
                 &[],
             )
             .await?;
         Ok(rows.into_iter().map(Probe::from).collect())
     }
 
-    /// Insere um novo ciclo de monitoramento e retorna o id gerado.
+    /// Insere um probe e retorna o id gerado.
     ///
     /// # Returns
-    /// * `Result<i64>` - ID do ciclo inserido ou erro de inserção
-    pub async fn insert_cycle(&self, cycle: &Cycle) -> Result<i64> {
+    /// * `Result<i32>` - ID do probe inserido ou erro de inserção
+    pub async fn insert_probe(&self, probe: &Probe) -> Result<i32> {
         let row = self
-            .client
+            .client()
             .query_one(
-                "INSERT INTO monitoring_cycles (started_at, ended_at, cycle_number, probe_count)
-                 VALUES ($1, $2, $3, $4) RETURNING id",
+                "INSERT INTO monitoring_probes (location, ip_address, provider)
+                 VALUES ($1, $2, $3) RETURNING id",
+                &[&probe.location, &probe.ip_address, &probe.provider],
+            )
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    /// Busca um target pelo id.
+    ///
+    /// # Returns
+    /// * `Result<Option<Target>>` - Target ou None se não encontrado
+    pub async fn get_target(&self, target_id: i32) -> Result<Option<Target>> {
+        let row = self
+            .client()
+            .query_opt(
+                "SELECT id, name, address, asn, provider, type, region, created_at,
+                        template, tags, check_interval_secs, fail_threshold, weight,
+                        degraded_rtt_ms, degraded_loss_percent, ping_payload_bytes, dscp,
+                        country_code, city, latitude, longitude, tenant, enabled
+                 FROM monitoring_targets WHERE id = $1",
+                &[&target_id],
+            )
+            .await?;
+        Ok(row.map(Target::from))
+    }
+
+    /// Grava os campos editáveis do target (o endereço e o tipo não mudam).
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` se o target não existe
+    pub async fn update_target(&self, target: &Target) -> Result<bool> {
+        let updated = self
+            .client()
+            .execute(
+                "UPDATE monitoring_targets
+                 SET name = $2, asn = $3, provider = $4, region = COALESCE($5, 'global'), tags = $6,
+                     check_interval_secs = $7, fail_threshold = $8, weight = $9,
+                     degraded_rtt_ms = $10, degraded_loss_percent = $11,
+                     ping_payload_bytes = $12, dscp = $13, tenant = $14, enabled = $15
+                 WHERE id = $1",
                 &[
-                    &cycle.started_at,
-                    &cycle.ended_at,
-                    &cycle.cycle_number,
-                    &cycle.probe_count,
+                    &target.id,
+                    &target.name,
+                    &target.asn,
+                    &target.provider,
+                    &target.region,
+                    &target.tags,
+                    &target.check_interval_secs,
+                    &target.fail_threshold,
+                    &target.weight,
+                    &target.degraded_rtt_ms,
+                    &target.degraded_loss_percent,
+                    &target.ping_payload_bytes,
+                    &target.dscp,
+                    &target.tenant,
+                    &target.enabled,
+                ],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// Pausa (`enabled = false`) ou retoma o monitoramento dos targets.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantos targets mudaram de estado
+    pub async fn set_targets_enabled(&self, target_ids: &[i32], enabled: bool) -> Result<u64> {
+        let updated = self
+            .client()
+            .execute(
+                "UPDATE monitoring_targets SET enabled = $2
+                 WHERE id = ANY($1) AND enabled <> $2",
+                &[&target_ids, &enabled],
+            )
+            .await?;
+        Ok(updated)
+    }
+
+    /// Ids dos targets com o monitoramento pausado.
+    ///
+    /// # Returns
+    /// * `Result<HashSet<i32>>` - Targets com `enabled = false`
+    pub async fn list_disabled_target_ids(&self) -> Result<HashSet<i32>> {
+        let rows = self
+            .client()
+            .query("SELECT id FROM monitoring_targets WHERE NOT enabled", &[])
+            .await?;
+        Ok(rows.into_iter().map(|r| r.get("id")).collect())
+    }
+
+    /// Remove um target. Sem `purge`, um target com histórico gravado é
+    /// mantido; com `purge`, o histórico por target é apagado junto.
+    ///
+    /// # Returns
+    /// * `Result<DeleteOutcome>` - Resultado da remoção
+    pub async fn delete_target(&self, target_id: i32, purge: bool) -> Result<DeleteOutcome> {
+        let client = self.client();
+        if purge {
+            for table in TARGET_HISTORY_TABLES {
+                client
+                    .execute(
+                        &format!("DELETE FROM {} WHERE target_id = $1", table),
+                        &[&target_id],
+                    )
+                    .await?;
+            }
+        } else if has_history(&client, &TARGET_HISTORY_TABLES, "target_id", target_id).await? {
+            return Ok(DeleteOutcome::HasHistory);
+        }
+        delete_row(&client, "monitoring_targets", target_id).await
+    }
+
+    /// Grava local, IP e provedor do probe.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` se o probe não existe
+    pub async fn update_probe(&self, probe: &Probe) -> Result<bool> {
+        let updated = self
+            .client()
+            .execute(
+                "UPDATE monitoring_probes SET location = $2, ip_address = $3, provider = $4
+                 WHERE id = $1",
+                &[
+                    &probe.id,
+                    &probe.location,
+                    &probe.ip_address,
+                    &probe.provider,
+                ],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// Remove um probe. Sem `purge`, um probe com histórico gravado é
+    /// mantido; com `purge`, o histórico por probe é apagado junto.
+    ///
+    /// # Returns
+    /// * `Result<DeleteOutcome>` - Resultado da remoção
+    pub async fn delete_probe(&self, probe_id: i32, purge: bool) -> Result<DeleteOutcome> {
+        let client = self.client();
+        if purge {
+            for table in PROBE_HISTORY_TABLES {
+                client
+                    .execute(
+                        &format!("DELETE FROM {} WHERE probe_id = $1", table),
+                        &[&probe_id],
+                    )
+                    .await?;
+            }
+        } else if has_history(&client, &PROBE_HISTORY_TABLES, "probe_id", probe_id).await? {
+            return Ok(DeleteOutcome::HasHistory);
+        }
+        delete_row(&client, "monitoring_probes", probe_id).await
+    }
+
+    /// Lista os targets atribuídos a cada probe (`probe_targets`). Probes
+    /// ausentes do mapa não têm atribuição e monitoram todos os targets.
+    ///
+    /// # Returns
+    /// * `Result<HashMap<i32, Vec<i32>>>` - target_ids por probe_id
+    pub async fn list_probe_targets(&self) -> Result<HashMap<i32, Vec<i32>>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id FROM probe_targets ORDER BY probe_id, target_id",
+                &[],
+            )
+            .await?;
+        let mut assignments: HashMap<i32, Vec<i32>> = HashMap::new();
+        for row in rows {
+            assignments
+                .entry(row.get("probe_id"))
+                .or_default()
+                .push(row.get("target_id"));
+        }
+        Ok(assignments)
+    }
+
+    /// Atribui targets a um probe (atribuições existentes são mantidas).
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Atribuições novas
+    pub async fn assign_probe_targets(&self, probe_id: i32, target_ids: &[i32]) -> Result<u64> {
+        Ok(self
+            .client()
+            .execute(
+                "INSERT INTO probe_targets (probe_id, target_id)
+                 SELECT $1, unnest($2::INTEGER[])
+                 ON CONFLICT DO NOTHING",
+                &[&probe_id, &target_ids],
+            )
+            .await?)
+    }
+
+    /// Remove atribuições de um probe; com `target_ids` vazio, todas (o probe
+    /// volta a monitorar todos os targets).
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Atribuições removidas
+    pub async fn unassign_probe_targets(&self, probe_id: i32, target_ids: &[i32]) -> Result<u64> {
+        let client = self.client();
+        let removed = if target_ids.is_empty() {
+            client
+                .execute(
+                    "DELETE FROM probe_targets WHERE probe_id = $1",
+                    &[&probe_id],
+                )
+                .await?
+        } else {
+            client
+                .execute(
+                    "DELETE FROM probe_targets WHERE probe_id = $1 AND target_id = ANY($2)",
+                    &[&probe_id, &target_ids],
+                )
+                .await?
+        };
+        Ok(removed)
+    }
+
+    /// Lista as janelas de manutenção; com `ending_after`, só as que
+    /// terminam depois desse instante (ativas ou futuras).
+    ///
+    /// # Returns
+    /// * `Result<Vec<MaintenanceWindow>>` - Janelas por início
+    pub async fn list_maintenance_windows(
+        &self,
+        ending_after: Option<DateTime<Utc>>,
+    ) -> Result<Vec<MaintenanceWindow>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, probe_id, target_id, starts_at, ends_at, reason, created_at
+                 FROM maintenance_windows
+                 WHERE $1::TIMESTAMPTZ IS NULL OR ends_at > $1
+                 ORDER BY starts_at, id",
+                &[&ending_after],
+            )
+            .await?;
+        Ok(rows.into_iter().map(MaintenanceWindow::from).collect())
+    }
+
+    /// Busca uma janela de manutenção pelo id.
+    ///
+    /// # Returns
+    /// * `Result<Option<MaintenanceWindow>>` - Janela ou None se não encontrada
+    pub async fn get_maintenance_window(&self, id: i64) -> Result<Option<MaintenanceWindow>> {
+        let row = self
+            .client()
+            .query_opt(
+                "SELECT id, probe_id, target_id, starts_at, ends_at, reason, created_at
+                 FROM maintenance_windows WHERE id = $1",
+                &[&id],
+            )
+            .await?;
+        Ok(row.map(MaintenanceWindow::from))
+    }
+
+    /// Insere uma janela de manutenção e retorna o id gerado.
+    ///
+    /// # Returns
+    /// * `Result<i64>` - ID da janela inserida ou erro de inserção
+    pub async fn insert_maintenance_window(&self, window: &MaintenanceWindow) -> Result<i64> {
+        let row = self
+            .client()
+            .query_one(
+                "INSERT INTO maintenance_windows (probe_id, target_id, starts_at, ends_at, reason)
+                 VALUES ($1, $2, $3, $4, $5) RETURNING id",
+                &[
+                    &window.probe_id,
+                    &window.target_id,
+                    &window.starts_at,
+                    &window.ends_at,
+                    &window.reason,
                 ],
             )
             .await?;
         Ok(row.get("id"))
     }
 
-    /// Insere uma métrica de conectividade (ping, tcp, http, dns).
+    /// Grava os campos da janela de manutenção.
     ///
     /// # Returns
-    /// * `Result<()>` - Sucesso ou erro de inserção
-    pub async fn insert_connectivity_metric(&self, metric: &ConnectivityMetric) -> Result<()> {
-        self.client
+    /// * `Result<bool>` - `false` se a janela não existe
+    pub async fn update_maintenance_window(&self, window: &MaintenanceWindow) -> Result<bool> {
+        let updated = self
+            .client()
             .execute(
-                "INSERT INTO connectivity_metrics
-                 (cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_message)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                "UPDATE maintenance_windows
+                 SET probe_id = $2, target_id = $3, starts_at = $4, ends_at = $5, reason = $6
+                 WHERE id = $1",
                 &[
-                    &metric.cycle_id,
-                    &metric.probe_id,
-                    &metric.target_id,
-                    &metric.timestamp,
-                    &metric.metric_type,
-                    &metric.status,
-                    &metric.response_time_ms,
-                    &metric.packet_loss_percent,
-                    &metric.error_message,
+                    &window.id,
+                    &window.probe_id,
+                    &window.target_id,
+                    &window.starts_at,
+                    &window.ends_at,
+                    &window.reason,
                 ],
             )
             .await?;
-        Ok(())
+        Ok(updated > 0)
+    }
+
+    /// Remove uma janela de manutenção.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` se a janela não existe
+    pub async fn delete_maintenance_window(&self, id: i64) -> Result<bool> {
+        let deleted = self
+            .client()
+            .execute("DELETE FROM maintenance_windows WHERE id = $1", &[&id])
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Duração (ms) de cada ciclo do probe: do início do ciclo à última
+    /// métrica medida nele.
+    ///
+    /// # Returns
+    /// * `Result<Vec<f64>>` - Durações em ordem de ciclo
+    pub async fn list_cycle_durations_ms(&self, probe_id: i32) -> Result<Vec<f64>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT (EXTRACT(EPOCH FROM MAX(m.timestamp) - c.started_at) * 1000)::DOUBLE PRECISION AS ms
+                 FROM monitoring_cycles c JOIN connectivity_metrics m ON m.cycle_id = c.id
+                 WHERE m.probe_id = $1
+                 GROUP BY c.id, c.started_at ORDER BY c.id",
+                &[&probe_id],
+            )
+            .await?;
+        Ok(rows.into_iter().map(|row| row.get("ms")).collect())
+    }
+
+    /// Remove um probe, seus targets e tudo o que foi gravado por eles
+    /// (usado pelo modo soak).
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de métricas removidas
+    pub async fn delete_probe_data(&self, probe_id: i32, target_ids: &[i32]) -> Result<u64> {
+        let client = self.client();
+        let cycle_ids: Vec<i64> = client
+            .query(
+                "SELECT DISTINCT cycle_id FROM connectivity_metrics
+                 WHERE probe_id = $1 AND cycle_id IS NOT NULL",
+                &[&probe_id],
+            )
+            .await?
+            .into_iter()
+            .map(|row| row.get("cycle_id"))
+            .collect();
+        let metrics = client
+            .execute(
+                "DELETE FROM connectivity_metrics WHERE probe_id = $1 OR target_id = ANY($2)",
+                &[&probe_id, &target_ids],
+            )
+            .await?;
+        client
+            .execute(
+                "DELETE FROM monitoring_cycles WHERE id = ANY($1)",
+                &[&cycle_ids],
+            )
+            .await?;
+        for table in [
+            "probe_host_samples",
+            "anomaly_events",
+            "scheduler_incidents",
+            "consensus_decisions",
+            "traceroutes",
+            "raw_samples",
+            "cycle_summaries",
+        ] {
+            client
+                .execute(
+                    &format!("DELETE FROM {} WHERE probe_id = $1", table),
+                    &[&probe_id],
+                )
+                .await?;
+        }
+        for table in ["outage_events", "shadow_outage_events"] {
+            client
+                .execute(
+                    &format!("DELETE FROM {} WHERE affected_targets && $1", table),
+                    &[&target_ids],
+                )
+                .await?;
+        }
+        client
+            .execute(
+                "DELETE FROM transitions WHERE probe_id = $1 OR target_id = ANY($2)",
+                &[&probe_id, &target_ids],
+            )
+            .await?;
+        client
+            .execute(
+                "DELETE FROM target_status WHERE target_id = ANY($1)",
+                &[&target_ids],
+            )
+            .await?;
+        client
+            .execute(
+                "DELETE FROM monitoring_targets WHERE id = ANY($1)",
+                &[&target_ids],
+            )
+            .await?;
+        client
+            .execute("DELETE FROM monitoring_probes WHERE id = $1", &[&probe_id])
+            .await?;
+        Ok(metrics)
     }
 
-    /// Insere um evento de outage.
+    /// Insere um novo ciclo de monitoramento e retorna o id gerado.
+    ///
+    /// # Returns
+    /// * `Result<i64>` - ID do ciclo inserido ou erro de inserção
+    pub async fn insert_cycle(&self, cycle: &Cycle) -> Result<i64> {
+        let row = self
+            .client()
+            .query_one(
+                "INSERT INTO monitoring_cycles (started_at, ended_at, cycle_number, probe_count, degraded_conditions)
+                 VALUES ($1, $2, $3, $4, $5) RETURNING id",
+                &[
+                    &cycle.started_at,
+                    &cycle.ended_at,
+                    &cycle.cycle_number,
+                    &cycle.probe_count,
+                    &cycle.degraded_conditions,
+                ],
+            )
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    /// Insere o resumo de um ciclo.
     ///
     /// # Returns
     /// * `Result<()>` - Sucesso ou erro de inserção
-    pub async fn insert_outage_event(&self, event: &OutageEvent) -> Result<()> {
-        self.client
+    pub async fn insert_cycle_summary(&self, summary: &CycleSummary) -> Result<()> {
+        self.client()
             .execute(
-                "INSERT INTO outage_events
-                 (start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                "INSERT INTO cycle_summaries
+                 (cycle_id, probe_id, started_at, targets_up, targets_degraded, targets_down, avg_rtt_ms, loss_avg_percent, duration_ms)
+                 VALUES (NULLIF($1::BIGINT, 0), $2, $3, $4, $5, $6, $7, $8, $9)",
                 &[
-                    &event.start_time,
-                    &event.end_time,
-                    &event.duration_seconds,
-                    &event.reason,
-                    &event.affected_targets,
-                    &event.affected_probes,
-                    &event.consensus_level,
-                    &event.details,
+                    &summary.cycle_id,
+                    &summary.probe_id,
+                    &summary.started_at,
+                    &summary.targets_up,
+                    &summary.targets_degraded,
+                    &summary.targets_down,
+                    &summary.avg_rtt_ms,
+                    &summary.loss_avg_percent,
+                    &summary.duration_ms,
                 ],
             )
             .await?;
         Ok(())
     }
 
-    /// Recupera o último status persistido do target.
+    /// Busca o resumo de um ciclo pelo id do ciclo.
     ///
     /// # Returns
-    /// * `Result<Option<MetricStatus>>` - Status ou None se não encontrado
-    pub async fn get_target_status(&self, target_id: i32) -> Result<Option<MetricStatus>> {
+    /// * `Result<Option<CycleSummary>>` - O resumo, se existir
+    pub async fn get_cycle_summary(&self, cycle_id: i64) -> Result<Option<CycleSummary>> {
         let row = self
-            .client
+            .client()
             .query_opt(
-                "SELECT last_status FROM target_status WHERE target_id = $1",
-                &[&target_id],
+                "SELECT cycle_id, probe_id, started_at, targets_up, targets_degraded, targets_down, avg_rtt_ms, loss_avg_percent, duration_ms
+                 FROM cycle_summaries WHERE cycle_id = $1",
+                &[&cycle_id],
             )
             .await?;
-        Ok(row.map(|r| r.get("last_status")))
+        Ok(row.map(CycleSummary::from))
     }
 
-    /// Atualiza o status persistido do target.
+    /// Lista os resumos de ciclo desde `since` (de um probe ou de todos), do
+    /// mais recente para o mais antigo, limitados a `limit` linhas.
     ///
     /// # Returns
-    /// * `Result<()>` - Sucesso ou erro de atualização
-    pub async fn set_target_status(&self, target_id: i32, status: &MetricStatus) -> Result<()> {
-        self.client
-            .execute(
-                "INSERT INTO target_status (target_id, last_status, last_change)
-                 VALUES ($1, $2, NOW())
-                 ON CONFLICT (target_id) DO UPDATE SET last_status = $2, last_change = NOW()",
-                &[&target_id, status],
+    /// * `Result<Vec<CycleSummary>>` - Resumos em ordem decrescente de início
+    pub async fn list_cycle_summaries(
+        &self,
+        probe_id: Option<i32>,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<CycleSummary>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT cycle_id, probe_id, started_at, targets_up, targets_degraded, targets_down, avg_rtt_ms, loss_avg_percent, duration_ms
+                 FROM cycle_summaries
+                 WHERE ($1::INTEGER IS NULL OR probe_id = $1) AND started_at >= $2
+                 ORDER BY started_at DESC
+                 LIMIT $3",
+                &[&probe_id, &since, &limit],
             )
             .await?;
-        Ok(())
+        Ok(rows.into_iter().map(CycleSummary::from).collect())
     }
 
-    /// Lista métricas de conectividade de um ciclo específico.
+    /// Último resumo de ciclo de cada probe, entre os iniciados desde `since`.
     ///
     /// # Returns
-    /// * `Result<Vec<ConnectivityMetric>>` - Lista de métricas do ciclo
-    pub async fn list_connectivity_metrics_by_cycle(
+    /// * `Result<Vec<CycleSummary>>` - Um resumo por probe, ordenados por probe
+    pub async fn list_latest_cycle_summaries(
         &self,
-        cycle_id: i64,
-    ) -> Result<Vec<ConnectivityMetric>> {
+        since: DateTime<Utc>,
+    ) -> Result<Vec<CycleSummary>> {
         let rows = self
-            .client
+            .client()
             .query(
-                "SELECT id, cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_message
-                 FROM connectivity_metrics
-                 WHERE cycle_id = $1
-                 ORDER BY timestamp",
-                &[&cycle_id],
+                "SELECT DISTINCT ON (probe_id) cycle_id, probe_id, started_at, targets_up, targets_degraded, targets_down, avg_rtt_ms, loss_avg_percent, duration_ms
+                 FROM cycle_summaries
+                 WHERE started_at >= $1
+                 ORDER BY probe_id, started_at DESC",
+                &[&since],
             )
             .await?;
-        Ok(rows.into_iter().map(ConnectivityMetric::from).collect())
+        Ok(rows.into_iter().map(CycleSummary::from).collect())
     }
 
-    /// Lista status de todos os targets.
+    /// Latência das consultas de `checks.dns_queries` desde `since`, por
+    /// resolver, nome e tipo de registro.
     ///
     /// # Returns
-    /// * `Result<Vec<TargetStatus>>` - Lista de status dos targets
-    pub async fn list_all_target_status(&self) -> Result<Vec<TargetStatus>> {
+    /// * `Result<Vec<DnsQueryLatency>>` - Ordenadas por target e nome
+    pub async fn list_dns_query_latency(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<DnsQueryLatency>> {
         let rows = self
-            .client
+            .client()
             .query(
-                "SELECT target_id, last_status, last_change FROM target_status ORDER BY target_id",
-                &[],
+                "SELECT m.target_id, q->>'name' AS name, q->>'record' AS record,
+                        (q->>'cached')::BOOLEAN AS cached,
+                        COUNT(*) AS samples,
+                        COUNT(*) FILTER (WHERE q->>'rtt_ms' IS NULL) AS failures,
+                        percentile_cont(0.5) WITHIN GROUP (ORDER BY (q->>'rtt_ms')::FLOAT8) AS p50_ms,
+                        percentile_cont(0.95) WITHIN GROUP (ORDER BY (q->>'rtt_ms')::FLOAT8) AS p95_ms,
+                        MAX((q->>'rtt_ms')::FLOAT8) AS max_ms
+                 FROM connectivity_metrics m,
+                      jsonb_array_elements(m.details->'queries') q
+                 WHERE m.timestamp >= $1
+                   AND m.metric_type IN ('dns_ipv4', 'dns_ipv6')
+                   AND jsonb_typeof(m.details->'queries') = 'array'
+                 GROUP BY 1, 2, 3, 4
+                 ORDER BY 1, 2, 3, 4",
+                &[&since],
             )
             .await?;
-        Ok(rows.into_iter().map(TargetStatus::from).collect())
+        Ok(rows.into_iter().map(DnsQueryLatency::from).collect())
+    }
+
+    /// Insere uma métrica de conectividade (ping, tcp, http, dns). Uma
+    /// métrica já gravada (mesmo ciclo, probe, target, tipo e horário, como
+    /// num reenvio do spool) é ignorada.
+    ///
+    /// # Returns
+    /// * `Result<Option<i64>>` - O id da linha gravada (`None` se já existia)
+    pub async fn insert_connectivity_metric(
+        &self,
+        metric: &ConnectivityMetric,
+    ) -> Result<Option<i64>> {
+        let provenance = ProvenanceColumns::of(metric);
+        let error_kind = metric.error.map(|e| e.as_str());
+        let row = self
+            .client()
+            .query_opt(
+                "INSERT INTO connectivity_metrics
+                 (cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_kind, details, backend, source_address, interface, probe_version)
+                 VALUES (NULLIF($1::BIGINT, 0), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                 ON CONFLICT DO NOTHING
+                 RETURNING id",
+                &[
+                    &metric.cycle_id,
+                    &metric.probe_id,
+                    &metric.target_id,
+                    &metric.timestamp,
+                    &metric.metric_type,
+                    &metric.status,
+                    &metric.response_time_ms,
+                    &metric.packet_loss_percent,
+                    &error_kind,
+                    &metric.details,
+                    &provenance.backend,
+                    &provenance.source_address,
+                    &provenance.interface,
+                    &provenance.probe_version,
+                ],
+            )
+            .await?;
+        Ok(row.map(|r| r.get("id")))
+    }
+
+    /// Insere um lote de métricas num único `INSERT` multi-linha, ignorando
+    /// as já gravadas (ver [`Storage::insert_connectivity_metric`]).
+    ///
+    /// # Returns
+    /// * `Result<Vec<(MetricKey, i64)>>` - Chave e id das linhas inseridas
+    ///   (sem as repetidas) ou erro (o lote falha por inteiro)
+    pub async fn insert_connectivity_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+    ) -> Result<Vec<(MetricKey, i64)>> {
+        const COLUMNS: usize = 14;
+        if metrics.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut sql = String::from(
+            "INSERT INTO connectivity_metrics
+             (cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_kind, details, backend, source_address, interface, probe_version)
+             VALUES ",
+        );
+        let provenances: Vec<ProvenanceColumns> =
+            metrics.iter().map(ProvenanceColumns::of).collect();
+        let error_kinds: Vec<Option<&str>> = metrics
+            .iter()
+            .map(|m| m.error.map(|e| e.as_str()))
+            .collect();
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+            Vec::with_capacity(metrics.len() * COLUMNS);
+        for (i, ((metric, provenance), error_kind)) in metrics
+            .iter()
+            .zip(&provenances)
+            .zip(&error_kinds)
+            .enumerate()
+        {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            // cycle_id 0 = ciclo não registrado (banco indisponível) -> NULL
+            let placeholders: Vec<String> = (1..=COLUMNS)
+                .map(|c| match c {
+                    1 => format!("NULLIF(${}::BIGINT, 0)", i * COLUMNS + c),
+                    _ => format!("${}", i * COLUMNS + c),
+                })
+                .collect();
+            sql.push('(');
+            sql.push_str(&placeholders.join(", "));
+            sql.push(')');
+            params.extend_from_slice(&[
+                &metric.cycle_id,
+                &metric.probe_id,
+                &metric.target_id,
+                &metric.timestamp,
+                &metric.metric_type,
+                &metric.status,
+                &metric.response_time_ms,
+                &metric.packet_loss_percent,
+                error_kind,
+                &metric.details,
+                &provenance.backend,
+                &provenance.source_address,
+                &provenance.interface,
+                &provenance.probe_version,
+            ]);
+        }
+        sql.push_str(
+            " ON CONFLICT DO NOTHING RETURNING id, probe_id, target_id, metric_type, timestamp",
+        );
+        let rows = self.client().query(sql.as_str(), &params).await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                let key = MetricKey {
+                    probe_id: r.get("probe_id"),
+                    target_id: r.get("target_id"),
+                    metric_type: r.get("metric_type"),
+                    timestamp: r.get("timestamp"),
+                };
+                (key, r.get("id"))
+            })
+            .collect())
+    }
+
+    /// Insere as tentativas individuais dos checks num único `INSERT`.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de amostras gravadas
+    pub async fn insert_raw_samples(&self, samples: &[RawSample]) -> Result<u64> {
+        if samples.is_empty() {
+            return Ok(0);
+        }
+        let cycle_ids: Vec<i64> = samples.iter().map(|s| s.cycle_id).collect();
+        let probe_ids: Vec<i32> = samples.iter().map(|s| s.probe_id).collect();
+        let target_ids: Vec<i32> = samples.iter().map(|s| s.target_id).collect();
+        let metric_types: Vec<&str> = samples.iter().map(|s| s.metric_type.as_str()).collect();
+        let timestamps: Vec<DateTime<Utc>> = samples.iter().map(|s| s.timestamp).collect();
+        let attempt_nos: Vec<i16> = samples.iter().map(|s| s.attempt_no).collect();
+        let rtts: Vec<Option<f64>> = samples.iter().map(|s| s.rtt_ms).collect();
+        let outcomes: Vec<&str> = samples.iter().map(|s| s.outcome.as_str()).collect();
+        Ok(self
+            .client()
+            .execute(
+                "INSERT INTO raw_samples (cycle_id, probe_id, target_id, metric_type, timestamp, attempt_no, rtt_ms, outcome)
+                 SELECT NULLIF(c, 0), p, t, m::metric_type, ts, a, r, o
+                 FROM UNNEST($1::BIGINT[], $2::INTEGER[], $3::INTEGER[], $4::TEXT[], $5::TIMESTAMPTZ[], $6::SMALLINT[], $7::DOUBLE PRECISION[], $8::TEXT[])
+                      AS s(c, p, t, m, ts, a, r, o)",
+                &[
+                    &cycle_ids,
+                    &probe_ids,
+                    &target_ids,
+                    &metric_types,
+                    &timestamps,
+                    &attempt_nos,
+                    &rtts,
+                    &outcomes,
+                ],
+            )
+            .await?)
+    }
+
+    /// Insere uma amostra dos recursos do host da probe.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_host_sample(&self, sample: &HostSample) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO probe_host_samples
+                 (probe_id, timestamp, load1, cpu_count, mem_available_percent, mem_pressure_avg10, disk_free_percent, fd_open, fd_limit, starved)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+                &[
+                    &sample.probe_id,
+                    &sample.timestamp,
+                    &sample.load1,
+                    &(sample.cpu_count as i32),
+                    &sample.mem_available_percent,
+                    &sample.mem_pressure_avg10,
+                    &sample.disk_free_percent,
+                    &sample.fd_open.map(|v| v as i64),
+                    &sample.fd_limit.map(|v| v as i64),
+                    &sample.starved,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Insere as leituras SNMP das interfaces dos dispositivos num único
+    /// `INSERT`.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de linhas gravadas
+    pub async fn insert_device_metrics(&self, metrics: &[DeviceMetric]) -> Result<u64> {
+        if metrics.is_empty() {
+            return Ok(0);
+        }
+        let timestamps: Vec<DateTime<Utc>> = metrics.iter().map(|m| m.timestamp).collect();
+        let devices: Vec<&str> = metrics.iter().map(|m| m.device.as_str()).collect();
+        let addresses: Vec<IpAddr> = metrics.iter().map(|m| m.address).collect();
+        let if_indexes: Vec<i32> = metrics.iter().map(|m| m.if_index).collect();
+        let descrs: Vec<Option<&str>> = metrics.iter().map(|m| m.if_descr.as_deref()).collect();
+        let statuses: Vec<Option<&str>> =
+            metrics.iter().map(|m| m.oper_status.as_deref()).collect();
+        let in_errors: Vec<Option<i64>> = metrics.iter().map(|m| m.in_errors).collect();
+        let out_discards: Vec<Option<i64>> = metrics.iter().map(|m| m.out_discards).collect();
+        let in_deltas: Vec<Option<i64>> = metrics.iter().map(|m| m.in_errors_delta).collect();
+        let out_deltas: Vec<Option<i64>> = metrics.iter().map(|m| m.out_discards_delta).collect();
+        let errors: Vec<Option<&str>> = metrics.iter().map(|m| m.error.as_deref()).collect();
+        Ok(self
+            .client()
+            .execute(
+                "INSERT INTO device_metrics (timestamp, device, address, if_index, if_descr, oper_status, in_errors, out_discards, in_errors_delta, out_discards_delta, error)
+                 SELECT * FROM UNNEST($1::TIMESTAMPTZ[], $2::TEXT[], $3::INET[], $4::INTEGER[], $5::TEXT[], $6::TEXT[], $7::BIGINT[], $8::BIGINT[], $9::BIGINT[], $10::BIGINT[], $11::TEXT[])",
+                &[
+                    &timestamps,
+                    &devices,
+                    &addresses,
+                    &if_indexes,
+                    &descrs,
+                    &statuses,
+                    &in_errors,
+                    &out_discards,
+                    &in_deltas,
+                    &out_deltas,
+                    &errors,
+                ],
+            )
+            .await?)
+    }
+
+    /// Insere as consultas ARP/ND aos gateways num único `INSERT`.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de linhas gravadas
+    pub async fn insert_neighbor_samples(&self, samples: &[NeighborSample]) -> Result<u64> {
+        if samples.is_empty() {
+            return Ok(0);
+        }
+        let timestamps: Vec<DateTime<Utc>> = samples.iter().map(|s| s.timestamp).collect();
+        let interfaces: Vec<&str> = samples.iter().map(|s| s.interface.as_str()).collect();
+        let gateways: Vec<IpAddr> = samples.iter().map(|s| s.gateway).collect();
+        let reachable: Vec<bool> = samples.iter().map(|s| s.reachable).collect();
+        let macs: Vec<Option<&str>> = samples.iter().map(|s| s.mac.as_deref()).collect();
+        let rtts: Vec<Option<f64>> = samples.iter().map(|s| s.rtt_ms).collect();
+        let previous: Vec<Option<&str>> =
+            samples.iter().map(|s| s.previous_mac.as_deref()).collect();
+        let changed: Vec<bool> = samples.iter().map(|s| s.mac_changed).collect();
+        // Arrays por linha não passam pelo UNNEST: vão como texto separado por vírgula
+        let conflicting: Vec<String> = samples
+            .iter()
+            .map(|s| s.conflicting_macs.join(","))
+            .collect();
+        let errors: Vec<Option<&str>> = samples.iter().map(|s| s.error.as_deref()).collect();
+        Ok(self
+            .client()
+            .execute(
+                "INSERT INTO neighbor_samples (timestamp, interface, gateway, reachable, mac, rtt_ms, previous_mac, mac_changed, conflicting_macs, error)
+                 SELECT ts, i, g, r, m, rtt, p, c, string_to_array(NULLIF(cm, ''), ','), e
+                 FROM UNNEST($1::TIMESTAMPTZ[], $2::TEXT[], $3::INET[], $4::BOOLEAN[], $5::TEXT[], $6::DOUBLE PRECISION[], $7::TEXT[], $8::BOOLEAN[], $9::TEXT[], $10::TEXT[])
+                      AS s(ts, i, g, r, m, rtt, p, c, cm, e)",
+                &[
+                    &timestamps,
+                    &interfaces,
+                    &gateways,
+                    &reachable,
+                    &macs,
+                    &rtts,
+                    &previous,
+                    &changed,
+                    &conflicting,
+                    &errors,
+                ],
+            )
+            .await?)
+    }
+
+    /// Último MAC gravado de cada gateway (interface, endereço).
+    ///
+    /// # Returns
+    /// * `Result<Vec<(String, IpAddr, String)>>` - Interface, gateway e MAC
+    pub async fn list_latest_gateway_macs(&self) -> Result<Vec<(String, IpAddr, String)>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT DISTINCT ON (interface, gateway) interface, gateway, mac
+                 FROM neighbor_samples WHERE mac IS NOT NULL
+                 ORDER BY interface, gateway, timestamp DESC, id DESC",
+                &[],
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| (r.get("interface"), r.get("gateway"), r.get("mac")))
+            .collect())
+    }
+
+    /// Insere uma mudança de interface ou de rota padrão do host.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro
+    pub async fn insert_host_network_event(&self, event: &HostNetworkEvent) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO host_network_events
+                 (timestamp, event, interface, oper_state, family, gateway, metric)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                &[
+                    &event.timestamp,
+                    &event.event.as_str(),
+                    &event.interface,
+                    &event.oper_state,
+                    &event.family,
+                    &event.gateway,
+                    &event.metric,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Insere um evento de anomalia (abertura ou encerramento).
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_anomaly_event(&self, event: &AnomalyEvent) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO anomaly_events
+                 (probe_id, target_id, kind, start_time, end_time, baseline_mean, baseline_stddev, peak_value, peak_sigmas)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                &[
+                    &event.probe_id,
+                    &event.target_id,
+                    &event.kind.as_str(),
+                    &event.start_time,
+                    &event.end_time,
+                    &event.baseline_mean,
+                    &event.baseline_stddev,
+                    &event.peak_value,
+                    &event.peak_sigmas,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Insere um traceroute (os saltos vão como JSON).
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_traceroute(&self, traceroute: &Traceroute) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO traceroutes (probe_id, target_id, cycle_id, timestamp, destination, reached, hops)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                &[
+                    &traceroute.probe_id,
+                    &traceroute.target_id,
+                    &traceroute.cycle_id,
+                    &traceroute.timestamp,
+                    &traceroute.destination,
+                    &traceroute.reached,
+                    &serde_json::to_value(&traceroute.hops)?,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Lista os traceroutes desde `since`, em ordem cronológica.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Traceroute>>` - Traceroutes com seus saltos
+    pub async fn list_traceroutes(&self, since: DateTime<Utc>) -> Result<Vec<Traceroute>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id, cycle_id, timestamp, destination, reached, hops
+                 FROM traceroutes
+                 WHERE timestamp >= $1
+                 ORDER BY timestamp, id",
+                &[&since],
+            )
+            .await?;
+        rows.into_iter().map(traceroute_from_row).collect()
+    }
+
+    /// Lista os traceroutes dos pares probe/target informados entre `from` e
+    /// `to`, em ordem cronológica.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Traceroute>>` - Traceroutes com seus saltos
+    pub async fn list_traceroutes_between(
+        &self,
+        probe_ids: &[i32],
+        target_ids: &[i32],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Traceroute>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id, cycle_id, timestamp, destination, reached, hops
+                 FROM traceroutes
+                 WHERE probe_id = ANY($1) AND target_id = ANY($2)
+                   AND timestamp BETWEEN $3 AND $4
+                 ORDER BY timestamp, id",
+                &[&probe_ids, &target_ids, &from, &to],
+            )
+            .await?;
+        rows.into_iter().map(traceroute_from_row).collect()
+    }
+
+    /// Lista os reinícios de scheduler dos probes informados entre `from` e
+    /// `to`, em ordem cronológica.
+    ///
+    /// # Returns
+    /// * `Result<Vec<SchedulerIncident>>` - Reinícios registrados no período
+    pub async fn list_scheduler_incidents_between(
+        &self,
+        probe_ids: &[i32],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SchedulerIncident>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, timestamp, kind, detail, restarts
+                 FROM scheduler_incidents
+                 WHERE probe_id = ANY($1) AND timestamp BETWEEN $2 AND $3
+                 ORDER BY timestamp, id",
+                &[&probe_ids, &from, &to],
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(SchedulerIncident {
+                    probe_id: row.get("probe_id"),
+                    timestamp: row.get("timestamp"),
+                    kind: serde_json::from_value(serde_json::Value::String(row.get("kind")))?,
+                    detail: row.get("detail"),
+                    restarts: row.get("restarts"),
+                })
+            })
+            .collect()
+    }
+
+    /// Insere um reinício de scheduler feito pelo supervisor.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_scheduler_incident(&self, incident: &SchedulerIncident) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO scheduler_incidents (probe_id, timestamp, kind, detail, restarts)
+                 VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &incident.probe_id,
+                    &incident.timestamp,
+                    &incident.kind.as_str(),
+                    &incident.detail,
+                    &incident.restarts,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Insere a decisão do consenso de um ciclo.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_consensus_decision(&self, decision: &ConsensusDecision) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO consensus_decisions
+                 (probe_id, cycle_id, timestamp, outcome, reason, history_len, fail_threshold, consensus, failed_targets, counted_targets, details)
+                 VALUES ($1, NULLIF($2::BIGINT, 0), $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+                &[
+                    &decision.probe_id,
+                    &decision.cycle_id,
+                    &decision.timestamp,
+                    &decision.outcome.as_str(),
+                    &decision.reason,
+                    &decision.history_len,
+                    &decision.fail_threshold,
+                    &decision.consensus,
+                    &decision.failed_targets,
+                    &decision.counted_targets,
+                    &decision.details,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Lista as decisões do consenso dos probes informados entre `from` e
+    /// `to`, em ordem cronológica.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ConsensusDecision>>` - Decisões registradas no período
+    pub async fn list_consensus_decisions_between(
+        &self,
+        probe_ids: &[i32],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ConsensusDecision>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, cycle_id, timestamp, outcome, reason, history_len, fail_threshold, consensus, failed_targets, counted_targets, details
+                 FROM consensus_decisions
+                 WHERE probe_id = ANY($1) AND timestamp BETWEEN $2 AND $3
+                 ORDER BY timestamp, id",
+                &[&probe_ids, &from, &to],
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(ConsensusDecision {
+                    probe_id: row.get("probe_id"),
+                    cycle_id: row.get::<_, Option<i64>>("cycle_id").unwrap_or(0),
+                    timestamp: row.get("timestamp"),
+                    outcome: serde_json::from_value(serde_json::Value::String(row.get("outcome")))?,
+                    reason: row.get("reason"),
+                    history_len: row.get("history_len"),
+                    fail_threshold: row.get("fail_threshold"),
+                    consensus: row.get("consensus"),
+                    failed_targets: row.get("failed_targets"),
+                    counted_targets: row.get("counted_targets"),
+                    details: row
+                        .get::<_, Option<serde_json::Value>>("details")
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove as decisões do consenso anteriores a `cutoff`.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de registros removidos
+    pub async fn delete_consensus_decisions_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        Ok(self
+            .client()
+            .execute(
+                "DELETE FROM consensus_decisions WHERE timestamp < $1",
+                &[&cutoff],
+            )
+            .await?)
+    }
+
+    /// Descarta os chunks de `connectivity_metrics` inteiramente anteriores a
+    /// `cutoff` (modo TimescaleDB).
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Quantidade de chunks descartados
+    pub async fn drop_metric_chunks_before(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        Ok(self
+            .client()
+            .query(
+                "SELECT drop_chunks('connectivity_metrics', older_than => $1::timestamptz)",
+                &[&cutoff],
+            )
+            .await?
+            .len())
+    }
+
+    /// Grava um evento de outage e retorna o id da linha. A abertura é
+    /// idempotente (reenvios do spool não duplicam a linha: o id retornado é
+    /// o da linha aberta já existente). O encerramento fecha a linha de
+    /// `event.id`, ou, sem id, a linha ainda aberta do mesmo evento (mesmo
+    /// tipo, início, probes e targets), e só insere uma linha nova se nenhuma
+    /// existir.
+    ///
+    /// # Returns
+    /// * `Result<i64>` - Id da linha aberta, encerrada ou inserida
+    pub async fn insert_outage_event(&self, event: &OutageEvent) -> Result<i64> {
+        let client = self.client();
+        if let (Some(end_time), Some(duration)) = (event.end_time, event.duration_seconds) {
+            if event.id > 0
+                && self
+                    .update_outage_event_end(event.id, end_time, duration, event.details.as_ref())
+                    .await?
+            {
+                return Ok(event.id);
+            }
+            let closed = client
+                .query(
+                    "UPDATE outage_events
+                     SET end_time = $1, duration_seconds = $2, reason = $3, consensus_level = $4,
+                         details = CASE WHEN details ?| ARRAY['bgp', 'annotations']
+                                        THEN COALESCE($5, '{}'::jsonb) || (details - ARRAY(SELECT k FROM jsonb_object_keys(details) k WHERE k NOT IN ('bgp', 'annotations')))
+                                        ELSE $5 END
+                     WHERE end_time IS NULL AND event_type = $6 AND start_time = $7
+                       AND affected_targets = $8 AND affected_probes IS NOT DISTINCT FROM $9
+                     RETURNING id",
+                    &[
+                        &event.end_time,
+                        &event.duration_seconds,
+                        &event.reason,
+                        &event.consensus_level,
+                        &event.details,
+                        &event.event_type.as_str(),
+                        &event.start_time,
+                        &event.affected_targets,
+                        &event.affected_probes,
+                    ],
+                )
+                .await?;
+            if let Some(row) = closed.first() {
+                return Ok(row.get("id"));
+            }
+        }
+        let row = client
+            .query_one(
+                "WITH existing AS (
+                     SELECT id FROM outage_events
+                     WHERE $2::TIMESTAMPTZ IS NULL AND end_time IS NULL AND event_type = $9
+                       AND start_time = $1 AND affected_targets = $5
+                       AND affected_probes IS NOT DISTINCT FROM $6
+                     ORDER BY id LIMIT 1
+                 ), inserted AS (
+                     INSERT INTO outage_events
+                     (start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, event_type, affected_asns)
+                     SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9,
+                            (SELECT array_agg(DISTINCT asn ORDER BY asn) FROM monitoring_targets
+                             WHERE id = ANY($5) AND asn IS NOT NULL)
+                     WHERE NOT EXISTS (SELECT 1 FROM existing)
+                     RETURNING id
+                 )
+                 SELECT id FROM inserted UNION ALL SELECT id FROM existing",
+                &[
+                    &event.start_time,
+                    &event.end_time,
+                    &event.duration_seconds,
+                    &event.reason,
+                    &event.affected_targets,
+                    &event.affected_probes,
+                    &event.consensus_level,
+                    &event.details,
+                    &event.event_type.as_str(),
+                ],
+            )
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    /// Encerra o outage aberto de `id`; com `details`, os detalhes da linha
+    /// são substituídos (escalações e anotações acumuladas enquanto aberto),
+    /// exceto `bgp` e `annotations`, gravados direto no banco.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` se não há outage aberto com esse id
+    pub async fn update_outage_event_end(
+        &self,
+        id: i64,
+        end_time: DateTime<Utc>,
+        duration_seconds: i32,
+        details: Option<&serde_json::Value>,
+    ) -> Result<bool> {
+        let updated = self
+            .client()
+            .execute(
+                "UPDATE outage_events
+                 SET end_time = $2, duration_seconds = $3,
+                     details = CASE WHEN $4::jsonb IS NOT NULL AND details ?| ARRAY['bgp', 'annotations']
+                                    THEN $4 || (details - ARRAY(SELECT k FROM jsonb_object_keys(details) k WHERE k NOT IN ('bgp', 'annotations')))
+                                    ELSE COALESCE($4, details) END
+                 WHERE id = $1 AND end_time IS NULL",
+                &[&id, &end_time, &duration_seconds, &details],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// Lista os outages ainda abertos (`end_time IS NULL`) de um probe, do
+    /// mais antigo ao mais recente.
+    ///
+    /// # Returns
+    /// * `Result<Vec<OutageEvent>>` - Outages abertos do probe
+    pub async fn list_open_outage_events(&self, probe_id: i32) -> Result<Vec<OutageEvent>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns
+                 FROM outage_events
+                 WHERE end_time IS NULL AND $1 = ANY(affected_probes)
+                 ORDER BY start_time, id",
+                &[&probe_id],
+            )
+            .await?;
+        Ok(rows.into_iter().map(OutageEvent::from).collect())
+    }
+
+    /// Outages e outages parciais a verificar no BGP: os abertos e os
+    /// encerrados desde `since` que ainda não foram verificados depois do
+    /// encerramento.
+    ///
+    /// # Returns
+    /// * `Result<Vec<OutageEvent>>` - Eventos do mais antigo ao mais recente
+    pub async fn list_outage_events_pending_bgp(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<OutageEvent>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns
+                 FROM outage_events
+                 WHERE event_type IN ('outage', 'partial')
+                   AND (end_time IS NULL
+                        OR (end_time > $1
+                            AND COALESCE((details->'bgp'->>'checked_at')::timestamptz < end_time, TRUE)))
+                 ORDER BY start_time, id",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(OutageEvent::from).collect())
+    }
+
+    /// Grava a verificação BGP em `details.bgp` do outage `id`.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn set_outage_event_bgp(&self, id: i64, bgp: &serde_json::Value) -> Result<()> {
+        self.client()
+            .execute(
+                "UPDATE outage_events
+                 SET details = COALESCE(details, '{}'::jsonb) || jsonb_build_object('bgp', $2::jsonb)
+                 WHERE id = $1",
+                &[&id, &bgp],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Acrescenta uma anotação de operador a `details.annotations` do outage
+    /// `id`.
+    ///
+    /// # Returns
+    /// * `Result<Option<OutageEvent>>` - O evento atualizado; ausente se não existe
+    pub async fn add_outage_annotation(
+        &self,
+        id: i64,
+        annotation: &OutageAnnotation,
+    ) -> Result<Option<OutageEvent>> {
+        let annotation = serde_json::to_value(annotation)?;
+        let row = self
+            .client()
+            .query_opt(
+                "UPDATE outage_events
+                 SET details = COALESCE(details, '{}'::jsonb)
+                     || jsonb_build_object('annotations',
+                            COALESCE(details->'annotations', '[]'::jsonb) || jsonb_build_array($2::jsonb))
+                 WHERE id = $1
+                 RETURNING id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns",
+                &[&id, &annotation],
+            )
+            .await?;
+        Ok(row.map(OutageEvent::from))
+    }
+
+    /// Remove a anotação de posição `index` (a partir de 0) do outage `id`.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - `false` se o outage ou a anotação não existem
+    pub async fn delete_outage_annotation(&self, id: i64, index: i32) -> Result<bool> {
+        let deleted = self
+            .client()
+            .execute(
+                "UPDATE outage_events
+                 SET details = details #- ARRAY['annotations', $2::INT::TEXT]
+                 WHERE id = $1 AND $2 >= 0
+                   AND $2 < jsonb_array_length(COALESCE(details->'annotations', '[]'::jsonb))",
+                &[&id, &index],
+            )
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Atualiza o ASN e o provedor de um target.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn update_target_asn(
+        &self,
+        target_id: i32,
+        asn: Option<i32>,
+        provider: Option<&str>,
+    ) -> Result<()> {
+        self.client()
+            .execute(
+                "UPDATE monitoring_targets SET asn = $2, provider = $3 WHERE id = $1",
+                &[&target_id, &asn, &provider],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Grava a localização GeoIP de um target.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn update_target_geo(&self, target_id: i32, geo: &GeoLocation) -> Result<()> {
+        self.client()
+            .execute(
+                "UPDATE monitoring_targets
+                 SET country_code = $2, city = $3, latitude = $4, longitude = $5
+                 WHERE id = $1",
+                &[
+                    &target_id,
+                    &geo.country_code,
+                    &geo.city,
+                    &geo.latitude,
+                    &geo.longitude,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Grava a localização GeoIP de um probe.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn update_probe_geo(&self, probe_id: i32, geo: &GeoLocation) -> Result<()> {
+        self.client()
+            .execute(
+                "UPDATE monitoring_probes
+                 SET country_code = $2, city = $3, latitude = $4, longitude = $5
+                 WHERE id = $1",
+                &[
+                    &probe_id,
+                    &geo.country_code,
+                    &geo.city,
+                    &geo.latitude,
+                    &geo.longitude,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Preenche `affected_asns` dos eventos gravados antes de os targets
+    /// afetados terem ASN.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Quantidade de eventos marcados
+    pub async fn tag_outage_event_asns(&self) -> Result<u64> {
+        Ok(self
+            .client()
+            .execute(
+                "UPDATE outage_events e
+                 SET affected_asns = asns.asns
+                 FROM (
+                     SELECT e2.id, array_agg(DISTINCT t.asn ORDER BY t.asn) AS asns
+                     FROM outage_events e2
+                     JOIN monitoring_targets t ON t.id = ANY(e2.affected_targets)
+                     WHERE e2.affected_asns IS NULL AND t.asn IS NOT NULL
+                     GROUP BY e2.id
+                 ) asns
+                 WHERE e.id = asns.id",
+                &[],
+            )
+            .await?)
+    }
+
+    /// Incorpora outages abertos sobrepostos em `event`: a linha de
+    /// `event.id` recebe os targets e os detalhes de `event` (mais as
+    /// anotações das linhas incorporadas) e as linhas de `merged_ids` são
+    /// removidas.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn merge_outage_events(&self, event: &OutageEvent, merged_ids: &[i64]) -> Result<()> {
+        self.client()
+            .execute(
+                "WITH merged AS (
+                     DELETE FROM outage_events WHERE id = ANY($6) AND end_time IS NULL
+                     RETURNING details
+                 ), moved AS (
+                     SELECT jsonb_agg(a) AS annotations
+                     FROM merged, jsonb_array_elements(merged.details->'annotations') a
+                 )
+                 UPDATE outage_events SET affected_targets = $2, consensus_level = $3,
+                     details = CASE WHEN details ?| ARRAY['bgp', 'annotations']
+                                         OR (SELECT annotations FROM moved) IS NOT NULL
+                                    THEN COALESCE($4, '{}'::jsonb)
+                                         || COALESCE((details - ARRAY(SELECT k FROM jsonb_object_keys(details) k WHERE k NOT IN ('bgp', 'annotations'))), '{}'::jsonb)
+                                         || COALESCE((SELECT jsonb_build_object('annotations',
+                                                                COALESCE(details->'annotations', '[]'::jsonb) || annotations)
+                                                      FROM moved WHERE annotations IS NOT NULL), '{}'::jsonb)
+                                    ELSE $4 END,
+                     affected_asns = (SELECT array_agg(DISTINCT asn ORDER BY asn) FROM monitoring_targets
+                                      WHERE id = ANY($2) AND asn IS NOT NULL)
+                 WHERE id = $1 AND start_time = $5",
+                &[
+                    &event.id,
+                    &event.affected_targets,
+                    &event.consensus_level,
+                    &event.details,
+                    &event.start_time,
+                    &merged_ids,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Timestamp da métrica mais recente de um probe.
+    ///
+    /// # Returns
+    /// * `Result<Option<DateTime<Utc>>>` - Ausente se o probe não tem métricas
+    pub async fn last_metric_time(&self, probe_id: i32) -> Result<Option<DateTime<Utc>>> {
+        let row = self
+            .client()
+            .query_one(
+                "SELECT MAX(timestamp) AS last FROM connectivity_metrics WHERE probe_id = $1",
+                &[&probe_id],
+            )
+            .await?;
+        Ok(row.get("last"))
+    }
+
+    /// Timestamp da métrica mais recente do probe por target e tipo de métrica.
+    ///
+    /// # Returns
+    /// * `Result<HashMap<(i32, String), DateTime<Utc>>>` - Por `(target_id, metric_type)`
+    pub async fn last_metric_times(
+        &self,
+        probe_id: i32,
+    ) -> Result<HashMap<(i32, String), DateTime<Utc>>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT target_id, metric_type::TEXT AS metric_type, MAX(timestamp) AS last
+                 FROM connectivity_metrics WHERE probe_id = $1
+                 GROUP BY target_id, metric_type",
+                &[&probe_id],
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    (row.get("target_id"), row.get("metric_type")),
+                    row.get("last"),
+                )
+            })
+            .collect())
+    }
+
+    /// Insere um outage simulado por uma instância de consenso sombra.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de inserção
+    pub async fn insert_shadow_outage_event(
+        &self,
+        shadow_name: &str,
+        event: &OutageEvent,
+    ) -> Result<()> {
+        self.client()
+            .execute(
+                "INSERT INTO shadow_outage_events
+                 (shadow_name, start_time, end_time, duration_seconds, reason, affected_targets, consensus_level, details)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &shadow_name,
+                    &event.start_time,
+                    &event.end_time,
+                    &event.duration_seconds,
+                    &event.reason,
+                    &event.affected_targets,
+                    &event.consensus_level,
+                    &event.details,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Atualiza o status persistido do target no probe e registra a
+    /// transição em `transitions`, com o status anterior visto pelo probe e o
+    /// id da métrica que a causou (`None` se ela não foi gravada). Chamado só
+    /// quando o status do target no probe muda.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn set_target_status(
+        &self,
+        target_id: i32,
+        status: &MetricStatus,
+        old_status: Option<&MetricStatus>,
+        probe_id: Option<i32>,
+        at: Option<DateTime<Utc>>,
+        metric_id: Option<i64>,
+    ) -> Result<()> {
+        self.client()
+            .execute(
+                "WITH upsert AS (
+                     INSERT INTO target_status (target_id, probe_id, last_status, last_change)
+                     VALUES ($1, COALESCE($3, 0), $2, COALESCE($4, NOW()))
+                     ON CONFLICT (target_id, probe_id) DO UPDATE
+                     SET last_status = $2, last_change = COALESCE($4, NOW())
+                 )
+                 INSERT INTO transitions
+                     (target_id, probe_id, old_status, new_status, metric_id, changed_at)
+                 VALUES ($1, $3, $5, $2, $6, COALESCE($4, NOW()))",
+                &[&target_id, status, &probe_id, &at, &old_status, &metric_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Status mais recente de cada (target, probe) em `transitions`.
+    ///
+    /// # Returns
+    /// * `Result<HashMap<(i32, Option<i32>), MetricStatus>>` - Status por target e probe
+    pub async fn list_latest_transitions(
+        &self,
+    ) -> Result<HashMap<(i32, Option<i32>), MetricStatus>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT DISTINCT ON (target_id, probe_id) target_id, probe_id, new_status
+                 FROM transitions ORDER BY target_id, probe_id, changed_at DESC, id DESC",
+                &[],
+            )
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| ((r.get("target_id"), r.get("probe_id")), r.get("new_status")))
+            .collect())
+    }
+
+    /// Lista as métricas mais recentes de um probe, limitadas a `per_target`
+    /// por target e a partir de `since`, da mais recente para a mais antiga.
+    /// Medições de diagnóstico (`details.diagnostic`) são ignoradas.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ConnectivityMetric>>` - Métricas ordenadas por target e timestamp decrescente
+    pub async fn list_recent_metrics_by_probe(
+        &self,
+        probe_id: i32,
+        since: DateTime<Utc>,
+        per_target: i64,
+    ) -> Result<Vec<ConnectivityMetric>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_kind, error_message, details, backend, source_address, interface, probe_version
+                 FROM (
+                     SELECT *, ROW_NUMBER() OVER (PARTITION BY target_id ORDER BY timestamp DESC) AS rn
+                     FROM connectivity_metrics
+                     WHERE probe_id = $1 AND timestamp >= $2
+                       AND (details IS NULL OR NOT details ? 'diagnostic')
+                 ) recent
+                 WHERE rn <= $3
+                 ORDER BY target_id, timestamp DESC",
+                &[&probe_id, &since, &per_target],
+            )
+            .await?;
+        Ok(rows.into_iter().map(ConnectivityMetric::from).collect())
+    }
+
+    /// Lista o status de todos os targets, uma linha por (target, probe).
+    ///
+    /// # Returns
+    /// * `Result<Vec<TargetStatus>>` - Lista de status dos targets
+    pub async fn list_all_target_status(&self) -> Result<Vec<TargetStatus>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT target_id, NULLIF(probe_id, 0) AS probe_id, last_status, last_change::timestamptz AS last_change
+                 FROM target_status ORDER BY target_id, probe_id",
+                &[],
+            )
+            .await?;
+        Ok(rows.into_iter().map(TargetStatus::from).collect())
+    }
+
+    /// Busca um evento de outage pelo id.
+    ///
+    /// # Returns
+    /// * `Result<Option<OutageEvent>>` - O evento, se existir
+    pub async fn get_outage_event(&self, id: i64) -> Result<Option<OutageEvent>> {
+        let row = self
+            .client()
+            .query_opt(
+                "SELECT id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns
+                 FROM outage_events
+                 WHERE id = $1",
+                &[&id],
+            )
+            .await?;
+        Ok(row.map(OutageEvent::from))
+    }
+
+    /// Lista as métricas dos probes e targets informados entre `from` e `to`,
+    /// em ordem cronológica.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ConnectivityMetric>>` - Métricas do período
+    pub async fn list_metrics_between(
+        &self,
+        probe_ids: &[i32],
+        target_ids: &[i32],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ConnectivityMetric>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_kind, error_message, details, backend, source_address, interface, probe_version
+                 FROM connectivity_metrics
+                 WHERE probe_id = ANY($1) AND target_id = ANY($2)
+                   AND timestamp BETWEEN $3 AND $4
+                 ORDER BY timestamp, id",
+                &[&probe_ids, &target_ids, &from, &to],
+            )
+            .await?;
+        Ok(rows.into_iter().map(ConnectivityMetric::from).collect())
+    }
+
+    /// Página de métricas entre `from` e `to`, em ordem de `id`, a partir do
+    /// `id` seguinte a `after_id`. Filtros ausentes não restringem.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ConnectivityMetric>>` - Até `limit` métricas
+    pub async fn list_metrics_page(
+        &self,
+        probe_ids: Option<&[i32]>,
+        target_ids: Option<&[i32]>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<ConnectivityMetric>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, cycle_id, probe_id, target_id, timestamp, metric_type, status, response_time_ms, packet_loss_percent, error_kind, details, backend, source_address, interface, probe_version
+                 FROM connectivity_metrics
+                 WHERE ($1::INT[] IS NULL OR probe_id = ANY($1))
+                   AND ($2::INT[] IS NULL OR target_id = ANY($2))
+                   AND timestamp BETWEEN $3 AND $4
+                   AND id > $5
+                 ORDER BY id
+                 LIMIT $6",
+                &[&probe_ids, &target_ids, &from, &to, &after_id, &limit],
+            )
+            .await?;
+        Ok(rows.into_iter().map(ConnectivityMetric::from).collect())
+    }
+
+    /// Lista os outages que se sobrepõem à janela, em ordem de início. Com
+    /// filtros, só os que afetam algum dos probes (ou todos, se o evento não
+    /// registra probes) e algum dos targets.
+    ///
+    /// # Returns
+    /// * `Result<Vec<OutageEvent>>` - Outages da janela
+    pub async fn list_outage_events_between(
+        &self,
+        probe_ids: Option<&[i32]>,
+        target_ids: Option<&[i32]>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<OutageEvent>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns
+                 FROM outage_events
+                 WHERE start_time <= $4 AND (end_time IS NULL OR end_time >= $3)
+                   AND ($1::INT[] IS NULL OR affected_probes IS NULL OR affected_probes && $1)
+                   AND ($2::INT[] IS NULL OR affected_targets && $2)
+                 ORDER BY start_time, id",
+                &[&probe_ids, &target_ids, &from, &to],
+            )
+            .await?;
+        Ok(rows.into_iter().map(OutageEvent::from).collect())
+    }
+
+    /// Disponibilidade, perda e RTT médios de cada target na janela `[from,
+    /// to)`, sem as medições de diagnóstico. Com filtro, só os targets
+    /// informados.
+    ///
+    /// # Returns
+    /// * `Result<Vec<TargetPeriodStats>>` - Uma linha por target medido
+    pub async fn list_target_period_stats(
+        &self,
+        target_ids: Option<&[i32]>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<TargetPeriodStats>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT target_id,
+                        COUNT(*) AS samples,
+                        COUNT(*) FILTER (WHERE status = 'up') AS up_samples,
+                        AVG(packet_loss_percent)::DOUBLE PRECISION AS loss_percent,
+                        COUNT(response_time_ms) AS rtt_samples,
+                        AVG(response_time_ms) AS avg_rtt_ms
+                 FROM connectivity_metrics
+                 WHERE timestamp >= $2 AND timestamp < $3
+                   AND ($1::INT[] IS NULL OR target_id = ANY($1))
+                   AND (details IS NULL OR NOT details ? 'diagnostic')
+                 GROUP BY target_id
+                 ORDER BY target_id",
+                &[&target_ids, &from, &to],
+            )
+            .await?;
+        Ok(rows.into_iter().map(TargetPeriodStats::from).collect())
+    }
+
+    /// Como [`Storage::list_target_period_stats`], por probe e target, com
+    /// as interfaces de saída das medições. Com filtros, só os probes e
+    /// targets informados.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ProbeTargetStats>>` - Uma linha por par probe/target medido
+    pub async fn list_probe_target_period_stats(
+        &self,
+        probe_ids: Option<&[i32]>,
+        target_ids: Option<&[i32]>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ProbeTargetStats>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id,
+                        COUNT(*) AS samples,
+                        COUNT(*) FILTER (WHERE status = 'up') AS up_samples,
+                        AVG(packet_loss_percent)::DOUBLE PRECISION AS loss_percent,
+                        COUNT(response_time_ms) AS rtt_samples,
+                        AVG(response_time_ms) AS avg_rtt_ms,
+                        COALESCE(ARRAY_AGG(DISTINCT interface) FILTER (WHERE interface IS NOT NULL), '{}') AS interfaces
+                 FROM connectivity_metrics
+                 WHERE timestamp >= $3 AND timestamp < $4
+                   AND ($1::INT[] IS NULL OR probe_id = ANY($1))
+                   AND ($2::INT[] IS NULL OR target_id = ANY($2))
+                   AND (details IS NULL OR NOT details ? 'diagnostic')
+                 GROUP BY probe_id, target_id
+                 ORDER BY probe_id, target_id",
+                &[&probe_ids, &target_ids, &from, &to],
+            )
+            .await?;
+        Ok(rows.into_iter().map(ProbeTargetStats::from).collect())
+    }
+
+    /// Lista os outages mais recentes (abertos ou encerrados).
+    ///
+    /// # Returns
+    /// * `Result<Vec<OutageEvent>>` - Até `limit` outages, do mais recente ao mais antigo
+    pub async fn list_recent_outage_events(&self, limit: i64) -> Result<Vec<OutageEvent>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT id, event_type, start_time, end_time, duration_seconds, reason, affected_targets, affected_probes, consensus_level, details, affected_asns
+                 FROM outage_events
+                 ORDER BY start_time DESC
+                 LIMIT $1",
+                &[&limit],
+            )
+            .await?;
+        Ok(rows.into_iter().map(OutageEvent::from).collect())
+    }
+
+    /// Atualiza as views materializadas usadas por dashboards (Grafana).
+    ///
+    /// # Returns
+    /// * `Result<()>` - Sucesso ou erro de atualização
+    pub async fn refresh_aggregated_views(&self) -> Result<()> {
+        self.client()
+            .batch_execute(
+                "REFRESH MATERIALIZED VIEW CONCURRENTLY mv_hourly_availability;
+                 REFRESH MATERIALIZED VIEW CONCURRENTLY mv_daily_outage_minutes;
+                 REFRESH MATERIALIZED VIEW CONCURRENTLY mv_hourly_latency;
+                 REFRESH MATERIALIZED VIEW CONCURRENTLY mv_hourly_probe_availability;
+                 REFRESH MATERIALIZED VIEW CONCURRENTLY mv_hourly_probe_latency;",
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Recalcula os histogramas de RTT das horas entre `from` e `to` (horas
+    /// cheias) a partir de `connectivity_metrics`. `bounds_ms` são os limites
+    /// superiores das faixas, em ordem crescente; o que passa do último cai
+    /// em `Infinity`. Faixas fora de `bounds_ms` (de uma configuração
+    /// anterior) são removidas da janela.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Linhas (faixas não vazias) gravadas
+    pub async fn aggregate_latency_histograms(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bounds_ms: &[f64],
+    ) -> Result<u64> {
+        let mut bounds = bounds_ms.to_vec();
+        bounds.push(f64::INFINITY);
+        let client = self.client();
+        let written = client
+            .execute(
+                "INSERT INTO latency_histograms (probe_id, target_id, metric_type, hour, le_ms, count)
+                 SELECT probe_id, target_id, metric_type, hour, le_ms, COUNT(*)
+                 FROM (
+                     SELECT probe_id, target_id, metric_type,
+                            date_trunc('hour', timestamp) AS hour,
+                            (SELECT MIN(b) FROM unnest($3::DOUBLE PRECISION[]) AS b
+                             WHERE b >= response_time_ms) AS le_ms
+                     FROM connectivity_metrics
+                     WHERE timestamp >= $1 AND timestamp < $2
+                       AND response_time_ms IS NOT NULL
+                       AND (details IS NULL OR NOT details ? 'diagnostic')
+                 ) AS samples
+                 GROUP BY probe_id, target_id, metric_type, hour, le_ms
+                 ON CONFLICT (target_id, probe_id, metric_type, hour, le_ms)
+                 DO UPDATE SET count = EXCLUDED.count",
+                &[&from, &to, &bounds],
+            )
+            .await?;
+        client
+            .execute(
+                "DELETE FROM latency_histograms
+                 WHERE hour >= $1 AND hour < $2 AND NOT (le_ms = ANY($3))",
+                &[&from, &to, &bounds],
+            )
+            .await?;
+        Ok(written)
+    }
+
+    /// Remove os histogramas de RTT de horas anteriores a `cutoff`.
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Linhas removidas
+    pub async fn delete_latency_histograms_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        Ok(self
+            .client()
+            .execute("DELETE FROM latency_histograms WHERE hour < $1", &[&cutoff])
+            .await?)
+    }
+
+    /// Lista a disponibilidade horária de todos os targets desde `since`.
+    ///
+    /// # Returns
+    /// * `Result<Vec<HourlyAvailability>>` - Linhas ordenadas por target e hora
+    pub async fn list_hourly_availability(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<HourlyAvailability>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT target_id, hour, samples, up_samples, availability_percent
+                 FROM mv_hourly_availability
+                 WHERE hour >= $1
+                 ORDER BY target_id, hour",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(HourlyAvailability::from).collect())
+    }
+
+    /// Lista os minutos de outage por dia desde `since`.
+    ///
+    /// # Returns
+    /// * `Result<Vec<DailyOutageMinutes>>` - Linhas ordenadas por dia
+    pub async fn list_daily_outage_minutes(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<DailyOutageMinutes>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT day, outage_count, outage_minutes
+                 FROM mv_daily_outage_minutes
+                 WHERE day >= $1
+                 ORDER BY day",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(DailyOutageMinutes::from).collect())
+    }
+
+    /// Lista os percentis de latência horários de todos os targets desde `since`.
+    ///
+    /// # Returns
+    /// * `Result<Vec<LatencyPercentiles>>` - Linhas ordenadas por target e hora
+    pub async fn list_hourly_latency(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<LatencyPercentiles>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT target_id, hour, avg_ms, p50_ms, p95_ms, p99_ms
+                 FROM mv_hourly_latency
+                 WHERE hour >= $1
+                 ORDER BY target_id, hour",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(LatencyPercentiles::from).collect())
+    }
+
+    /// Lista a disponibilidade horária por probe e target desde `since`.
+    ///
+    /// # Returns
+    /// * `Result<Vec<HourlyAvailability>>` - Linhas com `probe_id`, ordenadas por target e hora
+    pub async fn list_hourly_probe_availability(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<HourlyAvailability>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id, hour, samples, up_samples, availability_percent
+                 FROM mv_hourly_probe_availability
+                 WHERE hour >= $1
+                 ORDER BY target_id, hour, probe_id",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(HourlyAvailability::from).collect())
+    }
+
+    /// Lista os percentis de latência horários por probe e target desde `since`.
+    ///
+    /// # Returns
+    /// * `Result<Vec<LatencyPercentiles>>` - Linhas com `probe_id`, ordenadas por target e hora
+    pub async fn list_hourly_probe_latency(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<LatencyPercentiles>> {
+        let rows = self
+            .client()
+            .query(
+                "SELECT probe_id, target_id, hour, avg_ms, p50_ms, p95_ms, p99_ms
+                 FROM mv_hourly_probe_latency
+                 WHERE hour >= $1
+                 ORDER BY target_id, hour, probe_id",
+                &[&since],
+            )
+            .await?;
+        Ok(rows.into_iter().map(LatencyPercentiles::from).collect())
+    }
+}
+
+/// Colunas de proveniência de uma métrica (todas nulas sem proveniência).
+struct ProvenanceColumns {
+    backend: Option<&'static str>,
+    source_address: Option<IpAddr>,
+    interface: Option<String>,
+    probe_version: Option<String>,
+}
+
+impl ProvenanceColumns {
+    fn of(metric: &ConnectivityMetric) -> Self {
+        let provenance = metric.provenance.as_ref();
+        Self {
+            backend: provenance.map(|p| p.backend.as_str()),
+            source_address: provenance.and_then(|p| p.source_address),
+            interface: provenance.and_then(|p| p.interface.clone()),
+            probe_version: provenance.map(|p| p.probe_version.clone()),
+        }
+    }
+}
+
+fn traceroute_from_row(row: tokio_postgres::Row) -> Result<Traceroute> {
+    Ok(Traceroute {
+        probe_id: row.get("probe_id"),
+        target_id: row.get("target_id"),
+        cycle_id: row.get::<_, Option<i64>>("cycle_id").unwrap_or(0),
+        timestamp: row.get("timestamp"),
+        destination: row.get("destination"),
+        reached: row.get("reached"),
+        hops: serde_json::from_value(row.get("hops"))?,
+    })
+}
+
+/// Alguma das tabelas tem linhas com `column = id`?
+async fn has_history(client: &Client, tables: &[&str], column: &str, id: i32) -> Result<bool> {
+    for table in tables {
+        let row = client
+            .query_one(
+                &format!(
+                    "SELECT EXISTS (SELECT 1 FROM {} WHERE {} = $1) AS found",
+                    table, column
+                ),
+                &[&id],
+            )
+            .await?;
+        if row.get::<_, bool>("found") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Remove a linha `id` da tabela; uma chave estrangeira que ainda a
+/// referencia vira `HasHistory`.
+async fn delete_row(client: &Client, table: &str, id: i32) -> Result<DeleteOutcome> {
+    match client
+        .execute(&format!("DELETE FROM {} WHERE id = $1", table), &[&id])
+        .await
+    {
+        Ok(0) => Ok(DeleteOutcome::NotFound),
+        Ok(_) => Ok(DeleteOutcome::Deleted),
+        Err(e) if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) => {
+            Ok(DeleteOutcome::HasHistory)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Operações do banco usadas pelo scheduler e pelo pipeline de persistência
+/// (ciclo → métricas → consenso → outages). O PostgreSQL (`Storage`) é a
+/// implementação de produção; `MemoryStorage` permite rodar o loop de ponta
+/// a ponta sem banco. Consultas de relatórios, API e CLI continuam só em
+/// `Storage`.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// A conexão atual ainda está aberta?
+    fn is_connected(&self) -> bool;
+
+    /// Reabre a conexão se ela caiu.
+    async fn reconnect(&self) -> Result<()>;
+
+    async fn insert_cycle(&self, cycle: &Cycle) -> Result<i64>;
+
+    async fn insert_cycle_summary(&self, summary: &CycleSummary) -> Result<()>;
+
+    async fn insert_connectivity_metric(&self, metric: &ConnectivityMetric) -> Result<Option<i64>>;
+
+    async fn insert_connectivity_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+    ) -> Result<Vec<(MetricKey, i64)>>;
+
+    async fn insert_raw_samples(&self, samples: &[RawSample]) -> Result<u64>;
+
+    async fn insert_host_sample(&self, sample: &HostSample) -> Result<()>;
+
+    async fn insert_anomaly_event(&self, event: &AnomalyEvent) -> Result<()>;
+
+    async fn insert_traceroute(&self, traceroute: &Traceroute) -> Result<()>;
+
+    async fn insert_scheduler_incident(&self, incident: &SchedulerIncident) -> Result<()>;
+
+    async fn insert_consensus_decision(&self, decision: &ConsensusDecision) -> Result<()>;
+
+    /// Abre ou encerra um outage (ver [`Storage::insert_outage_event`]).
+    async fn insert_outage_event(&self, event: &OutageEvent) -> Result<i64>;
+
+    async fn insert_shadow_outage_event(
+        &self,
+        shadow_name: &str,
+        event: &OutageEvent,
+    ) -> Result<()>;
+
+    async fn set_target_status(
+        &self,
+        target_id: i32,
+        status: &MetricStatus,
+        old_status: Option<&MetricStatus>,
+        probe_id: Option<i32>,
+        at: Option<DateTime<Utc>>,
+        metric_id: Option<i64>,
+    ) -> Result<()>;
+
+    async fn list_latest_transitions(&self) -> Result<HashMap<(i32, Option<i32>), MetricStatus>>;
+
+    async fn list_all_target_status(&self) -> Result<Vec<TargetStatus>>;
+
+    async fn list_recent_metrics_by_probe(
+        &self,
+        probe_id: i32,
+        since: DateTime<Utc>,
+        per_target: i64,
+    ) -> Result<Vec<ConnectivityMetric>>;
+
+    async fn list_cycle_durations_ms(&self, probe_id: i32) -> Result<Vec<f64>>;
+
+    /// Targets pausados, relidos a cada ciclo.
+    async fn list_disabled_target_ids(&self) -> Result<HashSet<i32>>;
+}
+
+#[async_trait]
+impl StorageBackend for Storage {
+    fn is_connected(&self) -> bool {
+        Storage::is_connected(self)
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        Storage::reconnect(self).await
+    }
+
+    async fn insert_cycle(&self, cycle: &Cycle) -> Result<i64> {
+        Storage::insert_cycle(self, cycle).await
+    }
+
+    async fn insert_cycle_summary(&self, summary: &CycleSummary) -> Result<()> {
+        Storage::insert_cycle_summary(self, summary).await
+    }
+
+    async fn insert_connectivity_metric(&self, metric: &ConnectivityMetric) -> Result<Option<i64>> {
+        Storage::insert_connectivity_metric(self, metric).await
+    }
+
+    async fn insert_connectivity_metrics(
+        &self,
+        metrics: &[ConnectivityMetric],
+    ) -> Result<Vec<(MetricKey, i64)>> {
+        Storage::insert_connectivity_metrics(self, metrics).await
+    }
+
+    async fn insert_raw_samples(&self, samples: &[RawSample]) -> Result<u64> {
+        Storage::insert_raw_samples(self, samples).await
+    }
+
+    async fn insert_host_sample(&self, sample: &HostSample) -> Result<()> {
+        Storage::insert_host_sample(self, sample).await
+    }
+
+    async fn insert_anomaly_event(&self, event: &AnomalyEvent) -> Result<()> {
+        Storage::insert_anomaly_event(self, event).await
+    }
+
+    async fn insert_traceroute(&self, traceroute: &Traceroute) -> Result<()> {
+        Storage::insert_traceroute(self, traceroute).await
+    }
+
+    async fn insert_scheduler_incident(&self, incident: &SchedulerIncident) -> Result<()> {
+        Storage::insert_scheduler_incident(self, incident).await
+    }
+
+    async fn insert_consensus_decision(&self, decision: &ConsensusDecision) -> Result<()> {
+        Storage::insert_consensus_decision(self, decision).await
+    }
+
+    async fn insert_outage_event(&self, event: &OutageEvent) -> Result<i64> {
+        Storage::insert_outage_event(self, event).await
+    }
+
+    async fn insert_shadow_outage_event(
+        &self,
+        shadow_name: &str,
+        event: &OutageEvent,
+    ) -> Result<()> {
+        Storage::insert_shadow_outage_event(self, shadow_name, event).await
+    }
+
+    async fn set_target_status(
+        &self,
+        target_id: i32,
+        status: &MetricStatus,
+        old_status: Option<&MetricStatus>,
+        probe_id: Option<i32>,
+        at: Option<DateTime<Utc>>,
+        metric_id: Option<i64>,
+    ) -> Result<()> {
+        Storage::set_target_status(self, target_id, status, old_status, probe_id, at, metric_id)
+            .await
+    }
+
+    async fn list_latest_transitions(&self) -> Result<HashMap<(i32, Option<i32>), MetricStatus>> {
+        Storage::list_latest_transitions(self).await
+    }
+
+    async fn list_all_target_status(&self) -> Result<Vec<TargetStatus>> {
+        Storage::list_all_target_status(self).await
+    }
+
+    async fn list_recent_metrics_by_probe(
+        &self,
+        probe_id: i32,
+        since: DateTime<Utc>,
+        per_target: i64,
+    ) -> Result<Vec<ConnectivityMetric>> {
+        Storage::list_recent_metrics_by_probe(self, probe_id, since, per_target).await
+    }
+
+    async fn list_cycle_durations_ms(&self, probe_id: i32) -> Result<Vec<f64>> {
+        Storage::list_cycle_durations_ms(self, probe_id).await
+    }
+
+    async fn list_disabled_target_ids(&self) -> Result<HashSet<i32>> {
+        Storage::list_disabled_target_ids(self).await
     }
 }
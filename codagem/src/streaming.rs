@@ -0,0 +1,270 @@
+//! streaming.rs — Publicação em tempo real de métricas e outages
+//!
+//! Cada `ConnectivityMetric` e `OutageEvent` é emitido como JSON para
+//! `<prefixo>.metrics` e `<prefixo>.outages`, permitindo pipelines downstream
+//! (detecção de anomalias, billing) sem polling do banco.
+//!
+//! Backends suportados:
+//! - **NATS**: protocolo core sobre TCP (`PUB`), com reconexão automática.
+//! - **Kafka**: via Confluent REST Proxy (API v2), sem dependência de librdkafka.
+
+use crate::config::{StreamingBackend, StreamingConfig};
+use crate::types::{ConnectivityMetric, OutageEvent};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Metade de escrita de uma conexão NATS, marcada com a geração que a abriu.
+#[derive(Debug)]
+struct NatsConnection {
+    generation: u64,
+    writer: OwnedWriteHalf,
+}
+
+type SharedWriter = Arc<Mutex<Option<NatsConnection>>>;
+
+/// Publicador NATS minimalista (apenas `PUB`, responde `PING` do servidor).
+#[derive(Debug)]
+pub struct NatsPublisher {
+    addr: String,
+    writer: SharedWriter,
+    /// Geração da última conexão aberta: o leitor de uma conexão antiga não
+    /// pode responder nem invalidar a que a substituiu.
+    generation: AtomicU64,
+}
+
+impl NatsPublisher {
+    pub fn new(url: &str) -> Self {
+        let addr = url.trim_start_matches("nats://").trim_end_matches('/');
+        Self {
+            addr: addr.to_string(),
+            writer: Arc::new(Mutex::new(None)),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Abre a conexão, consome o `INFO` inicial e envia o `CONNECT`.
+    async fn connect(&self) -> Result<NatsConnection> {
+        let stream = tokio::time::timeout(Duration::from_secs(5), TcpStream::connect(&self.addr))
+            .await
+            .context("Timeout ao conectar ao NATS")?
+            .with_context(|| format!("Falha ao conectar ao NATS em {}", self.addr))?;
+        let (read_half, mut write_half) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+
+        let mut info = String::new();
+        reader.read_line(&mut info).await?;
+        if !info.starts_with("INFO") {
+            anyhow::bail!("Resposta inesperada do NATS: {}", info.trim());
+        }
+        let connect = json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "monitoramento_rede",
+        });
+        write_half
+            .write_all(format!("CONNECT {}\r\n", connect).as_bytes())
+            .await?;
+
+        // Leitor em background: responde PING e invalida a conexão ao cair
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let writer = Arc::clone(&self.writer);
+        tokio::spawn(async move {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match reader.read_line(&mut line).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) if line.starts_with("PING") => {
+                        if let Some(conn) = writer
+                            .lock()
+                            .await
+                            .as_mut()
+                            .filter(|conn| conn.generation == generation)
+                        {
+                            let _ = conn.writer.write_all(b"PONG\r\n").await;
+                        }
+                    }
+                    Ok(_) if line.starts_with("-ERR") => {
+                        warn!("[STREAMING] NATS retornou erro: {}", line.trim());
+                    }
+                    Ok(_) => {}
+                }
+            }
+            debug!("[STREAMING] Conexão NATS encerrada");
+            let mut guard = writer.lock().await;
+            if guard
+                .as_ref()
+                .is_some_and(|conn| conn.generation == generation)
+            {
+                *guard = None;
+            }
+        });
+
+        Ok(NatsConnection {
+            generation,
+            writer: write_half,
+        })
+    }
+
+    /// Publica um lote de mensagens no mesmo subject.
+    pub async fn publish(&self, subject: &str, payloads: &[String]) -> Result<()> {
+        let mut buffer = Vec::new();
+        for payload in payloads {
+            buffer.extend_from_slice(format!("PUB {} {}\r\n", subject, payload.len()).as_bytes());
+            buffer.extend_from_slice(payload.as_bytes());
+            buffer.extend_from_slice(b"\r\n");
+        }
+
+        let mut guard = self.writer.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.connect().await?);
+        }
+        let conn = guard.as_mut().expect("conexão NATS recém-aberta");
+        if let Err(e) = conn.writer.write_all(&buffer).await {
+            *guard = None;
+            return Err(e).context("Falha ao publicar no NATS");
+        }
+        Ok(())
+    }
+}
+
+/// Publicador Kafka via Confluent REST Proxy.
+#[derive(Debug)]
+pub struct KafkaRestPublisher {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl KafkaRestPublisher {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()?,
+            base_url: url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Publica um lote de registros JSON no tópico.
+    pub async fn publish(&self, topic: &str, records: &[serde_json::Value]) -> Result<()> {
+        let body = json!({
+            "records": records.iter().map(|value| json!({ "value": value })).collect::<Vec<_>>(),
+        });
+        let response = self
+            .client
+            .post(format!("{}/topics/{}", self.base_url, topic))
+            .header("Content-Type", "application/vnd.kafka.json.v2+json")
+            .body(body.to_string())
+            .send()
+            .await
+            .context("Falha ao publicar no Kafka REST Proxy")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Kafka REST Proxy respondeu {}: {}", status, body);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Publisher {
+    Nats(NatsPublisher),
+    KafkaRest(KafkaRestPublisher),
+}
+
+/// Stream de eventos do monitor.
+#[derive(Debug)]
+pub struct EventStream {
+    publisher: Publisher,
+    prefix: String,
+}
+
+impl EventStream {
+    pub fn new(config: &StreamingConfig) -> Result<Self> {
+        let publisher = match config.backend {
+            StreamingBackend::Nats => Publisher::Nats(NatsPublisher::new(&config.url)),
+            StreamingBackend::KafkaRest => {
+                Publisher::KafkaRest(KafkaRestPublisher::new(&config.url)?)
+            }
+        };
+        Ok(Self {
+            publisher,
+            prefix: config.subject_prefix.clone(),
+        })
+    }
+
+    async fn publish<T: Serialize>(&self, kind: &str, items: &[T]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let subject = format!("{}.{}", self.prefix, kind);
+        match &self.publisher {
+            Publisher::Nats(nats) => {
+                let payloads = items
+                    .iter()
+                    .map(serde_json::to_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                nats.publish(&subject, &payloads).await
+            }
+            Publisher::KafkaRest(kafka) => {
+                let records = items
+                    .iter()
+                    .map(serde_json::to_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+                kafka.publish(&subject, &records).await
+            }
+        }
+    }
+
+    /// Emite as métricas de um ciclo em `<prefixo>.metrics`.
+    pub async fn publish_metrics(&self, metrics: &[ConnectivityMetric]) -> Result<()> {
+        self.publish("metrics", metrics).await
+    }
+
+    /// Emite um outage (abertura ou encerramento) em `<prefixo>.outages`.
+    pub async fn publish_outage(&self, event: &OutageEvent) -> Result<()> {
+        self.publish("outages", std::slice::from_ref(event)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Aceita uma conexão NATS falsa: envia o `INFO` e devolve o socket.
+    async fn accept(listener: &TcpListener) -> TcpStream {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        socket.write_all(b"INFO {}\r\n").await.unwrap();
+        socket
+    }
+
+    #[tokio::test]
+    async fn leitor_antigo_nao_derruba_conexao_nova() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let publisher = NatsPublisher::new(&listener.local_addr().unwrap().to_string());
+        let payload = ["{}".to_string()];
+
+        let (result, first) = tokio::join!(publisher.publish("m", &payload), accept(&listener));
+        result.unwrap();
+        // Substitui a conexão antes de o leitor da primeira perceber a queda
+        *publisher.writer.lock().await = None;
+        let (result, _second) = tokio::join!(publisher.publish("m", &payload), accept(&listener));
+        result.unwrap();
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let guard = publisher.writer.lock().await;
+        assert_eq!(guard.as_ref().map(|conn| conn.generation), Some(2));
+    }
+}
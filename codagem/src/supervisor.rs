@@ -0,0 +1,146 @@
+//! supervisor.rs — Watchdog dos schedulers de probe
+//!
+//! Cada probe roda sob um supervisor que acompanha a task do scheduler: se
+//! ela termina (panic ou retorno inesperado) ou para de registrar ciclos
+//! (nenhum heartbeat em 3× `cycle_interval_secs`), a task é abortada e
+//! recriada com estado novo. Cada reinício é logado, contado no snapshot e
+//! gravado em `scheduler_incidents`, para que um probe nunca fique sem
+//! monitoramento silenciosamente.
+
+use crate::scheduler::{self, SchedulerContext};
+use crate::types::{Probe, Target};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// Ciclos sem heartbeat até o scheduler ser considerado travado.
+const STALL_CYCLES: u64 = 3;
+
+/// Último ciclo iniciado pelo scheduler (timestamp Unix em segundos).
+#[derive(Debug, Clone)]
+pub struct Heartbeat(Arc<AtomicI64>);
+
+impl Heartbeat {
+    fn new() -> Self {
+        Self(Arc::new(AtomicI64::new(Utc::now().timestamp())))
+    }
+
+    /// Registra que um ciclo começou.
+    pub fn beat(&self) {
+        self.0.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    fn age_secs(&self) -> i64 {
+        Utc::now().timestamp() - self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Motivo do reinício de um scheduler.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentKind {
+    /// A task entrou em panic.
+    Panicked,
+    /// A task terminou sem panic (o loop não deveria terminar).
+    Exited,
+    /// Sem heartbeat dentro do limite; a task foi abortada.
+    Stalled,
+}
+
+impl IncidentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IncidentKind::Panicked => "panicked",
+            IncidentKind::Exited => "exited",
+            IncidentKind::Stalled => "stalled",
+        }
+    }
+}
+
+/// Reinício de um scheduler pelo supervisor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerIncident {
+    pub probe_id: i32,
+    pub timestamp: DateTime<Utc>,
+    pub kind: IncidentKind,
+    pub detail: Option<String>,
+    /// Reinícios deste probe desde o início do processo (incluindo este).
+    pub restarts: i32,
+}
+
+/// Task do scheduler, abortada junto com o supervisor.
+struct SchedulerTask(JoinHandle<()>);
+
+impl Drop for SchedulerTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn spawn(probe: &Probe, targets: &[Target], ctx: &SchedulerContext) -> (SchedulerTask, Heartbeat) {
+    let heartbeat = Heartbeat::new();
+    let handle = tokio::spawn(scheduler::run_scheduler(
+        probe.clone(),
+        targets.to_vec(),
+        ctx.clone(),
+        heartbeat.clone(),
+    ));
+    (SchedulerTask(handle), heartbeat)
+}
+
+/// Roda o scheduler do probe e o recria sempre que ele morre ou trava.
+/// Nunca retorna; abortar o supervisor aborta também o scheduler.
+pub async fn supervise(probe: Probe, targets: Vec<Target>, ctx: SchedulerContext) {
+    let interval = Duration::from_secs(ctx.config.cycle_interval_secs.max(1));
+    let stall_secs = (STALL_CYCLES * interval.as_secs()) as i64;
+    let mut restarts = 0;
+    let (mut handle, mut heartbeat) = spawn(&probe, &targets, &ctx);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let (kind, detail) = if handle.0.is_finished() {
+            match (&mut handle.0).await {
+                Err(e) if e.is_panic() => (IncidentKind::Panicked, Some(format!("{:?}", e))),
+                Err(e) => (IncidentKind::Exited, Some(format!("{:?}", e))),
+                Ok(()) => (IncidentKind::Exited, None),
+            }
+        } else if heartbeat.age_secs() > stall_secs {
+            handle.0.abort();
+            (
+                IncidentKind::Stalled,
+                Some(format!(
+                    "sem heartbeat há {}s (limite: {}s)",
+                    heartbeat.age_secs(),
+                    stall_secs
+                )),
+            )
+        } else {
+            continue;
+        };
+
+        restarts += 1;
+        error!(
+            "[SUPERVISOR {}] Scheduler {} ({}); reiniciando com estado novo (reinício #{})",
+            probe.location,
+            kind.as_str(),
+            detail.as_deref().unwrap_or("-"),
+            restarts
+        );
+        let incident = SchedulerIncident {
+            probe_id: probe.id,
+            timestamp: Utc::now(),
+            kind,
+            detail,
+            restarts,
+        };
+        ctx.snapshot.record_scheduler_restart(&incident);
+        ctx.persistence.scheduler_incident(&incident).await;
+
+        (handle, heartbeat) = spawn(&probe, &targets, &ctx);
+        info!("[SUPERVISOR {}] Scheduler recriado", probe.location);
+    }
+}
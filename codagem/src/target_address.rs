@@ -0,0 +1,689 @@
+//! target_address.rs — Parsing de entradas de cadastro de targets
+//!
+//! Normaliza o que o usuário digita (CLI, API, importação) em valores
+//! tipados de [`TargetAddress`]:
+//!
+//! | Entrada                         | Resultado                         |
+//! |---------------------------------|-----------------------------------|
+//! | `8.8.8.8`, `2001:db8::1`        | `Ip`                              |
+//! | `[2001:db8::1]`                 | `Ip`                              |
+//! | `8.8.8.8:53`, `[::1]:443`       | `Socket`                          |
+//! | `dns.google`, `dns.google:853`  | `Host`                            |
+//! | `https://dns.google/dns-query`  | `Url` (porta padrão do esquema)   |
+//! | `192.0.2.0/30`, `2001:db8::/126`| vários `Ip` (expansão limitada)   |
+//!
+//! Cada forma tem seu próprio erro em [`AddressParseError`], para que a
+//! mensagem diga exatamente o que está errado na entrada.
+
+use serde::Serialize;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use thiserror::Error;
+
+/// Limite padrão de endereços gerados por um único bloco CIDR.
+pub const DEFAULT_MAX_EXPANSION: usize = 256;
+
+/// Host de uma entrada: IP literal ou nome DNS validado.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Host {
+    Ip(IpAddr),
+    Name(String),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ip(IpAddr::V6(v6)) => write!(f, "[{}]", v6),
+            Host::Ip(ip) => write!(f, "{}", ip),
+            Host::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Endereço de target normalizado.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TargetAddress {
+    /// IP literal, sem porta.
+    Ip { ip: IpAddr },
+    /// IP literal com porta.
+    Socket { addr: SocketAddr },
+    /// Nome DNS, com porta opcional.
+    Host { name: String, port: Option<u16> },
+    /// URL `http`/`https`; a porta é sempre preenchida (explícita ou padrão).
+    Url {
+        scheme: String,
+        host: Host,
+        port: u16,
+        path: String,
+    },
+}
+
+impl TargetAddress {
+    /// IP literal da entrada, se houver (nomes DNS precisam ser resolvidos).
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            TargetAddress::Ip { ip } => Some(*ip),
+            TargetAddress::Socket { addr } => Some(addr.ip()),
+            TargetAddress::Url {
+                host: Host::Ip(ip), ..
+            } => Some(*ip),
+            _ => None,
+        }
+    }
+
+    /// Host para resolução DNS, se a entrada for um nome.
+    pub fn hostname(&self) -> Option<&str> {
+        match self {
+            TargetAddress::Host { name, .. } => Some(name),
+            TargetAddress::Url {
+                host: Host::Name(name),
+                ..
+            } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Porta da entrada, se houver.
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            TargetAddress::Ip { .. } => None,
+            TargetAddress::Socket { addr } => Some(addr.port()),
+            TargetAddress::Host { port, .. } => *port,
+            TargetAddress::Url { port, .. } => Some(*port),
+        }
+    }
+
+    /// Tipo de verificação sugerido (`type` em `monitoring_targets`) para um
+    /// endereço já resolvido em `ip`.
+    pub fn check_type(&self, ip: IpAddr) -> String {
+        let base = match self {
+            TargetAddress::Ip { .. } => "ping",
+            TargetAddress::Socket { .. } | TargetAddress::Host { port: Some(_), .. } => "tcp",
+            TargetAddress::Host { port: None, .. } => "ping",
+            TargetAddress::Url { .. } => "http",
+        };
+        match ip {
+            IpAddr::V4(_) => format!("{}_ipv4", base),
+            IpAddr::V6(_) => format!("{}_ipv6", base),
+        }
+    }
+}
+
+impl fmt::Display for TargetAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetAddress::Ip { ip } => write!(f, "{}", ip),
+            TargetAddress::Socket { addr } => write!(f, "{}", addr),
+            TargetAddress::Host { name, port: None } => write!(f, "{}", name),
+            TargetAddress::Host {
+                name,
+                port: Some(port),
+            } => write!(f, "{}:{}", name, port),
+            TargetAddress::Url {
+                scheme,
+                host,
+                port,
+                path,
+            } => write!(f, "{}://{}:{}{}", scheme, host, port, path),
+        }
+    }
+}
+
+/// Erros de parsing, um por forma de entrada.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AddressParseError {
+    #[error("entrada vazia")]
+    Empty,
+    #[error("IP inválido: `{0}`")]
+    InvalidIp(String),
+    #[error("porta inválida em `{input}`: `{port}` (esperado 1-65535)")]
+    InvalidPort { input: String, port: String },
+    #[error("nome de host inválido: `{0}`")]
+    InvalidHostname(String),
+    #[error("URL inválida `{input}`: {reason}")]
+    InvalidUrl { input: String, reason: String },
+    #[error("esquema de URL não suportado: `{0}` (use http ou https)")]
+    UnsupportedScheme(String),
+    #[error("CIDR inválido `{input}`: {reason}")]
+    InvalidCidr { input: String, reason: String },
+    #[error("CIDR `{input}` gera {count} endereços (limite: {max})")]
+    CidrTooLarge {
+        input: String,
+        count: u128,
+        max: usize,
+    },
+}
+
+/// Faz o parsing de uma entrada que representa um único endereço.
+/// Blocos CIDR são rejeitados; use [`parse_many`] para expandi-los.
+pub fn parse(input: &str) -> Result<TargetAddress, AddressParseError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(AddressParseError::Empty);
+    }
+    if let Some((scheme, rest)) = input.split_once("://") {
+        return parse_url(input, scheme, rest);
+    }
+    if input.contains('/') {
+        return Err(AddressParseError::InvalidCidr {
+            input: input.to_string(),
+            reason: "bloco CIDR não permitido aqui".to_string(),
+        });
+    }
+    parse_authority(input)
+}
+
+/// Faz o parsing de uma entrada que pode ser um bloco CIDR, expandindo-o em
+/// no máximo `max_expansion` endereços. Em IPv4 com prefixo até /30, os
+/// endereços de rede e broadcast são omitidos.
+pub fn parse_many(
+    input: &str,
+    max_expansion: usize,
+) -> Result<Vec<TargetAddress>, AddressParseError> {
+    let trimmed = input.trim();
+    if !trimmed.contains("://")
+        && let Some((network, prefix)) = trimmed.split_once('/')
+    {
+        return expand_cidr(trimmed, network, prefix, max_expansion);
+    }
+    parse(trimmed).map(|address| vec![address])
+}
+
+/// `host`, `host:port`, `[v6]`, `[v6]:port` ou IP literal.
+fn parse_authority(input: &str) -> Result<TargetAddress, AddressParseError> {
+    // IPv6 entre colchetes, com ou sem porta
+    if let Some(rest) = input.strip_prefix('[') {
+        let (inside, after) = rest
+            .split_once(']')
+            .ok_or_else(|| AddressParseError::InvalidIp(input.to_string()))?;
+        let ip: Ipv6Addr = inside
+            .parse()
+            .map_err(|_| AddressParseError::InvalidIp(inside.to_string()))?;
+        return match after {
+            "" => Ok(TargetAddress::Ip { ip: IpAddr::V6(ip) }),
+            _ => {
+                let port = after
+                    .strip_prefix(':')
+                    .ok_or_else(|| AddressParseError::InvalidIp(input.to_string()))?;
+                Ok(TargetAddress::Socket {
+                    addr: SocketAddr::new(IpAddr::V6(ip), parse_port(input, port)?),
+                })
+            }
+        };
+    }
+
+    // IP literal (IPv6 sem colchetes contém vários `:` e nunca tem porta)
+    if let Ok(ip) = input.parse::<IpAddr>() {
+        return Ok(TargetAddress::Ip { ip });
+    }
+    if input.matches(':').count() > 1 {
+        return Err(AddressParseError::InvalidIp(input.to_string()));
+    }
+
+    let (host, port) = match input.rsplit_once(':') {
+        Some((host, port)) => (host, Some(parse_port(input, port)?)),
+        None => (input, None),
+    };
+    if let Ok(ip) = host.parse::<Ipv4Addr>() {
+        return Ok(match port {
+            Some(port) => TargetAddress::Socket {
+                addr: SocketAddr::new(IpAddr::V4(ip), port),
+            },
+            None => TargetAddress::Ip { ip: IpAddr::V4(ip) },
+        });
+    }
+    if looks_like_ipv4(host) {
+        return Err(AddressParseError::InvalidIp(host.to_string()));
+    }
+    Ok(TargetAddress::Host {
+        name: validate_hostname(host)?,
+        port,
+    })
+}
+
+fn parse_url(input: &str, scheme: &str, rest: &str) -> Result<TargetAddress, AddressParseError> {
+    let scheme = scheme.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => 80,
+        "https" => 443,
+        _ => return Err(AddressParseError::UnsupportedScheme(scheme)),
+    };
+    let invalid = |reason: &str| AddressParseError::InvalidUrl {
+        input: input.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(invalid("host ausente"));
+    }
+    if authority.contains('@') {
+        return Err(invalid("credenciais na URL não são aceitas"));
+    }
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+
+    let (host, port) = match parse_authority(authority)? {
+        TargetAddress::Ip { ip } => (Host::Ip(ip), default_port),
+        TargetAddress::Socket { addr } => (Host::Ip(addr.ip()), addr.port()),
+        TargetAddress::Host { name, port } => (Host::Name(name), port.unwrap_or(default_port)),
+        TargetAddress::Url { .. } => return Err(invalid("URL aninhada")),
+    };
+    Ok(TargetAddress::Url {
+        scheme,
+        host,
+        port,
+        path,
+    })
+}
+
+fn parse_port(input: &str, port: &str) -> Result<u16, AddressParseError> {
+    match port.parse::<u16>() {
+        Ok(p) if p > 0 => Ok(p),
+        _ => Err(AddressParseError::InvalidPort {
+            input: input.to_string(),
+            port: port.to_string(),
+        }),
+    }
+}
+
+/// Só dígitos e pontos: é uma tentativa de IPv4, não um nome DNS.
+fn looks_like_ipv4(host: &str) -> bool {
+    !host.is_empty() && host.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Valida um nome DNS (RFC 1123): rótulos de 1 a 63 caracteres
+/// alfanuméricos ou `-` (sem `-` nas pontas), até 253 caracteres no total.
+/// Um ponto final é aceito e removido; o nome é normalizado para minúsculas.
+fn validate_hostname(host: &str) -> Result<String, AddressParseError> {
+    let name = host.strip_suffix('.').unwrap_or(host);
+    let valid = !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(name.to_ascii_lowercase())
+    } else {
+        Err(AddressParseError::InvalidHostname(host.to_string()))
+    }
+}
+
+fn expand_cidr(
+    input: &str,
+    network: &str,
+    prefix: &str,
+    max_expansion: usize,
+) -> Result<Vec<TargetAddress>, AddressParseError> {
+    let invalid = |reason: String| AddressParseError::InvalidCidr {
+        input: input.to_string(),
+        reason,
+    };
+    let network: IpAddr = network
+        .parse()
+        .map_err(|_| invalid(format!("rede `{}` não é um IP", network)))?;
+    let bits: u32 = match network {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    let prefix: u32 = prefix
+        .parse()
+        .ok()
+        .filter(|p| *p <= bits)
+        .ok_or_else(|| invalid(format!("prefixo `{}` fora de 0-{}", prefix, bits)))?;
+
+    let host_bits = bits - prefix;
+    let base: u128 = match network {
+        IpAddr::V4(v4) => u32::from(v4) as u128,
+        IpAddr::V6(v6) => u128::from(v6),
+    };
+    let mask: u128 = if host_bits >= 128 {
+        0
+    } else {
+        !((1u128 << host_bits) - 1)
+    };
+    if base & !mask != 0 {
+        return Err(invalid(format!(
+            "bits de host ligados no endereço de rede (use {}/{})",
+            to_ip(base & mask, &network),
+            prefix
+        )));
+    }
+
+    // Em IPv4 até /30, rede e broadcast não são hosts utilizáveis
+    let skip_edges = matches!(network, IpAddr::V4(_)) && host_bits >= 2;
+    let total: u128 = if host_bits >= 128 {
+        u128::MAX
+    } else {
+        1u128 << host_bits
+    };
+    let usable = if skip_edges { total - 2 } else { total };
+    if usable > max_expansion as u128 {
+        return Err(AddressParseError::CidrTooLarge {
+            input: input.to_string(),
+            count: usable,
+            max: max_expansion,
+        });
+    }
+
+    let first = base + u128::from(skip_edges);
+    Ok((0..usable)
+        .map(|offset| TargetAddress::Ip {
+            ip: to_ip(first + offset, &network),
+        })
+        .collect())
+}
+
+fn to_ip(value: u128, family: &IpAddr) -> IpAddr {
+    match family {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(value as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ip_literal_sem_porta() {
+        assert_eq!(
+            parse("8.8.8.8"),
+            Ok(TargetAddress::Ip { ip: ip("8.8.8.8") })
+        );
+        assert_eq!(
+            parse("  2001:db8::1 "),
+            Ok(TargetAddress::Ip {
+                ip: ip("2001:db8::1")
+            })
+        );
+        assert_eq!(
+            parse("[2001:db8::1]"),
+            Ok(TargetAddress::Ip {
+                ip: ip("2001:db8::1")
+            })
+        );
+        let address = parse("8.8.8.8").unwrap();
+        assert_eq!(address.port(), None);
+        assert_eq!(address.check_type(ip("8.8.8.8")), "ping_ipv4");
+    }
+
+    #[test]
+    fn ip_com_porta() {
+        assert_eq!(
+            parse("8.8.8.8:53"),
+            Ok(TargetAddress::Socket {
+                addr: "8.8.8.8:53".parse().unwrap()
+            })
+        );
+        let address = parse("[::1]:443").unwrap();
+        assert_eq!(
+            address,
+            TargetAddress::Socket {
+                addr: "[::1]:443".parse().unwrap()
+            }
+        );
+        assert_eq!(address.ip(), Some(ip("::1")));
+        assert_eq!(address.port(), Some(443));
+        assert_eq!(address.check_type(ip("::1")), "tcp_ipv6");
+        assert_eq!(address.to_string(), "[::1]:443");
+    }
+
+    #[test]
+    fn host_com_e_sem_porta() {
+        assert_eq!(
+            parse("DNS.Google."),
+            Ok(TargetAddress::Host {
+                name: "dns.google".to_string(),
+                port: None
+            })
+        );
+        let address = parse("dns.google:853").unwrap();
+        assert_eq!(
+            address,
+            TargetAddress::Host {
+                name: "dns.google".to_string(),
+                port: Some(853)
+            }
+        );
+        assert_eq!(address.ip(), None);
+        assert_eq!(address.hostname(), Some("dns.google"));
+        assert_eq!(address.check_type(ip("8.8.8.8")), "tcp_ipv4");
+        assert_eq!(address.to_string(), "dns.google:853");
+    }
+
+    #[test]
+    fn url_com_porta_padrao_e_explicita() {
+        assert_eq!(
+            parse("https://dns.google/dns-query"),
+            Ok(TargetAddress::Url {
+                scheme: "https".to_string(),
+                host: Host::Name("dns.google".to_string()),
+                port: 443,
+                path: "/dns-query".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("HTTP://example.com"),
+            Ok(TargetAddress::Url {
+                scheme: "http".to_string(),
+                host: Host::Name("example.com".to_string()),
+                port: 80,
+                path: "/".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("http://example.com?q=1"),
+            Ok(TargetAddress::Url {
+                scheme: "http".to_string(),
+                host: Host::Name("example.com".to_string()),
+                port: 80,
+                path: "/?q=1".to_string(),
+            })
+        );
+        let address = parse("http://[2001:db8::1]:8080/health").unwrap();
+        assert_eq!(
+            address,
+            TargetAddress::Url {
+                scheme: "http".to_string(),
+                host: Host::Ip(ip("2001:db8::1")),
+                port: 8080,
+                path: "/health".to_string(),
+            }
+        );
+        assert_eq!(address.ip(), Some(ip("2001:db8::1")));
+        assert_eq!(address.check_type(ip("2001:db8::1")), "http_ipv6");
+        assert_eq!(address.to_string(), "http://[2001:db8::1]:8080/health");
+        assert_eq!(
+            parse("https://1.1.1.1").unwrap().port(),
+            Some(443),
+            "IP sem porta usa a do esquema"
+        );
+    }
+
+    #[test]
+    fn parse_many_sem_cidr_devolve_um_endereco() {
+        assert_eq!(
+            parse_many("https://dns.google/a/b", DEFAULT_MAX_EXPANSION),
+            Ok(vec![parse("https://dns.google/a/b").unwrap()])
+        );
+        assert_eq!(
+            parse_many("1.1.1.1", DEFAULT_MAX_EXPANSION),
+            Ok(vec![TargetAddress::Ip { ip: ip("1.1.1.1") }])
+        );
+    }
+
+    #[test]
+    fn cidr_ipv4_omite_rede_e_broadcast() {
+        let hosts: Vec<IpAddr> = parse_many("192.0.2.0/30", DEFAULT_MAX_EXPANSION)
+            .unwrap()
+            .iter()
+            .filter_map(TargetAddress::ip)
+            .collect();
+        assert_eq!(hosts, vec![ip("192.0.2.1"), ip("192.0.2.2")]);
+        // /31 e /32 não têm rede nem broadcast
+        assert_eq!(
+            parse_many("192.0.2.0/31", DEFAULT_MAX_EXPANSION)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            parse_many("192.0.2.7/32", DEFAULT_MAX_EXPANSION),
+            Ok(vec![TargetAddress::Ip {
+                ip: ip("192.0.2.7")
+            }])
+        );
+        assert_eq!(parse_many("10.0.0.0/24", 254).unwrap().len(), 254);
+    }
+
+    #[test]
+    fn cidr_ipv6_inclui_todos_os_enderecos() {
+        let hosts: Vec<IpAddr> = parse_many("2001:db8::/126", DEFAULT_MAX_EXPANSION)
+            .unwrap()
+            .iter()
+            .filter_map(TargetAddress::ip)
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![
+                ip("2001:db8::"),
+                ip("2001:db8::1"),
+                ip("2001:db8::2"),
+                ip("2001:db8::3")
+            ]
+        );
+    }
+
+    #[test]
+    fn cidr_acima_do_limite() {
+        assert_eq!(
+            parse_many("10.0.0.0/24", 253),
+            Err(AddressParseError::CidrTooLarge {
+                input: "10.0.0.0/24".to_string(),
+                count: 254,
+                max: 253,
+            })
+        );
+        assert_eq!(
+            parse_many("::/0", DEFAULT_MAX_EXPANSION),
+            Err(AddressParseError::CidrTooLarge {
+                input: "::/0".to_string(),
+                count: u128::MAX,
+                max: DEFAULT_MAX_EXPANSION,
+            })
+        );
+        assert!(matches!(
+            parse_many("0.0.0.0/0", DEFAULT_MAX_EXPANSION),
+            Err(AddressParseError::CidrTooLarge { count, .. }) if count == (1u128 << 32) - 2
+        ));
+    }
+
+    #[test]
+    fn cidr_invalido() {
+        let reason = |input: &str| match parse_many(input, DEFAULT_MAX_EXPANSION) {
+            Err(AddressParseError::InvalidCidr { reason, .. }) => reason,
+            other => panic!("{}: {:?}", input, other),
+        };
+        assert_eq!(reason("192.0.2.0/33"), "prefixo `33` fora de 0-32");
+        assert_eq!(reason("2001:db8::/129"), "prefixo `129` fora de 0-128");
+        assert_eq!(reason("192.0.2.0/x"), "prefixo `x` fora de 0-32");
+        assert_eq!(reason("host/24"), "rede `host` não é um IP");
+        assert_eq!(
+            reason("192.0.2.1/30"),
+            "bits de host ligados no endereço de rede (use 192.0.2.0/30)"
+        );
+        // Endereço único não aceita CIDR
+        assert_eq!(
+            parse("192.0.2.0/30"),
+            Err(AddressParseError::InvalidCidr {
+                input: "192.0.2.0/30".to_string(),
+                reason: "bloco CIDR não permitido aqui".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn erros_de_cada_forma() {
+        assert_eq!(parse("   "), Err(AddressParseError::Empty));
+        assert_eq!(
+            parse("256.1.1.1"),
+            Err(AddressParseError::InvalidIp("256.1.1.1".to_string()))
+        );
+        assert_eq!(
+            parse("2001:db8::zz"),
+            Err(AddressParseError::InvalidIp("2001:db8::zz".to_string()))
+        );
+        assert_eq!(
+            parse("[2001:db8::1"),
+            Err(AddressParseError::InvalidIp("[2001:db8::1".to_string()))
+        );
+        assert_eq!(
+            parse("[1.2.3.4]"),
+            Err(AddressParseError::InvalidIp("1.2.3.4".to_string()))
+        );
+        assert_eq!(
+            parse("[::1]443"),
+            Err(AddressParseError::InvalidIp("[::1]443".to_string()))
+        );
+        for (input, port) in [
+            ("8.8.8.8:0", "0"),
+            ("8.8.8.8:65536", "65536"),
+            ("dns.google:abc", "abc"),
+            ("[::1]:", ""),
+        ] {
+            assert_eq!(
+                parse(input),
+                Err(AddressParseError::InvalidPort {
+                    input: input.to_string(),
+                    port: port.to_string(),
+                })
+            );
+        }
+        for input in ["-bad.example", "bad_host", "a..b", &"a".repeat(64)] {
+            assert_eq!(
+                parse(input),
+                Err(AddressParseError::InvalidHostname(input.to_string()))
+            );
+        }
+        let url = |input: &str, reason: &str| AddressParseError::InvalidUrl {
+            input: input.to_string(),
+            reason: reason.to_string(),
+        };
+        assert_eq!(parse("https://"), Err(url("https://", "host ausente")));
+        assert_eq!(
+            parse("https://user:pw@example.com/"),
+            Err(url(
+                "https://user:pw@example.com/",
+                "credenciais na URL não são aceitas"
+            ))
+        );
+        assert_eq!(
+            parse("ftp://example.com"),
+            Err(AddressParseError::UnsupportedScheme("ftp".to_string()))
+        );
+        assert_eq!(
+            parse("https://example.com:99999/"),
+            Err(AddressParseError::InvalidPort {
+                input: "example.com:99999".to_string(),
+                port: "99999".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! templates.rs — Templates de target para serviços comuns
+//!
+//! Um template agrupa o check, o intervalo, o threshold e as tags padrão de um
+//! tipo de serviço, para que cadastrar um target seja uma linha:
+//! `monitoramento_rede targets add https://loja.exemplo.com --template web-service`.
+//! Os templates embutidos podem ser sobrescritos ou complementados por blocos
+//! `[[target_templates]]` na configuração.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Conjunto de padrões aplicados a um target no cadastro.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetTemplate {
+    pub name: String,
+    /// Check usado (`ping`, `tcp`, `http`, `dns`); a família vem do endereço.
+    pub check: String,
+    /// Intervalo mínimo entre checks, em segundos; ausente = todo ciclo.
+    pub check_interval_secs: Option<i32>,
+    /// Ciclos consecutivos de falha para o target contar no consenso.
+    pub fail_threshold: Option<i32>,
+    /// Peso do target no consenso (ausente = 1).
+    pub weight: Option<f64>,
+    /// RTT (ms) acima do qual a medição conta como degradação.
+    pub degraded_rtt_ms: Option<f64>,
+    /// Perda (%) acima da qual a medição conta como degradação.
+    pub degraded_loss_percent: Option<i16>,
+    /// Bytes de payload dos pings (ausente = 32).
+    pub ping_payload_bytes: Option<i32>,
+    /// Classe DSCP (0–63) marcada nos pings.
+    pub dscp: Option<i16>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub region: Option<String>,
+    pub provider: Option<String>,
+}
+
+fn builtin(
+    name: &str,
+    check: &str,
+    check_interval_secs: Option<i32>,
+    fail_threshold: Option<i32>,
+    tags: &[&str],
+    region: Option<&str>,
+) -> TargetTemplate {
+    TargetTemplate {
+        name: name.to_string(),
+        check: check.to_string(),
+        check_interval_secs,
+        fail_threshold,
+        weight: None,
+        degraded_rtt_ms: None,
+        degraded_loss_percent: None,
+        ping_payload_bytes: None,
+        dscp: None,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        region: region.map(str::to_string),
+        provider: None,
+    }
+}
+
+/// Templates embutidos.
+fn builtins() -> Vec<TargetTemplate> {
+    vec![
+        // Resolvers públicos: consulta DNS a cada ciclo
+        builtin(
+            "public-dns",
+            "dns",
+            None,
+            None,
+            &["dns", "public"],
+            Some("global"),
+        ),
+        // Serviços web: HTTP a cada minuto, tolera uma falha isolada
+        builtin("web-service", "http", Some(60), Some(2), &["web"], None),
+        // Gateway local: ping a cada ciclo, uma falha já conta
+        builtin(
+            "gateway",
+            "ping",
+            None,
+            Some(1),
+            &["gateway", "local"],
+            Some("local"),
+        ),
+    ]
+}
+
+/// Templates disponíveis por nome.
+#[derive(Debug, Clone)]
+pub struct TemplateSet {
+    templates: BTreeMap<String, TargetTemplate>,
+}
+
+impl TemplateSet {
+    /// Embutidos mais os da configuração (que sobrescrevem pelo nome).
+    pub fn new(custom: &[TargetTemplate]) -> Self {
+        let templates = builtins()
+            .into_iter()
+            .chain(custom.iter().cloned())
+            .map(|t| (t.name.clone(), t))
+            .collect();
+        Self { templates }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TargetTemplate> {
+        self.templates.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TargetTemplate> {
+        self.templates.values()
+    }
+
+    /// Nomes disponíveis, para mensagens de erro.
+    pub fn names(&self) -> Vec<&str> {
+        self.templates.keys().map(String::as_str).collect()
+    }
+}
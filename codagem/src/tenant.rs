@@ -0,0 +1,104 @@
+//! tenant.rs — Grupos isolados de targets por tenant (cliente)
+//!
+//! Com `[[tenants]]` configurado, os targets são separados pela coluna
+//! `tenant` e cada tenant roda o seu próprio grupo de schedulers (um por
+//! probe): consenso com os parâmetros do tenant sobre os targets dele e
+//! notificações pelas rotas dele. Uma queda na rede de um cliente não entra
+//! no consenso de outro. Targets sem tenant (ou com um tenant que não está
+//! na configuração) ficam no grupo padrão, com os parâmetros globais.
+
+use crate::config::Config;
+use crate::types::{Probe, Target};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Grupo de targets monitorado por schedulers próprios.
+pub struct TenantGroup {
+    /// `None` = grupo padrão.
+    pub name: Option<String>,
+    /// Configuração global com os parâmetros do tenant aplicados.
+    pub config: Arc<Config>,
+    pub targets: Vec<Target>,
+}
+
+impl TenantGroup {
+    /// Cópia do probe rotulada com o tenant (`tenant/localização`), para
+    /// distinguir os schedulers de cada grupo nos logs e notificações.
+    pub fn probe(&self, probe: &Probe) -> Probe {
+        match &self.name {
+            Some(name) => Probe {
+                location: format!("{}/{}", name, probe.location),
+                ..probe.clone()
+            },
+            None => probe.clone(),
+        }
+    }
+}
+
+/// Separa os targets pelos tenants da configuração; sem `[[tenants]]`, um
+/// único grupo padrão com todos eles. Tenants sem targets são omitidos.
+pub fn partition(config: &Arc<Config>, targets: &[Target]) -> Vec<TenantGroup> {
+    if config.tenants.is_empty() {
+        return vec![TenantGroup {
+            name: None,
+            config: Arc::clone(config),
+            targets: targets.to_vec(),
+        }];
+    }
+
+    let mut groups = Vec::new();
+    for tenant in &config.tenants {
+        let owned: Vec<Target> = targets
+            .iter()
+            .filter(|t| t.tenant.as_deref() == Some(tenant.name.as_str()))
+            .cloned()
+            .collect();
+        if owned.is_empty() {
+            warn!("⚠️ Tenant {} sem targets cadastrados", tenant.name);
+            continue;
+        }
+        let tenant_config = Config {
+            fail_threshold: tenant.fail_threshold.unwrap_or(config.fail_threshold),
+            consensus: tenant.consensus.unwrap_or(config.consensus),
+            consensus_fail_percent: tenant
+                .consensus_fail_percent
+                .or(config.consensus_fail_percent),
+            notifications: tenant
+                .notifications
+                .clone()
+                .or_else(|| config.notifications.clone()),
+            ..Config::clone(config)
+        };
+        groups.push(TenantGroup {
+            name: Some(tenant.name.clone()),
+            config: Arc::new(tenant_config),
+            targets: owned,
+        });
+    }
+
+    let unassigned: Vec<Target> = targets
+        .iter()
+        .filter(|t| {
+            t.tenant
+                .as_deref()
+                .is_none_or(|name| !config.tenants.iter().any(|c| c.name == name))
+        })
+        .cloned()
+        .collect();
+    for target in unassigned.iter().filter(|t| t.tenant.is_some()) {
+        warn!(
+            "⚠️ Target {} ({}) tem tenant `{}` fora de [[tenants]]; monitorado no grupo padrão",
+            target.name,
+            target.address,
+            target.tenant.as_deref().unwrap_or_default()
+        );
+    }
+    if !unassigned.is_empty() {
+        groups.push(TenantGroup {
+            name: None,
+            config: Arc::clone(config),
+            targets: unassigned,
+        });
+    }
+    groups
+}
@@ -0,0 +1,37 @@
+//! timescale.rs — Retenção das métricas no modo TimescaleDB
+//!
+//! Com `[timescale]`, `connectivity_metrics` é uma hypertable (ver
+//! `migrations::apply_timescale`). A retenção não apaga linha a linha: os
+//! chunks inteiramente mais antigos que `retention_days` são descartados com
+//! `drop_chunks`, o que não gera bloat nem vacuum na tabela.
+
+use crate::storage::Storage;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error, info};
+
+/// Intervalo entre descartes dos chunks expirados.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Loop de descarte dos chunks de métricas mais antigos que `retention_days`.
+pub async fn run_retention(storage: Arc<Storage>, retention_days: u32) {
+    let mut ticker = interval(RETENTION_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let cutoff = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+        match storage.drop_metric_chunks_before(cutoff).await {
+            Ok(0) => debug!("[TIMESCALE] Nenhum chunk de métricas expirado"),
+            Ok(dropped) => info!(
+                "[TIMESCALE] {} chunk(s) de métricas anteriores a {} descartado(s)",
+                dropped,
+                cutoff.format("%Y-%m-%d %H:%M")
+            ),
+            Err(e) => error!(
+                "[TIMESCALE] Falha ao descartar chunks de métricas expirados: {:?}",
+                e
+            ),
+        }
+    }
+}
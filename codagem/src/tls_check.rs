@@ -0,0 +1,397 @@
+//! tls_check.rs — Check `tls`: handshake, validade e revogação do certificado
+//!
+//! O check abre um handshake TLS na porta `checks.tls_port` (SNI = host do
+//! nome do target, quando houver) e registra em `details.tls` a cadeia
+//! servida: validação contra as raízes do webpki, vencimento e, com
+//! `checks.tls_ocsp`, a situação de revogação via OCSP. A resposta grampeada
+//! no handshake (stapling) tem preferência; sem ela, o responder do
+//! `Authority Information Access` é consultado e a resposta fica em cache até
+//! o `nextUpdate` (no máximo [`OCSP_CACHE_TTL`]).
+//!
+//! Certificado inválido ou revogado → `Down`; vencimento dentro de
+//! `checks.tls_expiry_warning_days` ou revogação agendada → `Degraded`. Falhas
+//! do responder OCSP só são registradas, sem mudar o status.
+
+use crate::check::{Attempt, Check, CheckKind, build_metric};
+use crate::config::{Config, SourceConfig};
+use crate::ocsp::{self, CertInfo, CertStatus, OcspStatus};
+use crate::ratelimit::RateLimiter;
+use crate::source;
+use crate::target_address;
+use crate::types::{CheckError, ConnectivityMetric, MetricStatus, Probe, Target};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+/// Validade máxima de uma resposta OCSP do responder em cache.
+const OCSP_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Resultado da validação feita no handshake.
+#[derive(Debug, Default)]
+struct Captured {
+    chain_error: Option<String>,
+    stapled: Option<Vec<u8>>,
+}
+
+/// Verificador que valida a cadeia com o webpki mas não derruba o handshake:
+/// o erro e a resposta OCSP grampeada ficam registrados para o check.
+#[derive(Debug)]
+struct CapturingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    captured: Mutex<Captured>,
+}
+
+impl ServerCertVerifier for CapturingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        let result = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        );
+        let mut captured = self.captured.lock().unwrap_or_else(|e| e.into_inner());
+        captured.chain_error = result.err().map(|e| e.to_string());
+        captured.stapled = (!ocsp_response.is_empty()).then(|| ocsp_response.to_vec());
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Handshake concluído.
+struct Handshake {
+    chain: Vec<CertificateDer<'static>>,
+    protocol: Option<String>,
+    captured: Captured,
+}
+
+/// Check TLS com vencimento e revogação do certificado.
+pub struct TlsCheck {
+    provider: Arc<CryptoProvider>,
+    verifier: Arc<WebPkiServerVerifier>,
+    http: reqwest::Client,
+    port: u16,
+    /// Origem do handshake e das consultas OCSP (`checks.sources.tls`).
+    source: SourceConfig,
+    attempts: usize,
+    timeout: Duration,
+    expiry_warning_days: i64,
+    ocsp: bool,
+    /// Respostas do responder por (emissor, serial), com o instante de expiração.
+    cache: Mutex<HashMap<Vec<u8>, (Instant, OcspStatus)>>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl TlsCheck {
+    pub fn new(config: &Config, limiter: Arc<RateLimiter>) -> Result<Self> {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), Arc::clone(&provider))
+                .build()
+                .context("Falha ao criar o verificador de certificados")?;
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let source = config.checks.source_for("tls").clone();
+        let mut http = reqwest::Client::builder().timeout(timeout);
+        if let Some(interface) = &source.interface {
+            http = http.interface(interface);
+        }
+        if let Some(address) = source.address {
+            http = http.local_address(address);
+        }
+        Ok(Self {
+            provider,
+            verifier,
+            http: http
+                .build()
+                .context("Falha ao criar o cliente HTTP do OCSP")?,
+            port: config.checks.tls_port,
+            source,
+            attempts: config.ping_count,
+            timeout,
+            expiry_warning_days: i64::from(config.checks.tls_expiry_warning_days),
+            ocsp: config.checks.tls_ocsp,
+            cache: Mutex::new(HashMap::new()),
+            limiter,
+        })
+    }
+
+    async fn handshake(&self, addr: SocketAddr, name: ServerName<'static>) -> Result<Handshake> {
+        let verifier = Arc::new(CapturingVerifier {
+            inner: Arc::clone(&self.verifier),
+            captured: Mutex::new(Captured::default()),
+        });
+        let config = ClientConfig::builder_with_provider(Arc::clone(&self.provider))
+            .with_safe_default_protocol_versions()
+            .context("Configuração TLS inválida")?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::clone(&verifier) as Arc<dyn ServerCertVerifier>)
+            .with_no_client_auth();
+        let stream = source::tcp_connect(&self.source, addr)
+            .await
+            .with_context(|| format!("falha no connect a {}", addr))?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(name, stream)
+            .await
+            .context("falha no handshake TLS")?;
+        let (_, connection) = stream.get_ref();
+        let chain = connection
+            .peer_certificates()
+            .map(|certs| certs.iter().map(|c| c.clone().into_owned()).collect())
+            .unwrap_or_default();
+        let protocol = connection.protocol_version().map(|v| format!("{:?}", v));
+        let captured =
+            std::mem::take(&mut *verifier.captured.lock().unwrap_or_else(|e| e.into_inner()));
+        Ok(Handshake {
+            chain,
+            protocol,
+            captured,
+        })
+    }
+
+    /// Situação de revogação: resposta grampeada ou consulta ao responder.
+    async fn revocation(
+        &self,
+        leaf: &CertInfo,
+        issuer: Option<&CertInfo>,
+        stapled: Option<&[u8]>,
+    ) -> (&'static str, Result<OcspStatus>) {
+        if let Some(stapled) = stapled {
+            return ("stapled", ocsp::parse_response(stapled, &leaf.serial));
+        }
+        ("responder", self.query_responder(leaf, issuer).await)
+    }
+
+    async fn query_responder(
+        &self,
+        leaf: &CertInfo,
+        issuer: Option<&CertInfo>,
+    ) -> Result<OcspStatus> {
+        let url = leaf
+            .ocsp_url
+            .as_deref()
+            .ok_or_else(|| anyhow!("certificado sem URL OCSP"))?;
+        let issuer = issuer.ok_or_else(|| anyhow!("servidor não enviou o certificado emissor"))?;
+        let key = [leaf.issuer.as_slice(), leaf.serial.as_slice()].concat();
+        if let Some((expires, status)) = self.lock_cache().get(&key)
+            && *expires > Instant::now()
+        {
+            return Ok(status.clone());
+        }
+
+        let body = self
+            .http
+            .post(url)
+            .header("Content-Type", "application/ocsp-request")
+            .body(ocsp::build_request(leaf, issuer))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("falha ao consultar o responder OCSP {}", url))?
+            .bytes()
+            .await
+            .context("falha ao ler a resposta OCSP")?;
+        let status = ocsp::parse_response(&body, &leaf.serial)?;
+
+        let ttl = status
+            .next_update
+            .and_then(|next| (next - Utc::now()).to_std().ok())
+            .map_or(OCSP_CACHE_TTL, |left| left.min(OCSP_CACHE_TTL));
+        self.lock_cache()
+            .insert(key, (Instant::now() + ttl, status.clone()));
+        Ok(status)
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, (Instant, OcspStatus)>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Avalia o certificado servido: detalhes, motivo de `Down` e se a
+    /// medição deve ficar `Degraded`.
+    async fn evaluate(
+        &self,
+        handshake: &Handshake,
+        name: &str,
+    ) -> (serde_json::Value, Option<String>, bool) {
+        let mut tls = serde_json::json!({
+            "server_name": name,
+            "protocol": handshake.protocol,
+            "chain_length": handshake.chain.len(),
+            "chain_error": handshake.captured.chain_error,
+            "stapled_ocsp": handshake.captured.stapled.is_some(),
+        });
+        let mut down = handshake
+            .captured
+            .chain_error
+            .as_ref()
+            .map(|e| format!("certificado inválido: {}", e));
+        let mut degraded = false;
+
+        let Some(leaf) = handshake.chain.first() else {
+            return (
+                tls,
+                Some("servidor não apresentou certificado".to_string()),
+                false,
+            );
+        };
+        let leaf = match ocsp::parse_certificate(leaf) {
+            Ok(leaf) => leaf,
+            Err(e) => {
+                tls["parse_error"] = serde_json::json!(format!("{:#}", e));
+                return (tls, down, degraded);
+            }
+        };
+        let now = Utc::now();
+        let days_left = (leaf.not_after - now).num_days();
+        tls["serial"] = serde_json::json!(leaf.serial_hex());
+        tls["not_before"] = serde_json::json!(leaf.not_before);
+        tls["not_after"] = serde_json::json!(leaf.not_after);
+        tls["days_left"] = serde_json::json!(days_left);
+        if leaf.not_after > now && days_left < self.expiry_warning_days {
+            degraded = true;
+        }
+
+        if self.ocsp {
+            let issuer = handshake
+                .chain
+                .get(1)
+                .and_then(|c| ocsp::parse_certificate(c).ok());
+            let (source, result) = self
+                .revocation(
+                    &leaf,
+                    issuer.as_ref(),
+                    handshake.captured.stapled.as_deref(),
+                )
+                .await;
+            tls["ocsp"] = match result {
+                Ok(status) => {
+                    if let CertStatus::Revoked { revoked_at, reason } = &status.status {
+                        let reason = reason.unwrap_or("sem motivo");
+                        if *revoked_at <= now {
+                            down = Some(format!(
+                                "certificado revogado em {} ({})",
+                                revoked_at.to_rfc3339(),
+                                reason
+                            ));
+                        } else {
+                            degraded = true;
+                        }
+                    }
+                    let mut value = serde_json::json!(status);
+                    value["source"] = serde_json::json!(source);
+                    value
+                }
+                Err(e) => serde_json::json!({ "source": source, "error": format!("{:#}", e) }),
+            };
+        }
+        (tls, down, degraded)
+    }
+}
+
+/// SNI do target: host do nome cadastrado (ex: `https://loja.exemplo.com/`)
+/// ou, sem nome DNS, o próprio IP.
+fn server_name(target: &Target) -> ServerName<'static> {
+    target_address::parse(&target.name)
+        .ok()
+        .and_then(|a| a.hostname().map(str::to_string))
+        .and_then(|host| ServerName::try_from(host).ok())
+        .unwrap_or_else(|| ServerName::IpAddress(target.address.into()))
+}
+
+#[async_trait]
+impl Check for TlsCheck {
+    async fn run(&self, target: &Target, probe: &Probe) -> ConnectivityMetric {
+        let addr = SocketAddr::new(target.address, self.port);
+        let name = server_name(target);
+        let mut attempts = Vec::with_capacity(self.attempts);
+        let mut last: Option<Handshake> = None;
+        for _ in 0..self.attempts {
+            self.limiter.acquire(target).await;
+            let started = Instant::now();
+            let attempt = match tokio::time::timeout(
+                self.timeout,
+                self.handshake(addr, name.clone()),
+            )
+            .await
+            {
+                Ok(Ok(handshake)) => {
+                    last = Some(handshake);
+                    Attempt::Success(started.elapsed().as_secs_f64() * 1000.0)
+                }
+                Ok(Err(e)) => {
+                    let message = format!("{:#}", e);
+                    // Erros do rustls chegam como io::Error InvalidData
+                    let kind = match e.downcast_ref::<std::io::Error>() {
+                        Some(io) if io.kind() != std::io::ErrorKind::InvalidData => {
+                            CheckError::from_io(io)
+                        }
+                        _ => CheckError::TlsError,
+                    };
+                    Attempt::Failure(kind, message)
+                }
+                Err(_) => Attempt::Timeout,
+            };
+            attempts.push(attempt);
+        }
+
+        let name = name.to_str().into_owned();
+        let (tls, down, degraded) = match &last {
+            Some(handshake) => self.evaluate(handshake, &name).await,
+            None => (serde_json::Value::Null, None, false),
+        };
+        let details = serde_json::json!({ "port": self.port, "tls": tls });
+        let mut metric = build_metric(target, probe, CheckKind::Tls, &attempts, Some(details));
+        if let Some(reason) = down {
+            metric.status = MetricStatus::Down;
+            metric.set_error(CheckError::TlsError, reason);
+        } else if degraded && metric.status == MetricStatus::Up {
+            metric.status = MetricStatus::Degraded;
+        }
+        metric
+    }
+
+    fn packets(&self) -> usize {
+        self.attempts
+    }
+}
@@ -0,0 +1,494 @@
+//! traceroute.rs — Caminho até cada target: saltos, RTT, perda e ASN
+//!
+//! Traceroute UDP no estilo do `tracepath`: um datagrama por sonda com TTL
+//! crescente e `IP_RECVERR`/`IPV6_RECVERR` ligados, para que o kernel entregue
+//! o ICMP de volta (time exceeded, port unreachable) na fila de erros do
+//! próprio socket. Não precisa de socket raw nem de privilégios. Cada salto
+//! recebe `probes_per_hop` sondas (perda do salto) e vale o menor RTT. O
+//! traceroute termina no destino (port unreachable ou resposta UDP), num
+//! unreachable de outro tipo ou depois de `MAX_SILENT_HOPS` saltos mudos.
+//!
+//! O ASN de cada salto com endereço público vem do DNS da Team Cymru
+//! (`origin.asn.cymru.com`), com cache em memória. Os traceroutes são
+//! gravados em `traceroutes` e agregados por AS em `as_health.rs`.
+
+use crate::config::TracerouteConfig;
+use crate::types::{Probe, Target};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Saltos consecutivos sem resposta que encerram o traceroute.
+const MAX_SILENT_HOPS: usize = 4;
+/// Validade de um ASN resolvido no cache.
+const ASN_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Um salto do caminho.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hop {
+    pub ttl: u8,
+    /// Roteador que respondeu; ausente se nenhuma sonda teve resposta.
+    pub address: Option<IpAddr>,
+    pub asn: Option<i32>,
+    pub sent: u8,
+    pub received: u8,
+    /// Menor RTT entre as respostas do salto.
+    pub rtt_ms: Option<f64>,
+}
+
+impl Hop {
+    pub fn loss_percent(&self) -> f64 {
+        100.0 * f64::from(self.sent - self.received) / f64::from(self.sent.max(1))
+    }
+}
+
+/// Traceroute de um probe até um target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Traceroute {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub cycle_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub destination: IpAddr,
+    /// O destino respondeu (o caminho está completo).
+    pub reached: bool,
+    pub hops: Vec<Hop>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplyKind {
+    /// TTL expirado num roteador intermediário.
+    TimeExceeded,
+    /// O destino respondeu (port unreachable ou resposta UDP).
+    Destination,
+    /// Unreachable de outro tipo: o caminho termina ali.
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reply {
+    from: IpAddr,
+    rtt_ms: f64,
+    kind: ReplyKind,
+}
+
+/// Converte o `sockaddr` do ofensor em IP.
+///
+/// # Safety
+/// `addr` deve apontar para um `sockaddr_in`/`sockaddr_in6` válido.
+#[cfg(target_os = "linux")]
+unsafe fn sockaddr_ip(addr: *const libc::sockaddr) -> Option<IpAddr> {
+    unsafe {
+        match i32::from((*addr).sa_family) {
+            libc::AF_INET => {
+                let sin = std::ptr::read_unaligned(addr as *const libc::sockaddr_in);
+                Some(IpAddr::from(
+                    u32::from_be(sin.sin_addr.s_addr).to_be_bytes(),
+                ))
+            }
+            libc::AF_INET6 => {
+                let sin6 = std::ptr::read_unaligned(addr as *const libc::sockaddr_in6);
+                Some(IpAddr::from(sin6.sin6_addr.s6_addr))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_option(
+    fd: libc::c_int,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> std::io::Result<()> {
+    // SAFETY: `value` vive durante a chamada e o tamanho informado é o dele.
+    let rc = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// ICMP lido da fila de erros de um socket com `IP_RECVERR`/`IPV6_RECVERR`.
+#[derive(Debug, Clone, Copy)]
+pub struct QueuedIcmp {
+    pub v6: bool,
+    pub icmp_type: u8,
+    pub code: u8,
+    /// Roteador ou host que gerou o ICMP.
+    pub from: Option<IpAddr>,
+}
+
+/// Lê um ICMP da fila de erros do socket, se houver (não bloqueia).
+#[cfg(target_os = "linux")]
+pub fn read_icmp_error(fd: libc::c_int) -> std::io::Result<Option<QueuedIcmp>> {
+    let mut data = [0u8; 512];
+    let mut control = [0u8; 512];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    // SAFETY: `msghdr` é POD; os ponteiros apontam para buffers locais que
+    // vivem até o fim da função.
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control.len() as _;
+    let rc = unsafe { libc::recvmsg(fd, &mut msg, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+    if rc < 0 {
+        let e = std::io::Error::last_os_error();
+        return if e.kind() == std::io::ErrorKind::WouldBlock {
+            Ok(None)
+        } else {
+            Err(e)
+        };
+    }
+
+    // SAFETY: o kernel preencheu `control` com `msg_controllen` bytes de
+    // cmsgs; as macros CMSG_* percorrem só esse intervalo.
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+    while !cmsg.is_null() {
+        let header = unsafe { std::ptr::read_unaligned(cmsg) };
+        let is_recverr = (header.cmsg_level == libc::SOL_IP
+            && header.cmsg_type == libc::IP_RECVERR)
+            || (header.cmsg_level == libc::SOL_IPV6 && header.cmsg_type == libc::IPV6_RECVERR);
+        if is_recverr {
+            let err = unsafe { libc::CMSG_DATA(cmsg) } as *const libc::sock_extended_err;
+            let ee = unsafe { std::ptr::read_unaligned(err) };
+            let v6 = match ee.ee_origin {
+                libc::SO_EE_ORIGIN_ICMP => false,
+                libc::SO_EE_ORIGIN_ICMP6 => true,
+                _ => return Ok(None),
+            };
+            // SAFETY: para erros de origem ICMP o kernel grava o endereço do
+            // ofensor logo após a `sock_extended_err`.
+            let from = unsafe { sockaddr_ip(libc::SO_EE_OFFENDER(err)) };
+            return Ok(Some(QueuedIcmp {
+                v6,
+                icmp_type: ee.ee_type,
+                code: ee.ee_code,
+                from,
+            }));
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+    }
+    Ok(None)
+}
+
+/// Resposta de traceroute na fila de erros do socket, se houver.
+#[cfg(target_os = "linux")]
+fn read_error_queue(fd: libc::c_int, rtt_ms: f64) -> std::io::Result<Option<Reply>> {
+    let Some(icmp) = read_icmp_error(fd)? else {
+        return Ok(None);
+    };
+    let kind = match (icmp.v6, icmp.icmp_type, icmp.code) {
+        (false, 11, _) | (true, 3, _) => ReplyKind::TimeExceeded,
+        (false, 3, 3) | (true, 1, 4) => ReplyKind::Destination,
+        (false, 3, _) | (true, 1, _) => ReplyKind::Unreachable,
+        _ => return Ok(None),
+    };
+    Ok(icmp.from.map(|from| Reply { from, rtt_ms, kind }))
+}
+
+/// Envia uma sonda com o TTL dado e espera a resposta (bloqueante).
+#[cfg(target_os = "linux")]
+fn send_probe(
+    destination: SocketAddr,
+    ttl: u8,
+    timeout: Duration,
+) -> std::io::Result<Option<Reply>> {
+    use std::net::{Ipv4Addr, Ipv6Addr, UdpSocket};
+    use std::os::fd::AsRawFd;
+
+    let (bind, level, ttl_option, recverr): (SocketAddr, _, _, _) = match destination {
+        SocketAddr::V4(_) => (
+            (Ipv4Addr::UNSPECIFIED, 0).into(),
+            libc::SOL_IP,
+            libc::IP_TTL,
+            libc::IP_RECVERR,
+        ),
+        SocketAddr::V6(_) => (
+            (Ipv6Addr::UNSPECIFIED, 0).into(),
+            libc::SOL_IPV6,
+            libc::IPV6_UNICAST_HOPS,
+            libc::IPV6_RECVERR,
+        ),
+    };
+    let socket = UdpSocket::bind(bind)?;
+    let fd = socket.as_raw_fd();
+    set_option(fd, level, ttl_option, libc::c_int::from(ttl))?;
+    set_option(fd, level, recverr, 1)?;
+    socket.connect(destination)?;
+
+    let started = Instant::now();
+    socket.send(b"monitoramento_rede traceroute")?;
+    loop {
+        let left = timeout.saturating_sub(started.elapsed());
+        if left.is_zero() {
+            return Ok(None);
+        }
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN | libc::POLLERR,
+            revents: 0,
+        };
+        // SAFETY: um único `pollfd` válido durante a chamada.
+        let rc = unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as libc::c_int) };
+        if rc < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        if rc == 0 {
+            return Ok(None);
+        }
+        let rtt_ms = started.elapsed().as_secs_f64() * 1000.0;
+        if let Some(reply) = read_error_queue(fd, rtt_ms)? {
+            return Ok(Some(reply));
+        }
+        if poll.revents & libc::POLLIN != 0 {
+            // Porta UDP aberta no destino: a própria resposta encerra o caminho
+            let mut buf = [0u8; 512];
+            let _ = socket.recv(&mut buf);
+            return Ok(Some(Reply {
+                from: destination.ip(),
+                rtt_ms,
+                kind: ReplyKind::Destination,
+            }));
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_probe(
+    _destination: SocketAddr,
+    _ttl: u8,
+    _timeout: Duration,
+) -> std::io::Result<Option<Reply>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "traceroute disponível apenas no Linux",
+    ))
+}
+
+/// Traceroute até `destination` (bloqueante).
+pub fn trace(destination: IpAddr, config: &TracerouteConfig) -> std::io::Result<(bool, Vec<Hop>)> {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut hops = Vec::new();
+    let mut silent = 0;
+    for ttl in 1..=config.max_hops {
+        let port = config.port.wrapping_add(u16::from(ttl) - 1);
+        let mut hop = Hop {
+            ttl,
+            address: None,
+            asn: None,
+            sent: 0,
+            received: 0,
+            rtt_ms: None,
+        };
+        let mut last_kind = None;
+        for _ in 0..config.probes_per_hop {
+            hop.sent += 1;
+            if let Some(reply) = send_probe(SocketAddr::new(destination, port), ttl, timeout)? {
+                hop.received += 1;
+                hop.address.get_or_insert(reply.from);
+                hop.rtt_ms = Some(hop.rtt_ms.map_or(reply.rtt_ms, |r| r.min(reply.rtt_ms)));
+                last_kind = Some(reply.kind);
+            }
+        }
+        hops.push(hop);
+        match last_kind {
+            Some(ReplyKind::Destination) => return Ok((true, hops)),
+            Some(ReplyKind::Unreachable) => break,
+            Some(ReplyKind::TimeExceeded) => silent = 0,
+            None => {
+                silent += 1;
+                if silent >= MAX_SILENT_HOPS {
+                    // Os saltos mudos do fim não dizem nada sobre o caminho
+                    hops.truncate(hops.len() - silent);
+                    break;
+                }
+            }
+        }
+    }
+    Ok((false, hops))
+}
+
+/// ASN de endereços públicos via DNS da Team Cymru, com cache.
+pub struct AsnResolver {
+    resolver: TokioAsyncResolver,
+    cache: Mutex<HashMap<IpAddr, (Instant, Option<i32>)>>,
+}
+
+impl AsnResolver {
+    pub fn from_system_conf() -> anyhow::Result<Self> {
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio_from_system_conf()?,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// ASN de origem do prefixo de `address`; `None` para endereços
+    /// privados, sem anúncio ou quando a consulta falha.
+    pub async fn lookup(&self, address: IpAddr) -> Option<i32> {
+        if !is_public(address) {
+            return None;
+        }
+        if let Some((at, asn)) = self.cache.lock().ok()?.get(&address)
+            && at.elapsed() < ASN_CACHE_TTL
+        {
+            return *asn;
+        }
+        let asn = match self.resolver.txt_lookup(cymru_name(address)).await {
+            // "13335 | 1.1.1.0/24 | AU | apnic | 2011-08-11"; prefixos
+            // anunciados por mais de um AS listam todos no primeiro campo
+            Ok(response) => response.iter().find_map(|txt| {
+                txt.to_string()
+                    .split('|')
+                    .next()?
+                    .split_whitespace()
+                    .next()?
+                    .parse()
+                    .ok()
+            }),
+            Err(e) => {
+                debug!("[TRACEROUTE] ASN de {} indisponível: {}", address, e);
+                None
+            }
+        };
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(address, (Instant::now(), asn));
+        }
+        asn
+    }
+
+    /// Nome do AS registrado no RIR (ex: "GOOGLE - Google LLC, US").
+    pub async fn lookup_name(&self, asn: i32) -> Option<String> {
+        // "15169 | US | arin | 2000-03-30 | GOOGLE - Google LLC, US"
+        let response = self
+            .resolver
+            .txt_lookup(format!("AS{}.asn.cymru.com.", asn))
+            .await
+            .map_err(|e| debug!("[ASN] Nome do AS{} indisponível: {}", asn, e))
+            .ok()?;
+        response.iter().find_map(|txt| {
+            let name = txt.to_string().split('|').nth(4)?.trim().to_string();
+            (!name.is_empty()).then_some(name)
+        })
+    }
+}
+
+/// Endereço roteável na internet (fora de faixas privadas e reservadas).
+pub fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn cymru_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{}.{}.{}.{}.origin.asn.cymru.com.", d, c, b, a)
+        }
+        IpAddr::V6(v6) => {
+            let nibbles: Vec<String> = v6
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|byte| [byte & 0x0f, byte >> 4])
+                .map(|n| format!("{:x}", n))
+                .collect();
+            format!("{}.origin6.asn.cymru.com.", nibbles.join("."))
+        }
+    }
+}
+
+/// Traceroute de todos os targets em paralelo (cada um numa thread
+/// bloqueante), com o ASN de cada salto.
+pub async fn trace_targets(
+    targets: &[Target],
+    probe: &Probe,
+    config: &TracerouteConfig,
+    asns: Option<&AsnResolver>,
+    cycle_id: i64,
+) -> Vec<Traceroute> {
+    let handles: Vec<_> = targets
+        .iter()
+        .map(|target| {
+            let destination = target.address;
+            let config = config.clone();
+            (
+                target.id,
+                destination,
+                tokio::task::spawn_blocking(move || trace(destination, &config)),
+            )
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for (target_id, destination, handle) in handles {
+        let (reached, mut hops) = match handle.await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                debug!(
+                    "[TRACEROUTE {}] Falha no traceroute até {}: {}",
+                    probe.location, destination, e
+                );
+                continue;
+            }
+            Err(_) => continue,
+        };
+        if let Some(asns) = asns {
+            for hop in &mut hops {
+                if let Some(address) = hop.address {
+                    hop.asn = asns.lookup(address).await;
+                }
+            }
+        }
+        results.push(Traceroute {
+            probe_id: probe.id,
+            target_id,
+            cycle_id,
+            timestamp: Utc::now(),
+            destination,
+            reached,
+            hops,
+        });
+    }
+    results
+}
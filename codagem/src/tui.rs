@@ -0,0 +1,229 @@
+//! tui.rs — Dashboard de terminal ao vivo (`--tui`)
+//!
+//! Mostra, a partir do `Snapshot` em memória (ring buffer de RTTs por target),
+//! o status de cada par probe/target, um sparkline do histórico de RTT, os
+//! outages em andamento e os contadores da janela de consenso. Pensado para o
+//! operador em campo acompanhar a recuperação da rede sem browser nem SQL.
+
+use crate::snapshot::{Snapshot, SnapshotStore};
+use crate::types::MetricStatus;
+use anyhow::Result;
+use chrono::Utc;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Quantidade de pontos exibidos no sparkline.
+const SPARKLINE_WIDTH: usize = 40;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Nomes usados para exibição.
+#[derive(Debug, Clone, Default)]
+pub struct Names {
+    pub targets: HashMap<i32, String>,
+    pub probes: HashMap<i32, String>,
+}
+
+/// Executa o dashboard até o usuário sair (`q`, `Esc` ou `Ctrl+C`).
+/// Bloqueante: deve rodar em `spawn_blocking`.
+pub fn run(snapshot: Arc<SnapshotStore>, names: Names) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<()> {
+        loop {
+            let current = snapshot.load();
+            terminal.draw(|frame| draw(frame, &current, &names))?;
+
+            if event::poll(Duration::from_millis(500))?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                let ctrl_c =
+                    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+                if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+fn draw(frame: &mut Frame, snapshot: &Snapshot, names: &Names) {
+    let [header, table, incidents] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Min(5),
+        Constraint::Length(8),
+    ])
+    .areas(frame.area());
+
+    let window = &snapshot.consensus;
+    let updated = snapshot
+        .generated_at
+        .map_or("aguardando primeiro ciclo".to_string(), |t| {
+            format!("{}s atrás", (Utc::now() - t).num_seconds())
+        });
+    let outage_span = if snapshot.active_incidents.is_empty() {
+        Span::styled("Sem outages", Style::default().fg(Color::Green))
+    } else {
+        Span::styled(
+            format!("{} OUTAGE(S) EM ANDAMENTO", snapshot.active_incidents.len()),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )
+    };
+    let header_text = vec![
+        Line::from(vec![
+            outage_span,
+            Span::raw(format!(
+                "   Ciclos: {}   Atualizado: {}",
+                snapshot.cycles, updated
+            )),
+        ]),
+        Line::from(format!(
+            "Consenso: {} ciclos na janela, {} falhas, ~{}/{} bytes, {} evictions   (q para sair)",
+            window.cycles,
+            window.failed_entries,
+            window.approx_bytes,
+            window.max_bytes,
+            window.evictions
+        )),
+    ];
+    frame.render_widget(
+        Paragraph::new(header_text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Monitoramento de Rede "),
+        ),
+        header,
+    );
+
+    let rows: Vec<Row> = snapshot
+        .targets
+        .values()
+        .map(|cell| {
+            let t = cell.load();
+            let probe = names
+                .probes
+                .get(&t.probe_id)
+                .cloned()
+                .unwrap_or_else(|| format!("#{}", t.probe_id));
+            let target = names
+                .targets
+                .get(&t.target_id)
+                .cloned()
+                .unwrap_or_else(|| format!("#{}", t.target_id));
+            let last_rtt = t
+                .recent_rtts
+                .back()
+                .copied()
+                .flatten()
+                .map_or("-".to_string(), |rtt| format!("{:.1} ms", rtt));
+            Row::new(vec![
+                Cell::from(probe),
+                Cell::from(target),
+                Cell::from(t.status.as_str()).style(status_style(&t.status)),
+                Cell::from(last_rtt),
+                Cell::from(
+                    t.packet_loss_percent
+                        .map_or("-".to_string(), |l| format!("{}%", l)),
+                ),
+                Cell::from(sparkline(&t.recent_rtts)),
+            ])
+        })
+        .collect();
+    let table_widget = Table::new(
+        rows,
+        [
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Min(SPARKLINE_WIDTH as u16),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Probe",
+            "Target",
+            "Status",
+            "RTT",
+            "Perda",
+            "Histórico RTT",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title(" Targets "));
+    frame.render_widget(table_widget, table);
+
+    let items: Vec<ListItem> = snapshot
+        .active_incidents
+        .iter()
+        .map(|incident| {
+            let affected: Vec<String> = incident
+                .affected_targets
+                .iter()
+                .map(|id| {
+                    names
+                        .targets
+                        .get(id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("#{}", id))
+                })
+                .collect();
+            ListItem::new(format!(
+                "desde {} ({}s) — {} — {}",
+                incident.start_time.format("%H:%M:%S"),
+                (Utc::now() - incident.start_time).num_seconds(),
+                incident.reason.as_deref().unwrap_or("-"),
+                affected.join(", ")
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Outages em andamento "),
+        ),
+        incidents,
+    );
+}
+
+fn status_style(status: &MetricStatus) -> Style {
+    match status {
+        MetricStatus::Up => Style::default().fg(Color::Green),
+        MetricStatus::Degraded => Style::default().fg(Color::Yellow),
+        MetricStatus::Down | MetricStatus::Timeout => {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        }
+    }
+}
+
+/// Sparkline em caracteres de bloco, relativo ao maior RTT exibido.
+/// Ciclos sem resposta aparecem como `×`.
+fn sparkline(rtts: &VecDeque<Option<f64>>) -> String {
+    let skip = rtts.len().saturating_sub(SPARKLINE_WIDTH);
+    let max = rtts
+        .iter()
+        .skip(skip)
+        .flatten()
+        .fold(0.0_f64, |acc, v| acc.max(*v));
+    rtts.iter()
+        .skip(skip)
+        .map(|rtt| match rtt {
+            Some(v) if max > 0.0 => {
+                let level = ((v / max) * (SPARK_LEVELS.len() - 1) as f64).round() as usize;
+                SPARK_LEVELS[level.min(SPARK_LEVELS.len() - 1)]
+            }
+            Some(_) => SPARK_LEVELS[0],
+            None => '×',
+        })
+        .collect()
+}
@@ -10,10 +10,12 @@
 //! assert_eq!(t.to_string(), "ping_ipv4");
 //! ```
 
+use crate::check::Attempt;
 use chrono::{DateTime, Utc};
 use postgres_types::{FromSql, ToSql};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::net::IpAddr;
 use tokio_postgres::Row;
 
@@ -27,6 +29,7 @@ pub enum SchedulerState {
 /// Enum para status da métrica (PostgreSQL)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSql, FromSql)]
 #[postgres(name = "metric_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum MetricStatus {
     Up,
     Down,
@@ -34,9 +37,74 @@ pub enum MetricStatus {
     Timeout,
 }
 
+impl MetricStatus {
+    /// Nome do valor no enum PostgreSQL.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricStatus::Up => "up",
+            MetricStatus::Down => "down",
+            MetricStatus::Degraded => "degraded",
+            MetricStatus::Timeout => "timeout",
+        }
+    }
+
+    /// Gravidade do status, de `Up` (0) a `Down` (3), para escolher o pior
+    /// entre os probes.
+    pub fn severity(&self) -> u8 {
+        match self {
+            MetricStatus::Up => 0,
+            MetricStatus::Degraded => 1,
+            MetricStatus::Timeout => 2,
+            MetricStatus::Down => 3,
+        }
+    }
+}
+
+impl fmt::Display for MetricStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Severidade de um outage aberto, escalonada conforme a idade.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Severidade registrada em `details.severity` (`minor` se ausente).
+    pub fn of(event: &OutageEvent) -> Self {
+        event
+            .details
+            .as_ref()
+            .and_then(|d| d.get("severity"))
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+            .unwrap_or(Severity::Minor)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Enum para tipo de métrica (PostgreSQL), granular por protocolo e pilha
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSql, FromSql)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, ToSql, FromSql)]
 #[postgres(name = "metric_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum MetricType {
     PingIpv4,
     PingIpv6,
@@ -46,6 +114,66 @@ pub enum MetricType {
     HttpIpv6,
     DnsIpv4,
     DnsIpv6,
+    TlsIpv4,
+    TlsIpv6,
+    UdpIpv4,
+    UdpIpv6,
+    SipIpv4,
+    SipIpv6,
+}
+
+impl MetricType {
+    /// Nome do valor no enum PostgreSQL.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::PingIpv4 => "ping_ipv4",
+            MetricType::PingIpv6 => "ping_ipv6",
+            MetricType::TcpIpv4 => "tcp_ipv4",
+            MetricType::TcpIpv6 => "tcp_ipv6",
+            MetricType::HttpIpv4 => "http_ipv4",
+            MetricType::HttpIpv6 => "http_ipv6",
+            MetricType::DnsIpv4 => "dns_ipv4",
+            MetricType::DnsIpv6 => "dns_ipv6",
+            MetricType::TlsIpv4 => "tls_ipv4",
+            MetricType::TlsIpv6 => "tls_ipv6",
+            MetricType::UdpIpv4 => "udp_ipv4",
+            MetricType::UdpIpv6 => "udp_ipv6",
+            MetricType::SipIpv4 => "sip_ipv4",
+            MetricType::SipIpv6 => "sip_ipv6",
+        }
+    }
+}
+
+impl fmt::Display for MetricType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Localização GeoIP de um endereço (colunas `country_code`, `city`,
+/// `latitude` e `longitude` de targets e probes).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocation {
+    /// Código ISO 3166-1 alfa-2 do país (ex: `BR`).
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl GeoLocation {
+    fn from_row(row: &Row) -> Self {
+        Self {
+            country_code: row.try_get("country_code").ok().flatten(),
+            city: row.try_get("city").ok().flatten(),
+            latitude: row.try_get("latitude").ok().flatten(),
+            longitude: row.try_get("longitude").ok().flatten(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.country_code.is_none() && self.city.is_none() && self.latitude.is_none()
+    }
 }
 
 /// Struct de alvo monitorado (monitoring_targets)
@@ -59,6 +187,71 @@ pub struct Target {
     pub type_: String, // Pode ser refinado para MetricType se o banco garantir ENUM
     pub region: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
+    /// Template aplicado no cadastro (ex: `web-service`).
+    pub template: Option<String>,
+    pub tags: Vec<String>,
+    /// Intervalo mínimo entre checks deste target; ausente = todo ciclo.
+    pub check_interval_secs: Option<i32>,
+    /// Ciclos consecutivos de falha para o target contar no consenso;
+    /// ausente = `fail_threshold` global.
+    pub fail_threshold: Option<i32>,
+    /// Peso do target no consenso; ausente = 1.
+    pub weight: Option<f64>,
+    /// RTT acima do qual uma medição bem-sucedida vira `Degraded`.
+    pub degraded_rtt_ms: Option<f64>,
+    /// Perda (%) acima da qual a medição conta como degradação.
+    pub degraded_loss_percent: Option<i16>,
+    /// Bytes de payload dos pings; ausente = 32.
+    pub ping_payload_bytes: Option<i32>,
+    /// Classe DSCP (0–63) marcada nos pings; ausente = 0 (best effort).
+    pub dscp: Option<i16>,
+    #[serde(default)]
+    pub geo: GeoLocation,
+    /// Tenant (cliente) dono do target; ausente = grupo padrão.
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// `false` = monitoramento pausado: o scheduler não checa o target, e o
+    /// histórico fica como está.
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+impl Target {
+    /// Target fora do cadastro (sem id), checado por ping; usado nas
+    /// verificações do próprio probe, cujos resultados não são gravados.
+    pub fn unregistered(name: String, address: IpAddr) -> Self {
+        Self {
+            id: 0,
+            name,
+            address,
+            asn: None,
+            provider: None,
+            type_: if address.is_ipv4() {
+                "ping_ipv4"
+            } else {
+                "ping_ipv6"
+            }
+            .to_string(),
+            region: None,
+            created_at: None,
+            template: None,
+            tags: Vec::new(),
+            check_interval_secs: None,
+            fail_threshold: None,
+            weight: None,
+            degraded_rtt_ms: None,
+            degraded_loss_percent: None,
+            ping_payload_bytes: None,
+            dscp: None,
+            geo: GeoLocation::default(),
+            tenant: None,
+            enabled: true,
+        }
+    }
 }
 
 impl From<Row> for Target {
@@ -72,6 +265,18 @@ impl From<Row> for Target {
             type_: row.get("type"),
             region: row.get("region"),
             created_at: row.try_get("created_at").ok(),
+            template: row.try_get("template").ok().flatten(),
+            tags: row.try_get("tags").ok().flatten().unwrap_or_default(),
+            check_interval_secs: row.try_get("check_interval_secs").ok().flatten(),
+            fail_threshold: row.try_get("fail_threshold").ok().flatten(),
+            weight: row.try_get("weight").ok().flatten(),
+            degraded_rtt_ms: row.try_get("degraded_rtt_ms").ok().flatten(),
+            degraded_loss_percent: row.try_get("degraded_loss_percent").ok().flatten(),
+            ping_payload_bytes: row.try_get("ping_payload_bytes").ok().flatten(),
+            dscp: row.try_get("dscp").ok().flatten(),
+            geo: GeoLocation::from_row(&row),
+            tenant: row.try_get("tenant").ok().flatten(),
+            enabled: row.try_get("enabled").unwrap_or(true),
         }
     }
 }
@@ -84,6 +289,8 @@ pub struct Probe {
     pub ip_address: Option<IpAddr>,
     pub provider: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub geo: GeoLocation,
 }
 
 impl From<Row> for Probe {
@@ -94,6 +301,7 @@ impl From<Row> for Probe {
             ip_address: row.get("ip_address"),
             provider: row.get("provider"),
             created_at: row.try_get("created_at").ok(),
+            geo: GeoLocation::from_row(&row),
         }
     }
 }
@@ -106,6 +314,8 @@ pub struct Cycle {
     pub ended_at: Option<DateTime<Utc>>,
     pub cycle_number: i32,
     pub probe_count: i32,
+    /// Medido com o host sob carga (checks pesados pausados).
+    pub degraded_conditions: bool,
 }
 
 impl From<Row> for Cycle {
@@ -116,6 +326,188 @@ impl From<Row> for Cycle {
             ended_at: row.get("ended_at"),
             cycle_number: row.get("cycle_number"),
             probe_count: row.get("probe_count"),
+            degraded_conditions: row
+                .try_get::<_, Option<bool>>("degraded_conditions")
+                .ok()
+                .flatten()
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Resumo de um ciclo de monitoramento (cycle_summaries)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleSummary {
+    /// 0 = ciclo não registrado (gravado como nulo).
+    pub cycle_id: i64,
+    pub probe_id: i32,
+    pub started_at: DateTime<Utc>,
+    pub targets_up: i32,
+    pub targets_degraded: i32,
+    /// Targets em Down ou Timeout.
+    pub targets_down: i32,
+    /// RTT médio das medições com resposta.
+    pub avg_rtt_ms: Option<f64>,
+    /// Perda média das medições com perda informada.
+    pub loss_avg_percent: Option<f64>,
+    pub duration_ms: f64,
+}
+
+impl CycleSummary {
+    /// Resume as medições de um ciclo.
+    pub fn from_metrics(
+        cycle_id: i64,
+        probe_id: i32,
+        started_at: DateTime<Utc>,
+        metrics: &[ConnectivityMetric],
+        duration_ms: f64,
+    ) -> Self {
+        let count = |status: &[MetricStatus]| {
+            metrics
+                .iter()
+                .filter(|m| status.contains(&m.status))
+                .count() as i32
+        };
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        Self {
+            cycle_id,
+            probe_id,
+            started_at,
+            targets_up: count(&[MetricStatus::Up]),
+            targets_degraded: count(&[MetricStatus::Degraded]),
+            targets_down: count(&[MetricStatus::Down, MetricStatus::Timeout]),
+            avg_rtt_ms: mean(metrics.iter().filter_map(|m| m.response_time_ms).collect()),
+            loss_avg_percent: mean(
+                metrics
+                    .iter()
+                    .filter_map(|m| m.packet_loss_percent.map(f64::from))
+                    .collect(),
+            ),
+            duration_ms,
+        }
+    }
+}
+
+impl From<Row> for CycleSummary {
+    fn from(row: Row) -> Self {
+        Self {
+            cycle_id: row.get::<_, Option<i64>>("cycle_id").unwrap_or(0),
+            probe_id: row.get("probe_id"),
+            started_at: row.get("started_at"),
+            targets_up: row.get("targets_up"),
+            targets_degraded: row.get("targets_degraded"),
+            targets_down: row.get("targets_down"),
+            avg_rtt_ms: row.get("avg_rtt_ms"),
+            loss_avg_percent: row.get("loss_avg_percent"),
+            duration_ms: row.get("duration_ms"),
+        }
+    }
+}
+
+/// Distribuição da latência de uma consulta de `checks.dns_queries` num
+/// resolver (target), calculada a partir de `details.queries`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsQueryLatency {
+    pub target_id: i32,
+    pub name: String,
+    pub record: String,
+    /// `false` para nomes com `{random}` (sempre fora do cache).
+    pub cached: bool,
+    pub samples: i64,
+    pub failures: i64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+impl From<Row> for DnsQueryLatency {
+    fn from(row: Row) -> Self {
+        Self {
+            target_id: row.get("target_id"),
+            name: row.get("name"),
+            record: row.get("record"),
+            cached: row.get("cached"),
+            samples: row.get("samples"),
+            failures: row.get("failures"),
+            p50_ms: row.get("p50_ms"),
+            p95_ms: row.get("p95_ms"),
+            max_ms: row.get("max_ms"),
+        }
+    }
+}
+
+/// Disponibilidade, perda e RTT médios de um target num período.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetPeriodStats {
+    pub target_id: i32,
+    pub samples: i64,
+    pub up_samples: i64,
+    pub loss_percent: Option<f64>,
+    /// Medições com RTT (as que responderam).
+    pub rtt_samples: i64,
+    pub avg_rtt_ms: Option<f64>,
+}
+
+impl From<Row> for TargetPeriodStats {
+    fn from(row: Row) -> Self {
+        Self {
+            target_id: row.get("target_id"),
+            samples: row.get("samples"),
+            up_samples: row.get("up_samples"),
+            loss_percent: row.get("loss_percent"),
+            rtt_samples: row.get("rtt_samples"),
+            avg_rtt_ms: row.get("avg_rtt_ms"),
+        }
+    }
+}
+
+/// Como [`TargetPeriodStats`], por probe e target, com as interfaces de
+/// saída registradas na proveniência.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeTargetStats {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub samples: i64,
+    pub up_samples: i64,
+    pub loss_percent: Option<f64>,
+    pub rtt_samples: i64,
+    pub avg_rtt_ms: Option<f64>,
+    pub interfaces: Vec<String>,
+}
+
+impl From<Row> for ProbeTargetStats {
+    fn from(row: Row) -> Self {
+        Self {
+            probe_id: row.get("probe_id"),
+            target_id: row.get("target_id"),
+            samples: row.get("samples"),
+            up_samples: row.get("up_samples"),
+            loss_percent: row.get("loss_percent"),
+            rtt_samples: row.get("rtt_samples"),
+            avg_rtt_ms: row.get("avg_rtt_ms"),
+            interfaces: row.get("interfaces"),
+        }
+    }
+}
+
+/// Identidade de uma medição: probe, target, tipo e horário.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MetricKey {
+    pub probe_id: i32,
+    pub target_id: i32,
+    pub metric_type: MetricType,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MetricKey {
+    pub fn of(metric: &ConnectivityMetric) -> Self {
+        Self {
+            probe_id: metric.probe_id,
+            target_id: metric.target_id,
+            metric_type: metric.metric_type.clone(),
+            timestamp: metric.timestamp,
         }
     }
 }
@@ -132,14 +524,185 @@ pub struct ConnectivityMetric {
     pub status: MetricStatus,
     pub response_time_ms: Option<f64>,
     pub packet_loss_percent: Option<i16>,
-    pub error_message: Option<String>,
+    /// Causa da falha (coluna `error_kind`); a mensagem original fica em
+    /// `details.error_message`.
+    pub error: Option<CheckError>,
+    /// Detalhes específicos da medição (ex: decomposição de RTT TCP).
+    pub details: Option<serde_json::Value>,
+    /// Origem da medição; `None` em métricas gravadas antes da coluna existir.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// Tentativas do ciclo, na ordem. Não vão para `connectivity_metrics`;
+    /// com `raw_samples = true` viram linhas de `raw_samples`.
+    #[serde(skip)]
+    pub attempts: Vec<Attempt>,
+}
+
+impl ConnectivityMetric {
+    /// Registra a causa da falha e a mensagem original em `details`.
+    pub fn set_error(&mut self, error: CheckError, message: impl Into<String>) {
+        self.error = Some(error);
+        let details = self.details.get_or_insert_with(|| serde_json::json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("error_message".into(), message.into().into());
+        }
+    }
+
+    /// Mensagem original da falha (`details.error_message`).
+    pub fn error_message(&self) -> Option<&str> {
+        self.details.as_ref()?.get("error_message")?.as_str()
+    }
+}
+
+/// Causa de falha de um check, para agregar e graficar falhas por causa.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckError {
+    Timeout,
+    /// Rede ou host inalcançável (sem rota, ICMP unreachable).
+    Unreachable,
+    /// Destino bloqueado por filtro (ICMP "administratively prohibited").
+    AdminProhibited,
+    ConnectionRefused,
+    /// Conexão resetada ou abortada pelo outro lado.
+    ResetByPeer,
+    /// O sistema negou a operação (socket ICMP sem privilégio, firewall local).
+    PermissionDenied,
+    DnsFailure,
+    TlsError,
+    /// Resposta HTTP de erro (5xx) ou cadeia de redirects inválida.
+    HttpError,
+    /// Resposta SIP de erro (5xx/6xx).
+    SipError,
+    Other,
+}
+
+impl CheckError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckError::Timeout => "timeout",
+            CheckError::Unreachable => "unreachable",
+            CheckError::AdminProhibited => "admin_prohibited",
+            CheckError::ConnectionRefused => "connection_refused",
+            CheckError::ResetByPeer => "reset_by_peer",
+            CheckError::PermissionDenied => "permission_denied",
+            CheckError::DnsFailure => "dns_failure",
+            CheckError::TlsError => "tls_error",
+            CheckError::HttpError => "http_error",
+            CheckError::SipError => "sip_error",
+            CheckError::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "timeout" => CheckError::Timeout,
+            "unreachable" => CheckError::Unreachable,
+            "admin_prohibited" => CheckError::AdminProhibited,
+            "connection_refused" => CheckError::ConnectionRefused,
+            "reset_by_peer" => CheckError::ResetByPeer,
+            "permission_denied" => CheckError::PermissionDenied,
+            "dns_failure" => CheckError::DnsFailure,
+            "tls_error" => CheckError::TlsError,
+            "http_error" => CheckError::HttpError,
+            "sip_error" => CheckError::SipError,
+            "other" => CheckError::Other,
+            _ => return None,
+        })
+    }
+
+    /// Causa de um erro de socket.
+    pub fn from_io(error: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match error.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => CheckError::Timeout,
+            ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => CheckError::Unreachable,
+            ErrorKind::ConnectionRefused => CheckError::ConnectionRefused,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => CheckError::ResetByPeer,
+            ErrorKind::PermissionDenied => CheckError::PermissionDenied,
+            _ => Self::classify(&error.to_string()),
+        }
+    }
+
+    /// Causa inferida do texto de um erro, para erros que só chegam como
+    /// mensagem (ping, bibliotecas) e métricas gravadas antes de `error_kind`.
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_ascii_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+        if has(&["timeout", "timed out"]) {
+            CheckError::Timeout
+        } else if has(&["prohibited"]) {
+            CheckError::AdminProhibited
+        } else if has(&["unreachable", "no route"]) {
+            CheckError::Unreachable
+        } else if has(&["refused"]) {
+            CheckError::ConnectionRefused
+        } else if has(&["reset", "aborted", "broken pipe"]) {
+            CheckError::ResetByPeer
+        } else if has(&["permission denied", "operation not permitted"]) {
+            CheckError::PermissionDenied
+        } else if has(&["dns", "resolve", "no record", "nxdomain"]) {
+            CheckError::DnsFailure
+        } else if has(&["tls", "certificate", "certificado", "handshake"]) {
+            CheckError::TlsError
+        } else if has(&["http", "redirect"]) {
+            CheckError::HttpError
+        } else {
+            CheckError::Other
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Implementação que produziu a medição.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MeasurementBackend {
+    /// Sockets do próprio processo (ICMP via surge-ping, TCP, HTTP, DNS).
+    Native,
+    /// Binário externo executado pelo probe.
+    Subprocess,
+}
+
+impl MeasurementBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MeasurementBackend::Native => "native",
+            MeasurementBackend::Subprocess => "subprocess",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "native" => Some(MeasurementBackend::Native),
+            "subprocess" => Some(MeasurementBackend::Subprocess),
+            _ => None,
+        }
+    }
+}
+
+/// Proveniência de uma métrica: backend, origem de rede e versão do binário.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Provenance {
+    pub backend: MeasurementBackend,
+    /// Endereço local usado para alcançar o target.
+    pub source_address: Option<IpAddr>,
+    /// Interface de saída (dona de `source_address`).
+    pub interface: Option<String>,
+    /// Versão do binário que mediu.
+    pub probe_version: String,
 }
 
 impl From<Row> for ConnectivityMetric {
     fn from(row: Row) -> Self {
-        Self {
+        let mut metric = Self {
             id: row.get("id"),
-            cycle_id: row.get("cycle_id"),
+            cycle_id: row.get::<_, Option<i64>>("cycle_id").unwrap_or(0),
             probe_id: row.get("probe_id"),
             target_id: row.get("target_id"),
             timestamp: row.get("timestamp"),
@@ -147,7 +710,65 @@ impl From<Row> for ConnectivityMetric {
             status: row.get("status"),
             response_time_ms: row.get("response_time_ms"),
             packet_loss_percent: row.get("packet_loss_percent"),
-            error_message: row.get("error_message"),
+            error: row
+                .try_get::<_, Option<String>>("error_kind")
+                .ok()
+                .flatten()
+                .and_then(|e| CheckError::parse(&e)),
+            details: row.get("details"),
+            provenance: row
+                .try_get::<_, Option<String>>("backend")
+                .ok()
+                .flatten()
+                .and_then(|b| MeasurementBackend::parse(&b))
+                .map(|backend| Provenance {
+                    backend,
+                    source_address: row.get("source_address"),
+                    interface: row.get("interface"),
+                    probe_version: row
+                        .get::<_, Option<String>>("probe_version")
+                        .unwrap_or_default(),
+                }),
+            attempts: Vec::new(),
+        };
+        // Métricas gravadas antes de `error_kind`: só a mensagem, na coluna
+        // `error_message`
+        if metric.error.is_none()
+            && let Ok(Some(message)) = row.try_get::<_, Option<String>>("error_message")
+        {
+            metric.set_error(CheckError::classify(&message), message);
+        }
+        metric
+    }
+}
+
+/// Tipo de evento em `outage_events`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutageEventType {
+    /// Targets sem resposta (consenso de Down/Timeout).
+    #[default]
+    Outage,
+    /// Target respondendo acima dos thresholds de RTT/perda (congestionamento).
+    Degradation,
+    /// Um único target sem resposta, abaixo do consenso (outage parcial).
+    Partial,
+}
+
+impl OutageEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutageEventType::Outage => "outage",
+            OutageEventType::Degradation => "degradation",
+            OutageEventType::Partial => "partial",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "degradation" => OutageEventType::Degradation,
+            "partial" => OutageEventType::Partial,
+            _ => OutageEventType::Outage,
         }
     }
 }
@@ -156,6 +777,8 @@ impl From<Row> for ConnectivityMetric {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutageEvent {
     pub id: i64,
+    #[serde(default)]
+    pub event_type: OutageEventType,
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
     pub duration_seconds: Option<i32>,
@@ -164,12 +787,20 @@ pub struct OutageEvent {
     pub affected_probes: Option<Vec<i32>>,
     pub consensus_level: Option<i32>,
     pub details: Option<serde_json::Value>,
+    /// ASNs dos targets afetados, preenchidos pelo banco na gravação.
+    #[serde(default)]
+    pub affected_asns: Vec<i32>,
 }
 
 impl From<Row> for OutageEvent {
     fn from(row: Row) -> Self {
         Self {
             id: row.get("id"),
+            event_type: row
+                .try_get::<_, Option<String>>("event_type")
+                .ok()
+                .flatten()
+                .map_or(OutageEventType::Outage, |t| OutageEventType::parse(&t)),
             start_time: row.get("start_time"),
             end_time: row.get("end_time"),
             duration_seconds: row.get("duration_seconds"),
@@ -178,6 +809,91 @@ impl From<Row> for OutageEvent {
             affected_probes: row.get("affected_probes"),
             consensus_level: row.get("consensus_level"),
             details: row.get("details"),
+            affected_asns: row
+                .try_get::<_, Option<Vec<i32>>>("affected_asns")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl OutageEvent {
+    /// Anotações dos operadores (`details.annotations`), da mais antiga à
+    /// mais recente.
+    pub fn annotations(&self) -> Vec<OutageAnnotation> {
+        self.details
+            .as_ref()
+            .and_then(|d| d.get("annotations"))
+            .and_then(|a| serde_json::from_value(a.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Anotação de um operador num outage (ex: "rompimento de fibra no km 12,
+/// chamado #1234"), gravada em `details.annotations`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutageAnnotation {
+    pub text: String,
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Janela de manutenção (maintenance_windows): sem `probe_id`, vale para
+/// todos os probes; sem `target_id`, para todos os targets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub id: i64,
+    pub probe_id: Option<i32>,
+    pub target_id: Option<i32>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+
+    /// A janela, ativa em `now`, cobre o target no evento (probe e target
+    /// conferem ou a janela não os restringe).
+    fn covers(&self, event: &OutageEvent, target_id: i32, now: DateTime<Utc>) -> bool {
+        self.is_active(now)
+            && self.target_id.is_none_or(|id| id == target_id)
+            && self.probe_id.is_none_or(|id| {
+                event
+                    .affected_probes
+                    .as_ref()
+                    .is_none_or(|probes| probes.contains(&id))
+            })
+    }
+
+    /// Todos os targets do evento estão em manutenção em `now`.
+    pub fn suppresses(
+        windows: &[MaintenanceWindow],
+        event: &OutageEvent,
+        now: DateTime<Utc>,
+    ) -> bool {
+        !event.affected_targets.is_empty()
+            && event
+                .affected_targets
+                .iter()
+                .all(|&target_id| windows.iter().any(|w| w.covers(event, target_id, now)))
+    }
+}
+
+impl From<Row> for MaintenanceWindow {
+    fn from(row: Row) -> Self {
+        Self {
+            id: row.get("id"),
+            probe_id: row.get("probe_id"),
+            target_id: row.get("target_id"),
+            starts_at: row.get("starts_at"),
+            ends_at: row.get("ends_at"),
+            reason: row.get("reason"),
+            created_at: row.try_get("created_at").ok(),
         }
     }
 }
@@ -186,6 +902,9 @@ impl From<Row> for OutageEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TargetStatus {
     pub target_id: i32,
+    /// Probe que viu o status (`None` nas linhas gravadas sem probe).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_id: Option<i32>,
     pub last_status: MetricStatus,
     pub last_change: DateTime<Utc>,
 }
@@ -194,12 +913,87 @@ impl From<Row> for TargetStatus {
     fn from(row: Row) -> Self {
         Self {
             target_id: row.get("target_id"),
+            probe_id: row.get("probe_id"),
             last_status: row.get("last_status"),
             last_change: row.get("last_change"),
         }
     }
 }
 
+/// Disponibilidade horária por target (mv_hourly_availability), ou por probe
+/// e target (mv_hourly_probe_availability)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyAvailability {
+    /// Probe que mediu; `None` nas linhas agregadas de todos os probes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_id: Option<i32>,
+    pub target_id: i32,
+    pub hour: DateTime<Utc>,
+    pub samples: i64,
+    pub up_samples: i64,
+    pub availability_percent: f64,
+}
+
+impl From<Row> for HourlyAvailability {
+    fn from(row: Row) -> Self {
+        Self {
+            probe_id: row.try_get("probe_id").ok(),
+            target_id: row.get("target_id"),
+            hour: row.get("hour"),
+            samples: row.get("samples"),
+            up_samples: row.get("up_samples"),
+            availability_percent: row.get("availability_percent"),
+        }
+    }
+}
+
+/// Minutos de outage por dia (mv_daily_outage_minutes)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyOutageMinutes {
+    pub day: DateTime<Utc>,
+    pub outage_count: i64,
+    pub outage_minutes: f64,
+}
+
+impl From<Row> for DailyOutageMinutes {
+    fn from(row: Row) -> Self {
+        Self {
+            day: row.get("day"),
+            outage_count: row.get("outage_count"),
+            outage_minutes: row.get("outage_minutes"),
+        }
+    }
+}
+
+/// Percentis de latência horários por target (mv_hourly_latency), ou por
+/// probe e target (mv_hourly_probe_latency)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    /// Probe que mediu; `None` nas linhas agregadas de todos os probes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_id: Option<i32>,
+    pub target_id: i32,
+    pub hour: DateTime<Utc>,
+    pub avg_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+impl From<Row> for LatencyPercentiles {
+    fn from(row: Row) -> Self {
+        Self {
+            probe_id: row.try_get("probe_id").ok(),
+            target_id: row.get("target_id"),
+            hour: row.get("hour"),
+            avg_ms: row.get("avg_ms"),
+            p50_ms: row.get("p50_ms"),
+            p95_ms: row.get("p95_ms"),
+            p99_ms: row.get("p99_ms"),
+        }
+    }
+}
+
 /// Estado de aquecimento de target (lógica de streaks)
 #[derive(Debug, Clone)]
 pub struct TargetWarmupState {
@@ -226,4 +1020,19 @@ impl TargetWarmupState {
         }
         self.success_streak[&target_id] >= self.required_streak
     }
+
+    /// Semeia o streak de um target a partir do histórico persistido
+    /// (cold-start), limitado ao streak necessário.
+    pub fn seed(&mut self, target_id: i32, streak: usize) {
+        self.success_streak
+            .insert(target_id, streak.min(self.required_streak));
+    }
+
+    /// Quantidade de targets já aquecidos.
+    pub fn warmed_count(&self) -> usize {
+        self.success_streak
+            .values()
+            .filter(|streak| **streak >= self.required_streak)
+            .count()
+    }
 }
@@ -0,0 +1,211 @@
+//! uplink_report.rs — Comparação de uplinks (provedores) de sites multi-homed
+//!
+//! Num site com dois links, cada uplink é medido por um probe lógico preso à
+//! sua interface (`source` do perfil do probe). O relatório põe os probes
+//! lado a lado no período: uptime, perda e RTT médios das medições e os
+//! minutos de outage de consenso de cada um, para o cliente decidir com qual
+//! provedor ficar. Para que a comparação seja justa, as médias usam só os
+//! targets medidos por todos os uplinks comparados (sem nenhum em comum,
+//! valem todos). O melhor uplink é o com menos minutos de outage, depois a
+//! menor perda e depois o menor RTT.
+
+use crate::storage::Storage;
+use crate::types::{OutageEvent, OutageEventType, Probe, ProbeTargetStats};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Um uplink (probe) no período.
+#[derive(Debug, Clone, Serialize)]
+pub struct UplinkStats {
+    pub probe_id: i32,
+    pub location: String,
+    /// Provedor cadastrado no probe (`monitoring_probes.provider`).
+    pub provider: Option<String>,
+    /// Interfaces de saída registradas na proveniência das medições.
+    pub interfaces: Vec<String>,
+    pub targets: usize,
+    pub samples: i64,
+    pub uptime_percent: Option<f64>,
+    pub loss_percent: Option<f64>,
+    pub avg_rtt_ms: Option<f64>,
+    /// Outages de consenso do probe que tocaram o período.
+    pub outages: usize,
+    /// Minutos com outage aberto dentro do período (sobreposições contadas
+    /// uma vez).
+    pub outage_minutes: f64,
+}
+
+/// Comparação dos uplinks, do melhor ao pior.
+#[derive(Debug, Clone, Serialize)]
+pub struct UplinkComparison {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Targets medidos por todos os uplinks (0 = sem targets em comum; as
+    /// médias usam todos os targets de cada um).
+    pub common_targets: usize,
+    pub uplinks: Vec<UplinkStats>,
+    /// Melhor uplink, com ao menos dois comparados.
+    pub best_probe_id: Option<i32>,
+}
+
+/// Compara os probes de `probe_ids` (ou todos os com medições) na janela
+/// `[from, to)`. Com `target_ids`, só os targets do grupo.
+pub async fn compare(
+    storage: &Storage,
+    probe_ids: Option<&[i32]>,
+    target_ids: Option<&[i32]>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<UplinkComparison> {
+    let stats = storage
+        .list_probe_target_period_stats(probe_ids, target_ids, from, to)
+        .await?;
+    let ids: Vec<i32> = stats
+        .iter()
+        .map(|s| s.probe_id)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let events = storage
+        .list_outage_events_between(Some(&ids), target_ids, from, to)
+        .await?;
+    let probes: HashMap<i32, _> = storage
+        .list_probes()
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect();
+
+    let mut per_probe: BTreeMap<i32, Vec<&ProbeTargetStats>> = BTreeMap::new();
+    for s in &stats {
+        per_probe.entry(s.probe_id).or_default().push(s);
+    }
+    let common: BTreeSet<i32> = per_probe
+        .values()
+        .map(|rows| rows.iter().map(|s| s.target_id).collect::<BTreeSet<_>>())
+        .reduce(|a, b| a.intersection(&b).copied().collect())
+        .unwrap_or_default();
+
+    let mut uplinks: Vec<UplinkStats> = per_probe
+        .into_iter()
+        .map(|(probe_id, rows)| {
+            let rows: Vec<_> = if common.is_empty() {
+                rows
+            } else {
+                rows.into_iter()
+                    .filter(|s| common.contains(&s.target_id))
+                    .collect()
+            };
+            let (outages, outage_minutes) = outage_minutes(&events, probe_id, from, to);
+            let probe = probes.get(&probe_id);
+            uplink(probe_id, &rows, outages, outage_minutes, probe)
+        })
+        .collect();
+    uplinks.sort_by(|a, b| {
+        a.outage_minutes
+            .total_cmp(&b.outage_minutes)
+            .then(
+                a.loss_percent
+                    .unwrap_or(100.0)
+                    .total_cmp(&b.loss_percent.unwrap_or(100.0)),
+            )
+            .then(
+                a.avg_rtt_ms
+                    .unwrap_or(f64::INFINITY)
+                    .total_cmp(&b.avg_rtt_ms.unwrap_or(f64::INFINITY)),
+            )
+    });
+    let best_probe_id = (uplinks.len() > 1).then(|| uplinks[0].probe_id);
+
+    Ok(UplinkComparison {
+        from,
+        to,
+        common_targets: common.len(),
+        uplinks,
+        best_probe_id,
+    })
+}
+
+fn uplink(
+    probe_id: i32,
+    rows: &[&ProbeTargetStats],
+    outages: usize,
+    outage_minutes: f64,
+    probe: Option<&Probe>,
+) -> UplinkStats {
+    let samples: i64 = rows.iter().map(|s| s.samples).sum();
+    let up_samples: i64 = rows.iter().map(|s| s.up_samples).sum();
+    let weighted = |value: fn(&ProbeTargetStats) -> Option<f64>,
+                    weight: fn(&ProbeTargetStats) -> i64| {
+        let (sum, count) = rows
+            .iter()
+            .fold((0.0, 0i64), |(sum, count), s| match value(s) {
+                Some(v) => (sum + v * weight(s) as f64, count + weight(s)),
+                None => (sum, count),
+            });
+        (count > 0).then(|| sum / count as f64)
+    };
+    let interfaces: BTreeSet<String> = rows
+        .iter()
+        .flat_map(|s| s.interfaces.iter().cloned())
+        .collect();
+    UplinkStats {
+        probe_id,
+        location: probe.map_or_else(|| format!("probe {}", probe_id), |p| p.location.clone()),
+        provider: probe.and_then(|p| p.provider.clone()),
+        interfaces: interfaces.into_iter().collect(),
+        targets: rows.len(),
+        samples,
+        uptime_percent: (samples > 0).then(|| 100.0 * up_samples as f64 / samples as f64),
+        loss_percent: weighted(|s| s.loss_percent, |s| s.samples),
+        avg_rtt_ms: weighted(|s| s.avg_rtt_ms, |s| s.rtt_samples),
+        outages,
+        outage_minutes,
+    }
+}
+
+/// Outages de consenso do probe e os minutos cobertos por eles em `[from,
+/// to)`, com os intervalos sobrepostos unidos.
+fn outage_minutes(
+    events: &[OutageEvent],
+    probe_id: i32,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> (usize, f64) {
+    let mut spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = events
+        .iter()
+        .filter(|e| e.event_type == OutageEventType::Outage)
+        .filter(|e| {
+            e.affected_probes
+                .as_ref()
+                .is_some_and(|probes| probes.contains(&probe_id))
+        })
+        .map(|e| {
+            (
+                e.start_time.max(from),
+                e.end_time.map_or(to, |end| end.min(to)),
+            )
+        })
+        .filter(|(start, end)| end > start)
+        .collect();
+    let count = spans.len();
+    spans.sort();
+    let mut seconds = 0;
+    let mut current: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    for (start, end) in spans {
+        current = match current {
+            Some((s, e)) if start <= e => Some((s, e.max(end))),
+            Some((s, e)) => {
+                seconds += (e - s).num_seconds();
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    if let Some((s, e)) = current {
+        seconds += (e - s).num_seconds();
+    }
+    (count, seconds as f64 / 60.0)
+}
@@ -74,6 +74,20 @@ CREATE TABLE target_status (
     last_change TIMESTAMP NOT NULL
 );
 
+-- 7.1 Outages simulados pelas instâncias de consenso sombra (shadow mode)
+CREATE TABLE shadow_outage_events (
+    id BIGSERIAL PRIMARY KEY,
+    shadow_name TEXT NOT NULL,
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ,
+    duration_seconds INTEGER,
+    reason TEXT,
+    affected_targets INTEGER[] NOT NULL,
+    consensus_level INTEGER,
+    details JSONB,
+    recorded_at TIMESTAMPTZ DEFAULT NOW()
+);
+
 -- 8. Índices otimizados para workloads de monitoramento
 CREATE INDEX idx_metrics_time_target ON connectivity_metrics (timestamp DESC, target_id);
 CREATE INDEX idx_metrics_status_time ON connectivity_metrics (status, timestamp DESC);
@@ -81,6 +95,7 @@ CREATE INDEX idx_metrics_type ON connectivity_metrics (metric_type);
 CREATE INDEX idx_cycles_started_at ON monitoring_cycles (started_at);
 CREATE INDEX idx_outage_time ON outage_events (start_time DESC);
 CREATE INDEX idx_outage_duration ON outage_events (duration_seconds) WHERE duration_seconds IS NOT NULL;
+CREATE INDEX idx_shadow_outage_name_time ON shadow_outage_events (shadow_name, start_time DESC);
 CREATE INDEX idx_metrics_brin_time ON connectivity_metrics USING BRIN (timestamp);
 
 -- 9. Ingestão de dados de exemplo